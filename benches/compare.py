@@ -0,0 +1,87 @@
+"""
+Compare the instruction counts of `cargo bench` against a saved baseline.
+
+    python3 benches/compare.py save [--baseline FILE]
+    python3 benches/compare.py check [--baseline FILE] [--threshold PERCENT]
+
+`save` runs the benchmarks and writes the instruction count of each one to the baseline file,
+`target/bench-baseline.json` by default.
+`check` runs them again and exits with status 1 if any benchmark executes more than
+`--threshold` percent (default 5) more instructions than in the baseline. Extra arguments
+after `--` are passed to `cargo bench`, for example `-- --bench documents`.
+
+The benchmarks use iai, so valgrind has to be installed.
+"""
+
+import argparse
+import json
+import re
+import subprocess
+import sys
+
+
+def run_benches(cargo_args):
+    output = subprocess.run(
+        ["cargo", "bench", *cargo_args],
+        check=True,
+        stdout=subprocess.PIPE,
+        text=True,
+    ).stdout
+
+    counts = {}
+    name = None
+    for line in output.splitlines():
+        if line and not line[0].isspace():
+            name = line.strip()
+        match = re.match(r"\s+Instructions:\s+(\d+)", line)
+        if match and name is not None:
+            counts[name] = int(match.group(1))
+
+    if not counts:
+        sys.exit("no benchmark results found in the output of cargo bench")
+    return counts
+
+
+def main():
+    parser = argparse.ArgumentParser(description=__doc__.strip().splitlines()[0])
+    parser.add_argument("command", choices=["save", "check"])
+    parser.add_argument("--baseline", default="target/bench-baseline.json")
+    parser.add_argument("--threshold", type=float, default=5.0)
+    argv = sys.argv[1:]
+    cargo_args = []
+    if "--" in argv:
+        cargo_args = argv[argv.index("--") + 1 :]
+        argv = argv[: argv.index("--")]
+    args = parser.parse_args(argv)
+
+    counts = run_benches(cargo_args)
+
+    if args.command == "save":
+        with open(args.baseline, "w") as f:
+            json.dump(counts, f, indent=2, sort_keys=True)
+            f.write("\n")
+        print(f"saved {len(counts)} results to {args.baseline}")
+        return
+
+    with open(args.baseline) as f:
+        baseline = json.load(f)
+
+    regressions = 0
+    for name, count in sorted(counts.items()):
+        if name not in baseline:
+            print(f"{name:30} {count:>14} (new)")
+            continue
+
+        change = (count - baseline[name]) / baseline[name] * 100
+        flag = ""
+        if change > args.threshold:
+            flag = "  REGRESSION"
+            regressions += 1
+        print(f"{name:30} {count:>14} ({change:+.2f}%){flag}")
+
+    if regressions:
+        sys.exit(f"{regressions} benchmarks regressed by more than {args.threshold}%")
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,165 @@
+//! Tokenize whole documents of different kinds, once with each of [`DefaultEmitter`],
+//! [`CallbackEmitter`], [`ValidateEmitter`] and [`NoopEmitter`]. The difference between the last
+//! one and the others is the cost of the emitter, the rest is the cost of the state machine.
+//!
+//! Building the documents is counted as well, but it is the same for all emitters.
+//!
+//! The `html5lib` benchmarks need the html5lib-tests submodule. Use `benches/compare.py` to
+//! compare the results against a saved baseline.
+use std::convert::Infallible;
+
+use iai::{black_box, main};
+
+use html5gum::emitters::callback::{CallbackEmitter, CallbackEvent};
+use html5gum::emitters::noop::NoopEmitter;
+use html5gum::emitters::validate::ValidateEmitter;
+use html5gum::{DefaultEmitter, Emitter, State, Tokenizer};
+
+/// Long paragraphs of text with a few inline tags.
+fn text_document() -> String {
+    let paragraph = "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod \
+                     tempor incididunt ut labore et dolore magna aliqua. Ut enim ad minim veniam, \
+                     quis nostrud <em>exercitation</em> ullamco laboris nisi ut aliquip ex ea \
+                     commodo consequat.\n";
+    let mut s = String::new();
+    for _ in 0..100 {
+        s.push_str("<p>");
+        for _ in 0..5 {
+            s.push_str(paragraph);
+        }
+        s.push_str("</p>\n");
+    }
+    s
+}
+
+/// Tags with many attributes in all quoting styles.
+fn attribute_document() -> String {
+    let mut s = String::new();
+    for i in 0..1000 {
+        s.push_str(&format!(
+            "<div id=\"item-{}\" class=\"a b c\" data-value='{}' title=unquoted hidden \
+             style=\"color: red; margin: 0\">x</div>\n",
+            i, i
+        ));
+    }
+    s
+}
+
+/// The content of a script, which is tokenized in the script data state.
+fn script_document() -> String {
+    let script =
+        "function f(a, b) {\n    if (a < b && b > 0) { return '<div>' + a + '</div>'; }\n    \
+                  // <!-- not a comment -->\n    return \"</scrip\" + 't>';\n}\n";
+    script.repeat(500)
+}
+
+/// Text with many character references.
+fn entity_document() -> String {
+    "<p>&amp;&lt;&gt;&quot;&#39;&#x41;&eacute;&notit; &amp a&#128512;&nbsp;&copy</p>\n".repeat(1000)
+}
+
+/// The inputs of all tokenizer tests of html5lib-tests, concatenated.
+fn html5lib_document() -> String {
+    let mut s = String::new();
+    let files = glob::glob("tests/html5lib-tests/tokenizer/*.test").unwrap();
+    for path in files {
+        let content = std::fs::read_to_string(path.unwrap()).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&content).unwrap();
+        for test in json["tests"].as_array().into_iter().flatten() {
+            s.push_str(test["input"].as_str().unwrap());
+        }
+    }
+
+    assert!(
+        !s.is_empty(),
+        "html5lib-tests not found, run `git submodule update --init`"
+    );
+    s
+}
+
+fn run<E: Emitter>(document: &str, state: State, emitter: E) {
+    let mut tokenizer = Tokenizer::new_with_emitter(black_box(document), emitter);
+    tokenizer.set_state(state);
+    for Ok(_) in tokenizer {}
+}
+
+fn default_emitter() -> DefaultEmitter {
+    DefaultEmitter::default()
+}
+
+fn callback_emitter() -> CallbackEmitter<fn(CallbackEvent<'_>) -> Option<Infallible>> {
+    CallbackEmitter::new(|_| None)
+}
+
+fn validate_emitter() -> ValidateEmitter {
+    ValidateEmitter::new()
+}
+
+fn noop_emitter() -> NoopEmitter {
+    NoopEmitter
+}
+
+macro_rules! document_benches {
+    ($(($document:ident, $state:expr, [$($name:ident: $emitter:ident),*]),)*) => {
+        $($(
+            fn $name() {
+                run(&$document(), $state, $emitter());
+            }
+        )*)*
+
+        main!($($($name),*),*);
+    }
+}
+
+document_benches![
+    (
+        text_document,
+        State::Data,
+        [
+            text_default: default_emitter,
+            text_callback: callback_emitter,
+            text_validate: validate_emitter,
+            text_noop: noop_emitter
+        ]
+    ),
+    (
+        attribute_document,
+        State::Data,
+        [
+            attributes_default: default_emitter,
+            attributes_callback: callback_emitter,
+            attributes_validate: validate_emitter,
+            attributes_noop: noop_emitter
+        ]
+    ),
+    (
+        script_document,
+        State::ScriptData,
+        [
+            script_default: default_emitter,
+            script_callback: callback_emitter,
+            script_validate: validate_emitter,
+            script_noop: noop_emitter
+        ]
+    ),
+    (
+        entity_document,
+        State::Data,
+        [
+            entities_default: default_emitter,
+            entities_callback: callback_emitter,
+            entities_validate: validate_emitter,
+            entities_noop: noop_emitter
+        ]
+    ),
+    (
+        html5lib_document,
+        State::Data,
+        [
+            html5lib_default: default_emitter,
+            html5lib_callback: callback_emitter,
+            html5lib_validate: validate_emitter,
+            html5lib_noop: noop_emitter
+        ]
+    ),
+];
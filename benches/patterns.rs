@@ -7,6 +7,37 @@ fn pattern(pattern: &str, i: usize) {
     for Ok(_) in Tokenizer::new(&s) {}
 }
 
+/// A single start tag with `i` distinct attribute names that share a long common prefix.
+fn long_attribute_names(prefix_len: usize, i: usize) {
+    let prefix = "a".repeat(prefix_len);
+    let mut s = String::from("<div");
+    for n in 0..i {
+        s.push_str(&format!(" {}{}=x", prefix, n));
+    }
+    s.push('>');
+    let s = black_box(s);
+    for Ok(_) in Tokenizer::new(&s) {}
+}
+
+fn long_attribute_names_10000() {
+    long_attribute_names(1000, 10000)
+}
+
+/// Many tiny documents, each with its own tokenizer.
+fn small_snippets_10000() {
+    const SNIPPETS: &[&str] = &[
+        "<p>Hello <b>world</b></p>",
+        "<a href=\"/x\">link</a> and text",
+        "<div class=a id=b><span>x</span><img src=y alt=z></div>",
+        "<li>item</li><li>item 2</li>",
+        "plain text only",
+        "<br><hr><input type=checkbox checked>",
+    ];
+    for i in 0..10000 {
+        for Ok(_) in Tokenizer::new(black_box(SNIPPETS[i % SNIPPETS.len()])) {}
+    }
+}
+
 macro_rules! pattern_tests {
     ($(($name:ident, $pattern:expr, $repeat:expr), )*) => {
         $(
@@ -15,7 +46,7 @@ macro_rules! pattern_tests {
             }
         )*
 
-        main!($($name),*);
+        main!($($name,)* long_attribute_names_10000, small_snippets_10000);
     }
 }
 
@@ -27,7 +27,7 @@ fn get_emitter() -> impl Emitter<Token = String> {
             is_href_attr = name == b"href";
             None
         }
-        CallbackEvent::AttributeValue { value } if is_anchor_tag && is_href_attr => {
+        CallbackEvent::AttributeValue { value, .. } if is_anchor_tag && is_href_attr => {
             Some(String::from_utf8_lossy(value).into_owned())
         }
         _ => None,
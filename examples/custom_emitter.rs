@@ -76,10 +76,12 @@ impl Emitter for LinkExtractor {
 
     fn emit_current_tag(&mut self) -> Option<State> {
         self.flush_old_attribute();
-        self.last_start_tag.clear();
-        if !self.current_tag_is_closing {
-            self.last_start_tag.extend(&self.current_tag_name);
+        if self.current_tag_is_closing {
+            return None;
         }
+
+        self.last_start_tag.clear();
+        self.last_start_tag.extend(&self.current_tag_name);
         self.current_tag_name.clear();
         html5gum::naive_next_state(&self.last_start_tag)
     }
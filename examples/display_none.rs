@@ -0,0 +1,53 @@
+//! Find all elements that are hidden using an inline `display: none` style.
+//!
+//! ```text
+//! printf '<p style="color: red">shown</p><div style="DISPLAY:none !important">hidden</div>' | cargo run --example=display_none
+//! ```
+//!
+//! Output:
+//!
+//! ```text
+//! hidden: div
+//! ```
+use html5gum::attrvalues::style_declarations;
+use html5gum::{IoReader, Reader, Token, Tokenizer};
+
+fn hidden_elements<R: Reader>(tokenizer: Tokenizer<R>) -> Vec<String> {
+    let mut rv = Vec::new();
+
+    for token in tokenizer.flatten() {
+        if let Token::StartTag(tag) = token {
+            let style = match tag.attributes.get(b"style".as_slice()) {
+                Some(style) => style,
+                None => continue,
+            };
+
+            let is_hidden = style_declarations(style).any(|(property, value)| {
+                let value = value.strip_suffix(b"!important").unwrap_or(value);
+                &*property == b"display" && value.trim_ascii().eq_ignore_ascii_case(b"none")
+            });
+
+            if is_hidden {
+                rv.push(String::from_utf8_lossy(&tag.name).into_owned());
+            }
+        }
+    }
+
+    rv
+}
+
+fn main() {
+    for name in hidden_elements(Tokenizer::new(IoReader::new(std::io::stdin().lock()))) {
+        println!("hidden: {}", name);
+    }
+}
+
+#[test]
+fn basic() {
+    let html = r#"<p style="color: red">shown</p><div style="DISPLAY:none !important">hidden</div>
+        <span style="background: url('display:none'); display: inline">shown</span>"#;
+    assert_eq!(
+        hidden_elements(Tokenizer::new(html)),
+        vec!["div".to_owned()]
+    );
+}
@@ -0,0 +1,345 @@
+//! Convert HTML to Markdown in a single pass over the tokens, without building a tree.
+//!
+//! ```text
+//! printf '<h1>Hello</h1><p>Some <b>bold</b> text and <a href="/x">a link</a>.</p>' | cargo run --example=html_to_markdown
+//! ```
+//!
+//! Output:
+//!
+//! ```text
+//! # Hello
+//!
+//! Some **bold** text and [a link](/x).
+//! ```
+//!
+//! Headings, paragraphs, emphasis, inline code, code blocks, links, images and nested lists are
+//! supported. Everything else is reduced to its text. The converter only ever looks at the
+//! current event, plus a stack of the open lists and links, so it uses very little memory and
+//! handles arbitrarily large documents. The price is that it trusts the HTML to be well-formed:
+//! implied end tags such as in `<li>a<li>b` work, but misnested markup comes out misnested.
+use std::convert::Infallible;
+use std::io::Read;
+
+use html5gum::emitters::callback::{CallbackEmitter, CallbackEvent};
+use html5gum::{IoReader, Readable, Reader, Tokenizer};
+
+/// What has to be written before the next piece of text. Later variants take precedence over
+/// earlier ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Separator {
+    None,
+    Space,
+    Newline,
+    Paragraph,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum List {
+    Unordered,
+    /// The number of the next item.
+    Ordered(usize),
+}
+
+impl List {
+    fn marker_width(self) -> usize {
+        match self {
+            List::Unordered => 2,
+            List::Ordered(_) => 3,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Converter {
+    out: String,
+    separator: Option<Separator>,
+    tag_name: Vec<u8>,
+    href: Option<String>,
+    src: String,
+    alt: String,
+    /// For every open `<a>`, whether it is written as a link, i.e. whether it has an `href`.
+    links: Vec<Option<String>>,
+    lists: Vec<List>,
+    in_pre: bool,
+    /// Whether the next text is the first in a `<pre>`, whose leading newline is ignored.
+    pre_start: bool,
+    in_code: bool,
+    /// The number of open elements whose contents are not converted, like `<script>`.
+    skip: usize,
+}
+
+impl Converter {
+    fn separate(&mut self, separator: Separator) {
+        self.separator = Some(self.separator.map_or(separator, |x| x.max(separator)));
+    }
+
+    /// Write the pending separator, and the indentation of the current list item after newlines.
+    fn flush(&mut self, list_levels: usize) {
+        let separator = self.separator.take().unwrap_or(Separator::None);
+        if self.out.is_empty() {
+            return;
+        }
+
+        match separator {
+            Separator::None => return,
+            Separator::Space => {
+                if !self.out.ends_with([' ', '\n']) {
+                    self.out.push(' ');
+                }
+                return;
+            }
+            Separator::Newline => self.out.push('\n'),
+            Separator::Paragraph => self.out.push_str("\n\n"),
+        }
+
+        let width: usize = self.lists[..list_levels]
+            .iter()
+            .map(|list| list.marker_width())
+            .sum();
+        self.out.push_str(&" ".repeat(width));
+    }
+
+    /// Write markup that starts something, like `**` or `[`.
+    fn open(&mut self, markup: &str) {
+        self.flush(self.lists.len());
+        self.out.push_str(markup);
+    }
+
+    /// Write markup that ends something, like `**` or `](href)`. Pending whitespace is written
+    /// after it.
+    fn close(&mut self, markup: &str) {
+        if self.separator.is_some_and(|x| x > Separator::Space) {
+            self.flush(self.lists.len());
+        }
+        self.out.push_str(markup);
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.in_pre {
+            let text = match self.pre_start {
+                true => text.strip_prefix('\n').unwrap_or(text),
+                false => text,
+            };
+            self.pre_start = false;
+            for (i, line) in text.split('\n').enumerate() {
+                if i > 0 {
+                    self.separate(Separator::Newline);
+                }
+                if !line.is_empty() {
+                    self.flush(self.lists.len());
+                    self.out.push_str(line);
+                }
+            }
+            return;
+        }
+
+        for (i, word) in text.split(|c: char| c.is_ascii_whitespace()).enumerate() {
+            if i > 0 {
+                self.separate(Separator::Space);
+            }
+            if word.is_empty() {
+                continue;
+            }
+
+            self.flush(self.lists.len());
+            if self.in_code {
+                self.out.push_str(word);
+            } else {
+                for c in word.chars() {
+                    if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']') {
+                        self.out.push('\\');
+                    }
+                    self.out.push(c);
+                }
+            }
+        }
+    }
+
+    fn start_tag(&mut self, self_closing: bool) {
+        let name = std::mem::take(&mut self.tag_name);
+        match &name[..] {
+            b"script" | b"style" | b"title" | b"template" if !self_closing => self.skip += 1,
+            _ if self.skip > 0 => {}
+            b"h1" | b"h2" | b"h3" | b"h4" | b"h5" | b"h6" => {
+                self.separate(Separator::Paragraph);
+                let level = usize::from(name[1] - b'0');
+                self.open(&format!("{} ", "#".repeat(level)));
+            }
+            b"p" | b"blockquote" => self.separate(Separator::Paragraph),
+            b"div" | b"section" | b"article" | b"header" | b"footer" | b"nav" | b"main"
+            | b"table" | b"tr" => self.separate(Separator::Newline),
+            b"br" => {
+                self.close("\\");
+                self.separate(Separator::Newline);
+            }
+            b"hr" => {
+                self.separate(Separator::Paragraph);
+                self.open("---");
+                self.separate(Separator::Paragraph);
+            }
+            b"ul" | b"ol" => {
+                self.separate(match self.lists.is_empty() {
+                    true => Separator::Paragraph,
+                    false => Separator::Newline,
+                });
+                self.lists.push(match &name[..] {
+                    b"ul" => List::Unordered,
+                    _ => List::Ordered(1),
+                });
+            }
+            b"li" => {
+                self.separate(Separator::Newline);
+                let marker = match self.lists.last_mut() {
+                    Some(List::Ordered(n)) => {
+                        *n += 1;
+                        format!("{}. ", *n - 1)
+                    }
+                    _ => "- ".to_owned(),
+                };
+                self.flush(self.lists.len().saturating_sub(1));
+                self.out.push_str(&marker);
+            }
+            _ if self.in_pre => {}
+            b"pre" => {
+                self.separate(Separator::Paragraph);
+                self.open("```");
+                self.separate(Separator::Newline);
+                self.in_pre = true;
+                self.pre_start = true;
+            }
+            b"em" | b"i" => self.open("*"),
+            b"strong" | b"b" => self.open("**"),
+            b"code" | b"kbd" | b"samp" => {
+                self.open("`");
+                self.in_code = true;
+            }
+            b"a" => {
+                let href = self.href.take();
+                if href.is_some() {
+                    self.open("[");
+                }
+                self.links.push(href);
+            }
+            b"img" => {
+                let image = format!("![{}]({})", self.alt, self.src);
+                self.open(&image);
+            }
+            _ => {}
+        }
+    }
+
+    fn end_tag(&mut self, name: &[u8]) {
+        match name {
+            b"script" | b"style" | b"title" | b"template" if self.skip > 0 => self.skip -= 1,
+            _ if self.skip > 0 => {}
+            b"h1" | b"h2" | b"h3" | b"h4" | b"h5" | b"h6" | b"p" | b"blockquote" => {
+                self.separate(Separator::Paragraph)
+            }
+            b"div" | b"section" | b"article" | b"header" | b"footer" | b"nav" | b"main"
+            | b"table" | b"tr" => self.separate(Separator::Newline),
+            b"ul" | b"ol" => {
+                self.lists.pop();
+                self.separate(match self.lists.is_empty() {
+                    true => Separator::Paragraph,
+                    false => Separator::Newline,
+                });
+            }
+            b"pre" if self.in_pre => {
+                self.separate(Separator::Newline);
+                self.open("```");
+                self.separate(Separator::Paragraph);
+                self.in_pre = false;
+            }
+            _ if self.in_pre => {}
+            b"em" | b"i" => self.close("*"),
+            b"strong" | b"b" => self.close("**"),
+            b"code" | b"kbd" | b"samp" if self.in_code => {
+                self.close("`");
+                self.in_code = false;
+            }
+            b"a" => {
+                if let Some(Some(href)) = self.links.pop() {
+                    self.close(&format!("]({})", href));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_event(&mut self, event: CallbackEvent<'_>) {
+        match event {
+            CallbackEvent::OpenStartTag { name } => {
+                self.tag_name.clear();
+                self.tag_name.extend(name);
+                self.href = None;
+                self.src.clear();
+                self.alt.clear();
+            }
+            // with CallbackEmitter::pair_attributes, names and values arrive together
+            CallbackEvent::Attribute { name, value, .. } => {
+                let value = String::from_utf8_lossy(value.unwrap_or_default());
+                match name {
+                    b"href" if self.href.is_none() => self.href = Some(value.into_owned()),
+                    b"src" if self.src.is_empty() => self.src = value.into_owned(),
+                    b"alt" if self.alt.is_empty() => self.alt = value.into_owned(),
+                    _ => {}
+                }
+            }
+            CallbackEvent::CloseStartTag { self_closing, .. } => self.start_tag(self_closing),
+            CallbackEvent::EndTag { name } => self.end_tag(name),
+            CallbackEvent::String { value } if self.skip == 0 => {
+                self.text(&String::from_utf8_lossy(value));
+            }
+            _ => {}
+        }
+    }
+}
+
+fn html_to_markdown<'a, R: Readable<'a>>(input: R) -> Result<String, <R::Reader as Reader>::Error> {
+    let mut converter = Converter::default();
+    let mut emitter = CallbackEmitter::new(|event: CallbackEvent<'_>| -> Option<Infallible> {
+        converter.handle_event(event);
+        None
+    });
+    // needed so that the contents of <script> and <style> are not tokenized as HTML
+    emitter.naively_switch_states(true);
+    emitter.pair_attributes(true);
+    Tokenizer::new_with_emitter(input, emitter).finish()?;
+
+    converter.out.push('\n');
+    Ok(converter.out)
+}
+
+fn main() {
+    let mut input = Vec::new();
+    std::io::stdin().lock().read_to_end(&mut input).unwrap();
+    let markdown = html_to_markdown(IoReader::new(input.as_slice())).unwrap();
+    print!("{}", markdown);
+}
+
+/// Convert every `tests/markdown/*.html` and compare the result with the `.md` file next to it.
+#[test]
+fn golden_files() {
+    let mut count = 0;
+    for path in glob::glob("tests/markdown/*.html").unwrap() {
+        let path = path.unwrap();
+        let html = std::fs::read_to_string(&path).unwrap();
+        let expected = std::fs::read_to_string(path.with_extension("md")).unwrap();
+        let Ok(markdown) = html_to_markdown(&html);
+        pretty_assertions::assert_eq!(markdown, expected, "{}", path.display());
+        count += 1;
+    }
+
+    assert!(count > 0);
+}
+
+#[test]
+fn basic() {
+    let Ok(markdown) = html_to_markdown(
+        "<h1>Hello</h1><p>Some <b>bold</b> text and <a href=\"/x\">a link</a>.</p>",
+    );
+    assert_eq!(
+        markdown,
+        "# Hello\n\nSome **bold** text and [a link](/x).\n"
+    );
+}
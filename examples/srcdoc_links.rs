@@ -0,0 +1,91 @@
+//! Extract links from a document, including links inside of `<iframe srcdoc>` documents.
+//!
+//! The value of a `srcdoc` attribute is a document of its own, so this example recursively runs
+//! the same link extractor on it. See [`html5gum::nested`] for how character references are
+//! decoded in nested documents.
+//!
+//! ```text
+//! printf '<a href="outer">x</a><iframe srcdoc="<a href=&quot;inner&quot;>y</a>">' | cargo run --example=srcdoc_links
+//! ```
+//!
+//! Output:
+//!
+//! ```text
+//! link: outer
+//! link: inner
+//! ```
+use html5gum::emitters::callback::{CallbackEmitter, CallbackEvent};
+use html5gum::nested::parse_srcdoc_with_emitter;
+use html5gum::{Emitter, IoReader, Reader, Tokenizer};
+
+enum Found {
+    Link(String),
+    Srcdoc(Vec<u8>),
+}
+
+fn get_emitter() -> impl Emitter<Token = Found> {
+    let mut tag_name = Vec::new();
+    let mut attribute_name = Vec::new();
+
+    CallbackEmitter::new(move |event: CallbackEvent<'_>| match event {
+        CallbackEvent::OpenStartTag { name } => {
+            tag_name.clear();
+            tag_name.extend(name);
+            attribute_name.clear();
+            None
+        }
+        CallbackEvent::AttributeName { name } => {
+            attribute_name.clear();
+            attribute_name.extend(name);
+            None
+        }
+        CallbackEvent::AttributeValue { value, .. } => match (&tag_name[..], &attribute_name[..]) {
+            (b"a", b"href") => Some(Found::Link(String::from_utf8_lossy(value).into_owned())),
+            (b"iframe", b"srcdoc") => Some(Found::Srcdoc(value.to_vec())),
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+fn extract_links<R: Reader>(tokenizer: Tokenizer<R, impl Emitter<Token = Found>>) -> Vec<String> {
+    let mut links = Vec::new();
+
+    for found in tokenizer.flatten() {
+        match found {
+            Found::Link(link) => links.push(link),
+            Found::Srcdoc(srcdoc) => {
+                links.extend(extract_links(parse_srcdoc_with_emitter(
+                    &srcdoc.into(),
+                    get_emitter(),
+                )));
+            }
+        }
+    }
+
+    links
+}
+
+fn main() {
+    let tokenizer =
+        Tokenizer::new_with_emitter(IoReader::new(std::io::stdin().lock()), get_emitter());
+
+    for link in extract_links(tokenizer) {
+        println!("link: {}", link);
+    }
+}
+
+#[test]
+fn basic() {
+    let html = r#"<a href="outer">x</a><iframe srcdoc="<a href=&quot;inner&quot;>y</a>"></iframe>"#;
+    let links = extract_links(Tokenizer::new_with_emitter(html, get_emitter()));
+    assert_eq!(links, vec!["outer".to_owned(), "inner".to_owned()]);
+}
+
+#[test]
+fn double_encoded_entities() {
+    // The outer document decodes `&amp;amp;` to `&amp;`, which the inner document decodes to `&`.
+    let html = r#"<iframe srcdoc="<a href='?a=1&amp;amp;b=2'>&amp;lt;</a><iframe srcdoc='<a href=&amp;quot;deep&amp;quot;>'>">"#;
+    let links = extract_links(Tokenizer::new_with_emitter(html, get_emitter()));
+    assert_eq!(links, vec!["?a=1&b=2".to_owned(), "deep".to_owned()]);
+}
@@ -85,7 +85,7 @@ impl<R: Reader, E: Emitter<Token = Token>> TokenSinkInner<R, E> {
             }
             (None, Token2::EOFToken) => {}
             (Some(Token::Comment(comment)), Token2::CommentToken(comment2)) => {
-                assert_eq!(comment, comment2.as_ref().as_bytes().to_owned());
+                assert_eq!(comment.value, comment2.as_ref().as_bytes().to_owned());
             }
             (Some(Token::Doctype(doctype)), Token2::DoctypeToken(doctype2)) => {
                 assert_eq!(
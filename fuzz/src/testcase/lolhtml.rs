@@ -1,4 +1,4 @@
-use html5gum::{Doctype, EndTag, StartTag, Token};
+use html5gum::{CommentKind, Doctype, EndTag, StartTag, Token};
 use lol_html::errors::RewritingError;
 use lol_html::html_content::DocumentEnd;
 use lol_html::{
@@ -63,6 +63,10 @@ pub fn run_lolhtml(data: &[u8]) {
             Token::StartTag(ref mut s) => {
                 s.attributes.clear();
             }
+            // lol-html does not distinguish bogus comments
+            Token::Comment(ref mut comment) => {
+                comment.kind = CommentKind::Regular;
+            }
             _ => (),
         }
 
@@ -6,13 +6,35 @@ mod old_html5gum;
 mod swc;
 
 pub fn run(s: &[u8]) {
-    let mut did_anything = env::var("FUZZ_BASIC").unwrap() == "1";
+    let mut did_anything = false;
 
     // unconditionally run tokenizer against raw bytes, it should never crash. we rely on running
     // in debug mode such that this is not just simply optimized away
     let testing_tokenizer = html5gum::Tokenizer::new(s);
     for Ok(_) in testing_tokenizer {}
 
+    if env::var("FUZZ_BASIC").unwrap() == "1" {
+        // every emitter and option, see tests/panic_freedom.rs
+        html5gum::testing::exhaustive::run(s);
+        did_anything = true;
+    }
+
+    if env::var("FUZZ_SPANS").unwrap() == "1" {
+        // see tests/span_invariants.rs
+        if let Err(violation) = html5gum::testing::spans::check_span_invariants(s) {
+            panic!("{}", violation);
+        }
+        did_anything = true;
+    }
+
+    if env::var("FUZZ_MEMORY_BOUNDS").unwrap() == "1" {
+        // see tests/memory_bounds.rs
+        if let Err(violation) = html5gum::testing::memory::check_memory_bounds(s) {
+            panic!("{}", violation);
+        }
+        did_anything = true;
+    }
+
     if env::var("FUZZ_OLD_HTML5GUM").unwrap() == "1" {
         if let Ok(data) = std::str::from_utf8(s) {
             old_html5gum::run_old_html5gum(data);
@@ -1,6 +1,6 @@
 use std::env;
 
-use html5gum::{Doctype, EndTag, StartTag, Token};
+use html5gum::{CommentKind, Doctype, EndTag, StartTag, Token};
 
 use pretty_assertions::assert_eq;
 
@@ -82,5 +82,12 @@ pub fn run_old_html5gum(s: &str) {
         })
         .collect();
 
+    // the old version does not distinguish bogus comments
+    for token in &mut testing_tokens {
+        if let Token::Comment(comment) = token {
+            comment.kind = CommentKind::Regular;
+        }
+    }
+
     assert_eq!(testing_tokens, reference_tokens);
 }
@@ -1,5 +1,3 @@
-use std::collections::BTreeMap;
-
 use swc_common::{input::StringInput, BytePos};
 use swc_html_ast::*;
 use swc_html_parser::lexer::Lexer;
@@ -50,11 +48,12 @@ pub fn run_swc(s: &str) {
                     self_closing: is_self_closing,
                     name: tag_name.to_string().into_bytes().into(),
                     attributes: {
-                        let mut gum_attributes = BTreeMap::new();
+                        let mut gum_attributes = html5gum::AttributeMap::new();
                         for token in attributes {
-                            gum_attributes
-                                .entry(token.name.to_string().into_bytes().into())
-                                .or_insert(
+                            let name = token.name.to_string().into_bytes().into();
+                            if !gum_attributes.contains_key(&name) {
+                                gum_attributes.insert(
+                                    name,
                                     token
                                         .value
                                         .unwrap_or_default()
@@ -62,6 +61,7 @@ pub fn run_swc(s: &str) {
                                         .into_bytes()
                                         .into(),
                                 );
+                            }
                         }
 
                         gum_attributes
@@ -94,6 +94,10 @@ pub fn run_swc(s: &str) {
     for Ok(token) in html5gum::Tokenizer::new(s) {
         match token {
             html5gum::Token::Error(_) => {}
+            // swc does not distinguish bogus comments
+            html5gum::Token::Comment(comment) => {
+                gum_tokens.push(html5gum::Token::Comment(comment.value.into()))
+            }
             token => gum_tokens.push(token),
         }
     }
@@ -21,6 +21,10 @@ impl<T: Copy, const CAP: usize> ArrayVec<T, CAP> {
         self.len += 1;
     }
 
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
     pub(crate) fn drain(&mut self) -> &[T] {
         let rv = &self.content[..self.len];
         self.len = 0;
@@ -0,0 +1,128 @@
+//! Tokenize input from a [`tokio::io::AsyncRead`], as it arrives.
+//!
+//! ```rust
+//! use html5gum::async_tokenizer::AsyncTokenizer;
+//! use html5gum::Token;
+//!
+//! # #[tokio::main(flavor = "current_thread")] // extern crate tokio;
+//! # async fn main() {
+//! // anything that implements AsyncRead, such as a TcpStream or a response body
+//! let input: &[u8] = b"<p>hello</p><p>world</p>";
+//! let mut tokenizer = AsyncTokenizer::new(input);
+//! let mut count = 0;
+//!
+//! while let Some(token) = tokenizer.next_token().await {
+//!     if let Token::StartTag(_) = token.unwrap() {
+//!         count += 1;
+//!     }
+//! }
+//!
+//! assert_eq!(count, 2);
+//! # }
+//! ```
+//!
+//! The tokenizer can't pause in the middle of a token. Instead, whenever it runs out of input,
+//! it starts over from the end of the last complete tag, comment or doctype once more input is
+//! available, just like [`crate::Tokenizer::resume_at`] does. Only the input after that point is
+//! kept in memory. This means that very long runs of text without any markup in between are
+//! tokenized several times, once per chunk returned by the reader.
+//!
+//! With the `async-stream` feature, [`AsyncTokenizer`] also implements
+//! [`futures_core::Stream`].
+//!
+//! This module requires the `async-reader` feature.
+use std::future::poll_fn;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tokio::io::{AsyncRead, ReadBuf};
+
+use crate::chunked::ChunkedTokenizer;
+use crate::Token;
+
+/// How much to read from the underlying reader at once.
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// A tokenizer for input from a [`tokio::io::AsyncRead`], see the [module docs](self).
+///
+/// It produces the same tokens as [`crate::Tokenizer`] with a [`crate::DefaultEmitter`].
+#[derive(Debug)]
+pub struct AsyncTokenizer<R> {
+    reader: R,
+    inner: ChunkedTokenizer,
+}
+
+impl<R: AsyncRead + Unpin> AsyncTokenizer<R> {
+    /// Create a new tokenizer that reads from `reader`.
+    pub fn new(reader: R) -> Self {
+        AsyncTokenizer {
+            reader,
+            inner: ChunkedTokenizer::new(),
+        }
+    }
+
+    /// Whether to use [`crate::naive_next_state`] to switch states automatically, see
+    /// [`crate::DefaultEmitter::naively_switch_states`].
+    ///
+    /// The default is off. This has to be called before the first token is read.
+    pub fn naively_switch_states(&mut self, yes: bool) {
+        self.inner.naively_switch_states(yes);
+    }
+
+    /// Return the next token, or `None` at the end of the input.
+    ///
+    /// Errors of the underlying reader are returned as they are. Calling this method again after
+    /// an error retries the read.
+    pub async fn next_token(&mut self) -> Option<io::Result<Token>> {
+        poll_fn(|cx| self.poll_next_token(cx)).await
+    }
+
+    /// Like [`AsyncTokenizer::next_token`], but for implementing futures by hand.
+    pub fn poll_next_token(&mut self, cx: &mut Context<'_>) -> Poll<Option<io::Result<Token>>> {
+        loop {
+            if let Some((token, _)) = self.inner.next_token() {
+                return Poll::Ready(Some(Ok(token)));
+            }
+
+            if self.inner.is_done() {
+                return Poll::Ready(None);
+            }
+
+            match self.poll_read(cx) {
+                Poll::Ready(Ok(())) => {}
+                Poll::Ready(Err(e)) => return Poll::Ready(Some(Err(e))),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+
+    fn poll_read(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut chunk = [0; CHUNK_SIZE];
+        let mut read_buf = ReadBuf::new(&mut chunk);
+        match Pin::new(&mut self.reader).poll_read(cx, &mut read_buf) {
+            Poll::Ready(Ok(())) => {
+                if read_buf.filled().is_empty() {
+                    self.inner.end();
+                } else {
+                    self.inner.push(read_buf.filled());
+                }
+                Poll::Ready(Ok(()))
+            }
+            Poll::Ready(Err(e)) if e.kind() == io::ErrorKind::Interrupted => {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+            result => result,
+        }
+    }
+}
+
+#[cfg(feature = "async-stream")]
+impl<R: AsyncRead + Unpin> futures_core::Stream for AsyncTokenizer<R> {
+    type Item = io::Result<Token>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_next_token(cx)
+    }
+}
@@ -0,0 +1,461 @@
+//! The attributes of a start tag, in source order. See [`AttributeMap`].
+use std::borrow::Borrow;
+use std::fmt::{self, Debug, Formatter};
+use std::iter::FromIterator;
+use std::ops::Index;
+
+use crate::HtmlString;
+
+/// A map of attribute names to values that remembers the order in which the attributes were
+/// inserted, which for [`crate::StartTag`] is the order in which they appear in the source.
+///
+/// Lookups by name take `O(log n)` time. Collecting a map from an iterator, as the tokenizer does
+/// for every start tag, takes `O(n log n)` time, but [`AttributeMap::insert`] and
+/// [`AttributeMap::remove`] take `O(n)` time each. Maps with only a few attributes don't need any
+/// allocations besides the attributes themselves.
+///
+/// Inserting a name that is already in the map replaces its value, but keeps its position. Two
+/// maps are equal if they contain the same attributes, regardless of their order.
+///
+/// ```rust
+/// use html5gum::{Token, Tokenizer};
+///
+/// let Some(Ok(Token::StartTag(tag))) = Tokenizer::new("<a title=x href=y>").next() else {
+///     panic!();
+/// };
+///
+/// let names: Vec<_> = tag.attributes.keys().map(|name| name.as_slice()).collect();
+/// assert_eq!(names, [b"title".as_slice(), b"href"]);
+/// assert_eq!(tag.attributes[b"href".as_slice()], b"y");
+/// ```
+#[derive(Default, Clone)]
+pub struct AttributeMap {
+    entries: Vec<(HtmlString, HtmlString)>,
+    // indices into `entries`, sorted by name, or empty if there are at most `LINEAR_SEARCH_MAX`
+    // entries
+    sorted: Vec<usize>,
+}
+
+/// Up to this many attributes are searched linearly, which is faster than maintaining an index
+/// for most tags.
+const LINEAR_SEARCH_MAX: usize = 8;
+
+impl AttributeMap {
+    /// Create an empty map.
+    pub fn new() -> Self {
+        AttributeMap::default()
+    }
+
+    /// The number of attributes.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether there are no attributes.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Find `name` in `sorted`, like [`slice::binary_search`].
+    fn search<Q>(&self, name: &Q) -> Result<usize, usize>
+    where
+        HtmlString: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.sorted
+            .binary_search_by(|&i| self.entries[i].0.borrow().cmp(name))
+    }
+
+    /// Return the index of `name` in `entries`.
+    fn find<Q>(&self, name: &Q) -> Option<usize>
+    where
+        HtmlString: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        if self.sorted.is_empty() {
+            self.entries
+                .iter()
+                .position(|(entry_name, _)| entry_name.borrow() == name)
+        } else {
+            Some(self.sorted[self.search(name).ok()?])
+        }
+    }
+
+    /// Return the value of the attribute `name`.
+    pub fn get<Q>(&self, name: &Q) -> Option<&HtmlString>
+    where
+        HtmlString: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let i = self.find(name)?;
+        Some(&self.entries[i].1)
+    }
+
+    /// Return the value of the attribute `name` for modification.
+    pub fn get_mut<Q>(&mut self, name: &Q) -> Option<&mut HtmlString>
+    where
+        HtmlString: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let i = self.find(name)?;
+        Some(&mut self.entries[i].1)
+    }
+
+    /// Whether there is an attribute `name`.
+    pub fn contains_key<Q>(&self, name: &Q) -> bool
+    where
+        HtmlString: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.find(name).is_some()
+    }
+
+    /// Set the value of the attribute `name`, and return its previous value.
+    ///
+    /// New attributes are appended at the end, existing ones keep their position.
+    pub fn insert(&mut self, name: HtmlString, value: HtmlString) -> Option<HtmlString> {
+        if let Some(i) = self.find(&name) {
+            return Some(std::mem::replace(&mut self.entries[i].1, value));
+        }
+
+        if !self.sorted.is_empty() {
+            // `find` returned `None`, so this is the insertion point
+            let (Ok(pos) | Err(pos)) = self.search(&name);
+            self.sorted.insert(pos, self.entries.len());
+        }
+        self.entries.push((name, value));
+        if self.entries.len() == LINEAR_SEARCH_MAX + 1 {
+            self.reindex();
+        }
+        None
+    }
+
+    /// Rebuild `sorted` from scratch, and merge entries with the same name like `insert` would.
+    fn reindex(&mut self) {
+        self.sorted.clear();
+        self.sorted.extend(0..self.entries.len());
+        let entries = &self.entries;
+        self.sorted
+            .sort_unstable_by(|&a, &b| entries[a].0.cmp(&entries[b].0).then(a.cmp(&b)));
+
+        if self
+            .sorted
+            .windows(2)
+            .any(|pair| entries[pair[0]].0 == entries[pair[1]].0)
+        {
+            self.merge_duplicates();
+        }
+    }
+
+    /// Move the value of the last of several entries with the same name into the first one, and
+    /// remove the others. `sorted` must be sorted by name, and then by position.
+    fn merge_duplicates(&mut self) {
+        let mut removed = vec![false; self.entries.len()];
+        let mut first = self.sorted[0];
+        for &i in &self.sorted[1..] {
+            if self.entries[i].0 == self.entries[first].0 {
+                self.entries[first].1 = std::mem::take(&mut self.entries[i].1);
+                removed[i] = true;
+            } else {
+                first = i;
+            }
+        }
+
+        let mut new_positions = Vec::with_capacity(removed.len());
+        let mut position = 0;
+        for &is_removed in &removed {
+            new_positions.push(position);
+            if !is_removed {
+                position += 1;
+            }
+        }
+
+        let mut is_removed = removed.iter();
+        self.entries.retain(|_| is_removed.next() == Some(&false));
+        self.sorted.retain(|&i| !removed[i]);
+        for i in &mut self.sorted {
+            *i = new_positions[*i];
+        }
+        if self.entries.len() <= LINEAR_SEARCH_MAX {
+            self.sorted.clear();
+        }
+    }
+
+    /// Remove the attribute `name`, and return its value.
+    ///
+    /// The attributes after it move up by one position. This takes `O(n)` time.
+    pub fn remove<Q>(&mut self, name: &Q) -> Option<HtmlString>
+    where
+        HtmlString: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let i = self.find(name)?;
+        if self.entries.len() == LINEAR_SEARCH_MAX + 1 {
+            self.sorted.clear();
+        } else if !self.sorted.is_empty() {
+            self.sorted.retain(|&j| j != i);
+            for j in &mut self.sorted {
+                if *j > i {
+                    *j -= 1;
+                }
+            }
+        }
+        Some(self.entries.remove(i).1)
+    }
+
+    /// Remove all attributes.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.sorted.clear();
+    }
+
+    /// Iterate over the names and values of the attributes, in insertion order.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            inner: self.entries.iter(),
+        }
+    }
+
+    /// Iterate over the names of the attributes, in insertion order.
+    pub fn keys(&self) -> impl Iterator<Item = &HtmlString> + '_ {
+        self.entries.iter().map(|(name, _)| name)
+    }
+
+    /// Iterate over the values of the attributes, in insertion order.
+    pub fn values(&self) -> impl Iterator<Item = &HtmlString> + '_ {
+        self.entries.iter().map(|(_, value)| value)
+    }
+}
+
+impl Debug for AttributeMap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl PartialEq for AttributeMap {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .all(|(name, value)| other.get(name) == Some(value))
+    }
+}
+
+impl Eq for AttributeMap {}
+
+impl<Q> Index<&Q> for AttributeMap
+where
+    HtmlString: Borrow<Q>,
+    Q: Ord + ?Sized,
+{
+    type Output = HtmlString;
+
+    /// Return the value of the attribute `name`.
+    ///
+    /// # Panics
+    ///
+    /// If there is no such attribute.
+    #[allow(clippy::expect_used)] // documented, like `HashMap`'s `Index`
+    fn index(&self, name: &Q) -> &HtmlString {
+        self.get(name).expect("no such attribute")
+    }
+}
+
+impl Extend<(HtmlString, HtmlString)> for AttributeMap {
+    fn extend<I: IntoIterator<Item = (HtmlString, HtmlString)>>(&mut self, iter: I) {
+        // Inserting one by one would update the index each time, which takes quadratic time for
+        // tags with thousands of attributes. Instead, the index is built once at the end.
+        let start = self.entries.len();
+        self.entries.extend(iter);
+        if self.entries.len() > LINEAR_SEARCH_MAX {
+            self.reindex();
+            return;
+        }
+
+        let mut i = start;
+        while i < self.entries.len() {
+            let name = &self.entries[i].0;
+            if let Some(j) = self.entries[..i]
+                .iter()
+                .position(|(other, _)| other == name)
+            {
+                let (_, value) = self.entries.remove(i);
+                self.entries[j].1 = value;
+            } else {
+                i += 1;
+            }
+        }
+    }
+}
+
+impl FromIterator<(HtmlString, HtmlString)> for AttributeMap {
+    fn from_iter<I: IntoIterator<Item = (HtmlString, HtmlString)>>(iter: I) -> Self {
+        let mut map = AttributeMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<const N: usize> From<[(HtmlString, HtmlString); N]> for AttributeMap {
+    fn from(attributes: [(HtmlString, HtmlString); N]) -> Self {
+        IntoIterator::into_iter(attributes).collect()
+    }
+}
+
+impl<'a> IntoIterator for &'a AttributeMap {
+    type Item = (&'a HtmlString, &'a HtmlString);
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+impl IntoIterator for AttributeMap {
+    type Item = (HtmlString, HtmlString);
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> IntoIter {
+        IntoIter {
+            inner: self.entries.into_iter(),
+        }
+    }
+}
+
+/// An iterator over the attributes of an [`AttributeMap`], see [`AttributeMap::iter`].
+#[derive(Debug, Clone)]
+pub struct Iter<'a> {
+    inner: std::slice::Iter<'a, (HtmlString, HtmlString)>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a HtmlString, &'a HtmlString);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(name, value)| (name, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(name, value)| (name, value))
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+/// An owning iterator over the attributes of an [`AttributeMap`].
+#[derive(Debug)]
+pub struct IntoIter {
+    inner: std::vec::IntoIter<(HtmlString, HtmlString)>,
+}
+
+impl Iterator for IntoIter {
+    type Item = (HtmlString, HtmlString);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for IntoIter {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back()
+    }
+}
+
+impl ExactSizeIterator for IntoIter {}
+
+#[test]
+fn test_insertion_order() {
+    let mut map = AttributeMap::new();
+    for name in ["b", "c", "a"] {
+        map.insert(name.as_bytes().to_vec().into(), b"1".to_vec().into());
+    }
+    assert_eq!(
+        map.insert(b"c".to_vec().into(), b"2".to_vec().into()),
+        Some(b"1".to_vec().into())
+    );
+
+    let attributes: Vec<_> = map.iter().map(|(k, v)| (&k[..], &v[..])).collect();
+    assert_eq!(
+        attributes,
+        [(&b"b"[..], &b"1"[..]), (b"c", b"2"), (b"a", b"1")]
+    );
+
+    assert_eq!(map.remove(b"b".as_slice()), Some(b"1".to_vec().into()));
+    assert_eq!(map.remove(b"b".as_slice()), None);
+    assert_eq!(map[b"a".as_slice()], b"1");
+    assert_eq!(map[b"c".as_slice()], b"2");
+    assert!(!map.contains_key(b"b".as_slice()));
+    assert_eq!(format!("{:?}", map), r#"{b"c": b"2", b"a": b"1"}"#);
+
+    // order doesn't matter for equality
+    let reversed: AttributeMap = map.clone().into_iter().rev().collect();
+    assert_eq!(map, reversed);
+    assert_ne!(map, AttributeMap::new());
+}
+
+#[test]
+fn test_many_attributes() {
+    let name = |i: usize| HtmlString::from(format!("a{}", 20 - i).into_bytes());
+    let mut map = AttributeMap::new();
+    for i in 0..20 {
+        assert_eq!(map.insert(name(i), name(i)), None);
+        for j in 0..=i {
+            assert_eq!(map.get(&name(j)), Some(&name(j)));
+        }
+        assert!(!map.contains_key(&name(i + 1)));
+    }
+
+    for i in (0..20).step_by(2) {
+        assert_eq!(map.remove(&name(i)), Some(name(i)));
+    }
+    for i in 0..20 {
+        assert_eq!(map.get(&name(i)).is_some(), i % 2 == 1);
+    }
+    for i in (1..20).step_by(2) {
+        assert_eq!(map.remove(&name(i)), Some(name(i)));
+        assert!(!map.contains_key(&name(i)));
+    }
+    assert!(map.is_empty());
+}
+
+#[test]
+fn test_collect_duplicates() {
+    for len in [3, 8, 9, 20] {
+        for offset in 0..len {
+            let attributes: Vec<(HtmlString, HtmlString)> = (0..len)
+                .map(|i| {
+                    let name = format!("a{}", (i * 7 + offset) % (len / 2 + 1));
+                    (name.into_bytes().into(), i.to_string().into_bytes().into())
+                })
+                .collect();
+
+            let mut expected = AttributeMap::new();
+            for (name, value) in attributes.clone() {
+                expected.insert(name, value);
+            }
+            let collected: AttributeMap = attributes.iter().cloned().collect();
+            assert!(collected.iter().eq(expected.iter()), "{:?}", attributes);
+
+            // extending a map that is already indexed
+            let mut extended: AttributeMap = attributes[..len / 2].iter().cloned().collect();
+            extended.extend(attributes[len / 2..].iter().cloned());
+            assert!(extended.iter().eq(expected.iter()), "{:?}", attributes);
+
+            for (name, _) in &attributes {
+                assert_eq!(collected.get(name), expected.get(name));
+            }
+        }
+    }
+}
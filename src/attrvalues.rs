@@ -0,0 +1,136 @@
+//! Helpers for interpreting the values of some common attributes.
+//!
+//! html5gum does not interpret attribute values in any way. The functions in this module are small
+//! conveniences on top of that, and are intentionally not full parsers for the respective
+//! languages.
+use std::borrow::Cow;
+
+/// Split the value of a `style` attribute into `(property, value)` pairs.
+///
+/// This is not a CSS parser. The declarations are split at each `;` that is not inside of quotes
+/// or parentheses (so `url(a;b)` stays intact), and each declaration is split at its first `:`.
+/// Property names and values have surrounding whitespace removed and property names are
+/// lowercased. Declarations without a `:`, or with an empty property name or value are skipped.
+///
+/// Values are returned as they are, including things like `!important`.
+///
+/// ```rust
+/// use html5gum::attrvalues::style_declarations;
+///
+/// let declarations: Vec<_> = style_declarations(b"DISPLAY: none; background: url(a;b.png);")
+///     .map(|(property, value)| (property.into_owned(), value))
+///     .collect();
+///
+/// assert_eq!(
+///     declarations,
+///     vec![
+///         (b"display".to_vec(), b"none".as_slice()),
+///         (b"background".to_vec(), b"url(a;b.png)".as_slice()),
+///     ]
+/// );
+/// ```
+pub fn style_declarations(value: &[u8]) -> impl Iterator<Item = (Cow<'_, [u8]>, &[u8])> {
+    split_top_level(value, b';').filter_map(|declaration| {
+        let colon = declaration.iter().position(|&c| c == b':')?;
+        let property = declaration[..colon].trim_ascii();
+        let value = declaration[colon + 1..].trim_ascii();
+
+        if property.is_empty() || value.is_empty() {
+            return None;
+        }
+
+        let property = if property.iter().any(u8::is_ascii_uppercase) {
+            Cow::Owned(property.to_ascii_lowercase())
+        } else {
+            Cow::Borrowed(property)
+        };
+
+        Some((property, value))
+    })
+}
+
+/// Split `s` at `separator`, except where it is inside of quotes or parentheses.
+fn split_top_level(s: &[u8], separator: u8) -> impl Iterator<Item = &[u8]> {
+    let mut rest = s;
+
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+
+        let mut quote = None;
+        let mut parens = 0usize;
+        let mut escaped = false;
+        let mut end = rest.len();
+
+        for (i, &c) in rest.iter().enumerate() {
+            if escaped {
+                escaped = false;
+                continue;
+            }
+
+            match (quote, c) {
+                (_, b'\\') => escaped = true,
+                (Some(q), _) if c == q => quote = None,
+                (Some(_), _) => {}
+                (None, b'"' | b'\'') => quote = Some(c),
+                (None, b'(') => parens += 1,
+                (None, b')') => parens = parens.saturating_sub(1),
+                (None, _) if c == separator && parens == 0 => {
+                    end = i;
+                    break;
+                }
+                (None, _) => {}
+            }
+        }
+
+        let rv = &rest[..end];
+        rest = rest.get(end + 1..).unwrap_or_default();
+        Some(rv)
+    })
+}
+
+#[test]
+fn test_style_declarations() {
+    fn declarations(value: &str) -> Vec<(String, String)> {
+        style_declarations(value.as_bytes())
+            .map(|(property, value)| {
+                (
+                    String::from_utf8(property.into_owned()).unwrap(),
+                    String::from_utf8(value.to_vec()).unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    fn pairs(xs: &[(&str, &str)]) -> Vec<(String, String)> {
+        xs.iter()
+            .map(|(a, b)| ((*a).to_owned(), (*b).to_owned()))
+            .collect()
+    }
+
+    assert_eq!(declarations(""), pairs(&[]));
+    assert_eq!(
+        declarations("color: red;;  WIDTH :10px ; "),
+        pairs(&[("color", "red"), ("width", "10px")])
+    );
+    assert_eq!(
+        declarations("background:url(\"a;b:c.png\") no-repeat;display:none !important"),
+        pairs(&[
+            ("background", "url(\"a;b:c.png\") no-repeat"),
+            ("display", "none !important")
+        ])
+    );
+    assert_eq!(
+        declarations("background: url(a;b.png); x"),
+        pairs(&[("background", "url(a;b.png)")])
+    );
+    assert_eq!(
+        declarations("content: 'a;b:c'; font-family: \"x\\\";y\""),
+        pairs(&[("content", "'a;b:c'"), ("font-family", "\"x\\\";y\"")])
+    );
+    assert_eq!(
+        declarations("color:; :red; color red; a: b"),
+        pairs(&[("a", "b")])
+    );
+}
@@ -1,3 +1,5 @@
+use std::convert::TryFrom;
+
 use crate::arrayvec::ArrayVec;
 use crate::{Emitter, Error};
 
@@ -5,6 +7,14 @@ use crate::{Emitter, Error};
 pub(crate) struct CharValidator {
     last_4_bytes: u32,
     character_error: ArrayVec<Error, 3>,
+    /// Whether to suppress repeated errors for runs of NUL and control characters, see
+    /// `GarbagePolicy::CoalesceErrors`.
+    pub(crate) coalesce_errors: bool,
+    previous_character_error: Option<Error>,
+    current_character_error: Option<Error>,
+    /// Whether errors for the bytes that are validated are held back, see `hold_errors`.
+    holding_errors: bool,
+    held_errors: Vec<Error>,
 }
 
 impl Default for CharValidator {
@@ -12,6 +22,11 @@ impl Default for CharValidator {
         CharValidator {
             last_4_bytes: 0,
             character_error: ArrayVec::new(Error::EofInTag),
+            coalesce_errors: false,
+            previous_character_error: None,
+            current_character_error: None,
+            holding_errors: false,
+            held_errors: Vec::new(),
         }
     }
 }
@@ -21,19 +36,67 @@ impl CharValidator {
         self.last_4_bytes = 0;
     }
 
+    /// Validate `next_bytes`, the first of which is at `offset` in the input.
     #[inline]
-    pub(crate) fn validate_bytes<E: Emitter>(&mut self, emitter: &mut E, next_bytes: &[u8]) {
+    pub(crate) fn validate_bytes<E: Emitter>(
+        &mut self,
+        emitter: &mut E,
+        next_bytes: &[u8],
+        offset: usize,
+    ) {
         if !emitter.should_emit_errors() {
             return;
         }
 
-        for &x in next_bytes {
-            self.validate_byte(emitter, x);
+        // Single bytes are common, for example the `<` that `read_until` stopped at.
+        if let [x] = next_bytes {
+            self.validate_byte(emitter, *x, offset);
+            return;
+        }
+
+        let mut i = 0;
+        while let Some(&x) = next_bytes.get(i) {
+            if is_plain_ascii(x) {
+                let plain_len = plain_ascii_prefix_len(&next_bytes[i..]);
+                self.skip_plain_ascii(emitter, offset + i, plain_len);
+                i += plain_len;
+            } else {
+                self.validate_byte(emitter, x, offset + i);
+                i += 1;
+            }
         }
     }
 
-    #[inline]
-    pub(crate) fn validate_byte<E: Emitter>(&mut self, emitter: &mut E, next_byte: u8) {
+    /// Do what validating `len` bytes for which `is_plain_ascii` is true does, starting at
+    /// `offset`. Only the first of them can produce errors, which are left over from the previous
+    /// character.
+    #[inline(always)]
+    fn skip_plain_ascii<E: Emitter>(&mut self, emitter: &mut E, offset: usize, len: usize) {
+        debug_assert!(len > 0);
+        self.last_4_bytes = 0;
+        let current_character_error = self.current_character_error.take();
+        self.previous_character_error = if len == 1 {
+            current_character_error
+        } else {
+            None
+        };
+        if !self.character_error.is_empty() {
+            self.flush_validation_errors(emitter, offset);
+        }
+    }
+
+    /// Validate `next_byte`, which is at `offset` in the input.
+    ///
+    /// Errors are reported at the offset of the byte that completes the character, as if the
+    /// input was read one byte at a time, so that they do not depend on how the reader splits up
+    /// its input.
+    #[inline(always)]
+    pub(crate) fn validate_byte<E: Emitter>(
+        &mut self,
+        emitter: &mut E,
+        next_byte: u8,
+        offset: usize,
+    ) {
         if !emitter.should_emit_errors() {
             return;
         }
@@ -41,16 +104,24 @@ impl CharValidator {
         if next_byte < 128 {
             // start of character (ascii)
             self.last_4_bytes = 0;
-            self.flush_character_error(emitter);
-            self.validate_last_4_bytes(emitter, u32::from(next_byte));
+            self.previous_character_error = self.current_character_error.take();
+            if !self.character_error.is_empty() {
+                self.flush_validation_errors(emitter, offset);
+            }
+            if !is_plain_ascii(next_byte) {
+                self.validate_last_4_bytes(emitter, u32::from(next_byte), offset);
+            }
         } else if next_byte >= 192 {
             // start of character (non-ascii)
             self.last_4_bytes = u32::from(next_byte);
-            self.flush_character_error(emitter);
+            self.previous_character_error = self.current_character_error.take();
+            if !self.character_error.is_empty() {
+                self.flush_validation_errors(emitter, offset);
+            }
         } else {
             self.last_4_bytes <<= 8;
             self.last_4_bytes |= u32::from(next_byte);
-            self.validate_last_4_bytes(emitter, self.last_4_bytes);
+            self.validate_last_4_bytes(emitter, self.last_4_bytes, offset);
         }
     }
 
@@ -64,20 +135,65 @@ impl CharValidator {
         }
     }
 
+    /// Hold back the errors that validating bytes produces until `release_held_errors` is
+    /// called.
+    ///
+    /// This is for bytes that the state machine reads ahead, but that are consumed by a later
+    /// state: their errors are reported in the order in which they would have been reported
+    /// without the lookahead. Errors produced by the state machine itself are not held back.
+    pub(crate) fn hold_errors(&mut self) {
+        self.holding_errors = true;
+    }
+
+    /// Emit the errors that were held back since `hold_errors`, and stop holding them back.
+    pub(crate) fn release_held_errors<E: Emitter>(&mut self, emitter: &mut E) {
+        self.holding_errors = false;
+        for e in self.held_errors.drain(..) {
+            emitter.emit_error(e);
+        }
+    }
+
+    #[cold]
+    fn emit_validation_error<E: Emitter>(&mut self, emitter: &mut E, error: Error, offset: usize) {
+        if self.holding_errors {
+            self.held_errors.push(error);
+        } else {
+            emitter.set_position(offset);
+            emitter.emit_error(error);
+        }
+    }
+
+    #[cold]
+    fn flush_validation_errors<E: Emitter>(&mut self, emitter: &mut E, offset: usize) {
+        if self.holding_errors {
+            self.held_errors.extend(self.character_error.drain());
+        } else {
+            emitter.set_position(offset);
+            self.flush_character_error(emitter);
+        }
+    }
+
     pub(crate) fn set_character_error<E: Emitter>(&mut self, emitter: &mut E, error: Error) {
         if !emitter.should_emit_errors() {
             return;
         }
 
         if self.last_4_bytes == 0 {
-            emitter.emit_error(error);
+            if !self.is_repeated_error(error) {
+                emitter.emit_error(error);
+            }
         } else {
             self.character_error.push(error);
         }
     }
 
     #[inline]
-    fn validate_last_4_bytes<E: Emitter>(&mut self, emitter: &mut E, last_4_bytes: u32) {
+    fn validate_last_4_bytes<E: Emitter>(
+        &mut self,
+        emitter: &mut E,
+        last_4_bytes: u32,
+        offset: usize,
+    ) {
         // generated with Python 3:
         // ' | '.join(map(hex, sorted([int.from_bytes(chr(x).encode("utf8"), 'big') for x in nonchars])))
         match last_4_bytes {
@@ -92,8 +208,14 @@ impl CharValidator {
             | 0xf28f_bfbe | 0xf28f_bfbf | 0xf29f_bfbe | 0xf29f_bfbf | 0xf2af_bfbe | 0xf2af_bfbf
             | 0xf2bf_bfbe | 0xf2bf_bfbf | 0xf38f_bfbe | 0xf38f_bfbf | 0xf39f_bfbe | 0xf39f_bfbf
             | 0xf3af_bfbe | 0xf3af_bfbf | 0xf3bf_bfbe | 0xf3bf_bfbf | 0xf48f_bfbe | 0xf48f_bfbf => {
-                emitter.emit_error(Error::NoncharacterInInputStream);
-                self.flush_character_error(emitter);
+                self.emit_validation_error(emitter, Error::NoncharacterInInputStream, offset);
+                self.flush_validation_errors(emitter, offset);
+            }
+            // surrogates encoded like other code points, which is not valid UTF-8 but occurs in
+            // CESU-8 and WTF-8
+            0x00ed_a080..=0x00ed_bfbf => {
+                self.emit_validation_error(emitter, Error::SurrogateInInputStream, offset);
+                self.flush_validation_errors(emitter, offset);
             }
             0x1 | 0x2 | 0x3 | 0x4 | 0x5 | 0x6 | 0x7 | 0x8 | 0xb | 0xd | 0xe | 0xf | 0x10 | 0x11
             | 0x12 | 0x13 | 0x14 | 0x15 | 0x16 | 0x17 | 0x18 | 0x19 | 0x1a | 0x1b | 0x1c | 0x1d
@@ -101,11 +223,63 @@ impl CharValidator {
             | 0xc287 | 0xc288 | 0xc289 | 0xc28a | 0xc28b | 0xc28c | 0xc28d | 0xc28e | 0xc28f
             | 0xc290 | 0xc291 | 0xc292 | 0xc293 | 0xc294 | 0xc295 | 0xc296 | 0xc297 | 0xc298
             | 0xc299 | 0xc29a | 0xc29b | 0xc29c | 0xc29d | 0xc29e | 0xc29f => {
-                emitter.emit_error(Error::ControlCharacterInInputStream);
-                self.flush_character_error(emitter);
+                if !self.is_repeated_error(Error::ControlCharacterInInputStream) {
+                    self.emit_validation_error(
+                        emitter,
+                        Error::ControlCharacterInInputStream,
+                        offset,
+                    );
+                }
+                self.flush_validation_errors(emitter, offset);
             }
 
             _ => (),
         }
     }
+
+    /// Record `error` for the current character, and return whether it should be suppressed
+    /// because the previous character already produced the same error.
+    fn is_repeated_error(&mut self, error: Error) -> bool {
+        self.current_character_error = Some(error);
+        self.coalesce_errors
+            && self.previous_character_error == Some(error)
+            && matches!(
+                error,
+                Error::UnexpectedNullCharacter | Error::ControlCharacterInInputStream
+            )
+    }
+}
+
+/// Whether `x` is an ASCII character that is valid in the input stream, so that validating it
+/// cannot produce errors.
+#[inline(always)]
+fn is_plain_ascii(x: u8) -> bool {
+    x.wrapping_sub(b' ') < b'\x7f' - b' ' || matches!(x, b'\t' | b'\n' | b'\x0c')
+}
+
+/// The number of bytes at the start of `xs` for which `is_plain_ascii` is true.
+#[inline(always)]
+fn plain_ascii_prefix_len(xs: &[u8]) -> usize {
+    const ONES: u64 = u64::MAX / 255;
+
+    // Check eight bytes at a time whether they are all between ' ' and '~', see
+    // https://graphics.stanford.edu/~seander/bithacks.html#HasLessInWord
+    let mut len = 0;
+    while let Some(word) = xs
+        .get(len..len + 8)
+        .and_then(|word| <[u8; 8]>::try_from(word).ok())
+    {
+        let word = u64::from_le_bytes(word);
+        let has_less = word.wrapping_sub(ONES * u64::from(b' ')) & !word;
+        let has_more = word.wrapping_add(ONES * u64::from(0x7f - b'~')) | word;
+        if (has_less | has_more) & (ONES * 0x80) != 0 {
+            break;
+        }
+        len += 8;
+    }
+
+    len + xs[len..]
+        .iter()
+        .position(|&x| !is_plain_ascii(x))
+        .unwrap_or(xs.len() - len)
 }
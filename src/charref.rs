@@ -0,0 +1,280 @@
+//! Resolution of named character references such as `&amp;`.
+//!
+//! By default, the tokenizer resolves named character references using the table from the HTML
+//! spec ([`HtmlEntities`]). Use [`Tokenizer::set_entity_resolver`](crate::Tokenizer::set_entity_resolver)
+//! to resolve only a subset of them, or additional ones:
+//!
+//! ```
+//! use html5gum::charref::CharacterReferenceResolver;
+//! use html5gum::{Token, Tokenizer};
+//!
+//! /// Only decode the entities that are also predefined in XML.
+//! struct XmlEntities;
+//!
+//! impl CharacterReferenceResolver for XmlEntities {
+//!     fn resolve(&self, name: &[u8], _in_attribute: bool) -> Option<&str> {
+//!         match name {
+//!             b"amp;" => Some("&"),
+//!             b"lt;" => Some("<"),
+//!             b"gt;" => Some(">"),
+//!             b"quot;" => Some("\""),
+//!             b"apos;" => Some("'"),
+//!             _ => None,
+//!         }
+//!     }
+//! }
+//!
+//! let tokenizer = Tokenizer::new("&lt;&nbsp;&gt;").set_entity_resolver(XmlEntities);
+//! let strings: Vec<_> = tokenizer
+//!     .flatten()
+//!     .filter(|token| matches!(token, Token::String(_)))
+//!     .collect();
+//! assert_eq!(strings, [Token::String(b"<&nbsp;>".to_vec().into())]);
+//! ```
+//!
+//! The same table and rules are available outside of the tokenizer as well, to decode text that
+//! comes from elsewhere with [`decode`], or to look up a single name with [`lookup_named`]:
+//!
+//! ```
+//! use html5gum::charref::{decode, lookup_named};
+//!
+//! assert_eq!(decode(b"&lt;p&gt; &notit; &#x80;", false), "<p> \u{ac}it; \u{20ac}".as_bytes());
+//! assert_eq!(decode(b"?a=1&copy=2", true), b"?a=1&copy=2".as_slice());
+//! assert_eq!(lookup_named(b"notin;"), Some("\u{2209}"));
+//! ```
+use std::borrow::Cow;
+use std::convert::Infallible;
+
+use crate::entities::try_read_character_reference;
+use crate::utils::{ctostr, noncharacter_pat, surrogate_pat};
+use crate::Error;
+
+/// Resolves the names of named character references, such as `amp;` in `&amp;`.
+///
+/// When the tokenizer encounters `&` followed by an ASCII alphanumeric character, it reads up to
+/// [`CharacterReferenceResolver::MAX_NAME_LEN`] ASCII alphanumeric characters, plus a `;` if one
+/// follows, and asks the resolver for the longest name at the start of those bytes. The bytes
+/// after that name are tokenized as if no character reference had been there.
+///
+/// Everything else about character references stays the same as in the spec: numeric character
+/// references are always resolved, names without `;` inside of attribute values are left alone if
+/// they are followed by `=` or an alphanumeric character, and the same errors are emitted.
+pub trait CharacterReferenceResolver {
+    /// The length of the longest name this resolver knows, including the trailing `;`. Longer
+    /// names are never passed to the resolver.
+    const MAX_NAME_LEN: usize = HtmlEntities::MAX_NAME_LEN;
+
+    /// Return the characters that the character reference `name` stands for, or `None` if it is
+    /// not a character reference.
+    ///
+    /// `name` does not include the leading `&`, but does include the trailing `;` if there is
+    /// one. `in_attribute` is true if the character reference is part of an attribute value.
+    fn resolve(&self, name: &[u8], in_attribute: bool) -> Option<&str>;
+
+    /// Find the longest prefix of `candidate` that [`CharacterReferenceResolver::resolve`]
+    /// resolves, and return its length and characters.
+    ///
+    /// The default implementation tries every prefix, starting with the longest one. Override it
+    /// if that can be done more efficiently.
+    fn resolve_longest(&self, candidate: &[u8], in_attribute: bool) -> Option<(usize, &str)> {
+        (1..=candidate.len())
+            .rev()
+            .find_map(|len| Some((len, self.resolve(&candidate[..len], in_attribute)?)))
+    }
+}
+
+/// The named character references defined in the HTML spec. This is the default resolver of
+/// [`Tokenizer`](crate::Tokenizer).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct HtmlEntities;
+
+impl CharacterReferenceResolver for HtmlEntities {
+    // `CounterClockwiseContourIntegral;`
+    const MAX_NAME_LEN: usize = 32;
+
+    fn resolve(&self, name: &[u8], in_attribute: bool) -> Option<&str> {
+        match self.resolve_longest(name, in_attribute) {
+            Some((len, characters)) if len == name.len() => Some(characters),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    fn resolve_longest(&self, candidate: &[u8], _in_attribute: bool) -> Option<(usize, &str)> {
+        longest_named(candidate)
+    }
+}
+
+/// Find the longest name from the HTML spec at the start of `candidate`, and return its length
+/// and characters.
+fn longest_named(candidate: &[u8]) -> Option<(usize, &'static str)> {
+    let (&first_char, rest) = candidate.split_first()?;
+    let Ok(char_ref) = try_read_character_reference(first_char as char, |x| {
+        Ok::<_, Infallible>(rest.starts_with(x.as_bytes()))
+    });
+    char_ref.map(|char_ref| (char_ref.name.len() + 1, char_ref.characters))
+}
+
+/// Return the characters that the named character reference `name` stands for according to the
+/// HTML spec, or `None` if there is no such character reference.
+///
+/// As with [`CharacterReferenceResolver::resolve`], `name` does not include the leading `&`, but
+/// does include the trailing `;`. Only a few legacy names such as `amp` are also known without it.
+///
+/// ```
+/// use html5gum::charref::lookup_named;
+///
+/// assert_eq!(lookup_named(b"amp;"), Some("&"));
+/// assert_eq!(lookup_named(b"amp"), Some("&"));
+/// assert_eq!(lookup_named(b"notin"), None);
+/// ```
+pub fn lookup_named(name: &[u8]) -> Option<&'static str> {
+    match longest_named(name) {
+        Some((len, characters)) if len == name.len() => Some(characters),
+        _ => None,
+    }
+}
+
+/// Decode all character references in `input`, both named and numeric ones, the way the tokenizer
+/// does in text (`in_attribute` is false) or in attribute values (`in_attribute` is true). Named
+/// character references are resolved using [`HtmlEntities`].
+///
+/// `input` is not tokenized otherwise, so for example `<` and newlines are left as they are, and
+/// parse errors such as a missing `;` are not reported. Returns `input` itself if it contains no
+/// `&`.
+///
+/// The difference between the two contexts is that in attribute values, named character
+/// references without `;` are left alone if they are followed by `=` or an ASCII alphanumeric
+/// character, so that URLs such as `?a=1&copy=2` keep working:
+///
+/// ```
+/// use html5gum::charref::decode;
+///
+/// assert_eq!(decode(b"&copy=2", false), "\u{a9}=2".as_bytes());
+/// assert_eq!(decode(b"&copy=2", true), b"&copy=2".as_slice());
+/// assert_eq!(decode(b"&copy;=2", true), "\u{a9}=2".as_bytes());
+/// ```
+pub fn decode(input: &[u8], in_attribute: bool) -> Cow<'_, [u8]> {
+    let Some(first) = input.iter().position(|&x| x == b'&') else {
+        return Cow::Borrowed(input);
+    };
+
+    let mut output = input[..first].to_vec();
+    let mut rest = &input[first..];
+    while let Some(ampersand) = rest.iter().position(|&x| x == b'&') {
+        output.extend_from_slice(&rest[..ampersand]);
+        rest = &rest[ampersand + 1..];
+        let len = decode_reference(rest, in_attribute, &mut output);
+        rest = &rest[len..];
+    }
+    output.extend_from_slice(rest);
+    Cow::Owned(output)
+}
+
+/// Decode the character reference in `input`, which starts right after a `&`, into `output`, and
+/// return its length. If there is none, only the `&` is written, and 0 is returned.
+fn decode_reference(input: &[u8], in_attribute: bool, output: &mut Vec<u8>) -> usize {
+    match input.first() {
+        Some(b'#') => {
+            let (digits_start, radix) = match input.get(1) {
+                Some(b'x' | b'X') => (2, 16),
+                _ => (1, 10),
+            };
+            let digits = input[digits_start.min(input.len())..]
+                .iter()
+                .take_while(|&&x| char::from(x).is_digit(radix))
+                .count();
+            if digits > 0 {
+                let digits_end = digits_start + digits;
+                let code = input[digits_start..digits_end]
+                    .iter()
+                    .fold(0, |code: u32, &x| {
+                        // the same as `mutate_character_reference!`
+                        code.saturating_mul(radix)
+                            .saturating_add(char::from(x).to_digit(radix).unwrap_or(0))
+                            .min(0x0011_0000)
+                    });
+                let (c, _error) = resolve_numeric(code);
+                output.extend(ctostr!(c).as_bytes());
+                return digits_end + usize::from(input.get(digits_end) == Some(&b';'));
+            }
+        }
+        Some(x) if x.is_ascii_alphanumeric() => {
+            let candidate = &input[..input.len().min(HtmlEntities::MAX_NAME_LEN)];
+            if let Some((len, characters)) = longest_named(candidate) {
+                let has_semicolon = input[len - 1] == b';';
+                if !in_attribute
+                    || has_semicolon
+                    || !is_left_alone_in_attribute(input.get(len).copied())
+                {
+                    output.extend(characters.as_bytes());
+                    return len;
+                }
+            }
+        }
+        _ => (),
+    }
+
+    output.push(b'&');
+    0
+}
+
+/// Whether a named character reference without `;` in an attribute value is left alone instead
+/// of being resolved, because of the character after it. This is for historical reasons.
+pub(crate) fn is_left_alone_in_attribute(next_character: Option<u8>) -> bool {
+    matches!(next_character, Some(x) if x == b'=' || x.is_ascii_alphanumeric())
+}
+
+/// Return the character that a numeric character reference with the code point `code` stands
+/// for, as the numeric character reference end state does, together with the error it causes, if
+/// any. `code` is expected to saturate at `0x110000`.
+pub(crate) fn resolve_numeric(code: u32) -> (char, Option<Error>) {
+    let (code, error) = match code {
+        0x00 => (0xfffd, Some(Error::NullCharacterReference)),
+        0x0011_0000.. => (0xfffd, Some(Error::CharacterReferenceOutsideUnicodeRange)),
+        surrogate_pat!() => (0xfffd, Some(Error::SurrogateCharacterReference)),
+        noncharacter_pat!() => (code, Some(Error::NoncharacterCharacterReference)),
+        // 0x000d, or a control that is not whitespace
+        x @ (0x000d | 0x0000..=0x001f | 0x007f..=0x009f)
+            if !matches!(x, 0x0009 | 0x000a | 0x000c | 0x0020) =>
+        {
+            let code = match x {
+                0x80 => 0x20AC, // EURO SIGN (€)
+                0x82 => 0x201A, // SINGLE LOW-9 QUOTATION MARK (‚)
+                0x83 => 0x0192, // LATIN SMALL LETTER F WITH HOOK (ƒ)
+                0x84 => 0x201E, // DOUBLE LOW-9 QUOTATION MARK („)
+                0x85 => 0x2026, // HORIZONTAL ELLIPSIS (…)
+                0x86 => 0x2020, // DAGGER (†)
+                0x87 => 0x2021, // DOUBLE DAGGER (‡)
+                0x88 => 0x02C6, // MODIFIER LETTER CIRCUMFLEX ACCENT (ˆ)
+                0x89 => 0x2030, // PER MILLE SIGN (‰)
+                0x8A => 0x0160, // LATIN CAPITAL LETTER S WITH CARON (Š)
+                0x8B => 0x2039, // SINGLE LEFT-POINTING ANGLE QUOTATION MARK (‹)
+                0x8C => 0x0152, // LATIN CAPITAL LIGATURE OE (Œ)
+                0x8E => 0x017D, // LATIN CAPITAL LETTER Z WITH CARON (Ž)
+                0x91 => 0x2018, // LEFT SINGLE QUOTATION MARK (‘)
+                0x92 => 0x2019, // RIGHT SINGLE QUOTATION MARK (’)
+                0x93 => 0x201C, // LEFT DOUBLE QUOTATION MARK (“)
+                0x94 => 0x201D, // RIGHT DOUBLE QUOTATION MARK (”)
+                0x95 => 0x2022, // BULLET (•)
+                0x96 => 0x2013, // EN DASH (–)
+                0x97 => 0x2014, // EM DASH (—)
+                0x98 => 0x02DC, // SMALL TILDE (˜)
+                0x99 => 0x2122, // TRADE MARK SIGN (™)
+                0x9A => 0x0161, // LATIN SMALL LETTER S WITH CARON (š)
+                0x9B => 0x203A, // SINGLE RIGHT-POINTING ANGLE QUOTATION MARK (›)
+                0x9C => 0x0153, // LATIN SMALL LIGATURE OE (œ)
+                0x9E => 0x017E, // LATIN SMALL LETTER Z WITH CARON (ž)
+                0x9F => 0x0178, // LATIN CAPITAL LETTER Y WITH DIAERESIS (Ÿ)
+                _ => x,
+            };
+            (code, Some(Error::ControlCharacterReference))
+        }
+        _ => (code, None),
+    };
+    // the code was already replaced with U+FFFD above if it is not a valid char
+    (
+        char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER),
+        error,
+    )
+}
@@ -0,0 +1,154 @@
+//! Tokenize input that arrives in chunks, without blocking on a [`Reader`].
+//!
+//! The tokenizer can't pause in the middle of a token. Instead, whenever it runs out of input,
+//! [`ChunkedTokenizer`] starts over from the end of the last complete tag, comment or doctype once
+//! more input is available, just like [`Tokenizer::resume_at`] does. Only the input after that
+//! point is kept in memory.
+use std::collections::VecDeque;
+
+use crate::push::{BufferReader, WouldBlock};
+use crate::{DefaultEmitter, ResumeContext, Span, Token, Tokenizer};
+
+/// A push-based driver around [`Tokenizer`], producing the same tokens as a [`DefaultEmitter`]
+/// with spans.
+#[derive(Debug, Default)]
+pub(crate) struct ChunkedTokenizer {
+    naively_switch_states: bool,
+    /// The input from `offset` on, as far as it has arrived.
+    buffer: Vec<u8>,
+    offset: usize,
+    /// The context at `offset`.
+    context: ResumeContext,
+    /// Whether the end of `buffer` is the end of the input.
+    eof: bool,
+    /// Tokens that end before `offset`, and have not been returned yet.
+    tokens: VecDeque<(Token, Span)>,
+    needs_input: bool,
+    done: bool,
+}
+
+impl ChunkedTokenizer {
+    pub(crate) fn new() -> Self {
+        ChunkedTokenizer {
+            needs_input: true,
+            ..ChunkedTokenizer::default()
+        }
+    }
+
+    /// See [`DefaultEmitter::naively_switch_states`].
+    pub(crate) fn naively_switch_states(&mut self, yes: bool) {
+        self.naively_switch_states = yes;
+    }
+
+    /// Append `chunk` to the input.
+    pub(crate) fn push(&mut self, chunk: &[u8]) {
+        debug_assert!(!self.eof);
+        self.buffer.extend(chunk);
+        self.needs_input = false;
+    }
+
+    /// Mark the end of the input.
+    pub(crate) fn end(&mut self) {
+        self.eof = true;
+        self.needs_input = false;
+    }
+
+    /// Whether all tokens have been returned.
+    pub(crate) fn is_done(&self) -> bool {
+        self.done && self.tokens.is_empty()
+    }
+
+    /// Return the next token, or `None` if more input is needed or all tokens have been returned.
+    pub(crate) fn next_token(&mut self) -> Option<(Token, Span)> {
+        loop {
+            if let Some(token) = self.tokens.pop_front() {
+                return Some(token);
+            }
+
+            if self.done || self.needs_input {
+                return None;
+            }
+
+            self.tokenize();
+        }
+    }
+
+    /// Tokenize the buffered input, and move `offset` to the end of the last complete tag,
+    /// comment or doctype.
+    fn tokenize(&mut self) {
+        let mut emitter = DefaultEmitter::with_spans();
+        emitter.naively_switch_states(self.naively_switch_states);
+        let reader = BufferReader {
+            input: &self.buffer,
+            eof: self.eof,
+        };
+        let mut tokenizer =
+            Tokenizer::resume_with_reader(reader, self.offset, self.context.clone(), emitter);
+
+        // tokens after the last complete tag, comment or doctype may change with more input
+        let mut uncertain_tokens = Vec::new();
+        let mut consumed = 0;
+        loop {
+            match tokenizer.next() {
+                Some(Ok((token, span))) => {
+                    let is_markup = matches!(
+                        token,
+                        Token::StartTag(_)
+                            | Token::EndTag(_)
+                            | Token::Comment(_)
+                            | Token::Doctype(_)
+                    );
+                    uncertain_tokens.push((token, span));
+                    if is_markup {
+                        self.context = tokenizer.context_at_last_token();
+                        consumed = span.end - self.offset;
+                        self.tokens.extend(uncertain_tokens.drain(..));
+                    }
+                }
+                Some(Err(WouldBlock)) => {
+                    self.needs_input = true;
+                    break;
+                }
+                None => {
+                    self.tokens.extend(uncertain_tokens.drain(..));
+                    self.done = true;
+                    break;
+                }
+            }
+        }
+
+        self.buffer.drain(..consumed);
+        self.offset += consumed;
+    }
+}
+
+#[test]
+fn same_tokens_for_every_split() {
+    let input = "<!DOCTYPE html><title>a &amp b</title><p class=x>hello\r\n<br/>world</p>\
+                 <script>if (a < b) {}</script><!-- c --><b";
+    let expected: Vec<_> = {
+        let mut emitter = DefaultEmitter::with_spans();
+        emitter.naively_switch_states(true);
+        Tokenizer::new_with_emitter(input, emitter)
+            .map(|token| match token {
+                Ok(token) => token,
+                Err(e) => match e {},
+            })
+            .collect()
+    };
+
+    for i in 0..=input.len() {
+        let mut tokenizer = ChunkedTokenizer::new();
+        tokenizer.naively_switch_states(true);
+        let mut tokens = Vec::new();
+        for chunk in [&input.as_bytes()[..i], &input.as_bytes()[i..]] {
+            tokenizer.push(chunk);
+            tokens.extend(std::iter::from_fn(|| tokenizer.next_token()));
+        }
+        assert!(!tokenizer.is_done());
+        tokenizer.end();
+        tokens.extend(std::iter::from_fn(|| tokenizer.next_token()));
+        assert!(tokenizer.is_done());
+        assert_eq!(tokens, expected, "split at {}", i);
+    }
+}
@@ -0,0 +1,215 @@
+//! Recognize Internet Explorer's [conditional
+//! comments](https://en.wikipedia.org/wiki/Conditional_comment) in comment tokens.
+//!
+//! Old versions of IE parsed markup inside of comments such as `<!--[if IE]>...<![endif]-->`,
+//! and legacy pages still contain plenty of them. To all other browsers, and to the tokenizer,
+//! they are ordinary comments. [`ConditionalComment::parse`] tells them apart:
+//!
+//! ```rust
+//! use html5gum::conditional_comments::ConditionalComment;
+//! use html5gum::{Token, Tokenizer};
+//!
+//! let html = r#"<!--[if lt IE 9]><script src="shiv.js"></script><![endif]-->"#;
+//! let Some(Ok(Token::Comment(comment))) = Tokenizer::new(html).next() else {
+//!     panic!();
+//! };
+//! assert_eq!(
+//!     ConditionalComment::parse(&comment.value),
+//!     Some(ConditionalComment::Hidden {
+//!         condition: b"lt IE 9",
+//!         content: br#"<script src="shiv.js"></script>"#,
+//!     })
+//! );
+//! ```
+//!
+//! There are two kinds of conditional comments:
+//!
+//! * _Downlevel-hidden_ ones contain markup that only old versions of IE saw:
+//!   `<!--[if IE]><p>IE</p><![endif]-->` is a single comment, which is parsed as
+//!   [`ConditionalComment::Hidden`].
+//! * _Downlevel-revealed_ ones surround markup that all other browsers see as well, as in
+//!   `<![if !IE]><p>not IE</p><![endif]>`, or `<!--[if !IE]><!--><p>not IE</p><!--<![endif]-->`
+//!   which hides the markers from validators. The markers are separate comments, which are parsed
+//!   as [`ConditionalComment::RevealedStart`] and [`ConditionalComment::RevealedEnd`], and the
+//!   markup in between is tokenized as usual.
+//!
+//! The keywords `if` and `endif` are matched case-insensitively, and whitespace around them and
+//! around the condition is allowed. Conditions are not interpreted.
+//!
+//! [`ExpandConditionalComments`] goes one step further, and tokenizes the markup of
+//! downlevel-hidden comments, as old versions of IE would have:
+//!
+//! ```rust
+//! use html5gum::conditional_comments::ExpandConditionalComments;
+//! use html5gum::{Token, Tokenizer};
+//!
+//! let html = "<!--[if IE]><p>IE<![endif]--><p>all";
+//! let tokens: Vec<_> = ExpandConditionalComments::new(Tokenizer::new(html).flatten())
+//!     .map(|token| (token.token, token.condition))
+//!     .collect();
+//!
+//! let ie = Some(b"IE".to_vec().into());
+//! assert!(matches!(&tokens[0], (Token::StartTag(tag), c) if tag.name == b"p" && *c == ie));
+//! assert_eq!(tokens[1], (Token::String(b"IE".to_vec().into()), ie));
+//! assert!(matches!(&tokens[2], (Token::StartTag(_), None)));
+//! assert_eq!(tokens[3], (Token::String(b"all".to_vec().into()), None));
+//! ```
+use std::collections::VecDeque;
+
+use crate::{DefaultEmitter, HtmlString, Token, Tokenizer};
+
+/// A comment that is part of a conditional comment. See the [module docs](self).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConditionalComment<'a> {
+    /// A downlevel-hidden conditional comment, such as `<!--[if IE]><p>IE</p><![endif]-->`.
+    Hidden {
+        /// The condition, such as `IE` or `(gt IE 5)&(lt IE 7)`.
+        condition: &'a [u8],
+        /// The markup inside of the comment, such as `<p>IE</p>`.
+        content: &'a [u8],
+    },
+    /// The start of a downlevel-revealed conditional comment, such as `<![if !IE]>` or
+    /// `<!--[if !IE]><!-->`.
+    RevealedStart {
+        /// The condition, such as `!IE`.
+        condition: &'a [u8],
+    },
+    /// The end of a downlevel-revealed conditional comment, such as `<![endif]>` or
+    /// `<!--<![endif]-->`.
+    RevealedEnd,
+}
+
+impl<'a> ConditionalComment<'a> {
+    /// Recognize the value of a comment token, without the `<!--` and `-->` or `<!` and `>`
+    /// around it. Returns `None` for comments that are not part of a conditional comment.
+    pub fn parse(comment: &'a [u8]) -> Option<Self> {
+        let comment = comment.trim_ascii();
+        if is_endif(comment) || comment.strip_prefix(b"<!").is_some_and(is_endif) {
+            return Some(ConditionalComment::RevealedEnd);
+        }
+
+        let rest = strip_keyword(comment.strip_prefix(b"[")?, b"if")?;
+        if !rest.first()?.is_ascii_whitespace() && rest[0] != b'(' && rest[0] != b'!' {
+            return None;
+        }
+
+        // The condition ends at the last `]` before the first `>`, or at the end for `<![if x]>`.
+        let gt = rest.iter().position(|&x| x == b'>');
+        let bracket = rest[..gt.unwrap_or(rest.len())]
+            .iter()
+            .rposition(|&x| x == b']')?;
+        let condition = rest[..bracket].trim_ascii();
+        let after = rest[bracket + 1..].trim_ascii_start();
+
+        let Some(content) = after.strip_prefix(b">") else {
+            return after
+                .is_empty()
+                .then_some(ConditionalComment::RevealedStart { condition });
+        };
+        if content.trim_ascii() == b"<!" {
+            return Some(ConditionalComment::RevealedStart { condition });
+        }
+
+        let end = content.len().checked_sub(b"<![endif]".len())?;
+        if !is_endif(&content[end + 2..]) || &content[end..end + 2] != b"<!" {
+            return None;
+        }
+        Some(ConditionalComment::Hidden {
+            condition,
+            content: &content[..end],
+        })
+    }
+}
+
+/// Whether `s` is `[endif]`.
+fn is_endif(s: &[u8]) -> bool {
+    s.strip_prefix(b"[")
+        .and_then(|s| strip_keyword(s, b"endif"))
+        .is_some_and(|s| s.trim_ascii() == b"]")
+}
+
+/// Strip whitespace and then `keyword`, ignoring case.
+fn strip_keyword<'a>(s: &'a [u8], keyword: &[u8]) -> Option<&'a [u8]> {
+    let s = s.trim_ascii_start();
+    let (prefix, rest) = s.split_at_checked(keyword.len())?;
+    prefix.eq_ignore_ascii_case(keyword).then_some(rest)
+}
+
+/// A token returned by [`ExpandConditionalComments`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConditionalToken {
+    /// The token itself.
+    pub token: Token,
+    /// The condition of the downlevel-hidden conditional comment that the token comes from, or
+    /// `None` if it was not inside of one.
+    pub condition: Option<HtmlString>,
+}
+
+impl ConditionalToken {
+    /// Whether the token comes from inside of a downlevel-hidden conditional comment, which
+    /// browsers other than old versions of IE do not see.
+    #[must_use]
+    pub fn in_conditional_comment(&self) -> bool {
+        self.condition.is_some()
+    }
+}
+
+/// An iterator adapter that replaces downlevel-hidden conditional comments with the tokens of
+/// their markup. See the [module docs](self).
+///
+/// The markup is tokenized with [`DefaultEmitter::naively_switch_states`], as if it was a
+/// separate document, and its errors are dropped. All other tokens, including the markers of
+/// downlevel-revealed conditional comments, are passed through with no condition.
+#[derive(Debug)]
+pub struct ExpandConditionalComments<I> {
+    tokens: I,
+    expanded: VecDeque<Token>,
+    condition: Option<HtmlString>,
+}
+
+impl<I: Iterator<Item = Token>> ExpandConditionalComments<I> {
+    /// Expand the conditional comments in `tokens`, for example those of a [`Tokenizer`] after
+    /// `.flatten()`.
+    pub fn new(tokens: I) -> Self {
+        ExpandConditionalComments {
+            tokens,
+            expanded: VecDeque::new(),
+            condition: None,
+        }
+    }
+}
+
+impl<I: Iterator<Item = Token>> Iterator for ExpandConditionalComments<I> {
+    type Item = ConditionalToken;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(token) = self.expanded.pop_front() {
+                return Some(ConditionalToken {
+                    token,
+                    condition: self.condition.clone(),
+                });
+            }
+
+            let token = self.tokens.next()?;
+            if let Token::Comment(comment) = &token {
+                if let Some(ConditionalComment::Hidden { condition, content }) =
+                    ConditionalComment::parse(&comment.value)
+                {
+                    let mut emitter = DefaultEmitter::default();
+                    emitter.naively_switch_states(true);
+                    emitter.suppress_errors(true);
+                    self.expanded
+                        .extend(Tokenizer::new_with_emitter(content, emitter).flatten());
+                    self.condition = Some(condition.to_vec().into());
+                    continue;
+                }
+            }
+
+            return Some(ConditionalToken {
+                token,
+                condition: None,
+            });
+        }
+    }
+}
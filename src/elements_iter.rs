@@ -0,0 +1,505 @@
+//! Iterate over complete elements of interest and their text, without building a tree.
+//!
+//! This covers the common scraping task of "give me every heading/paragraph/table cell with its
+//! text":
+//!
+//! ```rust
+//! use html5gum::elements_iter::ElementIter;
+//!
+//! let html = "<h1>Title</h1><ul><li>one<li>two <b>bold</b></ul><h2 id=x>Sub</h2>";
+//!
+//! let elements: Vec<_> = ElementIter::new(html, ["h1", "h2", "li"])
+//!     .flatten()
+//!     .map(|element| (element.name, element.text, element.span))
+//!     .collect();
+//!
+//! assert_eq!(
+//!     elements,
+//!     vec![
+//!         (b"h1".to_vec().into(), b"Title".to_vec().into(), 0..14),
+//!         (b"li".to_vec().into(), b"one".to_vec().into(), 18..25),
+//!         (b"li".to_vec().into(), b"two bold".to_vec().into(), 25..44),
+//!         (b"h2".to_vec().into(), b"Sub".to_vec().into(), 49..66),
+//!     ]
+//! );
+//! ```
+//!
+//! # Finding the end of an element
+//!
+//! Without a tree builder, end tags have to be matched up with start tags using a heuristic. The
+//! iterator keeps a stack of all open elements (not only the ones matching the filter), and an
+//! element ends at whichever of these comes first:
+//!
+//! 1. Its own end tag. End tags without a matching open element are ignored.
+//! 2. The end tag of an element it is nested in. `<ul><li>a</ul>` closes the `<li>`.
+//! 3. A start tag of the same name while it is the innermost open element. This handles unclosed
+//!    `<li>`, `<p>`, `<td>` and so on, while `<td><table><tr><td>` is still nested.
+//! 4. The end of the input.
+//!
+//! Void elements such as `<img>` never contain any text and end right away.
+//!
+//! # Text
+//!
+//! The text of an element is all text inside of it, with character references resolved. The
+//! contents of `<script>` and `<style>` are tokenized as such, and are not part of the text of
+//! the elements around them. They only show up in the text of `<script>` or `<style>` elements
+//! that match the filter themselves.
+//!
+//! Elements are yielded in the order of their start tags, which means that an element is only
+//! yielded once all elements before it have ended.
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::mem::take;
+use std::ops::Range;
+use std::rc::Rc;
+
+use crate::emitters::callback::{Callback, CallbackEmitter, CallbackEvent};
+use crate::utils::is_void_element;
+use crate::{
+    AttributeMap, AttributeValueSyntax, CommentKind, Emitter, Error, HtmlString, Readable, Reader,
+    State, Tokenizer,
+};
+
+/// An element found by [`ElementIter`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FlatElement {
+    /// The element's tag name, such as `"h1"`.
+    pub name: HtmlString,
+
+    /// The attributes of the element's start tag, in source order.
+    pub attributes: AttributeMap,
+
+    /// All text between the start tag and the end of the element. Whether text of nested elements
+    /// that match the filter is included depends on [`ElementIter::include_nested_text`].
+    pub text: HtmlString,
+
+    /// The byte range of the element in the input, from the start tag's `<` up to the end tag's
+    /// `>`. If the element is closed implicitly, the range ends where the tag that closed it
+    /// starts, or at the end of the input.
+    pub span: Range<usize>,
+}
+
+/// An iterator over all elements whose tag name is in a given list. See the [module
+/// docs](self).
+#[derive(Debug)]
+pub struct ElementIter<R: Reader> {
+    tokenizer: Tokenizer<CountingReader<R>, ElementEmitter>,
+}
+
+impl<R: Reader> ElementIter<R> {
+    /// Find all elements in `input` whose tag name is one of `tag_filter`.
+    pub fn new<'a, S, T>(input: S, tag_filter: impl IntoIterator<Item = T>) -> Self
+    where
+        S: Readable<'a, Reader = R>,
+        T: AsRef<[u8]>,
+    {
+        let position = Rc::new(Cell::new(0));
+        let reader = CountingReader {
+            inner: input.to_reader(),
+            position: position.clone(),
+        };
+
+        let mut inner = CallbackEmitter::new(ElementCollector {
+            tag_filter: tag_filter
+                .into_iter()
+                .map(|name| name.as_ref().to_ascii_lowercase())
+                .collect(),
+            position,
+            ..ElementCollector::default()
+        });
+        inner.naively_switch_states(true);
+
+        ElementIter {
+            tokenizer: Tokenizer::new_with_emitter(reader, ElementEmitter { inner }),
+        }
+    }
+
+    /// Whether the text of an element should include the text of matching elements nested in it.
+    ///
+    /// For example, with `<td>a<table><td>b</table></td>`, the outer cell's text is `a` by
+    /// default, and `ab` if this is enabled. The text of non-matching elements such as `<b>` is
+    /// always included.
+    ///
+    /// The default is off.
+    pub fn include_nested_text(&mut self, yes: bool) {
+        self.tokenizer
+            .emitter
+            .inner
+            .callback_mut()
+            .include_nested_text = yes;
+    }
+}
+
+impl<R: Reader> Iterator for ElementIter<R> {
+    type Item = Result<FlatElement, R::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.tokenizer.next()
+    }
+}
+
+/// Wraps a reader to keep track of the number of bytes read so far.
+#[derive(Debug)]
+struct CountingReader<R> {
+    inner: R,
+    position: Rc<Cell<usize>>,
+}
+
+impl<R: Reader> Reader for CountingReader<R> {
+    type Error = R::Error;
+
+    fn read_byte(&mut self) -> Result<Option<u8>, Self::Error> {
+        let rv = self.inner.read_byte()?;
+        if rv.is_some() {
+            self.position.set(self.position.get() + 1);
+        }
+        Ok(rv)
+    }
+
+    fn try_read_string(&mut self, s: &[u8], case_sensitive: bool) -> Result<bool, Self::Error> {
+        let rv = self.inner.try_read_string(s, case_sensitive)?;
+        if rv {
+            self.position.set(self.position.get() + s.len());
+        }
+        Ok(rv)
+    }
+
+    fn read_until<'b>(
+        &'b mut self,
+        needle: &[u8],
+        char_buf: &'b mut [u8; 4],
+    ) -> Result<Option<&'b [u8]>, Self::Error> {
+        let rv = self.inner.read_until(needle, char_buf)?;
+        if let Some(xs) = rv {
+            self.position.set(self.position.get() + xs.len());
+        }
+        Ok(rv)
+    }
+
+    fn size_hint(&self) -> (u64, Option<u64>) {
+        self.inner.size_hint()
+    }
+}
+
+/// Wraps the callback emitter to record where tags start, which the callback can't know.
+#[derive(Debug)]
+struct ElementEmitter {
+    inner: CallbackEmitter<ElementCollector, Infallible>,
+}
+
+impl ElementEmitter {
+    /// Called right after the first character of a tag name has been read. `prefix_len` is the
+    /// length of `<` or `</` plus that character.
+    fn record_tag_start(&mut self, prefix_len: usize) {
+        let collector = self.inner.callback_mut();
+        collector.tag_start = collector.position.get().saturating_sub(prefix_len);
+    }
+}
+
+impl Emitter for ElementEmitter {
+    type Token = FlatElement;
+
+    fn set_last_start_tag(&mut self, last_start_tag: Option<&[u8]>) {
+        self.inner.set_last_start_tag(last_start_tag)
+    }
+
+    fn last_start_tag(&self) -> Option<&[u8]> {
+        self.inner.last_start_tag()
+    }
+
+    fn begin(&mut self) {
+        self.inner.begin();
+    }
+
+    fn emit_eof(&mut self) {
+        self.inner.emit_eof();
+        let collector = self.inner.callback_mut();
+        collector.close_open_elements(0, collector.position.get());
+    }
+
+    fn finish(&mut self) {
+        self.inner.finish();
+    }
+
+    fn emit_error(&mut self, error: Error) {
+        self.inner.emit_error(error)
+    }
+
+    fn should_emit_errors(&mut self) -> bool {
+        false
+    }
+
+    fn pop_token(&mut self) -> Option<Self::Token> {
+        self.inner.callback_mut().pop_finished()
+    }
+
+    fn emit_string(&mut self, c: &[u8]) {
+        self.inner.emit_string(c)
+    }
+
+    fn init_start_tag(&mut self) {
+        self.record_tag_start(2);
+        self.inner.init_start_tag()
+    }
+
+    fn init_end_tag(&mut self) {
+        self.record_tag_start(3);
+        self.inner.init_end_tag()
+    }
+
+    fn init_comment(&mut self) {
+        self.inner.init_comment()
+    }
+
+    fn set_comment_kind(&mut self, kind: CommentKind) {
+        self.inner.set_comment_kind(kind)
+    }
+
+    fn emit_current_tag(&mut self) -> Option<State> {
+        self.inner.emit_current_tag()
+    }
+
+    fn emit_current_comment(&mut self) {
+        self.inner.emit_current_comment()
+    }
+
+    fn emit_current_doctype(&mut self) {
+        self.inner.emit_current_doctype()
+    }
+
+    fn set_self_closing(&mut self) {
+        self.inner.set_self_closing()
+    }
+
+    fn set_force_quirks(&mut self) {
+        self.inner.set_force_quirks()
+    }
+
+    fn push_tag_name(&mut self, s: &[u8]) {
+        self.inner.push_tag_name(s)
+    }
+
+    fn push_tag_name_original(&mut self, s: &[u8]) {
+        self.inner.push_tag_name_original(s)
+    }
+
+    fn push_comment(&mut self, s: &[u8]) {
+        self.inner.push_comment(s)
+    }
+
+    fn push_doctype_name(&mut self, s: &[u8]) {
+        self.inner.push_doctype_name(s)
+    }
+
+    fn init_doctype(&mut self) {
+        self.inner.init_doctype()
+    }
+
+    fn init_attribute(&mut self) {
+        self.inner.init_attribute()
+    }
+
+    fn push_attribute_name(&mut self, s: &[u8]) {
+        self.inner.push_attribute_name(s)
+    }
+
+    fn push_attribute_name_original(&mut self, s: &[u8]) {
+        self.inner.push_attribute_name_original(s)
+    }
+
+    fn init_attribute_value(&mut self) {
+        self.inner.init_attribute_value()
+    }
+
+    fn push_attribute_value(&mut self, s: &[u8]) {
+        self.inner.push_attribute_value(s)
+    }
+
+    fn set_doctype_public_identifier(&mut self, value: &[u8]) {
+        self.inner.set_doctype_public_identifier(value)
+    }
+
+    fn set_doctype_system_identifier(&mut self, value: &[u8]) {
+        self.inner.set_doctype_system_identifier(value)
+    }
+
+    fn push_doctype_public_identifier(&mut self, s: &[u8]) {
+        self.inner.push_doctype_public_identifier(s)
+    }
+
+    fn push_doctype_system_identifier(&mut self, s: &[u8]) {
+        self.inner.push_doctype_system_identifier(s)
+    }
+
+    fn current_is_appropriate_end_tag_token(&mut self) -> bool {
+        self.inner.current_is_appropriate_end_tag_token()
+    }
+
+    fn adjusted_current_node_present_but_not_in_html_namespace(&mut self) -> bool {
+        self.inner
+            .adjusted_current_node_present_but_not_in_html_namespace()
+    }
+    fn set_token_start(&mut self, offset: usize) {
+        self.inner.set_token_start(offset)
+    }
+
+    fn set_attribute_value_start(&mut self, offset: usize) {
+        self.inner.set_attribute_value_start(offset)
+    }
+
+    fn set_attribute_value_end(&mut self, offset: usize) {
+        self.inner.set_attribute_value_end(offset)
+    }
+
+    fn set_attribute_value_syntax(&mut self, syntax: AttributeValueSyntax) {
+        self.inner.set_attribute_value_syntax(syntax)
+    }
+
+    fn set_position(&mut self, offset: usize) {
+        self.inner.set_position(offset)
+    }
+
+    fn set_line(&mut self, line: usize) {
+        self.inner.set_line(line)
+    }
+
+    fn is_aborted(&self) -> bool {
+        self.inner.is_aborted()
+    }
+}
+
+#[derive(Debug)]
+struct OpenElement {
+    name: Vec<u8>,
+    /// The index of the element in [`ElementCollector::pending`] if it matches the filter. This
+    /// index is absolute, i.e. does not change when finished elements are popped off.
+    element: Option<usize>,
+}
+
+#[derive(Debug, Default)]
+struct ElementCollector {
+    tag_filter: Vec<Vec<u8>>,
+    include_nested_text: bool,
+
+    position: Rc<Cell<usize>>,
+    tag_start: usize,
+
+    tag_name: Vec<u8>,
+    tag_matches: bool,
+    attributes: Vec<(HtmlString, HtmlString)>,
+
+    open_elements: Vec<OpenElement>,
+    /// Matching elements in the order of their start tags, and whether they are finished.
+    pending: VecDeque<(FlatElement, bool)>,
+    /// The number of elements that have been popped off `pending`.
+    popped: usize,
+}
+
+impl Callback<Infallible> for ElementCollector {
+    fn handle_event(&mut self, event: CallbackEvent<'_>) -> Option<Infallible> {
+        match event {
+            CallbackEvent::OpenStartTag { name } => {
+                self.tag_name.clear();
+                self.tag_name.extend(name);
+                self.tag_matches = self.tag_filter.iter().any(|x| x == name);
+                self.attributes.clear();
+            }
+            CallbackEvent::AttributeName { name } if self.tag_matches => {
+                self.attributes
+                    .push((name.to_owned().into(), HtmlString::default()));
+            }
+            CallbackEvent::AttributeValue { value, .. } => {
+                if let Some((_, attribute_value)) = self.attributes.last_mut() {
+                    attribute_value.extend(value);
+                }
+            }
+            CallbackEvent::CloseStartTag { .. } => self.handle_start_tag(),
+            CallbackEvent::EndTag { name } => self.handle_end_tag(name),
+            CallbackEvent::String { value } => {
+                for open_element in self.open_elements.iter().rev() {
+                    if let Some(i) = open_element.element {
+                        self.pending[i - self.popped].0.text.extend(value);
+                        if !self.include_nested_text {
+                            break;
+                        }
+                    }
+                    if is_hidden_text_element(&open_element.name) {
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        None
+    }
+}
+
+impl ElementCollector {
+    fn handle_start_tag(&mut self) {
+        let name = take(&mut self.tag_name);
+        let start = self.tag_start;
+        let end = self.position.get();
+
+        if self.open_elements.last().is_some_and(|e| e.name == name) {
+            self.close_open_elements(self.open_elements.len() - 1, start);
+        }
+
+        let element = if self.tag_matches {
+            self.pending.push_back((
+                FlatElement {
+                    name: name.clone().into(),
+                    attributes: self.attributes.drain(..).collect(),
+                    text: HtmlString::default(),
+                    span: start..end,
+                },
+                false,
+            ));
+            Some(self.popped + self.pending.len() - 1)
+        } else {
+            None
+        };
+
+        if is_void_element(&name) {
+            if let Some(i) = element {
+                self.pending[i - self.popped].1 = true;
+            }
+        } else {
+            self.open_elements.push(OpenElement { name, element });
+        }
+    }
+
+    fn handle_end_tag(&mut self, name: &[u8]) {
+        if let Some(i) = self.open_elements.iter().rposition(|e| e.name == name) {
+            self.close_open_elements(i + 1, self.tag_start);
+            self.close_open_elements(i, self.position.get());
+        }
+    }
+
+    /// Close all open elements starting at index `from`, ending them at `end`.
+    fn close_open_elements(&mut self, from: usize, end: usize) {
+        for open_element in self.open_elements.drain(from..) {
+            if let Some(i) = open_element.element {
+                let (element, finished) = &mut self.pending[i - self.popped];
+                element.span.end = end;
+                *finished = true;
+            }
+        }
+    }
+
+    fn pop_finished(&mut self) -> Option<FlatElement> {
+        if !self.pending.front()?.1 {
+            return None;
+        }
+
+        self.popped += 1;
+        self.pending.pop_front().map(|(element, _)| element)
+    }
+}
+
+/// Whether the content of an element is never displayed, so that it does not count as the text of
+/// the elements around it.
+fn is_hidden_text_element(name: &[u8]) -> bool {
+    matches!(name, b"script" | b"style")
+}
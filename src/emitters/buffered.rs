@@ -0,0 +1,443 @@
+//! An emitter whose tokens borrow from internal buffers, so that tokenizing does not allocate for
+//! every token.
+//!
+//! This is a middle ground between [`crate::DefaultEmitter`] and
+//! [`crate::emitters::callback::CallbackEmitter`]: tokens are returned one at a time like from
+//! the iterator API, but they are only valid until the next token is requested. As the
+//! [`Iterator`] trait cannot express that, tokens are retrieved using
+//! [`crate::Tokenizer::next_token`] instead.
+//!
+//! ```rust
+//! use html5gum::emitters::buffered::{BufferedEmitter, TokenRef};
+//! use html5gum::Tokenizer;
+//!
+//! let mut tokenizer = Tokenizer::new_with_emitter("<a href=/>hello</a>", BufferedEmitter::default());
+//! let mut text = Vec::new();
+//! while let Some(Ok(token)) = tokenizer.next_token() {
+//!     match token {
+//!         TokenRef::StartTag(tag) => assert_eq!(tag.attribute(b"href"), Some(&b"/"[..])),
+//!         TokenRef::String(s) => text.extend_from_slice(s),
+//!         _ => {}
+//!     }
+//! }
+//!
+//! assert_eq!(text, b"hello");
+//! ```
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::ops::Range;
+
+use crate::emitters::callback::{Callback, CallbackEmitter, CallbackEvent, Limits};
+use crate::{CommentKind, ContentStateSwitcher, Emitter, Error, State};
+
+/// A token that was emitted, but not yet returned. All ranges point into [`Buffers::bytes`].
+#[derive(Debug)]
+enum PendingToken {
+    StartTag {
+        self_closing: bool,
+        name: Range<usize>,
+        attributes: Range<usize>,
+    },
+    EndTag(Range<usize>),
+    String(Range<usize>),
+    Comment(Range<usize>, CommentKind),
+    Doctype {
+        force_quirks: bool,
+        name: Range<usize>,
+        public_identifier: Option<Range<usize>>,
+        system_identifier: Option<Range<usize>>,
+    },
+    Error(Error),
+}
+
+#[derive(Debug, Default)]
+struct Buffers {
+    /// The strings of all pending tokens, and of the token that was returned last.
+    bytes: Vec<u8>,
+    /// Names and values of the attributes of pending start tags.
+    attributes: Vec<(Range<usize>, Range<usize>)>,
+    tokens: VecDeque<PendingToken>,
+    /// The name and attributes of the tag that is being tokenized. They are kept separately, as
+    /// `bytes` may be cleared while the tag is not complete yet.
+    tag: Vec<u8>,
+    tag_name: Range<usize>,
+    tag_attributes: Vec<(Range<usize>, Range<usize>)>,
+}
+
+fn push_bytes(buf: &mut Vec<u8>, s: &[u8]) -> Range<usize> {
+    let start = buf.len();
+    buf.extend(s);
+    start..buf.len()
+}
+
+fn offset(range: &Range<usize>, offset: usize) -> Range<usize> {
+    range.start + offset..range.end + offset
+}
+
+impl Buffers {
+    fn push_bytes(&mut self, s: &[u8]) -> Range<usize> {
+        push_bytes(&mut self.bytes, s)
+    }
+
+    fn clear_tag(&mut self) {
+        self.tag.clear();
+        self.tag_attributes.clear();
+    }
+}
+
+impl Callback<Infallible> for Buffers {
+    fn handle_event(&mut self, event: CallbackEvent<'_>) -> Option<Infallible> {
+        let token = match event {
+            CallbackEvent::OpenStartTag { name } => {
+                self.clear_tag();
+                self.tag_name = push_bytes(&mut self.tag, name);
+                return None;
+            }
+            CallbackEvent::AttributeName { name } => {
+                let name = push_bytes(&mut self.tag, name);
+                let end = self.tag.len();
+                self.tag_attributes.push((name, end..end));
+                return None;
+            }
+            CallbackEvent::AttributeValue { value, .. } => {
+                let value = push_bytes(&mut self.tag, value);
+                if let Some((_, attribute_value)) = self.tag_attributes.last_mut() {
+                    *attribute_value = value;
+                }
+                return None;
+            }
+            // only emitted with `CallbackEmitter::pair_attributes` and
+            // `CallbackEmitter::mark_cdata_sections`, which are off
+            CallbackEvent::Attribute { .. }
+            | CallbackEvent::CdataStart
+            | CallbackEvent::CdataEnd => return None,
+            CallbackEvent::CloseStartTag { self_closing, .. } => {
+                let base = self.bytes.len();
+                self.bytes.extend(&self.tag);
+                let attributes_start = self.attributes.len();
+                self.attributes.extend(
+                    self.tag_attributes
+                        .iter()
+                        .map(|(name, value)| (offset(name, base), offset(value, base))),
+                );
+                let token = PendingToken::StartTag {
+                    self_closing,
+                    name: offset(&self.tag_name, base),
+                    attributes: attributes_start..self.attributes.len(),
+                };
+                self.clear_tag();
+                token
+            }
+            CallbackEvent::EndTag { name } => {
+                // attributes of end tags are dropped
+                self.clear_tag();
+                PendingToken::EndTag(self.push_bytes(name))
+            }
+            CallbackEvent::String { value } => PendingToken::String(self.push_bytes(value)),
+            CallbackEvent::Comment { value, kind } => {
+                PendingToken::Comment(self.push_bytes(value), kind)
+            }
+            CallbackEvent::Doctype {
+                name,
+                public_identifier,
+                system_identifier,
+                force_quirks,
+            } => PendingToken::Doctype {
+                force_quirks,
+                name: self.push_bytes(name),
+                public_identifier: public_identifier.map(|s| self.push_bytes(s)),
+                system_identifier: system_identifier.map(|s| self.push_bytes(s)),
+            },
+            CallbackEvent::Error(error) => PendingToken::Error(error),
+        };
+
+        self.tokens.push_back(token);
+        None
+    }
+
+    fn reset(&mut self) {
+        self.bytes.clear();
+        self.attributes.clear();
+        self.tokens.clear();
+        self.clear_tag();
+        self.tag_name = 0..0;
+    }
+}
+
+/// An emitter whose tokens borrow from internal buffers. See the [module
+/// documentation](self).
+///
+/// Used as an iterator, a tokenizer with this emitter does not yield any tokens.
+#[derive(Debug, Default)]
+pub struct BufferedEmitter {
+    inner: CallbackEmitter<Buffers>,
+}
+
+impl BufferedEmitter {
+    /// Whether to use [`crate::naive_next_state`] to switch states automatically.
+    ///
+    /// The default is off.
+    pub fn naively_switch_states(&mut self, yes: bool) {
+        self.inner.naively_switch_states(yes)
+    }
+
+    /// Use a [`ContentStateSwitcher`] to switch states automatically, see
+    /// [`CallbackEmitter::switch_states`].
+    ///
+    /// The default is off.
+    pub fn switch_states(&mut self, switcher: Option<ContentStateSwitcher>) {
+        self.inner.switch_states(switcher)
+    }
+
+    /// Limit the sizes of tokens, see [`Limits`].
+    ///
+    /// The default is no limits.
+    pub fn limits(&mut self, limits: Limits) {
+        self.inner.limits(limits)
+    }
+
+    /// Release the tokens that have been returned, once all pending tokens have been returned.
+    ///
+    /// This happens lazily, at the start of `Tokenizer::next_token`, because the token returned
+    /// last is still borrowed until then.
+    pub(crate) fn release_tokens(&mut self) {
+        let buffers = self.inner.callback_mut();
+        if buffers.tokens.is_empty() {
+            buffers.bytes.clear();
+            buffers.attributes.clear();
+        }
+    }
+
+    pub(crate) fn has_token(&self) -> bool {
+        !self.inner.callback().tokens.is_empty()
+    }
+
+    pub(crate) fn pop_token_ref(&mut self) -> Option<TokenRef<'_>> {
+        let token = self.inner.callback_mut().tokens.pop_front()?;
+
+        let buffers = self.inner.callback();
+        let bytes = &buffers.bytes[..];
+        Some(match token {
+            PendingToken::StartTag {
+                self_closing,
+                name,
+                attributes,
+            } => TokenRef::StartTag(StartTagRef {
+                self_closing,
+                name: &bytes[name],
+                bytes,
+                attributes: &buffers.attributes[attributes],
+            }),
+            PendingToken::EndTag(name) => TokenRef::EndTag(&bytes[name]),
+            PendingToken::String(value) => TokenRef::String(&bytes[value]),
+            PendingToken::Comment(value, kind) => TokenRef::Comment(&bytes[value], kind),
+            PendingToken::Doctype {
+                force_quirks,
+                name,
+                public_identifier,
+                system_identifier,
+            } => TokenRef::Doctype(DoctypeRef {
+                force_quirks,
+                name: &bytes[name],
+                public_identifier: public_identifier.map(|x| &bytes[x]),
+                system_identifier: system_identifier.map(|x| &bytes[x]),
+            }),
+            PendingToken::Error(error) => TokenRef::Error(error),
+        })
+    }
+}
+
+/// A token returned by [`crate::Tokenizer::next_token`]. It borrows from the tokenizer until the
+/// next token is requested.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenRef<'a> {
+    /// A HTML start tag.
+    StartTag(StartTagRef<'a>),
+    /// A HTML end tag, with its name.
+    EndTag(&'a [u8]),
+    /// A literal string.
+    String(&'a [u8]),
+    /// A HTML comment, and whether it is a proper comment.
+    Comment(&'a [u8], CommentKind),
+    /// A HTML doctype declaration.
+    Doctype(DoctypeRef<'a>),
+    /// A HTML parsing error.
+    Error(Error),
+}
+
+/// A HTML start tag, such as `<p>` or `<a>`. See [`crate::StartTag`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StartTagRef<'a> {
+    /// Whether this tag is self-closing.
+    pub self_closing: bool,
+    /// The start tag's name, such as `"p"` or `"a"`.
+    pub name: &'a [u8],
+    bytes: &'a [u8],
+    attributes: &'a [(Range<usize>, Range<usize>)],
+}
+
+impl<'a> StartTagRef<'a> {
+    /// The tag's attributes as `(name, value)`, in source order and without duplicates.
+    pub fn attributes(&self) -> impl Iterator<Item = (&'a [u8], &'a [u8])> + 'a {
+        let bytes = self.bytes;
+        self.attributes
+            .iter()
+            .map(move |(name, value)| (&bytes[name.clone()], &bytes[value.clone()]))
+    }
+
+    /// The value of the attribute with the given name, if there is one.
+    #[must_use]
+    pub fn attribute(&self, name: &[u8]) -> Option<&'a [u8]> {
+        self.attributes()
+            .find(|&(attribute_name, _)| attribute_name == name)
+            .map(|(_, value)| value)
+    }
+}
+
+/// A doctype. See [`crate::Doctype`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DoctypeRef<'a> {
+    /// The ["force quirks"](https://html.spec.whatwg.org/#force-quirks-flag) flag.
+    pub force_quirks: bool,
+    /// The doctype's name. For HTML documents this is "html".
+    pub name: &'a [u8],
+    /// The doctype's public identifier, or `None` if it is missing.
+    pub public_identifier: Option<&'a [u8]>,
+    /// The doctype's system identifier, or `None` if it is missing.
+    pub system_identifier: Option<&'a [u8]>,
+}
+
+impl Emitter for BufferedEmitter {
+    type Token = Infallible;
+
+    // opaque type around inner emitter
+
+    fn set_last_start_tag(&mut self, last_start_tag: Option<&[u8]>) {
+        self.inner.set_last_start_tag(last_start_tag)
+    }
+
+    fn last_start_tag(&self) -> Option<&[u8]> {
+        self.inner.last_start_tag()
+    }
+
+    fn begin(&mut self) {
+        self.inner.begin();
+    }
+
+    fn emit_eof(&mut self) {
+        self.inner.emit_eof()
+    }
+
+    fn finish(&mut self) {
+        self.inner.finish();
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+    }
+
+    fn emit_error(&mut self, error: Error) {
+        self.inner.emit_error(error)
+    }
+
+    fn pop_token(&mut self) -> Option<Self::Token> {
+        None
+    }
+
+    fn emit_string(&mut self, c: &[u8]) {
+        self.inner.emit_string(c)
+    }
+
+    fn init_start_tag(&mut self) {
+        self.inner.init_start_tag()
+    }
+
+    fn init_end_tag(&mut self) {
+        self.inner.init_end_tag()
+    }
+
+    fn init_comment(&mut self) {
+        self.inner.init_comment()
+    }
+
+    fn set_comment_kind(&mut self, kind: CommentKind) {
+        self.inner.set_comment_kind(kind)
+    }
+
+    fn emit_current_tag(&mut self) -> Option<State> {
+        self.inner.emit_current_tag()
+    }
+
+    fn emit_current_comment(&mut self) {
+        self.inner.emit_current_comment()
+    }
+
+    fn emit_current_doctype(&mut self) {
+        self.inner.emit_current_doctype()
+    }
+
+    fn set_self_closing(&mut self) {
+        self.inner.set_self_closing()
+    }
+
+    fn set_force_quirks(&mut self) {
+        self.inner.set_force_quirks()
+    }
+
+    fn push_tag_name(&mut self, s: &[u8]) {
+        self.inner.push_tag_name(s)
+    }
+
+    fn push_comment(&mut self, s: &[u8]) {
+        self.inner.push_comment(s)
+    }
+
+    fn push_doctype_name(&mut self, s: &[u8]) {
+        self.inner.push_doctype_name(s)
+    }
+
+    fn init_doctype(&mut self) {
+        self.inner.init_doctype()
+    }
+
+    fn init_attribute(&mut self) {
+        self.inner.init_attribute()
+    }
+
+    fn push_attribute_name(&mut self, s: &[u8]) {
+        self.inner.push_attribute_name(s)
+    }
+
+    fn init_attribute_value(&mut self) {
+        self.inner.init_attribute_value()
+    }
+
+    fn push_attribute_value(&mut self, s: &[u8]) {
+        self.inner.push_attribute_value(s)
+    }
+
+    fn set_doctype_public_identifier(&mut self, value: &[u8]) {
+        self.inner.set_doctype_public_identifier(value)
+    }
+
+    fn set_doctype_system_identifier(&mut self, value: &[u8]) {
+        self.inner.set_doctype_system_identifier(value)
+    }
+
+    fn push_doctype_public_identifier(&mut self, s: &[u8]) {
+        self.inner.push_doctype_public_identifier(s)
+    }
+
+    fn push_doctype_system_identifier(&mut self, s: &[u8]) {
+        self.inner.push_doctype_system_identifier(s)
+    }
+
+    fn current_is_appropriate_end_tag_token(&mut self) -> bool {
+        self.inner.current_is_appropriate_end_tag_token()
+    }
+
+    fn adjusted_current_node_present_but_not_in_html_namespace(&mut self) -> bool {
+        self.inner
+            .adjusted_current_node_present_but_not_in_html_namespace()
+    }
+}
@@ -34,13 +34,24 @@
 //!
 //! assert_eq!(text_fragments, vec![b"Hello".to_vec()]);
 //! ```
+//!
+//! To stop tokenizing from within the callback, use [CallbackEmitter::new_try].
+//!
+//! To test a callback, compare its input with the events that a [RecordingCallback] collects.
 
-use std::collections::VecDeque;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
 use std::convert::Infallible;
-use std::mem::swap;
+use std::fmt::{self, Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use std::mem::{swap, take};
 
-use crate::utils::trace_log;
-use crate::{naive_next_state, Emitter, Error, State};
+pub use crate::emitters::recording::{OwnedCallbackEvent, RecordingCallback};
+use crate::utils::{is_void_element, trace_log, DebugBytes};
+use crate::{
+    naive_next_state, AttributeValueSyntax, CommentKind, ContentStateSwitcher, Emitter, Error,
+    Span, State,
+};
 
 /// Events used by [CallbackEmitter].
 ///
@@ -53,15 +64,19 @@ pub enum CallbackEvent<'a> {
     ///
     /// Attributes have not yet been read.
     OpenStartTag {
-        /// The name of the start tag.
+        /// The name of the start tag, in lowercase unless [`CallbackEmitter::preserve_case`] is on.
         name: &'a [u8],
     },
 
     /// Visit an attribute name, for example `"mykey"` in `"<mytag mykey=myvalue>"`.
     ///
     /// The attribute value has not yet been read.
+    ///
+    /// If a tag has several attributes with the same name, only the first one is visited. Every
+    /// further occurrence produces an [`Error::DuplicateAttribute`] event instead, and its value
+    /// is skipped.
     AttributeName {
-        /// The name of the attribute.
+        /// The name of the attribute, in lowercase unless [`CallbackEmitter::preserve_case`] is on.
         name: &'a [u8],
     },
 
@@ -74,6 +89,27 @@ pub enum CallbackEvent<'a> {
     AttributeValue {
         /// The value of the attribute.
         value: &'a [u8],
+        /// Whether the value was quoted in the input. This is never
+        /// [`AttributeValueSyntax::Empty`], as this event is only emitted for non-empty values. Use
+        /// [`CallbackEmitter::pair_attributes`] to see the syntax of every attribute.
+        syntax: AttributeValueSyntax,
+    },
+
+    /// Visit a complete attribute, for example `mykey=myvalue` in `"<mytag mykey=myvalue>"`.
+    ///
+    /// Only emitted instead of `AttributeName` and `AttributeValue` if
+    /// [`CallbackEmitter::pair_attributes`] is on. Every occurrence of a duplicate attribute
+    /// produces this event too, right after its [`Error::DuplicateAttribute`] event. Per spec,
+    /// those should be ignored.
+    Attribute {
+        /// The name of the attribute, in lowercase unless [`CallbackEmitter::preserve_case`] is on.
+        name: &'a [u8],
+        /// The value of the attribute, or `None` if there was no `=` after the name, as in
+        /// `"<input disabled>"`.
+        value: Option<&'a [u8]>,
+        /// Whether the value was quoted in the input, or [`AttributeValueSyntax::Empty`] if there
+        /// was no value.
+        syntax: AttributeValueSyntax,
     },
 
     /// Visit the end of the start tag, for example `">"` in `"<mytag mykey=myvalue>"`.
@@ -84,6 +120,9 @@ pub enum CallbackEvent<'a> {
         /// Note that in HTML5 this difference is largely ignored, and tags are considered
         /// self-closing based on a hardcoded list of names, not based on syntax.
         self_closing: bool,
+        /// Whether the tag is a void element such as `<br>`, which never has an end tag. See
+        /// [`crate::is_void_element`].
+        is_void: bool,
     },
 
     /// Visit `"</mytag>"`.
@@ -92,7 +131,7 @@ pub enum CallbackEvent<'a> {
     /// tags, before this event. It's best to ignore them as they are not valid HTML, but can still
     /// be observed through most HTML parsers.
     EndTag {
-        /// The name of the end tag.
+        /// The name of the end tag, in lowercase unless [`CallbackEmitter::preserve_case`] is on.
         name: &'a [u8],
     },
 
@@ -106,19 +145,34 @@ pub enum CallbackEvent<'a> {
         value: &'a [u8],
     },
 
+    /// Visit the `<![CDATA[` of a CDATA section, which is only recognized in foreign content such
+    /// as SVG. The content of the section follows as `String` events, which are not combined
+    /// with the text around it. No entities are decoded in it.
+    ///
+    /// Only emitted if [`CallbackEmitter::mark_cdata_sections`] is on.
+    CdataStart,
+
+    /// Visit the `]]>` of a CDATA section, or the end of the input if the section is not closed.
+    /// See `CdataStart`.
+    CdataEnd,
+
     /// Visit a comment, like `<!-- DON'T HACK THIS WEBSITE -->`
     Comment {
         /// The contents of the comment.
         value: &'a [u8],
+        /// Whether this is a proper comment, or something that was coerced into one, like
+        /// `<?xml?>`.
+        kind: CommentKind,
     },
 
     /// Visit `<!DOCTYPE html>`.
     Doctype {
         /// Name of the docstring.
         name: &'a [u8],
-        /// Public identifier (see spec)
+        /// Public identifier (see spec), `None` if missing and possibly empty otherwise. See
+        /// [`crate::Doctype`].
         public_identifier: Option<&'a [u8]>,
-        /// System identifier (see spec)
+        /// System identifier (see spec), `None` if missing and possibly empty otherwise.
         system_identifier: Option<&'a [u8]>,
         /// Enable quirksmode
         force_quirks: bool,
@@ -128,6 +182,23 @@ pub enum CallbackEvent<'a> {
     Error(Error),
 }
 
+/// The end tag that caused an error, see [`Callback::set_error_context`].
+#[derive(Debug, Clone, Copy)]
+pub struct ErrorContext<'a> {
+    /// The name of the end tag, as in [`CallbackEvent::EndTag`].
+    pub tag_name: &'a [u8],
+    attribute_names: &'a AttributeNames,
+}
+
+impl<'a> ErrorContext<'a> {
+    /// The names of the attributes that the end tag has so far, in the order they appeared. They
+    /// are in lowercase even with [`CallbackEmitter::preserve_case`], and do not include
+    /// duplicates or attributes that were dropped because of [`Limits::max_attributes`].
+    pub fn attribute_names(&self) -> impl Iterator<Item = &'a [u8]> {
+        self.attribute_names.iter()
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 enum CurrentTag {
     Start,
@@ -137,7 +208,52 @@ enum CurrentTag {
 #[derive(Debug)]
 struct CallbackState<F, T> {
     callback: F,
-    emitted_tokens: VecDeque<T>,
+    emitted_tokens: TokenQueue<T>,
+}
+
+/// A FIFO queue of tokens. The tokenizer rarely emits more than one token before popping it, so
+/// the first token is kept out of the `VecDeque`.
+#[derive(Debug)]
+struct TokenQueue<T> {
+    // `rest` is only used if `first` is set
+    first: Option<T>,
+    rest: VecDeque<T>,
+}
+
+impl<T> Default for TokenQueue<T> {
+    fn default() -> Self {
+        TokenQueue {
+            first: None,
+            rest: VecDeque::new(),
+        }
+    }
+}
+
+impl<T> TokenQueue<T> {
+    #[inline]
+    fn push(&mut self, token: T) {
+        if self.first.is_none() {
+            self.first = Some(token);
+        } else {
+            self.rest.push_back(token);
+        }
+    }
+
+    #[inline]
+    fn pop(&mut self) -> Option<T> {
+        let token = self.first.take()?;
+        self.first = self.rest.pop_front();
+        Some(token)
+    }
+
+    fn len(&self) -> usize {
+        usize::from(self.first.is_some()) + self.rest.len()
+    }
+
+    fn clear(&mut self) {
+        self.first = None;
+        self.rest.clear();
+    }
 }
 
 /// This trait is implemented for all functions that have the same signature as
@@ -147,6 +263,47 @@ pub trait Callback<T> {
     /// Perform some action on a parsing event, and, optionally, return a value that can be yielded
     /// from the [crate::Tokenizer] iterator.
     fn handle_event(&mut self, event: CallbackEvent<'_>) -> Option<T>;
+
+    /// Called with the byte offset of every `<` that may start a tag, comment or doctype. See
+    /// [`Emitter::set_token_start`].
+    fn set_token_start(&mut self, offset: usize) {
+        let _ = offset;
+    }
+
+    /// Called right before every [`CallbackEvent::AttributeValue`], and every
+    /// [`CallbackEvent::Attribute`] with a value, with the range of the input that the value was
+    /// decoded from, which excludes its quotes. See [`Emitter::set_attribute_value_end`].
+    fn set_attribute_value_span(&mut self, span: Span) {
+        let _ = span;
+    }
+
+    /// Called right before the [`CallbackEvent::Error`] of an [`Error::EndTagWithAttributes`] or
+    /// [`Error::EndTagWithTrailingSolidus`], with the end tag that caused it.
+    fn set_error_context(&mut self, context: ErrorContext<'_>) {
+        let _ = context;
+    }
+
+    /// Called with the byte offset up to which the input has been consumed. See
+    /// [`Emitter::set_position`].
+    #[inline]
+    fn set_position(&mut self, offset: usize) {
+        let _ = offset;
+    }
+
+    /// Called with the line up to which the input has been consumed. See [`Emitter::set_line`].
+    #[inline]
+    fn set_line(&mut self, line: usize) {
+        let _ = line;
+    }
+
+    /// Whether to stop tokenizing. See [`Emitter::is_aborted`].
+    #[inline]
+    fn is_aborted(&self) -> bool {
+        false
+    }
+
+    /// Called when the emitter is reset for another document. See [`Emitter::reset`].
+    fn reset(&mut self) {}
 }
 
 impl<T, F> Callback<T> for F
@@ -158,6 +315,44 @@ where
     }
 }
 
+/// A callback that can fail, see [`CallbackEmitter::new_try`].
+///
+/// The first error is yielded from the [crate::Tokenizer] iterator as `Err`, after which the
+/// tokenizer stops. Events that the tokenizer emits while stopping are not passed to the callback.
+#[derive(Debug)]
+pub struct TryCallback<F> {
+    callback: F,
+    failed: bool,
+}
+
+impl<T, E, F> Callback<Result<T, E>> for TryCallback<F>
+where
+    F: FnMut(CallbackEvent<'_>) -> Result<Option<T>, E>,
+{
+    fn handle_event(&mut self, event: CallbackEvent<'_>) -> Option<Result<T, E>> {
+        if self.failed {
+            return None;
+        }
+
+        match (self.callback)(event) {
+            Ok(token) => token.map(Ok),
+            Err(e) => {
+                self.failed = true;
+                Some(Err(e))
+            }
+        }
+    }
+
+    #[inline]
+    fn is_aborted(&self) -> bool {
+        self.failed
+    }
+
+    fn reset(&mut self) {
+        self.failed = false;
+    }
+}
+
 impl<F, T> CallbackState<F, T>
 where
     F: Callback<T>,
@@ -165,7 +360,7 @@ where
     fn emit_event(&mut self, event: CallbackEvent<'_>) {
         let res = self.callback.handle_event(event);
         if let Some(token) = res {
-            self.emitted_tokens.push_front(token);
+            self.emitted_tokens.push(token);
         }
     }
 }
@@ -177,7 +372,7 @@ where
     fn default() -> Self {
         CallbackState {
             callback: F::default(),
-            emitted_tokens: VecDeque::default(),
+            emitted_tokens: TokenQueue::default(),
         }
     }
 }
@@ -185,17 +380,42 @@ where
 #[derive(Debug, Default)]
 struct EmitterState {
     naively_switch_states: bool,
+    switcher: Option<ContentStateSwitcher>,
+    pair_attributes: bool,
+    preserve_case: bool,
+    skip_rawtext: bool,
+    mark_cdata_sections: bool,
+    /// The inverse of [`CallbackEmitter::coalesce_strings`], so that the default is off.
+    unbuffered_strings: bool,
+    limits: Limits,
+    /// Whether [`Error::LimitExceeded`] was already emitted for the current tag, comment or
+    /// doctype.
+    token_limit_exceeded: bool,
+    /// Whether [`Error::LimitExceeded`] was already emitted for the current run of characters.
+    string_limit_exceeded: bool,
 
     current_characters: Vec<u8>,
     current_comment: Vec<u8>,
+    current_comment_kind: CommentKind,
 
     last_start_tag: Vec<u8>,
     current_tag_had_attributes: bool,
     current_tag_type: Option<CurrentTag>,
     current_tag_self_closing: bool,
     current_tag_name: Vec<u8>,
+    /// Only used with [`CallbackEmitter::preserve_case`].
+    current_tag_name_original: Vec<u8>,
     current_attribute_name: Vec<u8>,
+    /// Only used with [`CallbackEmitter::preserve_case`].
+    current_attribute_name_original: Vec<u8>,
     current_attribute_value: Vec<u8>,
+    current_attribute_is_duplicate: bool,
+    current_attribute_is_dropped: bool,
+    current_attribute_has_value: bool,
+    current_attribute_value_span: Span,
+    current_attribute_value_syntax: AttributeValueSyntax,
+    attribute_count: usize,
+    attribute_names: AttributeNames,
 
     // strings related to doctype
     doctype_name: Vec<u8>,
@@ -206,9 +426,166 @@ struct EmitterState {
     doctype_force_quirks: bool,
 }
 
+impl EmitterState {
+    /// The name of the current tag, as passed to the callback.
+    fn tag_name(&self) -> &[u8] {
+        if self.preserve_case {
+            &self.current_tag_name_original
+        } else {
+            &self.current_tag_name
+        }
+    }
+
+    /// The name of the current attribute, as passed to the callback.
+    fn attribute_name(&self) -> &[u8] {
+        if self.preserve_case {
+            &self.current_attribute_name_original
+        } else {
+            &self.current_attribute_name
+        }
+    }
+}
+
+/// The names of the current tag's attributes, used to detect duplicates.
+///
+/// All names are stored in one buffer. Most tags have few attributes, for which a linear search is
+/// fastest. Tags with many attributes additionally get an index by hash, so that pathological
+/// inputs with thousands of attributes don't take quadratic time.
+#[derive(Debug, Default)]
+pub(crate) struct AttributeNames {
+    buf: Vec<u8>,
+    ends: Vec<usize>,
+    by_hash: HashMap<u64, usize>,
+}
+
+impl AttributeNames {
+    const MAX_LINEAR_SEARCH: usize = 8;
+
+    pub(crate) fn clear(&mut self) {
+        self.buf.clear();
+        self.ends.clear();
+        self.by_hash.clear();
+    }
+
+    fn get(&self, i: usize) -> &[u8] {
+        let start = if i == 0 { 0 } else { self.ends[i - 1] };
+        &self.buf[start..self.ends[i]]
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &[u8]> {
+        (0..self.ends.len()).map(move |i| self.get(i))
+    }
+
+    fn hash(name: &[u8]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        name.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Add a name, and return `false` if it was already present.
+    pub(crate) fn insert(&mut self, name: &[u8]) -> bool {
+        let len = self.ends.len();
+
+        if len < Self::MAX_LINEAR_SEARCH {
+            if (0..len).any(|i| self.get(i) == name) {
+                return false;
+            }
+        } else {
+            if len == Self::MAX_LINEAR_SEARCH {
+                for i in 0..len {
+                    let hash = Self::hash(self.get(i));
+                    self.by_hash.entry(hash).or_insert(i);
+                }
+            }
+
+            let hash = Self::hash(name);
+            match self.by_hash.get(&hash) {
+                // on a hash collision, fall back to a linear search
+                Some(&i) => {
+                    if self.get(i) == name || (0..len).any(|i| self.get(i) == name) {
+                        return false;
+                    }
+                }
+                None => {
+                    self.by_hash.insert(hash, len);
+                }
+            }
+        }
+
+        self.buf.extend(name);
+        self.ends.push(self.buf.len());
+        true
+    }
+}
+
+/// Upper bounds for the sizes of tokens, to keep memory usage bounded on untrusted input. See
+/// [`CallbackEmitter::limits`].
+///
+/// Whatever exceeds a limit is discarded, and a single [`Error::LimitExceeded`] is emitted for the
+/// token. All limits are in bytes, except for `max_attributes`. The default is no limits at all.
+///
+/// ```rust
+/// use html5gum::{DefaultEmitter, Error, Limits, Token, Tokenizer};
+///
+/// let mut emitter = DefaultEmitter::default();
+/// emitter.limits(Limits {
+///     max_comment_len: 5,
+///     ..Limits::default()
+/// });
+///
+/// let tokens: Vec<_> = Tokenizer::new_with_emitter("<!--hello world-->", emitter).flatten().collect();
+/// assert_eq!(
+///     tokens,
+///     [Token::Error(Error::LimitExceeded), Token::Comment(b"hello".to_vec().into())]
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// The maximum number of attributes per tag. Further attributes are dropped entirely.
+    pub max_attributes: usize,
+    /// The maximum length of tag names, attribute names and doctype names.
+    pub max_name_len: usize,
+    /// The maximum length of attribute values and doctype identifiers.
+    pub max_value_len: usize,
+    /// The maximum length of a comment.
+    pub max_comment_len: usize,
+    /// The maximum length of a run of characters between other tokens.
+    pub max_string_len: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_attributes: usize::MAX,
+            max_name_len: usize::MAX,
+            max_value_len: usize::MAX,
+            max_comment_len: usize::MAX,
+            max_string_len: usize::MAX,
+        }
+    }
+}
+
+/// Clear `buf`, keeping its capacity.
+fn cleared(mut buf: Vec<u8>) -> Vec<u8> {
+    buf.clear();
+    buf
+}
+
+/// Append as much of `s` to `buf` as `max_len` allows, and return whether anything was cut off.
+#[inline(always)]
+fn extend_limited(buf: &mut Vec<u8>, s: &[u8], max_len: usize) -> bool {
+    if buf.len() + s.len() <= max_len {
+        buf.extend_from_slice(s);
+        return false;
+    }
+
+    let len = max_len.saturating_sub(buf.len()).min(s.len());
+    buf.extend_from_slice(&s[..len]);
+    len < s.len()
+}
+
 /// The emitter class to pass to [crate::Tokenizer::new_with_emitter]. Please refer to the
 /// module-level documentation on [crate::emitters::callback] for usage.
-#[derive(Debug)]
 pub struct CallbackEmitter<F, T = Infallible> {
     // this struct is only split out so [CallbackState::emit_event] can borrow things concurrently
     // with other attributes.
@@ -216,6 +593,33 @@ pub struct CallbackEmitter<F, T = Infallible> {
     emitter_state: EmitterState,
 }
 
+impl<F, T> Debug for CallbackEmitter<F, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let state = &self.emitter_state;
+        let mut debug = f.debug_struct("CallbackEmitter");
+        debug
+            .field("naively_switch_states", &state.naively_switch_states)
+            .field("last_start_tag", &DebugBytes(&state.last_start_tag))
+            .field("current_tag_type", &state.current_tag_type);
+
+        if state.current_tag_type.is_some() {
+            debug
+                .field("current_tag_name", &DebugBytes(&state.current_tag_name))
+                .field(
+                    "current_attribute_name",
+                    &DebugBytes(&state.current_attribute_name),
+                );
+        }
+
+        debug
+            .field("current_characters", &DebugBytes(&state.current_characters))
+            .field("current_comment", &DebugBytes(&state.current_comment))
+            .field("doctype_name", &DebugBytes(&state.doctype_name))
+            .field("queued_tokens", &self.callback_state.emitted_tokens.len())
+            .finish_non_exhaustive()
+    }
+}
+
 impl<F, T> Default for CallbackEmitter<F, T>
 where
     F: Default,
@@ -228,6 +632,38 @@ where
     }
 }
 
+impl<F, T, E> CallbackEmitter<TryCallback<F>, Result<T, E>>
+where
+    F: FnMut(CallbackEvent<'_>) -> Result<Option<T>, E>,
+{
+    /// Create a new emitter with a callback that can fail.
+    ///
+    /// Tokens returned by the callback are yielded as `Ok`. Once it returns an error, the error is
+    /// yielded as `Err` and the tokenizer stops without reading any more input.
+    ///
+    /// ```
+    /// use html5gum::Tokenizer;
+    /// use html5gum::emitters::callback::{CallbackEmitter, CallbackEvent};
+    ///
+    /// let emitter = CallbackEmitter::new_try(|event: CallbackEvent<'_>| match event {
+    ///     CallbackEvent::OpenStartTag { name } if name == b"script" => Err("found a script"),
+    ///     CallbackEvent::String { value } => Ok(Some(value.to_vec())),
+    ///     _ => Ok(None),
+    /// });
+    ///
+    /// let tokens: Vec<_> = Tokenizer::new_with_emitter("hello<script>alert(1)</script>", emitter)
+    ///     .flatten()
+    ///     .collect();
+    /// assert_eq!(tokens, [Ok(b"hello".to_vec()), Err("found a script")]);
+    /// ```
+    pub fn new_try(callback: F) -> Self {
+        CallbackEmitter::new(TryCallback {
+            callback,
+            failed: false,
+        })
+    }
+}
+
 impl<F, T> CallbackEmitter<F, T>
 where
     F: Callback<T>,
@@ -240,12 +676,17 @@ where
         CallbackEmitter {
             callback_state: CallbackState {
                 callback,
-                emitted_tokens: VecDeque::new(),
+                emitted_tokens: TokenQueue::default(),
             },
             emitter_state: EmitterState::default(),
         }
     }
 
+    /// Get access to the inner callback.
+    pub fn callback(&self) -> &F {
+        &self.callback_state.callback
+    }
+
     /// Get mutable access to the inner callback.
     pub fn callback_mut(&mut self) -> &mut F {
         &mut self.callback_state.callback
@@ -258,26 +699,177 @@ where
         self.emitter_state.naively_switch_states = yes;
     }
 
+    /// Use a [`ContentStateSwitcher`] to switch states automatically. This takes precedence over
+    /// [`CallbackEmitter::naively_switch_states`], and also decides whether CDATA sections are
+    /// allowed.
+    ///
+    /// The default is off.
+    pub fn switch_states(&mut self, switcher: Option<ContentStateSwitcher>) {
+        self.emitter_state.switcher = switcher;
+    }
+
+    /// Skip the content of elements such as `<script>` and `<style>`, instead of passing it to the
+    /// callback as [`CallbackEvent::String`]. See [`Emitter::should_skip_rawtext`] for details.
+    ///
+    /// This only makes a difference if states are switched, with
+    /// [`CallbackEmitter::naively_switch_states`] or [`CallbackEmitter::switch_states`]. The
+    /// default is off.
+    pub fn skip_rawtext(&mut self, yes: bool) {
+        self.emitter_state.skip_rawtext = yes;
+    }
+
+    /// Surround the content of CDATA sections with [`CallbackEvent::CdataStart`] and
+    /// [`CallbackEvent::CdataEnd`], so that it can be told apart from other text. Otherwise it is
+    /// combined with the text around it into one [`CallbackEvent::String`].
+    ///
+    /// CDATA sections are only recognized in foreign content, see
+    /// [`Emitter::adjusted_current_node_present_but_not_in_html_namespace`]. The default is off.
+    pub fn mark_cdata_sections(&mut self, yes: bool) {
+        self.emitter_state.mark_cdata_sections = yes;
+    }
+
+    /// Emit [`CallbackEvent::Attribute`] instead of separate `AttributeName` and `AttributeValue`
+    /// events, once the attribute is complete.
+    ///
+    /// The default is off.
+    pub fn pair_attributes(&mut self, yes: bool) {
+        self.emitter_state.pair_attributes = yes;
+    }
+
+    /// Pass tag and attribute names to the callback as they appear in the input, instead of
+    /// lowercasing them. The names are still compared case-insensitively, both to find duplicate
+    /// attributes and to find the end tag of `<script>` and other elements whose content is not
+    /// HTML. See [`Emitter::push_tag_name_original`].
+    ///
+    /// The default is off.
+    pub fn preserve_case(&mut self, yes: bool) {
+        self.emitter_state.preserve_case = yes;
+    }
+
+    /// Whether to combine consecutive text into a single [`CallbackEvent::String`], which is
+    /// emitted right before the next tag, comment or doctype, or at the end of the input.
+    ///
+    /// When this is off, text is passed to the callback as soon as the tokenizer has read it,
+    /// without being copied into a buffer first. One run of text may then arrive as any number
+    /// of `String` events: for example, `a&amp;b` may produce `"a"`, `"&"` and `"b"`. The events
+    /// are still in the order of the input, and each one ends at the position last passed to
+    /// [`Callback::set_position`]. [`Limits::max_string_len`] does not apply in this mode.
+    ///
+    /// The default is on.
+    pub fn coalesce_strings(&mut self, yes: bool) {
+        if !yes {
+            self.flush_current_characters();
+        }
+        self.emitter_state.unbuffered_strings = !yes;
+    }
+
+    /// Limit the sizes of tokens. This bounds the memory used by this emitter, regardless of the
+    /// input.
+    ///
+    /// The default is no limits.
+    pub fn limits(&mut self, limits: Limits) {
+        self.emitter_state.limits = limits;
+    }
+
+    fn token_limit_exceeded(&mut self) {
+        if !self.emitter_state.token_limit_exceeded {
+            self.emitter_state.token_limit_exceeded = true;
+            self.emit_error(Error::LimitExceeded);
+        }
+    }
+
+    /// Emit an error about the current end tag, see [`Callback::set_error_context`].
+    fn emit_end_tag_error(&mut self, error: Error) {
+        self.callback_state
+            .callback
+            .set_error_context(ErrorContext {
+                tag_name: self.emitter_state.tag_name(),
+                attribute_names: &self.emitter_state.attribute_names,
+            });
+        self.emit_error(error);
+    }
+
     fn flush_attribute_name(&mut self) {
-        if !self.emitter_state.current_attribute_name.is_empty() {
-            self.callback_state
-                .emit_event(CallbackEvent::AttributeName {
-                    name: &self.emitter_state.current_attribute_name,
-                });
+        // with paired attributes, the name is kept until the value is complete
+        if !self.emitter_state.pair_attributes
+            && !self.emitter_state.current_attribute_name.is_empty()
+        {
+            if self
+                .emitter_state
+                .attribute_names
+                .insert(&self.emitter_state.current_attribute_name)
+            {
+                self.callback_state
+                    .emit_event(CallbackEvent::AttributeName {
+                        name: self.emitter_state.attribute_name(),
+                    });
+            } else {
+                self.emitter_state.current_attribute_is_duplicate = true;
+                self.emit_error(Error::DuplicateAttribute);
+            }
             self.emitter_state.current_attribute_name.clear();
+            self.emitter_state.current_attribute_name_original.clear();
         }
     }
 
     fn flush_attribute(&mut self) {
+        if self.emitter_state.pair_attributes {
+            self.flush_attribute_pair();
+            return;
+        }
+
         self.flush_attribute_name();
 
         if !self.emitter_state.current_attribute_value.is_empty() {
+            self.callback_state
+                .callback
+                .set_attribute_value_span(self.emitter_state.current_attribute_value_span);
             self.callback_state
                 .emit_event(CallbackEvent::AttributeValue {
                     value: &self.emitter_state.current_attribute_value,
+                    syntax: self.emitter_state.current_attribute_value_syntax,
                 });
             self.emitter_state.current_attribute_value.clear();
         }
+
+        self.emitter_state.current_attribute_is_duplicate = false;
+        self.emitter_state.current_attribute_is_dropped = false;
+        self.emitter_state.current_attribute_has_value = false;
+        self.emitter_state.current_attribute_value_syntax = AttributeValueSyntax::Empty;
+    }
+
+    fn flush_attribute_pair(&mut self) {
+        if !self.emitter_state.current_attribute_name.is_empty() {
+            if !self
+                .emitter_state
+                .attribute_names
+                .insert(&self.emitter_state.current_attribute_name)
+            {
+                self.emit_error(Error::DuplicateAttribute);
+            }
+
+            if self.emitter_state.current_attribute_has_value {
+                self.callback_state
+                    .callback
+                    .set_attribute_value_span(self.emitter_state.current_attribute_value_span);
+            }
+            self.callback_state.emit_event(CallbackEvent::Attribute {
+                name: self.emitter_state.attribute_name(),
+                value: if self.emitter_state.current_attribute_has_value {
+                    Some(&self.emitter_state.current_attribute_value)
+                } else {
+                    None
+                },
+                syntax: self.emitter_state.current_attribute_value_syntax,
+            });
+            self.emitter_state.current_attribute_name.clear();
+            self.emitter_state.current_attribute_name_original.clear();
+            self.emitter_state.current_attribute_value.clear();
+        }
+
+        self.emitter_state.current_attribute_is_dropped = false;
+        self.emitter_state.current_attribute_has_value = false;
+        self.emitter_state.current_attribute_value_syntax = AttributeValueSyntax::Empty;
     }
 
     fn flush_open_start_tag(&mut self) {
@@ -285,7 +877,7 @@ where
             && !self.emitter_state.current_tag_name.is_empty()
         {
             self.callback_state.emit_event(CallbackEvent::OpenStartTag {
-                name: &self.emitter_state.current_tag_name,
+                name: self.emitter_state.tag_name(),
             });
 
             self.emitter_state.last_start_tag.clear();
@@ -305,6 +897,7 @@ where
             value: &self.emitter_state.current_characters,
         });
         self.emitter_state.current_characters.clear();
+        self.emitter_state.string_limit_exceeded = false;
     }
 }
 impl<F, T> Emitter for CallbackEmitter<F, T>
@@ -320,38 +913,146 @@ where
             .extend(last_start_tag.unwrap_or_default());
     }
 
+    fn last_start_tag(&self) -> Option<&[u8]> {
+        Some(&self.emitter_state.last_start_tag[..]).filter(|x| !x.is_empty())
+    }
+
+    fn should_skip_rawtext(&mut self, _tag_name: &[u8]) -> bool {
+        self.emitter_state.skip_rawtext
+    }
+
     fn emit_eof(&mut self) {
         self.flush_current_characters();
     }
 
+    fn reset(&mut self) {
+        let state = take(&mut self.emitter_state);
+        let mut switcher = state.switcher;
+        if let Some(switcher) = &mut switcher {
+            switcher.reset();
+        }
+        let mut attribute_names = state.attribute_names;
+        attribute_names.clear();
+
+        self.emitter_state = EmitterState {
+            naively_switch_states: state.naively_switch_states,
+            switcher,
+            pair_attributes: state.pair_attributes,
+            preserve_case: state.preserve_case,
+            skip_rawtext: state.skip_rawtext,
+            mark_cdata_sections: state.mark_cdata_sections,
+            unbuffered_strings: state.unbuffered_strings,
+            limits: state.limits,
+            current_characters: cleared(state.current_characters),
+            current_comment: cleared(state.current_comment),
+            last_start_tag: cleared(state.last_start_tag),
+            current_tag_name: cleared(state.current_tag_name),
+            current_tag_name_original: cleared(state.current_tag_name_original),
+            current_attribute_name: cleared(state.current_attribute_name),
+            current_attribute_name_original: cleared(state.current_attribute_name_original),
+            current_attribute_value: cleared(state.current_attribute_value),
+            attribute_names,
+            doctype_name: cleared(state.doctype_name),
+            doctype_public_identifier: cleared(state.doctype_public_identifier),
+            doctype_system_identifier: cleared(state.doctype_system_identifier),
+            ..EmitterState::default()
+        };
+        self.callback_state.emitted_tokens.clear();
+        self.callback_state.callback.reset();
+    }
+
     fn emit_error(&mut self, error: Error) {
         self.callback_state.emit_event(CallbackEvent::Error(error));
     }
 
     fn pop_token(&mut self) -> Option<Self::Token> {
-        self.callback_state.emitted_tokens.pop_back()
+        self.callback_state.emitted_tokens.pop()
+    }
+
+    fn set_token_start(&mut self, offset: usize) {
+        self.callback_state.callback.set_token_start(offset);
+    }
+
+    fn set_attribute_value_start(&mut self, offset: usize) {
+        self.emitter_state.current_attribute_value_span = Span {
+            start: offset,
+            end: offset,
+        };
+    }
+
+    fn set_attribute_value_end(&mut self, offset: usize) {
+        self.emitter_state.current_attribute_value_span.end = offset;
+    }
+
+    fn set_attribute_value_syntax(&mut self, syntax: AttributeValueSyntax) {
+        self.emitter_state.current_attribute_value_syntax = syntax;
+    }
+
+    #[inline]
+    fn set_position(&mut self, offset: usize) {
+        self.callback_state.callback.set_position(offset);
+    }
+
+    #[inline]
+    fn set_line(&mut self, line: usize) {
+        self.callback_state.callback.set_line(line);
+    }
+
+    #[inline]
+    fn is_aborted(&self) -> bool {
+        self.callback_state.callback.is_aborted()
     }
 
     fn emit_string(&mut self, s: &[u8]) {
         crate::utils::trace_log!("callbacks: emit_string, len={}", s.len());
-        self.emitter_state.current_characters.extend(s);
+        if self.emitter_state.unbuffered_strings {
+            if !s.is_empty() {
+                self.callback_state
+                    .emit_event(CallbackEvent::String { value: s });
+            }
+            return;
+        }
+
+        if extend_limited(
+            &mut self.emitter_state.current_characters,
+            s,
+            self.emitter_state.limits.max_string_len,
+        ) && !self.emitter_state.string_limit_exceeded
+        {
+            self.emitter_state.string_limit_exceeded = true;
+            self.emit_error(Error::LimitExceeded);
+        }
     }
 
     fn init_start_tag(&mut self) {
+        self.emitter_state.token_limit_exceeded = false;
+        self.emitter_state.attribute_count = 0;
+        self.emitter_state.attribute_names.clear();
         self.emitter_state.current_tag_name.clear();
+        self.emitter_state.current_tag_name_original.clear();
         self.emitter_state.current_tag_type = Some(CurrentTag::Start);
         self.emitter_state.current_tag_self_closing = false;
     }
 
     fn init_end_tag(&mut self) {
+        self.emitter_state.token_limit_exceeded = false;
+        self.emitter_state.attribute_count = 0;
+        self.emitter_state.attribute_names.clear();
         self.emitter_state.current_tag_name.clear();
+        self.emitter_state.current_tag_name_original.clear();
         self.emitter_state.current_tag_type = Some(CurrentTag::End);
         self.emitter_state.current_tag_had_attributes = false;
     }
 
     fn init_comment(&mut self) {
         self.flush_current_characters();
+        self.emitter_state.token_limit_exceeded = false;
         self.emitter_state.current_comment.clear();
+        self.emitter_state.current_comment_kind = CommentKind::Regular;
+    }
+
+    fn set_comment_kind(&mut self, kind: CommentKind) {
+        self.emitter_state.current_comment_kind = kind;
     }
 
     fn emit_current_tag(&mut self) -> Option<State> {
@@ -363,29 +1064,40 @@ where
                 self.callback_state
                     .emit_event(CallbackEvent::CloseStartTag {
                         self_closing: self.emitter_state.current_tag_self_closing,
+                        is_void: is_void_element(&self.emitter_state.last_start_tag),
                     });
+
+                if let Some(switcher) = &mut self.emitter_state.switcher {
+                    return switcher.start_tag(
+                        &self.emitter_state.last_start_tag,
+                        self.emitter_state.current_tag_self_closing,
+                    );
+                } else if self.emitter_state.naively_switch_states {
+                    return naive_next_state(&self.emitter_state.last_start_tag);
+                }
             }
             Some(CurrentTag::End) => {
                 if self.emitter_state.current_tag_had_attributes {
-                    self.emit_error(Error::EndTagWithAttributes);
+                    self.emit_end_tag_error(Error::EndTagWithAttributes);
+                }
+                if let Some(switcher) = &mut self.emitter_state.switcher {
+                    switcher.end_tag(&self.emitter_state.current_tag_name);
                 }
-                self.emitter_state.last_start_tag.clear();
+                // The last start tag is kept, as per spec it is only ever replaced by the next
+                // start tag.
                 self.callback_state.emit_event(CallbackEvent::EndTag {
-                    name: &self.emitter_state.current_tag_name,
+                    name: self.emitter_state.tag_name(),
                 });
             }
             _ => {}
         }
 
-        if self.emitter_state.naively_switch_states {
-            naive_next_state(&self.emitter_state.last_start_tag)
-        } else {
-            None
-        }
+        None
     }
     fn emit_current_comment(&mut self) {
         self.callback_state.emit_event(CallbackEvent::Comment {
             value: &self.emitter_state.current_comment,
+            kind: self.emitter_state.current_comment_kind,
         });
         self.emitter_state.current_comment.clear();
     }
@@ -410,8 +1122,11 @@ where
     fn set_self_closing(&mut self) {
         trace_log!("set_self_closing");
         if matches!(self.emitter_state.current_tag_type, Some(CurrentTag::End)) {
-            self.callback_state
-                .emit_event(CallbackEvent::Error(Error::EndTagWithTrailingSolidus));
+            self.flush_attribute();
+            // the text before the end tag is complete, and comes first as with
+            // `Error::EndTagWithAttributes`
+            self.flush_current_characters();
+            self.emit_end_tag_error(Error::EndTagWithTrailingSolidus);
         } else {
             self.emitter_state.current_tag_self_closing = true;
         }
@@ -422,19 +1137,49 @@ where
     }
 
     fn push_tag_name(&mut self, s: &[u8]) {
-        self.emitter_state.current_tag_name.extend(s);
+        if extend_limited(
+            &mut self.emitter_state.current_tag_name,
+            s,
+            self.emitter_state.limits.max_name_len,
+        ) {
+            self.token_limit_exceeded();
+        }
+    }
+
+    fn push_tag_name_original(&mut self, s: &[u8]) {
+        // limits were already checked in push_tag_name
+        if self.emitter_state.preserve_case {
+            extend_limited(
+                &mut self.emitter_state.current_tag_name_original,
+                s,
+                self.emitter_state.limits.max_name_len,
+            );
+        }
     }
 
     fn push_comment(&mut self, s: &[u8]) {
-        self.emitter_state.current_comment.extend(s);
+        if extend_limited(
+            &mut self.emitter_state.current_comment,
+            s,
+            self.emitter_state.limits.max_comment_len,
+        ) {
+            self.token_limit_exceeded();
+        }
     }
 
     fn push_doctype_name(&mut self, s: &[u8]) {
-        self.emitter_state.doctype_name.extend(s);
+        if extend_limited(
+            &mut self.emitter_state.doctype_name,
+            s,
+            self.emitter_state.limits.max_name_len,
+        ) {
+            self.token_limit_exceeded();
+        }
     }
 
     fn init_doctype(&mut self) {
         self.flush_current_characters();
+        self.emitter_state.token_limit_exceeded = false;
         self.emitter_state.doctype_name.clear();
         self.emitter_state.doctype_has_public_identifier = false;
         self.emitter_state.doctype_has_system_identifier = false;
@@ -447,32 +1192,105 @@ where
         self.flush_open_start_tag();
         self.flush_attribute();
         self.emitter_state.current_tag_had_attributes = true;
+
+        if self.emitter_state.attribute_count < self.emitter_state.limits.max_attributes {
+            self.emitter_state.attribute_count += 1;
+        } else {
+            self.emitter_state.current_attribute_is_dropped = true;
+            self.token_limit_exceeded();
+        }
     }
 
     fn push_attribute_name(&mut self, s: &[u8]) {
-        self.emitter_state.current_attribute_name.extend(s);
+        if self.emitter_state.current_attribute_is_dropped {
+            return;
+        }
+
+        if extend_limited(
+            &mut self.emitter_state.current_attribute_name,
+            s,
+            self.emitter_state.limits.max_name_len,
+        ) {
+            self.token_limit_exceeded();
+        }
+    }
+
+    fn push_attribute_name_original(&mut self, s: &[u8]) {
+        if self.emitter_state.preserve_case && !self.emitter_state.current_attribute_is_dropped {
+            extend_limited(
+                &mut self.emitter_state.current_attribute_name_original,
+                s,
+                self.emitter_state.limits.max_name_len,
+            );
+        }
+    }
+
+    fn init_attribute_value(&mut self) {
+        self.emitter_state.current_attribute_has_value = true;
     }
 
     fn push_attribute_value(&mut self, s: &[u8]) {
         self.flush_attribute_name();
-        self.emitter_state.current_attribute_value.extend(s);
+        if !self.emitter_state.current_attribute_is_duplicate
+            && !self.emitter_state.current_attribute_is_dropped
+            && extend_limited(
+                &mut self.emitter_state.current_attribute_value,
+                s,
+                self.emitter_state.limits.max_value_len,
+            )
+        {
+            self.token_limit_exceeded();
+        }
     }
 
     fn set_doctype_public_identifier(&mut self, value: &[u8]) {
         self.emitter_state.doctype_has_public_identifier = true;
         self.emitter_state.doctype_public_identifier.clear();
-        self.emitter_state.doctype_public_identifier.extend(value);
+        self.push_doctype_public_identifier(value);
     }
     fn set_doctype_system_identifier(&mut self, value: &[u8]) {
         self.emitter_state.doctype_has_system_identifier = true;
         self.emitter_state.doctype_system_identifier.clear();
-        self.emitter_state.doctype_system_identifier.extend(value);
+        self.push_doctype_system_identifier(value);
     }
     fn push_doctype_public_identifier(&mut self, value: &[u8]) {
-        self.emitter_state.doctype_public_identifier.extend(value);
+        if extend_limited(
+            &mut self.emitter_state.doctype_public_identifier,
+            value,
+            self.emitter_state.limits.max_value_len,
+        ) {
+            self.token_limit_exceeded();
+        }
     }
     fn push_doctype_system_identifier(&mut self, value: &[u8]) {
-        self.emitter_state.doctype_system_identifier.extend(value);
+        if extend_limited(
+            &mut self.emitter_state.doctype_system_identifier,
+            value,
+            self.emitter_state.limits.max_value_len,
+        ) {
+            self.token_limit_exceeded();
+        }
+    }
+
+    fn adjusted_current_node_present_but_not_in_html_namespace(&mut self) -> bool {
+        self.emitter_state
+            .switcher
+            .as_ref()
+            .is_some_and(ContentStateSwitcher::in_foreign_content)
+    }
+
+    fn start_cdata(&mut self) {
+        if self.emitter_state.mark_cdata_sections {
+            self.flush_current_characters();
+            self.callback_state.emit_event(CallbackEvent::CdataStart);
+        }
+    }
+
+    fn end_cdata(&mut self) {
+        if self.emitter_state.mark_cdata_sections {
+            self.flush_current_characters();
+            self.callback_state.emit_event(CallbackEvent::CdataEnd);
+        }
     }
 
     fn current_is_appropriate_end_tag_token(&mut self) -> bool {
@@ -501,3 +1319,114 @@ where
         self.emitter_state.last_start_tag == self.emitter_state.current_tag_name
     }
 }
+
+#[test]
+#[cfg(feature = "error-strings")]
+fn test_duplicate_attributes() {
+    let mut events = Vec::new();
+    let emitter = CallbackEmitter::new(|event: CallbackEvent<'_>| -> Option<Infallible> {
+        events.push(match event {
+            CallbackEvent::OpenStartTag { name } => {
+                format!("open {}", String::from_utf8_lossy(name))
+            }
+            CallbackEvent::AttributeName { name } => {
+                format!("name {}", String::from_utf8_lossy(name))
+            }
+            CallbackEvent::AttributeValue { value, .. } => {
+                format!("value {}", String::from_utf8_lossy(value))
+            }
+            CallbackEvent::CloseStartTag { .. } => "close".to_owned(),
+            CallbackEvent::EndTag { name } => format!("end {}", String::from_utf8_lossy(name)),
+            CallbackEvent::Error(error) => format!("error {}", error),
+            event => panic!("unexpected event {:?}", event),
+        });
+        None
+    });
+
+    let Ok(()) = crate::Tokenizer::new_with_emitter("<a x=1 X=2 y x=3></a x x>", emitter).finish();
+    assert_eq!(
+        events,
+        [
+            "open a",
+            "name x",
+            "value 1",
+            "error duplicate-attribute",
+            "name y",
+            "error duplicate-attribute",
+            "close",
+            "name x",
+            "error duplicate-attribute",
+            "error end-tag-with-attributes",
+            "end a",
+        ]
+    );
+}
+
+#[test]
+#[cfg(feature = "error-strings")]
+fn test_pair_attributes() {
+    let mut events = Vec::new();
+    let mut emitter = CallbackEmitter::new(|event: CallbackEvent<'_>| -> Option<Infallible> {
+        events.push(match event {
+            CallbackEvent::OpenStartTag { name } => {
+                format!("open {}", String::from_utf8_lossy(name))
+            }
+            CallbackEvent::Attribute { name, value, .. } => format!(
+                "attribute {} {:?}",
+                String::from_utf8_lossy(name),
+                value.map(String::from_utf8_lossy)
+            ),
+            CallbackEvent::CloseStartTag { .. } => "close".to_owned(),
+            CallbackEvent::EndTag { name } => format!("end {}", String::from_utf8_lossy(name)),
+            CallbackEvent::Error(error) => format!("error {}", error),
+            event => panic!("unexpected event {:?}", event),
+        });
+        None
+    });
+    emitter.pair_attributes(true);
+
+    let Ok(()) = crate::Tokenizer::new_with_emitter(
+        "<input disabled a=\"\" b='&amp;&lt;x' c=d&gt e = f g=><p x=1 X=2 y x=3 /></p x>",
+        emitter,
+    )
+    .finish();
+    assert_eq!(
+        events,
+        [
+            "open input",
+            "attribute disabled None",
+            "attribute a Some(\"\")",
+            "attribute b Some(\"&<x\")",
+            "error missing-semicolon-after-character-reference",
+            "attribute c Some(\"d>\")",
+            "attribute e Some(\"f\")",
+            "error missing-attribute-value",
+            "attribute g Some(\"\")",
+            "close",
+            "open p",
+            "attribute x Some(\"1\")",
+            "error duplicate-attribute",
+            "attribute x Some(\"2\")",
+            "attribute y None",
+            "error duplicate-attribute",
+            "attribute x Some(\"3\")",
+            "close",
+            "attribute x None",
+            "error end-tag-with-attributes",
+            "end p",
+        ]
+    );
+}
+
+#[test]
+fn test_attribute_names() {
+    let mut names = AttributeNames::default();
+    for round in 0..2 {
+        for i in 0..100 {
+            assert!(names.insert(format!("attr{}", i).as_bytes()), "{}", round);
+            assert!(!names.insert(b"attr0"));
+            assert!(!names.insert(format!("attr{}", i / 2).as_bytes()));
+        }
+        names.clear();
+    }
+}
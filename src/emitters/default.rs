@@ -1,95 +1,434 @@
 //! The default emitter is what powers the simple SAX-like API that you see in the README.
-use std::collections::btree_map::Entry;
-use std::collections::BTreeMap;
 use std::mem::take;
 
-use crate::{Emitter, Error, HtmlString, State};
+use crate::{
+    is_void_element, AttributeMap, AttributeValueSyntax, CommentKind, ContentStateSwitcher,
+    Emitter, Error, ErrorSummary, HtmlString, Span, State,
+};
 
-use crate::emitters::callback::{Callback, CallbackEmitter, CallbackEvent};
+use crate::emitters::callback::{Callback, CallbackEmitter, CallbackEvent, ErrorContext, Limits};
 
 #[derive(Debug, Default)]
 struct OurCallback {
     tag_name: Vec<u8>,
-    attribute_name: HtmlString,
-    attribute_map: BTreeMap<HtmlString, HtmlString>,
+    // Duplicate attributes are already dropped by the CallbackEmitter, so attributes can be
+    // collected in a Vec and only indexed into the public AttributeMap once the tag is closed.
+    attributes: Vec<(HtmlString, HtmlString)>,
+    suppress_errors: bool,
+    mark_void_elements: bool,
+    error_summary: Option<ErrorSummary>,
+    error_details: Option<Vec<ErrorDetails>>,
+    // set by `set_error_context` right before the error it belongs to
+    error_context: Option<(HtmlString, Vec<HtmlString>)>,
+    // positions as reported by the tokenizer, see Emitter::set_token_start
+    token_start: usize,
+    position: usize,
+    previous_token_end: Option<usize>,
+    eof: bool,
 }
 
-impl Callback<Token> for OurCallback {
-    fn handle_event(&mut self, event: CallbackEvent<'_>) -> Option<Token> {
+impl OurCallback {
+    fn token_span(&mut self) -> Span {
+        let span = Span {
+            start: self.token_start,
+            end: self.position,
+        };
+        self.previous_token_end = Some(self.position);
+        span
+    }
+
+    /// Strings are emitted when the next token starts, and contain everything in between.
+    fn string_span(&self) -> Span {
+        Span {
+            start: self.previous_token_end.unwrap_or_default(),
+            end: if self.eof {
+                self.position
+            } else {
+                self.token_start
+            },
+        }
+    }
+}
+
+impl<T: DefaultEmitterToken> Callback<T> for OurCallback {
+    fn handle_event(&mut self, event: CallbackEvent<'_>) -> Option<T> {
         crate::utils::trace_log!("event: {:?}", event);
-        match event {
+        let (token, span) = match event {
             CallbackEvent::OpenStartTag { name } => {
                 self.tag_name.clear();
                 self.tag_name.extend(name);
-                None
+                return None;
             }
             CallbackEvent::AttributeName { name } => {
-                self.attribute_name.clear();
-                match self.attribute_map.entry(name.to_owned().into()) {
-                    Entry::Occupied(_) => Some(Token::Error(Error::DuplicateAttribute)),
-                    Entry::Vacant(vacant) => {
-                        self.attribute_name.extend(name);
-                        vacant.insert(Default::default());
-                        None
-                    }
-                }
+                self.attributes
+                    .push((name.to_owned().into(), HtmlString::default()));
+                return None;
             }
-            CallbackEvent::AttributeValue { value } => {
-                if !self.attribute_name.is_empty() {
-                    self.attribute_map
-                        .get_mut(&self.attribute_name)
-                        .unwrap()
-                        .extend(value);
+            CallbackEvent::AttributeValue { value, .. } => {
+                if let Some((_, attribute_value)) = self.attributes.last_mut() {
+                    attribute_value.extend(value);
                 }
-                None
+                return None;
             }
-            CallbackEvent::CloseStartTag { self_closing } => Some(Token::StartTag(StartTag {
+            // only emitted with `CallbackEmitter::pair_attributes` and
+            // `CallbackEmitter::mark_cdata_sections`, which are off
+            CallbackEvent::Attribute { .. }
+            | CallbackEvent::CdataStart
+            | CallbackEvent::CdataEnd => return None,
+            CallbackEvent::CloseStartTag {
                 self_closing,
-                name: take(&mut self.tag_name).into(),
-                attributes: take(&mut self.attribute_map),
-            })),
+                is_void,
+            } => (
+                Token::StartTag(StartTag {
+                    self_closing: self_closing || (is_void && self.mark_void_elements),
+                    name: take(&mut self.tag_name).into(),
+                    attributes: self.attributes.drain(..).collect(),
+                }),
+                self.token_span(),
+            ),
             CallbackEvent::EndTag { name } => {
-                self.attribute_map.clear();
-                Some(Token::EndTag(EndTag {
-                    name: name.to_owned().into(),
-                }))
+                self.attributes.clear();
+                (
+                    Token::EndTag(EndTag {
+                        name: name.to_owned().into(),
+                    }),
+                    self.token_span(),
+                )
             }
-            CallbackEvent::String { value } => Some(Token::String(value.to_owned().into())),
-            CallbackEvent::Comment { value } => Some(Token::Comment(value.to_owned().into())),
+            CallbackEvent::String { value } => {
+                (Token::String(value.to_owned().into()), self.string_span())
+            }
+            CallbackEvent::Comment { value, kind } => (
+                Token::Comment(Comment {
+                    value: value.to_owned().into(),
+                    kind,
+                }),
+                self.token_span(),
+            ),
             CallbackEvent::Doctype {
                 name,
                 public_identifier,
                 system_identifier,
                 force_quirks,
-            } => Some(Token::Doctype(Doctype {
-                force_quirks,
-                name: name.to_owned().into(),
-                public_identifier: public_identifier.map(|x| x.to_owned().into()),
-                system_identifier: system_identifier.map(|x| x.to_owned().into()),
-            })),
-            CallbackEvent::Error(error) => Some(Token::Error(error)),
+            } => (
+                Token::Doctype(Doctype {
+                    force_quirks,
+                    name: name.to_owned().into(),
+                    public_identifier: public_identifier.map(|x| x.to_owned().into()),
+                    system_identifier: system_identifier.map(|x| x.to_owned().into()),
+                }),
+                self.token_span(),
+            ),
+            CallbackEvent::Error(error) => {
+                if let Some(ref mut error_summary) = self.error_summary {
+                    error_summary.record(error);
+                }
+                if let (Some(error_details), Some((tag_name, attribute_names))) =
+                    (&mut self.error_details, self.error_context.take())
+                {
+                    error_details.push(ErrorDetails {
+                        error,
+                        tag_name,
+                        attribute_names,
+                    });
+                }
+
+                if self.suppress_errors {
+                    return None;
+                }
+
+                (
+                    Token::Error(error),
+                    Span {
+                        start: self.position,
+                        end: self.position,
+                    },
+                )
+            }
+        };
+
+        Some(T::from_token(token, span))
+    }
+
+    fn set_token_start(&mut self, offset: usize) {
+        self.token_start = offset;
+    }
+
+    fn set_error_context(&mut self, context: ErrorContext<'_>) {
+        if self.error_details.is_some() {
+            self.error_context = Some((
+                context.tag_name.to_owned().into(),
+                context
+                    .attribute_names()
+                    .map(|x| x.to_owned().into())
+                    .collect(),
+            ));
+        }
+    }
+
+    #[inline]
+    fn set_position(&mut self, offset: usize) {
+        self.position = offset;
+        // the first call is the offset at which the tokenizer starts
+        self.previous_token_end.get_or_insert(offset);
+    }
+
+    fn reset(&mut self) {
+        self.tag_name.clear();
+        self.attributes.clear();
+        if let Some(ref mut error_summary) = self.error_summary {
+            *error_summary = ErrorSummary::default();
         }
+        if let Some(ref mut error_details) = self.error_details {
+            error_details.clear();
+        }
+        self.error_context = None;
+        self.token_start = 0;
+        self.position = 0;
+        self.previous_token_end = None;
+        self.eof = false;
+    }
+}
+
+/// The token types that [`DefaultEmitter`] can produce: [`Token`] by default, or `(Token, Span)`
+/// when created with [`DefaultEmitter::with_spans`].
+pub trait DefaultEmitterToken {
+    /// Build the token from a [`Token`] and its location in the input.
+    fn from_token(token: Token, span: Span) -> Self;
+}
+
+impl DefaultEmitterToken for Token {
+    #[inline]
+    fn from_token(token: Token, _span: Span) -> Self {
+        token
+    }
+}
+
+impl DefaultEmitterToken for (Token, Span) {
+    #[inline]
+    fn from_token(token: Token, span: Span) -> Self {
+        (token, span)
     }
 }
 
 /// This is the emitter you implicitly use with [crate::Tokenizer::new]. Refer to the [crate
 /// docs](crate) for how usage looks like.
-#[derive(Default, Debug)]
-pub struct DefaultEmitter {
-    inner: CallbackEmitter<OurCallback, Token>,
+#[derive(Debug)]
+pub struct DefaultEmitter<T = Token> {
+    inner: CallbackEmitter<OurCallback, T>,
 }
 
-impl DefaultEmitter {
+impl Default for DefaultEmitter {
+    fn default() -> Self {
+        DefaultEmitter {
+            inner: CallbackEmitter::default(),
+        }
+    }
+}
+
+impl DefaultEmitter<(Token, Span)> {
+    /// Create an emitter that yields each token together with its location in the input.
+    ///
+    /// * Tags, comments and doctypes span from their `<` to right after their `>`. Comments and
+    ///   doctypes that are not closed span to the end of the input.
+    /// * Strings span everything between the surrounding tags, comments or doctypes, including
+    ///   any markup that did not produce a token (such as `</>`).
+    /// * Errors are empty spans at the position where they were detected, which can be somewhat
+    ///   after the character that caused them.
+    ///
+    /// Offsets are byte offsets into the input.
+    ///
+    /// ```rust
+    /// use html5gum::{DefaultEmitter, Span, Token, Tokenizer};
+    ///
+    /// let input = "<p class=a>hello</p>";
+    /// let tokenizer = Tokenizer::new_with_emitter(input, DefaultEmitter::with_spans());
+    /// let spans: Vec<_> = tokenizer.flatten().map(|(_, span)| &input[span.start..span.end]).collect();
+    /// assert_eq!(spans, ["<p class=a>", "hello", "</p>"]);
+    /// ```
+    #[must_use]
+    pub fn with_spans() -> Self {
+        DefaultEmitter {
+            inner: CallbackEmitter::default(),
+        }
+    }
+}
+
+impl<T: DefaultEmitterToken> DefaultEmitter<T> {
+    /// Not public, because some token types, such as [`crate::emitters::utf8::Token`], can only be
+    /// produced from some kinds of input.
+    pub(crate) fn with_token_type() -> Self {
+        DefaultEmitter {
+            inner: CallbackEmitter::default(),
+        }
+    }
+
     /// Whether to use [crate::naive_next_state] to switch states automatically.
     ///
     /// The default is off.
     pub fn naively_switch_states(&mut self, yes: bool) {
         self.inner.naively_switch_states(yes)
     }
+
+    /// Skip the content of elements such as `<script>` and `<style>`, see
+    /// [`CallbackEmitter::skip_rawtext`].
+    ///
+    /// The default is off.
+    pub fn skip_rawtext(&mut self, yes: bool) {
+        self.inner.skip_rawtext(yes)
+    }
+
+    /// Use a [`ContentStateSwitcher`] to switch states automatically, see
+    /// [`CallbackEmitter::switch_states`].
+    ///
+    /// The default is off.
+    pub fn switch_states(&mut self, switcher: Option<ContentStateSwitcher>) {
+        self.inner.switch_states(switcher)
+    }
+
+    /// Limit the sizes of tokens, see [`Limits`].
+    ///
+    /// The default is no limits.
+    pub fn limits(&mut self, limits: Limits) {
+        self.inner.limits(limits)
+    }
+
+    /// Whether to leave out [`Token::Error`] from the tokens. This does not affect
+    /// [`DefaultEmitter::error_summary`].
+    ///
+    /// The default is off.
+    pub fn suppress_errors(&mut self, yes: bool) {
+        self.inner.callback_mut().suppress_errors = yes;
+    }
+
+    /// Whether to set [`StartTag::self_closing`] on void elements such as `<br>` too, as they
+    /// never have an end tag. See [`crate::is_void_element`].
+    ///
+    /// With this on, `self_closing` no longer tells whether the tag was written with `/>`. Use
+    /// [`StartTag::is_void`] to find void elements without changing the flag.
+    ///
+    /// ```rust
+    /// use html5gum::{DefaultEmitter, Token, Tokenizer};
+    ///
+    /// let mut emitter = DefaultEmitter::default();
+    /// emitter.mark_void_elements(true);
+    /// let self_closing: Vec<_> = Tokenizer::new_with_emitter("<br><p><div/>", emitter)
+    ///     .flatten()
+    ///     .map(|token| match token {
+    ///         Token::StartTag(tag) => tag.self_closing,
+    ///         _ => panic!(),
+    ///     })
+    ///     .collect();
+    /// assert_eq!(self_closing, [true, false, true]);
+    /// ```
+    ///
+    /// The default is off.
+    pub fn mark_void_elements(&mut self, yes: bool) {
+        self.inner.callback_mut().mark_void_elements = yes;
+    }
+
+    /// Whether to count errors by error code, see [`DefaultEmitter::error_summary`].
+    ///
+    /// The default is off.
+    pub fn collect_error_summary(&mut self, yes: bool) {
+        let callback = self.inner.callback_mut();
+        if !yes {
+            callback.error_summary = None;
+        } else if callback.error_summary.is_none() {
+            callback.error_summary = Some(ErrorSummary::default());
+        }
+    }
+
+    /// A summary of all errors emitted so far, if enabled using
+    /// [`DefaultEmitter::collect_error_summary`]. It starts over when the emitter is reset, see
+    /// [`crate::Tokenizer::reset_with_reader`].
+    ///
+    /// Use [`crate::Tokenizer::emitter`] to get the summary after tokenizing:
+    ///
+    /// ```rust
+    /// use html5gum::{DefaultEmitter, Error, Tokenizer};
+    ///
+    /// let mut emitter = DefaultEmitter::default();
+    /// emitter.collect_error_summary(true);
+    /// emitter.suppress_errors(true);
+    ///
+    /// let mut tokenizer = Tokenizer::new_with_emitter("<a b b>\0</a c>", emitter);
+    /// assert_eq!(tokenizer.by_ref().count(), 3);
+    ///
+    /// let summary = tokenizer.emitter().error_summary().unwrap();
+    /// assert_eq!(summary.total(), 3);
+    /// assert_eq!(summary.count(Error::DuplicateAttribute), 1);
+    /// assert_eq!(summary.first_error(), Some(Error::DuplicateAttribute));
+    /// #[cfg(feature = "error-strings")]
+    /// assert_eq!(
+    ///     summary.to_string(),
+    ///     "3 errors\n  1 duplicate-attribute\n  1 unexpected-null-character\n  1 end-tag-with-attributes"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn error_summary(&self) -> Option<&ErrorSummary> {
+        self.inner.callback().error_summary.as_ref()
+    }
+
+    /// Whether to record the end tag behind every [`Error::EndTagWithAttributes`] and
+    /// [`Error::EndTagWithTrailingSolidus`], see [`DefaultEmitter::take_error_details`].
+    ///
+    /// The default is off.
+    pub fn collect_error_details(&mut self, yes: bool) {
+        let callback = self.inner.callback_mut();
+        if !yes {
+            callback.error_details = None;
+        } else if callback.error_details.is_none() {
+            callback.error_details = Some(Vec::new());
+        }
+    }
+
+    /// Return the details of the errors recorded since the last call, if enabled using
+    /// [`DefaultEmitter::collect_error_details`]. The errors themselves are emitted as usual,
+    /// unless [`DefaultEmitter::suppress_errors`] is on.
+    ///
+    /// ```rust
+    /// use html5gum::{DefaultEmitter, Error, ErrorDetails, Tokenizer};
+    ///
+    /// let mut emitter = DefaultEmitter::default();
+    /// emitter.collect_error_details(true);
+    ///
+    /// let mut tokenizer = Tokenizer::new_with_emitter("<p>a</p class=x>", emitter);
+    /// tokenizer.by_ref().for_each(drop);
+    ///
+    /// assert_eq!(
+    ///     tokenizer.emitter_mut().take_error_details(),
+    ///     [ErrorDetails {
+    ///         error: Error::EndTagWithAttributes,
+    ///         tag_name: b"p".to_vec().into(),
+    ///         attribute_names: vec![b"class".to_vec().into()],
+    ///     }]
+    /// );
+    /// ```
+    pub fn take_error_details(&mut self) -> Vec<ErrorDetails> {
+        self.inner
+            .callback_mut()
+            .error_details
+            .as_mut()
+            .map(take)
+            .unwrap_or_default()
+    }
+}
+
+/// The end tag behind an error, see [`DefaultEmitter::take_error_details`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorDetails {
+    /// The error, which is [`Error::EndTagWithAttributes`] or
+    /// [`Error::EndTagWithTrailingSolidus`].
+    pub error: Error,
+    /// The name of the end tag.
+    pub tag_name: HtmlString,
+    /// The names of the end tag's attributes, see [`ErrorContext::attribute_names`].
+    pub attribute_names: Vec<HtmlString>,
 }
 
-impl Emitter for DefaultEmitter {
-    type Token = Token;
+impl<T: DefaultEmitterToken> Emitter for DefaultEmitter<T> {
+    type Token = T;
 
     // opaque type around inner emitter
 
@@ -97,16 +436,37 @@ impl Emitter for DefaultEmitter {
         self.inner.set_last_start_tag(last_start_tag)
     }
 
+    fn last_start_tag(&self) -> Option<&[u8]> {
+        self.inner.last_start_tag()
+    }
+
+    fn begin(&mut self) {
+        self.inner.begin();
+    }
+
     fn emit_eof(&mut self) {
+        self.inner.callback_mut().eof = true;
         self.inner.emit_eof()
     }
 
+    fn finish(&mut self) {
+        self.inner.finish();
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+    }
+
     fn emit_error(&mut self, error: Error) {
         self.inner.emit_error(error)
     }
 
     fn should_emit_errors(&mut self) -> bool {
-        self.inner.should_emit_errors()
+        let callback = self.inner.callback_mut();
+        (!callback.suppress_errors
+            || callback.error_summary.is_some()
+            || callback.error_details.is_some())
+            && self.inner.should_emit_errors()
     }
 
     fn pop_token(&mut self) -> Option<Self::Token> {
@@ -128,10 +488,18 @@ impl Emitter for DefaultEmitter {
         self.inner.init_comment()
     }
 
+    fn set_comment_kind(&mut self, kind: CommentKind) {
+        self.inner.set_comment_kind(kind)
+    }
+
     fn emit_current_tag(&mut self) -> Option<State> {
         self.inner.emit_current_tag()
     }
 
+    fn should_skip_rawtext(&mut self, tag_name: &[u8]) -> bool {
+        self.inner.should_skip_rawtext(tag_name)
+    }
+
     fn emit_current_comment(&mut self) {
         self.inner.emit_current_comment()
     }
@@ -152,6 +520,10 @@ impl Emitter for DefaultEmitter {
         self.inner.push_tag_name(s)
     }
 
+    fn push_tag_name_original(&mut self, s: &[u8]) {
+        self.inner.push_tag_name_original(s)
+    }
+
     fn push_comment(&mut self, s: &[u8]) {
         self.inner.push_comment(s)
     }
@@ -172,6 +544,14 @@ impl Emitter for DefaultEmitter {
         self.inner.push_attribute_name(s)
     }
 
+    fn push_attribute_name_original(&mut self, s: &[u8]) {
+        self.inner.push_attribute_name_original(s)
+    }
+
+    fn init_attribute_value(&mut self) {
+        self.inner.init_attribute_value()
+    }
+
     fn push_attribute_value(&mut self, s: &[u8]) {
         self.inner.push_attribute_value(s)
     }
@@ -200,6 +580,37 @@ impl Emitter for DefaultEmitter {
         self.inner
             .adjusted_current_node_present_but_not_in_html_namespace()
     }
+
+    fn set_token_start(&mut self, offset: usize) {
+        self.inner.set_token_start(offset)
+    }
+
+    fn set_attribute_value_start(&mut self, offset: usize) {
+        self.inner.set_attribute_value_start(offset)
+    }
+
+    fn set_attribute_value_end(&mut self, offset: usize) {
+        self.inner.set_attribute_value_end(offset)
+    }
+
+    fn set_attribute_value_syntax(&mut self, syntax: AttributeValueSyntax) {
+        self.inner.set_attribute_value_syntax(syntax)
+    }
+
+    #[inline]
+    fn set_position(&mut self, offset: usize) {
+        self.inner.set_position(offset)
+    }
+
+    #[inline]
+    fn set_line(&mut self, line: usize) {
+        self.inner.set_line(line)
+    }
+
+    #[inline]
+    fn is_aborted(&self) -> bool {
+        self.inner.is_aborted()
+    }
 }
 
 /// A HTML end/close tag, such as `<p>` or `<a>`.
@@ -207,16 +618,28 @@ impl Emitter for DefaultEmitter {
 pub struct StartTag {
     /// Whether this tag is self-closing. If it is self-closing, no following [EndTag] should be
     /// expected.
+    ///
+    /// This is whether the tag ended with `/>`, or, with [`DefaultEmitter::mark_void_elements`],
+    /// whether it is a void element.
     pub self_closing: bool,
 
     /// The start tag's name, such as `"p"` or `"a"`.
     pub name: HtmlString,
 
-    /// A mapping for any HTML attributes this start tag may have.
+    /// A mapping for any HTML attributes this start tag may have, in source order.
     ///
     /// Duplicate attributes are ignored after the first one as per WHATWG spec. Implement your own
     /// [crate::Emitter] to tweak this behavior.
-    pub attributes: BTreeMap<HtmlString, HtmlString>,
+    pub attributes: AttributeMap,
+}
+
+impl StartTag {
+    /// Whether this is a void element such as `<br>`, which never has an end tag, regardless of
+    /// whether it was written with `/>`. See [`crate::is_void_element`].
+    #[must_use]
+    pub fn is_void(&self) -> bool {
+        is_void_element(&self.name)
+    }
 }
 
 /// A HTML end/close tag, such as `</p>` or `</a>`.
@@ -226,12 +649,47 @@ pub struct EndTag {
     pub name: HtmlString,
 }
 
+/// A HTML comment, such as `<!-- comment -->`.
+///
+/// Anything that the tokenizer coerces into a comment, such as `<?xml version="1.0"?>`, is a
+/// comment too. Use `kind` to tell them apart.
+#[derive(Debug, Default, Eq, PartialEq, Clone)]
+pub struct Comment {
+    /// The comment's contents, such as `" comment "`.
+    pub value: HtmlString,
+
+    /// Whether this is a proper comment.
+    pub kind: CommentKind,
+}
+
+impl From<HtmlString> for Comment {
+    /// Create a [`CommentKind::Regular`] comment.
+    fn from(value: HtmlString) -> Self {
+        Comment {
+            value,
+            kind: CommentKind::Regular,
+        }
+    }
+}
+
+impl From<Vec<u8>> for Comment {
+    /// Create a [`CommentKind::Regular`] comment.
+    fn from(value: Vec<u8>) -> Self {
+        HtmlString::from(value).into()
+    }
+}
+
 /// A doctype. Some examples:
 ///
 /// * `<!DOCTYPE {name}>`
 /// * `<!DOCTYPE {name} PUBLIC '{public_identifier}'>`
 /// * `<!DOCTYPE {name} SYSTEM '{system_identifier}'>`
 /// * `<!DOCTYPE {name} PUBLIC '{public_identifier}' '{system_identifier}'>`
+///
+/// An identifier is `None` if it is missing, and `Some` as soon as its opening quote appears,
+/// even if it is empty or the doctype ends before its closing quote. The distinction matters for
+/// [quirks mode](crate::quirks): `<!DOCTYPE html PUBLIC "">` has an empty public identifier,
+/// `<!DOCTYPE html>` and `<!DOCTYPE html PUBLIC>` have none.
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct Doctype {
     /// The ["force quirks"](https://html.spec.whatwg.org/#force-quirks-flag) flag.
@@ -240,10 +698,10 @@ pub struct Doctype {
     /// The doctype's name. For HTML documents this is "html".
     pub name: HtmlString,
 
-    /// The doctype's public identifier.
+    /// The doctype's public identifier, or `None` if it is missing.
     pub public_identifier: Option<HtmlString>,
 
-    /// The doctype's system identifier.
+    /// The doctype's system identifier, or `None` if it is missing.
     pub system_identifier: Option<HtmlString>,
 }
 
@@ -258,7 +716,7 @@ pub enum Token {
     /// A literal string.
     String(HtmlString),
     /// A HTML comment.
-    Comment(HtmlString),
+    Comment(Comment),
     /// A HTML doctype declaration.
     Doctype(Doctype),
     /// A HTML parsing error.
@@ -267,3 +725,20 @@ pub enum Token {
     /// more tokens afterward.
     Error(Error),
 }
+
+#[test]
+fn test_duplicate_attribute_order() {
+    let tokens: Vec<_> = crate::Tokenizer::new("<a x=1 x=2 x=3>").flatten().collect();
+    assert_eq!(
+        tokens,
+        [
+            Token::Error(Error::DuplicateAttribute),
+            Token::Error(Error::DuplicateAttribute),
+            Token::StartTag(StartTag {
+                self_closing: false,
+                name: b"a".to_vec().into(),
+                attributes: AttributeMap::from([(b"x".to_vec().into(), b"1".to_vec().into())]),
+            }),
+        ]
+    );
+}
@@ -1,3 +1,6 @@
+#[cfg(feature = "trace")]
+use crate::emitters::trace::TraceEvent;
+use crate::utils::breaks_out_of_foreign_content;
 use crate::{Error, State};
 
 /// An emitter is an object providing methods to the tokenizer to produce tokens.
@@ -33,10 +36,54 @@ pub trait Emitter {
     /// the current tag.
     fn set_last_start_tag(&mut self, last_start_tag: Option<&[u8]>);
 
+    /// Return the name of the _last start tag_, as set by [`Emitter::set_last_start_tag`] or by
+    /// emitting a start tag.
+    ///
+    /// This is used by [`crate::Tokenizer::context_at_last_token`], and to keep the memory used by
+    /// the tokenizer bounded in the RCDATA, RAWTEXT and script data states: end tag names that are
+    /// longer than the last start tag are emitted as text right away. The default implementation
+    /// returns `None`, which is only correct for emitters that never switch the tokenizer into
+    /// those states.
+    fn last_start_tag(&self) -> Option<&[u8]> {
+        None
+    }
+
+    /// Tokenization is about to start. The tokenizer calls this exactly once, before the first
+    /// state is run and before any other method except for configuration such as
+    /// [`Emitter::set_last_start_tag`] and [`Emitter::set_position`].
+    ///
+    /// The default implementation does nothing.
+    #[inline]
+    fn begin(&mut self) {}
+
     /// The state machine has reached the end of the file. It will soon call `pop_token` for the
     /// last time.
     fn emit_eof(&mut self);
 
+    /// Tokenization is over. The tokenizer calls this exactly once, after
+    /// [`Emitter::emit_eof`] and after `pop_token` has returned `None` for the last time. No other
+    /// method is called afterwards.
+    ///
+    /// This is not called if the tokenizer is dropped before it reaches the end of the input, for
+    /// example because of a reader error or [`crate::Tokenizer::take_tokens`].
+    ///
+    /// The default implementation does nothing.
+    #[inline]
+    fn finish(&mut self) {}
+
+    /// Forget everything about the current document, so that the emitter can be used for another
+    /// one. The tokenizer calls this in [`crate::Tokenizer::reset_with_reader`], which may happen
+    /// at any point, also in the middle of a token.
+    ///
+    /// Configuration should be kept, and buffers should keep their capacity. The _last start tag_
+    /// has to be cleared.
+    ///
+    /// The default implementation only clears the _last start tag_, which is enough for emitters
+    /// that keep no other state between documents.
+    fn reset(&mut self) {
+        self.set_last_start_tag(None);
+    }
+
     /// A (probably recoverable) parsing error has occured.
     fn emit_error(&mut self, error: Error);
 
@@ -62,6 +109,10 @@ pub trait Emitter {
     fn pop_token(&mut self) -> Option<Self::Token>;
 
     /// Emit a bunch of plain characters as character tokens.
+    ///
+    /// The tokenizer may call this many times in a row, for example at the boundaries of the
+    /// reader's internal buffer. Each call contains only whole characters, so if the input is
+    /// valid UTF-8, so is `c`.
     fn emit_string(&mut self, c: &[u8]);
 
     /// Set the _current token_ to a start tag.
@@ -73,17 +124,29 @@ pub trait Emitter {
     /// Set the _current token_ to a comment.
     fn init_comment(&mut self);
 
+    /// The current comment is not a regular `<!-- comment -->`, but something the tokenizer
+    /// coerced into a comment. This is called right after [`Emitter::init_comment`], which should
+    /// reset the kind to [`CommentKind::Regular`].
+    ///
+    /// The default implementation does nothing.
+    fn set_comment_kind(&mut self, kind: CommentKind) {
+        let _ = kind;
+    }
+
     /// Emit the _current token_, assuming it is a tag.
     ///
     /// Also get the current attribute and append it to the to-be-emitted tag. See docstring for
     /// [`Emitter::init_attribute`] for how duplicates should be handled.
     ///
-    /// If a start tag is emitted, update the _last start tag_.
+    /// If a start tag is emitted, update the _last start tag_. End tags leave it unchanged.
     ///
     /// If the current token is not a start/end tag, this method may panic.
     ///
     /// The return value is used to switch the tokenizer to a new state. Used in tree building.
     ///
+    /// If this method returns a state, the tokenizer continues in that state, no matter which state
+    /// it would have switched to otherwise. This applies to start and end tags alike.
+    ///
     /// If this method always returns `None`, states are never switched, which leads to artifacts
     /// like contents of `<script>` tags being incorrectly interpreted as HTML.
     ///
@@ -92,14 +155,39 @@ pub trait Emitter {
     /// construction](https://html.spec.whatwg.org/#tree-construction), which this crate does not
     /// offer.
     ///
-    /// You can approximate correct behavior using [`naive_next_state`], but the caveats of doing
-    /// so are not well-understood.
+    /// You can approximate correct behavior using [`naive_next_state`] or
+    /// [`ContentStateSwitcher`], but the caveats of doing so are not well-understood.
     ///
     /// See the `tokenize_with_state_switches` cargo example for a practical example where this
     /// matters.
     #[must_use]
     fn emit_current_tag(&mut self) -> Option<State>;
 
+    /// Whether to skip the content of the tag that was just emitted, instead of tokenizing it.
+    ///
+    /// This is called after [`Emitter::emit_current_tag`] switched the tokenizer to
+    /// [`State::RcData`], [`State::RawText`], [`State::ScriptData`] or [`State::PlainText`], with
+    /// the _last start tag_ as `tag_name` (or an empty name if [`Emitter::last_start_tag`] is not
+    /// implemented). If it returns `true`, the tokenizer searches for the next `<` and discards
+    /// everything in between, without validating it or passing it to [`Emitter::emit_string`].
+    /// This makes skipping large `<script>` or `<style>` elements much faster for emitters that
+    /// would ignore their content anyway.
+    ///
+    /// The end tag is still found as usual: only an _appropriate end tag_ ends the content, as
+    /// decided by [`Emitter::current_is_appropriate_end_tag_token`], and it is emitted like any
+    /// other end tag. Something like `</div>` in a `<script>` is skipped like the rest of the
+    /// content. The exception is `<!` in script data, which is passed to
+    /// [`Emitter::emit_string`]: if it starts `<!--`, the rest of the script is tokenized as usual,
+    /// since the escaping rules decide where it ends. The content of `<plaintext>` has no end, so
+    /// the rest of the input is skipped.
+    ///
+    /// The default implementation skips nothing.
+    #[inline]
+    fn should_skip_rawtext(&mut self, tag_name: &[u8]) -> bool {
+        let _ = tag_name;
+        false
+    }
+
     /// Emit the _current token_, assuming it is a comment.
     ///
     /// If the current token is not a comment, this method may panic.
@@ -128,6 +216,17 @@ pub trait Emitter {
     /// If the current token is not a start or end tag, this method may panic.
     fn push_tag_name(&mut self, s: &[u8]);
 
+    /// Called after every [`Emitter::push_tag_name`] with the same part of the name as it appears
+    /// in the input, before ASCII letters were lowercased. This is for emitters that want to
+    /// preserve the original case, for example to reproduce the input. Everything else, such as
+    /// [`Emitter::current_is_appropriate_end_tag_token`], should use the lowercased name.
+    ///
+    /// The default implementation does nothing.
+    #[inline]
+    fn push_tag_name_original(&mut self, s: &[u8]) {
+        let _ = s;
+    }
+
     /// Assuming the _current token_ is a comment, append a string to the comment's contents.
     ///
     /// If the current token is not a comment, this method may panic.
@@ -163,6 +262,32 @@ pub trait Emitter {
     /// If there is no current attribute, this method may panic.
     fn push_attribute_name(&mut self, s: &[u8]);
 
+    /// Called after every [`Emitter::push_attribute_name`] with the same part of the name as it
+    /// appears in the input, before ASCII letters were lowercased. See
+    /// [`Emitter::push_tag_name_original`].
+    ///
+    /// The default implementation does nothing.
+    #[inline]
+    fn push_attribute_name_original(&mut self, s: &[u8]) {
+        let _ = s;
+    }
+
+    /// The current attribute has a value, because its name is followed by `=`. This is called
+    /// before any [`Emitter::push_attribute_value`], and distinguishes `<input disabled>` from
+    /// `<input disabled="">`. The default implementation does nothing.
+    ///
+    /// If there is no current attribute, this method may panic.
+    fn init_attribute_value(&mut self) {}
+
+    /// How the value of the current attribute is written in the input. This is called right after
+    /// [`Emitter::set_attribute_value_start`]. Attributes for which it is not called have no value,
+    /// so [`Emitter::init_attribute`] should reset the syntax to [`AttributeValueSyntax::Empty`].
+    ///
+    /// The default implementation does nothing.
+    fn set_attribute_value_syntax(&mut self, syntax: AttributeValueSyntax) {
+        let _ = syntax;
+    }
+
     /// Append a string to the current attribute's value.
     ///
     /// If there is no current attribute, this method may panic.
@@ -170,11 +295,17 @@ pub trait Emitter {
 
     /// Assuming the _current token_ is a doctype, set its "public identifier" to the given string.
     ///
+    /// The tokenizer calls this with an empty string at the opening quote of the identifier. Until
+    /// then, the identifier is missing, which is different from being empty.
+    ///
     /// If the current token is not a doctype, this method may panic.
     fn set_doctype_public_identifier(&mut self, value: &[u8]);
 
     /// Assuming the _current token_ is a doctype, set its "system identifier" to the given string.
     ///
+    /// Like with [`Emitter::set_doctype_public_identifier`], the identifier is missing until this
+    /// is called.
+    ///
     /// If the current token is not a doctype, this method may panic.
     fn set_doctype_system_identifier(&mut self, value: &[u8]);
 
@@ -206,6 +337,125 @@ pub trait Emitter {
     fn adjusted_current_node_present_but_not_in_html_namespace(&mut self) -> bool {
         false
     }
+
+    /// A CDATA section such as `<![CDATA[x]]>` starts, which only happens in foreign content (see
+    /// [`Emitter::adjusted_current_node_present_but_not_in_html_namespace`]). Its content is
+    /// passed to [`Emitter::emit_string`] like any other text, followed by
+    /// [`Emitter::end_cdata`].
+    ///
+    /// The default implementation does nothing.
+    fn start_cdata(&mut self) {}
+
+    /// The current CDATA section ends, at its `]]>` or at the end of the input. See
+    /// [`Emitter::start_cdata`].
+    ///
+    /// The default implementation does nothing.
+    fn end_cdata(&mut self) {}
+
+    /// The tokenizer has consumed a `<` at the given byte offset into the input, which may be the
+    /// start of a tag, comment or doctype. If it is, the corresponding `init_*` method is called
+    /// before `set_token_start` is called again.
+    ///
+    /// Together with [`Emitter::set_position`], this can be used to compute the location of
+    /// tokens in the input. The default implementation does nothing.
+    fn set_token_start(&mut self, offset: usize) {
+        let _ = offset;
+    }
+
+    /// The value of the current attribute starts at the given byte offset into the input. For a
+    /// quoted value this is right after the opening quote, otherwise it is the offset of the
+    /// value's first character. This is called after [`Emitter::init_attribute_value`], before
+    /// any [`Emitter::push_attribute_value`].
+    ///
+    /// The default implementation does nothing.
+    fn set_attribute_value_start(&mut self, offset: usize) {
+        let _ = offset;
+    }
+
+    /// The value of the current attribute ends right before the given byte offset into the input,
+    /// which is the offset of the closing quote, or of the whitespace or `>` after an unquoted
+    /// value. Together with [`Emitter::set_attribute_value_start`], this is the range of the
+    /// input that the value was decoded from, including all of its character references. If the
+    /// value is missing, as in `<a b=>`, both are called with the offset of the `>`.
+    ///
+    /// This is not called if the input ends in the middle of the value. The default
+    /// implementation does nothing.
+    fn set_attribute_value_end(&mut self, offset: usize) {
+        let _ = offset;
+    }
+
+    /// The tokenizer has consumed the input up to the given byte offset. This is called every time
+    /// the tokenizer reads from the input, so when a tag, comment or doctype is emitted, the offset
+    /// is right after its last character. It is also called once before anything is read, with the
+    /// offset at which the tokenizer starts.
+    ///
+    /// Errors are sometimes only emitted after the tokenizer has read a bit further than the
+    /// character that caused them.
+    ///
+    /// The default implementation does nothing.
+    #[inline]
+    fn set_position(&mut self, offset: usize) {
+        let _ = offset;
+    }
+
+    /// The tokenizer has read the input up to the given line, counting from 1. This is called
+    /// whenever more input was read, but only if [`crate::Tokenizer::track_lines`] is on.
+    ///
+    /// The default implementation does nothing.
+    #[inline]
+    fn set_line(&mut self, line: usize) {
+        let _ = line;
+    }
+
+    /// Whether to stop tokenizing. This is checked after every step of the state machine. Once it
+    /// returns `true`, the tokenizer reads no more input and finishes as if the input had ended,
+    /// and [`crate::Tokenizer::is_truncated`] returns `true`.
+    ///
+    /// The default implementation never stops.
+    #[inline]
+    fn is_aborted(&self) -> bool {
+        false
+    }
+
+    /// The tokenizer switched states or read a chunk of input. Only available with the `trace`
+    /// feature, see [`crate::emitters::trace`].
+    ///
+    /// The default implementation does nothing.
+    #[cfg(feature = "trace")]
+    #[inline]
+    fn trace(&mut self, event: TraceEvent) {
+        let _ = event;
+    }
+}
+
+/// Where a comment token came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CommentKind {
+    /// A proper comment, such as `<!-- comment -->`.
+    #[default]
+    Regular,
+    /// A [bogus comment](https://html.spec.whatwg.org/multipage/#bogus-comment-state), such as
+    /// `<?xml version="1.0"?>`, `<!x>` or `</ x>`.
+    Bogus,
+    /// A CDATA section in HTML content, such as `<![CDATA[x]]>`, which is a bogus comment whose
+    /// value starts with `[CDATA[`. In foreign content these are emitted as strings instead.
+    Cdata,
+}
+
+/// How an attribute value is written in the input. See [`Emitter::set_attribute_value_syntax`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum AttributeValueSyntax {
+    /// The attribute has no value at all, as in `<input disabled>`. Not to be confused with an
+    /// empty value, such as in `<input disabled="">`.
+    #[default]
+    Empty,
+    /// The value is in double quotes, as in `<a href="x">`.
+    DoubleQuoted,
+    /// The value is in single quotes, as in `<a href='x'>`.
+    SingleQuoted,
+    /// The value is not quoted, as in `<a href=x>`. This includes a missing value after `=`, as in
+    /// `<a href=>`.
+    Unquoted,
 }
 
 /// Take an educated guess at the next state using the name of a just-now emitted start tag.
@@ -213,6 +463,8 @@ pub trait Emitter {
 /// This can be used to implement [`Emitter::emit_current_tag`] for most HTML scraping applications,
 /// but is unsuitable for implementing a browser.
 ///
+/// [`ContentStateSwitcher`] does the same, but also takes foreign content into account.
+///
 /// The mapping was inspired by `lol-html` which has additional safeguards to detect ambiguous
 /// parsing state: <https://github.com/cloudflare/lol-html/blob/f40a9f767c41caf07851548d7470649a6019548c/src/parser/tree_builder_simulator/mod.rs#L73-L86>
 #[must_use]
@@ -221,9 +473,152 @@ pub fn naive_next_state(tag_name: &[u8]) -> Option<State> {
         b"textarea" | b"title" => Some(State::RcData),
         b"plaintext" => Some(State::PlainText),
         b"script" => Some(State::ScriptData),
-        b"style" | b"iframe" | b"xmp" | b"noembed" | b"noframe" | b"noscript" => {
+        b"style" | b"iframe" | b"xmp" | b"noembed" | b"noframes" | b"noscript" => {
             Some(State::RawText)
         }
         _ => None,
     }
 }
+
+/// Switches states like [`naive_next_state`], but keeps track of foreign content (`<svg>` and
+/// `<math>`) and of end tags to avoid its most common mistakes.
+///
+/// * Elements in foreign content never switch states, for example `<svg><title>` or
+///   `<svg><style>`. HTML elements inside of integration points such as `<foreignObject>` still
+///   do.
+/// * `<noscript>` only switches to RAWTEXT if [`ContentStateSwitcher::scripting_enabled`] is on.
+///
+/// The self-closing flag is ignored for HTML elements, as browsers do: `<script/>` starts script
+/// data like `<script>` does.
+///
+/// Foreign content is tracked with a simplified stack of open elements, without a full tree
+/// builder. `<font>` with attributes does not break out of foreign content, and `<annotation-xml>`
+/// is never an integration point.
+///
+/// Call [`ContentStateSwitcher::start_tag`] and [`ContentStateSwitcher::end_tag`] from
+/// [`Emitter::emit_current_tag`], or use it through
+/// [`crate::emitters::callback::CallbackEmitter::switch_states`].
+///
+/// ```rust
+/// use html5gum::{ContentStateSwitcher, State};
+///
+/// let mut switcher = ContentStateSwitcher::default();
+/// assert_eq!(switcher.start_tag(b"title", false), Some(State::RcData));
+/// switcher.end_tag(b"title");
+///
+/// assert_eq!(switcher.start_tag(b"svg", false), None);
+/// assert!(switcher.in_foreign_content());
+/// assert_eq!(switcher.start_tag(b"title", false), None);
+/// switcher.end_tag(b"title");
+/// switcher.end_tag(b"svg");
+/// assert!(!switcher.in_foreign_content());
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct ContentStateSwitcher {
+    scripting_enabled: bool,
+    /// Elements that are open in foreign content, innermost last. HTML elements are not tracked,
+    /// so this is empty outside of foreign content.
+    foreign_elements: Vec<ForeignElement>,
+}
+
+#[derive(Debug, Clone)]
+struct ForeignElement {
+    name: Vec<u8>,
+    is_svg: bool,
+}
+
+impl ForeignElement {
+    /// Whether this is an [HTML integration
+    /// point](https://html.spec.whatwg.org/#html-integration-point) or a [MathML text integration
+    /// point](https://html.spec.whatwg.org/#mathml-text-integration-point), in which start tags
+    /// create HTML elements.
+    fn is_integration_point(&self) -> bool {
+        if self.is_svg {
+            matches!(&self.name[..], b"foreignobject" | b"desc" | b"title")
+        } else {
+            matches!(&self.name[..], b"mi" | b"mo" | b"mn" | b"ms" | b"mtext")
+        }
+    }
+}
+
+impl ContentStateSwitcher {
+    /// Whether scripting is enabled, which decides whether the contents of `<noscript>` are
+    /// RAWTEXT or HTML. Browsers usually have scripting enabled, but most scrapers and
+    /// sanitizers don't run scripts, so the contents of `<noscript>` are what they would see.
+    ///
+    /// The default is off.
+    pub fn scripting_enabled(&mut self, yes: bool) {
+        self.scripting_enabled = yes;
+    }
+
+    /// Forget all open elements, to process another document. See [`Emitter::reset`].
+    pub fn reset(&mut self) {
+        self.foreign_elements.clear();
+    }
+
+    /// Whether start tags currently create SVG or MathML elements, i.e. whether the current node
+    /// is a foreign element that is not an integration point.
+    ///
+    /// This can be used to implement
+    /// [`Emitter::adjusted_current_node_present_but_not_in_html_namespace`], except that CDATA
+    /// sections directly inside of integration points are not recognized.
+    #[must_use]
+    pub fn in_foreign_content(&self) -> bool {
+        self.foreign_elements
+            .last()
+            .is_some_and(|element| !element.is_integration_point())
+    }
+
+    /// Process a start tag, and return the state that the tokenizer should switch to.
+    pub fn start_tag(&mut self, name: &[u8], self_closing: bool) -> Option<State> {
+        if self.in_foreign_content() {
+            if !breaks_out_of_foreign_content(name) {
+                if !self_closing {
+                    let is_svg = self.foreign_elements.last().is_some_and(|x| x.is_svg);
+                    self.push(name, is_svg);
+                }
+                return None;
+            }
+
+            while self.in_foreign_content() {
+                self.foreign_elements.pop();
+            }
+        }
+
+        match name {
+            b"svg" | b"math" => {
+                if !self_closing {
+                    self.push(name, name == b"svg");
+                }
+                None
+            }
+            b"noscript" if !self.scripting_enabled => None,
+            _ => naive_next_state(name),
+        }
+    }
+
+    /// Process an end tag.
+    pub fn end_tag(&mut self, name: &[u8]) {
+        if matches!(name, b"br" | b"p") {
+            while self.in_foreign_content() {
+                self.foreign_elements.pop();
+            }
+            return;
+        }
+
+        if let Some(i) = self
+            .foreign_elements
+            .iter()
+            .rposition(|element| element.name == name)
+        {
+            self.foreign_elements.truncate(i);
+        }
+    }
+
+    fn push(&mut self, name: &[u8], is_svg: bool) {
+        self.foreign_elements.push(ForeignElement {
+            name: name.to_vec(),
+            is_svg,
+        });
+    }
+}
@@ -1,26 +1,86 @@
+//! Tree construction using html5ever's tree builder.
+//!
+//! html5gum does not implement [tree construction](https://html.spec.whatwg.org/#tree-construction)
+//! itself. Instead, [`parse_document`] and [`parse_fragment`] feed html5gum's tokens into
+//! html5ever's tree builder, which implements all of it, including the adoption agency algorithm
+//! and foreign content. The tree is built by any html5ever [`TreeSink`], such as
+//! [`scraper::Html`](https://docs.rs/scraper) or
+//! [`markup5ever_rcdom::RcDom`](https://docs.rs/markup5ever_rcdom).
+//!
+//! [`resume_document`] builds a tree for only the rest of a document, for example the body when
+//! the head was already handled using plain tokens.
+//!
+//! Scripts are never run. [`Html5everEmitter::on_script`] can supply the markup that a script would
+//! have written instead.
+//!
+//! [`Html5everEmitter::collect_spans`] records where in the input each token passed to the tree
+//! builder comes from, for example to map nodes back to their source.
+//!
 //! See [`examples/scraper.rs`] for usage.
 use std::convert::Infallible;
+use std::fmt;
 
 use crate::emitters::callback::{Callback, CallbackEmitter, CallbackEvent};
-use crate::utils::trace_log;
-use crate::{Emitter, Error, Readable, Reader, State, Tokenizer};
+use crate::utils::{is_void_element, trace_log};
+use crate::{
+    AttributeValueSyntax, CommentKind, Emitter, Error, Readable, Reader, ResumeContext, Span,
+    State, Tokenizer,
+};
 
 use html5ever::interface::{create_element, TreeSink};
 use html5ever::tokenizer::states::State as Html5everState;
 use html5ever::tokenizer::{
-    states::RawKind, Doctype, Tag, TagKind, Token as Html5everToken, TokenSink, TokenSinkResult,
+    states::{RawKind, ScriptEscapeKind},
+    Doctype, Tag, TagKind, Token as Html5everToken, TokenSink, TokenSinkResult,
 };
 use html5ever::tree_builder::TreeBuilder;
 use html5ever::ParseOpts;
-use html5ever::{Attribute, QualName};
+use html5ever::{namespace_url, ns, Attribute, QualName};
 
+/// The line number of tokens that are not in the input, see [`resume_document`].
 const BOGUS_LINENO: u64 = 1;
 
+/// A script that the tree builder would run now, see [`Html5everEmitter::on_script`].
+#[derive(Debug, Clone, Copy)]
+pub struct ScriptInfo<'a> {
+    /// The attributes of the `<script>` tag, such as `src`.
+    pub attrs: &'a [Attribute],
+    /// The text content of the script.
+    pub text: &'a str,
+}
+
+type ScriptFn<'a> = dyn FnMut(&ScriptInfo<'_>) -> Option<Vec<u8>> + 'a;
+
+struct ScriptHook<'a>(&'a mut ScriptFn<'a>);
+
+impl fmt::Debug for ScriptHook<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ScriptHook")
+    }
+}
+
 #[derive(Debug)]
 struct OurCallback<'a, S> {
     sink: &'a mut S,
     current_start_tag: Option<Tag>,
     next_state: Option<State>,
+    /// The line reported to the sink, as tracked by [`Tokenizer::track_lines`].
+    line: u64,
+    on_script: Option<ScriptHook<'a>>,
+    /// The attributes and text of the last `<script>`, until its end tag.
+    script: Option<(Vec<Attribute>, String)>,
+    /// Whether the sink asked to run a script.
+    script_pending: bool,
+    /// Whether this tokenizes markup returned by [`Html5everEmitter::on_script`], which is
+    /// followed by the rest of the document instead of EOF.
+    injected: bool,
+    /// See [`Html5everEmitter::collect_spans`].
+    spans: Option<Vec<Span>>,
+    // positions as reported by the tokenizer, like in the DefaultEmitter
+    token_start: usize,
+    position: usize,
+    previous_token_end: Option<usize>,
+    eof: bool,
 }
 
 impl<'a, S: TokenSink> OurCallback<'a, S> {
@@ -29,7 +89,7 @@ impl<'a, S: TokenSink> OurCallback<'a, S> {
             TokenSinkResult::Continue => {}
             TokenSinkResult::Script(_) => {
                 self.next_state = Some(State::Data);
-                // TODO: suspend tokenizer for script
+                self.script_pending = true;
             }
             TokenSinkResult::Plaintext => {
                 self.next_state = Some(State::PlainText);
@@ -43,17 +103,79 @@ impl<'a, S: TokenSink> OurCallback<'a, S> {
             TokenSinkResult::RawData(RawKind::ScriptData) => {
                 self.next_state = Some(State::ScriptData);
             }
-            TokenSinkResult::RawData(RawKind::ScriptDataEscaped(_)) => {
-                todo!()
+            TokenSinkResult::RawData(kind @ RawKind::ScriptDataEscaped(_)) => {
+                self.next_state = Some(map_tokenizer_state(Html5everState::RawData(kind)));
             }
         }
     }
 
-    fn sink_token(&mut self, token: Html5everToken) {
+    fn sink_token(&mut self, token: Html5everToken, span: Span) {
         trace_log!("sink_token: {:?}", token);
-        let result = self.sink.process_token(token, BOGUS_LINENO);
+        if let Some(ref mut spans) = self.spans {
+            spans.push(span);
+        }
+        let result = self.sink.process_token(token, self.line);
         self.handle_sink_result(result);
     }
+
+    fn token_span(&mut self) -> Span {
+        let span = Span {
+            start: self.token_start,
+            end: self.position,
+        };
+        self.previous_token_end = Some(self.position);
+        span
+    }
+
+    /// Strings are emitted when the next token starts, and contain everything in between.
+    fn string_span(&self) -> Span {
+        Span {
+            start: self.previous_token_end.unwrap_or_default(),
+            end: if self.eof {
+                self.position
+            } else {
+                self.token_start
+            },
+        }
+    }
+
+    fn position_span(&self) -> Span {
+        Span {
+            start: self.position,
+            end: self.position,
+        }
+    }
+
+    /// Pass the script to [`Html5everEmitter::on_script`], and tokenize the markup it returns
+    /// before the rest of the input.
+    fn run_script(&mut self, attrs: &[Attribute], text: &str) {
+        let hook = match self.on_script {
+            Some(ref mut hook) => hook,
+            None => return,
+        };
+        let markup = match (hook.0)(&ScriptInfo { attrs, text }) {
+            Some(markup) => markup,
+            None => return,
+        };
+
+        trace_log!("run_script: injecting {} bytes", markup.len());
+        let mut emitter = Html5everEmitter::new(&mut *self.sink);
+        emitter.collect_spans(self.spans.is_some());
+        let callback = emitter.emitter_inner.callback_mut();
+        callback.line = self.line;
+        callback.on_script = Some(ScriptHook(&mut *hook.0));
+        callback.injected = true;
+        let mut tokenizer = Tokenizer::new_with_emitter(&markup, emitter);
+        while tokenizer.next().is_some() {}
+
+        // The injected tokens are not in the input. They are attributed to the `</script>` that
+        // they were written at, which is the last token.
+        if let Some(ref mut spans) = self.spans {
+            let injected = tokenizer.emitter_mut().take_spans().len();
+            let script_end = spans.last().copied().unwrap_or_default();
+            spans.extend(std::iter::repeat_n(script_end, injected));
+        }
+    }
 }
 
 impl<'a, S: TokenSink> Callback<Infallible> for OurCallback<'a, S> {
@@ -80,42 +202,76 @@ impl<'a, S: TokenSink> Callback<Infallible> for OurCallback<'a, S> {
                     });
                 }
             }
-            CallbackEvent::AttributeValue { value } => {
+            CallbackEvent::AttributeValue { value, .. } => {
                 if let Some(ref mut tag) = self.current_start_tag {
                     if let Some(attr) = tag.attrs.last_mut() {
                         attr.value.push_slice(&String::from_utf8_lossy(value));
                     }
                 }
             }
-            CallbackEvent::CloseStartTag { self_closing } => {
+            // only emitted with `CallbackEmitter::pair_attributes` and
+            // `CallbackEmitter::mark_cdata_sections`, which are off
+            CallbackEvent::Attribute { .. }
+            | CallbackEvent::CdataStart
+            | CallbackEvent::CdataEnd => {}
+            CallbackEvent::CloseStartTag { self_closing, .. } => {
                 if let Some(mut tag) = self.current_start_tag.take() {
                     tag.self_closing = self_closing;
-                    self.sink_token(Html5everToken::TagToken(tag));
+                    if self.on_script.is_some() && &*tag.name == "script" {
+                        self.script = Some((tag.attrs.clone(), String::new()));
+                    }
+                    let span = self.token_span();
+                    self.sink_token(Html5everToken::TagToken(tag), span);
                 }
             }
             CallbackEvent::EndTag { name } => {
-                self.sink_token(Html5everToken::TagToken(Tag {
-                    kind: TagKind::EndTag,
-                    name: String::from_utf8_lossy(name).into_owned().into(),
-                    self_closing: false,
-                    attrs: Default::default(),
-                }));
+                let script = if name == b"script" {
+                    self.script.take()
+                } else {
+                    None
+                };
+                let span = self.token_span();
+                self.sink_token(
+                    Html5everToken::TagToken(Tag {
+                        kind: TagKind::EndTag,
+                        name: String::from_utf8_lossy(name).into_owned().into(),
+                        self_closing: false,
+                        attrs: Default::default(),
+                    }),
+                    span,
+                );
+                if std::mem::take(&mut self.script_pending) {
+                    let (attrs, text) = script.unwrap_or_default();
+                    self.run_script(&attrs, &text);
+                }
             }
             CallbackEvent::String { value } => {
+                if let Some((_, ref mut text)) = self.script {
+                    text.push_str(&String::from_utf8_lossy(value));
+                }
+                // all tokens of a run of text share its span
+                let span = self.string_span();
                 let mut first = true;
                 for part in String::from_utf8_lossy(value).split('\0') {
                     if !first {
-                        self.sink_token(Html5everToken::NullCharacterToken);
+                        self.sink_token(Html5everToken::NullCharacterToken, span);
                     }
 
                     first = false;
-                    self.sink_token(Html5everToken::CharacterTokens(part.to_owned().into()));
+                    self.sink_token(
+                        Html5everToken::CharacterTokens(part.to_owned().into()),
+                        span,
+                    );
                 }
             }
-            CallbackEvent::Comment { value } => {
-                self.sink_token(Html5everToken::CommentToken(
-                    String::from_utf8_lossy(value).into_owned().into(),
-                ));
+            CallbackEvent::Comment { value, .. } => {
+                let span = self.token_span();
+                self.sink_token(
+                    Html5everToken::CommentToken(
+                        String::from_utf8_lossy(value).into_owned().into(),
+                    ),
+                    span,
+                );
             }
             CallbackEvent::Doctype {
                 name,
@@ -123,24 +279,59 @@ impl<'a, S: TokenSink> Callback<Infallible> for OurCallback<'a, S> {
                 system_identifier,
                 force_quirks,
             } => {
-                self.sink_token(Html5everToken::DoctypeToken(Doctype {
-                    name: Some(name)
-                        .filter(|x| !x.is_empty())
-                        .map(|x| String::from_utf8_lossy(x).into_owned().into()),
-                    public_id: public_identifier
-                        .map(|x| String::from_utf8_lossy(x).into_owned().into()),
-                    system_id: system_identifier
-                        .map(|x| String::from_utf8_lossy(x).into_owned().into()),
-                    force_quirks,
-                }));
+                let span = self.token_span();
+                self.sink_token(
+                    Html5everToken::DoctypeToken(html5ever_doctype(
+                        name,
+                        public_identifier,
+                        system_identifier,
+                        force_quirks,
+                    )),
+                    span,
+                );
             }
             CallbackEvent::Error(error) => {
-                self.sink_token(Html5everToken::ParseError(error.as_str().into()));
+                #[cfg(feature = "error-strings")]
+                let message = error.as_str().into();
+                #[cfg(not(feature = "error-strings"))]
+                let message = format!("error {}", error.code()).into();
+                let span = self.position_span();
+                self.sink_token(Html5everToken::ParseError(message), span);
             }
         }
 
         None
     }
+
+    fn set_line(&mut self, line: usize) {
+        self.line = line as u64;
+    }
+
+    fn set_token_start(&mut self, offset: usize) {
+        self.token_start = offset;
+    }
+
+    fn set_position(&mut self, offset: usize) {
+        self.position = offset;
+        // the first call is the offset at which the tokenizer starts
+        self.previous_token_end.get_or_insert(offset);
+    }
+}
+
+fn html5ever_doctype(
+    name: &[u8],
+    public_identifier: Option<&[u8]>,
+    system_identifier: Option<&[u8]>,
+    force_quirks: bool,
+) -> Doctype {
+    Doctype {
+        name: Some(name)
+            .filter(|x| !x.is_empty())
+            .map(|x| String::from_utf8_lossy(x).into_owned().into()),
+        public_id: public_identifier.map(|x| String::from_utf8_lossy(x).into_owned().into()),
+        system_id: system_identifier.map(|x| String::from_utf8_lossy(x).into_owned().into()),
+        force_quirks,
+    }
 }
 
 /// A compatibility layer that allows you to plug the TreeBuilder from html5ever into the tokenizer
@@ -160,9 +351,117 @@ impl<'a, S: TokenSink> Html5everEmitter<'a, S> {
                 sink,
                 current_start_tag: None,
                 next_state: None,
+                line: 1,
+                on_script: None,
+                script: None,
+                script_pending: false,
+                injected: false,
+                spans: None,
+                token_start: 0,
+                position: 0,
+                previous_token_end: None,
+                eof: false,
             }),
         }
     }
+
+    /// Record the [`Span`] of every token that is passed to the sink, see
+    /// [`Html5everEmitter::take_spans`]. The default is off.
+    ///
+    /// Tags, comments and doctypes span from their `<` to right after their `>`, like with
+    /// [`crate::DefaultEmitter::with_spans`]. html5ever receives text as several tokens if it
+    /// contains NUL characters, which all get the span of the entire text. Parse errors and the
+    /// end of the input get an empty span. Tokens that were written by a script (see
+    /// [`Html5everEmitter::on_script`]) get the span of its `</script>`.
+    pub fn collect_spans(&mut self, yes: bool) {
+        let callback = self.emitter_inner.callback_mut();
+        callback.spans = yes.then(|| callback.spans.take().unwrap_or_default());
+    }
+
+    /// Return the spans recorded since the last call, if [`Html5everEmitter::collect_spans`] is
+    /// on. The spans are in the same order as the calls to [`TokenSink::process_token`], so the
+    /// sink can count tokens to find the span of each of them:
+    ///
+    /// ```
+    /// use std::cell::{Cell, RefCell};
+    ///
+    /// use html5ever::tokenizer::{Tag, TagKind, Token, TokenSink, TokenSinkResult};
+    /// use html5gum::emitters::html5ever::Html5everEmitter;
+    /// use html5gum::Tokenizer;
+    ///
+    /// /// Remembers the index of each start tag.
+    /// #[derive(Default)]
+    /// struct StartTags {
+    ///     count: Cell<usize>,
+    ///     start_tags: RefCell<Vec<(String, usize)>>,
+    /// }
+    ///
+    /// impl TokenSink for StartTags {
+    ///     type Handle = ();
+    ///
+    ///     fn process_token(&self, token: Token, _line_number: u64) -> TokenSinkResult<()> {
+    ///         if let Token::TagToken(Tag { kind: TagKind::StartTag, name, .. }) = token {
+    ///             self.start_tags.borrow_mut().push((name.to_string(), self.count.get()));
+    ///         }
+    ///         self.count.set(self.count.get() + 1);
+    ///         TokenSinkResult::Continue
+    ///     }
+    /// }
+    ///
+    /// let html = "<p>hello <b>world</b>";
+    /// let mut sink = StartTags::default();
+    /// let mut emitter = Html5everEmitter::new(&mut sink);
+    /// emitter.collect_spans(true);
+    /// let mut tokenizer = Tokenizer::new_with_emitter(html, emitter);
+    /// while tokenizer.next().is_some() {}
+    /// let spans = tokenizer.emitter_mut().take_spans();
+    /// drop(tokenizer);
+    ///
+    /// let start_tags: Vec<_> = sink
+    ///     .start_tags
+    ///     .take()
+    ///     .into_iter()
+    ///     .map(|(name, index)| (name, &html[spans[index].start..spans[index].end]))
+    ///     .collect();
+    /// assert_eq!(start_tags, [("p".to_owned(), "<p>"), ("b".to_owned(), "<b>")]);
+    /// ```
+    pub fn take_spans(&mut self) -> Vec<Span> {
+        self.emitter_inner
+            .callback_mut()
+            .spans
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    /// Call `f` whenever the tree builder would run a script, which is at the end tag of a
+    /// `<script>` in the document's HTML content.
+    ///
+    /// Scripts are never run, but `f` can return the markup that a script would write using
+    /// `document.write`, if it is known. That markup is tokenized on its own and passed to the
+    /// tree builder right away, before the rest of the input is read. A tag that is cut off at the
+    /// end of the markup is dropped, as if it was at the end of the document.
+    ///
+    /// ```
+    /// use html5ever::tree_builder::TreeBuilder;
+    /// use html5gum::emitters::html5ever::{Html5everEmitter, ScriptInfo};
+    /// use html5gum::Tokenizer;
+    /// use markup5ever_rcdom::RcDom;
+    ///
+    /// let html = "<p><script>document.write('<b>hi</b>')</script>";
+    ///
+    /// let mut on_script = |script: &ScriptInfo<'_>| {
+    ///     let text = script.text.strip_prefix("document.write('")?;
+    ///     Some(text.strip_suffix("')")?.as_bytes().to_vec())
+    /// };
+    /// let mut tree_builder = TreeBuilder::new(RcDom::default(), Default::default());
+    /// let mut emitter = Html5everEmitter::new(&mut tree_builder);
+    /// emitter.on_script(&mut on_script);
+    /// let Ok(()) = Tokenizer::new_with_emitter(html, emitter).finish();
+    /// ```
+    pub fn on_script(&mut self, f: &'a mut dyn FnMut(&ScriptInfo<'_>) -> Option<Vec<u8>>) {
+        self.emitter_inner.callback_mut().on_script = Some(ScriptHook(f));
+    }
 }
 
 impl<'a, S: TokenSink> Emitter for Html5everEmitter<'a, S> {
@@ -172,11 +471,35 @@ impl<'a, S: TokenSink> Emitter for Html5everEmitter<'a, S> {
         self.emitter_inner.set_last_start_tag(last_start_tag)
     }
 
+    fn last_start_tag(&self) -> Option<&[u8]> {
+        self.emitter_inner.last_start_tag()
+    }
+
+    fn begin(&mut self) {
+        self.emitter_inner.begin();
+    }
+
     fn emit_eof(&mut self) {
+        self.emitter_inner.callback_mut().eof = true;
         self.emitter_inner.emit_eof();
-        let sink = &mut self.emitter_inner.callback_mut().sink;
-        let _ignored = sink.process_token(Html5everToken::EOFToken, BOGUS_LINENO);
-        sink.end();
+        let callback = self.emitter_inner.callback_mut();
+        if callback.injected {
+            return;
+        }
+        if let Some(ref mut spans) = callback.spans {
+            spans.push(Span {
+                start: callback.position,
+                end: callback.position,
+            });
+        }
+        let _ignored = callback
+            .sink
+            .process_token(Html5everToken::EOFToken, callback.line);
+        callback.sink.end();
+    }
+
+    fn finish(&mut self) {
+        self.emitter_inner.finish();
     }
 
     fn emit_error(&mut self, error: Error) {
@@ -206,9 +529,19 @@ impl<'a, S: TokenSink> Emitter for Html5everEmitter<'a, S> {
         self.emitter_inner.init_comment()
     }
 
+    fn set_comment_kind(&mut self, kind: CommentKind) {
+        self.emitter_inner.set_comment_kind(kind)
+    }
+
     fn emit_current_tag(&mut self) -> Option<State> {
-        assert!(self.emitter_inner.emit_current_tag().is_none());
-        self.emitter_inner.callback_mut().next_state.take()
+        // the inner emitter has no state switching configured, the sink decides instead
+        let state = self.emitter_inner.emit_current_tag();
+        debug_assert!(state.is_none());
+        self.emitter_inner
+            .callback_mut()
+            .next_state
+            .take()
+            .or(state)
     }
 
     fn emit_current_comment(&mut self) {
@@ -231,6 +564,10 @@ impl<'a, S: TokenSink> Emitter for Html5everEmitter<'a, S> {
         self.emitter_inner.push_tag_name(s)
     }
 
+    fn push_tag_name_original(&mut self, s: &[u8]) {
+        self.emitter_inner.push_tag_name_original(s)
+    }
+
     fn push_comment(&mut self, s: &[u8]) {
         self.emitter_inner.push_comment(s)
     }
@@ -251,6 +588,14 @@ impl<'a, S: TokenSink> Emitter for Html5everEmitter<'a, S> {
         self.emitter_inner.push_attribute_name(s)
     }
 
+    fn push_attribute_name_original(&mut self, s: &[u8]) {
+        self.emitter_inner.push_attribute_name_original(s)
+    }
+
+    fn init_attribute_value(&mut self) {
+        self.emitter_inner.init_attribute_value()
+    }
+
     fn push_attribute_value(&mut self, s: &[u8]) {
         self.emitter_inner.push_attribute_value(s)
     }
@@ -281,6 +626,33 @@ impl<'a, S: TokenSink> Emitter for Html5everEmitter<'a, S> {
             .sink
             .adjusted_current_node_present_but_not_in_html_namespace()
     }
+    fn set_token_start(&mut self, offset: usize) {
+        self.emitter_inner.set_token_start(offset)
+    }
+
+    fn set_attribute_value_start(&mut self, offset: usize) {
+        self.emitter_inner.set_attribute_value_start(offset)
+    }
+
+    fn set_attribute_value_end(&mut self, offset: usize) {
+        self.emitter_inner.set_attribute_value_end(offset)
+    }
+
+    fn set_attribute_value_syntax(&mut self, syntax: AttributeValueSyntax) {
+        self.emitter_inner.set_attribute_value_syntax(syntax)
+    }
+
+    fn set_position(&mut self, offset: usize) {
+        self.emitter_inner.set_position(offset)
+    }
+
+    fn set_line(&mut self, line: usize) {
+        self.emitter_inner.set_line(line)
+    }
+
+    fn is_aborted(&self) -> bool {
+        self.emitter_inner.is_aborted()
+    }
 }
 
 fn map_tokenizer_state(input: Html5everState) -> State {
@@ -290,7 +662,18 @@ fn map_tokenizer_state(input: Html5everState) -> State {
         Html5everState::RawData(RawKind::Rcdata) => State::RcData,
         Html5everState::RawData(RawKind::Rawtext) => State::RawText,
         Html5everState::RawData(RawKind::ScriptData) => State::ScriptData,
-        x => todo!("{:?}", x),
+        Html5everState::RawData(RawKind::ScriptDataEscaped(ScriptEscapeKind::Escaped)) => {
+            State::ScriptDataEscaped
+        }
+        Html5everState::RawData(RawKind::ScriptDataEscaped(ScriptEscapeKind::DoubleEscaped)) => {
+            State::ScriptDataDoubleEscaped
+        }
+        // html5ever's tree builder only ever asks for the states above. The others are internal to
+        // its tokenizer, and the best we can do is to start over in the data state.
+        x => {
+            trace_log!("map_tokenizer_state: unsupported state {:?}", x);
+            State::Data
+        }
     }
 }
 
@@ -354,6 +737,7 @@ where
     let initial_state = map_tokenizer_state(tree_builder.tokenizer_state_for_context_elem());
     let token_emitter = Html5everEmitter::new(&mut tree_builder);
     let mut tokenizer = Tokenizer::new_with_emitter(input, token_emitter);
+    tokenizer.track_lines(true);
     tokenizer.set_state(initial_state);
     tokenizer.finish()?;
     Ok(tree_builder.sink)
@@ -391,7 +775,184 @@ where
 {
     let mut tree_builder = TreeBuilder::new(sink, opts.tree_builder);
     let token_emitter = Html5everEmitter::new(&mut tree_builder);
-    let tokenizer = Tokenizer::new_with_emitter(input, token_emitter);
+    let mut tokenizer = Tokenizer::new_with_emitter(input, token_emitter);
+    tokenizer.track_lines(true);
     tokenizer.finish()?;
     Ok(tree_builder.sink)
 }
+
+/// What the tree builder needs to know about the part of a document that was not parsed, see
+/// [`resume_document`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AttachContext {
+    /// The document's doctype, if any. It determines the quirks mode.
+    pub doctype: Option<crate::Doctype>,
+    /// The names of the open elements, outermost first, such as `html` and `body`.
+    ///
+    /// The first element has to be `html`. Open elements are restored by creating new elements
+    /// without attributes, and the insertion mode follows from them. See [`AttachError`] for
+    /// which elements are supported.
+    pub open_elements: Vec<QualName>,
+}
+
+/// The error returned by [`resume_document`].
+#[derive(Debug)]
+pub enum AttachError<E> {
+    /// The stack of open elements can not be restored from its names alone. This is the case for
+    /// elements that are not in the HTML namespace, `<template>`, elements in `<head>`, elements
+    /// that can not have children such as `<br>` or `<title>`, and table parts without their
+    /// parent, such as `<tr>` directly in `<table>`.
+    UnsupportedElement(QualName),
+    /// The reader failed.
+    Read(E),
+}
+
+impl<E: fmt::Display> fmt::Display for AttachError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AttachError::UnsupportedElement(name) => {
+                write!(f, "cannot attach inside of <{}>", name.local)
+            }
+            AttachError::Read(e) => e.fmt(f),
+        }
+    }
+}
+
+impl<E: std::error::Error> std::error::Error for AttachError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AttachError::UnsupportedElement(_) => None,
+            AttachError::Read(e) => e.source(),
+        }
+    }
+}
+
+/// Find the first open element that would end up elsewhere, or not at all, when replaying the
+/// start tags of `open_elements` in order.
+fn unsupported_open_element(open_elements: &[QualName]) -> Option<&QualName> {
+    let mut parent: Option<&str> = None;
+    for (i, name) in open_elements.iter().enumerate() {
+        let local: &str = &name.local;
+        let supported = name.ns == ns!(html)
+            && parent != Some("head")
+            && !is_void_element(local.as_bytes())
+            && match (i, local) {
+                (0, "html") | (1, "head" | "body") => true,
+                (0, _) | (_, "html" | "head" | "body") => false,
+                (
+                    _,
+                    "template" | "frameset" | "svg" | "math" | "textarea" | "title" | "plaintext"
+                    | "script" | "style" | "iframe" | "xmp" | "noembed" | "noframes" | "noscript",
+                ) => false,
+                (_, "tbody" | "thead" | "tfoot" | "caption" | "colgroup") => {
+                    parent == Some("table")
+                }
+                (_, "tr") => matches!(parent, Some("tbody" | "thead" | "tfoot")),
+                (_, "td" | "th") => parent == Some("tr"),
+                _ => true,
+            };
+
+        if !supported {
+            return Some(name);
+        }
+        parent = Some(local);
+    }
+
+    None
+}
+
+/// Parse the rest of an HTML document, starting at byte `offset`.
+///
+/// The tokenizer is resumed like [`Tokenizer::resume_at`] does, and the tree builder continues
+/// with the doctype and open elements from `attach`. This allows tokenizing the head of a document
+/// directly and only building a tree for the body, without tokenizing the head again:
+///
+/// ```rust
+/// use html5ever::{local_name, ns, namespace_url, QualName}; // extern crate html5ever;
+/// use html5gum::emitters::html5ever::{resume_document, AttachContext};
+/// use html5gum::{DefaultEmitter, Token, Tokenizer};
+/// use markup5ever_rcdom::RcDom; // extern crate markup5ever_rcdom;
+///
+/// let html = "<!DOCTYPE html><title>x</title><body><p>hello";
+///
+/// let mut tokenizer = Tokenizer::new_with_emitter(html, DefaultEmitter::with_spans());
+/// let mut doctype = None;
+/// let mut resume = None;
+/// for (token, span) in tokenizer.by_ref().flatten() {
+///     match token {
+///         Token::Doctype(x) => doctype = Some(x),
+///         Token::StartTag(tag) if tag.name == b"body" => {
+///             resume = Some((span.end, tokenizer.context_at_last_token()));
+///             break;
+///         }
+///         _ => {}
+///     }
+/// }
+///
+/// let (offset, context) = resume.unwrap();
+/// let attach = AttachContext {
+///     doctype,
+///     open_elements: vec![
+///         QualName::new(None, ns!(html), local_name!("html")),
+///         QualName::new(None, ns!(html), local_name!("body")),
+///     ],
+/// };
+/// let dom = resume_document(html, offset, context, attach, RcDom::default(), Default::default())
+///     .unwrap();
+/// ```
+///
+/// The resulting tree is only an approximation of the tree for the entire document:
+///
+/// * The open elements don't have attributes, and there is a `<head>` element without any
+///   children.
+/// * The tree builder's list of active formatting elements only contains the open ones, and its
+///   form element pointer is only set if a `<form>` is open.
+///
+/// Returns [`AttachError::UnsupportedElement`] before anything is read if the open elements can
+/// not be restored.
+pub fn resume_document<'a, R, Sink>(
+    input: R,
+    offset: usize,
+    context: ResumeContext,
+    attach: AttachContext,
+    sink: Sink,
+    opts: ParseOpts,
+) -> Result<Sink, AttachError<<R::Reader as Reader>::Error>>
+where
+    R: Readable<'a>,
+    Sink: TreeSink,
+{
+    if let Some(name) = unsupported_open_element(&attach.open_elements) {
+        return Err(AttachError::UnsupportedElement(name.clone()));
+    }
+
+    let mut tree_builder = TreeBuilder::new(sink, opts.tree_builder);
+    if let Some(doctype) = attach.doctype {
+        let _ = tree_builder.process_token(
+            Html5everToken::DoctypeToken(html5ever_doctype(
+                &doctype.name,
+                doctype.public_identifier.as_deref().map(Vec::as_slice),
+                doctype.system_identifier.as_deref().map(Vec::as_slice),
+                doctype.force_quirks,
+            )),
+            BOGUS_LINENO,
+        );
+    }
+    for name in attach.open_elements {
+        let _ = tree_builder.process_token(
+            Html5everToken::TagToken(Tag {
+                kind: TagKind::StartTag,
+                name: name.local,
+                self_closing: false,
+                attrs: Vec::new(),
+            }),
+            BOGUS_LINENO,
+        );
+    }
+
+    let token_emitter = Html5everEmitter::new(&mut tree_builder);
+    let tokenizer = Tokenizer::resume_at_with_emitter(input, offset, context, token_emitter)
+        .map_err(AttachError::Read)?;
+    tokenizer.finish().map_err(AttachError::Read)?;
+    Ok(tree_builder.sink)
+}
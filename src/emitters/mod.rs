@@ -13,13 +13,28 @@
 //! Emitters are "a way to consume parsing results." The following ways are available:
 //!
 //! * [default::DefaultEmitter], if you don't care about speed and only want convenience.
+//! * [utf8::Utf8Emitter], if your input is valid UTF-8 and you want `String`s instead of bytes.
+//! * [buffered::BufferedEmitter], if you want fewer allocations but can handle tokens one at a time.
 //! * [callback::CallbackEmitter], if you can deal with some lifetime problems in exchange for way fewer allocations.
+//! * [strict::StrictEmitter] around any of the above, if you want to stop at the first parse error.
+//! * [validate::ValidateEmitter], if you only want the parse errors.
+//! * [noop::NoopEmitter], if you only want to run the tokenizer, for example to benchmark it.
 //! * Implementing your own [Emitter] for maximum performance and maximum pain.
+pub mod buffered;
 pub mod callback;
 pub mod default;
 #[cfg(feature = "html5ever")]
 pub mod html5ever;
+pub mod noop;
+pub mod strict;
+#[cfg(feature = "trace")]
+pub mod trace;
+pub mod utf8;
+pub mod validate;
 
 mod emitter;
+mod recording;
 
-pub use emitter::{naive_next_state, Emitter};
+pub use emitter::{
+    naive_next_state, AttributeValueSyntax, CommentKind, ContentStateSwitcher, Emitter,
+};
@@ -0,0 +1,122 @@
+//! An emitter that ignores everything, for running the tokenizer only for its side effects.
+//!
+//! This is useful to measure the cost of the tokenizer's state machine alone, or together with an
+//! emitter wrapper that does the actual work. For example, this checks whether a document has
+//! parse errors without allocating any tokens:
+//!
+//! ```rust
+//! use html5gum::emitters::noop::NoopEmitter;
+//! use html5gum::emitters::strict::StrictEmitter;
+//! use html5gum::{Error, Tokenizer};
+//!
+//! let emitter = StrictEmitter::new(NoopEmitter::default());
+//! let mut tokenizer = Tokenizer::new_with_emitter("<p>fish &amp chips</p>", emitter);
+//! let error = tokenizer.find_map(|token| token.unwrap().err());
+//! assert_eq!(
+//!     error.map(|e| e.error),
+//!     Some(Error::MissingSemicolonAfterCharacterReference)
+//! );
+//! ```
+//!
+//! Errors that are detected by emitters, such as [`Error::DuplicateAttribute`], are not found this
+//! way.
+use std::convert::Infallible;
+
+use crate::{Emitter, Error, State};
+
+/// An emitter that produces no tokens and ignores errors. See the [module documentation](self).
+///
+/// It does not keep the names of tags either. So it never switches states, and in the RCDATA,
+/// RAWTEXT and script data states no end tag is appropriate.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopEmitter;
+
+impl Emitter for NoopEmitter {
+    type Token = Infallible;
+
+    #[inline]
+    fn set_last_start_tag(&mut self, _last_start_tag: Option<&[u8]>) {}
+
+    #[inline]
+    fn emit_eof(&mut self) {}
+
+    #[inline]
+    fn emit_error(&mut self, _error: Error) {}
+
+    #[inline]
+    fn should_emit_errors(&mut self) -> bool {
+        false
+    }
+
+    #[inline]
+    fn pop_token(&mut self) -> Option<Self::Token> {
+        None
+    }
+
+    #[inline]
+    fn emit_string(&mut self, _c: &[u8]) {}
+
+    #[inline]
+    fn init_start_tag(&mut self) {}
+
+    #[inline]
+    fn init_end_tag(&mut self) {}
+
+    #[inline]
+    fn init_comment(&mut self) {}
+
+    #[inline]
+    fn emit_current_tag(&mut self) -> Option<State> {
+        None
+    }
+
+    #[inline]
+    fn emit_current_comment(&mut self) {}
+
+    #[inline]
+    fn emit_current_doctype(&mut self) {}
+
+    #[inline]
+    fn set_self_closing(&mut self) {}
+
+    #[inline]
+    fn set_force_quirks(&mut self) {}
+
+    #[inline]
+    fn push_tag_name(&mut self, _s: &[u8]) {}
+
+    #[inline]
+    fn push_comment(&mut self, _s: &[u8]) {}
+
+    #[inline]
+    fn push_doctype_name(&mut self, _s: &[u8]) {}
+
+    #[inline]
+    fn init_doctype(&mut self) {}
+
+    #[inline]
+    fn init_attribute(&mut self) {}
+
+    #[inline]
+    fn push_attribute_name(&mut self, _s: &[u8]) {}
+
+    #[inline]
+    fn push_attribute_value(&mut self, _s: &[u8]) {}
+
+    #[inline]
+    fn set_doctype_public_identifier(&mut self, _value: &[u8]) {}
+
+    #[inline]
+    fn set_doctype_system_identifier(&mut self, _value: &[u8]) {}
+
+    #[inline]
+    fn push_doctype_public_identifier(&mut self, _s: &[u8]) {}
+
+    #[inline]
+    fn push_doctype_system_identifier(&mut self, _s: &[u8]) {}
+
+    #[inline]
+    fn current_is_appropriate_end_tag_token(&mut self) -> bool {
+        false
+    }
+}
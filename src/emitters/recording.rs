@@ -0,0 +1,371 @@
+//! Owned copies of [`CallbackEvent`], for tests of [`Callback`] implementations. Re-exported from
+//! [`crate::emitters::callback`].
+use std::convert::Infallible;
+use std::fmt::{self, Debug, Formatter};
+
+use crate::emitters::callback::{Callback, CallbackEvent};
+use crate::utils::DebugAllBytes;
+use crate::{AttributeValueSyntax, CommentKind, Error, Span};
+
+/// An owned copy of a [`CallbackEvent`], with the same variants and fields.
+///
+/// Unlike `CallbackEvent`, it can be collected and compared, and its `Debug` output shows bytes
+/// as strings, so that failing `assert_eq!`s are readable. See [`RecordingCallback`].
+#[derive(Clone, PartialEq, Eq)]
+pub enum OwnedCallbackEvent {
+    /// See [`CallbackEvent::OpenStartTag`].
+    OpenStartTag {
+        /// The name of the start tag.
+        name: Vec<u8>,
+    },
+    /// See [`CallbackEvent::AttributeName`].
+    AttributeName {
+        /// The name of the attribute.
+        name: Vec<u8>,
+    },
+    /// See [`CallbackEvent::AttributeValue`].
+    AttributeValue {
+        /// The value of the attribute.
+        value: Vec<u8>,
+        /// Whether the value was quoted in the input.
+        syntax: AttributeValueSyntax,
+    },
+    /// See [`CallbackEvent::Attribute`].
+    Attribute {
+        /// The name of the attribute.
+        name: Vec<u8>,
+        /// The value of the attribute, or `None` if there was no `=` after the name.
+        value: Option<Vec<u8>>,
+        /// Whether the value was quoted in the input.
+        syntax: AttributeValueSyntax,
+    },
+    /// See [`CallbackEvent::CloseStartTag`].
+    CloseStartTag {
+        /// Whether the tag ended with `"/>"`.
+        self_closing: bool,
+        /// Whether the tag is a void element such as `<br>`.
+        is_void: bool,
+    },
+    /// See [`CallbackEvent::EndTag`].
+    EndTag {
+        /// The name of the end tag.
+        name: Vec<u8>,
+    },
+    /// See [`CallbackEvent::String`].
+    String {
+        /// A series of character tokens.
+        value: Vec<u8>,
+    },
+    /// See [`CallbackEvent::CdataStart`].
+    CdataStart,
+    /// See [`CallbackEvent::CdataEnd`].
+    CdataEnd,
+    /// See [`CallbackEvent::Comment`].
+    Comment {
+        /// The contents of the comment.
+        value: Vec<u8>,
+        /// Whether this is a proper comment.
+        kind: CommentKind,
+    },
+    /// See [`CallbackEvent::Doctype`].
+    Doctype {
+        /// Name of the docstring.
+        name: Vec<u8>,
+        /// Public identifier, `None` if missing.
+        public_identifier: Option<Vec<u8>>,
+        /// System identifier, `None` if missing.
+        system_identifier: Option<Vec<u8>>,
+        /// Enable quirksmode
+        force_quirks: bool,
+    },
+    /// See [`CallbackEvent::Error`].
+    Error(Error),
+}
+
+impl From<CallbackEvent<'_>> for OwnedCallbackEvent {
+    fn from(event: CallbackEvent<'_>) -> Self {
+        match event {
+            CallbackEvent::OpenStartTag { name } => OwnedCallbackEvent::OpenStartTag {
+                name: name.to_vec(),
+            },
+            CallbackEvent::AttributeName { name } => OwnedCallbackEvent::AttributeName {
+                name: name.to_vec(),
+            },
+            CallbackEvent::AttributeValue { value, syntax } => OwnedCallbackEvent::AttributeValue {
+                value: value.to_vec(),
+                syntax,
+            },
+            CallbackEvent::Attribute {
+                name,
+                value,
+                syntax,
+            } => OwnedCallbackEvent::Attribute {
+                name: name.to_vec(),
+                value: value.map(<[u8]>::to_vec),
+                syntax,
+            },
+            CallbackEvent::CloseStartTag {
+                self_closing,
+                is_void,
+            } => OwnedCallbackEvent::CloseStartTag {
+                self_closing,
+                is_void,
+            },
+            CallbackEvent::EndTag { name } => OwnedCallbackEvent::EndTag {
+                name: name.to_vec(),
+            },
+            CallbackEvent::String { value } => OwnedCallbackEvent::String {
+                value: value.to_vec(),
+            },
+            CallbackEvent::CdataStart => OwnedCallbackEvent::CdataStart,
+            CallbackEvent::CdataEnd => OwnedCallbackEvent::CdataEnd,
+            CallbackEvent::Comment { value, kind } => OwnedCallbackEvent::Comment {
+                value: value.to_vec(),
+                kind,
+            },
+            CallbackEvent::Doctype {
+                name,
+                public_identifier,
+                system_identifier,
+                force_quirks,
+            } => OwnedCallbackEvent::Doctype {
+                name: name.to_vec(),
+                public_identifier: public_identifier.map(<[u8]>::to_vec),
+                system_identifier: system_identifier.map(<[u8]>::to_vec),
+                force_quirks,
+            },
+            CallbackEvent::Error(error) => OwnedCallbackEvent::Error(error),
+        }
+    }
+}
+
+impl Debug for OwnedCallbackEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            OwnedCallbackEvent::OpenStartTag { name } => f
+                .debug_struct("OpenStartTag")
+                .field("name", &DebugAllBytes(name))
+                .finish(),
+            OwnedCallbackEvent::AttributeName { name } => f
+                .debug_struct("AttributeName")
+                .field("name", &DebugAllBytes(name))
+                .finish(),
+            OwnedCallbackEvent::AttributeValue { value, syntax } => f
+                .debug_struct("AttributeValue")
+                .field("value", &DebugAllBytes(value))
+                .field("syntax", syntax)
+                .finish(),
+            OwnedCallbackEvent::Attribute {
+                name,
+                value,
+                syntax,
+            } => f
+                .debug_struct("Attribute")
+                .field("name", &DebugAllBytes(name))
+                .field("value", &value.as_deref().map(DebugAllBytes))
+                .field("syntax", syntax)
+                .finish(),
+            OwnedCallbackEvent::CloseStartTag {
+                self_closing,
+                is_void,
+            } => f
+                .debug_struct("CloseStartTag")
+                .field("self_closing", self_closing)
+                .field("is_void", is_void)
+                .finish(),
+            OwnedCallbackEvent::EndTag { name } => f
+                .debug_struct("EndTag")
+                .field("name", &DebugAllBytes(name))
+                .finish(),
+            OwnedCallbackEvent::String { value } => f
+                .debug_struct("String")
+                .field("value", &DebugAllBytes(value))
+                .finish(),
+            OwnedCallbackEvent::CdataStart => f.write_str("CdataStart"),
+            OwnedCallbackEvent::CdataEnd => f.write_str("CdataEnd"),
+            OwnedCallbackEvent::Comment { value, kind } => f
+                .debug_struct("Comment")
+                .field("value", &DebugAllBytes(value))
+                .field("kind", kind)
+                .finish(),
+            OwnedCallbackEvent::Doctype {
+                name,
+                public_identifier,
+                system_identifier,
+                force_quirks,
+            } => f
+                .debug_struct("Doctype")
+                .field("name", &DebugAllBytes(name))
+                .field(
+                    "public_identifier",
+                    &public_identifier.as_deref().map(DebugAllBytes),
+                )
+                .field(
+                    "system_identifier",
+                    &system_identifier.as_deref().map(DebugAllBytes),
+                )
+                .field("force_quirks", force_quirks)
+                .finish(),
+            OwnedCallbackEvent::Error(error) => f.debug_tuple("Error").field(error).finish(),
+        }
+    }
+}
+
+/// A [`Callback`] that records every event together with its span, to compare them with an
+/// expected sequence in tests.
+///
+/// Spans follow [`crate::DefaultEmitter::with_spans`]: everything that belongs to a tag, comment
+/// or doctype spans from its `<` to as far as the input has been read, which is right after the
+/// `>` for events that end one. Strings span everything since the end of the previous tag,
+/// comment or doctype, attribute values span their unquoted value, and errors are empty spans at
+/// the position where they were detected. `CdataStart` and `CdataEnd` span the `<![CDATA[` and
+/// the `]]>`, and the text in between spans exactly that text.
+///
+/// ```
+/// use html5gum::emitters::callback::{CallbackEmitter, OwnedCallbackEvent, RecordingCallback};
+/// use html5gum::{AttributeValueSyntax, Span, Tokenizer};
+///
+/// let input = "<a href=x>hi</a>";
+/// let mut tokenizer =
+///     Tokenizer::new_with_emitter(input, CallbackEmitter::new(RecordingCallback::default()));
+/// for _ in tokenizer.by_ref() {}
+///
+/// let span = |start, end| Span { start, end };
+/// assert_eq!(
+///     tokenizer.emitter().callback().events(),
+///     [
+///         (OwnedCallbackEvent::OpenStartTag { name: b"a".to_vec() }, span(0, 4)),
+///         (OwnedCallbackEvent::AttributeName { name: b"href".to_vec() }, span(0, 9)),
+///         (
+///             OwnedCallbackEvent::AttributeValue {
+///                 value: b"x".to_vec(),
+///                 syntax: AttributeValueSyntax::Unquoted,
+///             },
+///             span(8, 9),
+///         ),
+///         (
+///             OwnedCallbackEvent::CloseStartTag { self_closing: false, is_void: false },
+///             span(0, 10),
+///         ),
+///         (OwnedCallbackEvent::String { value: b"hi".to_vec() }, span(10, 12)),
+///         (OwnedCallbackEvent::EndTag { name: b"a".to_vec() }, span(12, 16)),
+///     ]
+/// );
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct RecordingCallback {
+    events: Vec<(OwnedCallbackEvent, Span)>,
+    // positions as reported by the tokenizer, see Emitter::set_token_start
+    token_start: usize,
+    position: usize,
+    previous_token_end: Option<usize>,
+    value_span: Option<Span>,
+    /// Set inside CDATA sections, to where their `]]>` starts once that is known.
+    cdata_end: Option<Option<usize>>,
+}
+
+impl RecordingCallback {
+    /// The events recorded so far.
+    pub fn events(&self) -> &[(OwnedCallbackEvent, Span)] {
+        &self.events
+    }
+
+    /// Return the events recorded so far.
+    pub fn into_events(self) -> Vec<(OwnedCallbackEvent, Span)> {
+        self.events
+    }
+
+    /// Where the `]]>` of a CDATA section starts, right after it has been read.
+    fn closed_cdata_end(&self) -> usize {
+        self.position.saturating_sub(b"]]>".len())
+    }
+
+    fn token_span(&self) -> Span {
+        Span {
+            start: self.token_start,
+            end: self.position,
+        }
+    }
+}
+
+impl Callback<Infallible> for RecordingCallback {
+    fn handle_event(&mut self, event: CallbackEvent<'_>) -> Option<Infallible> {
+        let span = match event {
+            CallbackEvent::OpenStartTag { .. } | CallbackEvent::AttributeName { .. } => {
+                self.token_span()
+            }
+            CallbackEvent::AttributeValue { .. } | CallbackEvent::Attribute { .. } => {
+                self.value_span.take().unwrap_or_else(|| self.token_span())
+            }
+            CallbackEvent::CloseStartTag { .. }
+            | CallbackEvent::EndTag { .. }
+            | CallbackEvent::Comment { .. }
+            | CallbackEvent::Doctype { .. } => {
+                self.previous_token_end = Some(self.position);
+                self.token_span()
+            }
+            CallbackEvent::CdataStart => {
+                let end = self.token_start + b"<![CDATA[".len();
+                self.previous_token_end = Some(end);
+                self.cdata_end = Some(None);
+                Span {
+                    start: self.token_start,
+                    end,
+                }
+            }
+            CallbackEvent::CdataEnd => {
+                let start = self.cdata_end.take().flatten();
+                self.previous_token_end = Some(self.position);
+                Span {
+                    start: start.unwrap_or_else(|| self.closed_cdata_end()),
+                    end: self.position,
+                }
+            }
+            CallbackEvent::String { .. } => {
+                let start = self.previous_token_end.unwrap_or_default();
+                let closed_cdata_end = self.closed_cdata_end();
+                let end = match self.cdata_end {
+                    // the text of a CDATA section is emitted right after its `]]>` is read
+                    Some(ref mut cdata_end) => *cdata_end.get_or_insert(closed_cdata_end),
+                    // strings are emitted when the next token starts, or at the end of the input
+                    None if self.token_start > start => self.token_start,
+                    None => self.position,
+                };
+                Span { start, end }
+            }
+            CallbackEvent::Error(Error::EofInCdata) => {
+                if let Some(ref mut cdata_end) = self.cdata_end {
+                    *cdata_end = Some(self.position);
+                }
+                Span {
+                    start: self.position,
+                    end: self.position,
+                }
+            }
+            CallbackEvent::Error(_) => Span {
+                start: self.position,
+                end: self.position,
+            },
+        };
+        self.events.push((event.into(), span));
+        None
+    }
+
+    fn set_token_start(&mut self, offset: usize) {
+        self.token_start = offset;
+    }
+
+    fn set_attribute_value_span(&mut self, span: Span) {
+        self.value_span = Some(span);
+    }
+
+    fn set_position(&mut self, offset: usize) {
+        self.position = offset;
+        // the first call is the offset at which the tokenizer starts
+        self.previous_token_end.get_or_insert(offset);
+    }
+
+    fn reset(&mut self) {
+        *self = RecordingCallback::default();
+    }
+}
@@ -0,0 +1,320 @@
+//! An emitter wrapper that stops tokenizing at the first parse error, for validating HTML instead
+//! of recovering from errors.
+//!
+//! ```rust
+//! use html5gum::emitters::strict::{StrictEmitter, StrictError};
+//! use html5gum::{DefaultEmitter, Error, Token, Tokenizer};
+//!
+//! let emitter = StrictEmitter::new(DefaultEmitter::default());
+//! let mut tokenizer = Tokenizer::new_with_emitter("<p>fish &amp chips</p>", emitter);
+//! let tokens: Vec<_> = tokenizer.by_ref().flatten().collect();
+//! assert!(matches!(tokens[0], Ok(Token::StartTag(_))));
+//! assert_eq!(
+//!     tokens[1],
+//!     Err(StrictError {
+//!         error: Error::MissingSemicolonAfterCharacterReference,
+//!         offset: 13,
+//!     })
+//! );
+//! assert_eq!(tokens.len(), 2);
+//! assert!(tokenizer.is_truncated());
+//! ```
+//!
+//! Any emitter can be wrapped, including [`crate::emitters::callback::CallbackEmitter`]. Its
+//! callback does not receive errors. The tokenizer finishes the step in which the error occured,
+//! so the callback may still be called for the rest of that step, but nothing it returns
+//! afterwards is passed on.
+use std::fmt;
+
+use crate::{AttributeValueSyntax, CommentKind, Emitter, Error, State};
+
+/// The first parse error in the input, returned by [`StrictEmitter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StrictError {
+    /// The kind of error.
+    pub error: Error,
+    /// The byte offset into the input at which the error was emitted. See
+    /// [`Emitter::set_position`] for how this relates to the location of the error.
+    pub offset: usize,
+}
+
+impl fmt::Display for StrictError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        #[cfg(feature = "error-strings")]
+        write!(f, "{}", self.error)?;
+        #[cfg(not(feature = "error-strings"))]
+        write!(f, "error {}", self.error.code())?;
+        write!(f, " at byte offset {}", self.offset)
+    }
+}
+
+impl std::error::Error for StrictError {}
+
+/// An emitter that wraps another emitter and stops the tokenizer at the first parse error. See the
+/// [module documentation](self).
+///
+/// The tokens of the inner emitter are returned as `Ok`, followed by the error as `Err`. Tokens
+/// that were not complete when the error occured, and errors that the inner emitter would have
+/// emitted, are not returned. Afterwards, [`crate::Tokenizer::is_truncated`] returns `true`.
+///
+/// Errors are only known to the wrapper, so this does not make tokenizing slower for emitters
+/// that are not wrapped.
+#[derive(Debug)]
+pub struct StrictEmitter<E: Emitter> {
+    inner: E,
+    position: usize,
+    error: Option<StrictError>,
+    /// Tokens that were emitted before the error, but not popped yet.
+    pending: Vec<E::Token>,
+    finished: bool,
+}
+
+impl<E: Emitter> StrictEmitter<E> {
+    /// Wrap `inner`.
+    pub fn new(inner: E) -> Self {
+        StrictEmitter {
+            inner,
+            position: 0,
+            error: None,
+            pending: Vec::new(),
+            finished: false,
+        }
+    }
+
+    /// Get access to the inner emitter.
+    pub fn inner(&self) -> &E {
+        &self.inner
+    }
+
+    /// Get mutable access to the inner emitter, for example to change its options.
+    pub fn inner_mut(&mut self) -> &mut E {
+        &mut self.inner
+    }
+
+    /// Return the inner emitter.
+    pub fn into_inner(self) -> E {
+        self.inner
+    }
+
+    /// Return the error that stopped the tokenizer, if any.
+    pub fn error(&self) -> Option<&StrictError> {
+        self.error.as_ref()
+    }
+}
+
+impl<E: Emitter> Emitter for StrictEmitter<E> {
+    type Token = Result<E::Token, StrictError>;
+
+    fn set_last_start_tag(&mut self, last_start_tag: Option<&[u8]>) {
+        self.inner.set_last_start_tag(last_start_tag)
+    }
+
+    fn last_start_tag(&self) -> Option<&[u8]> {
+        self.inner.last_start_tag()
+    }
+
+    fn begin(&mut self) {
+        self.inner.begin();
+    }
+
+    fn emit_eof(&mut self) {
+        self.inner.emit_eof()
+    }
+
+    fn finish(&mut self) {
+        self.inner.finish();
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+        self.position = 0;
+        self.error = None;
+        self.pending.clear();
+        self.finished = false;
+    }
+
+    fn emit_error(&mut self, error: Error) {
+        if self.error.is_some() {
+            return;
+        }
+
+        self.error = Some(StrictError {
+            error,
+            offset: self.position,
+        });
+        while let Some(token) = self.inner.pop_token() {
+            self.pending.push(token);
+        }
+        self.pending.reverse();
+    }
+
+    #[inline]
+    fn should_emit_errors(&mut self) -> bool {
+        true
+    }
+
+    fn pop_token(&mut self) -> Option<Self::Token> {
+        match self.error {
+            None => self.inner.pop_token().map(Ok),
+            Some(error) => {
+                if let Some(token) = self.pending.pop() {
+                    Some(Ok(token))
+                } else if self.finished {
+                    None
+                } else {
+                    self.finished = true;
+                    Some(Err(error))
+                }
+            }
+        }
+    }
+
+    fn emit_string(&mut self, c: &[u8]) {
+        self.inner.emit_string(c)
+    }
+
+    fn init_start_tag(&mut self) {
+        self.inner.init_start_tag()
+    }
+
+    fn init_end_tag(&mut self) {
+        self.inner.init_end_tag()
+    }
+
+    fn init_comment(&mut self) {
+        self.inner.init_comment()
+    }
+
+    fn set_comment_kind(&mut self, kind: CommentKind) {
+        self.inner.set_comment_kind(kind)
+    }
+
+    fn emit_current_tag(&mut self) -> Option<State> {
+        self.inner.emit_current_tag()
+    }
+
+    fn should_skip_rawtext(&mut self, tag_name: &[u8]) -> bool {
+        self.inner.should_skip_rawtext(tag_name)
+    }
+
+    fn emit_current_comment(&mut self) {
+        self.inner.emit_current_comment()
+    }
+
+    fn emit_current_doctype(&mut self) {
+        self.inner.emit_current_doctype()
+    }
+
+    fn set_self_closing(&mut self) {
+        self.inner.set_self_closing()
+    }
+
+    fn set_force_quirks(&mut self) {
+        self.inner.set_force_quirks()
+    }
+
+    fn push_tag_name(&mut self, s: &[u8]) {
+        self.inner.push_tag_name(s)
+    }
+
+    fn push_tag_name_original(&mut self, s: &[u8]) {
+        self.inner.push_tag_name_original(s)
+    }
+
+    fn push_comment(&mut self, s: &[u8]) {
+        self.inner.push_comment(s)
+    }
+
+    fn push_doctype_name(&mut self, s: &[u8]) {
+        self.inner.push_doctype_name(s)
+    }
+
+    fn init_doctype(&mut self) {
+        self.inner.init_doctype()
+    }
+
+    fn init_attribute(&mut self) {
+        self.inner.init_attribute()
+    }
+
+    fn push_attribute_name(&mut self, s: &[u8]) {
+        self.inner.push_attribute_name(s)
+    }
+
+    fn push_attribute_name_original(&mut self, s: &[u8]) {
+        self.inner.push_attribute_name_original(s)
+    }
+
+    fn init_attribute_value(&mut self) {
+        self.inner.init_attribute_value()
+    }
+
+    fn push_attribute_value(&mut self, s: &[u8]) {
+        self.inner.push_attribute_value(s)
+    }
+
+    fn set_doctype_public_identifier(&mut self, value: &[u8]) {
+        self.inner.set_doctype_public_identifier(value)
+    }
+
+    fn set_doctype_system_identifier(&mut self, value: &[u8]) {
+        self.inner.set_doctype_system_identifier(value)
+    }
+
+    fn push_doctype_public_identifier(&mut self, s: &[u8]) {
+        self.inner.push_doctype_public_identifier(s)
+    }
+
+    fn push_doctype_system_identifier(&mut self, s: &[u8]) {
+        self.inner.push_doctype_system_identifier(s)
+    }
+
+    fn current_is_appropriate_end_tag_token(&mut self) -> bool {
+        self.inner.current_is_appropriate_end_tag_token()
+    }
+
+    fn adjusted_current_node_present_but_not_in_html_namespace(&mut self) -> bool {
+        self.inner
+            .adjusted_current_node_present_but_not_in_html_namespace()
+    }
+
+    fn start_cdata(&mut self) {
+        self.inner.start_cdata()
+    }
+
+    fn end_cdata(&mut self) {
+        self.inner.end_cdata()
+    }
+
+    fn set_token_start(&mut self, offset: usize) {
+        self.inner.set_token_start(offset)
+    }
+
+    fn set_attribute_value_start(&mut self, offset: usize) {
+        self.inner.set_attribute_value_start(offset)
+    }
+
+    fn set_attribute_value_end(&mut self, offset: usize) {
+        self.inner.set_attribute_value_end(offset)
+    }
+
+    fn set_attribute_value_syntax(&mut self, syntax: AttributeValueSyntax) {
+        self.inner.set_attribute_value_syntax(syntax)
+    }
+
+    #[inline]
+    fn set_position(&mut self, offset: usize) {
+        self.position = offset;
+        self.inner.set_position(offset)
+    }
+
+    #[inline]
+    fn set_line(&mut self, line: usize) {
+        self.inner.set_line(line)
+    }
+
+    #[inline]
+    fn is_aborted(&self) -> bool {
+        self.error.is_some() || self.inner.is_aborted()
+    }
+}
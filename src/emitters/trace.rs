@@ -0,0 +1,348 @@
+//! Trace what the tokenizer does, to debug why a document is tokenized the way it is. Only
+//! available with the `trace` feature.
+//!
+//! [`crate::Tokenizer::set_trace`] calls a closure with a [`TraceEvent`] for every state
+//! transition of the state machine, every call to the emitter, and every chunk of input that is
+//! read at once. For example, this is how `<a href=x>` is tokenized:
+//!
+//! ```rust
+//! use html5gum::emitters::trace::TraceEvent;
+//! use html5gum::Tokenizer;
+//!
+//! let mut transitions = Vec::new();
+//! let tokenizer = Tokenizer::new("<a href=x>").set_trace(|event| {
+//!     if let TraceEvent::StateTransition { from, to, byte } = event {
+//!         transitions.push(format!("{} -> {} at {:?}", from, to, byte.map(char::from)));
+//!     }
+//! });
+//! assert_eq!(tokenizer.count(), 1);
+//!
+//! assert_eq!(
+//!     transitions,
+//!     [
+//!         "Data -> TagOpen at Some('<')",
+//!         "TagOpen -> TagName at Some('a')",
+//!         "TagName -> BeforeAttributeName at Some(' ')",
+//!         "BeforeAttributeName -> AttributeName at Some('h')",
+//!         "AttributeName -> BeforeAttributeValue at Some('=')",
+//!         "BeforeAttributeValue -> AttributeValueUnquoted at Some('x')",
+//!         "AttributeValueUnquoted -> Data at Some('>')",
+//!     ]
+//! );
+//! ```
+//!
+//! The names of states are the ones of the [WHATWG
+//! spec](https://html.spec.whatwg.org/multipage/parsing.html#tokenization) in CamelCase, plus a
+//! few that html5gum uses internally. They are meant for reading, and may change between
+//! versions.
+//!
+//! Without the `trace` feature, none of this is compiled in, so tracing has no cost when it is
+//! not used.
+use std::fmt;
+
+use crate::{AttributeValueSyntax, CommentKind, Emitter, Error, State};
+
+/// Something the tokenizer did, as reported to [`crate::Tokenizer::set_trace`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceEvent {
+    /// The state machine switched from one state to another.
+    StateTransition {
+        /// The name of the previous state, such as `TagOpen`.
+        from: &'static str,
+        /// The name of the next state.
+        to: &'static str,
+        /// The last byte that was read before the transition, or `None` at the end of the input.
+        /// Newlines are normalized, so `\r` and `\r\n` show up as `\n`, except for a `\r` that
+        /// ends a chunk of input.
+        byte: Option<u8>,
+    },
+    /// A method of the emitter was called, such as `emit_string` or `init_start_tag`.
+    /// [`Emitter::set_position`], [`Emitter::set_line`] and the methods that only ask the emitter
+    /// for something, such as [`Emitter::pop_token`], are left out.
+    EmitterCall {
+        /// The name of the method.
+        name: &'static str,
+    },
+    /// The tokenizer read a chunk of input from the reader at once, with
+    /// [`crate::Reader::read_until`]. Input that is read byte by byte is not reported.
+    Read {
+        /// The length of the chunk, in bytes.
+        bytes: usize,
+    },
+}
+
+/// An emitter that wraps another emitter and reports the calls to it, as well as the events that
+/// the tokenizer passes to [`Emitter::trace`], to a closure. Returned by
+/// [`crate::Tokenizer::set_trace`].
+pub struct TraceEmitter<E, F> {
+    inner: E,
+    hook: F,
+}
+
+impl<E: Emitter, F: FnMut(TraceEvent)> TraceEmitter<E, F> {
+    /// Wrap `inner`, and call `hook` for every event.
+    pub fn new(inner: E, hook: F) -> Self {
+        TraceEmitter { inner, hook }
+    }
+
+    /// Get access to the inner emitter.
+    pub fn inner(&self) -> &E {
+        &self.inner
+    }
+
+    /// Get mutable access to the inner emitter, for example to change its options.
+    pub fn inner_mut(&mut self) -> &mut E {
+        &mut self.inner
+    }
+
+    /// Return the inner emitter.
+    pub fn into_inner(self) -> E {
+        self.inner
+    }
+
+    #[inline]
+    fn call(&mut self, name: &'static str) {
+        (self.hook)(TraceEvent::EmitterCall { name });
+    }
+}
+
+impl<E: fmt::Debug, F> fmt::Debug for TraceEmitter<E, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TraceEmitter")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<E: Emitter, F: FnMut(TraceEvent)> Emitter for TraceEmitter<E, F> {
+    type Token = E::Token;
+
+    fn set_last_start_tag(&mut self, last_start_tag: Option<&[u8]>) {
+        self.call("set_last_start_tag");
+        self.inner.set_last_start_tag(last_start_tag)
+    }
+
+    fn last_start_tag(&self) -> Option<&[u8]> {
+        self.inner.last_start_tag()
+    }
+
+    fn begin(&mut self) {
+        self.call("begin");
+        self.inner.begin();
+    }
+
+    fn emit_eof(&mut self) {
+        self.call("emit_eof");
+        self.inner.emit_eof()
+    }
+
+    fn finish(&mut self) {
+        self.call("finish");
+        self.inner.finish();
+    }
+
+    fn reset(&mut self) {
+        self.call("reset");
+        self.inner.reset();
+    }
+
+    fn emit_error(&mut self, error: Error) {
+        self.call("emit_error");
+        self.inner.emit_error(error)
+    }
+
+    #[inline]
+    fn should_emit_errors(&mut self) -> bool {
+        self.inner.should_emit_errors()
+    }
+
+    fn pop_token(&mut self) -> Option<Self::Token> {
+        self.inner.pop_token()
+    }
+
+    fn emit_string(&mut self, c: &[u8]) {
+        self.call("emit_string");
+        self.inner.emit_string(c)
+    }
+
+    fn init_start_tag(&mut self) {
+        self.call("init_start_tag");
+        self.inner.init_start_tag()
+    }
+
+    fn init_end_tag(&mut self) {
+        self.call("init_end_tag");
+        self.inner.init_end_tag()
+    }
+
+    fn init_comment(&mut self) {
+        self.call("init_comment");
+        self.inner.init_comment()
+    }
+
+    fn set_comment_kind(&mut self, kind: CommentKind) {
+        self.call("set_comment_kind");
+        self.inner.set_comment_kind(kind)
+    }
+
+    fn emit_current_tag(&mut self) -> Option<State> {
+        self.call("emit_current_tag");
+        self.inner.emit_current_tag()
+    }
+
+    fn should_skip_rawtext(&mut self, tag_name: &[u8]) -> bool {
+        self.call("should_skip_rawtext");
+        self.inner.should_skip_rawtext(tag_name)
+    }
+
+    fn emit_current_comment(&mut self) {
+        self.call("emit_current_comment");
+        self.inner.emit_current_comment()
+    }
+
+    fn emit_current_doctype(&mut self) {
+        self.call("emit_current_doctype");
+        self.inner.emit_current_doctype()
+    }
+
+    fn set_self_closing(&mut self) {
+        self.call("set_self_closing");
+        self.inner.set_self_closing()
+    }
+
+    fn set_force_quirks(&mut self) {
+        self.call("set_force_quirks");
+        self.inner.set_force_quirks()
+    }
+
+    fn push_tag_name(&mut self, s: &[u8]) {
+        self.call("push_tag_name");
+        self.inner.push_tag_name(s)
+    }
+
+    fn push_tag_name_original(&mut self, s: &[u8]) {
+        self.call("push_tag_name_original");
+        self.inner.push_tag_name_original(s)
+    }
+
+    fn push_comment(&mut self, s: &[u8]) {
+        self.call("push_comment");
+        self.inner.push_comment(s)
+    }
+
+    fn push_doctype_name(&mut self, s: &[u8]) {
+        self.call("push_doctype_name");
+        self.inner.push_doctype_name(s)
+    }
+
+    fn init_doctype(&mut self) {
+        self.call("init_doctype");
+        self.inner.init_doctype()
+    }
+
+    fn init_attribute(&mut self) {
+        self.call("init_attribute");
+        self.inner.init_attribute()
+    }
+
+    fn push_attribute_name(&mut self, s: &[u8]) {
+        self.call("push_attribute_name");
+        self.inner.push_attribute_name(s)
+    }
+
+    fn push_attribute_name_original(&mut self, s: &[u8]) {
+        self.call("push_attribute_name_original");
+        self.inner.push_attribute_name_original(s)
+    }
+
+    fn init_attribute_value(&mut self) {
+        self.call("init_attribute_value");
+        self.inner.init_attribute_value()
+    }
+
+    fn push_attribute_value(&mut self, s: &[u8]) {
+        self.call("push_attribute_value");
+        self.inner.push_attribute_value(s)
+    }
+
+    fn set_doctype_public_identifier(&mut self, value: &[u8]) {
+        self.call("set_doctype_public_identifier");
+        self.inner.set_doctype_public_identifier(value)
+    }
+
+    fn set_doctype_system_identifier(&mut self, value: &[u8]) {
+        self.call("set_doctype_system_identifier");
+        self.inner.set_doctype_system_identifier(value)
+    }
+
+    fn push_doctype_public_identifier(&mut self, s: &[u8]) {
+        self.call("push_doctype_public_identifier");
+        self.inner.push_doctype_public_identifier(s)
+    }
+
+    fn push_doctype_system_identifier(&mut self, s: &[u8]) {
+        self.call("push_doctype_system_identifier");
+        self.inner.push_doctype_system_identifier(s)
+    }
+
+    fn current_is_appropriate_end_tag_token(&mut self) -> bool {
+        self.call("current_is_appropriate_end_tag_token");
+        self.inner.current_is_appropriate_end_tag_token()
+    }
+
+    fn adjusted_current_node_present_but_not_in_html_namespace(&mut self) -> bool {
+        self.call("adjusted_current_node_present_but_not_in_html_namespace");
+        self.inner
+            .adjusted_current_node_present_but_not_in_html_namespace()
+    }
+
+    fn start_cdata(&mut self) {
+        self.call("start_cdata");
+        self.inner.start_cdata()
+    }
+
+    fn end_cdata(&mut self) {
+        self.call("end_cdata");
+        self.inner.end_cdata()
+    }
+
+    fn set_token_start(&mut self, offset: usize) {
+        self.call("set_token_start");
+        self.inner.set_token_start(offset)
+    }
+
+    fn set_attribute_value_start(&mut self, offset: usize) {
+        self.call("set_attribute_value_start");
+        self.inner.set_attribute_value_start(offset)
+    }
+
+    fn set_attribute_value_end(&mut self, offset: usize) {
+        self.call("set_attribute_value_end");
+        self.inner.set_attribute_value_end(offset)
+    }
+
+    fn set_attribute_value_syntax(&mut self, syntax: AttributeValueSyntax) {
+        self.call("set_attribute_value_syntax");
+        self.inner.set_attribute_value_syntax(syntax)
+    }
+
+    #[inline]
+    fn set_position(&mut self, offset: usize) {
+        self.inner.set_position(offset)
+    }
+
+    #[inline]
+    fn set_line(&mut self, line: usize) {
+        self.inner.set_line(line)
+    }
+
+    #[inline]
+    fn is_aborted(&self) -> bool {
+        self.inner.is_aborted()
+    }
+
+    fn trace(&mut self, event: TraceEvent) {
+        (self.hook)(event);
+        self.inner.trace(event);
+    }
+}
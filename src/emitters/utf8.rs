@@ -0,0 +1,156 @@
+//! A variant of the default emitter that produces `String`s instead of bytestrings, for input that
+//! is known to be valid UTF-8.
+//!
+//! The tokenizer only ever emits bytes from the input, ASCII characters and characters produced
+//! by character references or as replacement characters. So if the input is valid UTF-8, all
+//! tokens are as well. [`Utf8Emitter`] can only be created together with a tokenizer that
+//! guarantees valid input:
+//!
+//! * [`crate::Tokenizer::new_utf8`] for `&str`,
+//! * [`crate::Tokenizer::new_utf8_bytes`] for byte slices, which are validated up front,
+//! * [`crate::Tokenizer::new_utf8_reader`] for any other reader, which is validated while reading
+//!   using [`crate::Utf8Reader`].
+//!
+//! ```rust
+//! use html5gum::emitters::utf8::{StartTag, Token};
+//! use html5gum::Tokenizer;
+//!
+//! let tokens: Vec<_> = Tokenizer::new_utf8("<p class=grüß>&auml;</p>").flatten().collect();
+//! assert_eq!(
+//!     tokens[..2],
+//!     [
+//!         Token::StartTag(StartTag {
+//!             self_closing: false,
+//!             name: "p".to_owned(),
+//!             attributes: vec![("class".to_owned(), "grüß".to_owned())],
+//!         }),
+//!         Token::String("ä".to_owned()),
+//!     ]
+//! );
+//!
+//! assert!(Tokenizer::new_utf8_bytes(b"<p>\xff</p>").is_err());
+//! ```
+use crate::emitters::default::{self, DefaultEmitterToken};
+use crate::{is_void_element, CommentKind, DefaultEmitter, Error, HtmlString, Span};
+
+/// An emitter that works like [`DefaultEmitter`], but produces [`Token`]s with `String`s. See the
+/// [module documentation](self) for how to create one.
+///
+/// Options can be changed using [`crate::Tokenizer::emitter_mut`].
+pub type Utf8Emitter = DefaultEmitter<Token>;
+
+/// A HTML start tag, such as `<p>` or `<a>`. See [`default::StartTag`].
+#[derive(Debug, Default, Eq, PartialEq, Clone)]
+pub struct StartTag {
+    /// Whether this tag is self-closing.
+    pub self_closing: bool,
+
+    /// The start tag's name, such as `"p"` or `"a"`.
+    pub name: String,
+
+    /// The tag's attributes as `(name, value)`, in source order and without duplicates.
+    pub attributes: Vec<(String, String)>,
+}
+
+impl StartTag {
+    /// Whether this is a void element such as `<br>`. See [`default::StartTag::is_void`].
+    #[must_use]
+    pub fn is_void(&self) -> bool {
+        is_void_element(self.name.as_bytes())
+    }
+}
+
+/// A HTML end tag, such as `</p>` or `</a>`. See [`default::EndTag`].
+#[derive(Debug, Default, Eq, PartialEq, Clone)]
+pub struct EndTag {
+    /// The ending tag's name, such as `"p"` or `"a"`.
+    pub name: String,
+}
+
+/// A HTML comment. See [`default::Comment`].
+#[derive(Debug, Default, Eq, PartialEq, Clone)]
+pub struct Comment {
+    /// The comment's contents, such as `" comment "`.
+    pub value: String,
+
+    /// Whether this is a proper comment.
+    pub kind: CommentKind,
+}
+
+/// A doctype. See [`default::Doctype`].
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Doctype {
+    /// The ["force quirks"](https://html.spec.whatwg.org/#force-quirks-flag) flag.
+    pub force_quirks: bool,
+
+    /// The doctype's name. For HTML documents this is "html".
+    pub name: String,
+
+    /// The doctype's public identifier, or `None` if it is missing.
+    pub public_identifier: Option<String>,
+
+    /// The doctype's system identifier, or `None` if it is missing.
+    pub system_identifier: Option<String>,
+}
+
+/// The token type produced by [`Utf8Emitter`]. See [`default::Token`].
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum Token {
+    /// A HTML start tag.
+    StartTag(StartTag),
+    /// A HTML end tag.
+    EndTag(EndTag),
+    /// A literal string.
+    String(String),
+    /// A HTML comment.
+    Comment(Comment),
+    /// A HTML doctype declaration.
+    Doctype(Doctype),
+    /// A HTML parsing error.
+    Error(Error),
+}
+
+fn into_string(s: HtmlString) -> String {
+    match String::from_utf8(s.0) {
+        Ok(s) => s,
+        Err(e) => {
+            // The constructors of Utf8Emitter make sure that the input is valid UTF-8, so this
+            // is a bug in the tokenizer.
+            if cfg!(debug_assertions) {
+                panic!("tokenizer produced invalid UTF-8: {:?}", e);
+            }
+            String::from_utf8_lossy(e.as_bytes()).into_owned()
+        }
+    }
+}
+
+impl DefaultEmitterToken for Token {
+    fn from_token(token: default::Token, _span: Span) -> Self {
+        match token {
+            default::Token::StartTag(tag) => Token::StartTag(StartTag {
+                self_closing: tag.self_closing,
+                name: into_string(tag.name),
+                attributes: tag
+                    .attributes
+                    .into_iter()
+                    .map(|(name, value)| (into_string(name), into_string(value)))
+                    .collect(),
+            }),
+            default::Token::EndTag(tag) => Token::EndTag(EndTag {
+                name: into_string(tag.name),
+            }),
+            default::Token::String(s) => Token::String(into_string(s)),
+            default::Token::Comment(comment) => Token::Comment(Comment {
+                value: into_string(comment.value),
+                kind: comment.kind,
+            }),
+            default::Token::Doctype(doctype) => Token::Doctype(Doctype {
+                force_quirks: doctype.force_quirks,
+                name: into_string(doctype.name),
+                public_identifier: doctype.public_identifier.map(into_string),
+                system_identifier: doctype.system_identifier.map(into_string),
+            }),
+            default::Token::Error(error) => Token::Error(error),
+        }
+    }
+}
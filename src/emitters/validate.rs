@@ -0,0 +1,302 @@
+//! An emitter that only produces parse errors, for checking whether a document tokenizes cleanly.
+//!
+//! ```rust
+//! use html5gum::emitters::validate::ValidateEmitter;
+//! use html5gum::{Error, Span, Tokenizer};
+//!
+//! let input = "<p class=a class=b>fish &amp chips</p>";
+//! let errors: Vec<_> = Tokenizer::new_with_emitter(input, ValidateEmitter::new())
+//!     .flatten()
+//!     .collect();
+//! assert_eq!(
+//!     errors,
+//!     [
+//!         (Error::DuplicateAttribute, Span { start: 18, end: 18 }),
+//!         (
+//!             Error::MissingSemicolonAfterCharacterReference,
+//!             Span { start: 29, end: 29 }
+//!         ),
+//!     ]
+//! );
+//! ```
+//!
+//! Scanning a file works the same way. The iterator returns `Err` if the file can't be read:
+//!
+//! ```no_run
+//! use std::fs::File;
+//!
+//! use html5gum::emitters::validate::ValidateEmitter;
+//! use html5gum::Tokenizer;
+//!
+//! # fn main() -> std::io::Result<()> {
+//! let file = File::open("index.html")?;
+//! for result in Tokenizer::new_with_emitter(file, ValidateEmitter::new()) {
+//!     let (error, span) = result?;
+//!     println!("{:?} at byte {}", error, span.start);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use std::collections::VecDeque;
+
+use crate::emitters::callback::AttributeNames;
+use crate::{naive_next_state, Emitter, Error, Span, State};
+
+/// The longest tag name that is kept. Longer names never switch states, see [`naive_next_state`].
+const MAX_TAG_NAME_LEN: usize = 16;
+
+/// A tag name that is stored without allocating, as long as it is short enough.
+#[derive(Debug, Clone, Copy, Default)]
+struct TagName {
+    buf: [u8; MAX_TAG_NAME_LEN],
+    len: usize,
+    too_long: bool,
+}
+
+impl TagName {
+    fn clear(&mut self) {
+        self.len = 0;
+        self.too_long = false;
+    }
+
+    fn push(&mut self, s: &[u8]) {
+        let len = s.len().min(MAX_TAG_NAME_LEN - self.len);
+        self.buf[self.len..self.len + len].copy_from_slice(&s[..len]);
+        self.len += len;
+        self.too_long |= len < s.len();
+    }
+
+    /// The name, unless it was too long to be stored.
+    fn get(&self) -> Option<&[u8]> {
+        Some(&self.buf[..self.len]).filter(|_| !self.too_long)
+    }
+}
+
+/// An emitter that produces every parse error with its position, and nothing else. See the
+/// [module documentation](self).
+///
+/// It reports the same errors as [`crate::DefaultEmitter`], as long as that switches states with
+/// [`crate::DefaultEmitter::naively_switch_states`], which this emitter always does. Only the name
+/// of the last start tag is kept, plus the attribute names of the current tag to find duplicates.
+/// All other content is discarded while it is read.
+///
+/// Every error has an empty span at the offset at which it was emitted, like with
+/// [`crate::DefaultEmitter::with_spans`].
+#[derive(Debug, Default)]
+pub struct ValidateEmitter {
+    errors: VecDeque<(Error, Span)>,
+    position: usize,
+    token_count: usize,
+    last_start_tag: TagName,
+    current_tag_name: TagName,
+    current_tag_is_end: bool,
+    current_tag_had_attributes: bool,
+    current_attribute_name: Vec<u8>,
+    attribute_names: AttributeNames,
+}
+
+impl ValidateEmitter {
+    /// Create a new emitter.
+    pub fn new() -> Self {
+        ValidateEmitter::default()
+    }
+
+    /// The number of tags, comments and doctypes that were emitted so far. Text is not counted.
+    ///
+    /// This starts over at zero when the emitter is reset, see [`Emitter::reset`].
+    pub fn token_count(&self) -> usize {
+        self.token_count
+    }
+
+    fn flush_attribute_name(&mut self) {
+        if !self.current_attribute_name.is_empty() {
+            if !self.attribute_names.insert(&self.current_attribute_name) {
+                self.emit_error(Error::DuplicateAttribute);
+            }
+            self.current_attribute_name.clear();
+        }
+    }
+
+    fn init_tag(&mut self, is_end: bool) {
+        self.current_tag_name.clear();
+        self.current_tag_is_end = is_end;
+        self.current_tag_had_attributes = false;
+        self.current_attribute_name.clear();
+        self.attribute_names.clear();
+    }
+}
+
+impl Emitter for ValidateEmitter {
+    type Token = (Error, Span);
+
+    fn set_last_start_tag(&mut self, last_start_tag: Option<&[u8]>) {
+        self.last_start_tag.clear();
+        self.last_start_tag.push(last_start_tag.unwrap_or_default());
+    }
+
+    fn last_start_tag(&self) -> Option<&[u8]> {
+        self.last_start_tag.get().filter(|x| !x.is_empty())
+    }
+
+    fn emit_eof(&mut self) {}
+
+    fn reset(&mut self) {
+        self.errors.clear();
+        self.position = 0;
+        self.token_count = 0;
+        self.last_start_tag.clear();
+        self.init_tag(false);
+    }
+
+    fn emit_error(&mut self, error: Error) {
+        let span = Span {
+            start: self.position,
+            end: self.position,
+        };
+        self.errors.push_back((error, span));
+    }
+
+    fn pop_token(&mut self) -> Option<Self::Token> {
+        self.errors.pop_front()
+    }
+
+    #[inline]
+    fn emit_string(&mut self, _c: &[u8]) {}
+
+    fn init_start_tag(&mut self) {
+        self.init_tag(false);
+    }
+
+    fn init_end_tag(&mut self) {
+        self.init_tag(true);
+    }
+
+    fn init_comment(&mut self) {}
+
+    fn emit_current_tag(&mut self) -> Option<State> {
+        self.flush_attribute_name();
+        self.token_count += 1;
+
+        if self.current_tag_is_end {
+            if self.current_tag_had_attributes {
+                self.emit_error(Error::EndTagWithAttributes);
+            }
+            None
+        } else {
+            self.last_start_tag = self.current_tag_name;
+            naive_next_state(self.current_tag_name.get()?)
+        }
+    }
+
+    fn emit_current_comment(&mut self) {
+        self.token_count += 1;
+    }
+
+    fn emit_current_doctype(&mut self) {
+        self.token_count += 1;
+    }
+
+    fn set_self_closing(&mut self) {
+        if self.current_tag_is_end {
+            // the same order as the DefaultEmitter, which reports the attributes with the error
+            self.flush_attribute_name();
+            self.emit_error(Error::EndTagWithTrailingSolidus);
+        }
+    }
+
+    fn set_force_quirks(&mut self) {}
+
+    #[inline]
+    fn push_tag_name(&mut self, s: &[u8]) {
+        self.current_tag_name.push(s);
+    }
+
+    #[inline]
+    fn push_comment(&mut self, _s: &[u8]) {}
+
+    #[inline]
+    fn push_doctype_name(&mut self, _s: &[u8]) {}
+
+    fn init_doctype(&mut self) {}
+
+    fn init_attribute(&mut self) {
+        self.flush_attribute_name();
+        self.current_tag_had_attributes = true;
+    }
+
+    #[inline]
+    fn push_attribute_name(&mut self, s: &[u8]) {
+        self.current_attribute_name.extend(s);
+    }
+
+    #[inline]
+    fn push_attribute_value(&mut self, _s: &[u8]) {
+        self.flush_attribute_name();
+    }
+
+    fn set_doctype_public_identifier(&mut self, _value: &[u8]) {}
+
+    fn set_doctype_system_identifier(&mut self, _value: &[u8]) {}
+
+    #[inline]
+    fn push_doctype_public_identifier(&mut self, _s: &[u8]) {}
+
+    #[inline]
+    fn push_doctype_system_identifier(&mut self, _s: &[u8]) {}
+
+    fn current_is_appropriate_end_tag_token(&mut self) -> bool {
+        self.current_tag_is_end
+            && self.last_start_tag().is_some()
+            && self.current_tag_name.get() == self.last_start_tag()
+    }
+
+    #[inline]
+    fn set_position(&mut self, offset: usize) {
+        self.position = offset;
+    }
+}
+
+#[test]
+fn test_switch_states() {
+    use crate::Tokenizer;
+
+    let errors = |input: &str| {
+        let mut tokenizer = Tokenizer::new_with_emitter(input, ValidateEmitter::new());
+        let errors: Vec<_> = tokenizer
+            .by_ref()
+            .flatten()
+            .map(|(error, _)| error)
+            .collect();
+        (errors, tokenizer.emitter_mut().token_count())
+    };
+
+    // no errors in RCDATA, script data and RAWTEXT
+    assert_eq!(
+        errors("<title><p a a></title><script>a</b>&amp</script><style></stylex></style>"),
+        (vec![], 6)
+    );
+    // the end tag needs to match the start tag exactly, even if it is longer than the buffer
+    assert_eq!(
+        errors("<textarea></textareaaaaaaaaaaaaaaaaaa a a></textarea><p a a>"),
+        (vec![Error::DuplicateAttribute], 3)
+    );
+    assert_eq!(
+        errors("<aaaaaaaaaaaaaaaaaaaa a a></aaaaaaaaaaaaaaaaaaaa a>"),
+        (
+            vec![Error::DuplicateAttribute, Error::EndTagWithAttributes],
+            2
+        )
+    );
+    // a duplicate attribute right before the trailing solidus is reported first
+    assert_eq!(
+        errors("</div a a/>"),
+        (
+            vec![
+                Error::DuplicateAttribute,
+                Error::EndTagWithTrailingSolidus,
+                Error::EndTagWithAttributes
+            ],
+            1
+        )
+    );
+}
@@ -10,6 +10,7 @@ macro_rules! impl_error {
                 $variant
             ),*
         }
+        #[cfg(feature = "error-strings")]
         impl std::str::FromStr for Error {
             type Err = ();
 
@@ -24,8 +25,14 @@ macro_rules! impl_error {
         }
 
         impl Error {
+            /// All variants, in the order of their discriminants, which is also the order of
+            /// their [`Error::code`]s.
+            pub const ALL: &'static [Error] = &[ $( Self::$variant, )* ];
+
             /// Convert an enum variant back into the `kebap-case` error code as typically written
-            /// in the WHATWG spec.
+            /// in the WHATWG spec, such as `"duplicate-attribute"` for
+            /// [`Error::DuplicateAttribute`]. This is the inverse of [`str::parse`].
+            #[cfg(feature = "error-strings")]
             #[must_use]
             pub fn as_str(&self) -> &'static str {
                 match *self {
@@ -36,6 +43,97 @@ macro_rules! impl_error {
     }
 }
 
+impl Error {
+    /// A numeric code for this error, which is available even without the `error-strings`
+    /// feature. For the error code of the spec, use [`Error::as_str`].
+    ///
+    /// Codes are assigned in the order in which the variants are declared. Errors that are not
+    /// part of the spec are declared last.
+    #[must_use]
+    pub fn code(&self) -> u16 {
+        *self as u16
+    }
+
+    /// The kind of syntax this error is about.
+    #[must_use]
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            Error::EndTagWithAttributes
+            | Error::EndTagWithTrailingSolidus
+            | Error::EofBeforeTagName
+            | Error::EofInTag
+            | Error::InvalidFirstCharacterOfTagName
+            | Error::MissingAttributeValue
+            | Error::MissingEndTagName
+            | Error::MissingWhitespaceBetweenAttributes
+            | Error::UnexpectedCharacterInAttributeName
+            | Error::UnexpectedCharacterInUnquotedAttributeValue
+            | Error::UnexpectedEqualsSignBeforeAttributeName
+            | Error::UnexpectedQuestionMarkInsteadOfTagName
+            | Error::UnexpectedSolidusInTag
+            | Error::DuplicateAttribute => ErrorCategory::Tag,
+            Error::AbruptClosingOfEmptyComment
+            | Error::CdataInHtmlContent
+            | Error::EofInCdata
+            | Error::EofInComment
+            | Error::EofInScriptHtmlCommentLikeText
+            | Error::IncorrectlyClosedComment
+            | Error::IncorrectlyOpenedComment
+            | Error::NestedComment => ErrorCategory::Comment,
+            Error::AbruptDoctypePublicIdentifier
+            | Error::AbruptDoctypeSystemIdentifier
+            | Error::EofInDoctype
+            | Error::InvalidCharacterSequenceAfterDoctypeName
+            | Error::MissingDoctypeName
+            | Error::MissingDoctypePublicIdentifier
+            | Error::MissingDoctypeSystemIdentifier
+            | Error::MissingQuoteBeforeDoctypePublicIdentifier
+            | Error::MissingQuoteBeforeDoctypeSystemIdentifier
+            | Error::MissingWhitespaceAfterDoctypePublicKeyword
+            | Error::MissingWhitespaceAfterDoctypeSystemKeyword
+            | Error::MissingWhitespaceBeforeDoctypeName
+            | Error::MissingWhitespaceBetweenDoctypePublicAndSystemIdentifiers
+            | Error::UnexpectedCharacterAfterDoctypeSystemIdentifier => ErrorCategory::Doctype,
+            Error::AbsenceOfDigitsInNumericCharacterReference
+            | Error::CharacterReferenceOutsideUnicodeRange
+            | Error::ControlCharacterReference
+            | Error::MissingSemicolonAfterCharacterReference
+            | Error::NoncharacterCharacterReference
+            | Error::NullCharacterReference
+            | Error::SurrogateCharacterReference
+            | Error::UnknownNamedCharacterReference => ErrorCategory::CharacterReference,
+            Error::ControlCharacterInInputStream
+            | Error::NoncharacterInInputStream
+            | Error::SurrogateInInputStream
+            | Error::UnexpectedNullCharacter
+            | Error::SkippedBinaryGarbage
+            | Error::InvalidUtf8InInputStream => ErrorCategory::InputStream,
+            Error::LimitExceeded => ErrorCategory::Other,
+        }
+    }
+}
+
+/// The kind of syntax an [`Error`] is about, see [`Error::category`].
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
+#[non_exhaustive]
+pub enum ErrorCategory {
+    /// Start and end tags, including their attributes.
+    Tag,
+    /// Comments, including bogus comments, CDATA sections and comment-like text in scripts.
+    Comment,
+    /// Doctypes.
+    Doctype,
+    /// Character references such as `&amp;` or `&#x26;`.
+    CharacterReference,
+    /// Characters in the input that are not allowed anywhere, such as control characters and
+    /// noncharacters, or bytes that are not text at all.
+    InputStream,
+    /// Errors that are not about the syntax of the document, such as
+    /// [`Error::LimitExceeded`].
+    Other,
+}
+
+#[cfg(feature = "error-strings")]
 impl std::fmt::Display for Error {
     /// Convert an enum variant back into the `kebap-case` error code as typically written
     /// in the WHATWG spec.
@@ -93,4 +191,101 @@ impl_error! {
     "unknown-named-character-reference" <=> UnknownNamedCharacterReference,
     "duplicate-attribute" <=> DuplicateAttribute,
     "control-character-in-input-stream" <=> ControlCharacterInInputStream,
+    // not part of the spec, see GarbagePolicy::SkipUntilPlausibleTag
+    "skipped-binary-garbage" <=> SkippedBinaryGarbage,
+    // not part of the spec, see emitters::callback::Limits
+    "limit-exceeded" <=> LimitExceeded,
+    // not part of the spec, see Utf8Policy::Error
+    "invalid-utf8-in-input-stream" <=> InvalidUtf8InInputStream,
+}
+
+/// Counts of all errors that occurred while tokenizing a document.
+///
+/// See [`crate::DefaultEmitter::collect_error_summary`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorSummary {
+    total: usize,
+    counts: [usize; Error::ALL.len()],
+    /// For each error code, the number of errors that occurred before its first occurrence.
+    first_occurrences: [Option<usize>; Error::ALL.len()],
+}
+
+impl Default for ErrorSummary {
+    fn default() -> Self {
+        ErrorSummary {
+            total: 0,
+            counts: [0; Error::ALL.len()],
+            first_occurrences: [None; Error::ALL.len()],
+        }
+    }
+}
+
+impl ErrorSummary {
+    pub(crate) fn record(&mut self, error: Error) {
+        let i = error as usize;
+        self.counts[i] += 1;
+        self.first_occurrences[i].get_or_insert(self.total);
+        self.total += 1;
+    }
+
+    /// The total number of errors.
+    #[must_use]
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// How often `error` occurred.
+    #[must_use]
+    pub fn count(&self, error: Error) -> usize {
+        self.counts[error as usize]
+    }
+
+    /// The first error that occurred.
+    #[must_use]
+    pub fn first_error(&self) -> Option<Error> {
+        self.iter().next().map(|(error, _)| error)
+    }
+
+    /// The position of the first occurrence of `error` among all errors, starting at zero. For
+    /// example, `Some(0)` means that `error` was the first error in the document.
+    #[must_use]
+    pub fn first_occurrence(&self, error: Error) -> Option<usize> {
+        self.first_occurrences[error as usize]
+    }
+
+    /// All errors that occurred at least once with their counts, ordered by first occurrence.
+    pub fn iter(&self) -> impl Iterator<Item = (Error, usize)> + '_ {
+        let mut errors: Vec<_> = Error::ALL
+            .iter()
+            .filter_map(|&error| Some((self.first_occurrence(error)?, error)))
+            .collect();
+        errors.sort_unstable_by_key(|&(first_occurrence, _)| first_occurrence);
+        errors
+            .into_iter()
+            .map(move |(_, error)| (error, self.count(error)))
+    }
+}
+
+#[cfg(feature = "error-strings")]
+impl std::fmt::Display for ErrorSummary {
+    /// A short report such as:
+    ///
+    /// ```text
+    /// 3 errors
+    ///   2 unexpected-null-character
+    ///   1 eof-in-tag
+    /// ```
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.total {
+            0 => write!(f, "no errors")?,
+            1 => write!(f, "1 error")?,
+            n => write!(f, "{} errors", n)?,
+        }
+
+        for (error, count) in self.iter() {
+            write!(f, "\n  {} {}", count, error)?;
+        }
+
+        Ok(())
+    }
 }
@@ -5,7 +5,16 @@ use std::ops::{Deref, DerefMut};
 /// A wrapper around a bytestring.
 ///
 /// This newtype only exists to provide a nicer `Debug` impl
+///
+/// Every non-empty `HtmlString` is a separate heap allocation. Emitters that borrow strings from
+/// their buffers instead, such as [`crate::emitters::buffered::BufferedEmitter`], avoid those. See
+/// `examples/allocations.rs` for a comparison.
 #[derive(Clone, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
 pub struct HtmlString(pub Vec<u8>);
 
 impl Deref for HtmlString {
@@ -23,6 +23,8 @@
 )]
 #![allow(clippy::module_name_repetitions)]
 #![allow(clippy::missing_errors_doc)]
+// The tokenizer must not panic on any input, see "Other features" in the README.
+#![cfg_attr(not(test), deny(clippy::unwrap_used, clippy::expect_used))]
 
 macro_rules! blob_url_prefix {
     () => {
@@ -38,27 +40,70 @@ macro_rules! blob_url_prefix {
 use blob_url_prefix;
 
 mod arrayvec;
+#[cfg(feature = "async-reader")]
+pub mod async_tokenizer;
+pub mod attributes;
+pub mod attrvalues;
 mod char_validator;
+pub mod charref;
+#[cfg(any(feature = "async-reader", feature = "wasm"))]
+mod chunked;
+pub mod conditional_comments;
+pub mod elements_iter;
 pub mod emitters;
 mod entities;
 mod error;
 mod htmlstring;
 mod machine;
 mod machine_helper;
+pub mod naive_sanitizer;
+pub mod nested;
+pub mod outline;
+pub mod parallel;
+pub mod push;
+pub mod quirks;
 mod read_helper;
-mod reader;
+pub mod reader;
+pub mod rewriter;
+pub mod serializer;
+mod span;
 mod state;
+pub mod stats;
+#[cfg(feature = "async-stream")]
+pub mod stream;
+pub mod structured;
+#[cfg(feature = "testing")]
+#[allow(clippy::unwrap_used, clippy::expect_used)] // test harness, not part of the tokenizer
+pub mod testing;
 mod tokenizer;
 mod utils;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
-#[cfg(debug_assertions)]
+#[cfg(any(debug_assertions, feature = "testing"))]
 #[doc(hidden)]
 pub mod testutils;
 
-pub use emitters::default::{DefaultEmitter, Doctype, EndTag, StartTag, Token};
-pub use emitters::{naive_next_state, Emitter};
-pub use error::Error;
+pub use attributes::AttributeMap;
+pub use emitters::callback::Limits;
+pub use emitters::default::{
+    Comment, DefaultEmitter, Doctype, EndTag, ErrorDetails, StartTag, Token,
+};
+pub use emitters::{
+    naive_next_state, AttributeValueSyntax, CommentKind, ContentStateSwitcher, Emitter,
+};
+pub use error::{Error, ErrorCategory, ErrorSummary};
 pub use htmlstring::HtmlString;
-pub use reader::{IoReader, Readable, Reader, StringReader};
+#[cfg(feature = "encoding")]
+pub use reader::DecodingReader;
+pub use reader::{
+    CharIterItem, CharIterReader, IoReader, Readable, Reader, RecordingError, RecordingReader,
+    SliceIterReader, StringReader, Utf8Reader, Utf8ReaderError,
+};
+pub use span::{LineIndex, Span};
 pub use state::State;
-pub use tokenizer::Tokenizer;
+pub use tokenizer::{
+    GarbagePolicy, NullHandling, Remainder, ResumeContext, Tokenizer, TokenizerSnapshot, UntilErr,
+    Utf8Policy,
+};
+pub use utils::is_void_element;
@@ -1,12 +1,12 @@
-use crate::entities::try_read_character_reference;
+use crate::charref::{is_left_alone_in_attribute, resolve_numeric, CharacterReferenceResolver};
 use crate::machine_helper::{
-    cont, emit_current_tag_and_switch_to, enter_state, eof, error, error_immediate, exit_state,
-    mutate_character_reference, read_byte, reconsume_in, reconsume_in_return_state, switch_to,
-    ControlToken,
+    cont, emit_content, emit_current_tag_and_switch_to, enter_state, eof, error, error_immediate,
+    exit_state, mutate_character_reference, read_byte, reconsume_in, reconsume_in_return_state,
+    skip_content, switch_to, ControlToken,
 };
 use crate::read_helper::{fast_read_char, slow_read_byte};
-use crate::utils::{ctostr, noncharacter_pat, surrogate_pat, with_lowercase_str};
-use crate::{Emitter, Error, Reader, Tokenizer};
+use crate::utils::{ctostr, with_lowercase_str};
+use crate::{AttributeValueSyntax, CommentKind, Emitter, Error, Reader, Tokenizer};
 
 macro_rules! define_state {
     ($state:ident, $slf:ident, $($body:tt)*) => {
@@ -15,7 +15,7 @@ macro_rules! define_state {
             use super::*;
 
             #[inline(always)]
-            pub(crate) fn run<R: Reader, E: Emitter>($slf: &mut Tokenizer<R, E>) -> Result<ControlToken<R, E>, R::Error> {
+            pub(crate) fn run<R: Reader, E: Emitter, C: CharacterReferenceResolver>($slf: &mut Tokenizer<R, E, C>) -> Result<ControlToken<R, E, C>, R::Error> {
                 $($body)*
             }
         }
@@ -33,11 +33,13 @@ pub(crate) mod states {
                     enter_state!(slf, CharacterReference, false)
                 }
                 Some(b"<") => {
+                    slf.emitter.set_token_start(slf.reader.position - 1);
                     switch_to!(slf, TagOpen)?.inline_next_state(slf)
                 }
                 Some(b"\0") => {
                     error!(slf, Error::UnexpectedNullCharacter);
-                    slf.emitter.emit_string(b"\0");
+                    slf.emitter
+                        .emit_string(slf.machine_helper.null_handling.replace(b"\0"));
                     cont!()
                 }
                 Some(xs) => {
@@ -51,7 +53,49 @@ pub(crate) mod states {
         )
     });
 
+    define_state!(SkipGarbage, slf, {
+        // see GarbagePolicy::SkipUntilPlausibleTag
+        let (skipped, tag) = slf.reader.skip_until_plausible_tag()?;
+        slf.machine_helper.skipped_garbage_len = skipped;
+        if let Some(tag) = tag {
+            slf.emitter
+                .set_token_start(slf.reader.position - tag.len() - 1);
+        }
+        if skipped > 0 {
+            error!(slf, Error::SkippedBinaryGarbage);
+        }
+
+        match tag {
+            Some(b"!DOCTYPE HTML") => {
+                slf.emitter.init_doctype();
+                slf.emitter.push_doctype_name(b"html");
+                switch_to!(slf, DoctypeName)
+            }
+            Some(b"!--") => {
+                slf.emitter.init_comment();
+                switch_to!(slf, CommentStart)
+            }
+            Some(name) => {
+                slf.emitter.init_start_tag();
+                let emitter = &mut slf.emitter;
+                with_lowercase_str(name, |x| {
+                    emitter.push_tag_name(x);
+                });
+                // the original case of the tag is not known here
+                emitter.push_tag_name_original(name);
+                switch_to!(slf, TagName)
+            }
+            None => {
+                eof!()
+            }
+        }
+    });
+
     define_state!(RcData, slf, {
+        if slf.machine_helper.skip_content {
+            return skip_content!(slf, RcDataLessThanSign);
+        }
+
         fast_read_char!(
             slf,
             match xs {
@@ -59,11 +103,16 @@ pub(crate) mod states {
                     enter_state!(slf, CharacterReference, false)
                 }
                 Some(b"<") => {
+                    slf.emitter.set_token_start(slf.reader.position - 1);
                     switch_to!(slf, RcDataLessThanSign)
                 }
                 Some(b"\0") => {
                     error!(slf, Error::UnexpectedNullCharacter);
-                    slf.emitter.emit_string("\u{fffd}".as_bytes());
+                    slf.emitter.emit_string(
+                        slf.machine_helper
+                            .null_handling
+                            .replace("\u{fffd}".as_bytes()),
+                    );
                     cont!()
                 }
                 Some(xs) => {
@@ -78,15 +127,24 @@ pub(crate) mod states {
     });
 
     define_state!(RawText, slf, {
+        if slf.machine_helper.skip_content {
+            return skip_content!(slf, RawTextLessThanSign);
+        }
+
         fast_read_char!(
             slf,
             match xs {
                 Some(b"<") => {
+                    slf.emitter.set_token_start(slf.reader.position - 1);
                     switch_to!(slf, RawTextLessThanSign)
                 }
                 Some(b"\0") => {
                     error!(slf, Error::UnexpectedNullCharacter);
-                    slf.emitter.emit_string("\u{fffd}".as_bytes());
+                    slf.emitter.emit_string(
+                        slf.machine_helper
+                            .null_handling
+                            .replace("\u{fffd}".as_bytes()),
+                    );
                     cont!()
                 }
                 Some(xs) => {
@@ -101,15 +159,24 @@ pub(crate) mod states {
     });
 
     define_state!(ScriptData, slf, {
+        if slf.machine_helper.skip_content {
+            return skip_content!(slf, ScriptDataLessThanSign);
+        }
+
         fast_read_char!(
             slf,
             match xs {
                 Some(b"<") => {
+                    slf.emitter.set_token_start(slf.reader.position - 1);
                     switch_to!(slf, ScriptDataLessThanSign)
                 }
                 Some(b"\0") => {
                     error!(slf, Error::UnexpectedNullCharacter);
-                    slf.emitter.emit_string("\u{fffd}".as_bytes());
+                    slf.emitter.emit_string(
+                        slf.machine_helper
+                            .null_handling
+                            .replace("\u{fffd}".as_bytes()),
+                    );
                     cont!()
                 }
                 Some(xs) => {
@@ -124,12 +191,25 @@ pub(crate) mod states {
     });
 
     define_state!(PlainText, slf, {
+        if slf.machine_helper.skip_content {
+            // PLAINTEXT never ends, so this discards the rest of the input
+            while slf
+                .reader
+                .skip_until_lt(&mut slf.validator, &mut slf.emitter)?
+            {}
+            return eof!();
+        }
+
         fast_read_char!(
             slf,
             match xs {
                 Some(b"\0") => {
                     error!(slf, Error::UnexpectedNullCharacter);
-                    slf.emitter.emit_string("\u{fffd}".as_bytes());
+                    slf.emitter.emit_string(
+                        slf.machine_helper
+                            .null_handling
+                            .replace("\u{fffd}".as_bytes()),
+                    );
                     cont!()
                 }
                 Some(xs) => {
@@ -160,6 +240,7 @@ pub(crate) mod states {
                 c @ Some(b'?') => {
                     error!(slf, Error::UnexpectedQuestionMarkInsteadOfTagName);
                     slf.emitter.init_comment();
+                    slf.emitter.set_comment_kind(CommentKind::Bogus);
                     reconsume_in!(slf, c, BogusComment)
                 }
                 None => {
@@ -167,6 +248,10 @@ pub(crate) mod states {
                     slf.emitter.emit_string(b"<");
                     eof!()
                 }
+                Some(x) if x >= 0x80 && slf.machine_helper.lenient_tag_names => {
+                    slf.emitter.init_start_tag();
+                    reconsume_in!(slf, Some(x), TagName)
+                }
                 c @ Some(_) => {
                     error!(slf, Error::InvalidFirstCharacterOfTagName);
                     slf.emitter.emit_string(b"<");
@@ -193,9 +278,14 @@ pub(crate) mod states {
                     slf.emitter.emit_string(b"</");
                     eof!()
                 }
+                Some(x) if x >= 0x80 && slf.machine_helper.lenient_tag_names => {
+                    slf.emitter.init_end_tag();
+                    reconsume_in!(slf, Some(x), TagName)
+                }
                 Some(x) => {
                     error!(slf, Error::InvalidFirstCharacterOfTagName);
                     slf.emitter.init_comment();
+                    slf.emitter.set_comment_kind(CommentKind::Bogus);
                     reconsume_in!(slf, Some(x), BogusComment)
                 }
             }
@@ -218,7 +308,16 @@ pub(crate) mod states {
                 }
                 Some(b"\0") => {
                     error!(slf, Error::UnexpectedNullCharacter);
-                    slf.emitter.push_tag_name("\u{fffd}".as_bytes());
+                    slf.emitter.push_tag_name(
+                        slf.machine_helper
+                            .null_handling
+                            .replace("\u{fffd}".as_bytes()),
+                    );
+                    slf.emitter.push_tag_name_original(
+                        slf.machine_helper
+                            .null_handling
+                            .replace("\u{fffd}".as_bytes()),
+                    );
                     cont!()
                 }
                 Some(xs) => {
@@ -226,6 +325,7 @@ pub(crate) mod states {
                     with_lowercase_str(xs, |x| {
                         emitter.push_tag_name(x);
                     });
+                    emitter.push_tag_name_original(xs);
 
                     cont!()
                 }
@@ -246,7 +346,7 @@ pub(crate) mod states {
                     switch_to!(slf, RcDataEndTagOpen)
                 }
                 c => {
-                    slf.emitter.emit_string(b"<");
+                    emit_content!(slf, b"<");
                     reconsume_in!(slf, c, RcData)
                 }
             }
@@ -262,7 +362,7 @@ pub(crate) mod states {
                     reconsume_in!(slf, Some(x), RcDataEndTagName)
                 }
                 c => {
-                    slf.emitter.emit_string(b"</");
+                    emit_content!(slf, b"</");
                     reconsume_in!(slf, c, RcData)
                 }
             }
@@ -285,13 +385,20 @@ pub(crate) mod states {
                     emit_current_tag_and_switch_to!(slf, Data)
                 }
                 Some(x) if x.is_ascii_alphabetic() => {
-                    slf.emitter.push_tag_name(&[x.to_ascii_lowercase()]);
-                    slf.machine_helper.temporary_buffer.push(x);
-                    cont!()
+                    if slf.machine_helper.is_end_tag_name_too_long(&slf.emitter) {
+                        slf.machine_helper
+                            .flush_end_tag_characters(&mut slf.emitter);
+                        reconsume_in!(slf, Some(x), RcData)
+                    } else {
+                        slf.emitter.push_tag_name(&[x.to_ascii_lowercase()]);
+                        slf.emitter.push_tag_name_original(&[x]);
+                        slf.machine_helper.temporary_buffer.push(x);
+                        cont!()
+                    }
                 }
                 c => {
-                    slf.emitter.emit_string(b"</");
-                    slf.machine_helper.flush_buffer_characters(&mut slf.emitter);
+                    slf.machine_helper
+                        .flush_end_tag_characters(&mut slf.emitter);
                     reconsume_in!(slf, c, RcData)
                 }
             }
@@ -307,7 +414,7 @@ pub(crate) mod states {
                     switch_to!(slf, RawTextEndTagOpen)
                 }
                 c => {
-                    slf.emitter.emit_string(b"<");
+                    emit_content!(slf, b"<");
                     reconsume_in!(slf, c, RawText)
                 }
             }
@@ -323,7 +430,7 @@ pub(crate) mod states {
                     reconsume_in!(slf, Some(x), RawTextEndTagName)
                 }
                 c => {
-                    slf.emitter.emit_string(b"</");
+                    emit_content!(slf, b"</");
                     reconsume_in!(slf, c, RawText)
                 }
             }
@@ -346,13 +453,20 @@ pub(crate) mod states {
                     emit_current_tag_and_switch_to!(slf, Data)
                 }
                 Some(x) if x.is_ascii_alphabetic() => {
-                    slf.emitter.push_tag_name(&[x.to_ascii_lowercase()]);
-                    slf.machine_helper.temporary_buffer.push(x);
-                    cont!()
+                    if slf.machine_helper.is_end_tag_name_too_long(&slf.emitter) {
+                        slf.machine_helper
+                            .flush_end_tag_characters(&mut slf.emitter);
+                        reconsume_in!(slf, Some(x), RawText)
+                    } else {
+                        slf.emitter.push_tag_name(&[x.to_ascii_lowercase()]);
+                        slf.emitter.push_tag_name_original(&[x]);
+                        slf.machine_helper.temporary_buffer.push(x);
+                        cont!()
+                    }
                 }
                 c => {
-                    slf.emitter.emit_string(b"</");
-                    slf.machine_helper.flush_buffer_characters(&mut slf.emitter);
+                    slf.machine_helper
+                        .flush_end_tag_characters(&mut slf.emitter);
                     reconsume_in!(slf, c, RawText)
                 }
             }
@@ -372,7 +486,7 @@ pub(crate) mod states {
                     switch_to!(slf, ScriptDataEscapeStart)
                 }
                 c => {
-                    slf.emitter.emit_string(b"<");
+                    emit_content!(slf, b"<");
                     reconsume_in!(slf, c, ScriptData)
                 }
             }
@@ -388,7 +502,7 @@ pub(crate) mod states {
                     reconsume_in!(slf, Some(x), ScriptDataEndTagName)
                 }
                 c => {
-                    slf.emitter.emit_string(b"</");
+                    emit_content!(slf, b"</");
                     reconsume_in!(slf, c, ScriptData)
                 }
             }
@@ -411,13 +525,20 @@ pub(crate) mod states {
                     emit_current_tag_and_switch_to!(slf, Data)
                 }
                 Some(x) if x.is_ascii_alphabetic() => {
-                    slf.emitter.push_tag_name(&[x.to_ascii_lowercase()]);
-                    slf.machine_helper.temporary_buffer.push(x);
-                    cont!()
+                    if slf.machine_helper.is_end_tag_name_too_long(&slf.emitter) {
+                        slf.machine_helper
+                            .flush_end_tag_characters(&mut slf.emitter);
+                        reconsume_in!(slf, Some(x), ScriptData)
+                    } else {
+                        slf.emitter.push_tag_name(&[x.to_ascii_lowercase()]);
+                        slf.emitter.push_tag_name_original(&[x]);
+                        slf.machine_helper.temporary_buffer.push(x);
+                        cont!()
+                    }
                 }
                 c => {
-                    slf.emitter.emit_string(b"</");
-                    slf.machine_helper.flush_buffer_characters(&mut slf.emitter);
+                    slf.machine_helper
+                        .flush_end_tag_characters(&mut slf.emitter);
                     reconsume_in!(slf, c, ScriptData)
                 }
             }
@@ -463,11 +584,16 @@ pub(crate) mod states {
                     switch_to!(slf, ScriptDataEscapedDash)
                 }
                 Some(b"<") => {
+                    slf.emitter.set_token_start(slf.reader.position - 1);
                     switch_to!(slf, ScriptDataEscapedLessThanSign)
                 }
                 Some(b"\0") => {
                     error!(slf, Error::UnexpectedNullCharacter);
-                    slf.emitter.emit_string("\u{fffd}".as_bytes());
+                    slf.emitter.emit_string(
+                        slf.machine_helper
+                            .null_handling
+                            .replace("\u{fffd}".as_bytes()),
+                    );
                     cont!()
                 }
                 Some(xs) => {
@@ -491,17 +617,21 @@ pub(crate) mod states {
                     switch_to!(slf, ScriptDataEscapedDashDash)
                 }
                 Some(b'<') => {
+                    slf.emitter.set_token_start(slf.reader.position - 1);
                     switch_to!(slf, ScriptDataEscapedLessThanSign)
                 }
                 Some(b'\0') => {
                     error!(slf, Error::UnexpectedNullCharacter);
-                    slf.emitter.emit_string("\u{fffd}".as_bytes());
-                    switch_to!(slf, ScriptDataEscaped)
-                }
-                Some(x) => {
-                    slf.emitter.emit_string(&[x]);
+                    slf.emitter.emit_string(
+                        slf.machine_helper
+                            .null_handling
+                            .replace("\u{fffd}".as_bytes()),
+                    );
                     switch_to!(slf, ScriptDataEscaped)
                 }
+                // reconsume instead of emitting x right away, as it may be the first byte of a
+                // multi-byte character
+                c @ Some(_) => reconsume_in!(slf, c, ScriptDataEscaped),
                 None => {
                     error!(slf, Error::EofInScriptHtmlCommentLikeText);
                     eof!()
@@ -519,6 +649,7 @@ pub(crate) mod states {
                     cont!()
                 }
                 Some(b'<') => {
+                    slf.emitter.set_token_start(slf.reader.position - 1);
                     switch_to!(slf, ScriptDataEscapedLessThanSign)
                 }
                 Some(b'>') => {
@@ -527,13 +658,14 @@ pub(crate) mod states {
                 }
                 Some(b'\0') => {
                     error!(slf, Error::UnexpectedNullCharacter);
-                    slf.emitter.emit_string("\u{fffd}".as_bytes());
-                    switch_to!(slf, ScriptDataEscaped)
-                }
-                Some(x) => {
-                    slf.emitter.emit_string(&[x]);
+                    slf.emitter.emit_string(
+                        slf.machine_helper
+                            .null_handling
+                            .replace("\u{fffd}".as_bytes()),
+                    );
                     switch_to!(slf, ScriptDataEscaped)
                 }
+                c @ Some(_) => reconsume_in!(slf, c, ScriptDataEscaped),
                 None => {
                     error!(slf, Error::EofInScriptHtmlCommentLikeText);
                     eof!()
@@ -595,9 +727,16 @@ pub(crate) mod states {
                     emit_current_tag_and_switch_to!(slf, Data)
                 }
                 Some(x) if x.is_ascii_alphabetic() => {
-                    slf.emitter.push_tag_name(&[x.to_ascii_lowercase()]);
-                    slf.machine_helper.temporary_buffer.extend(&[x]);
-                    cont!()
+                    if slf.machine_helper.is_end_tag_name_too_long(&slf.emitter) {
+                        slf.emitter.emit_string(b"</");
+                        slf.machine_helper.flush_buffer_characters(&mut slf.emitter);
+                        reconsume_in!(slf, Some(x), ScriptDataEscaped)
+                    } else {
+                        slf.emitter.push_tag_name(&[x.to_ascii_lowercase()]);
+                        slf.emitter.push_tag_name_original(&[x]);
+                        slf.machine_helper.temporary_buffer.push(x);
+                        cont!()
+                    }
                 }
                 c => {
                     slf.emitter.emit_string(b"</");
@@ -621,9 +760,7 @@ pub(crate) mod states {
                     }
                 }
                 Some(x) if x.is_ascii_alphabetic() => {
-                    slf.machine_helper
-                        .temporary_buffer
-                        .push(x.to_ascii_lowercase());
+                    slf.machine_helper.push_script_name_character(x);
                     slf.emitter.emit_string(&[x]);
                     cont!()
                 }
@@ -648,7 +785,11 @@ pub(crate) mod states {
                 }
                 Some(b"\0") => {
                     error!(slf, Error::UnexpectedNullCharacter);
-                    slf.emitter.emit_string("\u{fffd}".as_bytes());
+                    slf.emitter.emit_string(
+                        slf.machine_helper
+                            .null_handling
+                            .replace("\u{fffd}".as_bytes()),
+                    );
                     cont!()
                 }
                 Some(xs) => {
@@ -677,13 +818,14 @@ pub(crate) mod states {
                 }
                 Some(b'\0') => {
                     error!(slf, Error::UnexpectedNullCharacter);
-                    slf.emitter.emit_string("\u{fffd}".as_bytes());
-                    switch_to!(slf, ScriptDataDoubleEscaped)
-                }
-                Some(x) => {
-                    slf.emitter.emit_string(&[x]);
+                    slf.emitter.emit_string(
+                        slf.machine_helper
+                            .null_handling
+                            .replace("\u{fffd}".as_bytes()),
+                    );
                     switch_to!(slf, ScriptDataDoubleEscaped)
                 }
+                c @ Some(_) => reconsume_in!(slf, c, ScriptDataDoubleEscaped),
                 None => {
                     error!(slf, Error::EofInScriptHtmlCommentLikeText);
                     eof!()
@@ -710,13 +852,14 @@ pub(crate) mod states {
                 }
                 Some(b'\0') => {
                     error!(slf, Error::UnexpectedNullCharacter);
-                    slf.emitter.emit_string("\u{fffd}".as_bytes());
-                    switch_to!(slf, ScriptDataDoubleEscaped)
-                }
-                Some(x) => {
-                    slf.emitter.emit_string(&[x]);
+                    slf.emitter.emit_string(
+                        slf.machine_helper
+                            .null_handling
+                            .replace("\u{fffd}".as_bytes()),
+                    );
                     switch_to!(slf, ScriptDataDoubleEscaped)
                 }
+                c @ Some(_) => reconsume_in!(slf, c, ScriptDataDoubleEscaped),
                 None => {
                     error!(slf, Error::EofInScriptHtmlCommentLikeText);
                     eof!()
@@ -755,9 +898,7 @@ pub(crate) mod states {
                     }
                 }
                 Some(x) if x.is_ascii_alphabetic() => {
-                    slf.machine_helper
-                        .temporary_buffer
-                        .push(x.to_ascii_lowercase());
+                    slf.machine_helper.push_script_name_character(x);
                     slf.emitter.emit_string(&[x]);
                     cont!()
                 }
@@ -780,6 +921,7 @@ pub(crate) mod states {
                     error!(slf, Error::UnexpectedEqualsSignBeforeAttributeName);
                     slf.emitter.init_attribute();
                     slf.emitter.push_attribute_name("=".as_bytes());
+                    slf.emitter.push_attribute_name_original("=".as_bytes());
                     switch_to!(slf, AttributeName)
                 }
                 Some(x) => {
@@ -795,19 +937,30 @@ pub(crate) mod states {
             slf,
             match xs {
                 Some(b"\t" | b"\x0A" | b"\x0C" | b" " | b"/" | b">") => {
-                    reconsume_in!(slf, Some(xs.unwrap()[0]), AfterAttributeName)
+                    reconsume_in!(slf, Some(xs[0]), AfterAttributeName)
                 }
                 Some(b"=") => {
+                    slf.emitter.init_attribute_value();
                     switch_to!(slf, BeforeAttributeValue)?.inline_next_state(slf)
                 }
                 Some(b"\0") => {
                     error!(slf, Error::UnexpectedNullCharacter);
-                    slf.emitter.push_attribute_name("\u{fffd}".as_bytes());
+                    slf.emitter.push_attribute_name(
+                        slf.machine_helper
+                            .null_handling
+                            .replace("\u{fffd}".as_bytes()),
+                    );
+                    slf.emitter.push_attribute_name_original(
+                        slf.machine_helper
+                            .null_handling
+                            .replace("\u{fffd}".as_bytes()),
+                    );
                     cont!()
                 }
                 Some(b"\"" | b"'" | b"<") => {
                     error!(slf, Error::UnexpectedCharacterInAttributeName);
-                    slf.emitter.push_attribute_name(xs.unwrap());
+                    slf.emitter.push_attribute_name(xs);
+                    slf.emitter.push_attribute_name_original(xs);
                     cont!()
                 }
                 Some(xs) => {
@@ -815,6 +968,7 @@ pub(crate) mod states {
                     with_lowercase_str(xs, |xs| {
                         emitter.push_attribute_name(xs);
                     });
+                    emitter.push_attribute_name_original(xs);
                     cont!()
                 }
                 None => {
@@ -833,6 +987,7 @@ pub(crate) mod states {
                     switch_to!(slf, SelfClosingStartTag)
                 }
                 Some(b'=') => {
+                    slf.emitter.init_attribute_value();
                     switch_to!(slf, BeforeAttributeValue)
                 }
                 Some(b'>') => {
@@ -856,16 +1011,31 @@ pub(crate) mod states {
             match c {
                 Some(b'\t' | b'\x0A' | b'\x0C' | b' ') => cont!(),
                 Some(b'"') => {
+                    slf.emitter.set_attribute_value_start(slf.reader.position);
+                    slf.emitter
+                        .set_attribute_value_syntax(AttributeValueSyntax::DoubleQuoted);
                     switch_to!(slf, AttributeValueDoubleQuoted)?.inline_next_state(slf)
                 }
                 Some(b'\'') => {
+                    slf.emitter.set_attribute_value_start(slf.reader.position);
+                    slf.emitter
+                        .set_attribute_value_syntax(AttributeValueSyntax::SingleQuoted);
                     switch_to!(slf, AttributeValueSingleQuoted)
                 }
                 Some(b'>') => {
                     error!(slf, Error::MissingAttributeValue);
+                    slf.emitter
+                        .set_attribute_value_start(slf.reader.position - 1);
+                    slf.emitter
+                        .set_attribute_value_syntax(AttributeValueSyntax::Unquoted);
+                    slf.emitter.set_attribute_value_end(slf.reader.position - 1);
                     emit_current_tag_and_switch_to!(slf, Data)
                 }
                 c => {
+                    slf.emitter
+                        .set_attribute_value_start(slf.reader.position - usize::from(c.is_some()));
+                    slf.emitter
+                        .set_attribute_value_syntax(AttributeValueSyntax::Unquoted);
                     reconsume_in!(slf, c, AttributeValueUnquoted)
                 }
             }
@@ -877,6 +1047,7 @@ pub(crate) mod states {
             slf,
             match xs {
                 Some(b"\"") => {
+                    slf.emitter.set_attribute_value_end(slf.reader.position - 1);
                     switch_to!(slf, AfterAttributeValueQuoted)?.inline_next_state(slf)
                 }
                 Some(b"&") => {
@@ -884,7 +1055,11 @@ pub(crate) mod states {
                 }
                 Some(b"\0") => {
                     error!(slf, Error::UnexpectedNullCharacter);
-                    slf.emitter.push_attribute_value("\u{fffd}".as_bytes());
+                    slf.emitter.push_attribute_value(
+                        slf.machine_helper
+                            .null_handling
+                            .replace("\u{fffd}".as_bytes()),
+                    );
                     cont!()
                 }
                 Some(xs) => {
@@ -904,6 +1079,7 @@ pub(crate) mod states {
             slf,
             match xs {
                 Some(b"'") => {
+                    slf.emitter.set_attribute_value_end(slf.reader.position - 1);
                     switch_to!(slf, AfterAttributeValueQuoted)
                 }
                 Some(b"&") => {
@@ -911,7 +1087,11 @@ pub(crate) mod states {
                 }
                 Some(b"\0") => {
                     error!(slf, Error::UnexpectedNullCharacter);
-                    slf.emitter.push_attribute_value("\u{fffd}".as_bytes());
+                    slf.emitter.push_attribute_value(
+                        slf.machine_helper
+                            .null_handling
+                            .replace("\u{fffd}".as_bytes()),
+                    );
                     cont!()
                 }
                 Some(xs) => {
@@ -931,22 +1111,28 @@ pub(crate) mod states {
             slf,
             match xs {
                 Some(b"\t" | b"\x0A" | b"\x0C" | b" ") => {
+                    slf.emitter.set_attribute_value_end(slf.reader.position - 1);
                     switch_to!(slf, BeforeAttributeName)
                 }
                 Some(b"&") => {
                     enter_state!(slf, CharacterReference, true)
                 }
                 Some(b">") => {
+                    slf.emitter.set_attribute_value_end(slf.reader.position - 1);
                     emit_current_tag_and_switch_to!(slf, Data)
                 }
                 Some(b"\0") => {
                     error!(slf, Error::UnexpectedNullCharacter);
-                    slf.emitter.push_attribute_value("\u{fffd}".as_bytes());
+                    slf.emitter.push_attribute_value(
+                        slf.machine_helper
+                            .null_handling
+                            .replace("\u{fffd}".as_bytes()),
+                    );
                     cont!()
                 }
                 Some(b"\"" | b"'" | b"<" | b"=" | b"\x60") => {
                     error!(slf, Error::UnexpectedCharacterInUnquotedAttributeValue);
-                    slf.emitter.push_attribute_value(xs.unwrap());
+                    slf.emitter.push_attribute_value(xs);
                     cont!()
                 }
                 Some(xs) => {
@@ -1006,7 +1192,11 @@ pub(crate) mod states {
                 }
                 Some(b"\0") => {
                     error!(slf, Error::UnexpectedNullCharacter);
-                    slf.emitter.push_comment("\u{fffd}".as_bytes());
+                    slf.emitter.push_comment(
+                        slf.machine_helper
+                            .null_handling
+                            .replace("\u{fffd}".as_bytes()),
+                    );
                     cont!()
                 }
                 Some(xs) => {
@@ -1041,15 +1231,21 @@ pub(crate) mod states {
                         .reader
                         .try_read_string(&mut slf.validator, "CDATA[", true)? =>
                 {
-                    if slf
-                        .emitter
-                        .adjusted_current_node_present_but_not_in_html_namespace()
-                    {
+                    let in_foreign_content = match slf.machine_helper.in_foreign_content {
+                        Some(x) => x,
+                        None => slf
+                            .emitter
+                            .adjusted_current_node_present_but_not_in_html_namespace(),
+                    };
+
+                    if in_foreign_content {
+                        slf.emitter.start_cdata();
                         switch_to!(slf, CdataSection)
                     } else {
                         error!(slf, Error::CdataInHtmlContent);
 
                         slf.emitter.init_comment();
+                        slf.emitter.set_comment_kind(CommentKind::Cdata);
                         slf.emitter.push_comment(b"[CDATA[");
                         switch_to!(slf, BogusComment)
                     }
@@ -1057,6 +1253,7 @@ pub(crate) mod states {
                 c => {
                     error!(slf, Error::IncorrectlyOpenedComment);
                     slf.emitter.init_comment();
+                    slf.emitter.set_comment_kind(CommentKind::Bogus);
                     reconsume_in!(slf, c, BogusComment)
                 }
             }
@@ -1120,7 +1317,11 @@ pub(crate) mod states {
                 }
                 Some(b"\0") => {
                     error!(slf, Error::UnexpectedNullCharacter);
-                    slf.emitter.push_comment("\u{fffd}".as_bytes());
+                    slf.emitter.push_comment(
+                        slf.machine_helper
+                            .null_handling
+                            .replace("\u{fffd}".as_bytes()),
+                    );
                     cont!()
                 }
                 Some(xs) => {
@@ -1305,7 +1506,11 @@ pub(crate) mod states {
                 Some(b'\0') => {
                     error!(slf, Error::UnexpectedNullCharacter);
                     slf.emitter.init_doctype();
-                    slf.emitter.push_doctype_name("\u{fffd}".as_bytes());
+                    slf.emitter.push_doctype_name(
+                        slf.machine_helper
+                            .null_handling
+                            .replace("\u{fffd}".as_bytes()),
+                    );
                     switch_to!(slf, DoctypeName)
                 }
                 Some(b'>') => {
@@ -1344,7 +1549,11 @@ pub(crate) mod states {
                 }
                 Some(b"\0") => {
                     error!(slf, Error::UnexpectedNullCharacter);
-                    slf.emitter.push_doctype_name("\u{fffd}".as_bytes());
+                    slf.emitter.push_doctype_name(
+                        slf.machine_helper
+                            .null_handling
+                            .replace("\u{fffd}".as_bytes()),
+                    );
                     cont!()
                 }
                 Some(xs) => {
@@ -1483,8 +1692,11 @@ pub(crate) mod states {
                 }
                 Some(b"\0") => {
                     error!(slf, Error::UnexpectedNullCharacter);
-                    slf.emitter
-                        .push_doctype_public_identifier("\u{fffd}".as_bytes());
+                    slf.emitter.push_doctype_public_identifier(
+                        slf.machine_helper
+                            .null_handling
+                            .replace("\u{fffd}".as_bytes()),
+                    );
                     cont!()
                 }
                 Some(b">") => {
@@ -1516,8 +1728,11 @@ pub(crate) mod states {
                 }
                 Some(b"\0") => {
                     error!(slf, Error::UnexpectedNullCharacter);
-                    slf.emitter
-                        .push_doctype_public_identifier("\u{fffd}".as_bytes());
+                    slf.emitter.push_doctype_public_identifier(
+                        slf.machine_helper
+                            .null_handling
+                            .replace("\u{fffd}".as_bytes()),
+                    );
                     cont!()
                 }
                 Some(b">") => {
@@ -1695,8 +1910,11 @@ pub(crate) mod states {
                 }
                 Some(b"\0") => {
                     error!(slf, Error::UnexpectedNullCharacter);
-                    slf.emitter
-                        .push_doctype_system_identifier("\u{fffd}".as_bytes());
+                    slf.emitter.push_doctype_system_identifier(
+                        slf.machine_helper
+                            .null_handling
+                            .replace("\u{fffd}".as_bytes()),
+                    );
                     cont!()
                 }
                 Some(b">") => {
@@ -1728,8 +1946,11 @@ pub(crate) mod states {
                 }
                 Some(b"\0") => {
                     error!(slf, Error::UnexpectedNullCharacter);
-                    slf.emitter
-                        .push_doctype_system_identifier("\u{fffd}".as_bytes());
+                    slf.emitter.push_doctype_system_identifier(
+                        slf.machine_helper
+                            .null_handling
+                            .replace("\u{fffd}".as_bytes()),
+                    );
                     cont!()
                 }
                 Some(b">") => {
@@ -1811,6 +2032,7 @@ pub(crate) mod states {
                 }
                 None => {
                     error!(slf, Error::EofInCdata);
+                    slf.emitter.end_cdata();
                     eof!()
                 }
             }
@@ -1841,6 +2063,7 @@ pub(crate) mod states {
                     cont!()
                 }
                 Some(b'>') => {
+                    slf.emitter.end_cdata();
                     switch_to!(slf, Data)
                 }
                 c => {
@@ -1875,47 +2098,126 @@ pub(crate) mod states {
     });
 
     define_state!(NamedCharacterReference, slf, {
-        let c = read_byte!(slf)?;
-
-        let char_ref = match c {
-            Some(x) => try_read_character_reference(x as char, |x| {
-                slf.reader.try_read_string(&mut slf.validator, x, true)
-            })?
-            .map(|char_ref| (x, char_ref)),
-
-            None => None,
-        };
-
-        if let Some((x, char_ref)) = char_ref {
-            let char_ref_name_last_character = char_ref.name.chars().last();
-            let next_character = read_byte!(slf)?;
-
-            if !slf.machine_helper.is_consumed_as_part_of_an_attribute()
-                || char_ref_name_last_character == Some(';')
-                || !matches!(next_character, Some(x) if x == b'=' || x.is_ascii_alphanumeric())
-            {
-                if char_ref_name_last_character != Some(';') {
-                    error!(slf, Error::MissingSemicolonAfterCharacterReference);
+        // The candidate name is collected in the temporary buffer, after the `&`. The byte that
+        // ended it, if any, is reconsumed in the return state.
+        let name_start = slf.reader.position;
+        let mut terminator = None;
+        // The byte after the name is consumed by a later state, so its errors are held back
+        // until then.
+        let mut terminator_start = name_start;
+        slf.validator.hold_errors();
+        while slf.machine_helper.temporary_buffer.len() <= C::MAX_NAME_LEN {
+            terminator_start = slf.reader.position;
+            match read_byte!(slf)? {
+                Some(x) if x.is_ascii_alphanumeric() => {
+                    slf.machine_helper.temporary_buffer.push(x);
+                }
+                Some(b';') => {
+                    slf.machine_helper.temporary_buffer.push(b';');
+                    break;
+                }
+                c => {
+                    terminator = Some(c);
+                    break;
+                }
+            }
+        }
+
+        let is_attribute = slf.machine_helper.is_consumed_as_part_of_an_attribute();
+        let candidate = &slf.machine_helper.temporary_buffer[1..];
+        let char_ref = slf.entity_resolver.resolve_longest(candidate, is_attribute);
+
+        // Whatever was read after the name is tokenized as usual, see `rest` below. Until then,
+        // positions are reported as if it hadn't been read yet, so that errors end up in the same
+        // place as without the lookahead.
+        let mut rest;
+        // where the part of the input that is flushed first ends
+        let reference_end;
+        let mut unknown_named_character_reference = false;
+        let mut cut_off = false;
+        if let Some((name_len, characters)) = char_ref {
+            let has_semicolon = candidate[name_len - 1] == b';';
+            let next_character = match candidate.get(name_len) {
+                Some(&x) => Some(x),
+                None => match terminator {
+                    Some(c) => c,
+                    None => {
+                        terminator_start = slf.reader.position;
+                        let c = read_byte!(slf)?;
+                        terminator = Some(c);
+                        c
+                    }
+                },
+            };
+            if candidate.len() == name_len {
+                // the byte after the name is the next character, which is read before the
+                // reference is resolved
+                slf.emitter.set_position(terminator_start);
+                slf.validator.release_held_errors(&mut slf.emitter);
+            }
+
+            rest = slf.machine_helper.temporary_buffer.split_off(name_len + 1);
+            reference_end = name_start + name_len;
+            slf.emitter
+                .set_position(name_start + name_len + usize::from(next_character.is_some()));
+
+            if !is_attribute || has_semicolon || !is_left_alone_in_attribute(next_character) {
+                if !has_semicolon {
+                    if rest.is_empty() {
+                        error!(slf, Error::MissingSemicolonAfterCharacterReference);
+                    } else {
+                        // the next character is ASCII, so the error doesn't have to wait for it
+                        error_immediate!(slf, Error::MissingSemicolonAfterCharacterReference);
+                    }
                 }
 
                 slf.machine_helper.temporary_buffer.clear();
                 slf.machine_helper
                     .temporary_buffer
-                    .extend(char_ref.characters.as_bytes());
-            } else {
-                slf.machine_helper.temporary_buffer.extend(&[x]);
-                slf.machine_helper
-                    .temporary_buffer
-                    .extend(char_ref.name.as_bytes());
+                    .extend(characters.as_bytes());
             }
+        } else {
+            // what the ambiguous ampersand state would do
+            rest = slf.machine_helper.temporary_buffer.split_off(1);
+            reference_end = name_start;
+            unknown_named_character_reference = rest.last() == Some(&b';');
+            if unknown_named_character_reference {
+                rest.pop();
+            } else if terminator.is_none() {
+                // there may be more alphanumeric characters after `MAX_NAME_LEN`
+                cut_off = true;
+            }
+            slf.emitter.set_position(name_start + 1);
+        }
 
+        if !slf.machine_helper.temporary_buffer.is_empty() {
+            // errors have been emitted already, the position is only relevant for callbacks
+            // that receive strings as they are emitted
+            slf.emitter.set_position(reference_end);
             slf.machine_helper
                 .flush_code_points_consumed_as_character_reference(&mut slf.emitter);
-            reconsume_in_return_state!(slf, next_character)
-        } else {
+        }
+        slf.emitter.set_position(slf.reader.position);
+        if !rest.is_empty() {
+            slf.machine_helper.temporary_buffer.extend(&rest);
+            slf.machine_helper
+                .flush_code_points_consumed_as_character_reference(&mut slf.emitter);
+        }
+
+        if unknown_named_character_reference {
+            error!(slf, Error::UnknownNamedCharacterReference);
+            slf.machine_helper.temporary_buffer.push(b';');
             slf.machine_helper
                 .flush_code_points_consumed_as_character_reference(&mut slf.emitter);
-            reconsume_in!(slf, c, AmbiguousAmpersand)
+        }
+
+        slf.emitter.set_position(terminator_start);
+        slf.validator.release_held_errors(&mut slf.emitter);
+        slf.emitter.set_position(slf.reader.position);
+        match terminator {
+            Some(c) => reconsume_in_return_state!(slf, c),
+            None if cut_off => switch_to!(slf, AmbiguousAmpersand),
+            None => exit_state!(slf),
         }
     });
 
@@ -2030,67 +2332,17 @@ pub(crate) mod states {
     });
 
     define_state!(NumericCharacterReferenceEnd, slf, {
-        match slf.machine_helper.character_reference_code {
-            0x00 => {
-                error!(slf, Error::NullCharacterReference);
-                slf.machine_helper.character_reference_code = 0xfffd;
-            }
-            0x0011_0000.. => {
-                error!(slf, Error::CharacterReferenceOutsideUnicodeRange);
-                slf.machine_helper.character_reference_code = 0xfffd;
-            }
-            surrogate_pat!() => {
-                error!(slf, Error::SurrogateCharacterReference);
-                slf.machine_helper.character_reference_code = 0xfffd;
-            }
-            // noncharacter
-            noncharacter_pat!() => {
-                error!(slf, Error::NoncharacterCharacterReference);
-            }
-            // 0x000d, or a control that is not whitespace
-            x @ (0x000d | 0x0d | 0x0000..=0x001f | 0x007f..=0x009f)
-                if !matches!(x, 0x0009 | 0x000a | 0x000c | 0x0020) =>
-            {
-                error!(slf, Error::ControlCharacterReference);
-                slf.machine_helper.character_reference_code = match x {
-                    0x80 => 0x20AC, // EURO SIGN (€)
-                    0x82 => 0x201A, // SINGLE LOW-9 QUOTATION MARK (‚)
-                    0x83 => 0x0192, // LATIN SMALL LETTER F WITH HOOK (ƒ)
-                    0x84 => 0x201E, // DOUBLE LOW-9 QUOTATION MARK („)
-                    0x85 => 0x2026, // HORIZONTAL ELLIPSIS (…)
-                    0x86 => 0x2020, // DAGGER (†)
-                    0x87 => 0x2021, // DOUBLE DAGGER (‡)
-                    0x88 => 0x02C6, // MODIFIER LETTER CIRCUMFLEX ACCENT (ˆ)
-                    0x89 => 0x2030, // PER MILLE SIGN (‰)
-                    0x8A => 0x0160, // LATIN CAPITAL LETTER S WITH CARON (Š)
-                    0x8B => 0x2039, // SINGLE LEFT-POINTING ANGLE QUOTATION MARK (‹)
-                    0x8C => 0x0152, // LATIN CAPITAL LIGATURE OE (Œ)
-                    0x8E => 0x017D, // LATIN CAPITAL LETTER Z WITH CARON (Ž)
-                    0x91 => 0x2018, // LEFT SINGLE QUOTATION MARK (‘)
-                    0x92 => 0x2019, // RIGHT SINGLE QUOTATION MARK (’)
-                    0x93 => 0x201C, // LEFT DOUBLE QUOTATION MARK (“)
-                    0x94 => 0x201D, // RIGHT DOUBLE QUOTATION MARK (”)
-                    0x95 => 0x2022, // BULLET (•)
-                    0x96 => 0x2013, // EN DASH (–)
-                    0x97 => 0x2014, // EM DASH (—)
-                    0x98 => 0x02DC, // SMALL TILDE (˜)
-                    0x99 => 0x2122, // TRADE MARK SIGN (™)
-                    0x9A => 0x0161, // LATIN SMALL LETTER S WITH CARON (š)
-                    0x9B => 0x203A, // SINGLE RIGHT-POINTING ANGLE QUOTATION MARK (›)
-                    0x9C => 0x0153, // LATIN SMALL LIGATURE OE (œ)
-                    0x9E => 0x017E, // LATIN SMALL LETTER Z WITH CARON (ž)
-                    0x9F => 0x0178, // LATIN CAPITAL LETTER Y WITH DIAERESIS (Ÿ)
-                    _ => slf.machine_helper.character_reference_code,
-                };
-            }
-            _ => (),
+        let (c, error) = resolve_numeric(slf.machine_helper.character_reference_code);
+        if let Some(error) = error {
+            error!(slf, error);
         }
 
         slf.machine_helper.temporary_buffer.clear();
-        slf.machine_helper.temporary_buffer.extend(
-            ctostr!(std::char::from_u32(slf.machine_helper.character_reference_code).unwrap())
-                .as_bytes(),
-        );
+        slf.machine_helper
+            .temporary_buffer
+            .extend(ctostr!(c).as_bytes());
+        // the character after the reference may have been reconsumed
+        slf.emitter.set_position(slf.reader.position);
         slf.machine_helper
             .flush_code_points_consumed_as_character_reference(&mut slf.emitter);
         exit_state!(slf)
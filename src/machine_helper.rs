@@ -1,36 +1,53 @@
-use crate::utils::trace_log;
-use crate::{Emitter, Reader, State, Tokenizer};
+use std::fmt::{self, Debug, Formatter};
 
-#[derive(Debug)]
-pub(crate) struct MachineState<R: Reader, E: Emitter> {
+use crate::charref::CharacterReferenceResolver;
+use crate::utils::{trace_log, DebugBytes};
+use crate::{Emitter, NullHandling, Reader, State, Tokenizer};
+
+pub(crate) struct MachineState<R: Reader, E: Emitter, C: CharacterReferenceResolver> {
     #[allow(clippy::type_complexity)]
-    pub function: fn(&mut Tokenizer<R, E>) -> Result<ControlToken<R, E>, R::Error>,
-    #[cfg(debug_assertions)]
+    pub function: fn(&mut Tokenizer<R, E, C>) -> Result<ControlToken<R, E, C>, R::Error>,
+    #[cfg(any(debug_assertions, feature = "trace"))]
     pub debug_name: &'static str,
 }
 
-impl<R: Reader, E: Emitter> Copy for MachineState<R, E> {}
-impl<R: Reader, E: Emitter> Clone for MachineState<R, E> {
+impl<R: Reader, E: Emitter, C: CharacterReferenceResolver> Debug for MachineState<R, E, C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // state names are not kept around in release builds, to keep this struct small
+        #[cfg(any(debug_assertions, feature = "trace"))]
+        return f.write_str(self.debug_name);
+
+        #[cfg(not(any(debug_assertions, feature = "trace")))]
+        return f.write_str("<unknown state>");
+    }
+}
+
+impl<R: Reader, E: Emitter, C: CharacterReferenceResolver> Copy for MachineState<R, E, C> {}
+impl<R: Reader, E: Emitter, C: CharacterReferenceResolver> Clone for MachineState<R, E, C> {
     fn clone(&self) -> Self {
         *self
     }
 }
 
-pub(crate) enum ControlToken<R: Reader, E: Emitter> {
+pub(crate) enum ControlToken<R: Reader, E: Emitter, C: CharacterReferenceResolver> {
     Eof,
     Continue,
-    SwitchTo(MachineState<R, E>),
+    SwitchTo(MachineState<R, E, C>),
 }
 
-impl<R: Reader, E: Emitter> ControlToken<R, E> {
+impl<R: Reader, E: Emitter, C: CharacterReferenceResolver> ControlToken<R, E, C> {
+    /// Switch to the next state and run it right away, instead of returning to the main loop.
+    ///
+    /// The result of the next state is returned as-is, including any state switch it did itself
+    /// (see `emit_current_tag_and_switch_to`).
     #[inline(always)]
     pub(crate) fn inline_next_state(
         self,
-        tokenizer: &mut Tokenizer<R, E>,
+        tokenizer: &mut Tokenizer<R, E, C>,
     ) -> Result<Self, R::Error> {
         match self {
             ControlToken::SwitchTo(state) => {
-                tokenizer.machine_helper.switch_to(state);
+                tokenizer.switch_to(state);
                 (state.function)(tokenizer)
             }
             _ => {
@@ -45,8 +62,8 @@ impl<R: Reader, E: Emitter> ControlToken<R, E> {
 }
 
 #[allow(clippy::from_over_into)]
-impl<R: Reader, E: Emitter> Into<MachineState<R, E>> for State {
-    fn into(self) -> MachineState<R, E> {
+impl<R: Reader, E: Emitter, C: CharacterReferenceResolver> Into<MachineState<R, E, C>> for State {
+    fn into(self) -> MachineState<R, E, C> {
         // TODO: instead of this conversion, can we rig the enums to be of same layout?
         match self {
             State::Data => state_ref!(Data),
@@ -54,32 +71,85 @@ impl<R: Reader, E: Emitter> Into<MachineState<R, E>> for State {
             State::RcData => state_ref!(RcData),
             State::RawText => state_ref!(RawText),
             State::ScriptData => state_ref!(ScriptData),
+            State::ScriptDataEscaped => state_ref!(ScriptDataEscaped),
+            State::ScriptDataDoubleEscaped => state_ref!(ScriptDataDoubleEscaped),
             State::CdataSection => state_ref!(CdataSection),
         }
     }
 }
 
-#[derive(Debug)]
-pub(crate) struct MachineHelper<R: Reader, E: Emitter> {
-    // XXX: allocation that cannot be controlled/reused by the user
+pub(crate) struct MachineHelper<R: Reader, E: Emitter, C: CharacterReferenceResolver> {
+    /// The spec's temporary buffer, also used for character references. Its length is bounded by
+    /// `CharacterReferenceResolver::MAX_NAME_LEN` plus a few bytes, or by the length of the last
+    /// start tag, see `is_end_tag_name_too_long`. `testing::memory` checks that.
     pub(crate) temporary_buffer: Vec<u8>,
     pub(crate) character_reference_code: u32,
-    pub(crate) state: MachineState<R, E>,
-    return_state: Option<(MachineState<R, E>, bool)>,
+    pub(crate) state: MachineState<R, E, C>,
+    return_state: Option<(MachineState<R, E, C>, bool)>,
+    /// The state the tokenizer was switched to after the last emitted tag, or by
+    /// `Tokenizer::set_state`. Used for `Tokenizer::context_at_last_token`.
+    pub(crate) last_tag_state: State,
+    /// Overrides the emitter's answer to `adjusted_current_node_present_but_not_in_html_namespace`
+    /// until the next tag is emitted. Set by `Tokenizer::resume_at`.
+    pub(crate) in_foreign_content: Option<bool>,
+    /// How many bytes were skipped by `GarbagePolicy::SkipUntilPlausibleTag`.
+    pub(crate) skipped_garbage_len: usize,
+    /// Whether the input starts in the `SkipGarbage` state, see `Tokenizer::binary_garbage_mode`.
+    pub(crate) skip_garbage: bool,
+    /// See `Tokenizer::lenient_tag_names`.
+    pub(crate) lenient_tag_names: bool,
+    /// See `Tokenizer::null_handling`.
+    pub(crate) null_handling: NullHandling,
+    /// The offset right after the last emitted tag, or where `Tokenizer::set_state` was called.
+    /// Taken by `Tokenizer::save_state` as a checkpoint if nothing was read after it.
+    pub(crate) checkpoint_at: Option<usize>,
+    /// The offset right after the last emitted tag, see `Tokenizer::last_tag_end`.
+    pub(crate) last_tag_end: Option<usize>,
+    /// Whether the content of the last start tag is skipped, see `Emitter::should_skip_rawtext`.
+    pub(crate) skip_content: bool,
+}
+
+impl<R: Reader, E: Emitter, C: CharacterReferenceResolver> Debug for MachineHelper<R, E, C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MachineHelper")
+            .field("state", &self.state)
+            .field("return_state", &self.return_state)
+            .field("temporary_buffer", &DebugBytes(&self.temporary_buffer))
+            .field("character_reference_code", &self.character_reference_code)
+            .field("last_tag_state", &self.last_tag_state)
+            .field("in_foreign_content", &self.in_foreign_content)
+            .field("skipped_garbage_len", &self.skipped_garbage_len)
+            .field("skip_garbage", &self.skip_garbage)
+            .field("lenient_tag_names", &self.lenient_tag_names)
+            .field("null_handling", &self.null_handling)
+            .field("checkpoint_at", &self.checkpoint_at)
+            .field("last_tag_end", &self.last_tag_end)
+            .field("skip_content", &self.skip_content)
+            .finish()
+    }
 }
 
-impl<R: Reader, E: Emitter> Default for MachineHelper<R, E> {
+impl<R: Reader, E: Emitter, C: CharacterReferenceResolver> Default for MachineHelper<R, E, C> {
     fn default() -> Self {
         MachineHelper {
             temporary_buffer: Vec::new(),
             character_reference_code: 0,
             state: state_ref!(Data),
             return_state: None,
+            last_tag_state: State::Data,
+            in_foreign_content: None,
+            skipped_garbage_len: 0,
+            skip_garbage: false,
+            lenient_tag_names: false,
+            null_handling: NullHandling::Spec,
+            checkpoint_at: None,
+            last_tag_end: None,
+            skip_content: false,
         }
     }
 }
 
-impl<R: Reader, E: Emitter> MachineHelper<R, E> {
+impl<R: Reader, E: Emitter, C: CharacterReferenceResolver> MachineHelper<R, E, C> {
     pub(crate) fn is_consumed_as_part_of_an_attribute(&self) -> bool {
         match self.return_state {
             Some((_state, is_attribute)) => is_attribute,
@@ -101,22 +171,113 @@ impl<R: Reader, E: Emitter> MachineHelper<R, E> {
         self.temporary_buffer.clear();
     }
 
-    pub(crate) fn enter_state(&mut self, state: MachineState<R, E>, is_attribute: bool) {
+    /// Emit `</` and the end tag name in the temporary buffer as text, when they turned out not to
+    /// be an appropriate end tag. Nothing is emitted if the content is skipped.
+    pub(crate) fn flush_end_tag_characters(&mut self, emitter: &mut E) {
+        if !self.skip_content {
+            emitter.emit_string(b"</");
+            emitter.emit_string(&self.temporary_buffer);
+        }
+        self.temporary_buffer.clear();
+    }
+
+    /// Whether the end tag name in the temporary buffer can't be an appropriate end tag anymore
+    /// once another character is appended, because it is as long as the last start tag already.
+    ///
+    /// The end tag name states then flush the name as text right away instead of waiting for the
+    /// end of the name, which results in the same characters. This keeps the temporary buffer
+    /// bounded by the length of the last start tag, for emitters that report it.
+    pub(crate) fn is_end_tag_name_too_long(&self, emitter: &E) -> bool {
+        emitter
+            .last_start_tag()
+            .is_some_and(|name| self.temporary_buffer.len() >= name.len())
+    }
+
+    /// Append a character to the name that is compared with `script` in the script data double
+    /// escape states. Characters beyond the length of `script` are dropped, as the name can't be
+    /// `script` anymore at that point.
+    pub(crate) fn push_script_name_character(&mut self, x: u8) {
+        if self.temporary_buffer.len() <= b"script".len() {
+            self.temporary_buffer.push(x.to_ascii_lowercase());
+        }
+    }
+
+    /// Remember the current state as the return state, for `enter_state!`.
+    pub(crate) fn push_return_state(&mut self, is_attribute: bool) {
         debug_assert!(self.return_state.is_none());
         self.return_state = Some((self.state, is_attribute));
-        self.switch_to(state);
     }
 
-    pub(crate) fn pop_return_state(&mut self) -> MachineState<R, E> {
-        self.return_state.take().unwrap().0
+    /// Forget about the current state, for `Tokenizer::set_state`.
+    pub(crate) fn reset_state(&mut self, state: State) {
+        self.switch_to(state.into());
+        self.return_state = None;
+        self.temporary_buffer.clear();
+        self.last_tag_state = state;
+        self.skip_content = false;
     }
 
-    pub(crate) fn exit_state(&mut self) {
-        let state = self.pop_return_state();
-        self.switch_to(state);
+    pub(crate) fn pop_return_state(&mut self) -> MachineState<R, E, C> {
+        // Every state that exits to the return state is only reachable through `enter_state`. Should
+        // that ever not hold, continue in the data state rather than panicking.
+        debug_assert!(self.return_state.is_some());
+        self.return_state
+            .take()
+            .map_or_else(|| State::Data.into(), |(state, _)| state)
     }
 
-    pub(crate) fn switch_to(&mut self, state: MachineState<R, E>) {
+    /// Convert to a machine for another resolver or emitter type, for
+    /// `Tokenizer::set_entity_resolver` and `Tokenizer::set_trace`.
+    ///
+    /// States can't be converted in general, so the machine starts over in the state of the last
+    /// tag, or the one from `Tokenizer::set_state`.
+    pub(crate) fn with_types<E2: Emitter, C2: CharacterReferenceResolver>(
+        self,
+    ) -> MachineHelper<R, E2, C2> {
+        debug_assert!(self.return_state.is_none());
+        MachineHelper {
+            temporary_buffer: self.temporary_buffer,
+            character_reference_code: self.character_reference_code,
+            state: self.last_tag_state.into(),
+            return_state: None,
+            last_tag_state: self.last_tag_state,
+            in_foreign_content: self.in_foreign_content,
+            skipped_garbage_len: self.skipped_garbage_len,
+            skip_garbage: self.skip_garbage,
+            lenient_tag_names: self.lenient_tag_names,
+            null_handling: self.null_handling,
+            checkpoint_at: self.checkpoint_at,
+            last_tag_end: self.last_tag_end,
+            skip_content: self.skip_content,
+        }
+    }
+
+    /// Start over at the beginning of another input, for `Tokenizer::reset_with_reader`. Options
+    /// are kept, and so is the capacity of the temporary buffer.
+    pub(crate) fn reset<R2: Reader>(mut self) -> MachineHelper<R2, E, C> {
+        self.temporary_buffer.clear();
+        MachineHelper {
+            temporary_buffer: self.temporary_buffer,
+            character_reference_code: 0,
+            state: if self.skip_garbage {
+                state_ref!(SkipGarbage)
+            } else {
+                state_ref!(Data)
+            },
+            return_state: None,
+            last_tag_state: State::Data,
+            in_foreign_content: None,
+            skipped_garbage_len: 0,
+            skip_garbage: self.skip_garbage,
+            lenient_tag_names: self.lenient_tag_names,
+            null_handling: self.null_handling,
+            checkpoint_at: None,
+            last_tag_end: None,
+            skip_content: false,
+        }
+    }
+
+    pub(crate) fn switch_to(&mut self, state: MachineState<R, E, C>) {
         trace_log!(
             "switch_to: {} -> {}",
             self.state.debug_name,
@@ -130,7 +291,7 @@ macro_rules! state_ref {
     ($state:ident) => {{
         crate::machine_helper::MachineState {
             function: crate::machine::states::$state::run,
-            #[cfg(debug_assertions)]
+            #[cfg(any(debug_assertions, feature = "trace"))]
             debug_name: stringify!($state),
         }
     }};
@@ -138,33 +299,42 @@ macro_rules! state_ref {
 
 pub(crate) use state_ref;
 
+/// Append a digit to the character reference code.
+///
+/// The code saturates at 0x110000, the first value outside of the Unicode range, so that any
+/// number of digits results in `CharacterReferenceOutsideUnicodeRange` instead of an overflow.
 macro_rules! mutate_character_reference {
     ($slf:expr, * $mul:literal + $x:ident - $sub:literal) => {
-        match $slf
+        $slf.machine_helper.character_reference_code = $slf
             .machine_helper
             .character_reference_code
-            .checked_mul($mul)
-            .and_then(|cr| cr.checked_add($x as u32 - $sub))
-        {
-            Some(cr) => $slf.machine_helper.character_reference_code = cr,
-            None => {
-                // provoke err
-                $slf.machine_helper.character_reference_code = 0x110000;
-            }
-        };
+            .saturating_mul($mul)
+            .saturating_add($x as u32 - $sub)
+            .min(0x0011_0000);
     };
 }
 
 pub(crate) use mutate_character_reference;
 
+/// Emit the current tag and switch to `$state`, or to the state returned by the emitter.
+///
+/// The emitter's state always takes precedence and is applied exactly once, here. This has to be
+/// the last thing a state function does, and it evaluates to `Continue` rather than `SwitchTo`.
+/// That way, when it's reached through a chain of `inline_next_state` calls, the `Continue` is
+/// passed up unchanged and none of the calling states can switch to another state afterwards.
 macro_rules! emit_current_tag_and_switch_to {
     ($slf:expr, $state:ident) => {{
-        let state = $slf.emitter.emit_current_tag().map(Into::into);
+        let state = $slf.emitter.emit_current_tag();
         if state.is_some() {
             crate::utils::trace_log!("emitter asked for state switch:");
         }
-        $slf.machine_helper
-            .switch_to(state.unwrap_or($crate::machine_helper::state_ref!($state)));
+        let state = state.unwrap_or($crate::State::$state);
+        $slf.machine_helper.last_tag_state = state;
+        $slf.machine_helper.in_foreign_content = None;
+        $slf.machine_helper.checkpoint_at = Some($slf.reader.position);
+        $slf.machine_helper.last_tag_end = Some($slf.reader.position);
+        $slf.machine_helper.skip_content = $slf.should_skip_content(state);
+        $slf.switch_to(state.into());
         Ok(ControlToken::Continue)
     }};
 }
@@ -182,8 +352,8 @@ pub(crate) use switch_to;
 
 macro_rules! enter_state {
     ($slf:expr, $state:ident, $is_attribute:expr) => {{
-        $slf.machine_helper
-            .enter_state($crate::machine_helper::state_ref!($state), $is_attribute);
+        $slf.machine_helper.push_return_state($is_attribute);
+        $slf.switch_to($crate::machine_helper::state_ref!($state));
         Ok(ControlToken::Continue)
     }};
 }
@@ -192,13 +362,15 @@ pub(crate) use enter_state;
 
 macro_rules! exit_state {
     ($slf:expr) => {{
-        $slf.machine_helper.exit_state();
+        let state = $slf.machine_helper.pop_return_state();
+        $slf.switch_to(state);
         Ok(ControlToken::Continue)
     }};
 }
 
 pub(crate) use exit_state;
 
+/// Switch to `$state` and read `$c` again there, by pushing it back into the reader.
 macro_rules! reconsume_in {
     ($slf:expr, $c:expr, $state:ident) => {{
         let new_state = $crate::machine_helper::state_ref!($state);
@@ -229,6 +401,35 @@ macro_rules! cont {
 
 pub(crate) use cont;
 
+/// Discard the content of the last start tag up to the next `<`, and continue in `$state`, which
+/// checks whether it starts the end tag. See `Emitter::should_skip_rawtext`.
+macro_rules! skip_content {
+    ($slf:expr, $state:ident) => {{
+        if $slf
+            .reader
+            .skip_until_lt(&mut $slf.validator, &mut $slf.emitter)?
+        {
+            $slf.emitter.set_token_start($slf.reader.position - 1);
+            switch_to!($slf, $state)
+        } else {
+            eof!()
+        }
+    }};
+}
+
+pub(crate) use skip_content;
+
+/// Emit `$s` as text of RCDATA, RAWTEXT or script data, unless the content is skipped.
+macro_rules! emit_content {
+    ($slf:expr, $s:expr) => {
+        if !$slf.machine_helper.skip_content {
+            $slf.emitter.emit_string($s);
+        }
+    };
+}
+
+pub(crate) use emit_content;
+
 macro_rules! eof {
     () => {{
         Ok(ControlToken::Eof)
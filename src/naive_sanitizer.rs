@@ -0,0 +1,301 @@
+//! Remove everything from HTML except for an allowlist of tags and attributes.
+//!
+//! ```rust
+//! use html5gum::naive_sanitizer::sanitize;
+//!
+//! let Ok(html) = sanitize(r#"<p onclick="steal()">Hi <a href="javascript:steal()">there</a><script>steal()</script>"#);
+//! assert_eq!(html, b"<p>Hi <a>there</a></p>");
+//! ```
+//!
+//! The output is written from scratch instead of copying parts of the input: it only contains
+//! allowed tags with allowed attributes, and text. Attribute values and text are escaped like
+//! [`crate::serializer`] does, so character references in the input cannot turn into markup.
+//! Everything else is removed:
+//!
+//! * Tags that are not allowed are removed, but their text is kept. The exception are elements
+//!   whose contents the tokenizer reads as raw text, such as `<script>`, `<style>`, `<iframe>`
+//!   and `<plaintext>`, which are removed together with their contents and can not be allowed.
+//!   Which elements these are is decided by [`crate::naive_next_state`].
+//! * Attributes that are not allowed for their tag, and all event handler attributes such as
+//!   `onclick`.
+//! * URL attributes such as `href` and `src` whose scheme is not allowed, such as `javascript:`
+//!   URLs. Relative URLs are always allowed. Other attribute values are not inspected, so
+//!   attributes like `style` should not be allowed.
+//! * Comments and doctypes.
+//!
+//! The sanitizer does not build a tree, which is why it is naive: end tags of allowed elements
+//! are only kept if the element is open, and elements that are still open at the end of the input
+//! are closed. Tags that are closed implicitly in HTML, such as in `<p>a<p>b`, are not
+//! recognized, so the output may nest differently than the input would in a browser. It is
+//! always safe to embed it in the body of a page though.
+use std::collections::HashMap;
+use std::convert::Infallible;
+
+use crate::emitters::callback::{CallbackEmitter, CallbackEvent};
+use crate::serializer::escape;
+use crate::utils::is_void_element;
+use crate::{naive_next_state, Readable, Reader, State, Tokenizer};
+
+/// Attributes that contain a URL, see [`Sanitizer::allow_url_scheme`].
+const URL_ATTRIBUTES: &[&[u8]] = &[
+    b"action",
+    b"background",
+    b"cite",
+    b"codebase",
+    b"data",
+    b"formaction",
+    b"href",
+    b"longdesc",
+    b"manifest",
+    b"poster",
+    b"src",
+    b"xlink:href",
+];
+
+/// Sanitize `input` with the default [`Sanitizer`].
+pub fn sanitize<'a, R: Readable<'a>>(input: R) -> Result<Vec<u8>, <R::Reader as Reader>::Error> {
+    Sanitizer::default().sanitize(input)
+}
+
+/// The configuration of the sanitizer: which tags and attributes are allowed. See the [module
+/// documentation](self) for what is removed.
+///
+/// The default allows common formatting elements, links and images:
+///
+/// | Tag | Attributes |
+/// | --- | --- |
+/// | `a` | `href`, `title` |
+/// | `img` | `src`, `alt`, `title`, `width`, `height` |
+/// | `blockquote`, `q` | `cite` |
+/// | `b`, `br`, `code`, `em`, `h1` to `h6`, `hr`, `i`, `li`, `ol`, `p`, `pre`, `strong`, `ul` | none |
+///
+/// URLs may be relative, or use the `http`, `https` or `mailto` scheme.
+///
+/// ```rust
+/// use html5gum::naive_sanitizer::Sanitizer;
+///
+/// let mut sanitizer = Sanitizer::new();
+/// sanitizer.allow_tag("span", &["class"]);
+/// let Ok(html) = sanitizer.sanitize("<span class=x id=y>a<b>b</b></span>");
+/// assert_eq!(html, b"<span class=\"x\">ab</span>");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Sanitizer {
+    tags: HashMap<Vec<u8>, Vec<Vec<u8>>>,
+    url_schemes: Vec<Vec<u8>>,
+}
+
+impl Default for Sanitizer {
+    fn default() -> Self {
+        let mut sanitizer = Sanitizer::new();
+        sanitizer.allow_tag("a", &["href", "title"]);
+        sanitizer.allow_tag("img", &["src", "alt", "title", "width", "height"]);
+        for name in ["blockquote", "q"] {
+            sanitizer.allow_tag(name, &["cite"]);
+        }
+        for name in [
+            "b", "br", "code", "em", "h1", "h2", "h3", "h4", "h5", "h6", "hr", "i", "li", "ol",
+            "p", "pre", "strong", "ul",
+        ] {
+            sanitizer.allow_tag(name, &[]);
+        }
+        sanitizer
+    }
+}
+
+impl Sanitizer {
+    /// Create a sanitizer that allows no tags at all, and the URL schemes `http`, `https` and
+    /// `mailto`.
+    #[must_use]
+    pub fn new() -> Self {
+        Sanitizer {
+            tags: HashMap::new(),
+            url_schemes: vec![b"http".to_vec(), b"https".to_vec(), b"mailto".to_vec()],
+        }
+    }
+
+    /// Allow the tag `name` with the given attributes. Calling this again for the same tag adds
+    /// to its attributes.
+    ///
+    /// Names are case-insensitive. Event handler attributes such as `onclick`, and elements such
+    /// as `<script>` whose contents are raw text are never allowed.
+    pub fn allow_tag(&mut self, name: &str, attributes: &[&str]) {
+        self.tags
+            .entry(name.to_ascii_lowercase().into_bytes())
+            .or_default()
+            .extend(
+                attributes
+                    .iter()
+                    .map(|attribute| attribute.to_ascii_lowercase().into_bytes()),
+            );
+    }
+
+    /// Allow URLs with the scheme `scheme`, such as `"ftp"`, in URL attributes such as `href`
+    /// and `src`. The scheme is case-insensitive.
+    pub fn allow_url_scheme(&mut self, scheme: &str) {
+        self.url_schemes
+            .push(scheme.to_ascii_lowercase().into_bytes());
+    }
+
+    /// Sanitize `input` and return the resulting HTML.
+    pub fn sanitize<'a, R: Readable<'a>>(
+        &self,
+        input: R,
+    ) -> Result<Vec<u8>, <R::Reader as Reader>::Error> {
+        let mut state = SanitizerState {
+            sanitizer: self,
+            out: Vec::new(),
+            tag_name: Vec::new(),
+            attributes: Vec::new(),
+            open_elements: Vec::new(),
+            in_raw_text: false,
+        };
+        let mut emitter = CallbackEmitter::new(|event: CallbackEvent<'_>| -> Option<Infallible> {
+            state.handle_event(event);
+            None
+        });
+        // needed so that the contents of <script> are not tokenized as HTML
+        emitter.naively_switch_states(true);
+        emitter.pair_attributes(true);
+        Tokenizer::new_with_emitter(input, emitter).finish()?;
+
+        while let Some(name) = state.open_elements.pop() {
+            state.write_end_tag(&name);
+        }
+        Ok(state.out)
+    }
+
+    fn is_allowed_attribute(&self, allowed: &[Vec<u8>], name: &[u8], value: &[u8]) -> bool {
+        !name.starts_with(b"on")
+            && allowed.iter().any(|x| x == name)
+            && (!URL_ATTRIBUTES.contains(&name) || self.is_allowed_url(value))
+    }
+
+    fn is_allowed_url(&self, url: &[u8]) -> bool {
+        // like the URL parser: tabs and newlines are removed, and leading C0 controls and spaces
+        // are ignored
+        let url: Vec<u8> = url
+            .iter()
+            .copied()
+            .filter(|b| !matches!(b, b'\t' | b'\n' | b'\r'))
+            .skip_while(|&b| b <= b' ')
+            .collect();
+        let Some(colon) = url.iter().position(|&b| b == b':') else {
+            return true;
+        };
+        let scheme = &url[..colon];
+        let is_scheme = scheme.first().is_some_and(u8::is_ascii_alphabetic)
+            && scheme
+                .iter()
+                .all(|&b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'-' | b'.'));
+        // anything else before the colon makes it a relative URL, such as `/a:b`
+        !is_scheme
+            || self
+                .url_schemes
+                .iter()
+                .any(|x| x.eq_ignore_ascii_case(scheme))
+    }
+}
+
+struct SanitizerState<'s> {
+    sanitizer: &'s Sanitizer,
+    out: Vec<u8>,
+    tag_name: Vec<u8>,
+    /// The allowed attributes of the current start tag.
+    attributes: Vec<(Vec<u8>, Option<Vec<u8>>)>,
+    /// The allowed elements that have been written, but not closed yet.
+    open_elements: Vec<Vec<u8>>,
+    /// Whether the tokenizer is in an element that is removed with its contents.
+    in_raw_text: bool,
+}
+
+impl SanitizerState<'_> {
+    fn handle_event(&mut self, event: CallbackEvent<'_>) {
+        match event {
+            CallbackEvent::OpenStartTag { name } => {
+                self.tag_name.clear();
+                self.tag_name.extend(name);
+                self.attributes.clear();
+            }
+            CallbackEvent::Attribute { name, value, .. } => {
+                let Some(allowed) = self.sanitizer.tags.get(&self.tag_name) else {
+                    return;
+                };
+                if self
+                    .sanitizer
+                    .is_allowed_attribute(allowed, name, value.unwrap_or_default())
+                {
+                    self.attributes
+                        .push((name.to_vec(), value.map(<[u8]>::to_vec)));
+                }
+            }
+            CallbackEvent::CloseStartTag { .. } => self.handle_start_tag(),
+            CallbackEvent::EndTag { name } => self.handle_end_tag(name),
+            CallbackEvent::String { value } => {
+                if !self.in_raw_text {
+                    // writing to a Vec can not fail
+                    let _ = escape(&mut self.out, value, false);
+                }
+            }
+            CallbackEvent::AttributeName { .. }
+            | CallbackEvent::AttributeValue { .. }
+            | CallbackEvent::CdataStart
+            | CallbackEvent::CdataEnd
+            | CallbackEvent::Comment { .. }
+            | CallbackEvent::Doctype { .. }
+            | CallbackEvent::Error(_) => {}
+        }
+    }
+
+    fn handle_start_tag(&mut self) {
+        if matches!(
+            naive_next_state(&self.tag_name),
+            Some(State::RawText | State::ScriptData | State::PlainText)
+        ) {
+            self.in_raw_text = true;
+            return;
+        }
+
+        if !self.sanitizer.tags.contains_key(&self.tag_name) {
+            return;
+        }
+
+        self.out.push(b'<');
+        self.out.extend(&self.tag_name);
+        for (name, value) in &self.attributes {
+            self.out.push(b' ');
+            self.out.extend(name);
+            if let Some(value) = value {
+                self.out.extend(b"=\"");
+                let _ = escape(&mut self.out, value, true);
+                self.out.push(b'"');
+            }
+        }
+        // the self-closing flag is dropped, as browsers ignore it on HTML elements
+        self.out.push(b'>');
+
+        if !is_void_element(&self.tag_name) {
+            self.open_elements.push(self.tag_name.clone());
+        }
+    }
+
+    fn handle_end_tag(&mut self, name: &[u8]) {
+        if self.in_raw_text {
+            // the tokenizer only leaves raw text at the matching end tag
+            self.in_raw_text = false;
+            return;
+        }
+
+        if let Some(i) = self.open_elements.iter().rposition(|x| x == name) {
+            for name in self.open_elements.split_off(i).iter().rev() {
+                self.write_end_tag(name);
+            }
+        }
+    }
+
+    fn write_end_tag(&mut self, name: &[u8]) {
+        self.out.extend(b"</");
+        self.out.extend(name);
+        self.out.push(b'>');
+    }
+}
@@ -0,0 +1,80 @@
+//! Helpers for parsing documents nested inside of attributes, such as `<iframe srcdoc>`.
+//!
+//! The value of a `srcdoc` attribute is a complete HTML document. Character references in it are
+//! decoded twice: once by the outer document's tokenizer as part of the attribute value, and once
+//! more by the inner document's tokenizer. For example, in
+//!
+//! ```html
+//! <iframe srcdoc="<p>&amp;lt;</p>"></iframe>
+//! ```
+//!
+//! the outer tokenizer produces the attribute value `<p>&lt;</p>`, and the inner document contains
+//! a paragraph with the text `<`. Attribute values emitted by html5gum are already decoded, so the
+//! functions in this module take them as-is and must not be given the raw attribute source.
+//!
+//! ```rust
+//! use html5gum::{Token, Tokenizer};
+//! use html5gum::nested::parse_srcdoc;
+//!
+//! let outer = r#"<iframe srcdoc="<p>&amp;lt;</p>"></iframe>"#;
+//!
+//! for Ok(token) in Tokenizer::new(outer) {
+//!     if let Token::StartTag(tag) = token {
+//!         let srcdoc = &tag.attributes[b"srcdoc".as_slice()];
+//!         assert_eq!(srcdoc.as_slice(), b"<p>&lt;</p>");
+//!
+//!         let inner: Vec<_> = parse_srcdoc(srcdoc).flatten().collect();
+//!         assert_eq!(inner[1], Token::String(b"<".to_vec().into()));
+//!     }
+//! }
+//! ```
+//!
+//! See `examples/srcdoc_links.rs` for a link extractor that recurses into `srcdoc` documents.
+use crate::{Emitter, HtmlString, StringReader, Tokenizer};
+
+/// Tokenize the document contained in the (already decoded) value of a `srcdoc` attribute.
+pub fn parse_srcdoc(attribute_value: &HtmlString) -> Tokenizer<StringReader<'_>> {
+    Tokenizer::new(attribute_value.as_slice())
+}
+
+/// Like [`parse_srcdoc`], but with a custom emitter.
+pub fn parse_srcdoc_with_emitter<E: Emitter>(
+    attribute_value: &HtmlString,
+    emitter: E,
+) -> Tokenizer<StringReader<'_>, E> {
+    Tokenizer::new_with_emitter(attribute_value.as_slice(), emitter)
+}
+
+/// Parse the document contained in the (already decoded) value of a `srcdoc` attribute using
+/// html5ever's tree builder.
+///
+/// This is equivalent to [`crate::emitters::html5ever::parse_document`], except that the tree
+/// builder is told it is parsing an [iframe srcdoc
+/// document](https://html.spec.whatwg.org/#an-iframe-srcdoc-document), which never goes into
+/// quirks mode because of a missing doctype.
+///
+/// ```rust
+/// use html5ever::interface::{QuirksMode, TreeSink}; // extern crate html5ever;
+/// use scraper::{Html, HtmlTreeSink}; // extern crate scraper;
+///
+/// let srcdoc = b"<p>no doctype here</p>".to_vec().into();
+/// let tree_sink = HtmlTreeSink::new(Html::new_document());
+/// let tree_sink =
+///     html5gum::nested::parse_srcdoc_document(&srcdoc, tree_sink, Default::default());
+/// let dom: Html = tree_sink.finish();
+/// assert_eq!(dom.quirks_mode, QuirksMode::NoQuirks);
+/// ```
+#[cfg(feature = "html5ever")]
+pub fn parse_srcdoc_document<Sink>(
+    attribute_value: &HtmlString,
+    sink: Sink,
+    mut opts: html5ever::ParseOpts,
+) -> Sink
+where
+    Sink: html5ever::interface::TreeSink,
+{
+    opts.tree_builder.iframe_srcdoc = true;
+    let Ok(sink) =
+        crate::emitters::html5ever::parse_document(attribute_value.as_slice(), sink, opts);
+    sink
+}
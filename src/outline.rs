@@ -0,0 +1,894 @@
+//! Count paragraphs, list items and other blocks of a document without building a tree.
+//!
+//! Counting start tags is not enough for this, because HTML allows to omit many tags: `<p>a<p>b`
+//! has two paragraphs, `</p>` on its own creates an empty paragraph, and `<table><td>` implies a
+//! table row. [`count_blocks`] keeps track of the open elements and implements the subset of the
+//! [tree construction rules](https://html.spec.whatwg.org/#tree-construction) that decides
+//! whether such elements are created or closed.
+//!
+//! ```rust
+//! use html5gum::outline::{count_blocks, BlockCounts};
+//!
+//! let Ok(counts) = count_blocks("<p>one<p>two<ul><li>a<li>b</ul></p><table><td>x<td>y</table>");
+//! assert_eq!(
+//!     counts,
+//!     BlockCounts {
+//!         paragraphs: 3,
+//!         list_items: 2,
+//!         table_rows: 1,
+//!         table_cells: 2,
+//!         text_blocks: 6,
+//!         ..BlockCounts::default()
+//!     }
+//! );
+//! ```
+//!
+//! This is an approximation of what a browser does. In particular, the following is not
+//! implemented:
+//!
+//! * The adoption agency algorithm. Misnested formatting elements such as `<b><p>a</b>b` are
+//!   closed like any other element, and are not reconstructed in later blocks.
+//! * Of [quirks mode](crate::quirks), only the effect on `<table>` is implemented, which does not
+//!   close an open paragraph in quirks mode.
+//! * `<template>` contents are counted as if they were part of the document.
+//! * Foreign content (`<svg>` and `<math>`) is tracked well enough to not count its elements,
+//!   but `<font>` with attributes does not break out of it, and CDATA sections are parsed as
+//!   bogus comments.
+//! * The tokenizer switches to raw text states based on the tag name alone, like with
+//!   [`crate::naive_next_state`].
+use std::convert::Infallible;
+
+use crate::emitters::callback::{CallbackEmitter, CallbackEvent};
+use crate::quirks::{classify_doctype, QuirksMode};
+use crate::utils::{breaks_out_of_foreign_content, is_special_element, is_void_element};
+use crate::{Readable, Reader, Tokenizer};
+
+/// The number of blocks in a document, as counted by [`count_blocks`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlockCounts {
+    /// `<p>` elements, including empty ones created by a stray `</p>`.
+    pub paragraphs: usize,
+    /// `<li>` elements.
+    pub list_items: usize,
+    /// `<dt>` elements.
+    pub description_terms: usize,
+    /// `<dd>` elements.
+    pub description_details: usize,
+    /// `<tr>` elements, including ones implied by a `<td>` or `<th>` directly inside of a table.
+    pub table_rows: usize,
+    /// `<td>` and `<th>` elements.
+    pub table_cells: usize,
+    /// `<option>` elements.
+    pub options: usize,
+    /// Runs of text that contain something else than whitespace and are not interrupted by a
+    /// block-level element, such as `<div>`, `<p>`, `<li>` or `<br>`. Text in `<script>`,
+    /// `<style>`, `<title>` and similar elements is not counted.
+    pub text_blocks: usize,
+}
+
+/// Count the [`BlockCounts`] of a document in a single pass over its tokens.
+///
+/// See the [module documentation](self) for what is and isn't taken into account.
+pub fn count_blocks<'a, R: Readable<'a>>(
+    input: R,
+) -> Result<BlockCounts, <R::Reader as Reader>::Error> {
+    let mut counter = Counter::default();
+    let mut emitter = CallbackEmitter::new(|event: CallbackEvent<'_>| -> Option<Infallible> {
+        counter.handle_event(event);
+        None
+    });
+    // needed so that the contents of <script> are not tokenized as HTML
+    emitter.naively_switch_states(true);
+    Tokenizer::new_with_emitter(input, emitter).finish()?;
+    Ok(counter.counts)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Namespace {
+    Html,
+    Svg,
+    MathMl,
+}
+
+#[derive(Debug)]
+struct OpenElement {
+    name: Vec<u8>,
+    namespace: Namespace,
+}
+
+impl OpenElement {
+    fn is_html(&self, names: &[&[u8]]) -> bool {
+        self.namespace == Namespace::Html && names.contains(&&self.name[..])
+    }
+
+    /// Whether HTML start tags inside of this foreign element are parsed as HTML elements.
+    fn is_integration_point(&self) -> bool {
+        match self.namespace {
+            Namespace::Html => false,
+            Namespace::Svg => matches!(&self.name[..], b"foreignobject" | b"desc" | b"title"),
+            Namespace::MathMl => matches!(&self.name[..], b"mi" | b"mo" | b"mn" | b"ms" | b"mtext"),
+        }
+    }
+
+    fn is_special(&self) -> bool {
+        match self.namespace {
+            Namespace::Html => is_special_element(&self.name),
+            Namespace::MathMl => self.is_integration_point() || self.name == b"annotation-xml",
+            Namespace::Svg => self.is_integration_point(),
+        }
+    }
+
+    /// Whether this element ends the [default
+    /// scope](https://html.spec.whatwg.org/#has-an-element-in-scope).
+    fn ends_scope(&self) -> bool {
+        match self.namespace {
+            Namespace::Html => matches!(
+                &self.name[..],
+                b"applet"
+                    | b"caption"
+                    | b"html"
+                    | b"table"
+                    | b"td"
+                    | b"th"
+                    | b"marquee"
+                    | b"object"
+                    | b"template"
+            ),
+            _ => self.is_special(),
+        }
+    }
+}
+
+/// The insertion modes that are relevant for counting. Everything that happens before `<body>`
+/// is treated like "in body".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Body,
+    Table,
+    Caption,
+    TableBody,
+    Row,
+    Cell,
+    Select,
+}
+
+const TABLE_SECTIONS: &[&[u8]] = &[b"tbody", b"thead", b"tfoot"];
+const TABLE_PARTS: &[&[u8]] = &[
+    b"caption",
+    b"col",
+    b"colgroup",
+    b"tbody",
+    b"td",
+    b"tfoot",
+    b"th",
+    b"thead",
+    b"tr",
+];
+const IMPLIED_END_TAGS: &[&[u8]] = &[
+    b"dd",
+    b"dt",
+    b"li",
+    b"optgroup",
+    b"option",
+    b"p",
+    b"rb",
+    b"rp",
+    b"rt",
+    b"rtc",
+];
+const HEADINGS: &[&[u8]] = &[b"h1", b"h2", b"h3", b"h4", b"h5", b"h6"];
+
+#[derive(Debug, Default)]
+struct Counter {
+    counts: BlockCounts,
+    open_elements: Vec<OpenElement>,
+    tag_name: Vec<u8>,
+    no_quirks: bool,
+    /// Whether the body has been started, either explicitly or by content that doesn't belong
+    /// into the head.
+    in_body: bool,
+    in_text_block: bool,
+}
+
+impl Counter {
+    fn handle_event(&mut self, event: CallbackEvent<'_>) {
+        match event {
+            CallbackEvent::OpenStartTag { name } => {
+                self.tag_name.clear();
+                self.tag_name.extend(name);
+            }
+            CallbackEvent::CloseStartTag { self_closing, .. } => {
+                let name = std::mem::take(&mut self.tag_name);
+                self.handle_start_tag(&name, self_closing);
+                self.tag_name = name;
+            }
+            CallbackEvent::EndTag { name } => self.handle_end_tag(name),
+            CallbackEvent::String { value } => self.handle_string(value),
+            CallbackEvent::Doctype {
+                name,
+                public_identifier,
+                system_identifier,
+                force_quirks,
+            } => {
+                self.no_quirks =
+                    classify_doctype(name, public_identifier, system_identifier, force_quirks)
+                        != QuirksMode::Quirks;
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_string(&mut self, value: &[u8]) {
+        if value.iter().all(u8::is_ascii_whitespace) {
+            return;
+        }
+
+        let in_raw_text = self.open_elements.last().is_some_and(|element| {
+            element.is_html(&[
+                b"script",
+                b"style",
+                b"title",
+                b"textarea",
+                b"xmp",
+                b"iframe",
+                b"noembed",
+                b"noframes",
+                b"noscript",
+            ])
+        });
+
+        if !in_raw_text {
+            self.in_body = true;
+            if !self.in_text_block {
+                self.in_text_block = true;
+                self.counts.text_blocks += 1;
+            }
+        }
+    }
+
+    fn mode(&self) -> Mode {
+        for element in self.open_elements.iter().rev() {
+            if element.namespace != Namespace::Html {
+                continue;
+            }
+
+            match &element.name[..] {
+                b"td" | b"th" => return Mode::Cell,
+                b"tr" => return Mode::Row,
+                b"tbody" | b"thead" | b"tfoot" => return Mode::TableBody,
+                b"caption" => return Mode::Caption,
+                b"table" => return Mode::Table,
+                b"select" => return Mode::Select,
+                b"template" | b"html" => return Mode::Body,
+                _ => {}
+            }
+        }
+
+        Mode::Body
+    }
+
+    fn current_node_is(&self, names: &[&[u8]]) -> bool {
+        self.open_elements
+            .last()
+            .is_some_and(|element| element.is_html(names))
+    }
+
+    /// Whether the current node is a foreign element in which start tags create foreign elements.
+    fn in_foreign_content(&self) -> Option<Namespace> {
+        let element = self.open_elements.last()?;
+        if element.namespace == Namespace::Html || element.is_integration_point() {
+            None
+        } else {
+            Some(element.namespace)
+        }
+    }
+
+    fn push(&mut self, name: &[u8]) {
+        self.push_with_namespace(name, Namespace::Html);
+    }
+
+    fn push_with_namespace(&mut self, name: &[u8], namespace: Namespace) {
+        self.open_elements.push(OpenElement {
+            name: name.to_vec(),
+            namespace,
+        });
+    }
+
+    /// Whether an HTML element with one of `names` is in the specific scope whose end is defined
+    /// by `ends_scope`. See [has an element in the specific
+    /// scope](https://html.spec.whatwg.org/#has-an-element-in-the-specific-scope).
+    fn has_in_specific_scope(
+        &self,
+        names: &[&[u8]],
+        ends_scope: impl Fn(&OpenElement) -> bool,
+    ) -> bool {
+        for element in self.open_elements.iter().rev() {
+            if element.is_html(names) {
+                return true;
+            }
+
+            if ends_scope(element) {
+                return false;
+            }
+        }
+
+        false
+    }
+
+    fn has_in_scope(&self, names: &[&[u8]]) -> bool {
+        self.has_in_specific_scope(names, OpenElement::ends_scope)
+    }
+
+    fn has_in_button_scope(&self, names: &[&[u8]]) -> bool {
+        self.has_in_specific_scope(names, |element| {
+            element.ends_scope() || element.is_html(&[b"button"])
+        })
+    }
+
+    fn has_in_list_item_scope(&self, names: &[&[u8]]) -> bool {
+        self.has_in_specific_scope(names, |element| {
+            element.ends_scope() || element.is_html(&[b"ol", b"ul"])
+        })
+    }
+
+    fn has_in_table_scope(&self, names: &[&[u8]]) -> bool {
+        self.has_in_specific_scope(names, |element| {
+            element.is_html(&[b"html", b"table", b"template"])
+        })
+    }
+
+    /// Unlike the other scopes, the select scope is ended by all elements except `optgroup` and
+    /// `option`.
+    fn has_in_select_scope(&self, names: &[&[u8]]) -> bool {
+        self.has_in_specific_scope(names, |element| !element.is_html(&[b"optgroup", b"option"]))
+    }
+
+    /// Pop elements until an HTML element with one of `names` has been popped. It has to be on the
+    /// stack.
+    fn pop_until(&mut self, names: &[&[u8]]) {
+        while let Some(element) = self.open_elements.pop() {
+            if element.is_html(names) {
+                break;
+            }
+        }
+    }
+
+    /// Pop elements until the current node is an HTML element with one of `names`, or `html`.
+    fn clear_back_to(&mut self, names: &[&[u8]]) {
+        while let Some(element) = self.open_elements.last() {
+            if element.is_html(names) || element.is_html(&[b"html", b"template"]) {
+                break;
+            }
+            self.open_elements.pop();
+        }
+    }
+
+    fn generate_implied_end_tags(&mut self, except: &[u8]) {
+        while self.current_node_is(IMPLIED_END_TAGS) && !self.current_node_is(&[except]) {
+            self.open_elements.pop();
+        }
+    }
+
+    fn close_p_in_button_scope(&mut self) {
+        if self.has_in_button_scope(&[b"p"]) {
+            self.generate_implied_end_tags(b"p");
+            self.pop_until(&[b"p"]);
+        }
+    }
+
+    fn handle_start_tag(&mut self, name: &[u8], self_closing: bool) {
+        if is_block_boundary(name) {
+            self.in_text_block = false;
+        }
+
+        if !matches!(
+            name,
+            b"html"
+                | b"head"
+                | b"base"
+                | b"basefont"
+                | b"bgsound"
+                | b"link"
+                | b"meta"
+                | b"title"
+                | b"noscript"
+                | b"noframes"
+                | b"style"
+                | b"script"
+                | b"template"
+        ) {
+            self.in_body = true;
+        }
+
+        if let Some(namespace) = self.in_foreign_content() {
+            if !breaks_out_of_foreign_content(name) {
+                if !self_closing {
+                    self.push_with_namespace(name, namespace);
+                }
+                return;
+            }
+
+            while self.in_foreign_content().is_some() {
+                self.open_elements.pop();
+            }
+        }
+
+        self.start_tag_in_mode(self.mode(), name, self_closing);
+    }
+
+    fn start_tag_in_mode(&mut self, mode: Mode, name: &[u8], self_closing: bool) {
+        match mode {
+            Mode::Select => self.start_tag_in_select(name),
+            Mode::Cell | Mode::Caption if TABLE_PARTS.contains(&name) => {
+                let names: &[&[u8]] = if mode == Mode::Cell {
+                    &[b"td", b"th"]
+                } else {
+                    &[b"caption"]
+                };
+                if self.has_in_table_scope(names) {
+                    self.generate_implied_end_tags(b"");
+                    self.pop_until(names);
+                    self.start_tag_in_mode(self.mode(), name, self_closing);
+                }
+            }
+            Mode::Row => match name {
+                b"td" | b"th" => {
+                    self.clear_back_to(&[b"tr"]);
+                    self.push(name);
+                    self.counts.table_cells += 1;
+                }
+                _ if TABLE_PARTS.contains(&name) => {
+                    self.clear_back_to(&[b"tr"]);
+                    self.open_elements.pop();
+                    self.start_tag_in_mode(self.mode(), name, self_closing);
+                }
+                _ => self.start_tag_in_table(name, self_closing),
+            },
+            Mode::TableBody => match name {
+                b"tr" => {
+                    self.clear_back_to(TABLE_SECTIONS);
+                    self.push(name);
+                    self.counts.table_rows += 1;
+                }
+                b"td" | b"th" => {
+                    self.clear_back_to(TABLE_SECTIONS);
+                    self.push(b"tr");
+                    self.counts.table_rows += 1;
+                    self.start_tag_in_mode(Mode::Row, name, self_closing);
+                }
+                b"caption" | b"col" | b"colgroup" | b"tbody" | b"tfoot" | b"thead" => {
+                    self.clear_back_to(TABLE_SECTIONS);
+                    self.open_elements.pop();
+                    self.start_tag_in_mode(self.mode(), name, self_closing);
+                }
+                _ => self.start_tag_in_table(name, self_closing),
+            },
+            Mode::Table => self.start_tag_in_table(name, self_closing),
+            Mode::Body | Mode::Cell | Mode::Caption => self.start_tag_in_body(name, self_closing),
+        }
+    }
+
+    fn start_tag_in_table(&mut self, name: &[u8], self_closing: bool) {
+        match name {
+            b"caption" | b"tbody" | b"tfoot" | b"thead" => {
+                self.clear_back_to(&[b"table"]);
+                self.push(name);
+            }
+            b"tr" | b"td" | b"th" => {
+                self.clear_back_to(&[b"table"]);
+                self.push(b"tbody");
+                self.start_tag_in_mode(Mode::TableBody, name, self_closing);
+            }
+            b"col" | b"colgroup" | b"input" | b"form" => {}
+            b"table" => {
+                if self.has_in_table_scope(&[b"table"]) {
+                    self.pop_until(&[b"table"]);
+                    self.start_tag_in_mode(self.mode(), name, self_closing);
+                }
+            }
+            // everything else is foster-parented, which does not matter for counting
+            _ => self.start_tag_in_body(name, self_closing),
+        }
+    }
+
+    fn start_tag_in_select(&mut self, name: &[u8]) {
+        match name {
+            b"option" => {
+                if self.current_node_is(&[b"option"]) {
+                    self.open_elements.pop();
+                }
+                self.push(name);
+                self.counts.options += 1;
+            }
+            b"optgroup" => {
+                if self.current_node_is(&[b"option"]) {
+                    self.open_elements.pop();
+                }
+                if self.current_node_is(&[b"optgroup"]) {
+                    self.open_elements.pop();
+                }
+                self.push(name);
+            }
+            b"select" if self.has_in_select_scope(&[b"select"]) => self.pop_until(&[b"select"]),
+            b"input" | b"keygen" | b"textarea" if self.has_in_select_scope(&[b"select"]) => {
+                self.pop_until(&[b"select"]);
+                self.start_tag_in_mode(self.mode(), name, false);
+            }
+            _ if (TABLE_PARTS.contains(&name) || name == b"table")
+                && self.has_in_table_scope(&[b"select"]) =>
+            {
+                self.pop_until(&[b"select"]);
+                self.start_tag_in_mode(self.mode(), name, false);
+            }
+            b"script" | b"template" => self.push(name),
+            _ => {}
+        }
+    }
+
+    fn start_tag_in_body(&mut self, name: &[u8], self_closing: bool) {
+        match name {
+            b"address" | b"article" | b"aside" | b"blockquote" | b"center" | b"details"
+            | b"dialog" | b"dir" | b"div" | b"dl" | b"fieldset" | b"figcaption" | b"figure"
+            | b"footer" | b"header" | b"hgroup" | b"main" | b"menu" | b"nav" | b"ol" | b"p"
+            | b"search" | b"section" | b"summary" | b"ul" | b"pre" | b"listing" | b"form"
+            | b"plaintext" | b"xmp" => {
+                self.close_p_in_button_scope();
+                self.push(name);
+                if name == b"p" {
+                    self.counts.paragraphs += 1;
+                }
+            }
+            _ if HEADINGS.contains(&name) => {
+                self.close_p_in_button_scope();
+                if self.current_node_is(HEADINGS) {
+                    self.open_elements.pop();
+                }
+                self.push(name);
+            }
+            b"li" | b"dd" | b"dt" => {
+                let siblings: &[&[u8]] = if name == b"li" {
+                    &[b"li"]
+                } else {
+                    &[b"dd", b"dt"]
+                };
+
+                for i in (0..self.open_elements.len()).rev() {
+                    let element = &self.open_elements[i];
+                    if element.is_html(siblings) {
+                        let name = element.name.clone();
+                        self.generate_implied_end_tags(&name);
+                        self.pop_until(siblings);
+                        break;
+                    }
+
+                    if element.is_special() && !element.is_html(&[b"address", b"div", b"p"]) {
+                        break;
+                    }
+                }
+
+                self.close_p_in_button_scope();
+                self.push(name);
+                match name {
+                    b"li" => self.counts.list_items += 1,
+                    b"dd" => self.counts.description_details += 1,
+                    _ => self.counts.description_terms += 1,
+                }
+            }
+            b"button" => {
+                if self.has_in_scope(&[b"button"]) {
+                    self.generate_implied_end_tags(b"");
+                    self.pop_until(&[b"button"]);
+                }
+                self.push(name);
+            }
+            b"table" => {
+                if self.no_quirks {
+                    self.close_p_in_button_scope();
+                }
+                self.push(name);
+            }
+            b"hr" => self.close_p_in_button_scope(),
+            b"option" | b"optgroup" => {
+                if self.current_node_is(&[b"option"]) {
+                    self.open_elements.pop();
+                }
+                self.push(name);
+                if name == b"option" {
+                    self.counts.options += 1;
+                }
+            }
+            b"rb" | b"rtc" => {
+                if self.has_in_scope(&[b"ruby"]) {
+                    self.generate_implied_end_tags(b"");
+                }
+                self.push(name);
+            }
+            b"rp" | b"rt" => {
+                if self.has_in_scope(&[b"ruby"]) {
+                    self.generate_implied_end_tags(b"rtc");
+                }
+                self.push(name);
+            }
+            b"svg" | b"math" => {
+                if !self_closing {
+                    let namespace = if name == b"svg" {
+                        Namespace::Svg
+                    } else {
+                        Namespace::MathMl
+                    };
+                    self.push_with_namespace(name, namespace);
+                }
+            }
+            b"html" | b"head" | b"body" | b"frameset" | b"frame" | b"image" => {}
+            _ if TABLE_PARTS.contains(&name) || is_void_element(name) => {}
+            _ => self.push(name),
+        }
+    }
+
+    fn handle_end_tag(&mut self, name: &[u8]) {
+        if is_block_boundary(name) {
+            self.in_text_block = false;
+        }
+
+        // before the body, only these end tags are not ignored
+        if matches!(name, b"body" | b"html" | b"br") {
+            self.in_body = true;
+        } else if !self.in_body {
+            return;
+        }
+
+        if self.in_foreign_content().is_some() {
+            for i in (0..self.open_elements.len()).rev() {
+                let element = &self.open_elements[i];
+                if element.namespace == Namespace::Html {
+                    break;
+                }
+
+                if element.name == name {
+                    self.open_elements.truncate(i);
+                    return;
+                }
+            }
+        }
+
+        self.end_tag_in_mode(self.mode(), name);
+    }
+
+    fn end_tag_in_mode(&mut self, mode: Mode, name: &[u8]) {
+        match (mode, name) {
+            (Mode::Select, b"option") => {
+                if self.current_node_is(&[b"option"]) {
+                    self.open_elements.pop();
+                }
+            }
+            (Mode::Select, b"optgroup") => {
+                let len = self.open_elements.len();
+                if self.current_node_is(&[b"option"])
+                    && len >= 2
+                    && self.open_elements[len - 2].is_html(&[b"optgroup"])
+                {
+                    self.open_elements.pop();
+                }
+                if self.current_node_is(&[b"optgroup"]) {
+                    self.open_elements.pop();
+                }
+            }
+            (Mode::Select, b"select") => {
+                if self.has_in_select_scope(&[b"select"]) {
+                    self.pop_until(&[b"select"]);
+                }
+            }
+            (Mode::Select, _) => {
+                if (TABLE_PARTS.contains(&name) || name == b"table")
+                    && self.has_in_table_scope(&[name])
+                {
+                    self.pop_until(&[b"select"]);
+                    self.end_tag_in_mode(self.mode(), name);
+                }
+            }
+            (Mode::Body, _) => self.end_tag_in_body(name),
+            (_, b"table" | b"caption" | b"tbody" | b"thead" | b"tfoot" | b"tr" | b"td" | b"th") => {
+                if self.has_in_table_scope(&[name]) {
+                    self.generate_implied_end_tags(b"");
+                    self.pop_until(&[name]);
+                }
+            }
+            (_, b"body" | b"col" | b"colgroup" | b"html") => {}
+            _ => self.end_tag_in_body(name),
+        }
+    }
+
+    fn end_tag_in_body(&mut self, name: &[u8]) {
+        match name {
+            b"p" => {
+                if self.has_in_button_scope(&[b"p"]) {
+                    self.generate_implied_end_tags(b"p");
+                    self.pop_until(&[b"p"]);
+                } else {
+                    // an empty paragraph is created and closed right away
+                    self.counts.paragraphs += 1;
+                }
+            }
+            b"li" => {
+                if self.has_in_list_item_scope(&[b"li"]) {
+                    self.generate_implied_end_tags(b"li");
+                    self.pop_until(&[b"li"]);
+                }
+            }
+            _ if HEADINGS.contains(&name) => {
+                if self.has_in_scope(HEADINGS) {
+                    self.generate_implied_end_tags(b"");
+                    self.pop_until(HEADINGS);
+                }
+            }
+            b"body" | b"html" | b"br" => {}
+            _ if is_special_element(name) => {
+                if self.has_in_scope(&[name]) {
+                    self.generate_implied_end_tags(name);
+                    self.pop_until(&[name]);
+                }
+            }
+            _ => {
+                // any other end tag
+                for i in (0..self.open_elements.len()).rev() {
+                    let element = &self.open_elements[i];
+                    if element.is_html(&[name]) {
+                        self.generate_implied_end_tags(name);
+                        self.open_elements.truncate(i);
+                        break;
+                    }
+
+                    if element.is_special() {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Whether a start or end tag with `name` ends the current text block.
+fn is_block_boundary(name: &[u8]) -> bool {
+    match name {
+        b"option" | b"optgroup" => true,
+        // special, but not blocks
+        b"applet" | b"area" | b"base" | b"basefont" | b"bgsound" | b"button" | b"embed"
+        | b"iframe" | b"img" | b"input" | b"keygen" | b"link" | b"meta" | b"noembed"
+        | b"noframes" | b"noscript" | b"object" | b"param" | b"script" | b"select" | b"source"
+        | b"style" | b"template" | b"textarea" | b"title" | b"track" | b"wbr" => false,
+        _ => is_special_element(name),
+    }
+}
+
+#[cfg(test)]
+fn counter_with_stack(elements: &[(&str, Namespace)]) -> Counter {
+    let mut counter = Counter::default();
+    for (name, namespace) in elements {
+        counter.push_with_namespace(name.as_bytes(), *namespace);
+    }
+    counter
+}
+
+#[test]
+fn button_scope() {
+    use Namespace::Html;
+
+    let counter = counter_with_stack(&[("html", Html), ("p", Html), ("button", Html)]);
+    assert!(counter.has_in_scope(&[b"p"]));
+    assert!(!counter.has_in_button_scope(&[b"p"]));
+    assert!(counter.has_in_button_scope(&[b"button"]));
+
+    let counter = counter_with_stack(&[("html", Html), ("p", Html), ("td", Html)]);
+    assert!(!counter.has_in_scope(&[b"p"]));
+    assert!(!counter.has_in_button_scope(&[b"p"]));
+}
+
+#[test]
+fn list_item_scope() {
+    use Namespace::Html;
+
+    let counter = counter_with_stack(&[("html", Html), ("li", Html), ("ul", Html), ("p", Html)]);
+    assert!(counter.has_in_scope(&[b"li"]));
+    assert!(!counter.has_in_list_item_scope(&[b"li"]));
+    assert!(counter.has_in_list_item_scope(&[b"ul", b"p"]));
+}
+
+#[test]
+fn table_scope() {
+    use Namespace::Html;
+
+    let counter = counter_with_stack(&[
+        ("html", Html),
+        ("table", Html),
+        ("tbody", Html),
+        ("tr", Html),
+        ("td", Html),
+        ("applet", Html),
+        ("button", Html),
+    ]);
+    assert!(counter.has_in_table_scope(&[b"table"]));
+    assert!(counter.has_in_table_scope(&[b"td"]));
+    assert!(!counter.has_in_scope(&[b"td"]));
+    assert!(!counter.has_in_scope(&[b"table"]));
+
+    for terminator in ["html", "table", "template"] {
+        let counter = counter_with_stack(&[("html", Html), ("tr", Html), (terminator, Html)]);
+        assert!(!counter.has_in_table_scope(&[b"tr"]), "{}", terminator);
+    }
+
+    // foreign elements named like HTML elements don't end the table scope
+    let counter = counter_with_stack(&[
+        ("html", Html),
+        ("tr", Html),
+        ("math", Namespace::MathMl),
+        ("table", Namespace::MathMl),
+    ]);
+    assert!(counter.has_in_table_scope(&[b"tr"]));
+}
+
+#[test]
+fn select_scope() {
+    use Namespace::Html;
+
+    let counter = counter_with_stack(&[
+        ("html", Html),
+        ("select", Html),
+        ("optgroup", Html),
+        ("option", Html),
+    ]);
+    assert!(counter.has_in_select_scope(&[b"select"]));
+
+    let counter = counter_with_stack(&[("html", Html), ("select", Html), ("template", Html)]);
+    assert!(!counter.has_in_select_scope(&[b"select"]));
+    assert!(counter.has_in_table_scope(&[b"template"]));
+}
+
+#[test]
+fn foreign_scope() {
+    use Namespace::{Html, MathMl, Svg};
+
+    let counter = counter_with_stack(&[("html", Html), ("p", Html), ("math", MathMl)]);
+    assert!(counter.has_in_scope(&[b"p"]));
+
+    for (name, namespace) in [
+        ("mi", MathMl),
+        ("mo", MathMl),
+        ("mn", MathMl),
+        ("ms", MathMl),
+        ("mtext", MathMl),
+        ("annotation-xml", MathMl),
+        ("foreignobject", Svg),
+        ("desc", Svg),
+        ("title", Svg),
+    ] {
+        let counter = counter_with_stack(&[("html", Html), ("p", Html), (name, namespace)]);
+        assert!(!counter.has_in_scope(&[b"p"]), "{}", name);
+        assert!(!counter.has_in_button_scope(&[b"p"]), "{}", name);
+        assert!(counter.has_in_table_scope(&[b"p"]), "{}", name);
+    }
+
+    // an HTML element name in a foreign namespace is not matched
+    let counter = counter_with_stack(&[("html", Html), ("svg", Svg), ("p", Svg)]);
+    assert!(!counter.has_in_scope(&[b"p"]));
+}
+
+#[test]
+fn special_elements() {
+    use Namespace::{Html, MathMl, Svg};
+
+    let element = |name: &str, namespace| OpenElement {
+        name: name.as_bytes().to_vec(),
+        namespace,
+    };
+
+    assert!(element("address", Html).is_special());
+    assert!(element("title", Html).is_special());
+    assert!(!element("span", Html).is_special());
+    assert!(element("annotation-xml", MathMl).is_special());
+    assert!(element("mi", MathMl).is_special());
+    assert!(!element("math", MathMl).is_special());
+    assert!(!element("address", MathMl).is_special());
+    assert!(element("title", Svg).is_special());
+    assert!(!element("svg", Svg).is_special());
+    assert!(!element("a", Svg).is_special());
+}
@@ -0,0 +1,308 @@
+//! Helpers for tokenizing very large documents in parallel.
+//!
+//! Tokenizing HTML in parallel is not possible in general: whether a `<` starts a tag depends on
+//! everything that came before it. For example, it may be part of a comment, an attribute value or
+//! a `<script>` element that started megabytes earlier.
+//!
+//! What this module offers instead is [`find_split_points`], which uses a cheap scan over the
+//! input to find positions right after a tag, such that tokenizing each chunk separately and
+//! concatenating the resulting tokens produces the same tokens as tokenizing the whole document at
+//! once.
+//!
+//! ```rust
+//! use html5gum::{DefaultEmitter, Token, Tokenizer};
+//! use html5gum::parallel::find_split_points;
+//!
+//! fn tokenize(input: &str) -> Vec<Token> {
+//!     let mut emitter = DefaultEmitter::default();
+//!     emitter.naively_switch_states(true);
+//!     Tokenizer::new_with_emitter(input, emitter).flatten().collect()
+//! }
+//!
+//! let input = "<ul><li>first</li><li>second</li><li>third</li></ul>".repeat(1000);
+//! let points = find_split_points(input.as_bytes(), 4);
+//! assert_eq!(points.len(), 3);
+//!
+//! let mut chunks = Vec::new();
+//! let mut start = 0;
+//! for end in points.into_iter().chain(Some(input.len())) {
+//!     chunks.push(&input[start..end]);
+//!     start = end;
+//! }
+//!
+//! // each chunk could be tokenized on a different thread here
+//! let tokens: Vec<Token> = chunks.into_iter().flat_map(tokenize).collect();
+//!
+//! assert_eq!(tokens, tokenize(&input));
+//! ```
+use crate::reader::fast_find;
+use crate::{naive_next_state, DefaultEmitter, State, Token, Tokenizer};
+
+/// Find up to `n - 1` positions at which `input` can be split into `n` chunks that can be tokenized
+/// independently.
+///
+/// The returned positions are sorted and lie strictly between `0` and `input.len()`. Chunks are
+/// roughly equally sized. If no safe position is found within a chunk, fewer positions are
+/// returned, down to none at all. For example, a document consisting of a single huge `<script>`
+/// element can't be split.
+///
+/// A position is only returned if it is right after the `>` of a start or end tag that does not
+/// switch the tokenizer into a different state (like `<script>` or `<title>` do), and re-tokenizing
+/// that tag on its own agrees with that. Finding those positions requires a sequential scan over
+/// the input, but that scan only looks for the boundaries of tags, comments and elements with
+/// special content models, which is a lot cheaper than tokenizing.
+///
+/// The split points assume that the tokenizer switches states like [`naive_next_state`] does, for
+/// example by using [`DefaultEmitter::naively_switch_states`]. CDATA sections are assumed to be
+/// tokenized as bogus comments, which is what all emitters except tree builders do.
+#[must_use]
+pub fn find_split_points(input: &[u8], n: usize) -> Vec<usize> {
+    let mut rv = Vec::new();
+    let mut target_i = 1;
+    let mut scanner = Scanner { input, pos: 0 };
+
+    while target_i < n {
+        let target = input.len() * target_i / n;
+
+        let (tag_start, point) = match scanner.next_tag() {
+            Some(x) => x,
+            None => break,
+        };
+
+        if point >= target && point < input.len() && is_single_tag(&input[tag_start..point]) {
+            rv.push(point);
+            // skip all targets that have been passed already
+            while target_i < n && input.len() * target_i / n <= point {
+                target_i += 1;
+            }
+        }
+    }
+
+    rv
+}
+
+/// Verify a tag found by [`Scanner`] with the actual tokenizer.
+fn is_single_tag(input: &[u8]) -> bool {
+    let mut emitter = DefaultEmitter::default();
+    emitter.naively_switch_states(true);
+    let mut tokenizer = Tokenizer::new_with_emitter(input, emitter);
+
+    matches!(
+        (tokenizer.next(), tokenizer.next()),
+        (Some(Ok(Token::StartTag(_) | Token::EndTag(_))), None)
+    )
+}
+
+/// A very coarse approximation of the tokenizer, only tracking where tags, comments and special
+/// elements start and end.
+struct Scanner<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl Scanner<'_> {
+    /// Find the next start or end tag after which the tokenizer is in the data state. Returns
+    /// start and end position of that tag.
+    fn next_tag(&mut self) -> Option<(usize, usize)> {
+        loop {
+            let start = self.pos + fast_find(b"<", &self.input[self.pos..])?;
+            let rest = &self.input[start..];
+
+            if rest.starts_with(b"<!--") {
+                // `<!-->` and `<!--->` are complete comments, but `<!--!>` is not.
+                let dash_dash_gt = find(self.input, start + 2, b"-->").map(|x| x + 3);
+                let dash_dash_bang_gt = find(self.input, start + 4, b"--!>").map(|x| x + 4);
+                self.pos = match (dash_dash_gt, dash_dash_bang_gt) {
+                    (Some(a), Some(b)) => a.min(b),
+                    (a, b) => a.or(b)?,
+                };
+            } else if rest.starts_with(b"<!") || rest.starts_with(b"<?") {
+                // doctypes and bogus comments, including CDATA sections
+                self.pos = find(self.input, start + 2, b">")? + 1;
+            } else if rest.starts_with(b"</>") {
+                self.pos = start + 3;
+            } else if rest.starts_with(b"</") && rest.len() > 2 && !rest[2].is_ascii_alphabetic() {
+                // bogus comment
+                self.pos = find(self.input, start + 2, b">")? + 1;
+            } else if rest.starts_with(b"</") {
+                self.pos = self.skip_tag(start + 2)?;
+                return Some((start, self.pos));
+            } else if rest.len() > 1 && rest[1].is_ascii_alphabetic() {
+                let name_end = start
+                    + rest
+                        .iter()
+                        .position(|&c| {
+                            matches!(c, b'\t' | b'\n' | b'\x0c' | b'\r' | b' ' | b'/' | b'>')
+                        })
+                        .unwrap_or(rest.len());
+                self.pos = self.skip_tag(start + 1)?;
+
+                let name = self.input[start + 1..name_end].to_ascii_lowercase();
+                match naive_next_state(&name) {
+                    None => return Some((start, self.pos)),
+                    Some(State::PlainText) => return None,
+                    Some(State::ScriptData) => self.skip_script()?,
+                    Some(_) => self.pos = find_end_tag(self.input, self.pos, &name)?,
+                }
+            } else {
+                self.pos = start + 1;
+            }
+        }
+    }
+
+    /// Skip over the rest of a tag, starting after `<` or `</`. Returns the position after `>`.
+    ///
+    /// Only quoted attribute values can contain `>`, but quotes only start an attribute value if
+    /// they come after an attribute name and `=`, so this needs to track a few more states.
+    fn skip_tag(&self, mut pos: usize) -> Option<usize> {
+        #[derive(Clone, Copy)]
+        enum TagState {
+            TagName,
+            BeforeAttributeName,
+            AttributeName,
+            AfterAttributeName,
+            BeforeAttributeValue,
+            AttributeValueUnquoted,
+        }
+
+        let mut state = TagState::TagName;
+
+        loop {
+            let c = *self.input.get(pos)?;
+            pos += 1;
+
+            if c == b'>' {
+                return Some(pos);
+            }
+
+            let is_whitespace = matches!(c, b'\t' | b'\n' | b'\x0c' | b'\r' | b' ');
+
+            state = match (state, c) {
+                (TagState::BeforeAttributeValue, b'"' | b'\'') => {
+                    pos = find(self.input, pos, &[c])? + 1;
+                    TagState::BeforeAttributeName
+                }
+                (TagState::BeforeAttributeValue, _) if is_whitespace => state,
+                (TagState::BeforeAttributeValue, _) => TagState::AttributeValueUnquoted,
+                (TagState::AttributeValueUnquoted, _) if is_whitespace => {
+                    TagState::BeforeAttributeName
+                }
+                (TagState::AttributeValueUnquoted, _) => state,
+                (_, b'/') => TagState::BeforeAttributeName,
+                (TagState::TagName, _) if is_whitespace => TagState::BeforeAttributeName,
+                (TagState::TagName, _) => state,
+                (TagState::BeforeAttributeName, _) if is_whitespace => state,
+                (TagState::BeforeAttributeName, _) => TagState::AttributeName,
+                (TagState::AttributeName | TagState::AfterAttributeName, b'=') => {
+                    TagState::BeforeAttributeValue
+                }
+                (TagState::AttributeName | TagState::AfterAttributeName, _) if is_whitespace => {
+                    TagState::AfterAttributeName
+                }
+                (TagState::AttributeName | TagState::AfterAttributeName, _) => {
+                    TagState::AttributeName
+                }
+            };
+        }
+    }
+
+    /// Skip over the contents of a `<script>` element, up until its end tag.
+    ///
+    /// This follows the script data states of the spec exactly. After `<!--`, the script is
+    /// escaped, but `</script>` still ends it. Only a `<script>` inside of the escaped part starts
+    /// the double escaped state, in which `</script>` goes back to the escaped state instead.
+    /// `-->` ends both of them.
+    fn skip_script(&mut self) -> Option<()> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum Escape {
+            None,
+            Escaped,
+            DoubleEscaped,
+        }
+
+        let input = self.input;
+        let mut escape = Escape::None;
+        // the number of dashes right before `pos` in the escaped states, up to two
+        let mut dashes = 0;
+        let mut pos = self.pos;
+
+        loop {
+            if escape == Escape::None {
+                pos += fast_find(b"<", input.get(pos..)?)?;
+            }
+            let c = *input.get(pos)?;
+            pos += 1;
+            let rest = &input[pos..];
+
+            match (escape, c) {
+                (Escape::None | Escape::Escaped, b'<')
+                    if rest.starts_with(b"/") && starts_with_tag_name(&rest[1..], b"script") =>
+                {
+                    self.pos = pos - 1;
+                    return Some(());
+                }
+                (Escape::None, b'<') if rest.starts_with(b"!--") => {
+                    // `<!-->` is already the end of the escaped part
+                    escape = Escape::Escaped;
+                    dashes = 2;
+                    pos += 3;
+                    continue;
+                }
+                (Escape::Escaped, b'<') if starts_with_tag_name(rest, b"script") => {
+                    escape = Escape::DoubleEscaped;
+                    pos += b"script>".len();
+                }
+                (Escape::DoubleEscaped, b'<')
+                    if rest.starts_with(b"/") && starts_with_tag_name(&rest[1..], b"script") =>
+                {
+                    escape = Escape::Escaped;
+                    pos += b"/script>".len();
+                }
+                (Escape::Escaped | Escape::DoubleEscaped, b'-') => {
+                    dashes = (dashes + 1).min(2);
+                    continue;
+                }
+                (Escape::Escaped | Escape::DoubleEscaped, b'>') if dashes == 2 => {
+                    escape = Escape::None;
+                }
+                _ => {}
+            }
+
+            dashes = 0;
+        }
+    }
+}
+
+/// Find the position of `</{name}` followed by whitespace, `/` or `>`.
+fn find_end_tag(input: &[u8], mut pos: usize, name: &[u8]) -> Option<usize> {
+    loop {
+        let start = find(input, pos, b"</")?;
+        if starts_with_tag_name(&input[start + 2..], name) {
+            return Some(start);
+        }
+        pos = start + 2;
+    }
+}
+
+/// Whether `input` starts with `name`, ignoring ASCII case, followed by whitespace, `/` or `>`.
+fn starts_with_tag_name(input: &[u8], name: &[u8]) -> bool {
+    input
+        .get(..name.len())
+        .is_some_and(|x| x.eq_ignore_ascii_case(name))
+        && matches!(
+            input.get(name.len()),
+            Some(b'\t' | b'\n' | b'\x0c' | b'\r' | b' ' | b'/' | b'>')
+        )
+}
+
+/// Find the position of `needle` in `input`, ignoring ASCII case, starting the search at `pos`.
+fn find(input: &[u8], mut pos: usize, needle: &[u8]) -> Option<usize> {
+    loop {
+        let start = pos + fast_find(&needle[..1], input.get(pos..)?)?;
+        let candidate = input.get(start..start + needle.len())?;
+        if candidate.eq_ignore_ascii_case(needle) {
+            return Some(start);
+        }
+        pos = start + 1;
+    }
+}
@@ -0,0 +1,220 @@
+//! Tokenize input that is pushed to the tokenizer as it arrives, for example from the callbacks of
+//! an event loop, without implementing [`Reader`].
+//!
+//! ```rust
+//! use html5gum::push::PushTokenizer;
+//! use html5gum::{DefaultEmitter, Token};
+//!
+//! let mut tokenizer = PushTokenizer::new(DefaultEmitter::default());
+//! let mut tokens: Vec<Token> = tokenizer.push(b"<p class=a>hel").collect();
+//! assert_eq!(tokens.len(), 1);
+//! tokens.extend(tokenizer.push(b"lo</p>"));
+//! tokens.extend(tokenizer.finish());
+//!
+//! assert_eq!(tokens[1], Token::String(b"hello".to_vec().into()));
+//! assert_eq!(tokens.len(), 3);
+//! ```
+//!
+//! The tokenizer can't pause in the middle of a token. Instead, whenever it runs out of input, it
+//! starts over from the end of the last complete tag once more input is pushed, just like
+//! [`Tokenizer::resume`] does with a [`crate::TokenizerSnapshot`]. Tokens after that tag that
+//! have already been returned are tokenized again and dropped. Only the input after that tag is
+//! kept in memory. This means that long runs of input without any complete tags in between, such
+//! as text, comments or the content of `<script>`, are tokenized several times. To keep the total
+//! work linear in the size of the input, the tokenizer only starts over once enough input has been
+//! pushed since the last attempt, see [`PushTokenizer`].
+use std::collections::VecDeque;
+use std::fmt;
+
+use crate::reader::fast_find;
+use crate::{Emitter, Reader, Tokenizer, TokenizerSnapshot};
+
+/// A tokenizer that input is pushed to in chunks, see the [module docs](self).
+///
+/// This is not a real incremental tokenizer: it can't suspend in the middle of a token, and
+/// instead tokenizes the input after the last complete tag again on every attempt. To bound that
+/// work, [`PushTokenizer::push`] only makes another attempt once the newly pushed input is at least
+/// a quarter as long as the input kept from the last attempt, so that every byte is tokenized at
+/// most about five times. The downside is that a token may be returned by a later call to `push`
+/// than the one that completed it, or only by [`PushTokenizer::finish`].
+///
+/// Every time tokenizing starts over, the emitter is reset using [`Emitter::reset`], as with
+/// [`Tokenizer::reset_with_reader`]. Only its configuration carries over, not state it collects
+/// about the whole document, such as [`crate::DefaultEmitter::collect_error_summary`].
+///
+/// The emitter also has to produce the same tokens no matter how the input is split into chunks.
+/// [`crate::DefaultEmitter`] does, because it only emits text once it is complete, but a
+/// [`crate::emitters::callback::CallbackEmitter`] that does not
+/// [coalesce strings](crate::emitters::callback::CallbackEmitter::coalesce_strings) does not.
+#[derive(Debug)]
+pub struct PushTokenizer<E: Emitter> {
+    /// `None` only while tokenizing.
+    emitter: Option<E>,
+    /// The input from `snapshot.offset` on, as far as it has been pushed.
+    buffer: Vec<u8>,
+    /// Where to start over once more input has been pushed.
+    snapshot: TokenizerSnapshot,
+    /// Whether the emitter has been used before, and needs to be reset.
+    started: bool,
+    /// Tokens that have not been returned yet.
+    tokens: VecDeque<E::Token>,
+    /// The length of `buffer` after the last attempt, all of which will be tokenized again.
+    retained: usize,
+}
+
+/// Tokenize again once the input pushed since the last attempt is at least `1 / RETRY_RATIO` of
+/// the input kept from it.
+const RETRY_RATIO: usize = 4;
+
+impl<E: Emitter> PushTokenizer<E> {
+    /// Create a new tokenizer that passes the input to `emitter`.
+    pub fn new(emitter: E) -> Self {
+        PushTokenizer {
+            emitter: Some(emitter),
+            buffer: Vec::new(),
+            snapshot: TokenizerSnapshot::default(),
+            started: false,
+            tokens: VecDeque::new(),
+            retained: 0,
+        }
+    }
+
+    /// Append `bytes` to the input, and return the tokens that are complete afterwards.
+    ///
+    /// Tokens may be held back until enough input has been pushed, see the [type docs](Self).
+    /// Tokens that are not returned by the iterator, for example because it is dropped early, are
+    /// returned by the next call to `push` or [`PushTokenizer::finish`].
+    pub fn push(&mut self, bytes: &[u8]) -> impl Iterator<Item = E::Token> + '_ {
+        self.buffer.extend_from_slice(bytes);
+        let pushed = self.buffer.len() - self.retained;
+        if pushed > 0 && pushed * RETRY_RATIO >= self.retained {
+            self.tokenize(false);
+        }
+        self.tokens.drain(..)
+    }
+
+    /// Mark the end of the input, and return all remaining tokens.
+    pub fn finish(mut self) -> impl Iterator<Item = E::Token> {
+        self.tokenize(true);
+        self.tokens.into_iter()
+    }
+
+    /// Tokenize the buffered input, and move the snapshot to the end of the last complete tag.
+    fn tokenize(&mut self, eof: bool) {
+        let Some(mut emitter) = self.emitter.take() else {
+            return;
+        };
+        if self.started {
+            emitter.reset();
+        }
+        self.started = true;
+
+        let reader = BufferReader {
+            input: &self.buffer,
+            eof,
+        };
+        let mut tokenizer = if self.snapshot.offset == 0 {
+            // No tag has been read yet, so there is no context to restore. In particular, the
+            // emitter still decides whether the input starts in foreign content.
+            Tokenizer::new_with_emitter(reader, emitter)
+        } else {
+            Tokenizer::resume_with_reader(
+                reader,
+                self.snapshot.offset,
+                self.snapshot.context.clone(),
+                emitter,
+            )
+        };
+        tokenizer.skip_tokens(self.snapshot.skip_tokens);
+        // stops at the end of the input, or where more input is needed
+        self.tokens.extend(tokenizer.by_ref().map_while(Result::ok));
+
+        let snapshot = tokenizer.save_state();
+        self.emitter = Some(tokenizer.into_emitter());
+        self.buffer.drain(..snapshot.offset - self.snapshot.offset);
+        self.snapshot = snapshot;
+        self.retained = self.buffer.len();
+    }
+}
+
+/// Signals that the [`BufferReader`] needs more input to continue.
+#[derive(Debug)]
+pub(crate) struct WouldBlock;
+
+impl fmt::Display for WouldBlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("more input is needed")
+    }
+}
+
+impl std::error::Error for WouldBlock {}
+
+/// Reads the input that has arrived so far, and fails with [`WouldBlock`] where more is needed.
+///
+/// This includes lookahead: if the input ends in the middle of a string passed to
+/// [`Reader::try_read_string`], such as `<!DOC`, the tokenizer has to wait for more input before
+/// it can decide what to do.
+pub(crate) struct BufferReader<'a> {
+    pub(crate) input: &'a [u8],
+    /// Whether the end of `input` is the end of the document.
+    pub(crate) eof: bool,
+}
+
+impl BufferReader<'_> {
+    fn end_of_input(&self) -> Result<(), WouldBlock> {
+        if self.eof {
+            Ok(())
+        } else {
+            Err(WouldBlock)
+        }
+    }
+}
+
+impl<'a> Reader for BufferReader<'a> {
+    type Error = WouldBlock;
+
+    fn read_byte(&mut self) -> Result<Option<u8>, Self::Error> {
+        match self.input.split_first() {
+            Some((&x, rest)) => {
+                self.input = rest;
+                Ok(Some(x))
+            }
+            None => self.end_of_input().map(|()| None),
+        }
+    }
+
+    fn try_read_string(&mut self, s: &[u8], case_sensitive: bool) -> Result<bool, Self::Error> {
+        let n = s.len().min(self.input.len());
+        let (s1, s2) = (&s[..n], &self.input[..n]);
+        if !(s1 == s2 || (!case_sensitive && s1.eq_ignore_ascii_case(s2))) {
+            return Ok(false);
+        }
+
+        if n < s.len() {
+            // the input ends in the middle of `s`
+            return self.end_of_input().map(|()| false);
+        }
+
+        self.input = &self.input[n..];
+        Ok(true)
+    }
+
+    fn read_until<'b>(
+        &'b mut self,
+        needle: &[u8],
+        _char_buf: &'b mut [u8; 4],
+    ) -> Result<Option<&'b [u8]>, Self::Error> {
+        if self.input.is_empty() {
+            return self.end_of_input().map(|()| None);
+        }
+
+        let len = match fast_find(needle, self.input) {
+            Some(0) => 1,
+            Some(needle_pos) => needle_pos,
+            None => self.input.len(),
+        };
+        let (rv, rest) = self.input.split_at(len);
+        self.input = rest;
+        Ok(Some(rv))
+    }
+}
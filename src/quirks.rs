@@ -0,0 +1,286 @@
+//! Decide whether a document is rendered in quirks mode, based on its doctype.
+//!
+//! Browsers use the doctype to pick one of three [document
+//! modes](https://dom.spec.whatwg.org/#concept-document-quirks), which affect both tree
+//! construction and rendering. [`classify_doctype`] implements the rules of the [initial insertion
+//! mode](https://html.spec.whatwg.org/#the-initial-insertion-mode), so that the mode can be
+//! determined without building a tree.
+//!
+//! ```rust
+//! use html5gum::quirks::{classify_doctype, QuirksMode};
+//! use html5gum::{Token, Tokenizer};
+//!
+//! let input = r#"<!DOCTYPE html PUBLIC "-//W3C//DTD HTML 4.01 Transitional//EN"><p>hello"#;
+//! let mode = match Tokenizer::new(input).flatten().next() {
+//!     Some(Token::Doctype(doctype)) => classify_doctype(
+//!         &doctype.name,
+//!         doctype.public_identifier.as_deref().map(Vec::as_slice),
+//!         doctype.system_identifier.as_deref().map(Vec::as_slice),
+//!         doctype.force_quirks,
+//!     ),
+//!     _ => QuirksMode::Quirks,
+//! };
+//! assert_eq!(mode, QuirksMode::Quirks);
+//! ```
+//!
+//! A document without a doctype is in quirks mode, unless it is an `iframe` `srcdoc` document.
+
+/// The [mode](https://dom.spec.whatwg.org/#concept-document-quirks) of a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QuirksMode {
+    /// "no-quirks mode", also known as standards mode, for example for `<!DOCTYPE html>`.
+    NoQuirks,
+    /// "limited-quirks mode", also known as almost standards mode, for example for XHTML 1.0
+    /// Transitional.
+    LimitedQuirks,
+    /// "quirks mode", for old and missing doctypes.
+    Quirks,
+}
+
+/// Public identifiers that are compared as a whole.
+const QUIRKY_PUBLIC_IDS: &[&[u8]] = &[
+    b"-//W3O//DTD W3 HTML Strict 3.0//EN//",
+    b"-/W3C/DTD HTML 4.0 Transitional/EN",
+    b"HTML",
+];
+
+/// Prefixes of public identifiers that always cause quirks mode.
+const QUIRKY_PUBLIC_ID_PREFIXES: &[&[u8]] = &[
+    b"+//Silmaril//dtd html Pro v0r11 19970101//",
+    b"-//AS//DTD HTML 3.0 asWedit + extensions//",
+    b"-//AdvaSoft Ltd//DTD HTML 3.0 asWedit + extensions//",
+    b"-//IETF//DTD HTML 2.0 Level 1//",
+    b"-//IETF//DTD HTML 2.0 Level 2//",
+    b"-//IETF//DTD HTML 2.0 Strict Level 1//",
+    b"-//IETF//DTD HTML 2.0 Strict Level 2//",
+    b"-//IETF//DTD HTML 2.0 Strict//",
+    b"-//IETF//DTD HTML 2.0//",
+    b"-//IETF//DTD HTML 2.1E//",
+    b"-//IETF//DTD HTML 3.0//",
+    b"-//IETF//DTD HTML 3.2 Final//",
+    b"-//IETF//DTD HTML 3.2//",
+    b"-//IETF//DTD HTML 3//",
+    b"-//IETF//DTD HTML Level 0//",
+    b"-//IETF//DTD HTML Level 1//",
+    b"-//IETF//DTD HTML Level 2//",
+    b"-//IETF//DTD HTML Level 3//",
+    b"-//IETF//DTD HTML Strict Level 0//",
+    b"-//IETF//DTD HTML Strict Level 1//",
+    b"-//IETF//DTD HTML Strict Level 2//",
+    b"-//IETF//DTD HTML Strict Level 3//",
+    b"-//IETF//DTD HTML Strict//",
+    b"-//IETF//DTD HTML//",
+    b"-//Metrius//DTD Metrius Presentational//",
+    b"-//Microsoft//DTD Internet Explorer 2.0 HTML Strict//",
+    b"-//Microsoft//DTD Internet Explorer 2.0 HTML//",
+    b"-//Microsoft//DTD Internet Explorer 2.0 Tables//",
+    b"-//Microsoft//DTD Internet Explorer 3.0 HTML Strict//",
+    b"-//Microsoft//DTD Internet Explorer 3.0 HTML//",
+    b"-//Microsoft//DTD Internet Explorer 3.0 Tables//",
+    b"-//Netscape Comm. Corp.//DTD HTML//",
+    b"-//Netscape Comm. Corp.//DTD Strict HTML//",
+    b"-//O'Reilly and Associates//DTD HTML 2.0//",
+    b"-//O'Reilly and Associates//DTD HTML Extended 1.0//",
+    b"-//O'Reilly and Associates//DTD HTML Extended Relaxed 1.0//",
+    b"-//SQ//DTD HTML 2.0 HoTMetaL + extensions//",
+    b"-//SoftQuad Software//DTD HoTMetaL PRO 6.0::19990601::extensions to HTML 4.0//",
+    b"-//SoftQuad//DTD HoTMetaL PRO 4.0::19971010::extensions to HTML 4.0//",
+    b"-//Spyglass//DTD HTML 2.0 Extended//",
+    b"-//Sun Microsystems Corp.//DTD HotJava HTML//",
+    b"-//Sun Microsystems Corp.//DTD HotJava Strict HTML//",
+    b"-//W3C//DTD HTML 3 1995-03-24//",
+    b"-//W3C//DTD HTML 3.2 Draft//",
+    b"-//W3C//DTD HTML 3.2 Final//",
+    b"-//W3C//DTD HTML 3.2//",
+    b"-//W3C//DTD HTML 3.2S Draft//",
+    b"-//W3C//DTD HTML 4.0 Frameset//",
+    b"-//W3C//DTD HTML 4.0 Transitional//",
+    b"-//W3C//DTD HTML Experimental 19960712//",
+    b"-//W3C//DTD HTML Experimental 970421//",
+    b"-//W3C//DTD W3 HTML//",
+    b"-//W3O//DTD W3 HTML 3.0//",
+    b"-//WebTechs//DTD Mozilla HTML 2.0//",
+    b"-//WebTechs//DTD Mozilla HTML//",
+];
+
+const QUIRKY_SYSTEM_ID: &[u8] = b"http://www.ibm.com/data/dtd/v11/ibmxhtml1-transitional.dtd";
+
+/// Prefixes of public identifiers that cause quirks mode without a system identifier, and
+/// limited-quirks mode with one.
+const HTML_401_PUBLIC_ID_PREFIXES: &[&[u8]] = &[
+    b"-//W3C//DTD HTML 4.01 Frameset//",
+    b"-//W3C//DTD HTML 4.01 Transitional//",
+];
+
+/// Prefixes of public identifiers that always cause limited-quirks mode.
+const LIMITED_QUIRKS_PUBLIC_ID_PREFIXES: &[&[u8]] = &[
+    b"-//W3C//DTD XHTML 1.0 Frameset//",
+    b"-//W3C//DTD XHTML 1.0 Transitional//",
+];
+
+fn starts_with_any(id: &[u8], prefixes: &[&[u8]]) -> bool {
+    prefixes.iter().any(|prefix| {
+        id.get(..prefix.len())
+            .is_some_and(|x| x.eq_ignore_ascii_case(prefix))
+    })
+}
+
+/// Determine the document mode from the parts of a doctype token, as the [initial insertion
+/// mode](https://html.spec.whatwg.org/#the-initial-insertion-mode) does.
+///
+/// The identifiers are compared ASCII case-insensitively. The name is compared exactly, as the
+/// tokenizer already converts it to lowercase.
+///
+/// ```rust
+/// use html5gum::quirks::{classify_doctype, QuirksMode};
+///
+/// assert_eq!(classify_doctype(b"html", None, None, false), QuirksMode::NoQuirks);
+/// assert_eq!(
+///     classify_doctype(b"html", Some(b"-//W3C//DTD XHTML 1.0 Transitional//EN"), None, false),
+///     QuirksMode::LimitedQuirks
+/// );
+/// assert_eq!(classify_doctype(b"html", None, None, true), QuirksMode::Quirks);
+/// ```
+pub fn classify_doctype(
+    name: &[u8],
+    public_id: Option<&[u8]>,
+    system_id: Option<&[u8]>,
+    force_quirks: bool,
+) -> QuirksMode {
+    if force_quirks || name != b"html" {
+        return QuirksMode::Quirks;
+    }
+
+    if system_id.is_some_and(|id| id.eq_ignore_ascii_case(QUIRKY_SYSTEM_ID)) {
+        return QuirksMode::Quirks;
+    }
+
+    let Some(public_id) = public_id else {
+        return QuirksMode::NoQuirks;
+    };
+
+    if QUIRKY_PUBLIC_IDS
+        .iter()
+        .any(|id| public_id.eq_ignore_ascii_case(id))
+        || starts_with_any(public_id, QUIRKY_PUBLIC_ID_PREFIXES)
+    {
+        QuirksMode::Quirks
+    } else if starts_with_any(public_id, HTML_401_PUBLIC_ID_PREFIXES) {
+        if system_id.is_none() {
+            QuirksMode::Quirks
+        } else {
+            QuirksMode::LimitedQuirks
+        }
+    } else if starts_with_any(public_id, LIMITED_QUIRKS_PUBLIC_ID_PREFIXES) {
+        QuirksMode::LimitedQuirks
+    } else {
+        QuirksMode::NoQuirks
+    }
+}
+
+#[test]
+fn mixed_case_public_ids() {
+    for public_id in [
+        "-//W3C//DTD HTML 3.2 Final//EN",
+        "-//w3c//dtd html 3.2 final//en",
+        "-//W3c//Dtd Html 3.2 FINAL//EN",
+        "html",
+        "-/w3c/dtd html 4.0 transitional/en",
+    ] {
+        assert_eq!(
+            classify_doctype(b"html", Some(public_id.as_bytes()), None, false),
+            QuirksMode::Quirks,
+            "{}",
+            public_id
+        );
+    }
+
+    // the whole-identifier matches are not prefixes
+    assert_eq!(
+        classify_doctype(b"html", Some(b"HTML 5"), None, false),
+        QuirksMode::NoQuirks
+    );
+}
+
+#[test]
+fn html_401() {
+    let public_id = Some(&b"-//w3c//dtd html 4.01 transitional//en"[..]);
+    assert_eq!(
+        classify_doctype(b"html", public_id, None, false),
+        QuirksMode::Quirks
+    );
+    assert_eq!(
+        classify_doctype(
+            b"html",
+            public_id,
+            Some(b"http://www.w3.org/TR/html4/loose.dtd"),
+            false
+        ),
+        QuirksMode::LimitedQuirks
+    );
+    assert_eq!(
+        classify_doctype(
+            b"html",
+            Some(b"-//W3C//DTD HTML 4.01//EN"),
+            Some(b"http://www.w3.org/TR/html4/strict.dtd"),
+            false
+        ),
+        QuirksMode::NoQuirks
+    );
+}
+
+#[test]
+fn xhtml() {
+    assert_eq!(
+        classify_doctype(
+            b"html",
+            Some(b"-//W3C//DTD XHTML 1.0 Frameset//EN"),
+            Some(b"http://www.w3.org/TR/xhtml1/DTD/xhtml1-frameset.dtd"),
+            false
+        ),
+        QuirksMode::LimitedQuirks
+    );
+    assert_eq!(
+        classify_doctype(
+            b"html",
+            Some(b"-//W3C//DTD XHTML 1.0 Strict//EN"),
+            Some(b"http://www.w3.org/TR/xhtml1/DTD/xhtml1-strict.dtd"),
+            false
+        ),
+        QuirksMode::NoQuirks
+    );
+}
+
+#[test]
+fn system_ids() {
+    assert_eq!(
+        classify_doctype(b"html", None, Some(b"about:legacy-compat"), false),
+        QuirksMode::NoQuirks
+    );
+    assert_eq!(
+        classify_doctype(
+            b"html",
+            None,
+            Some(b"HTTP://www.IBM.com/data/dtd/v11/ibmxhtml1-transitional.dtd"),
+            false
+        ),
+        QuirksMode::Quirks
+    );
+}
+
+#[test]
+fn name_and_force_quirks() {
+    assert_eq!(
+        classify_doctype(b"html", None, None, false),
+        QuirksMode::NoQuirks
+    );
+    assert_eq!(
+        classify_doctype(b"html", None, None, true),
+        QuirksMode::Quirks
+    );
+    assert_eq!(classify_doctype(b"", None, None, false), QuirksMode::Quirks);
+    assert_eq!(
+        classify_doctype(b"svg", None, None, false),
+        QuirksMode::Quirks
+    );
+}
@@ -1,51 +1,217 @@
+use std::fmt::{self, Debug, Formatter};
+
 use crate::char_validator::CharValidator;
-use crate::Emitter;
-use crate::Reader;
+#[cfg(feature = "trace")]
+use crate::emitters::trace::TraceEvent;
+use crate::reader::fast_find;
+use crate::span::LineIndex;
+#[cfg(debug_assertions)]
+use crate::utils::DebugBytes;
+use crate::{Emitter, Error, Reader, Utf8Policy};
 
-#[derive(Debug)]
 pub(crate) struct ReadHelper<R: Reader> {
-    reader: R,
+    reader: ByteLimit<R>,
+    /// `None` for `Utf8Policy::PassThrough`.
+    utf8_check: Option<Utf8Check>,
+    /// The offset into the input right after the last character that was consumed (and not
+    /// unread).
+    pub(crate) position: usize,
+    /// See `Tokenizer::track_lines`.
+    pub(crate) line_index: Option<LineIndex>,
     last_character_was_cr: bool,
-    #[allow(clippy::option_option)]
-    to_reconsume: Option<Option<u8>>,
+    to_reconsume: ToReconsume,
+    #[cfg(debug_assertions)]
+    recently_read: RecentlyRead,
+    /// The last byte that was consumed, or `None` at the end of the input. Reported with state
+    /// transitions by `Tokenizer::switch_to`.
+    #[cfg(feature = "trace")]
+    pub(crate) last_byte: Option<u8>,
+}
+
+impl<R: Reader> Debug for ReadHelper<R> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // The reader itself is left out, as it may contain the entire input.
+        let mut debug = f.debug_struct("ReadHelper");
+        debug
+            .field("position", &self.position)
+            .field("last_character_was_cr", &self.last_character_was_cr)
+            .field("to_reconsume", &self.to_reconsume)
+            .field("remaining_bytes", &self.reader.remaining);
+        #[cfg(debug_assertions)]
+        debug.field("recently_read", &self.recently_read);
+        debug.finish_non_exhaustive()
+    }
 }
 
 impl<R: Reader> ReadHelper<R> {
-    pub(crate) fn new(reader: R) -> Self {
+    pub(crate) fn new(reader: R, position: usize) -> Self {
         ReadHelper {
-            reader,
+            reader: ByteLimit {
+                reader,
+                remaining: usize::MAX,
+                truncated: None,
+                read_past_limit: Vec::new(),
+            },
+            utf8_check: None,
+            position,
+            line_index: None,
             last_character_was_cr: false,
-            to_reconsume: None,
+            to_reconsume: ToReconsume::default(),
+            #[cfg(debug_assertions)]
+            recently_read: RecentlyRead::default(),
+            #[cfg(feature = "trace")]
+            last_byte: None,
         }
     }
 
+    /// Start over with another reader, for `Tokenizer::reset_with_reader`. Anything that was
+    /// going to be reconsumed is dropped, and the line index and UTF-8 policy are kept.
+    pub(crate) fn reset<R2: Reader>(self, reader: R2) -> ReadHelper<R2> {
+        let mut helper = ReadHelper::new(reader, 0);
+        helper.set_utf8_policy(
+            self.utf8_check
+                .map_or(Utf8Policy::PassThrough, |x| x.policy),
+        );
+        helper.line_index = self.line_index.map(|mut line_index| {
+            line_index.reset(0);
+            line_index
+        });
+        helper
+    }
+
+    /// See `Tokenizer::utf8_policy`.
+    pub(crate) fn set_utf8_policy(&mut self, policy: Utf8Policy) {
+        self.utf8_check = match policy {
+            Utf8Policy::PassThrough => None,
+            policy => Some(Utf8Check::new(policy)),
+        };
+    }
+
+    /// See `Tokenizer::take_bytes`.
+    pub(crate) fn take_bytes(&mut self, n: usize) {
+        self.reader.remaining = n;
+        self.reader.truncated = None;
+    }
+
+    /// Read no more input, see `Emitter::is_aborted`.
+    pub(crate) fn abort(&mut self) {
+        self.reader.remaining = 0;
+        self.reader.truncated = Some(true);
+    }
+
+    /// Bounds on the number of bytes that have not been consumed yet, see `Reader::size_hint`.
+    pub(crate) fn size_hint(&self) -> (u64, Option<u64>) {
+        let reconsumed = (self.to_reconsume.len
+            + self.utf8_check.as_ref().map_or(0, |x| x.buffered().len()))
+            as u64;
+        let (lower, upper) = self.reader.size_hint();
+        (lower + reconsumed, upper.map(|upper| upper + reconsumed))
+    }
+
+    /// Return the reader and the bytes that were taken from it but not consumed, for
+    /// `Tokenizer::into_reader`.
+    pub(crate) fn into_reader(self) -> (R, Vec<u8>) {
+        let mut read_ahead = self.to_reconsume.bytes_in_order();
+        // A \r that is reconsumed has already been turned into \n. It is always the last byte
+        // that was read, so the flag tells them apart.
+        if self.last_character_was_cr && read_ahead == b"\n" {
+            read_ahead[0] = b'\r';
+        }
+        if let Some(utf8_check) = &self.utf8_check {
+            read_ahead.extend(utf8_check.buffered());
+        }
+        read_ahead.extend(self.reader.read_past_limit);
+        (self.reader.reader, read_ahead)
+    }
+
+    /// Whether input was left unread because of `take_bytes` or `abort`.
+    pub(crate) fn is_truncated(&self) -> bool {
+        self.reader.truncated == Some(true)
+    }
+
     #[inline(always)]
     pub(crate) fn read_byte<E: Emitter>(
         &mut self,
         char_validator: &mut CharValidator,
         emitter: &mut E,
     ) -> Result<Option<u8>, R::Error> {
-        if let Some(c) = self.to_reconsume.take() {
-            return Ok(c);
+        if let Some(x) = self.to_reconsume.pop_front() {
+            self.position += 1;
+            #[cfg(feature = "trace")]
+            {
+                self.last_byte = Some(x);
+            }
+            report_position(emitter, self.position, &self.line_index);
+            return Ok(Some(x));
+        }
+
+        if self.to_reconsume.eof {
+            self.to_reconsume.eof = false;
+            #[cfg(feature = "trace")]
+            {
+                self.last_byte = None;
+            }
+            report_position(emitter, self.position, &self.line_index);
+            return Ok(None);
+        }
+
+        let c = self.read_new_byte(char_validator, emitter);
+        #[cfg(feature = "trace")]
+        if let Ok(x) = c {
+            self.last_byte = x;
+        }
+        c
+    }
+
+    /// Read a byte from the reader, bypassing `to_reconsume`.
+    #[inline(always)]
+    fn read_new_byte<E: Emitter>(
+        &mut self,
+        char_validator: &mut CharValidator,
+        emitter: &mut E,
+    ) -> Result<Option<u8>, R::Error> {
+        let mut c = self.read_input_byte(emitter);
+        if self.last_character_was_cr && matches!(c, Ok(Some((b'\n', _)))) {
+            #[cfg(debug_assertions)]
+            self.recently_read.push(b"\n");
+            track_lines(&mut self.line_index, self.position, b"\n");
+            self.position += 1;
+            c = self.read_input_byte(emitter);
         }
 
-        let mut c = self.reader.read_byte();
-        if self.last_character_was_cr && matches!(c, Ok(Some(b'\n'))) {
-            c = self.reader.read_byte();
+        if let Ok(Some((x, _))) = c {
+            #[cfg(debug_assertions)]
+            self.recently_read.push(&[x]);
+            track_lines(&mut self.line_index, self.position, &[x]);
         }
 
-        if matches!(c, Ok(Some(b'\r'))) {
+        if matches!(c, Ok(Some((b'\r', _)))) {
             self.last_character_was_cr = true;
-            c = Ok(Some(b'\n'));
+            c = Ok(Some((b'\n', 1)));
         } else {
             self.last_character_was_cr = false;
         }
 
-        if let Ok(Some(x)) = c {
-            char_validator.validate_byte(emitter, x);
+        if let Ok(Some((x, input_len))) = c {
+            char_validator.validate_byte(emitter, x, self.position);
+            self.position += input_len;
         }
 
-        c
+        report_position(emitter, self.position, &self.line_index);
+        c.map(|c| c.map(|(x, _)| x))
+    }
+
+    /// Read a byte from the reader, through `utf8_check`. Returns the byte, and how many bytes
+    /// of the input it stands for.
+    #[inline(always)]
+    fn read_input_byte<E: Emitter>(
+        &mut self,
+        emitter: &mut E,
+    ) -> Result<Option<(u8, usize)>, R::Error> {
+        match &mut self.utf8_check {
+            Some(utf8_check) => utf8_check.read_byte(&mut self.reader, emitter, self.position),
+            None => Ok(self.reader.read_byte()?.map(|x| (x, 1))),
+        }
     }
 
     #[inline(always)]
@@ -59,25 +225,42 @@ impl<R: Reader> ReadHelper<R> {
         debug_assert!(!s.contains('\r'));
 
         let to_reconsume_bak = self.to_reconsume;
-        let mut bytes = s.as_bytes().iter();
-        if let Some(c) = self.to_reconsume.take() {
-            match (c, bytes.next()) {
-                (Some(x), Some(&x2))
-                    if x == x2
-                        || (!case_sensitive
-                            && x.to_ascii_lowercase() == x2.to_ascii_lowercase()) =>
-                {
-                    s = &s[1..];
-                }
-                _ => {
+        let mut consumed = 0;
+        for &x2 in s.as_bytes() {
+            match self.to_reconsume.pop_front() {
+                Some(x) if x == x2 || (!case_sensitive && x.eq_ignore_ascii_case(&x2)) => {
+                    consumed += 1;
+                }
+                Some(_) => {
                     self.to_reconsume = to_reconsume_bak;
                     return Ok(false);
                 }
+                None => break,
             }
         }
+        s = &s[consumed..];
+        if !s.is_empty() && self.to_reconsume.eof {
+            self.to_reconsume = to_reconsume_bak;
+            return Ok(false);
+        }
 
-        if s.is_empty() || self.reader.try_read_string(s.as_bytes(), case_sensitive)? {
+        let matches = match &mut self.utf8_check {
+            _ if s.is_empty() => true,
+            Some(utf8_check) => {
+                utf8_check.try_read_string(&mut self.reader, s.as_bytes(), case_sensitive)?
+            }
+            None => self.reader.try_read_string(s.as_bytes(), case_sensitive)?,
+        };
+        if matches {
+            #[cfg(debug_assertions)]
+            self.recently_read.push(s.as_bytes());
+            track_lines(&mut self.line_index, self.position + consumed, s.as_bytes());
+            self.position += consumed + s.len();
             self.last_character_was_cr = false;
+            #[cfg(feature = "trace")]
+            {
+                self.last_byte = s.as_bytes().last().copied().or(self.last_byte);
+            }
             char_validator.reset();
             Ok(true)
         } else {
@@ -99,15 +282,53 @@ impl<R: Reader> ReadHelper<R> {
     {
         const MAX_NEEDLE_LEN: usize = 13;
 
-        match self.to_reconsume.take() {
-            Some(Some(x)) => {
-                return Ok(Some({
-                    char_buf[0] = x;
-                    &char_buf[..1]
-                }))
+        if let Some(x) = self.to_reconsume.pop_front() {
+            self.position += 1;
+            char_buf[0] = x;
+            let mut len = 1;
+
+            // Return a whole character, so that chunks always end at character boundaries.
+            if x >= 0x80 {
+                while len < 4 && self.to_reconsume.front().is_some_and(is_continuation_byte) {
+                    let Some(x) = self.to_reconsume.pop_front() else {
+                        break;
+                    };
+                    self.position += 1;
+                    char_buf[len] = x;
+                    len += 1;
+                }
+
+                while len < utf8_sequence_len(char_buf[0]) {
+                    report_position(emitter, self.position, &self.line_index);
+                    match self.read_new_byte(char_validator, emitter)? {
+                        Some(x) if is_continuation_byte(x) => {
+                            char_buf[len] = x;
+                            len += 1;
+                        }
+                        c => {
+                            self.unread_byte(c);
+                            break;
+                        }
+                    }
+                }
             }
-            Some(None) => return Ok(None),
-            None => (),
+
+            #[cfg(feature = "trace")]
+            {
+                self.last_byte = Some(char_buf[len - 1]);
+            }
+            report_position(emitter, self.position, &self.line_index);
+            return Ok(Some(&char_buf[..len]));
+        }
+
+        if self.to_reconsume.eof {
+            self.to_reconsume.eof = false;
+            #[cfg(feature = "trace")]
+            {
+                self.last_byte = None;
+            }
+            report_position(emitter, self.position, &self.line_index);
+            return Ok(None);
         }
 
         let mut needle2 = [b'\0'; MAX_NEEDLE_LEN];
@@ -118,32 +339,800 @@ impl<R: Reader> ReadHelper<R> {
         needle2[needle.len()] = b'\r';
         let needle2_slice = &needle2[..=needle.len()];
 
-        match self.reader.read_until(needle2_slice, char_buf)? {
+        // how many bytes of the input the chunk stands for, which is only different from its
+        // length if it is a replacement character
+        let (rv, input_len) = match &mut self.utf8_check {
+            Some(utf8_check) => match utf8_check.read_until(
+                &mut self.reader,
+                needle2_slice,
+                char_buf,
+                emitter,
+                self.position,
+            )? {
+                Some((xs, input_len)) => (Some(xs), input_len),
+                None => (None, 0),
+            },
+            None => {
+                let rv = self.reader.read_until(needle2_slice, char_buf)?;
+                (rv, rv.map_or(0, <[u8]>::len))
+            }
+        };
+
+        if let Some(xs) = rv {
+            #[cfg(debug_assertions)]
+            self.recently_read.push(xs);
+            track_lines(&mut self.line_index, self.position, xs);
+        }
+
+        #[cfg(feature = "trace")]
+        {
+            self.last_byte = rv.and_then(|xs| xs.last().copied());
+            if let Some(xs) = rv {
+                emitter.trace(TraceEvent::Read { bytes: xs.len() });
+            }
+        }
+
+        match rv {
             Some(b"\r") => {
                 self.last_character_was_cr = true;
-                char_validator.validate_byte(emitter, b'\n');
+                char_validator.validate_byte(emitter, b'\n', self.position);
+                self.position += 1;
+                report_position(emitter, self.position, &self.line_index);
                 Ok(Some(b"\n"))
             }
             Some(mut xs) => {
-                char_validator.validate_bytes(emitter, xs);
+                char_validator.validate_bytes(emitter, xs, self.position);
+                self.position += input_len;
+                report_position(emitter, self.position, &self.line_index);
+
+                // An empty chunk does not tell us anything about the character after \r.
+                if xs.is_empty() {
+                    return Ok(Some(xs));
+                }
 
                 if self.last_character_was_cr && xs.starts_with(b"\n") {
                     xs = &xs[1..];
                 }
 
                 self.last_character_was_cr = false;
+
+                // If the reader split a character, hold back its start until the rest has been
+                // read. The chunk may become empty this way.
+                let tail_len = incomplete_tail_len(xs);
+                if tail_len > 0 {
+                    let (head, tail) = xs.split_at(xs.len() - tail_len);
+                    for &x in tail {
+                        self.to_reconsume.push_back(x);
+                    }
+                    self.position -= tail_len;
+                    report_position(emitter, self.position, &self.line_index);
+                    xs = head;
+                }
+
                 Ok(Some(xs))
             }
             None => {
                 self.last_character_was_cr = false;
+                report_position(emitter, self.position, &self.line_index);
                 Ok(None)
             }
         }
     }
 
+    /// Push back a byte or EOF that was just returned by `read_byte`, so that the next read
+    /// returns it again.
+    ///
+    /// This is how the state machine reconsumes characters, and the only place where `position`
+    /// moves backwards because of it. Only a single character can be pushed back at a time.
     #[inline]
     pub(crate) fn unread_byte(&mut self, c: Option<u8>) {
-        self.to_reconsume = Some(c);
+        debug_assert!(!self.to_reconsume.eof);
+        match c {
+            Some(x) => {
+                self.position -= 1;
+                self.to_reconsume.push_front(x);
+            }
+            None => self.to_reconsume.eof = true,
+        }
+    }
+
+    /// Discard input up to and including the next `<`, for `Emitter::should_skip_rawtext`.
+    ///
+    /// Returns whether a `<` was found before the end of the input. Like
+    /// `skip_until_plausible_tag`, the discarded bytes bypass input stream preprocessing and
+    /// validation entirely.
+    pub(crate) fn skip_until_lt<E: Emitter>(
+        &mut self,
+        char_validator: &mut CharValidator,
+        emitter: &mut E,
+    ) -> Result<bool, R::Error> {
+        char_validator.reset();
+        self.last_character_was_cr = false;
+
+        while let Some(x) = self.to_reconsume.pop_front() {
+            self.position += 1;
+            if x == b'<' {
+                report_position(emitter, self.position, &self.line_index);
+                return Ok(true);
+            }
+        }
+        if let Some(utf8_check) = &mut self.utf8_check {
+            while let Some(x) = utf8_check.pop_buffered() {
+                track_lines(&mut self.line_index, self.position, &[x]);
+                self.position += 1;
+                if x == b'<' {
+                    report_position(emitter, self.position, &self.line_index);
+                    return Ok(true);
+                }
+            }
+            if utf8_check.eof {
+                utf8_check.eof = false;
+                self.to_reconsume.eof = true;
+            }
+        }
+
+        let mut char_buf = [0; 4];
+        let found = loop {
+            if self.to_reconsume.eof {
+                self.to_reconsume.eof = false;
+                break false;
+            }
+
+            match self.reader.read_until(b"<", &mut char_buf)? {
+                Some(xs) => {
+                    #[cfg(feature = "trace")]
+                    emitter.trace(TraceEvent::Read { bytes: xs.len() });
+                    track_lines(&mut self.line_index, self.position, xs);
+                    self.position += xs.len();
+                    if xs == b"<" {
+                        break true;
+                    }
+                }
+                None => break false,
+            }
+        };
+
+        #[cfg(feature = "trace")]
+        {
+            self.last_byte = found.then_some(b'<');
+        }
+        report_position(emitter, self.position, &self.line_index);
+        Ok(found)
+    }
+
+    /// Discard input until one of [`PLAUSIBLE_TAGS`] is found, followed by a space or `>`.
+    ///
+    /// Returns the number of discarded bytes and the tag that was found, if any. The tag itself has
+    /// been consumed, but the byte after it is reconsumed. The discarded bytes bypass input
+    /// stream preprocessing and validation entirely.
+    pub(crate) fn skip_until_plausible_tag(
+        &mut self,
+    ) -> Result<(usize, Option<&'static [u8]>), R::Error> {
+        debug_assert!(self.to_reconsume.is_empty());
+        debug_assert!(self
+            .utf8_check
+            .as_ref()
+            .is_none_or(|x| x.buffered().is_empty()));
+
+        let mut skipped = 0;
+        let mut char_buf = [0; 4];
+        let mut found_lt = false;
+
+        loop {
+            if !found_lt {
+                match self.reader.read_until(b"<", &mut char_buf)? {
+                    Some(b"<") => track_lines(&mut self.line_index, self.position + skipped, b"<"),
+                    Some(xs) => {
+                        track_lines(&mut self.line_index, self.position + skipped, xs);
+                        skipped += xs.len();
+                        continue;
+                    }
+                    None => {
+                        self.position += skipped;
+                        return Ok((skipped, None));
+                    }
+                }
+            }
+
+            found_lt = false;
+            skipped += 1;
+
+            for &tag in PLAUSIBLE_TAGS {
+                if self.reader.try_read_string(tag, false)? {
+                    track_lines(&mut self.line_index, self.position + skipped, tag);
+                    let c = self.reader.read_byte()?;
+                    if let Some(c) = c {
+                        track_lines(
+                            &mut self.line_index,
+                            self.position + skipped + tag.len(),
+                            &[c],
+                        );
+                    }
+
+                    match c {
+                        Some(c @ (b' ' | b'>')) => {
+                            self.to_reconsume.push_back(c);
+                            self.last_character_was_cr = false;
+                            self.position += skipped + tag.len();
+                            return Ok((skipped - 1, Some(tag)));
+                        }
+                        Some(b'<') => {
+                            skipped += tag.len();
+                            found_lt = true;
+                        }
+                        Some(_) => skipped += tag.len() + 1,
+                        None => {
+                            self.position += skipped + tag.len();
+                            return Ok((skipped + tag.len(), None));
+                        }
+                    }
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// A reader that ends after a number of bytes. See `Tokenizer::take_bytes`.
+#[derive(Debug)]
+struct ByteLimit<R> {
+    reader: R,
+    remaining: usize,
+    /// Whether there was more input after the limit, once the limit has been reached.
+    truncated: Option<bool>,
+    /// Bytes that were taken from `reader` after the limit: the byte that tells whether there is
+    /// more input, or the rest of a chunk that was cut off.
+    read_past_limit: Vec<u8>,
+}
+
+impl<R: Reader> ByteLimit<R> {
+    #[cold]
+    fn reached_limit(&mut self) -> Result<(), R::Error> {
+        if self.truncated.is_none() {
+            let c = self.reader.read_byte()?;
+            self.read_past_limit.extend(c);
+            self.truncated = Some(c.is_some());
+        }
+
+        Ok(())
+    }
+}
+
+impl<R: Reader> Reader for ByteLimit<R> {
+    type Error = R::Error;
+
+    #[inline(always)]
+    fn read_byte(&mut self) -> Result<Option<u8>, Self::Error> {
+        if self.remaining == 0 {
+            self.reached_limit()?;
+            return Ok(None);
+        }
+
+        let c = self.reader.read_byte()?;
+        if c.is_some() {
+            self.remaining -= 1;
+        }
+        Ok(c)
+    }
+
+    #[inline(always)]
+    fn try_read_string(&mut self, s: &[u8], case_sensitive: bool) -> Result<bool, Self::Error> {
+        if s.len() > self.remaining {
+            return Ok(false);
+        }
+
+        let rv = self.reader.try_read_string(s, case_sensitive)?;
+        if rv {
+            self.remaining -= s.len();
+        }
+        Ok(rv)
+    }
+
+    #[inline(always)]
+    fn read_until<'b>(
+        &'b mut self,
+        needle: &[u8],
+        char_buf: &'b mut [u8; 4],
+    ) -> Result<Option<&'b [u8]>, Self::Error> {
+        if self.remaining == 0 {
+            self.reached_limit()?;
+            return Ok(None);
+        }
+
+        match self.reader.read_until(needle, char_buf)? {
+            Some(mut xs) => {
+                if xs.len() > self.remaining {
+                    let (head, tail) = xs.split_at(self.remaining);
+                    self.read_past_limit.extend(tail);
+                    xs = head;
+                    self.truncated = Some(true);
+                }
+                self.remaining -= xs.len();
+                Ok(Some(xs))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> (u64, Option<u64>) {
+        let limit = self.remaining as u64;
+        let (lower, upper) = self.reader.size_hint();
+        if self.remaining == usize::MAX {
+            (lower, upper)
+        } else {
+            (
+                lower.min(limit),
+                Some(upper.map_or(limit, |upper| upper.min(limit))),
+            )
+        }
+    }
+}
+
+/// Finds input that is not valid UTF-8, and replaces or reports it, see `Tokenizer::utf8_policy`.
+///
+/// Sequences are decoded one at a time where the input is not valid UTF-8, so that no bytes of an
+/// invalid sequence reach the state machine before it is known to be invalid. Every byte that is
+/// returned either is a byte of the input, or part of a replacement character that stands for a
+/// whole invalid sequence. The latter is returned with the length of the sequence, so that
+/// positions keep referring to the input.
+#[derive(Debug)]
+struct Utf8Check {
+    policy: Utf8Policy,
+    /// Bytes that were taken from the reader, but not returned yet. They start at `start`.
+    ///
+    /// This is the rest of a chunk that contains invalid UTF-8, or the bytes that were read to
+    /// decide whether a sequence is valid.
+    lookahead: Vec<u8>,
+    start: usize,
+    /// How many bytes at the start of `lookahead` have been decoded already, and are returned
+    /// as they are.
+    decoded: usize,
+    /// Whether the reader returned EOF after `lookahead`.
+    eof: bool,
+    /// How many bytes of the current replacement character have not been returned yet.
+    replacement_left: usize,
+}
+
+/// U+FFFD REPLACEMENT CHARACTER
+const REPLACEMENT_CHARACTER: &[u8; 3] = b"\xef\xbf\xbd";
+
+impl Utf8Check {
+    fn new(policy: Utf8Policy) -> Self {
+        Utf8Check {
+            policy,
+            lookahead: Vec::new(),
+            start: 0,
+            decoded: 0,
+            eof: false,
+            replacement_left: 0,
+        }
+    }
+
+    fn buffered(&self) -> &[u8] {
+        &self.lookahead[self.start..]
+    }
+
+    /// Read the next byte, and return it together with the number of bytes of the input it
+    /// stands for.
+    fn read_byte<R: Reader, E: Emitter>(
+        &mut self,
+        reader: &mut R,
+        emitter: &mut E,
+        position: usize,
+    ) -> Result<Option<(u8, usize)>, R::Error> {
+        if let Some(rv) = self.pending_byte() {
+            return Ok(Some(rv));
+        }
+        self.decode_sequence(reader, emitter, position)
+    }
+
+    /// Return the next byte of a sequence that has been decoded already, if any.
+    fn pending_byte(&mut self) -> Option<(u8, usize)> {
+        if self.replacement_left > 0 {
+            let x = REPLACEMENT_CHARACTER[3 - self.replacement_left];
+            self.replacement_left -= 1;
+            Some((x, 0))
+        } else if self.decoded > 0 {
+            self.decoded -= 1;
+            self.start += 1;
+            Some((self.lookahead[self.start - 1], 1))
+        } else {
+            None
+        }
+    }
+
+    /// Read a chunk like `Reader::read_until`, and return it together with the number of bytes
+    /// of the input it stands for.
+    fn read_until<'b, R: Reader, E: Emitter>(
+        &'b mut self,
+        reader: &'b mut R,
+        needle: &[u8],
+        char_buf: &'b mut [u8; 4],
+        emitter: &mut E,
+        position: usize,
+    ) -> Result<Option<(&'b [u8], usize)>, R::Error> {
+        if self.replacement_left > 0 {
+            let rest = &REPLACEMENT_CHARACTER[3 - self.replacement_left..];
+            self.replacement_left = 0;
+            return Ok(Some((rest, 0)));
+        }
+
+        if self.decoded > 0 {
+            let range = self.start..self.start + self.decoded;
+            self.start += self.decoded;
+            self.decoded = 0;
+            return Ok(Some((&self.lookahead[range.clone()], range.len())));
+        }
+
+        if self.start < self.lookahead.len() {
+            return self.read_buffered(reader, needle, char_buf, emitter, position);
+        }
+
+        self.lookahead.clear();
+        self.start = 0;
+        if self.eof {
+            self.eof = false;
+            return Ok(None);
+        }
+
+        let Some(xs) = reader.read_until(needle, char_buf)? else {
+            return Ok(None);
+        };
+        match std::str::from_utf8(xs) {
+            Ok(_) => Ok(Some((xs, xs.len()))),
+            Err(e) => {
+                // The rest is decoded with the next call. The valid part may be empty.
+                let (valid, rest) = xs.split_at(e.valid_up_to());
+                self.lookahead.extend_from_slice(rest);
+                Ok(Some((valid, valid.len())))
+            }
+        }
+    }
+
+    /// `read_until` for when there are bytes in `lookahead`.
+    fn read_buffered<'b, R: Reader, E: Emitter>(
+        &'b mut self,
+        reader: &mut R,
+        needle: &[u8],
+        char_buf: &'b mut [u8; 4],
+        emitter: &mut E,
+        position: usize,
+    ) -> Result<Option<(&'b [u8], usize)>, R::Error> {
+        let buffered = self.buffered();
+        let valid_len = match std::str::from_utf8(buffered) {
+            Ok(_) => buffered.len(),
+            Err(e) => e.valid_up_to(),
+        };
+        if valid_len > 0 {
+            let len = match fast_find(needle, &buffered[..valid_len]) {
+                Some(0) => 1,
+                Some(needle_pos) => needle_pos,
+                None => valid_len,
+            };
+            let range = self.start..self.start + len;
+            self.start += len;
+            return Ok(Some((&self.lookahead[range], len)));
+        }
+
+        // Return the whole sequence, so that chunks always end at character boundaries.
+        let Some((x, mut input_len)) = self.decode_sequence(reader, emitter, position)? else {
+            return Ok(None);
+        };
+        char_buf[0] = x;
+        let mut len = 1;
+        while let Some((x, x_input_len)) = self.pending_byte() {
+            char_buf[len] = x;
+            len += 1;
+            input_len += x_input_len;
+        }
+        Ok(Some((&char_buf[..len], input_len)))
+    }
+
+    /// Decode the sequence that starts with the next byte of the input, and return its first
+    /// byte. The rest is returned by `pending_byte`.
+    fn decode_sequence<R: Reader, E: Emitter>(
+        &mut self,
+        reader: &mut R,
+        emitter: &mut E,
+        position: usize,
+    ) -> Result<Option<(u8, usize)>, R::Error> {
+        let Some(x) = self.next_input_byte(reader)? else {
+            return Ok(None);
+        };
+
+        // The number of continuation bytes, and the range of the first one, as in the UTF-8
+        // decoder of the Encoding spec.
+        let (needed, mut lower, mut upper) = match x {
+            0x00..=0x7f => return Ok(Some((x, 1))),
+            0xc2..=0xdf => (1, 0x80, 0xbf),
+            0xe0 => (2, 0xa0, 0xbf),
+            0xed => (2, 0x80, 0x9f),
+            0xe1..=0xef => (2, 0x80, 0xbf),
+            0xf0 => (3, 0x90, 0xbf),
+            0xf1..=0xf3 => (3, 0x80, 0xbf),
+            0xf4 => (3, 0x80, 0x8f),
+            _ => return Ok(Some(self.invalid_sequence(&[x], emitter, position))),
+        };
+
+        let mut sequence = [x, 0, 0, 0];
+        let mut len = 1;
+        while len <= needed {
+            match self.next_input_byte(reader) {
+                Ok(Some(y)) if (lower..=upper).contains(&y) => {
+                    sequence[len] = y;
+                    len += 1;
+                    lower = 0x80;
+                    upper = 0xbf;
+                }
+                Ok(y) => {
+                    // `y` starts the next sequence
+                    self.unread_input(y.as_slice());
+                    self.eof |= y.is_none();
+                    return Ok(Some(self.invalid_sequence(
+                        &sequence[..len],
+                        emitter,
+                        position,
+                    )));
+                }
+                Err(e) => {
+                    self.unread_input(&sequence[..len]);
+                    return Err(e);
+                }
+            }
+        }
+
+        self.unread_input(&sequence[1..len]);
+        self.decoded = len - 1;
+        Ok(Some((x, 1)))
+    }
+
+    fn invalid_sequence<E: Emitter>(
+        &mut self,
+        sequence: &[u8],
+        emitter: &mut E,
+        position: usize,
+    ) -> (u8, usize) {
+        match self.policy {
+            Utf8Policy::ReplaceInvalid => {
+                self.replacement_left = 2;
+                (REPLACEMENT_CHARACTER[0], sequence.len())
+            }
+            Utf8Policy::PassThrough | Utf8Policy::Error => {
+                if self.policy == Utf8Policy::Error && emitter.should_emit_errors() {
+                    emitter.set_position(position);
+                    emitter.emit_error(Error::InvalidUtf8InInputStream);
+                }
+                self.unread_input(&sequence[1..]);
+                self.decoded = sequence.len() - 1;
+                (sequence[0], 1)
+            }
+        }
+    }
+
+    fn next_input_byte<R: Reader>(&mut self, reader: &mut R) -> Result<Option<u8>, R::Error> {
+        if let Some(&x) = self.lookahead.get(self.start) {
+            self.start += 1;
+            Ok(Some(x))
+        } else if self.eof {
+            self.eof = false;
+            Ok(None)
+        } else {
+            reader.read_byte()
+        }
+    }
+
+    /// Put `bytes` in front of `lookahead`.
+    fn unread_input(&mut self, bytes: &[u8]) {
+        if self.start >= bytes.len() {
+            self.start -= bytes.len();
+            self.lookahead[self.start..self.start + bytes.len()].copy_from_slice(bytes);
+        } else {
+            self.lookahead.splice(..self.start, bytes.iter().copied());
+            self.start = 0;
+        }
+    }
+
+    /// See `Reader::try_read_string`. `s` is ASCII.
+    fn try_read_string<R: Reader>(
+        &mut self,
+        reader: &mut R,
+        s: &[u8],
+        case_sensitive: bool,
+    ) -> Result<bool, R::Error> {
+        if self.replacement_left > 0 || self.decoded > 0 {
+            // the next byte is not ASCII
+            return Ok(false);
+        }
+
+        let buffered = self.buffered();
+        let n = s.len().min(buffered.len());
+        let (s1, s2) = (&s[..n], &buffered[..n]);
+        if !(s1 == s2 || (!case_sensitive && s1.eq_ignore_ascii_case(s2))) {
+            return Ok(false);
+        }
+        if n < s.len() && (self.eof || !reader.try_read_string(&s[n..], case_sensitive)?) {
+            return Ok(false);
+        }
+
+        self.start += n;
+        Ok(true)
+    }
+
+    /// Discard the rest of the current sequence, and return the next byte of the input that has
+    /// been taken from the reader already, if any.
+    fn pop_buffered(&mut self) -> Option<u8> {
+        self.replacement_left = 0;
+        self.decoded = 0;
+        let x = self.lookahead.get(self.start).copied();
+        self.start += usize::from(x.is_some());
+        x
+    }
+}
+
+/// Bytes that have been consumed already, but are read again.
+///
+/// This is either a single byte or EOF that the state machine reconsumes, or the start of a
+/// character that `read_until` held back so that chunks end at character boundaries, or both.
+#[derive(Debug, Default, Clone, Copy)]
+struct ToReconsume {
+    /// The bytes in reverse order, so that the byte that is read next is the last one.
+    bytes: [u8; 4],
+    len: usize,
+    eof: bool,
+}
+
+impl ToReconsume {
+    fn is_empty(&self) -> bool {
+        self.len == 0 && !self.eof
+    }
+
+    /// The bytes in the order in which they are read.
+    fn bytes_in_order(self) -> Vec<u8> {
+        self.bytes[..self.len].iter().rev().copied().collect()
+    }
+
+    #[inline(always)]
+    fn push_front(&mut self, x: u8) {
+        self.bytes[self.len] = x;
+        self.len += 1;
+    }
+
+    fn push_back(&mut self, x: u8) {
+        self.bytes.copy_within(..self.len, 1);
+        self.bytes[0] = x;
+        self.len += 1;
+    }
+
+    #[inline(always)]
+    fn front(&self) -> Option<u8> {
+        self.len.checked_sub(1).map(|i| self.bytes[i])
+    }
+
+    #[inline(always)]
+    fn pop_front(&mut self) -> Option<u8> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        Some(self.bytes[self.len])
+    }
+}
+
+fn is_continuation_byte(x: u8) -> bool {
+    x & 0xc0 == 0x80
+}
+
+/// The length of the UTF-8 sequence that starts with `x`, or 1 if `x` cannot start one.
+fn utf8_sequence_len(x: u8) -> usize {
+    match x {
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => 1,
+    }
+}
+
+/// The number of bytes at the end of `xs` that start a UTF-8 sequence, but do not complete it.
+#[inline(always)]
+fn incomplete_tail_len(xs: &[u8]) -> usize {
+    if xs.last().is_none_or(u8::is_ascii) {
+        return 0;
+    }
+    incomplete_non_ascii_tail_len(xs)
+}
+
+fn incomplete_non_ascii_tail_len(xs: &[u8]) -> usize {
+    for (tail_len, &x) in xs.iter().rev().take(3).enumerate().map(|(i, x)| (i + 1, x)) {
+        if !is_continuation_byte(x) {
+            return if utf8_sequence_len(x) > tail_len {
+                tail_len
+            } else {
+                0
+            };
+        }
+    }
+    0
+}
+
+/// Tell the emitter how far the input has been read.
+#[inline(always)]
+fn report_position<E: Emitter>(emitter: &mut E, position: usize, line_index: &Option<LineIndex>) {
+    emitter.set_position(position);
+    if let Some(line_index) = line_index {
+        emitter.set_line(line_index.line_count());
+    }
+}
+
+#[inline(always)]
+fn track_lines(line_index: &mut Option<LineIndex>, offset: usize, bytes: &[u8]) {
+    if let Some(line_index) = line_index {
+        line_index.track(offset, bytes);
+    }
+}
+
+/// The tags from the [MIME sniffing
+/// algorithm](https://mimesniff.spec.whatwg.org/#identifying-a-resource-with-an-unknown-mime-type)
+/// that identify a resource as HTML, without the leading `<`. They are compared
+/// case-insensitively.
+///
+/// Patterns that are a prefix of other patterns come last, so that `<b` does not shadow `<body`.
+pub(crate) const PLAUSIBLE_TAGS: &[&[u8]] = &[
+    b"!DOCTYPE HTML",
+    b"!--",
+    b"HTML",
+    b"HEAD",
+    b"SCRIPT",
+    b"IFRAME",
+    b"H1",
+    b"DIV",
+    b"FONT",
+    b"TABLE",
+    b"STYLE",
+    b"TITLE",
+    b"BODY",
+    b"BR",
+    b"A",
+    b"B",
+    b"P",
+];
+
+/// The last few bytes read from the reader, and how many bytes have been read in total, for
+/// `Debug` output.
+#[cfg(debug_assertions)]
+#[derive(Default)]
+struct RecentlyRead {
+    ring: [u8; RecentlyRead::LEN],
+    bytes_read: usize,
+}
+
+#[cfg(debug_assertions)]
+impl RecentlyRead {
+    const LEN: usize = 32;
+
+    fn push(&mut self, xs: &[u8]) {
+        let skipped = xs.len().saturating_sub(Self::LEN);
+        self.bytes_read += skipped;
+        for &x in &xs[skipped..] {
+            self.ring[self.bytes_read % Self::LEN] = x;
+            self.bytes_read += 1;
+        }
+    }
+}
+
+#[cfg(debug_assertions)]
+impl Debug for RecentlyRead {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let len = self.bytes_read.min(Self::LEN);
+        let bytes: Vec<u8> = (self.bytes_read - len..self.bytes_read)
+            .map(|i| self.ring[i % Self::LEN])
+            .collect();
+        write!(
+            f,
+            "{}{:?} ({} bytes read in total)",
+            if len < self.bytes_read { "..." } else { "" },
+            DebugBytes(&bytes),
+            self.bytes_read
+        )
     }
 }
 
@@ -196,7 +1185,14 @@ macro_rules! fast_read_char {
             &mut char_buf,
         )?;
         break match $read_char {
-            $(Some($($lit)|*) => $arm)*
+            // Readers may return empty chunks, and a chunk may become empty when the \n of a \r\n
+            // is skipped. Passing those on would call emitter methods without any data.
+            Some(b"") => continue,
+            // The literal arms can use $xs as well, it is the single byte that matched.
+            $(
+                #[allow(unused_variables, unused_parens)]
+                Some($xs @ ($($lit)|*)) => $arm
+            )*
                 Some($xs) => {
                     // Prevent catch-all arm from using the machine_helper.
                     //
@@ -0,0 +1,297 @@
+//! A test kit for custom [`Reader`] implementations. Requires the `reader-conformance` feature.
+//!
+//! [`check`] tokenizes a set of documents with the reader under test and compares the result with
+//! what [`StringReader`](crate::StringReader) produces for the same input. Each document is also
+//! tokenized with the reader wrapped in adapters that return the data in the smallest chunks the
+//! [`Reader`] contract allows, so that misbehavior of the reader shows up regardless of how
+//! large its chunks normally are.
+//!
+//! In a test of your crate:
+//!
+//! ```rust
+//! use html5gum::IoReader;
+//! use html5gum::reader::conformance;
+//!
+//! conformance::check(|input| IoReader::new(input));
+//! ```
+use crate::{DefaultEmitter, Readable, Reader, Span, Token, Tokenizer};
+
+/// The documents used by [`check`]. They cover all tokenizer states, character references,
+/// newline normalization and invalid input, as well as every kind of token ending at the end of
+/// the input.
+pub const DOCUMENTS: &[&[u8]] = &[
+    b"",
+    b"hello world",
+    b"<!DOCTYPE html><html lang=en><head><title>hi</title></head><body><p>hello</p></body></html>",
+    b"<p class=a id='b' title=\"c\" hidden>text</p >",
+    b"<br/><br /><img src=x alt=\"\"/>",
+    b"<a b=c d='e' f=\"g\"h=i j k= l =m>",
+    b"<p a=1 a=2>duplicate</p x=y></p/>",
+    b"<!-- comment --><!----><!---><!--><!-- a -- b --!><!-- <!-- nested -->",
+    b"<!DOCTYPE html PUBLIC \"-//W3C//DTD HTML 4.01//EN\" \"http://www.w3.org/TR/html4/strict.dtd\">",
+    b"<!doctype html system 'about:legacy-compat'><!DOCTYPE><!DOCTYPEhtml><!DOCTYPE a b>",
+    b"<?xml version=\"1.0\"?><!bogus><![CDATA[x]]></>",
+    b"&amp; &lt &gt; &notit; &notin; &#65; &#x41; &#x; &#; &#0; &#xD800; &#x110000; &#128; &#99999999999999999999;",
+    b"<a href=\"?a=1&b=2&amp;c=3&copy=4&copy;\">&unknown; &</a>",
+    b"a\r\nb\rc\n\r\r\nd\r",
+    b"<a\r\nb='\r\n'\rc=\"\r\">\r\n<!--\r\n-->\r",
+    b"\0<\0a \0=\0>\0</a>&\0",
+    b"\x01\x0b\x7f \xc2\x80 \xef\xb7\x90 \xef\xbf\xbf \xf4\x8f\xbf\xbf",
+    b"caf\xc3\xa9 \xe2\x82\xac \xf0\x9d\x84\x9e <p title='\xc3\xa9\xe2\x82\xac'>\xf0\x9f\x98\x80</p>",
+    b"invalid \xff\xfe \xc3 utf8 \xe2\x82",
+    b"<script>a<b>c</d></script><script>if (a<b && c>d) {}</script>",
+    b"<script><!-- a --></script><script><!--<script>a</script>b--></script>c</script>",
+    b"<script><!--<script>--><!--</script>",
+    b"<script><!-- - -- <-- <!-- <script </script</SCRIPT>",
+    b"<style>a > b { content: '</p>' }</style><xmp><b></xmp><iframe><i></iframe>",
+    b"<title>&amp; <b></title><textarea>\r\n&lt;</textarea>",
+    b"<noembed>a</noembed><noframes>b</noframes><noscript>c</noscript>",
+    b"<plaintext><a></plaintext>&amp;",
+    b"<title>a</titl</title></TITLE>",
+    b"<a",
+    b"<a b",
+    b"<a b=",
+    b"<a b='c",
+    b"<a b=\"c",
+    b"<a b=c",
+    b"<a/",
+    b"</a",
+    b"</",
+    b"<",
+    b"&",
+    b"&#",
+    b"&#x",
+    b"&am",
+    b"<!",
+    b"<!-",
+    b"<!--",
+    b"<!-- a -",
+    b"<!-- a --",
+    b"<!-- a --!",
+    b"<!DOCTYPE",
+    b"<!DOCTYPE html PUBLIC",
+    b"<!DOCTYPE html PUBLIC \"a",
+    b"<!DOCTYPE html SYSTEM 'a' b",
+    b"<script><!--",
+    b"<script><!--<script>",
+    b"<title>a<",
+    b"<title>a</",
+    b"<title>a</title",
+];
+
+/// Check that the readers returned by `make_reader` produce the same tokens as
+/// [`StringReader`](crate::StringReader) for [`DOCUMENTS`].
+///
+/// # Panics
+///
+/// If the tokens differ, or if the reader returns an error.
+pub fn check<R: Reader>(make_reader: impl Fn(&'static [u8]) -> R) {
+    check_documents(make_reader, DOCUMENTS.iter().copied());
+}
+
+/// Like [`check`], but with a custom set of documents.
+///
+/// # Panics
+///
+/// If the tokens differ, or if the reader returns an error.
+pub fn check_documents<'a, R: Reader>(
+    make_reader: impl Fn(&'a [u8]) -> R,
+    documents: impl IntoIterator<Item = &'a [u8]>,
+) {
+    for document in documents {
+        for naive in [false, true] {
+            let expected = tokenize(document.to_reader(), naive);
+            let compare = |description: &str, actual: Vec<(Token, Span)>| {
+                assert!(
+                    actual == expected,
+                    "{} produced different tokens than StringReader (naive state switching: {})\n\
+                     input: {:?}\n\
+                     expected: {:?}\n\
+                     actual: {:?}",
+                    description,
+                    naive,
+                    String::from_utf8_lossy(document),
+                    expected,
+                    actual
+                );
+            };
+
+            compare("reader", tokenize(make_reader(document), naive));
+            compare(
+                "reader with one-byte reads",
+                tokenize(OneByteReads(make_reader(document)), naive),
+            );
+            compare(
+                "reader with empty reads",
+                tokenize(
+                    ShortReads::new(make_reader(document), usize::MAX, true),
+                    naive,
+                ),
+            );
+
+            for max_len in 1..4 {
+                compare(
+                    &format!("reader with reads of at most {} bytes", max_len),
+                    tokenize(
+                        ShortReads::new(make_reader(document), max_len, false),
+                        naive,
+                    ),
+                );
+            }
+
+            for split_at in 1..document.len() {
+                let mut reader = ShortReads::new(make_reader(document), usize::MAX, false);
+                reader.split_at = Some(split_at);
+                compare(
+                    &format!("reader with reads split at offset {}", split_at),
+                    tokenize(reader, naive),
+                );
+            }
+        }
+    }
+}
+
+fn tokenize<R: Reader>(reader: R, naive: bool) -> Vec<(Token, Span)> {
+    let mut emitter = DefaultEmitter::with_spans();
+    emitter.naively_switch_states(naive);
+    Tokenizer::new_with_emitter(reader, emitter)
+        .map(|token| token.unwrap_or_else(|e| panic!("reader returned an error: {}", e)))
+        .collect()
+}
+
+/// Ignores the needle passed to `read_until` and reads one byte at a time.
+struct OneByteReads<R>(R);
+
+impl<R: Reader> Reader for OneByteReads<R> {
+    type Error = R::Error;
+
+    fn read_byte(&mut self) -> Result<Option<u8>, Self::Error> {
+        self.0.read_byte()
+    }
+
+    fn try_read_string(&mut self, s: &[u8], case_sensitive: bool) -> Result<bool, Self::Error> {
+        self.0.try_read_string(s, case_sensitive)
+    }
+}
+
+/// Buffers the chunks returned by `read_until` and hands them out in smaller pieces.
+struct ShortReads<R> {
+    inner: R,
+    /// Bytes that have been read from `inner`, but not consumed yet.
+    buf: Vec<u8>,
+    buf_start: usize,
+    /// How many bytes have been consumed in total.
+    offset: usize,
+    max_len: usize,
+    /// Make sure that no chunk spans across this offset.
+    split_at: Option<usize>,
+    /// Return an empty chunk before every non-empty chunk.
+    empty_reads: bool,
+    last_read_was_empty: bool,
+}
+
+impl<R: Reader> ShortReads<R> {
+    fn new(inner: R, max_len: usize, empty_reads: bool) -> Self {
+        ShortReads {
+            inner,
+            buf: Vec::new(),
+            buf_start: 0,
+            offset: 0,
+            max_len,
+            split_at: None,
+            empty_reads,
+            last_read_was_empty: false,
+        }
+    }
+
+    fn consume(&mut self, n: usize) {
+        self.buf_start += n;
+        self.offset += n;
+    }
+}
+
+impl<R: Reader> Reader for ShortReads<R> {
+    type Error = R::Error;
+
+    fn read_byte(&mut self) -> Result<Option<u8>, Self::Error> {
+        if let Some(&x) = self.buf.get(self.buf_start) {
+            self.consume(1);
+            return Ok(Some(x));
+        }
+
+        let rv = self.inner.read_byte()?;
+        if rv.is_some() {
+            self.offset += 1;
+        }
+        Ok(rv)
+    }
+
+    fn try_read_string(&mut self, s: &[u8], case_sensitive: bool) -> Result<bool, Self::Error> {
+        let pending = &self.buf[self.buf_start..];
+        let n = pending.len().min(s.len());
+        if !(pending[..n] == s[..n]
+            || (!case_sensitive && pending[..n].eq_ignore_ascii_case(&s[..n])))
+        {
+            return Ok(false);
+        }
+
+        if n == s.len() || self.inner.try_read_string(&s[n..], case_sensitive)? {
+            self.consume(n);
+            self.offset += s.len() - n;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn read_until<'b>(
+        &'b mut self,
+        needle: &[u8],
+        _char_buf: &'b mut [u8; 4],
+    ) -> Result<Option<&'b [u8]>, Self::Error> {
+        if self.empty_reads {
+            self.last_read_was_empty = !self.last_read_was_empty;
+            if self.last_read_was_empty {
+                return Ok(Some(&[]));
+            }
+        }
+
+        if self.buf_start == self.buf.len() {
+            let mut char_buf = [0; 4];
+            match self.inner.read_until(needle, &mut char_buf)? {
+                Some(xs) => {
+                    self.buf.clear();
+                    self.buf.extend_from_slice(xs);
+                    self.buf_start = 0;
+                }
+                None => return Ok(None),
+            }
+
+            if self.buf.is_empty() {
+                return Ok(Some(&[]));
+            }
+        }
+
+        // the buffered data may have been read with a different needle
+        let pending = &self.buf[self.buf_start..];
+        let mut len = if needle.contains(&pending[0]) {
+            1
+        } else {
+            pending
+                .iter()
+                .position(|x| needle.contains(x))
+                .unwrap_or(pending.len())
+        };
+
+        len = len.min(self.max_len);
+        if let Some(split_at) = self.split_at {
+            if self.offset < split_at {
+                len = len.min(split_at - self.offset);
+            }
+        }
+
+        let start = self.buf_start;
+        self.consume(len);
+        Ok(Some(&self.buf[start..start + len]))
+    }
+}
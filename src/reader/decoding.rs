@@ -0,0 +1,428 @@
+use std::cmp::min;
+use std::io::{self, Read};
+
+use encoding_rs::{
+    CoderResult, Decoder, Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252, X_USER_DEFINED,
+};
+
+use super::{fast_find, Reader};
+
+/// How many bytes at the start of the input are searched for a `<meta charset>`.
+const PRESCAN_LEN: usize = 1024;
+
+/// How many bytes are read from the inner reader and decoded at once.
+const CHUNK_LEN: usize = 8192;
+
+/// A [`DecodingReader`] detects the character encoding of a document, and converts it to UTF-8
+/// while it is being read. Requires the `encoding` feature.
+///
+/// The encoding is determined the way browsers do it for a document without a `Content-Type`
+/// charset:
+///
+/// 1. A byte order mark for UTF-8, UTF-16LE or UTF-16BE. It is removed from the input.
+/// 2. Otherwise, a `<meta charset=...>` or `<meta http-equiv=content-type content=...>` found by
+///    [prescanning](https://html.spec.whatwg.org/multipage/parsing.html#prescan-a-byte-stream-to-determine-its-encoding)
+///    the first 1024 bytes.
+/// 3. Otherwise UTF-8 if those bytes are valid UTF-8, and windows-1252 if they are not. Use
+///    [`DecodingReader::new_with_fallback`] to always use a specific encoding here instead.
+///
+/// The first 1024 bytes are buffered until the encoding is known, so a declaration within them
+/// applies to the whole document. Declarations after that are ignored: a browser would start
+/// parsing over in that case, while `DecodingReader` never changes the encoding once it has
+/// decoded anything. [`DecodingReader::encoding`] is therefore the encoding of every byte that
+/// the tokenizer sees.
+///
+/// Malformed input is replaced with U+FFFD. Spans refer to the decoded UTF-8, not to the original
+/// input.
+///
+/// ```rust
+/// use html5gum::{DecodingReader, Token, Tokenizer};
+///
+/// let html = &b"<meta charset=windows-1252><p>caf\xe9"[..];
+/// let reader = DecodingReader::new(html).unwrap();
+/// assert_eq!(reader.encoding().name(), "windows-1252");
+///
+/// let tokens: Vec<_> = Tokenizer::new(reader).flatten().collect();
+/// assert_eq!(tokens[2], Token::String("café".as_bytes().to_vec().into()));
+/// ```
+#[derive(Debug)]
+pub struct DecodingReader<R> {
+    reader: R,
+    encoding: &'static Encoding,
+    decoder: Decoder,
+    /// Input that has been read from `reader` but not decoded yet.
+    raw: Vec<u8>,
+    eof: bool,
+    /// Decoded UTF-8 that has not been consumed by the tokenizer yet, starting at `read_cursor`.
+    buf: Vec<u8>,
+    read_cursor: usize,
+}
+
+impl<R: Read> DecodingReader<R> {
+    /// Construct a new `DecodingReader`, and determine the encoding of `reader`.
+    ///
+    /// This reads up to 1024 bytes from `reader`, and fails if that fails.
+    pub fn new(reader: R) -> io::Result<Self> {
+        Self::new_impl(reader, None)
+    }
+
+    /// Like [`DecodingReader::new`], but use `fallback` if the document neither has a byte order
+    /// mark nor declares its encoding.
+    pub fn new_with_fallback(reader: R, fallback: &'static Encoding) -> io::Result<Self> {
+        Self::new_impl(reader, Some(fallback))
+    }
+
+    fn new_impl(mut reader: R, fallback: Option<&'static Encoding>) -> io::Result<Self> {
+        let mut raw = Vec::with_capacity(CHUNK_LEN);
+        let eof = read_chunk(&mut reader, &mut raw, PRESCAN_LEN)?;
+
+        let encoding = match Encoding::for_bom(&raw) {
+            Some((encoding, bom_len)) => {
+                raw.drain(..bom_len);
+                encoding
+            }
+            None => prescan(&raw)
+                .or(fallback)
+                .unwrap_or_else(|| match std::str::from_utf8(&raw) {
+                    // a character that is cut off by the end of the prescan can still be UTF-8
+                    Err(e) if e.error_len().is_some() || eof => WINDOWS_1252,
+                    _ => UTF_8,
+                }),
+        };
+
+        Ok(DecodingReader {
+            reader,
+            encoding,
+            decoder: encoding.new_decoder_without_bom_handling(),
+            raw,
+            eof,
+            buf: Vec::new(),
+            read_cursor: 0,
+        })
+    }
+
+    /// The encoding of the document.
+    pub fn encoding(&self) -> &'static Encoding {
+        self.encoding
+    }
+
+    /// Ensure that the buffer contains at least `min_read_len` bytes to read, unless the input
+    /// has ended.
+    #[inline(always)]
+    fn prepare_buf(&mut self, min_read_len: usize) -> io::Result<()> {
+        if self.buf.len() - self.read_cursor >= min_read_len {
+            return Ok(());
+        }
+
+        self.buf.drain(..self.read_cursor);
+        self.read_cursor = 0;
+
+        while self.buf.len() < min_read_len {
+            if self.raw.is_empty() {
+                if self.eof {
+                    break;
+                }
+                self.eof = read_chunk(&mut self.reader, &mut self.raw, CHUNK_LEN)?;
+            }
+            self.decode();
+        }
+
+        Ok(())
+    }
+
+    /// Decode all of `raw` into `buf`. An incomplete character at the end is kept in the decoder
+    /// until the next call.
+    fn decode(&mut self) {
+        let mut input = &self.raw[..];
+        loop {
+            let start = self.buf.len();
+            self.buf.resize(start + CHUNK_LEN, 0);
+            let (result, read, written, _) =
+                self.decoder
+                    .decode_to_utf8(input, &mut self.buf[start..], self.eof);
+            self.buf.truncate(start + written);
+            input = &input[read..];
+            if result == CoderResult::InputEmpty {
+                break;
+            }
+        }
+        self.raw.clear();
+    }
+}
+
+/// Read from `reader` into `raw` until it contains `len` bytes. Return whether the input ended.
+fn read_chunk<R: Read>(reader: &mut R, raw: &mut Vec<u8>, len: usize) -> io::Result<bool> {
+    let start = raw.len();
+    raw.resize(start + len, 0);
+    let mut filled = start;
+    let result = loop {
+        match reader.read(&mut raw[filled..]) {
+            Ok(0) => break Ok(true),
+            Ok(n) => {
+                filled += n;
+                if filled == raw.len() {
+                    break Ok(false);
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => break Err(e),
+        }
+    };
+    raw.truncate(filled);
+    result
+}
+
+impl<R: Read> Reader for DecodingReader<R> {
+    type Error = io::Error;
+
+    #[inline(always)]
+    fn read_byte(&mut self) -> Result<Option<u8>, Self::Error> {
+        self.prepare_buf(1)?;
+        let rv = self.buf.get(self.read_cursor).copied();
+        if rv.is_some() {
+            self.read_cursor += 1;
+        }
+        Ok(rv)
+    }
+
+    #[inline(always)]
+    fn try_read_string(&mut self, s1: &[u8], case_sensitive: bool) -> Result<bool, Self::Error> {
+        debug_assert!(!s1.contains(&b'\r'));
+        debug_assert!(!s1.contains(&b'\n'));
+
+        self.prepare_buf(s1.len())?;
+        let s2 = &self.buf[self.read_cursor..min(self.read_cursor + s1.len(), self.buf.len())];
+        if s1 == s2 || (!case_sensitive && s1.eq_ignore_ascii_case(s2)) {
+            self.read_cursor += s1.len();
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    #[inline(always)]
+    fn read_until<'b>(
+        &'b mut self,
+        needle: &[u8],
+        _: &'b mut [u8; 4],
+    ) -> Result<Option<&'b [u8]>, Self::Error> {
+        self.prepare_buf(1)?;
+        let buf = &self.buf[self.read_cursor..];
+        if buf.is_empty() {
+            Ok(None)
+        } else if let Some(needle_pos) = fast_find(needle, buf) {
+            if needle_pos == 0 {
+                self.read_cursor += 1;
+                Ok(Some(&buf[..1]))
+            } else {
+                self.read_cursor += needle_pos;
+                Ok(Some(&buf[..needle_pos]))
+            }
+        } else {
+            self.read_cursor += buf.len();
+            Ok(Some(buf))
+        }
+    }
+}
+
+fn is_whitespace(b: u8) -> bool {
+    matches!(b, b'\t' | b'\n' | b'\x0C' | b'\r' | b' ')
+}
+
+/// Whether `s` starts with `<` or `</`, followed by an ASCII letter.
+fn is_tag_start(s: &[u8]) -> bool {
+    let name = s.strip_prefix(b"</").or_else(|| s.strip_prefix(b"<"));
+    name.and_then(|name| name.first())
+        .is_some_and(u8::is_ascii_alphabetic)
+}
+
+/// The result of getting an attribute during the prescan.
+enum Attribute {
+    Found(Vec<u8>, Vec<u8>),
+    End,
+}
+
+/// [Prescan a byte stream to determine its
+/// encoding](https://html.spec.whatwg.org/multipage/parsing.html#prescan-a-byte-stream-to-determine-its-encoding).
+fn prescan(input: &[u8]) -> Option<&'static Encoding> {
+    let mut pos = 0;
+    while pos < input.len() {
+        let rest = &input[pos..];
+        if rest.starts_with(b"<!--") {
+            // the dashes of "<!--" can be part of "-->"
+            pos += 2 + find(&rest[2..], b"-->")? + 3;
+            continue;
+        } else if starts_with_ignore_case(rest, b"<meta")
+            && rest.get(5).is_some_and(|&b| is_whitespace(b) || b == b'/')
+        {
+            pos += 5;
+            if let Some(encoding) = meta(input, &mut pos)? {
+                return Some(encoding);
+            }
+        } else if is_tag_start(rest) {
+            pos += rest
+                .iter()
+                .position(|&b| is_whitespace(b) || b == b'>')
+                .unwrap_or(rest.len());
+            while let Attribute::Found(..) = attribute(input, &mut pos)? {}
+        } else if rest.starts_with(b"<!") || rest.starts_with(b"</") || rest.starts_with(b"<?") {
+            pos += find(rest, b">")?;
+        }
+        pos += 1;
+    }
+    None
+}
+
+/// Process the attributes of a `<meta>` tag. The outer `None` means that the input ended.
+#[allow(clippy::option_option)]
+fn meta(input: &[u8], pos: &mut usize) -> Option<Option<&'static Encoding>> {
+    let mut seen = Vec::new();
+    let mut got_pragma = false;
+    let mut need_pragma = None;
+    let mut charset = None;
+
+    while let Attribute::Found(name, value) = attribute(input, pos)? {
+        if seen.contains(&name) {
+            continue;
+        }
+        match &*name {
+            b"http-equiv" => got_pragma |= value == b"content-type",
+            b"content" if charset.is_none() => {
+                if let Some(encoding) = charset_from_content(&value) {
+                    charset = Some(encoding);
+                    need_pragma = Some(true);
+                }
+            }
+            b"charset" => {
+                charset = Encoding::for_label(&value);
+                need_pragma = Some(false);
+            }
+            _ => {}
+        }
+        seen.push(name);
+    }
+
+    Some(match (need_pragma, charset) {
+        (Some(true), _) if !got_pragma => None,
+        (Some(_), Some(encoding)) if encoding == UTF_16BE || encoding == UTF_16LE => Some(UTF_8),
+        (Some(_), Some(encoding)) if encoding == X_USER_DEFINED => Some(WINDOWS_1252),
+        (Some(_), charset) => charset,
+        (None, _) => None,
+    })
+}
+
+/// [Get an attribute](https://html.spec.whatwg.org/multipage/parsing.html#concept-get-attributes-when-sniffing).
+/// `None` means that the input ended.
+fn attribute(input: &[u8], pos: &mut usize) -> Option<Attribute> {
+    let mut b = next(input, pos)?;
+    while is_whitespace(b) || b == b'/' {
+        b = next(input, pos)?;
+    }
+    if b == b'>' {
+        *pos -= 1;
+        return Some(Attribute::End);
+    }
+
+    let mut name = Vec::new();
+    let mut value = Vec::new();
+    loop {
+        match b {
+            b'=' if !name.is_empty() => break,
+            _ if is_whitespace(b) => {
+                while is_whitespace(b) {
+                    b = next(input, pos)?;
+                }
+                if b != b'=' {
+                    *pos -= 1;
+                    return Some(Attribute::Found(name, value));
+                }
+                break;
+            }
+            b'/' | b'>' => {
+                *pos -= 1;
+                return Some(Attribute::Found(name, value));
+            }
+            _ => name.push(b.to_ascii_lowercase()),
+        }
+        b = next(input, pos)?;
+    }
+
+    b = next(input, pos)?;
+    while is_whitespace(b) {
+        b = next(input, pos)?;
+    }
+    match b {
+        b'"' | b'\'' => loop {
+            let c = next(input, pos)?;
+            if c == b {
+                return Some(Attribute::Found(name, value));
+            }
+            value.push(c.to_ascii_lowercase());
+        },
+        b'>' => {
+            *pos -= 1;
+            return Some(Attribute::Found(name, value));
+        }
+        b => value.push(b.to_ascii_lowercase()),
+    }
+    loop {
+        b = next(input, pos)?;
+        if is_whitespace(b) || b == b'>' {
+            *pos -= 1;
+            return Some(Attribute::Found(name, value));
+        }
+        value.push(b.to_ascii_lowercase());
+    }
+}
+
+/// [Extract a character encoding from a meta
+/// element](https://html.spec.whatwg.org/multipage/urls-and-fetching.html#algorithm-for-extracting-a-character-encoding-from-a-meta-element).
+fn charset_from_content(content: &[u8]) -> Option<&'static Encoding> {
+    let mut rest = content;
+    loop {
+        let start = rest
+            .windows(7)
+            .position(|window| window.eq_ignore_ascii_case(b"charset"))?;
+        rest = trim_start(&rest[start + 7..]);
+        if let Some(value) = rest.strip_prefix(b"=") {
+            rest = trim_start(value);
+            break;
+        }
+    }
+
+    match rest.first()? {
+        &quote @ (b'"' | b'\'') => {
+            let end = rest[1..].iter().position(|&b| b == quote)?;
+            Encoding::for_label(&rest[1..=end])
+        }
+        _ => {
+            let end = rest
+                .iter()
+                .position(|&b| is_whitespace(b) || b == b';')
+                .unwrap_or(rest.len());
+            Encoding::for_label(&rest[..end])
+        }
+    }
+}
+
+fn next(input: &[u8], pos: &mut usize) -> Option<u8> {
+    let b = *input.get(*pos)?;
+    *pos += 1;
+    Some(b)
+}
+
+fn trim_start(s: &[u8]) -> &[u8] {
+    let start = s.iter().position(|&b| !is_whitespace(b)).unwrap_or(s.len());
+    &s[start..]
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn starts_with_ignore_case(s: &[u8], prefix: &[u8]) -> bool {
+    s.get(..prefix.len())
+        .is_some_and(|s| s.eq_ignore_ascii_case(prefix))
+}
@@ -0,0 +1,1275 @@
+//! Readers provide the input to the tokenizer. The types in this module are also re-exported at
+//! the crate root.
+use std::cmp::min;
+use std::convert::Infallible;
+use std::fmt::{self, Debug};
+use std::fs::File;
+use std::io::{self, Read, Seek, Write};
+
+#[cfg(feature = "reader-conformance")]
+pub mod conformance;
+#[cfg(feature = "encoding")]
+mod decoding;
+
+#[cfg(feature = "encoding")]
+pub use decoding::DecodingReader;
+
+/// An object that provides characters to the tokenizer.
+///
+/// See [`crate::Tokenizer::new`] for more information.
+pub trait Reader {
+    /// The error returned by this reader.
+    type Error: std::error::Error;
+
+    /// Return a new byte from the input stream.
+    ///
+    /// The input stream does **not** have to be preprocessed in any way, it can contain standalone
+    /// surrogates and have inconsistent newlines.
+    fn read_byte(&mut self) -> Result<Option<u8>, Self::Error>;
+
+    /// Attempt to read an entire string at once, either case-insensitively or not.
+    ///
+    /// `case_sensitive=false` means that characters of the input stream should be compared while
+    /// ignoring ASCII-casing.
+    ///
+    /// It can be assumed that this function is never called with a string that contains `\r` or
+    /// `\n`.
+    ///
+    /// If the next characters equal to `s`, this function consumes the respective characters from
+    /// the input stream and returns `true`. If not, it does nothing and returns `false`.
+    fn try_read_string(&mut self, s: &[u8], case_sensitive: bool) -> Result<bool, Self::Error>;
+
+    /// Read an arbitrary amount of characters up until and including the next character that
+    /// matches an array entry in `needle`.
+    ///
+    /// Return either:
+    ///
+    /// 1. A chunk of consumed characters that does not contain any characters from `needle`. The chunk can be arbitrarily large or small.
+    /// 2. If the next character is included in `needle`, a string with just that character and nothing else.
+    ///
+    /// In other words, case 1 means "we didn't find the needle yet, but here's some read data",
+    /// while case 2 means "we have found the needle".
+    ///
+    /// Chunks may be empty, and may end in the middle of a multi-byte character. The tokenizer
+    /// produces the same tokens no matter how the input is split up, which can be verified with
+    /// the test kit in `html5gum::reader::conformance` (requires the `reader-conformance`
+    /// feature).
+    ///
+    /// The default implementation simply reads one character and calls `read_cb` with that
+    /// character, ignoring the needle entirely. It is recommended to manually implement
+    /// `read_until` if there is any sort of in-memory buffer where some sort of efficient string
+    /// search (see `memchr` or `jetscii` crate) can be run on.
+    ///
+    /// The return value is usually borrowed from underlying buffers. If that's not possible, a
+    /// small buffer is provided as `char_buf` to put a single character into.
+    ///
+    /// # Example
+    ///
+    /// Here is how [`StringReader`] behaves:
+    ///
+    /// ```rust
+    /// use html5gum::{Reader, Readable};
+    ///
+    /// let mut reader = "hello world".to_reader();
+    /// let mut eof = false;
+    /// let mut chunks = Vec::new();
+    /// while !eof {
+    ///     let mut char_buf = [0; 4];
+    ///     let xs = reader.read_until(&[b' ', b'r'], &mut char_buf).unwrap();
+    ///     if let Some(xs) = xs {
+    ///         chunks.push(std::str::from_utf8(xs).unwrap().to_owned());
+    ///     } else {
+    ///         eof = true;
+    ///     }
+    /// }
+    ///
+    /// assert_eq!(chunks, &["hello", " ", "wo", "r", "ld"]);
+    /// ```
+    ///
+    /// The inefficient default implementation produces:
+    ///
+    /// ```text
+    /// ["h", "e", "l", "l", "o", " ", "w", "o", "r", "l", "d"]
+    /// ```
+    #[inline(always)]
+    fn read_until<'b>(
+        &'b mut self,
+        needle: &[u8],
+        char_buf: &'b mut [u8; 4],
+    ) -> Result<Option<&'b [u8]>, Self::Error> {
+        let _ = needle;
+
+        match self.read_byte()? {
+            Some(x) => {
+                char_buf[0] = x;
+                Ok(Some(&char_buf[..1]))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Skip the next `n` bytes of the input stream, or less if the input ends before that.
+    ///
+    /// This is used by [`crate::Tokenizer::resume_at`]. The default implementation calls
+    /// `read_byte` `n` times, readers that can seek or slice their input should override it.
+    fn skip_bytes(&mut self, n: usize) -> Result<(), Self::Error> {
+        for _ in 0..n {
+            if self.read_byte()?.is_none() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Return bounds on the number of bytes left in the input stream, in the same way as
+    /// [`Iterator::size_hint`]. This is used by [`crate::Tokenizer::progress`].
+    ///
+    /// The default implementation returns `(0, None)`, which is correct for any reader. Readers
+    /// that know the size of their input, such as [`StringReader`], should override it.
+    fn size_hint(&self) -> (u64, Option<u64>) {
+        (0, None)
+    }
+}
+
+/// An object that can be converted into a [`crate::Reader`].
+///
+/// For example, any utf8-string can be converted into a `StringReader`, such that
+/// `Tokenizer::new("mystring")` and `Tokenizer::new(&String::new("foo"))` work.
+pub trait Readable<'a> {
+    /// The reader type to which this type should be converted.
+    type Reader: Reader + 'a;
+
+    /// Convert self to some sort of reader.
+    fn to_reader(self) -> Self::Reader;
+}
+
+impl<'a, R: 'a + Reader> Readable<'a> for R {
+    type Reader = Self;
+
+    fn to_reader(self) -> Self::Reader {
+        self
+    }
+}
+
+/// A helper struct to seek forwards and backwards in strings. Used by the tokenizer to read HTML
+/// from strings.
+///
+/// Example:
+///
+/// ```rust
+/// use std::fmt::Write;
+/// use html5gum::{Tokenizer, Token};
+///
+/// let html = "<title   >hello world</title>";
+/// let mut new_html = String::new();
+///
+/// for Ok(token) in Tokenizer::new(html) {
+///     match token {
+///         Token::StartTag(tag) => {
+///             write!(new_html, "<{}>", String::from_utf8_lossy(&tag.name)).unwrap();
+///         }
+///         Token::String(hello_world) => {
+///             write!(new_html, "{}", String::from_utf8_lossy(&hello_world)).unwrap();
+///         }
+///         Token::EndTag(tag) => {
+///             write!(new_html, "</{}>", String::from_utf8_lossy(&tag.name)).unwrap();
+///         }
+///         _ => panic!("unexpected input"),
+///     }
+/// }
+///
+/// assert_eq!(new_html, "<title>hello world</title>");
+/// ```
+#[derive(Debug)]
+pub struct StringReader<'a> {
+    input: &'a [u8],
+}
+
+impl<'a> StringReader<'a> {
+    fn new(input: &'a [u8]) -> Self {
+        StringReader { input }
+    }
+
+    /// Return the part of the input that has not been read yet.
+    pub fn remaining(&self) -> &'a [u8] {
+        self.input
+    }
+}
+
+impl<'a> Reader for StringReader<'a> {
+    type Error = Infallible;
+
+    #[inline(always)]
+    fn read_byte(&mut self) -> Result<Option<u8>, Self::Error> {
+        if self.input.is_empty() {
+            Ok(None)
+        } else {
+            let rv = self.input[0];
+            self.input = &self.input[1..];
+            Ok(Some(rv))
+        }
+    }
+
+    #[inline(always)]
+    fn read_until<'b>(
+        &'b mut self,
+        needle: &[u8],
+        _: &'b mut [u8; 4],
+    ) -> Result<Option<&'b [u8]>, Self::Error> {
+        if self.input.is_empty() {
+            return Ok(None);
+        }
+
+        if let Some(needle_pos) = fast_find(needle, self.input) {
+            if needle_pos == 0 {
+                let (rv, new_input) = self.input.split_at(1);
+                self.input = new_input;
+                Ok(Some(rv))
+            } else {
+                let (rv, new_input) = self.input.split_at(needle_pos);
+                self.input = new_input;
+                Ok(Some(rv))
+            }
+        } else {
+            let rv = self.input;
+            self.input = b"";
+            Ok(Some(rv))
+        }
+    }
+
+    #[inline(always)]
+    fn try_read_string(&mut self, s1: &[u8], case_sensitive: bool) -> Result<bool, Self::Error> {
+        // we do not need to call validate_char here because `s` hopefully does not contain invalid
+        // characters
+        if let Some(s2) = self.input.get(..s1.len()) {
+            if s1 == s2 || (!case_sensitive && s1.eq_ignore_ascii_case(s2)) {
+                self.input = &self.input[s1.len()..];
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    fn skip_bytes(&mut self, n: usize) -> Result<(), Self::Error> {
+        self.input = self.input.get(n..).unwrap_or_default();
+        Ok(())
+    }
+
+    fn size_hint(&self) -> (u64, Option<u64>) {
+        let len = self.input.len() as u64;
+        (len, Some(len))
+    }
+}
+
+impl<'a> Readable<'a> for &'a str {
+    type Reader = StringReader<'a>;
+
+    fn to_reader(self) -> Self::Reader {
+        StringReader::new(self.as_bytes())
+    }
+}
+
+impl<'a> Readable<'a> for &'a String {
+    type Reader = StringReader<'a>;
+
+    fn to_reader(self) -> Self::Reader {
+        StringReader::new(self.as_bytes())
+    }
+}
+
+impl<'a> Readable<'a> for &'a Vec<u8> {
+    type Reader = StringReader<'a>;
+
+    fn to_reader(self) -> Self::Reader {
+        StringReader::new(self.as_slice())
+    }
+}
+
+impl<'a> Readable<'a> for &'a [u8] {
+    type Reader = StringReader<'a>;
+
+    fn to_reader(self) -> Self::Reader {
+        StringReader::new(self)
+    }
+}
+
+/// A [`IoReader`] can be used to construct a tokenizer from any type that implements
+/// `std::io::Read`.
+///
+/// Because of trait impl conflicts, `IoReader` needs to be explicitly constructed. The exception
+/// to that is `File`, which can be directly passed to `Tokenizer::new`.
+///
+/// When passing `Read`-types into html5gum, no I/O buffering is required. html5gum maintains its
+/// own read-buffer (16kb, heap-allocated) such that it can be accessed directly. Put more simply,
+/// it's wasteful to wrap your `File` in a `std::io::BufReader` before passing it to html5gum.
+/// The size of the buffer can be changed with [`IoReader::with_capacity`].
+///
+/// Reads that fail with [`io::ErrorKind::Interrupted`] are retried, like `std::io::Read::read_exact`
+/// does. All other errors are returned by the tokenizer.
+///
+/// Example:
+///
+/// ```rust
+/// use std::fmt::Write;
+/// use html5gum::{Token, IoReader, Tokenizer};
+///
+/// let tokenizer = Tokenizer::new(IoReader::new("<title>hello world</title>".as_bytes()));
+/// // more realistically: Tokenizer::new(File::open("index.html")?)
+/// // long-form: Tokenizer::new(IoReader::new(File::open("index.html")?))
+///
+/// let mut new_html = String::new();
+///
+/// for token in tokenizer {
+///     let token = token.unwrap();
+///
+///     match token {
+///         Token::StartTag(tag) => {
+///             write!(new_html, "<{}>", String::from_utf8_lossy(&tag.name)).unwrap();
+///         }
+///         Token::String(hello_world) => {
+///             write!(new_html, "{}", String::from_utf8_lossy(&hello_world)).unwrap();
+///         }
+///         Token::EndTag(tag) => {
+///             write!(new_html, "</{}>", String::from_utf8_lossy(&tag.name)).unwrap();
+///         }
+///         _ => panic!("unexpected input"),
+///     }
+///
+/// }
+///
+/// assert_eq!(new_html, "<title>hello world</title>");
+/// ```
+#[derive(Debug)]
+pub struct IoReader<R: Read, Buffer: AsMut<[u8]> = Box<[u8]>> {
+    buf: Buffer,
+    read_cursor: usize,
+    write_cursor: usize,
+    reader: R,
+    /// How many bytes `reader` returns in total, see [`IoReader::with_input_len`].
+    input_len: Option<u64>,
+    /// How many bytes have been read from `reader` so far.
+    bytes_read: u64,
+}
+
+impl<R: Read> IoReader<R> {
+    /// Construct a new `BufReadReader` from any type that implements `Read`.
+    pub fn new(reader: R) -> Self {
+        Self::new_with_buffer_size::<16384>(reader)
+    }
+
+    /// Construct a new `BufReadReader` with a buffer of `capacity` bytes, which is at least 32.
+    ///
+    /// `new` defaults to a heap-allocated buffer of size 16kB. Larger buffers mean fewer calls to
+    /// `reader`, and longer runs of text can be passed to the emitter at once. Smaller buffers are
+    /// only useful to limit memory usage.
+    pub fn with_capacity(reader: R, capacity: usize) -> Self {
+        Self::new_with_buffer_impl(reader, vec![0; capacity.max(32)].into_boxed_slice())
+    }
+
+    /// Construct a new `BufReadReader` with a specific internal buffer size.
+    ///
+    /// `new` defaults to a heap-allocated buffer of size 16kB.
+    ///
+    /// The buffer has to be at least 32 bytes large, which is the longest lookahead the tokenizer
+    /// needs (for named character references).
+    pub fn new_with_buffer_size<const BUF_SIZE: usize>(reader: R) -> Self {
+        Self::new_with_buffer_impl(reader, Box::new([0; BUF_SIZE]))
+    }
+}
+
+impl<'a, R: Read> IoReader<R, &'a mut [u8]> {
+    /// Instantiate `IoReader` with a custom kind of buffer.
+    ///
+    /// Buffers do not need to be zero-initialized, but have to be at least 32 bytes large.
+    pub fn new_with_buffer(reader: R, buf: &'a mut [u8]) -> Self {
+        Self::new_with_buffer_impl(reader, buf)
+    }
+}
+
+impl<R: Read, Buffer: AsMut<[u8]>> IoReader<R, Buffer> {
+    // new_with_buffer_impl is not exposed because we cannot use any kind of AsMut. It has to be
+    // one where we can be sure that the size of the buffer does not change with repeated calls to
+    // `as_mut()`. There are complex solutions to this sort of thing, but for now it seems simpler
+    // to allow either Box<[u8; _]> or &mut [u8], and nothing else.
+    //
+    // See discussion at https://users.rust-lang.org/t/cowmut-or-borrowed-owned-mutable-temp-buffers/96595
+    fn new_with_buffer_impl(reader: R, buf: Buffer) -> Self {
+        IoReader {
+            buf,
+            read_cursor: 0,
+            write_cursor: 0,
+            reader,
+            input_len: None,
+            bytes_read: 0,
+        }
+    }
+
+    /// Tell the reader how many bytes `reader` is going to return, for example from the
+    /// `Content-Length` of an HTTP response. This is only used for [`Reader::size_hint`], which
+    /// enables [`crate::Tokenizer::progress`].
+    ///
+    /// Files passed to `Tokenizer::new` get this from their metadata automatically.
+    #[must_use]
+    pub fn with_input_len(mut self, len: u64) -> Self {
+        self.input_len = Some(len);
+        self
+    }
+
+    /// Return the bytes that have been read from the inner reader, but not returned yet.
+    ///
+    /// Together with [`IoReader::into_inner`], this recovers the rest of the input, for example
+    /// after [`crate::Tokenizer::into_reader`].
+    pub fn buffer(&mut self) -> &[u8] {
+        &self.buf.as_mut()[self.read_cursor..self.write_cursor]
+    }
+
+    /// Return the inner reader. Anything in [`IoReader::buffer`] is dropped.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Ensure that the buffer contains at leaast `min_read_len` bytes to read.
+    ///
+    /// Shift all to-be-read buffer contents between `self.read_cursor` and `self.write_cursor` to
+    /// the beginning of the buffer, and read extra bytes if necessary.
+    #[inline(always)]
+    fn prepare_buf(&mut self, min_read_len: usize) -> Result<(), io::Error> {
+        let mut readable_len = self.write_cursor - self.read_cursor;
+        debug_assert!(min_read_len <= self.buf.as_mut().len());
+        debug_assert!(readable_len <= self.buf.as_mut().len());
+        if readable_len < min_read_len {
+            let raw_buf = self.buf.as_mut();
+            raw_buf.copy_within(self.read_cursor..self.write_cursor, 0);
+            self.read_cursor = 0;
+            self.write_cursor = readable_len;
+            while readable_len < min_read_len {
+                // the cursors are up to date, so that nothing is lost if this fails and the
+                // caller tries again
+                let n = match self.reader.read(&mut raw_buf[readable_len..]) {
+                    Ok(n) => n,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e),
+                };
+                if n == 0 {
+                    break;
+                }
+                self.bytes_read += n as u64;
+                readable_len += n;
+                self.write_cursor = readable_len;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read, Buffer: AsMut<[u8]>> Reader for IoReader<R, Buffer> {
+    type Error = io::Error;
+
+    #[inline(always)]
+    fn read_byte(&mut self) -> Result<Option<u8>, Self::Error> {
+        self.prepare_buf(1)?;
+        if self.read_cursor == self.write_cursor {
+            return Ok(None);
+        }
+        let rv = self.buf.as_mut().get(self.read_cursor).copied();
+        if rv.is_some() {
+            self.read_cursor += 1;
+        }
+        Ok(rv)
+    }
+
+    #[inline(always)]
+    fn try_read_string(&mut self, s1: &[u8], case_sensitive: bool) -> Result<bool, Self::Error> {
+        debug_assert!(!s1.contains(&b'\r'));
+        debug_assert!(!s1.contains(&b'\n'));
+
+        self.prepare_buf(s1.len())?;
+        let s2 = &self.buf.as_mut()
+            [self.read_cursor..min(self.read_cursor + s1.len(), self.write_cursor)];
+        if s1 == s2 || (!case_sensitive && s1.eq_ignore_ascii_case(s2)) {
+            self.read_cursor += s1.len();
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    #[inline(always)]
+    fn read_until<'b>(
+        &'b mut self,
+        needle: &[u8],
+        _: &'b mut [u8; 4],
+    ) -> Result<Option<&'b [u8]>, Self::Error> {
+        self.prepare_buf(4)?;
+        let buf = &self.buf.as_mut()[self.read_cursor..self.write_cursor];
+        if buf.is_empty() {
+            Ok(None)
+        } else if let Some(needle_pos) = fast_find(needle, buf) {
+            if needle_pos == 0 {
+                self.read_cursor += 1;
+                Ok(Some(&buf[..1]))
+            } else {
+                self.read_cursor += needle_pos;
+                Ok(Some(&buf[..needle_pos]))
+            }
+        } else {
+            self.read_cursor += buf.len();
+            Ok(Some(buf))
+        }
+    }
+
+    fn size_hint(&self) -> (u64, Option<u64>) {
+        let buffered = (self.write_cursor - self.read_cursor) as u64;
+        // the reader may return less or more than announced, so only the buffered bytes are
+        // certain
+        let unread = self
+            .input_len
+            .map(|len| buffered + len.saturating_sub(self.bytes_read));
+        (buffered, unread)
+    }
+}
+
+impl<'a> Readable<'a> for File {
+    type Reader = IoReader<File>;
+
+    fn to_reader(mut self) -> Self::Reader {
+        // pipes and devices do not have a meaningful length
+        let len = match self.metadata() {
+            Ok(metadata) if metadata.is_file() => Some(metadata.len()),
+            _ => None,
+        };
+        let position = self.stream_position().ok();
+        let reader = IoReader::new(self);
+        match (len, position) {
+            (Some(len), Some(position)) if len >= position => reader.with_input_len(len - position),
+            _ => reader,
+        }
+    }
+}
+
+/// The item type of iterators accepted by [`CharIterReader`].
+///
+/// This is implemented for `char`, and for `Result<char, E>` in case the source of characters can
+/// fail (for example, a decoder reading from the network).
+pub trait CharIterItem {
+    /// The error returned by [`CharIterReader`] when reading this item fails.
+    type Error: std::error::Error;
+
+    /// Convert this item into a character.
+    fn into_char(self) -> Result<char, Self::Error>;
+}
+
+impl CharIterItem for char {
+    type Error = Infallible;
+
+    #[inline(always)]
+    fn into_char(self) -> Result<char, Self::Error> {
+        Ok(self)
+    }
+}
+
+impl<E: std::error::Error> CharIterItem for Result<char, E> {
+    type Error = E;
+
+    #[inline(always)]
+    fn into_char(self) -> Result<char, Self::Error> {
+        self
+    }
+}
+
+/// A [`CharIterReader`] can be used to construct a tokenizer from an iterator of already-decoded
+/// characters, such as `str::chars()` or the output of a transcoding layer.
+///
+/// Characters are encoded to UTF-8 on the fly into a small internal buffer, so the input never has
+/// to be collected into a string first. Iterators yielding `Result<char, E>` are supported as well,
+/// and their errors are returned from the tokenizer.
+///
+/// Because of trait impl conflicts, `CharIterReader` needs to be explicitly constructed.
+///
+/// Example:
+///
+/// ```rust
+/// use html5gum::{CharIterReader, Token, Tokenizer};
+///
+/// let tokens = Tokenizer::new(CharIterReader::new("<p>hello</p>".chars()))
+///     .collect::<Result<Vec<_>, _>>()
+///     .unwrap();
+///
+/// assert_eq!(tokens.len(), 3);
+/// assert!(matches!(tokens[1], Token::String(ref s) if s.as_slice() == b"hello"));
+/// ```
+#[derive(Debug)]
+pub struct CharIterReader<I: Iterator>
+where
+    I::Item: CharIterItem,
+{
+    iter: I,
+    // UTF-8 encoded characters that have been taken from `iter` but not consumed by the tokenizer
+    // yet.
+    buf: Vec<u8>,
+    read_cursor: usize,
+    // An error that `iter` returned while filling `buf`. It is returned once the tokenizer has
+    // consumed the characters before it.
+    error: Option<<I::Item as CharIterItem>::Error>,
+}
+
+impl<I> CharIterReader<I>
+where
+    I: Iterator,
+    I::Item: CharIterItem,
+{
+    /// How many bytes to take from the iterator at once, so that `read_until` can return more than
+    /// one character per call.
+    const CHUNK_LEN: usize = 1024;
+
+    /// Construct a new `CharIterReader` from any iterator of characters.
+    pub fn new(iter: I) -> Self {
+        CharIterReader {
+            iter,
+            buf: Vec::new(),
+            read_cursor: 0,
+            error: None,
+        }
+    }
+
+    /// Ensure that the buffer contains at least `min_read_len` bytes to read, unless the iterator
+    /// is exhausted.
+    #[inline(always)]
+    fn prepare_buf(&mut self, min_read_len: usize) -> Result<(), <I::Item as CharIterItem>::Error> {
+        if self.buf.len() - self.read_cursor >= min_read_len {
+            return Ok(());
+        }
+
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+
+        self.buf.drain(..self.read_cursor);
+        self.read_cursor = 0;
+
+        while self.buf.len() < min_read_len.max(Self::CHUNK_LEN) {
+            match self.iter.next().map(CharIterItem::into_char) {
+                Some(Ok(c)) => {
+                    let mut char_buf = [0; 4];
+                    self.buf
+                        .extend_from_slice(c.encode_utf8(&mut char_buf).as_bytes());
+                }
+                Some(Err(e)) if self.buf.len() < min_read_len => return Err(e),
+                Some(Err(e)) => {
+                    self.error = Some(e);
+                    break;
+                }
+                None => break,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<I> Reader for CharIterReader<I>
+where
+    I: Iterator,
+    I::Item: CharIterItem,
+{
+    type Error = <I::Item as CharIterItem>::Error;
+
+    #[inline(always)]
+    fn read_byte(&mut self) -> Result<Option<u8>, Self::Error> {
+        self.prepare_buf(1)?;
+        let rv = self.buf.get(self.read_cursor).copied();
+        if rv.is_some() {
+            self.read_cursor += 1;
+        }
+        Ok(rv)
+    }
+
+    #[inline(always)]
+    fn try_read_string(&mut self, s1: &[u8], case_sensitive: bool) -> Result<bool, Self::Error> {
+        debug_assert!(!s1.contains(&b'\r'));
+        debug_assert!(!s1.contains(&b'\n'));
+
+        self.prepare_buf(s1.len())?;
+        let s2 = &self.buf[self.read_cursor..min(self.read_cursor + s1.len(), self.buf.len())];
+        if s1 == s2 || (!case_sensitive && s1.eq_ignore_ascii_case(s2)) {
+            self.read_cursor += s1.len();
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    #[inline(always)]
+    fn read_until<'b>(
+        &'b mut self,
+        needle: &[u8],
+        _: &'b mut [u8; 4],
+    ) -> Result<Option<&'b [u8]>, Self::Error> {
+        self.prepare_buf(1)?;
+        let buf = &self.buf[self.read_cursor..];
+        if buf.is_empty() {
+            Ok(None)
+        } else if let Some(needle_pos) = fast_find(needle, buf) {
+            if needle_pos == 0 {
+                self.read_cursor += 1;
+                Ok(Some(&buf[..1]))
+            } else {
+                self.read_cursor += needle_pos;
+                Ok(Some(&buf[..needle_pos]))
+            }
+        } else {
+            self.read_cursor += buf.len();
+            Ok(Some(buf))
+        }
+    }
+}
+
+/// A [`SliceIterReader`] reads input that is split into several byte slices, such as the chunks
+/// of an HTTP body, without concatenating them first.
+///
+/// Text is returned to the tokenizer straight from the slices. Only when the tokenizer needs to
+/// look ahead across the end of a slice, for example to check whether `<!doc` continues with
+/// `type`, the few bytes involved are copied into a small internal buffer.
+///
+/// A slice of byte slices can be passed to the tokenizer directly. Other iterators need to be
+/// wrapped explicitly, because of trait impl conflicts.
+///
+/// Example:
+///
+/// ```rust
+/// use html5gum::{SliceIterReader, Token, Tokenizer};
+///
+/// let chunks: &[&[u8]] = &[b"<p>hel", b"lo</p><!DOC", b"TYPE html>"];
+/// let tokens: Vec<_> = Tokenizer::new(chunks).flatten().collect();
+/// assert_eq!(tokens.len(), 4);
+///
+/// let chunks = vec![b"<p>hel".to_vec(), b"lo</p>".to_vec()];
+/// let reader = SliceIterReader::new(chunks.iter().map(Vec::as_slice));
+/// assert_eq!(Tokenizer::new(reader).flatten().count(), 3);
+/// ```
+#[derive(Debug)]
+pub struct SliceIterReader<'a, I> {
+    iter: I,
+    // The unread part of the current slice.
+    current: &'a [u8],
+    // Bytes that come before `current` and have not been consumed by the tokenizer yet. These
+    // were copied from previous slices for `try_read_string`, so there are only ever a few.
+    carry: Vec<u8>,
+    carry_cursor: usize,
+}
+
+impl<'a, I: Iterator<Item = &'a [u8]>> SliceIterReader<'a, I> {
+    /// Construct a new `SliceIterReader` from any iterator of byte slices.
+    pub fn new(iter: I) -> Self {
+        SliceIterReader {
+            iter,
+            current: b"",
+            carry: Vec::new(),
+            carry_cursor: 0,
+        }
+    }
+
+    /// Make `current` non-empty, unless the iterator is exhausted.
+    #[inline(always)]
+    fn prepare_current(&mut self) {
+        while self.current.is_empty() {
+            match self.iter.next() {
+                Some(slice) => self.current = slice,
+                None => break,
+            }
+        }
+    }
+
+    /// Ensure that `carry` contains at least `min_read_len` bytes to read, unless the input ends
+    /// before that.
+    fn prepare_carry(&mut self, min_read_len: usize) {
+        self.carry.drain(..self.carry_cursor);
+        self.carry_cursor = 0;
+
+        while self.carry.len() < min_read_len {
+            self.prepare_current();
+            if self.current.is_empty() {
+                break;
+            }
+
+            let len = min(min_read_len - self.carry.len(), self.current.len());
+            let (taken, rest) = self.current.split_at(len);
+            self.carry.extend_from_slice(taken);
+            self.current = rest;
+        }
+    }
+}
+
+impl<'a, I: Iterator<Item = &'a [u8]>> Reader for SliceIterReader<'a, I> {
+    type Error = Infallible;
+
+    #[inline(always)]
+    fn read_byte(&mut self) -> Result<Option<u8>, Self::Error> {
+        if let Some(&x) = self.carry.get(self.carry_cursor) {
+            self.carry_cursor += 1;
+            return Ok(Some(x));
+        }
+
+        self.prepare_current();
+        match self.current.split_first() {
+            Some((&x, rest)) => {
+                self.current = rest;
+                Ok(Some(x))
+            }
+            None => Ok(None),
+        }
+    }
+
+    #[inline(always)]
+    fn try_read_string(&mut self, s1: &[u8], case_sensitive: bool) -> Result<bool, Self::Error> {
+        debug_assert!(!s1.contains(&b'\r'));
+        debug_assert!(!s1.contains(&b'\n'));
+
+        let eq = |s2: &[u8]| s1 == s2 || (!case_sensitive && s1.eq_ignore_ascii_case(s2));
+
+        if self.carry_cursor == self.carry.len() {
+            self.prepare_current();
+            if let Some(s2) = self.current.get(..s1.len()) {
+                if eq(s2) {
+                    self.current = &self.current[s1.len()..];
+                    return Ok(true);
+                }
+
+                return Ok(false);
+            }
+        }
+
+        self.prepare_carry(s1.len());
+        match self.carry.get(..s1.len()) {
+            Some(s2) if eq(s2) => {
+                self.carry_cursor += s1.len();
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    #[inline(always)]
+    fn read_until<'b>(
+        &'b mut self,
+        needle: &[u8],
+        _: &'b mut [u8; 4],
+    ) -> Result<Option<&'b [u8]>, Self::Error> {
+        let buf = if self.carry_cursor < self.carry.len() {
+            &self.carry[self.carry_cursor..]
+        } else {
+            self.prepare_current();
+            self.current
+        };
+
+        let len = match fast_find(needle, buf) {
+            _ if buf.is_empty() => return Ok(None),
+            Some(0) => 1,
+            Some(needle_pos) => needle_pos,
+            None => buf.len(),
+        };
+
+        if self.carry_cursor < self.carry.len() {
+            self.carry_cursor += len;
+            Ok(Some(
+                &self.carry[self.carry_cursor - len..self.carry_cursor],
+            ))
+        } else {
+            let (rv, rest) = self.current.split_at(len);
+            self.current = rest;
+            Ok(Some(rv))
+        }
+    }
+
+    fn skip_bytes(&mut self, mut n: usize) -> Result<(), Self::Error> {
+        let from_carry = min(n, self.carry.len() - self.carry_cursor);
+        self.carry_cursor += from_carry;
+        n -= from_carry;
+
+        while n > 0 {
+            self.prepare_current();
+            if self.current.is_empty() {
+                break;
+            }
+
+            let len = min(n, self.current.len());
+            self.current = &self.current[len..];
+            n -= len;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> Readable<'a> for &'a [&'a [u8]] {
+    type Reader = SliceIterReader<'a, std::iter::Copied<std::slice::Iter<'a, &'a [u8]>>>;
+
+    fn to_reader(self) -> Self::Reader {
+        SliceIterReader::new(self.iter().copied())
+    }
+}
+
+/// A [`RecordingReader`] wraps another reader and writes every byte the tokenizer consumes from it
+/// to a [`Write`] sink, such as a file. Tokenizing the recording again produces the same tokens,
+/// which makes it possible to reproduce a parse of a network stream after the fact.
+///
+/// Precisely, the recording contains:
+///
+/// * every byte returned by [`Reader::read_byte`] and [`Reader::read_until`],
+/// * the bytes matched by [`Reader::try_read_string`] if it returns `true`, but nothing if it
+///   returns `false`, as the inner reader does not consume anything in that case,
+/// * the bytes skipped by [`crate::Tokenizer::resume_at`], so that the recording can be resumed at
+///   the same offset.
+///
+/// The tokenizer's own lookahead is always consumed from the reader before it is looked at, so it
+/// is part of the recording. When the tokenizer is dropped early, the recording therefore ends at
+/// the position up to which the input was read, which may be a few bytes past the last token.
+///
+/// One caveat is that keywords the tokenizer matches case-insensitively, such as `DOCTYPE` and
+/// `PUBLIC`, are recorded in the case the tokenizer spelled them in, because the reader does not
+/// report how they were spelled in the input. This does not change the tokens.
+///
+/// When the inner reader reaches the end of the input, the sink is flushed. Errors from writing
+/// to the sink are returned from the tokenizer as [`RecordingError::Write`], after which the
+/// recording is incomplete.
+///
+/// Example:
+///
+/// ```rust
+/// use html5gum::{Readable, RecordingReader, Tokenizer};
+///
+/// let mut recording = Vec::new();
+/// let reader = RecordingReader::new("<p>hello</p>".to_reader(), &mut recording);
+/// let tokens: Vec<_> = Tokenizer::new(reader).flatten().collect();
+///
+/// assert_eq!(recording, b"<p>hello</p>");
+/// assert_eq!(Tokenizer::new(&recording).flatten().collect::<Vec<_>>(), tokens);
+/// ```
+#[derive(Debug)]
+pub struct RecordingReader<R, W> {
+    reader: R,
+    writer: W,
+}
+
+impl<R: Reader, W: Write> RecordingReader<R, W> {
+    /// Construct a new `RecordingReader` that reads from `reader` and writes to `writer`.
+    pub fn new(reader: R, writer: W) -> Self {
+        RecordingReader { reader, writer }
+    }
+
+    /// Return the inner reader and the sink.
+    pub fn into_inner(self) -> (R, W) {
+        (self.reader, self.writer)
+    }
+
+    #[inline(always)]
+    fn record(&mut self, bytes: Option<&[u8]>) -> Result<(), RecordingError<R::Error>> {
+        match bytes {
+            Some(bytes) => self.writer.write_all(bytes),
+            None => self.writer.flush(),
+        }
+        .map_err(RecordingError::Write)
+    }
+}
+
+impl<R: Reader, W: Write> Reader for RecordingReader<R, W> {
+    type Error = RecordingError<R::Error>;
+
+    #[inline(always)]
+    fn read_byte(&mut self) -> Result<Option<u8>, Self::Error> {
+        let rv = self.reader.read_byte().map_err(RecordingError::Read)?;
+        self.record(rv.as_ref().map(std::slice::from_ref))?;
+        Ok(rv)
+    }
+
+    #[inline(always)]
+    fn try_read_string(&mut self, s: &[u8], case_sensitive: bool) -> Result<bool, Self::Error> {
+        let rv = self
+            .reader
+            .try_read_string(s, case_sensitive)
+            .map_err(RecordingError::Read)?;
+        if rv {
+            self.record(Some(s))?;
+        }
+        Ok(rv)
+    }
+
+    #[inline(always)]
+    fn read_until<'b>(
+        &'b mut self,
+        needle: &[u8],
+        char_buf: &'b mut [u8; 4],
+    ) -> Result<Option<&'b [u8]>, Self::Error> {
+        let rv = self
+            .reader
+            .read_until(needle, char_buf)
+            .map_err(RecordingError::Read)?;
+        match rv {
+            Some(xs) => self.writer.write_all(xs),
+            None => self.writer.flush(),
+        }
+        .map_err(RecordingError::Write)?;
+        Ok(rv)
+    }
+
+    fn size_hint(&self) -> (u64, Option<u64>) {
+        self.reader.size_hint()
+    }
+}
+
+/// The error returned by [`RecordingReader`].
+#[derive(Debug)]
+pub enum RecordingError<E> {
+    /// The inner reader failed.
+    Read(E),
+    /// Writing to the sink failed.
+    Write(io::Error),
+}
+
+impl<E: fmt::Display> fmt::Display for RecordingError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordingError::Read(e) => e.fmt(f),
+            RecordingError::Write(e) => write!(f, "failed to write recording: {}", e),
+        }
+    }
+}
+
+impl<E: std::error::Error> std::error::Error for RecordingError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RecordingError::Read(e) => e.source(),
+            RecordingError::Write(e) => Some(e),
+        }
+    }
+}
+
+/// A [`Utf8Reader`] wraps another reader and checks that the input is valid UTF-8 while it is
+/// being read. This is used by [`crate::Tokenizer::new_utf8_reader`].
+///
+/// If the input is not valid UTF-8, the tokenizer returns [`Utf8ReaderError::InvalidUtf8`]
+/// instead of the token that contains the invalid bytes.
+///
+/// Example:
+///
+/// ```rust
+/// use html5gum::{IoReader, Tokenizer, Utf8Reader, Utf8ReaderError};
+///
+/// let reader = Utf8Reader::new(IoReader::new(&b"<p>\xe4</p>"[..]));
+/// let error = Tokenizer::new(reader).find_map(Result::err).unwrap();
+/// assert!(matches!(error, Utf8ReaderError::InvalidUtf8 { offset: 3 }));
+/// ```
+#[derive(Debug)]
+pub struct Utf8Reader<R> {
+    reader: R,
+    validator: Utf8Validator,
+}
+
+impl<R: Reader> Utf8Reader<R> {
+    /// Construct a new `Utf8Reader` that validates the input of `reader`.
+    pub fn new(reader: R) -> Self {
+        Utf8Reader {
+            reader,
+            validator: Utf8Validator::default(),
+        }
+    }
+
+    #[inline(always)]
+    fn validate(&mut self, bytes: Option<&[u8]>) -> Result<(), Utf8ReaderError<R::Error>> {
+        match bytes {
+            Some(bytes) => self.validator.validate(bytes),
+            None => self.validator.finish(),
+        }
+        .map_err(|offset| Utf8ReaderError::InvalidUtf8 { offset })
+    }
+}
+
+impl<R: Reader> Reader for Utf8Reader<R> {
+    type Error = Utf8ReaderError<R::Error>;
+
+    #[inline(always)]
+    fn read_byte(&mut self) -> Result<Option<u8>, Self::Error> {
+        let rv = self.reader.read_byte().map_err(Utf8ReaderError::Read)?;
+        self.validate(rv.as_ref().map(std::slice::from_ref))?;
+        Ok(rv)
+    }
+
+    #[inline(always)]
+    fn try_read_string(&mut self, s: &[u8], case_sensitive: bool) -> Result<bool, Self::Error> {
+        let rv = self
+            .reader
+            .try_read_string(s, case_sensitive)
+            .map_err(Utf8ReaderError::Read)?;
+        if rv {
+            self.validate(Some(s))?;
+        }
+        Ok(rv)
+    }
+
+    #[inline(always)]
+    fn read_until<'b>(
+        &'b mut self,
+        needle: &[u8],
+        char_buf: &'b mut [u8; 4],
+    ) -> Result<Option<&'b [u8]>, Self::Error> {
+        let rv = self
+            .reader
+            .read_until(needle, char_buf)
+            .map_err(Utf8ReaderError::Read)?;
+        match rv {
+            Some(xs) => self.validator.validate(xs),
+            None => self.validator.finish(),
+        }
+        .map_err(|offset| Utf8ReaderError::InvalidUtf8 { offset })?;
+        Ok(rv)
+    }
+
+    fn size_hint(&self) -> (u64, Option<u64>) {
+        self.reader.size_hint()
+    }
+}
+
+/// The error returned by [`Utf8Reader`].
+#[derive(Debug)]
+pub enum Utf8ReaderError<E> {
+    /// The inner reader failed.
+    Read(E),
+    /// The input is not valid UTF-8.
+    InvalidUtf8 {
+        /// The byte offset of the first byte that is not part of a valid UTF-8 sequence.
+        offset: usize,
+    },
+}
+
+impl<E: fmt::Display> fmt::Display for Utf8ReaderError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Utf8ReaderError::Read(e) => e.fmt(f),
+            Utf8ReaderError::InvalidUtf8 { offset } => {
+                write!(f, "invalid UTF-8 at byte offset {}", offset)
+            }
+        }
+    }
+}
+
+impl<E: std::error::Error> std::error::Error for Utf8ReaderError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Utf8ReaderError::Read(e) => e.source(),
+            Utf8ReaderError::InvalidUtf8 { .. } => None,
+        }
+    }
+}
+
+/// Validates UTF-8 that arrives in arbitrary chunks.
+#[derive(Debug)]
+struct Utf8Validator {
+    /// The number of bytes validated so far.
+    offset: usize,
+    /// The offset of the current multi-byte sequence.
+    sequence_start: usize,
+    /// How many continuation bytes the current sequence still needs.
+    needed: u8,
+    /// The range of the next continuation byte.
+    lower: u8,
+    upper: u8,
+}
+
+impl Default for Utf8Validator {
+    fn default() -> Self {
+        Utf8Validator {
+            offset: 0,
+            sequence_start: 0,
+            needed: 0,
+            lower: 0x80,
+            upper: 0xbf,
+        }
+    }
+}
+
+impl Utf8Validator {
+    /// Validate the next bytes of the input. On error, return the offset of the first invalid
+    /// byte.
+    fn validate(&mut self, mut bytes: &[u8]) -> Result<(), usize> {
+        while !bytes.is_empty() {
+            if self.needed == 0 {
+                match std::str::from_utf8(bytes) {
+                    Ok(_) => {
+                        self.offset += bytes.len();
+                        return Ok(());
+                    }
+                    Err(e) => {
+                        self.offset += e.valid_up_to();
+                        if e.error_len().is_some() {
+                            return Err(self.offset);
+                        }
+
+                        // the sequence at the end is continued in the next chunk
+                        bytes = &bytes[e.valid_up_to()..];
+                    }
+                }
+            }
+
+            self.push(bytes[0])?;
+            bytes = &bytes[1..];
+        }
+
+        Ok(())
+    }
+
+    fn push(&mut self, x: u8) -> Result<(), usize> {
+        if self.needed == 0 {
+            // https://www.unicode.org/versions/Unicode15.0.0/ch03.pdf, table 3-7
+            let (needed, lower, upper) = match x {
+                0x00..=0x7f => (0, 0x80, 0xbf),
+                0xc2..=0xdf => (1, 0x80, 0xbf),
+                0xe0 => (2, 0xa0, 0xbf),
+                0xed => (2, 0x80, 0x9f),
+                0xe1..=0xef => (2, 0x80, 0xbf),
+                0xf0 => (3, 0x90, 0xbf),
+                0xf1..=0xf3 => (3, 0x80, 0xbf),
+                0xf4 => (3, 0x80, 0x8f),
+                _ => return Err(self.offset),
+            };
+            self.sequence_start = self.offset;
+            self.needed = needed;
+            self.lower = lower;
+            self.upper = upper;
+        } else if (self.lower..=self.upper).contains(&x) {
+            self.needed -= 1;
+            self.lower = 0x80;
+            self.upper = 0xbf;
+        } else {
+            return Err(self.sequence_start);
+        }
+
+        self.offset += 1;
+        Ok(())
+    }
+
+    /// The input has ended. Return an error if it ended in the middle of a sequence.
+    fn finish(&self) -> Result<(), usize> {
+        if self.needed == 0 {
+            Ok(())
+        } else {
+            Err(self.sequence_start)
+        }
+    }
+}
+
+#[inline]
+pub(crate) fn fast_find(needle: &[u8], haystack: &[u8]) -> Option<usize> {
+    #[cfg(feature = "jetscii")]
+    {
+        debug_assert!(needle.len() <= 16);
+        let mut needle_arr = [0; 16];
+        needle_arr[..needle.len()].copy_from_slice(needle);
+        #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+        jetscii::Bytes::new(needle_arr, needle.len() as i32, |b| needle.contains(&b)).find(haystack)
+    }
+
+    #[cfg(not(feature = "jetscii"))]
+    haystack.iter().position(|b| needle.contains(b))
+}
@@ -0,0 +1,297 @@
+//! Rewrite start tags of a document while copying everything else unchanged.
+//!
+//! ```rust
+//! use html5gum::rewriter::Rewriter;
+//!
+//! let mut rewriter = Rewriter::new(Vec::new()).on_tag(b"img", |tag| {
+//!     if tag.get_attribute(b"src") == Some(b"cat.png".as_slice()) {
+//!         tag.set_attribute(b"src", b"dog.png");
+//!     }
+//! });
+//! rewriter.rewrite(b"<IMG SRC=cat.png>\n<img src='bird.png' >").unwrap();
+//! assert_eq!(rewriter.into_inner(), b"<img src=\"dog.png\">\n<img src='bird.png' >");
+//! ```
+//!
+//! Only the start tags that a handler changes are written from scratch, like
+//! [`crate::serializer::Serializer::write_start_tag`] does: with lowercase names and
+//! double-quoted, escaped attribute values. All other bytes of the input are copied to the output
+//! as they are, including their character references, whitespace, case and parse errors. This is
+//! what tokenizing and serializing the document cannot do.
+//!
+//! Like [`crate::naive_sanitizer`], the rewriter does not build a tree. It only sees start tags,
+//! so renaming or removing an element does not affect its end tag, with the exception of elements
+//! whose contents the tokenizer reads as text, such as `<script>`, `<style>` and `<title>`:
+//! removing them removes their contents and end tag too. Which elements these are is decided by
+//! [`crate::naive_next_state`].
+use std::convert::Infallible;
+use std::fmt::{self, Debug, Formatter};
+use std::io::{self, Write};
+
+use crate::emitters::callback::{Callback, CallbackEmitter, CallbackEvent};
+use crate::serializer::Serializer;
+use crate::{naive_next_state, HtmlString, StartTag, Tokenizer};
+
+type Handler<'h> = Box<dyn FnMut(&mut Tag) + 'h>;
+
+/// A start tag passed to the handlers of a [`Rewriter`], which can be changed or removed.
+///
+/// Names are compared case-insensitively. Duplicate attributes of the tag are ignored after the
+/// first one, and attributes without a value, as in `<input disabled>`, have an empty value.
+#[derive(Debug)]
+pub struct Tag {
+    tag: StartTag,
+    modified: bool,
+    removed: bool,
+}
+
+impl Tag {
+    /// The tag's name, in lowercase.
+    #[must_use]
+    pub fn name(&self) -> &[u8] {
+        &self.tag.name
+    }
+
+    /// Rename the tag. Its end tag is not renamed.
+    pub fn set_name(&mut self, name: &[u8]) {
+        self.tag.name = name.to_ascii_lowercase().into();
+        self.modified = true;
+    }
+
+    /// Whether the tag ended with `"/>"`.
+    #[must_use]
+    pub fn self_closing(&self) -> bool {
+        self.tag.self_closing
+    }
+
+    /// The value of the attribute `name`, if the tag has it.
+    #[must_use]
+    pub fn get_attribute(&self, name: &[u8]) -> Option<&[u8]> {
+        self.tag
+            .attributes
+            .get(name.to_ascii_lowercase().as_slice())
+            .map(|value| value.as_slice())
+    }
+
+    /// Set the attribute `name` to `value`, adding it after the other attributes if the tag does
+    /// not have it yet.
+    pub fn set_attribute(&mut self, name: &[u8], value: &[u8]) {
+        self.tag
+            .attributes
+            .insert(name.to_ascii_lowercase().into(), value.to_vec().into());
+        self.modified = true;
+    }
+
+    /// Remove the attribute `name`, and return its value if the tag had it.
+    pub fn remove_attribute(&mut self, name: &[u8]) -> Option<HtmlString> {
+        let value = self
+            .tag
+            .attributes
+            .remove(name.to_ascii_lowercase().as_slice());
+        self.modified |= value.is_some();
+        value
+    }
+
+    /// Remove the tag from the output. If the element's contents are read as text, such as with
+    /// `<script>`, its contents and end tag are removed as well. Handlers registered after the
+    /// current one are not called.
+    pub fn remove(&mut self) {
+        self.removed = true;
+    }
+}
+
+/// Calls handlers for start tags and writes the rewritten document to an [`io::Write`]. See the
+/// [module documentation](self).
+///
+/// The input is copied in a few large pieces per changed tag, and the output is flushed at the
+/// end of every document.
+pub struct Rewriter<'h, W> {
+    out: W,
+    handlers: Vec<(Vec<u8>, Handler<'h>)>,
+}
+
+impl<W: Debug> Debug for Rewriter<'_, W> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Rewriter")
+            .field("out", &self.out)
+            .field("handlers", &self.handlers.len())
+            .finish()
+    }
+}
+
+impl<'h, W: Write> Rewriter<'h, W> {
+    /// Create a rewriter without any handlers, which writes to `out`.
+    pub fn new(out: W) -> Self {
+        Rewriter {
+            out,
+            handlers: Vec::new(),
+        }
+    }
+
+    /// Call `handler` for every start tag named `name`, which is case-insensitive. If several
+    /// handlers match a tag, they are called in the order in which they were added.
+    #[must_use]
+    pub fn on_tag(mut self, name: &[u8], handler: impl FnMut(&mut Tag) + 'h) -> Self {
+        self.handlers
+            .push((name.to_ascii_lowercase(), Box::new(handler)));
+        self
+    }
+
+    /// Rewrite the document `input` and write it to the output.
+    ///
+    /// Writing fails with [`io::ErrorKind::InvalidInput`] if a handler gave a tag or attribute a
+    /// name that would be tokenized differently, such as one containing whitespace. The output
+    /// then ends right before that tag.
+    pub fn rewrite(&mut self, input: &[u8]) -> io::Result<()> {
+        let mut state = RewriterState {
+            input,
+            out: &mut self.out,
+            handlers: &mut self.handlers,
+            copied: 0,
+            token_start: 0,
+            position: 0,
+            tag: None,
+            skipping: false,
+            error: None,
+        };
+        let mut emitter = CallbackEmitter::new(&mut state);
+        // needed so that the contents of <script> are not tokenized as HTML, and so that they
+        // can be skipped
+        emitter.naively_switch_states(true);
+        emitter.pair_attributes(true);
+        let Ok(()) = Tokenizer::new_with_emitter(input, emitter).finish();
+
+        if let Some(error) = state.error {
+            return Err(error);
+        }
+        if !state.skipping {
+            state.out.write_all(&input[state.copied..])?;
+        }
+        state.out.flush()
+    }
+
+    /// Return the output.
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+struct RewriterState<'a, 'r, 'h, W> {
+    input: &'a [u8],
+    out: &'r mut W,
+    handlers: &'r mut Vec<(Vec<u8>, Handler<'h>)>,
+    /// The offset up to which the input has been written to the output.
+    copied: usize,
+    /// The offset of the `<` that started the current token.
+    token_start: usize,
+    position: usize,
+    /// The current start tag, if any handler is interested in it.
+    tag: Option<StartTag>,
+    /// Whether the tokenizer is in the contents of a removed element.
+    skipping: bool,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> RewriterState<'_, '_, '_, W> {
+    fn handle_event(&mut self, event: CallbackEvent<'_>) -> io::Result<()> {
+        match event {
+            CallbackEvent::OpenStartTag { name } => {
+                self.tag = if self.handlers.iter().any(|(x, _)| x == name) {
+                    Some(StartTag {
+                        name: name.to_vec().into(),
+                        ..StartTag::default()
+                    })
+                } else {
+                    None
+                };
+            }
+            CallbackEvent::Attribute { name, value, .. } => {
+                if let Some(tag) = &mut self.tag {
+                    if !tag.attributes.contains_key(name) {
+                        tag.attributes.insert(
+                            name.to_vec().into(),
+                            value.unwrap_or_default().to_vec().into(),
+                        );
+                    }
+                }
+            }
+            CallbackEvent::CloseStartTag { self_closing, .. } => {
+                if let Some(mut tag) = self.tag.take() {
+                    tag.self_closing = self_closing;
+                    self.handle_start_tag(tag)?;
+                }
+            }
+            CallbackEvent::EndTag { .. } => {
+                if self.skipping {
+                    // the tokenizer only leaves the contents at the matching end tag
+                    self.skipping = false;
+                    self.copied = self.position;
+                }
+            }
+            CallbackEvent::AttributeName { .. }
+            | CallbackEvent::AttributeValue { .. }
+            | CallbackEvent::String { .. }
+            | CallbackEvent::CdataStart
+            | CallbackEvent::CdataEnd
+            | CallbackEvent::Comment { .. }
+            | CallbackEvent::Doctype { .. }
+            | CallbackEvent::Error(_) => {}
+        }
+        Ok(())
+    }
+
+    fn handle_start_tag(&mut self, tag: StartTag) -> io::Result<()> {
+        let original_name = tag.name.clone();
+        let mut tag = Tag {
+            tag,
+            modified: false,
+            removed: false,
+        };
+        for (name, handler) in self.handlers.iter_mut() {
+            if *name == *original_name {
+                handler(&mut tag);
+                if tag.removed {
+                    break;
+                }
+            }
+        }
+
+        if !tag.modified && !tag.removed {
+            return Ok(());
+        }
+
+        self.out
+            .write_all(&self.input[self.copied..self.token_start])?;
+        self.copied = self.position;
+        if tag.removed {
+            self.skipping = naive_next_state(&original_name).is_some();
+        } else {
+            let mut serializer = Serializer::new(&mut *self.out);
+            serializer.self_closing(true);
+            serializer.write_start_tag(&tag.tag)?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Callback<Infallible> for &mut RewriterState<'_, '_, '_, W> {
+    fn handle_event(&mut self, event: CallbackEvent<'_>) -> Option<Infallible> {
+        if self.error.is_none() {
+            if let Err(e) = RewriterState::handle_event(self, event) {
+                self.error = Some(e);
+            }
+        }
+        None
+    }
+
+    fn set_token_start(&mut self, offset: usize) {
+        self.token_start = offset;
+    }
+
+    fn set_position(&mut self, offset: usize) {
+        self.position = offset;
+    }
+
+    fn is_aborted(&self) -> bool {
+        self.error.is_some()
+    }
+}
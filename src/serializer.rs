@@ -0,0 +1,351 @@
+//! Write [`Token`]s back out as HTML.
+//!
+//! ```rust
+//! use html5gum::{Token, Tokenizer};
+//! use html5gum::serializer::serialize_tokens;
+//!
+//! let mut tokens: Vec<Token> = Tokenizer::new("<a href=/x title='\"hi\"'>1 &lt; 2</a>")
+//!     .flatten()
+//!     .collect();
+//! if let Token::StartTag(tag) = &mut tokens[0] {
+//!     tag.attributes.remove(b"href".as_slice());
+//! }
+//!
+//! let mut html = Vec::new();
+//! serialize_tokens(&tokens, &mut html).unwrap();
+//! assert_eq!(html, b"<a title=\"&quot;hi&quot;\">1 &lt; 2</a>");
+//! ```
+//!
+//! Text and attribute values are escaped like in the [HTML fragment serialization
+//! algorithm](https://html.spec.whatwg.org/#serialising-html-fragments), so that tokenizing the
+//! output again yields the same tokens. Names, comments and doctypes cannot be escaped. If one of
+//! them would be tokenized differently, for example a comment containing `-->`, writing it fails
+//! with [`std::io::ErrorKind::InvalidInput`] and nothing is written.
+//!
+//! Unlike the spec's algorithm, the serializer only sees tokens and not a tree, so it does not know
+//! whether text is inside of `<script>` and must not be escaped. See
+//! [`Serializer::raw_text`] for how to handle that.
+use std::io::{self, Write};
+
+use crate::utils::is_void_element;
+use crate::{naive_next_state, Doctype, HtmlString, StartTag, State, Token};
+
+/// Write every token in `tokens` to `out` as HTML, using a [`Serializer`] with the default
+/// options.
+pub fn serialize_tokens<'a, W: Write>(
+    tokens: impl IntoIterator<Item = &'a Token>,
+    out: W,
+) -> io::Result<()> {
+    let mut serializer = Serializer::new(out);
+    for token in tokens {
+        serializer.write_token(token)?;
+    }
+    Ok(())
+}
+
+/// Writes tokens to an [`io::Write`] as HTML. See the [module documentation](self).
+///
+/// The output is written in many small pieces, so `out` should be buffered.
+#[derive(Debug)]
+pub struct Serializer<W> {
+    out: W,
+    self_closing: bool,
+    raw_text: bool,
+    void_end_tags: bool,
+    in_raw_text: bool,
+}
+
+impl<W: Write> Serializer<W> {
+    /// Create a serializer that writes to `out`.
+    pub fn new(out: W) -> Self {
+        Serializer {
+            out,
+            self_closing: false,
+            raw_text: false,
+            void_end_tags: false,
+            in_raw_text: false,
+        }
+    }
+
+    /// Write `/>` at the end of start tags that have [`StartTag::self_closing`] set.
+    ///
+    /// Browsers ignore the slash on HTML elements, and only respect it on elements in `<svg>` and
+    /// `<math>`. The default is off, in which case the flag is dropped like in the spec's
+    /// serialization algorithm.
+    pub fn self_closing(&mut self, yes: bool) {
+        self.self_closing = yes;
+    }
+
+    /// Do not escape text after the start tag of an element whose contents are raw text, such as
+    /// `<script>` and `<style>`, until the next end tag.
+    ///
+    /// This is only correct if the tokens have been produced by a tokenizer that switched states
+    /// the same way, such as with [`crate::DefaultEmitter::naively_switch_states`]: which
+    /// elements are raw text is decided by [`crate::naive_next_state`]. Otherwise, `<` in the
+    /// text of a `<script>` is written as `&lt;`, which is only correct if the `<script>` was
+    /// tokenized as regular text too.
+    ///
+    /// The text is written as it is, so it is up to the caller that it does not contain an end tag
+    /// such as `</script>`. The default is off.
+    pub fn raw_text(&mut self, yes: bool) {
+        self.raw_text = yes;
+    }
+
+    /// Write end tags of [void elements](https://html.spec.whatwg.org/#void-elements) such as
+    /// `</img>` as they are.
+    ///
+    /// Void elements never have an end tag. By default, their end tags are left out, as browsers
+    /// ignore them anyway. The exception is `</br>`, which browsers treat like `<br>`, and which is
+    /// therefore written as `<br>`. Turn this on to get back exactly the tokens that have been
+    /// written when tokenizing the output. The default is off.
+    pub fn void_end_tags(&mut self, yes: bool) {
+        self.void_end_tags = yes;
+    }
+
+    /// Write a single token. [`Token::Error`] is ignored.
+    pub fn write_token(&mut self, token: &Token) -> io::Result<()> {
+        match token {
+            Token::StartTag(tag) => self.write_start_tag(tag),
+            Token::EndTag(tag) => self.write_end_tag(&tag.name),
+            Token::String(text) => self.write_text(text),
+            Token::Comment(comment) => self.write_comment(&comment.value),
+            Token::Doctype(doctype) => self.write_doctype(doctype),
+            Token::Error(_) => Ok(()),
+        }
+    }
+
+    /// Write a start tag. Attribute values are always double-quoted.
+    pub fn write_start_tag(&mut self, tag: &StartTag) -> io::Result<()> {
+        check_tag_name(&tag.name)?;
+        for name in tag.attributes.keys() {
+            check_attribute_name(name)?;
+        }
+
+        self.out.write_all(b"<")?;
+        self.out.write_all(&tag.name)?;
+        for (name, value) in &tag.attributes {
+            self.out.write_all(b" ")?;
+            self.out.write_all(name)?;
+            self.out.write_all(b"=\"")?;
+            escape(&mut self.out, value, true)?;
+            self.out.write_all(b"\"")?;
+        }
+        if self.self_closing && tag.self_closing {
+            self.out.write_all(b"/")?;
+        }
+        self.out.write_all(b">")?;
+
+        self.in_raw_text = self.raw_text
+            && matches!(
+                naive_next_state(&tag.name),
+                Some(State::RawText | State::ScriptData | State::PlainText)
+            );
+        Ok(())
+    }
+
+    /// Write an end tag.
+    pub fn write_end_tag(&mut self, name: &[u8]) -> io::Result<()> {
+        check_tag_name(name)?;
+        self.in_raw_text = false;
+
+        if !self.void_end_tags && is_void_element(name) {
+            return match name {
+                b"br" => self.out.write_all(b"<br>"),
+                _ => Ok(()),
+            };
+        }
+
+        self.out.write_all(b"</")?;
+        self.out.write_all(name)?;
+        self.out.write_all(b">")
+    }
+
+    /// Write text, escaping it unless it is inside of a raw text element (see
+    /// [`Serializer::raw_text`]).
+    pub fn write_text(&mut self, text: &[u8]) -> io::Result<()> {
+        if self.in_raw_text {
+            self.out.write_all(text)
+        } else {
+            escape(&mut self.out, text, false)
+        }
+    }
+
+    /// Write a comment.
+    ///
+    /// Fails if `data` starts with `>` or `->`, or contains `-->` or `--!>`, as any of these
+    /// would end the comment early.
+    pub fn write_comment(&mut self, data: &[u8]) -> io::Result<()> {
+        if data.starts_with(b">")
+            || data.starts_with(b"->")
+            || contains(data, b"-->")
+            || contains(data, b"--!>")
+        {
+            return Err(invalid_input("comment data would end the comment early"));
+        }
+
+        self.out.write_all(b"<!--")?;
+        self.out.write_all(data)?;
+        self.out.write_all(b"-->")
+    }
+
+    /// Write a doctype.
+    ///
+    /// Identifiers are quoted with `"`, or with `'` if they contain `"`. If
+    /// [`Doctype::force_quirks`] is set, the closing quote of the last identifier is left out, or
+    /// if there are no identifiers, the public identifier after the `PUBLIC` keyword, which makes
+    /// the tokenizer set the flag again.
+    ///
+    /// Fails if the name is empty and the doctype has identifiers or does not force quirks mode,
+    /// if the name contains whitespace or `>`, or if an identifier contains `>` or both kinds of
+    /// quotes.
+    pub fn write_doctype(&mut self, doctype: &Doctype) -> io::Result<()> {
+        let identifiers = [&doctype.public_identifier, &doctype.system_identifier];
+        if doctype.name.is_empty() {
+            if !doctype.force_quirks || identifiers.iter().any(|x| x.is_some()) {
+                return Err(invalid_input(
+                    "a doctype without a name can neither have identifiers nor be without quirks",
+                ));
+            }
+            return self.out.write_all(b"<!DOCTYPE>");
+        }
+        if doctype.name.iter().any(|&b| is_whitespace(b) || b == b'>') {
+            return Err(invalid_input("invalid doctype name"));
+        }
+        let mut quotes = [b'"'; 2];
+        for (identifier, quote) in identifiers.iter().zip(&mut quotes) {
+            if let Some(identifier) = identifier {
+                if identifier.contains(&b'"') {
+                    *quote = b'\'';
+                }
+                if identifier.contains(&b'>') || (*quote == b'\'' && identifier.contains(&b'\'')) {
+                    return Err(invalid_input("invalid doctype identifier"));
+                }
+            }
+        }
+
+        self.out.write_all(b"<!DOCTYPE ")?;
+        self.out.write_all(&doctype.name)?;
+        match identifiers {
+            [None, None] => {
+                if doctype.force_quirks {
+                    self.out.write_all(b" PUBLIC")?;
+                }
+            }
+            [public, system] => {
+                match public {
+                    Some(public) => {
+                        self.out.write_all(b" PUBLIC ")?;
+                        self.write_identifier(
+                            public,
+                            quotes[0],
+                            doctype.force_quirks && system.is_none(),
+                        )?;
+                    }
+                    None => self.out.write_all(b" SYSTEM")?,
+                }
+                if let Some(system) = system {
+                    self.out.write_all(b" ")?;
+                    self.write_identifier(system, quotes[1], doctype.force_quirks)?;
+                }
+            }
+        }
+        self.out.write_all(b">")
+    }
+
+    fn write_identifier(
+        &mut self,
+        identifier: &HtmlString,
+        quote: u8,
+        force_quirks: bool,
+    ) -> io::Result<()> {
+        self.out.write_all(&[quote])?;
+        self.out.write_all(identifier)?;
+        // an identifier that is ended by `>` instead of a quote sets the force-quirks flag
+        if !force_quirks {
+            self.out.write_all(&[quote])?;
+        }
+        Ok(())
+    }
+
+    /// Return the writer.
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+fn invalid_input(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+fn is_whitespace(b: u8) -> bool {
+    // carriage returns are turned into line feeds by the tokenizer
+    matches!(b, b'\t' | b'\n' | b'\x0c' | b' ' | b'\r')
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+fn check_tag_name(name: &[u8]) -> io::Result<()> {
+    match name.first() {
+        Some(b) if b.is_ascii_alphabetic() => {}
+        _ => return Err(invalid_input("tag names must start with an ASCII letter")),
+    }
+    if name
+        .iter()
+        .any(|&b| is_whitespace(b) || b == b'/' || b == b'>')
+    {
+        return Err(invalid_input("invalid tag name"));
+    }
+    Ok(())
+}
+
+fn check_attribute_name(name: &[u8]) -> io::Result<()> {
+    // `=` is only part of the name if it comes first, otherwise it starts the value
+    if name.is_empty()
+        || name[1..].contains(&b'=')
+        || name
+            .iter()
+            .any(|&b| is_whitespace(b) || b == b'/' || b == b'>')
+    {
+        return Err(invalid_input("invalid attribute name"));
+    }
+    Ok(())
+}
+
+/// [Escape](https://html.spec.whatwg.org/#escapingString) `text`.
+///
+/// Carriage returns are escaped too, as the tokenizer would otherwise turn them into line feeds.
+pub(crate) fn escape(out: &mut impl Write, text: &[u8], attribute_mode: bool) -> io::Result<()> {
+    let mut start = 0;
+    for (i, &b) in text.iter().enumerate() {
+        let replacement: &[u8] = match b {
+            b'&' => b"&amp;",
+            b'<' => b"&lt;",
+            b'>' => b"&gt;",
+            b'"' if attribute_mode => b"&quot;",
+            b'\r' => b"&#13;",
+            // the second byte of U+00A0 NO-BREAK SPACE
+            0xa0 if i > 0 && text[i - 1] == 0xc2 => b"&nbsp;",
+            _ => continue,
+        };
+        let end = if b == 0xa0 { i - 1 } else { i };
+        out.write_all(&text[start..end])?;
+        out.write_all(replacement)?;
+        start = i + 1;
+    }
+    out.write_all(&text[start..])
+}
+
+#[test]
+fn test_escape() {
+    let mut out = Vec::new();
+    escape(&mut out, "a<b>&\"\u{a0}\r\n\u{e0}".as_bytes(), false).unwrap();
+    assert_eq!(out, b"a&lt;b&gt;&amp;\"&nbsp;&#13;\n\xc3\xa0");
+
+    out.clear();
+    escape(&mut out, b"\"'\xa0", true).unwrap();
+    assert_eq!(out, b"&quot;'\xa0");
+}
@@ -0,0 +1,97 @@
+use std::ops::Range;
+
+/// A range of bytes in the input, as returned by [`crate::DefaultEmitter::with_spans`].
+///
+/// Offsets refer to the original input, before newlines are normalized.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Span {
+    /// The offset of the first byte.
+    pub start: usize,
+    /// The offset right after the last byte.
+    pub end: usize,
+}
+
+impl Span {
+    /// The number of bytes in this span.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Whether this span is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+impl From<Span> for Range<usize> {
+    fn from(span: Span) -> Self {
+        span.start..span.end
+    }
+}
+
+/// Converts byte offsets into line and column numbers. See [`crate::Tokenizer::track_lines`].
+///
+/// Lines are separated by `\n`, `\r\n` or a lone `\r`, as per the HTML spec's newline
+/// normalization. Both line and column numbers start at 1. Columns are counted in bytes, like
+/// GCC and Clang do by default.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineIndex {
+    /// The offset at which each line starts.
+    line_starts: Vec<usize>,
+    previous_was_cr: bool,
+}
+
+impl LineIndex {
+    pub(crate) fn new(start: usize) -> Self {
+        LineIndex {
+            line_starts: vec![start],
+            previous_was_cr: false,
+        }
+    }
+
+    /// Forget all lines, to start over with another input at `start`.
+    pub(crate) fn reset(&mut self, start: usize) {
+        self.line_starts.clear();
+        self.line_starts.push(start);
+        self.previous_was_cr = false;
+    }
+
+    /// Record `bytes`, which have been read from the input at `offset`.
+    pub(crate) fn track(&mut self, offset: usize, bytes: &[u8]) {
+        for (i, &x) in bytes.iter().enumerate() {
+            match x {
+                b'\n' if self.previous_was_cr => {
+                    // \r\n is a single newline, so the line starts after \n instead
+                    if let Some(line_start) = self.line_starts.last_mut() {
+                        *line_start = offset + i + 1;
+                    }
+                }
+                b'\n' | b'\r' => self.line_starts.push(offset + i + 1),
+                _ => {}
+            }
+            self.previous_was_cr = x == b'\r';
+        }
+    }
+
+    /// The number of lines seen so far.
+    #[must_use]
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Return `(line, column)` of the given byte offset, for example [`Span::start`].
+    ///
+    /// Offsets that have not been read by the tokenizer yet are treated as if they were on the
+    /// last line seen so far.
+    #[must_use]
+    pub fn line_column(&self, offset: usize) -> (usize, usize) {
+        let line = self
+            .line_starts
+            .partition_point(|&line_start| line_start <= offset)
+            .max(1);
+        let column = offset.saturating_sub(self.line_starts[line - 1]) + 1;
+        (line, column)
+    }
+}
@@ -1,5 +1,7 @@
-/// Tokenizer that the tokenizer can be switched to from within the emitter.
+/// States that the tokenizer can be switched to, either from within the emitter (see
+/// [`crate::Emitter::emit_current_tag`]) or from outside using [`crate::Tokenizer::set_state`].
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum State {
     /// The data state.
     Data,
@@ -11,6 +13,10 @@ pub enum State {
     RawText,
     /// The script data state.
     ScriptData,
+    /// The script data escaped state, entered after `<!--` in script data.
+    ScriptDataEscaped,
+    /// The script data double escaped state, entered after `<!--<script` in script data.
+    ScriptDataDoubleEscaped,
     /// The cdata section state.
     CdataSection,
 }
@@ -0,0 +1,123 @@
+//! Measure the largest text runs, attribute values, comments and nesting depth of a document.
+//!
+//! This helps to pick buffer sizes and depth limits for documents like the ones at hand, without
+//! building a tree and without storing any of the document's contents.
+//!
+//! ```rust
+//! use html5gum::stats::{document_stats, Stats};
+//!
+//! let Ok(stats) = document_stats("<div><p class=intro id=x>hello<br>world</p></div><!-- end -->");
+//! assert_eq!(
+//!     stats,
+//!     Stats {
+//!         longest_text: 5,
+//!         longest_attribute_value: 5,
+//!         longest_comment: 5,
+//!         max_depth: 2,
+//!         max_attributes: 2,
+//!     }
+//! );
+//! ```
+use std::convert::Infallible;
+
+use crate::emitters::callback::{CallbackEmitter, CallbackEvent};
+use crate::utils::is_void_element;
+use crate::{Readable, Reader, Tokenizer};
+
+/// The maxima of a document, as measured by [`document_stats`].
+///
+/// All lengths are in bytes, after decoding character references and replacing invalid
+/// characters.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Stats {
+    /// The longest run of text between two tags, comments or doctypes. Text in `<script>`,
+    /// `<style>` and similar elements counts too.
+    pub longest_text: usize,
+    /// The longest attribute value of a start tag. Values of duplicate attributes are not
+    /// measured, as the tokenizer skips them.
+    pub longest_attribute_value: usize,
+    /// The longest comment, without `<!--` and `-->`.
+    pub longest_comment: usize,
+    /// The maximum number of elements that are open at the same time.
+    ///
+    /// This is an estimate based on balancing start and end tags: void elements such as `<br>`
+    /// and self-closing tags such as `<path/>` are not counted, end tags close one element even if
+    /// they don't match, and elements that are closed implicitly, like in `<p>a<p>b`, stay open.
+    pub max_depth: usize,
+    /// The most attributes on a single start tag, not counting duplicates.
+    pub max_attributes: usize,
+}
+
+/// Measure the [`Stats`] of a document in a single pass over its tokens.
+///
+/// This uses constant memory, no matter how large the measured texts are.
+pub fn document_stats<'a, R: Readable<'a>>(
+    input: R,
+) -> Result<Stats, <R::Reader as Reader>::Error> {
+    let mut counter = Counter::default();
+    let mut emitter = CallbackEmitter::new(|event: CallbackEvent<'_>| -> Option<Infallible> {
+        counter.handle_event(event);
+        None
+    });
+    // needed so that the contents of <script> are measured as one text
+    emitter.naively_switch_states(true);
+    Tokenizer::new_with_emitter(input, emitter).finish()?;
+    Ok(counter.stats)
+}
+
+#[derive(Debug, Default)]
+struct Counter {
+    stats: Stats,
+    text_len: usize,
+    depth: usize,
+    attributes: usize,
+    /// Whether attributes belong to a start tag, and not to an end tag.
+    in_start_tag: bool,
+    is_void: bool,
+}
+
+impl Counter {
+    fn handle_event(&mut self, event: CallbackEvent<'_>) {
+        match event {
+            CallbackEvent::String { value } => {
+                self.text_len += value.len();
+                self.stats.longest_text = self.stats.longest_text.max(self.text_len);
+                return;
+            }
+            // errors and the bounds of CDATA sections don't interrupt text
+            CallbackEvent::Error(_) | CallbackEvent::CdataStart | CallbackEvent::CdataEnd => return,
+            CallbackEvent::OpenStartTag { name } => {
+                self.attributes = 0;
+                self.in_start_tag = true;
+                self.is_void = is_void_element(name);
+            }
+            CallbackEvent::AttributeName { .. } if self.in_start_tag => {
+                self.attributes += 1;
+            }
+            CallbackEvent::AttributeValue { value, .. } if self.in_start_tag => {
+                self.stats.longest_attribute_value =
+                    self.stats.longest_attribute_value.max(value.len());
+            }
+            CallbackEvent::AttributeName { .. }
+            | CallbackEvent::AttributeValue { .. }
+            | CallbackEvent::Attribute { .. } => {}
+            CallbackEvent::CloseStartTag { self_closing, .. } => {
+                self.in_start_tag = false;
+                self.stats.max_attributes = self.stats.max_attributes.max(self.attributes);
+                if !self.is_void && !self_closing {
+                    self.depth += 1;
+                    self.stats.max_depth = self.stats.max_depth.max(self.depth);
+                }
+            }
+            CallbackEvent::EndTag { .. } => {
+                self.depth = self.depth.saturating_sub(1);
+            }
+            CallbackEvent::Comment { value, .. } => {
+                self.stats.longest_comment = self.stats.longest_comment.max(value.len());
+            }
+            CallbackEvent::Doctype { .. } => {}
+        }
+
+        self.text_len = 0;
+    }
+}
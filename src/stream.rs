@@ -0,0 +1,146 @@
+//! Expose tokens as a [`futures_core::Stream`], for use in async pipelines.
+//!
+//! This is not an async reader: the input still has to be available synchronously, for example
+//! because the whole document is already in memory. What [`TokenStream`] adds is cooperative
+//! scheduling. Tokenizing a huge document in one go would block the executor's thread, so the
+//! stream regularly returns `Poll::Pending` (after waking itself up) to let other tasks run, like
+//! `tokio::task::yield_now` would.
+//!
+//! ```rust
+//! use std::future::poll_fn;
+//! use std::pin::Pin;
+//!
+//! use futures_core::Stream; // extern crate futures_core;
+//! use html5gum::stream::{tokens, StreamOptions};
+//! use html5gum::Token;
+//!
+//! # #[tokio::main(flavor = "current_thread")] // extern crate tokio;
+//! # async fn main() {
+//! let html = "<p>hello</p>".repeat(1000);
+//! let mut stream = tokens(html.as_str(), StreamOptions::default());
+//! let mut count = 0;
+//!
+//! while let Some(token) = poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await {
+//!     if let Token::StartTag(_) = token.unwrap() {
+//!         count += 1;
+//!     }
+//! }
+//!
+//! assert_eq!(count, 1000);
+//! # }
+//! ```
+//!
+//! This module requires the `async-stream` feature.
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+
+use crate::{Readable, Reader, Token, Tokenizer};
+
+/// How much work [`TokenStream`] does before yielding to the executor.
+///
+/// Whichever limit is reached first causes the stream to yield.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamOptions {
+    /// The maximum number of tokens produced before yielding.
+    pub tokens_per_yield: usize,
+
+    /// The maximum number of bytes produced before yielding, counting the content of tokens (tag
+    /// names, attributes, text and so on). The limit is only checked between tokens, so a single
+    /// large token can exceed it.
+    pub bytes_per_yield: usize,
+}
+
+impl Default for StreamOptions {
+    fn default() -> Self {
+        StreamOptions {
+            tokens_per_yield: 256,
+            bytes_per_yield: 64 * 1024,
+        }
+    }
+}
+
+/// Tokenize `input` as a stream of tokens. See [`TokenStream`].
+pub fn tokens<'a, S: Readable<'a>>(input: S, options: StreamOptions) -> TokenStream<S::Reader> {
+    TokenStream::new(Tokenizer::new(input), options)
+}
+
+/// A [`Stream`] that owns a [`Tokenizer`] and yields its tokens, see the [module docs](self).
+#[derive(Debug)]
+pub struct TokenStream<R: Reader> {
+    tokenizer: Tokenizer<R>,
+    options: StreamOptions,
+    tokens_since_yield: usize,
+    bytes_since_yield: usize,
+}
+
+impl<R: Reader> TokenStream<R> {
+    /// Wrap an existing tokenizer, for example one with
+    /// [`crate::DefaultEmitter::naively_switch_states`] enabled.
+    pub fn new(tokenizer: Tokenizer<R>, options: StreamOptions) -> Self {
+        TokenStream {
+            tokenizer,
+            options,
+            tokens_since_yield: 0,
+            bytes_since_yield: 0,
+        }
+    }
+
+    /// Return the wrapped tokenizer.
+    pub fn into_inner(self) -> Tokenizer<R> {
+        self.tokenizer
+    }
+}
+
+// The tokenizer is never pinned itself, it is only ever accessed through `&mut`.
+impl<R: Reader> Unpin for TokenStream<R> {}
+
+impl<R: Reader> Stream for TokenStream<R> {
+    type Item = Result<Token, R::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        // at least one token is produced per poll, so that limits of zero can't stall the stream
+        if this.tokens_since_yield > 0
+            && (this.tokens_since_yield >= this.options.tokens_per_yield
+                || this.bytes_since_yield >= this.options.bytes_per_yield)
+        {
+            this.tokens_since_yield = 0;
+            this.bytes_since_yield = 0;
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+
+        let result = this.tokenizer.next();
+        if let Some(Ok(ref token)) = result {
+            this.tokens_since_yield += 1;
+            this.bytes_since_yield += token_len(token);
+        }
+
+        Poll::Ready(result)
+    }
+}
+
+fn token_len(token: &Token) -> usize {
+    match token {
+        Token::StartTag(tag) => {
+            tag.name.len()
+                + tag
+                    .attributes
+                    .iter()
+                    .map(|(key, value)| key.len() + value.len())
+                    .sum::<usize>()
+        }
+        Token::EndTag(tag) => tag.name.len(),
+        Token::String(s) => s.len(),
+        Token::Comment(comment) => comment.value.len(),
+        Token::Doctype(doctype) => {
+            doctype.name.len()
+                + doctype.public_identifier.as_ref().map_or(0, |x| x.len())
+                + doctype.system_identifier.as_ref().map_or(0, |x| x.len())
+        }
+        Token::Error(_) => 0,
+    }
+}
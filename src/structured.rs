@@ -0,0 +1,306 @@
+//! Extract structured data (JSON-LD, OpenGraph and microdata) from a document in one pass.
+//!
+//! ```rust
+//! use html5gum::structured::extract;
+//!
+//! let html = r#"
+//!     <meta property="og:title" content="Hello">
+//!     <script type="application/ld+json">{"@type": "Person"}</script>
+//!     <div itemscope itemtype="https://schema.org/Person">
+//!         <span itemprop="name">Jane</span>
+//!     </div>
+//! "#;
+//!
+//! let Ok(data) = extract(html);
+//! assert_eq!(data.meta_og, vec![(b"og:title".to_vec().into(), b"Hello".to_vec().into())]);
+//! assert_eq!(data.json_ld, vec![br#"{"@type": "Person"}"#.to_vec()]);
+//! assert_eq!(
+//!     data.microdata_items,
+//!     vec![(
+//!         b"https://schema.org/Person".to_vec().into(),
+//!         vec![(b"name".to_vec().into(), b"Jane".to_vec().into())]
+//!     )]
+//! );
+//! ```
+use std::convert::Infallible;
+
+use crate::emitters::callback::{CallbackEmitter, CallbackEvent};
+use crate::utils::is_void_element;
+use crate::{HtmlString, Readable, Reader, Tokenizer};
+
+/// Structured data found in a document by [`extract`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct StructuredData {
+    /// The contents of all `<script type="application/ld+json">` elements, unparsed.
+    pub json_ld: Vec<Vec<u8>>,
+
+    /// `(property, content)` of all `<meta property="og:...">` elements.
+    pub meta_og: Vec<(HtmlString, HtmlString)>,
+
+    /// `(name, content)` of all `<meta name="...">` elements.
+    pub meta_name: Vec<(HtmlString, HtmlString)>,
+
+    /// All [microdata](https://html.spec.whatwg.org/multipage/microdata.html) items as
+    /// `(itemtype, properties)`, in the order of their `itemscope` elements.
+    ///
+    /// Nested items are not represented as a tree. Instead, a property whose element is an item
+    /// itself has that item's type as value, and the nested item shows up in this list as well.
+    ///
+    /// Property values are taken from the [element's
+    /// attributes](https://html.spec.whatwg.org/multipage/microdata.html#values) (`content` for
+    /// `<meta>`, `href` for `<a>` and so on), or otherwise from its text content. Since there is no
+    /// tree builder involved, which text belongs to which element is only approximated from start
+    /// and end tags.
+    pub microdata_items: Vec<(HtmlString, Vec<(HtmlString, HtmlString)>)>,
+}
+
+/// Extract all [`StructuredData`] from a document.
+pub fn extract<'a, R: Readable<'a>>(
+    input: R,
+) -> Result<StructuredData, <R::Reader as Reader>::Error> {
+    let mut extractor = Extractor::default();
+    let mut emitter = CallbackEmitter::new(|event: CallbackEvent<'_>| -> Option<Infallible> {
+        extractor.handle_event(event);
+        None
+    });
+    // needed so that the contents of <script> are not tokenized as HTML
+    emitter.naively_switch_states(true);
+    Tokenizer::new_with_emitter(input, emitter).finish()?;
+    Ok(extractor.data)
+}
+
+#[derive(Debug)]
+struct OpenElement {
+    name: Vec<u8>,
+    /// Whether this element has `itemscope`, i.e. is the top of the stack of items.
+    is_item: bool,
+    /// Properties (as indices into `microdata_items`) whose value is this element's text content.
+    text_properties: Vec<(usize, usize)>,
+}
+
+#[derive(Debug, Default)]
+struct Extractor {
+    data: StructuredData,
+    open_elements: Vec<OpenElement>,
+    item_stack: Vec<usize>,
+
+    tag_name: Vec<u8>,
+    attributes: Vec<(Vec<u8>, Vec<u8>)>,
+    json_ld: Option<Vec<u8>>,
+}
+
+impl Extractor {
+    fn handle_event(&mut self, event: CallbackEvent<'_>) {
+        match event {
+            CallbackEvent::OpenStartTag { name } => {
+                self.tag_name.clear();
+                self.tag_name.extend(name);
+                self.attributes.clear();
+            }
+            CallbackEvent::AttributeName { name } => {
+                self.attributes.push((name.to_vec(), Vec::new()));
+            }
+            CallbackEvent::AttributeValue { value, .. } => {
+                if let Some((_, attribute_value)) = self.attributes.last_mut() {
+                    attribute_value.extend(value);
+                }
+            }
+            CallbackEvent::CloseStartTag { .. } => self.handle_start_tag(),
+            CallbackEvent::EndTag { name } => self.handle_end_tag(name),
+            CallbackEvent::String { value } => {
+                if let Some(ref mut json_ld) = self.json_ld {
+                    json_ld.extend(value);
+                }
+
+                for element in &self.open_elements {
+                    for &(item, property) in &element.text_properties {
+                        self.data.microdata_items[item].1[property].1.extend(value);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_start_tag(&mut self) {
+        let name = std::mem::take(&mut self.tag_name);
+        let attributes = std::mem::take(&mut self.attributes);
+        // the first attribute wins, like in the tokenizer
+        let attribute = |name: &[u8]| {
+            attributes
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.as_slice())
+        };
+
+        match &name[..] {
+            b"script"
+                if attribute(b"type").is_some_and(|ty| {
+                    ty.trim_ascii().eq_ignore_ascii_case(b"application/ld+json")
+                }) =>
+            {
+                self.json_ld = Some(Vec::new());
+            }
+            b"meta" => {
+                if let Some(content) = attribute(b"content") {
+                    if let Some(property) = attribute(b"property") {
+                        if property.starts_with(b"og:") {
+                            self.data
+                                .meta_og
+                                .push((property.to_vec().into(), content.to_vec().into()));
+                        }
+                    }
+                    if let Some(meta_name) = attribute(b"name") {
+                        self.data
+                            .meta_name
+                            .push((meta_name.to_vec().into(), content.to_vec().into()));
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        let is_item = attribute(b"itemscope").is_some();
+        let mut text_properties = Vec::new();
+
+        if let (Some(itemprop), Some(&item)) = (attribute(b"itemprop"), self.item_stack.last()) {
+            let value_attribute: Option<&[u8]> = match &name[..] {
+                _ if is_item => Some(b"itemtype"),
+                b"meta" => Some(b"content"),
+                b"audio" | b"embed" | b"iframe" | b"img" | b"source" | b"track" | b"video" => {
+                    Some(b"src")
+                }
+                b"a" | b"area" | b"link" => Some(b"href"),
+                b"object" => Some(b"data"),
+                b"data" | b"meter" => Some(b"value"),
+                b"time" if attribute(b"datetime").is_some() => Some(b"datetime"),
+                _ => None,
+            };
+
+            let value = value_attribute
+                .map(|value_attribute| attribute(value_attribute).unwrap_or_default().to_vec())
+                .unwrap_or_default();
+
+            let properties = &mut self.data.microdata_items[item].1;
+            for property_name in itemprop.split(u8::is_ascii_whitespace) {
+                if property_name.is_empty() {
+                    continue;
+                }
+
+                if value_attribute.is_none() {
+                    text_properties.push((item, properties.len()));
+                }
+
+                properties.push((property_name.to_vec().into(), value.clone().into()));
+            }
+        }
+
+        if is_item {
+            let itemtype = attribute(b"itemtype").unwrap_or_default().to_vec();
+            self.item_stack.push(self.data.microdata_items.len());
+            self.data
+                .microdata_items
+                .push((itemtype.into(), Vec::new()));
+        }
+
+        self.attributes = attributes;
+
+        if is_void_element(&name) {
+            if is_item {
+                self.item_stack.pop();
+            }
+        } else {
+            self.open_elements.push(OpenElement {
+                name,
+                is_item,
+                text_properties,
+            });
+        }
+    }
+
+    fn handle_end_tag(&mut self, name: &[u8]) {
+        if name == b"script" {
+            if let Some(json_ld) = self.json_ld.take() {
+                self.data.json_ld.push(json_ld);
+            }
+        }
+
+        if let Some(i) = self.open_elements.iter().rposition(|e| e.name == name) {
+            for element in self.open_elements.drain(i..) {
+                if element.is_item {
+                    self.item_stack.pop();
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_extract() {
+    let html = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta name="description" content="A page">
+<META PROPERTY="og:image" CONTENT="/cover.png">
+<meta property="article:author" content="ignored">
+<script type=" Application/LD+JSON ">{"text": "<!-- <script>x</script> -->"}</script>
+<script>var notJsonLd = 1;</script>
+</head>
+<body>
+<div itemscope itemtype="https://schema.org/Movie">
+  <h1 itemprop="name">Avatar</h1>
+  <img itemprop="image" src="avatar.jpg">
+  <span itemprop="genre keywords">Science <b>fiction</b></span>
+  <div itemprop="director" itemscope itemtype="https://schema.org/Person">
+    <span itemprop="name">James Cameron</span>
+    <time itemprop="birthDate" datetime="1954-08-16">August 16</time>
+  </div>
+  <a itemprop="trailer" href="trailer.mp4">Trailer</a>
+</div>
+<span itemprop="orphan">not part of any item</span>
+</body>
+</html>"#;
+
+    let Ok(data) = extract(html);
+
+    assert_eq!(
+        data.json_ld,
+        vec![br#"{"text": "<!-- <script>x</script> -->"}"#.to_vec()]
+    );
+    assert_eq!(
+        data.meta_og,
+        vec![(b"og:image".to_vec().into(), b"/cover.png".to_vec().into())]
+    );
+    assert_eq!(
+        data.meta_name,
+        vec![(b"description".to_vec().into(), b"A page".to_vec().into())]
+    );
+
+    let properties = |props: &[(&str, &str)]| {
+        props
+            .iter()
+            .map(|(k, v)| (k.as_bytes().to_vec().into(), v.as_bytes().to_vec().into()))
+            .collect::<Vec<(HtmlString, HtmlString)>>()
+    };
+
+    assert_eq!(
+        data.microdata_items,
+        vec![
+            (
+                b"https://schema.org/Movie".to_vec().into(),
+                properties(&[
+                    ("name", "Avatar"),
+                    ("image", "avatar.jpg"),
+                    ("genre", "Science fiction"),
+                    ("keywords", "Science fiction"),
+                    ("director", "https://schema.org/Person"),
+                    ("trailer", "trailer.mp4"),
+                ])
+            ),
+            (
+                b"https://schema.org/Person".to_vec().into(),
+                properties(&[("name", "James Cameron"), ("birthDate", "1954-08-16")])
+            ),
+        ]
+    );
+}
@@ -0,0 +1,121 @@
+//! Run input through every built-in emitter and tokenizer option, and discard the output.
+//!
+//! None of this may panic, for any input. The fuzz target in `fuzz/` and html5gum's own
+//! `tests/panic_freedom.rs` both use [`run`] to check that.
+use std::convert::Infallible;
+
+use crate::elements_iter::ElementIter;
+use crate::emitters::buffered::BufferedEmitter;
+use crate::emitters::callback::{CallbackEmitter, CallbackEvent, Limits};
+use crate::testutils::{DebugValidateEmitter, SlowReader};
+use crate::{
+    ContentStateSwitcher, DefaultEmitter, Emitter, GarbagePolicy, IoReader, Readable, Reader,
+    State, Tokenizer,
+};
+
+/// All states that the tokenizer can be started in with [`Tokenizer::set_state`].
+const STATES: [State; 8] = [
+    State::Data,
+    State::PlainText,
+    State::RcData,
+    State::RawText,
+    State::ScriptData,
+    State::ScriptDataEscaped,
+    State::ScriptDataDoubleEscaped,
+    State::CdataSection,
+];
+
+/// Limits that are small enough to be hit by most inputs.
+const SMALL_LIMITS: Limits = Limits {
+    max_attributes: 2,
+    max_name_len: 3,
+    max_value_len: 3,
+    max_comment_len: 3,
+    max_string_len: 3,
+};
+
+fn exhaust<R: Reader, E: Emitter>(tokenizer: Tokenizer<R, E>) {
+    for _ in tokenizer {}
+}
+
+/// Run `input` through every built-in emitter, in every initial state and with every tokenizer
+/// option, and discard the output.
+///
+/// # Panics
+///
+/// Only if html5gum has a bug.
+pub fn run(input: &[u8]) {
+    for state in STATES {
+        let mut tokenizer = Tokenizer::new_with_emitter(input, DefaultEmitter::with_spans());
+        tokenizer.set_state(state);
+        tokenizer.set_last_start_tag(Some("script"));
+        exhaust(tokenizer);
+    }
+
+    let mut emitter = DefaultEmitter::with_spans();
+    emitter.naively_switch_states(true);
+    exhaust(Tokenizer::new_with_emitter(
+        SlowReader(input.to_reader()),
+        emitter,
+    ));
+
+    let mut emitter = DefaultEmitter::default();
+    emitter.switch_states(Some(ContentStateSwitcher::default()));
+    emitter.limits(SMALL_LIMITS);
+    emitter.suppress_errors(true);
+    emitter.collect_error_summary(true);
+    exhaust(Tokenizer::new_with_emitter(
+        IoReader::new_with_buffer_size::<32>(input),
+        emitter,
+    ));
+
+    exhaust(Tokenizer::new_with_emitter(
+        input,
+        DebugValidateEmitter::new(DefaultEmitter::default()),
+    ));
+
+    for policy in [
+        GarbagePolicy::ErrorPerByte,
+        GarbagePolicy::CoalesceErrors,
+        GarbagePolicy::SkipUntilPlausibleTag,
+    ] {
+        let mut tokenizer = Tokenizer::new(input);
+        tokenizer.binary_garbage_mode(policy);
+        tokenizer.track_lines(true);
+        tokenizer.lenient_tag_names(true);
+        exhaust(tokenizer);
+    }
+
+    let mut tokenizer = Tokenizer::new(input);
+    tokenizer.take_bytes(input.len() / 2);
+    tokenizer.take_tokens(3);
+    exhaust(tokenizer);
+
+    for pair_attributes in [false, true] {
+        let mut emitter =
+            CallbackEmitter::new(|_: CallbackEvent<'_>| -> Option<Infallible> { None });
+        emitter.naively_switch_states(true);
+        emitter.pair_attributes(pair_attributes);
+        emitter.coalesce_strings(!pair_attributes);
+        if pair_attributes {
+            emitter.limits(SMALL_LIMITS);
+        }
+        let Ok(()) = Tokenizer::new_with_emitter(input, emitter).finish();
+    }
+
+    let mut emitter = BufferedEmitter::default();
+    emitter.naively_switch_states(true);
+    let mut tokenizer = Tokenizer::new_with_emitter(input, emitter);
+    while let Some(Ok(_)) = tokenizer.next_token() {}
+
+    if let Ok(tokenizer) = Tokenizer::new_utf8_bytes(input) {
+        exhaust(tokenizer);
+    }
+
+    for Ok(_) in ElementIter::new(input, ["p", "script", "title"]) {}
+
+    #[cfg(feature = "encoding")]
+    if let Ok(reader) = crate::DecodingReader::new(input) {
+        exhaust(Tokenizer::new(reader));
+    }
+}
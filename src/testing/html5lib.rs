@@ -0,0 +1,495 @@
+//! Run test cases in the format of the [html5lib-tests tokenizer
+//! tests](https://github.com/html5lib/html5lib-tests/tree/master/tokenizer).
+//!
+//! This is what html5gum's own test suite uses to run html5lib-tests. It can also be used to
+//! write regression tests in the same format, without adding a `.test` file:
+//!
+//! ```rust
+//! html5gum::test_case!({
+//!     "description": "an empty comment with too many dashes",
+//!     "input": "<!--->",
+//!     "output": [["Comment", ""]],
+//!     "errors": [{"code": "abrupt-closing-of-empty-comment"}]
+//! });
+//! ```
+//!
+//! [`test_case!`](crate::test_case) panics if the tokens or errors differ from the expected ones.
+//! Use [`Test::from_json`] and [`run_case`] to inspect the result instead.
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer};
+
+use crate::testutils::{DebugValidateEmitter, SlowReader};
+use crate::{
+    CharIterReader, DefaultEmitter, Doctype, EndTag, Error, IoReader, Readable, Reader, StartTag,
+    State, Token, Tokenizer,
+};
+
+#[doc(hidden)]
+pub use serde_json::json as __json;
+
+/// Run a test case given as a JSON literal, and panic if it fails. See the [module
+/// docs](crate::testing::html5lib).
+///
+/// Fields that are missing from the literal get the same defaults as in html5lib-tests, and
+/// `description` may be left out too.
+#[macro_export]
+macro_rules! test_case {
+    ($($json:tt)+) => {
+        $crate::testing::html5lib::run_case(
+            &$crate::testing::html5lib::Test::from_json(
+                $crate::testing::html5lib::__json!($($json)+)
+            ).expect("invalid test case"),
+        ).unwrap()
+    };
+}
+
+/// A single test case, i.e. one entry of the `tests` array of a `.test` file.
+///
+/// If the test case is marked as `doubleEscaped`, the input and output have already been
+/// unescaped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Test {
+    /// What is being tested.
+    pub description: String,
+    /// The input to the tokenizer.
+    pub input: Vec<u8>,
+    /// The expected tokens, without errors.
+    pub output: Vec<Token>,
+    /// The states to start tokenizing in. Every test case is run once for each of them.
+    pub initial_states: Vec<State>,
+    /// The _last start tag_ to start tokenizing with.
+    pub last_start_tag: Option<String>,
+    /// The expected errors, in order.
+    pub errors: Vec<Error>,
+}
+
+impl Test {
+    /// Parse a test case from its JSON representation.
+    pub fn from_json(value: serde_json::Value) -> Result<Self, serde_json::Error> {
+        Test::deserialize(value)
+    }
+}
+
+/// A `.test` file of html5lib-tests.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct TestFile {
+    /// The test cases in the file.
+    pub tests: Vec<Test>,
+}
+
+/// The ways in which test cases are fed to the tokenizer. Each of them exercises different code
+/// paths of the tokenizer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReaderKind {
+    /// [`crate::StringReader`], only using `read_byte` and `try_read_string`.
+    SlowString,
+    /// [`crate::StringReader`].
+    String,
+    /// [`IoReader`].
+    BufRead,
+    /// [`IoReader`], only using `read_byte` and `try_read_string`.
+    SlowBufRead,
+    /// [`CharIterReader`]. Test cases whose input is not valid UTF-8 are skipped.
+    CharIter,
+}
+
+impl ReaderKind {
+    /// All reader kinds.
+    pub const ALL: [ReaderKind; 5] = [
+        ReaderKind::SlowString,
+        ReaderKind::String,
+        ReaderKind::BufRead,
+        ReaderKind::SlowBufRead,
+        ReaderKind::CharIter,
+    ];
+}
+
+/// The outcome of running a test case with [`run_case`] or [`run_case_with`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CaseResult {
+    /// The runs that produced different tokens or errors than expected.
+    pub failures: Vec<Failure>,
+}
+
+impl CaseResult {
+    /// Whether all runs produced the expected tokens and errors.
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Panic if any run failed, showing the output of the first failed run.
+    #[track_caller]
+    pub fn unwrap(self) {
+        if let Some(failure) = self.failures.first() {
+            panic!("{}", failure);
+        }
+    }
+}
+
+/// A run of a test case that produced different tokens or errors than expected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    /// The description of the test case.
+    pub description: String,
+    /// The state the tokenizer started in.
+    pub initial_state: State,
+    /// How the input was fed to the tokenizer.
+    pub reader: ReaderKind,
+    /// The expected tokens.
+    pub expected_tokens: Vec<Token>,
+    /// The tokens that were produced.
+    pub actual_tokens: Vec<Token>,
+    /// The expected errors.
+    pub expected_errors: Vec<Error>,
+    /// The errors that were produced.
+    pub actual_errors: Vec<Error>,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "test case {:?} failed (initial state: {:?}, reader: {:?})",
+            self.description, self.initial_state, self.reader
+        )?;
+        writeln!(f, "expected tokens: {:?}", self.expected_tokens)?;
+        writeln!(f, "actual tokens:   {:?}", self.actual_tokens)?;
+        writeln!(f, "expected errors: {:?}", self.expected_errors)?;
+        write!(f, "actual errors:   {:?}", self.actual_errors)
+    }
+}
+
+/// Run a test case in each of its initial states, with every [`ReaderKind`].
+pub fn run_case(test: &Test) -> CaseResult {
+    let mut result = CaseResult::default();
+    for &state in &test.initial_states {
+        for reader in ReaderKind::ALL {
+            result
+                .failures
+                .extend(run_case_with(test, state, reader).failures);
+        }
+    }
+    result
+}
+
+/// Run a test case once, starting in `state` and reading the input with `reader`.
+///
+/// `state` doesn't need to be one of the test case's initial states.
+pub fn run_case_with(test: &Test, state: State, reader: ReaderKind) -> CaseResult {
+    let input = test.input.as_slice();
+    let output = match reader {
+        ReaderKind::SlowString => run(test, state, SlowReader(input.to_reader())),
+        ReaderKind::String => run(test, state, input.to_reader()),
+        ReaderKind::BufRead => run(test, state, IoReader::new(input)),
+        ReaderKind::SlowBufRead => run(test, state, SlowReader(IoReader::new(input))),
+        // test cases with invalid UTF-8 cannot be represented as a char iterator
+        ReaderKind::CharIter => match std::str::from_utf8(input) {
+            Ok(input) => run(test, state, CharIterReader::new(input.chars())),
+            Err(_) => return CaseResult::default(),
+        },
+    };
+
+    let (actual_tokens, actual_errors) = output;
+    let mut result = CaseResult::default();
+    if actual_tokens != test.output || actual_errors != test.errors {
+        result.failures.push(Failure {
+            description: test.description.clone(),
+            initial_state: state,
+            reader,
+            expected_tokens: test.output.clone(),
+            actual_tokens,
+            expected_errors: test.errors.clone(),
+            actual_errors,
+        });
+    }
+    result
+}
+
+fn run<R: Reader>(test: &Test, state: State, reader: R) -> (Vec<Token>, Vec<Error>) {
+    let mut emitter = DebugValidateEmitter::new(DefaultEmitter::default());
+    emitter.expect_utf8_strings(std::str::from_utf8(&test.input).is_ok());
+    let mut tokenizer = Tokenizer::new_with_emitter(reader, emitter);
+    tokenizer.set_state(state);
+    tokenizer.set_last_start_tag(test.last_start_tag.as_deref());
+
+    let mut tokens = Vec::new();
+    let mut errors = Vec::new();
+
+    for token in tokenizer {
+        match token.expect("failed to read test input") {
+            Token::Error(e) => errors.push(e),
+            // html5lib-tests does not distinguish bogus comments
+            Token::Comment(comment) => tokens.push(Token::Comment(comment.value.into())),
+            token => tokens.push(token),
+        }
+    }
+
+    (tokens, errors)
+}
+
+#[derive(Deserialize, Ord, PartialOrd, PartialEq, Eq, Default, Clone)]
+struct HtmlString(#[serde(with = "serde_bytes")] Vec<u8>);
+
+struct ExpectedOutputTokens(Vec<Token>);
+
+impl<'de> Deserialize<'de> for ExpectedOutputTokens {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // this macro is a horrible way to define a type that deserializes only from a particular
+        // string. Together with serde(untagged) this gives us really flexible enum tagging with really
+        // terrible error messages.
+        macro_rules! def_const {
+            ($str:expr, $ty:ident) => {
+                #[derive(Deserialize)]
+                enum $ty {
+                    #[serde(rename = $str)]
+                    $ty,
+                }
+            };
+        }
+
+        def_const!("DOCTYPE", DoctypeConst);
+        def_const!("StartTag", StartTagConst);
+        def_const!("EndTag", EndTagConst);
+        def_const!("Comment", CommentConst);
+        def_const!("Character", CharacterConst);
+
+        type Attributes = BTreeMap<HtmlString, HtmlString>;
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum OutputToken {
+            // "DOCTYPE", name, public_id, system_id, correctness
+            Doctype(
+                DoctypeConst,
+                Option<HtmlString>,
+                Option<HtmlString>,
+                Option<HtmlString>,
+                bool,
+            ),
+            // "StartTag", name, attributes, self_closing
+            StartTag(StartTagConst, HtmlString, Attributes),
+            StartTag2(StartTagConst, HtmlString, Attributes, bool),
+            // "EndTag", name
+            EndTag(EndTagConst, HtmlString),
+            // "Comment", data
+            Comment(CommentConst, HtmlString),
+            // "Character", data
+            Character(CharacterConst, HtmlString),
+        }
+
+        Ok(ExpectedOutputTokens(
+            Vec::deserialize(deserializer)?
+                .into_iter()
+                .map(|output_token| match output_token {
+                    OutputToken::Doctype(
+                        _,
+                        name,
+                        public_identifier,
+                        system_identifier,
+                        correctness,
+                    ) => Token::Doctype(Doctype {
+                        name: name.unwrap_or_default().0.into(),
+                        public_identifier: public_identifier.map(|x| x.0.into()),
+                        system_identifier: system_identifier.map(|x| x.0.into()),
+                        force_quirks: !correctness,
+                    }),
+                    OutputToken::StartTag(_, name, attributes) => Token::StartTag(StartTag {
+                        self_closing: false,
+                        name: name.0.into(),
+                        attributes: attributes
+                            .into_iter()
+                            .map(|(k, v)| (k.0.into(), v.0.into()))
+                            .collect(),
+                    }),
+                    OutputToken::StartTag2(_, name, attributes, self_closing) => {
+                        Token::StartTag(StartTag {
+                            self_closing,
+                            name: name.0.into(),
+                            attributes: attributes
+                                .into_iter()
+                                .map(|(k, v)| (k.0.into(), v.0.into()))
+                                .collect(),
+                        })
+                    }
+                    OutputToken::EndTag(_, name) => Token::EndTag(EndTag {
+                        name: name.0.into(),
+                    }),
+                    OutputToken::Comment(_, data) => Token::Comment(data.0.into()),
+                    OutputToken::Character(_, data) => Token::String(data.0.into()),
+                })
+                .collect::<Vec<Token>>(),
+        ))
+    }
+}
+
+struct InitialState(State);
+
+impl<'de> Deserialize<'de> for InitialState {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        enum RawInitialState {
+            #[serde(rename = "Data state")]
+            Data,
+            #[serde(rename = "PLAINTEXT state")]
+            PlainText,
+            #[serde(rename = "RCDATA state")]
+            RcData,
+            #[serde(rename = "RAWTEXT state")]
+            RawText,
+            #[serde(rename = "Script data state")]
+            ScriptData,
+            #[serde(rename = "Script data escaped state")]
+            ScriptDataEscaped,
+            #[serde(rename = "Script data double escaped state")]
+            ScriptDataDoubleEscaped,
+            #[serde(rename = "CDATA section state")]
+            CdataSection,
+        }
+
+        Ok(Self(match RawInitialState::deserialize(deserializer)? {
+            RawInitialState::Data => State::Data,
+            RawInitialState::PlainText => State::PlainText,
+            RawInitialState::RcData => State::RcData,
+            RawInitialState::RawText => State::RawText,
+            RawInitialState::ScriptData => State::ScriptData,
+            RawInitialState::ScriptDataEscaped => State::ScriptDataEscaped,
+            RawInitialState::ScriptDataDoubleEscaped => State::ScriptDataDoubleEscaped,
+            RawInitialState::CdataSection => State::CdataSection,
+        }))
+    }
+}
+
+fn initial_states_default() -> Vec<InitialState> {
+    vec![InitialState(State::Data)]
+}
+
+struct ParseErrorInner(Error);
+
+impl<'de> Deserialize<'de> for ParseErrorInner {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let str_err = String::deserialize(deserializer)?;
+        let err: Error = str_err
+            .parse()
+            .map_err(|_| D::Error::custom(format!("failed to deserialize error: {}", str_err)))?;
+        Ok(ParseErrorInner(err))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ParseError {
+    code: ParseErrorInner,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawTest {
+    #[serde(default)]
+    description: String,
+    input: HtmlString,
+    output: ExpectedOutputTokens,
+    #[serde(default = "initial_states_default")]
+    initial_states: Vec<InitialState>,
+    #[serde(default)]
+    double_escaped: bool,
+    #[serde(default)]
+    last_start_tag: Option<String>,
+    #[serde(default)]
+    errors: Vec<ParseError>,
+}
+
+impl<'de> Deserialize<'de> for Test {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let raw = RawTest::deserialize(deserializer)?;
+        let mut test = Test {
+            description: raw.description,
+            input: raw.input.0,
+            output: raw.output.0,
+            initial_states: raw.initial_states.into_iter().map(|x| x.0).collect(),
+            last_start_tag: raw.last_start_tag,
+            errors: raw.errors.into_iter().map(|x| x.code.0).collect(),
+        };
+
+        if raw.double_escaped {
+            test.input = unescape(&test.input);
+            test.output = test
+                .output
+                .into_iter()
+                .map(|token| match token {
+                    Token::String(x) => Token::String(unescape(x.as_slice()).into()),
+                    Token::Comment(x) => Token::Comment(unescape(x.value.as_slice()).into()),
+                    token => token,
+                })
+                .collect();
+        }
+
+        Ok(test)
+    }
+}
+
+/// Implements the escape sequences described in the tokenizer tests of html5lib-tests (and nothing
+/// more)
+fn unescape(data: &[u8]) -> Vec<u8> {
+    let mut stream = data.iter();
+    let mut rv = Vec::new();
+
+    loop {
+        match stream.next() {
+            Some(b'\\') => (),
+            Some(x) => {
+                rv.push(*x);
+                continue;
+            }
+            None => break,
+        }
+
+        match stream.next() {
+            Some(b'u') => (),
+            x => panic!("unexpected escape: {:?}", x),
+        }
+
+        let orig_len = rv.len();
+
+        for _ in 0..4 {
+            rv.push(match stream.next() {
+                Some(x) => *x,
+                None => panic!("unexpected eof after \\u"),
+            });
+        }
+
+        let c = u32::from_str_radix(std::str::from_utf8(&rv[orig_len..]).unwrap(), 16)
+            .expect("failed to parse as hex");
+        rv.truncate(orig_len);
+
+        if let Some(c) = char::from_u32(c) {
+            rv.push(0);
+            rv.push(0);
+            rv.push(0);
+            rv.push(0);
+            let char_len = c.encode_utf8(&mut rv[orig_len..]).len();
+            rv.truncate(orig_len + char_len);
+        } else if (0xD800..=0xDFFF).contains(&c) {
+            // a surrogate
+            for b in &c.to_be_bytes()[2..] {
+                rv.push(*b);
+            }
+        }
+    }
+
+    rv
+}
@@ -0,0 +1,99 @@
+//! Check that the memory used by the tokenizer itself is bounded, regardless of the input.
+//!
+//! Besides the reader, the tokenizer allocates nothing but a small temporary buffer, for
+//! character references and for end tag names in RCDATA, RAWTEXT and script data. Tokens are
+//! collected by the emitter, so their memory use is up to the emitter. See [`crate::Limits`] for
+//! the built-in emitters.
+//!
+//! [`check_memory_bounds`] tokenizes a document with an emitter that discards everything, and
+//! checks how large the temporary buffer grew. The fuzz target in `fuzz/` runs it with
+//! `FUZZ_MEMORY_BOUNDS=1`, and `tests/memory_bounds.rs` runs it over adversarial inputs.
+//!
+//! ```rust
+//! use html5gum::testing::memory::check_memory_bounds;
+//!
+//! check_memory_bounds("&".repeat(10_000).as_bytes()).unwrap();
+//! ```
+use std::convert::Infallible;
+use std::fmt::{self, Display, Formatter};
+
+use crate::emitters::callback::{CallbackEmitter, CallbackEvent};
+use crate::{Reader, State, Tokenizer};
+
+/// The number of bytes the temporary buffer may take up. The longest thing it has to hold is
+/// `&CounterClockwiseContourIntegral;`.
+pub const MAX_TEMPORARY_BUFFER_CAPACITY: usize = 64;
+
+/// All states that the tokenizer can be started in with [`Tokenizer::set_state`].
+const STATES: [State; 8] = [
+    State::Data,
+    State::PlainText,
+    State::RcData,
+    State::RawText,
+    State::ScriptData,
+    State::ScriptDataEscaped,
+    State::ScriptDataDoubleEscaped,
+    State::CdataSection,
+];
+
+/// A tokenizer run whose temporary buffer grew too large, as returned by
+/// [`check_memory_bounds`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryViolation {
+    /// The state the tokenizer was started in.
+    pub initial_state: State,
+    /// The capacity the temporary buffer grew to, in bytes.
+    pub temporary_buffer_capacity: usize,
+}
+
+impl Display for MemoryViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "temporary buffer grew to {} bytes, starting in {:?}",
+            self.temporary_buffer_capacity, self.initial_state
+        )
+    }
+}
+
+impl std::error::Error for MemoryViolation {}
+
+/// Tokenize `input` in every initial state with `script` as the last start tag, and with
+/// [`crate::naive_next_state`], and check that the temporary buffer never takes up more than
+/// [`MAX_TEMPORARY_BUFFER_CAPACITY`] bytes.
+pub fn check_memory_bounds(input: &[u8]) -> Result<(), MemoryViolation> {
+    for initial_state in STATES {
+        let mut tokenizer = Tokenizer::new_with_emitter(input, discarding_emitter());
+        tokenizer.set_state(initial_state);
+        tokenizer.set_last_start_tag(Some("script"));
+        check(tokenizer, initial_state)?;
+    }
+
+    let mut emitter = discarding_emitter();
+    emitter.naively_switch_states(true);
+    check(Tokenizer::new_with_emitter(input, emitter), State::Data)
+}
+
+type DiscardingEmitter = CallbackEmitter<fn(CallbackEvent<'_>) -> Option<Infallible>>;
+
+fn discarding_emitter() -> DiscardingEmitter {
+    CallbackEmitter::new(|_| None)
+}
+
+fn check<R: Reader>(
+    mut tokenizer: Tokenizer<R, DiscardingEmitter>,
+    initial_state: State,
+) -> Result<(), MemoryViolation> {
+    // nothing is yielded, so this runs until the end of the input
+    for _ in tokenizer.by_ref() {}
+
+    // the buffer is only ever cleared, so its capacity is at least as large as it ever was
+    let temporary_buffer_capacity = tokenizer.machine_helper.temporary_buffer.capacity();
+    if temporary_buffer_capacity > MAX_TEMPORARY_BUFFER_CAPACITY {
+        return Err(MemoryViolation {
+            initial_state,
+            temporary_buffer_capacity,
+        });
+    }
+    Ok(())
+}
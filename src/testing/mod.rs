@@ -0,0 +1,5 @@
+//! Helpers for testing html5gum, and code that builds on it. Requires the `testing` feature.
+pub mod exhaustive;
+pub mod html5lib;
+pub mod memory;
+pub mod spans;
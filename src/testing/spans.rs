@@ -0,0 +1,306 @@
+//! Check that the spans html5gum reports are consistent with the input.
+//!
+//! [`check_span_invariants`] tokenizes a document with [`DefaultEmitter::with_spans`] and with a
+//! [`CallbackEmitter`], and compares every reported span with the bytes it covers. The fuzz
+//! target in `fuzz/` runs it with `FUZZ_SPANS=1`, and `tests/span_invariants.rs` runs it over the
+//! inputs of all test suites. Code that builds its own spans on top of html5gum can run it in its
+//! tests too:
+//!
+//! ```rust
+//! use html5gum::testing::spans::check_span_invariants;
+//!
+//! check_span_invariants(b"<p class=a>hello &amp; goodbye</p>").unwrap();
+//! ```
+use std::convert::Infallible;
+use std::fmt::{self, Display, Formatter};
+
+use crate::emitters::callback::{Callback, CallbackEmitter, CallbackEvent};
+use crate::{DefaultEmitter, Span, Token, Tokenizer};
+
+/// A span that does not match the input, as returned by [`check_span_invariants`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpanViolation {
+    /// The offending span.
+    pub span: Span,
+    /// Which invariant the span violates.
+    pub message: String,
+    /// The input covered by the span, or an empty string if the span is out of bounds.
+    pub source: String,
+}
+
+impl SpanViolation {
+    fn new(input: &[u8], span: Span, message: impl Into<String>) -> Self {
+        SpanViolation {
+            span,
+            message: message.into(),
+            source: String::from_utf8_lossy(input.get(span.start..span.end).unwrap_or_default())
+                .into_owned(),
+        }
+    }
+}
+
+impl Display for SpanViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at {}..{}: {:?}",
+            self.message, self.span.start, self.span.end, self.source
+        )
+    }
+}
+
+impl std::error::Error for SpanViolation {}
+
+/// Tokenize `input` and check the reported spans:
+///
+/// * Every span is within the input, and does not start after it ends.
+/// * Tokens of [`DefaultEmitter::with_spans`] do not overlap, and errors have empty spans.
+/// * Start tags span from `<` to `>`, end tags from `</` to `>`, comments and doctypes start with
+///   `<`.
+/// * Decoding the span of a string on its own produces the same text. Strings are only checked
+///   in the data state, so no state switching is done.
+/// * [`CallbackEvent::OpenStartTag`] and [`CallbackEvent::EndTag`] are preceded by `<` or `</`
+///   and the tag name, and attribute names appear in the input in the order they were reported,
+///   each before its value.
+/// * Attribute value spans lie between the end of their name and the end of the tag, start after
+///   `=` or a quote and end before the same quote. Values without character references, newlines
+///   and NUL characters are equal to their span.
+///
+/// Names that contain U+FFFD REPLACEMENT CHARACTER are not looked up in the input, as they may
+/// have been NUL characters.
+pub fn check_span_invariants(input: &[u8]) -> Result<(), SpanViolation> {
+    check_token_spans(input)?;
+    check_callback_spans(input)
+}
+
+/// Return the input covered by `span`, or a violation if it is out of bounds.
+fn source(input: &[u8], span: Span) -> Result<&[u8], SpanViolation> {
+    if span.start > span.end {
+        Err(SpanViolation::new(input, span, "span starts after its end"))
+    } else if span.end > input.len() {
+        Err(SpanViolation::new(
+            input,
+            span,
+            "span ends after the end of the input",
+        ))
+    } else {
+        Ok(&input[span.start..span.end])
+    }
+}
+
+/// The text of all strings in `input`.
+fn decode_text(input: &[u8]) -> Vec<u8> {
+    let mut text = Vec::new();
+    for Ok(token) in Tokenizer::new(input) {
+        if let Token::String(value) = token {
+            text.extend(&*value);
+        }
+    }
+    text
+}
+
+/// Whether `name` starts `input`, or cannot be looked up in the input.
+fn starts_with_name(input: &[u8], name: &[u8]) -> bool {
+    is_replaced(name) || input.starts_with(name)
+}
+
+/// Whether `name` may contain NUL characters that have been replaced.
+fn is_replaced(name: &[u8]) -> bool {
+    name.windows(3).any(|x| x == "\u{fffd}".as_bytes())
+}
+
+fn check_token_spans(input: &[u8]) -> Result<(), SpanViolation> {
+    let mut previous_end = 0;
+    for Ok((token, span)) in Tokenizer::new_with_emitter(input, DefaultEmitter::with_spans()) {
+        let source = source(input, span)?;
+        let check = |ok: bool, message: &str| {
+            if ok {
+                Ok(())
+            } else {
+                Err(SpanViolation::new(input, span, message))
+            }
+        };
+
+        match token {
+            Token::Error(_) => {
+                check(span.is_empty(), "error span is not empty")?;
+                continue;
+            }
+            Token::StartTag(_) => check(
+                source.starts_with(b"<") && source.ends_with(b">"),
+                "start tag does not span from < to >",
+            )?,
+            Token::EndTag(_) => check(
+                source.starts_with(b"</") && source.ends_with(b">"),
+                "end tag does not span from </ to >",
+            )?,
+            Token::Comment(_) => check(source.starts_with(b"<"), "comment does not start with <")?,
+            Token::Doctype(_) => check(source.starts_with(b"<"), "doctype does not start with <")?,
+            Token::String(value) => check(
+                decode_text(source) == *value,
+                "string span does not decode to the string",
+            )?,
+        }
+
+        check(
+            span.start >= previous_end,
+            "span overlaps the previous token",
+        )?;
+        previous_end = span.end;
+    }
+
+    Ok(())
+}
+
+/// Checks the events of a [`CallbackEmitter`] against the input, and stops at the first
+/// violation.
+#[derive(Debug)]
+struct SpanChecker<'a> {
+    input: &'a [u8],
+    token_start: usize,
+    position: usize,
+    /// Where the next attribute name or value may start.
+    attribute_start: usize,
+    value_span: Option<Span>,
+    violation: Option<SpanViolation>,
+}
+
+impl SpanChecker<'_> {
+    fn tag_span(&self) -> Span {
+        Span {
+            start: self.token_start,
+            end: self.position,
+        }
+    }
+
+    fn check_event(&mut self, event: CallbackEvent<'_>) -> Result<(), SpanViolation> {
+        let input = self.input;
+        let check = |ok: bool, span: Span, message: &str| {
+            if ok {
+                Ok(())
+            } else {
+                Err(SpanViolation::new(input, span, message))
+            }
+        };
+
+        match event {
+            CallbackEvent::OpenStartTag { name } => {
+                let span = self.tag_span();
+                let source = source(input, span)?;
+                check(
+                    source.starts_with(b"<") && starts_with_name(&source[1..], name),
+                    span,
+                    "start tag does not start with < and its name",
+                )?;
+                if !is_replaced(name) {
+                    self.attribute_start = span.start + 1 + name.len();
+                }
+            }
+            CallbackEvent::EndTag { name } => {
+                let span = self.tag_span();
+                let source = source(input, span)?;
+                check(
+                    source.starts_with(b"</")
+                        && starts_with_name(&source[2..], name)
+                        && source.ends_with(b">"),
+                    span,
+                    "end tag does not start with </ and its name, and end with >",
+                )?;
+            }
+            CallbackEvent::AttributeName { name } if !is_replaced(name) => {
+                let span = Span {
+                    start: self.attribute_start,
+                    end: self.position,
+                };
+                let found = source(input, span)?
+                    .windows(name.len())
+                    .position(|x| x == name);
+                check(
+                    found.is_some(),
+                    span,
+                    "attribute name does not appear in its tag",
+                )?;
+                self.attribute_start += found.unwrap_or_default() + name.len();
+            }
+            CallbackEvent::AttributeValue { value, .. } => {
+                let span = self.value_span.take().ok_or_else(|| {
+                    SpanViolation::new(input, self.tag_span(), "attribute value has no span")
+                })?;
+                let source = source(input, span)?;
+                check(
+                    span.start >= self.attribute_start && span.end <= self.position,
+                    span,
+                    "attribute value is not between its name and the end of its tag",
+                )?;
+
+                let before = &input[..span.start];
+                let delimited = match before.last() {
+                    Some(&quote @ (b'"' | b'\'')) => {
+                        input.get(span.end).copied().unwrap_or(quote) == quote
+                    }
+                    _ => before.trim_ascii_end().ends_with(b"="),
+                };
+                check(
+                    delimited,
+                    span,
+                    "attribute value is not delimited by = or quotes",
+                )?;
+
+                check(
+                    source.iter().any(|x| matches!(x, b'&' | b'\r' | b'\0')) || source == value,
+                    span,
+                    "attribute value differs from its span",
+                )?;
+                self.attribute_start = span.end;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+impl Callback<Infallible> for SpanChecker<'_> {
+    fn handle_event(&mut self, event: CallbackEvent<'_>) -> Option<Infallible> {
+        if let Err(violation) = self.check_event(event) {
+            self.violation = Some(violation);
+        }
+        None
+    }
+
+    fn set_token_start(&mut self, offset: usize) {
+        self.token_start = offset;
+        self.attribute_start = offset;
+    }
+
+    fn set_attribute_value_span(&mut self, span: Span) {
+        self.value_span = Some(span);
+    }
+
+    fn set_position(&mut self, offset: usize) {
+        self.position = offset;
+    }
+
+    fn is_aborted(&self) -> bool {
+        self.violation.is_some()
+    }
+}
+
+fn check_callback_spans(input: &[u8]) -> Result<(), SpanViolation> {
+    let mut emitter = CallbackEmitter::new(SpanChecker {
+        input,
+        token_start: 0,
+        position: 0,
+        attribute_start: 0,
+        value_span: None,
+        violation: None,
+    });
+    emitter.preserve_case(true);
+
+    let mut tokenizer = Tokenizer::new_with_emitter(input, emitter);
+    for Ok(_) in &mut tokenizer {}
+    match tokenizer.emitter_mut().callback_mut().violation.take() {
+        Some(violation) => Err(violation),
+        None => Ok(()),
+    }
+}
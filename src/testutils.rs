@@ -2,7 +2,7 @@
 //!
 //! Those tests should only test public API surface in general, with some exceptions as provided by
 //! this module.
-use crate::Reader;
+use crate::{AttributeValueSyntax, CommentKind, Emitter, Error, Reader, State};
 use std::cell::Cell;
 
 thread_local! {
@@ -47,3 +47,401 @@ impl<R: Reader> Reader for SlowReader<R> {
         self.0.try_read_string(s, case_sensitive)
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CurrentToken {
+    StartTag,
+    EndTag,
+    Comment,
+    Doctype,
+}
+
+/// An emitter that wraps another emitter and panics if the tokenizer calls its methods in an order
+/// that violates the contract documented on [`Emitter`].
+///
+/// Only available in tests. `tests/html5lib_tokenizer.rs` runs all testcases through it.
+#[derive(Debug)]
+pub struct DebugValidateEmitter<E> {
+    inner: E,
+    current_token: Option<CurrentToken>,
+    has_attribute: bool,
+    attribute_value_start: Option<usize>,
+    has_public_identifier: bool,
+    has_system_identifier: bool,
+    in_cdata: bool,
+    begun: bool,
+    eof: bool,
+    finished: bool,
+    utf8_strings: bool,
+}
+
+impl<E: Emitter> DebugValidateEmitter<E> {
+    /// Wrap an emitter.
+    pub fn new(inner: E) -> Self {
+        DebugValidateEmitter {
+            inner,
+            current_token: None,
+            has_attribute: false,
+            attribute_value_start: None,
+            has_public_identifier: false,
+            has_system_identifier: false,
+            in_cdata: false,
+            begun: false,
+            eof: false,
+            finished: false,
+            utf8_strings: false,
+        }
+    }
+
+    /// Also panic if `emit_string` is called with invalid UTF-8. This is only guaranteed not to
+    /// happen if the input is valid UTF-8.
+    pub fn expect_utf8_strings(&mut self, yes: bool) {
+        self.utf8_strings = yes;
+    }
+
+    /// Unwrap the inner emitter.
+    pub fn into_inner(self) -> E {
+        self.inner
+    }
+
+    #[track_caller]
+    fn check(&self, method: &str, ok: bool, invariant: &str) {
+        assert!(
+            ok,
+            "Emitter contract violated: {} was called {} (current token: {:?})",
+            method, invariant, self.current_token
+        );
+    }
+
+    #[track_caller]
+    fn expect_not_eof(&self, method: &str) {
+        self.check(method, self.begun, "before begin");
+        self.check(method, !self.eof, "after emit_eof");
+    }
+
+    #[track_caller]
+    fn expect_token(&self, method: &str, expected: &[CurrentToken]) {
+        self.expect_not_eof(method);
+        self.check(
+            method,
+            self.current_token
+                .is_some_and(|token| expected.contains(&token)),
+            &format!("while the current token is not one of {:?}", expected),
+        );
+    }
+
+    #[track_caller]
+    fn expect_tag(&self, method: &str) {
+        self.expect_token(method, &[CurrentToken::StartTag, CurrentToken::EndTag]);
+    }
+
+    #[track_caller]
+    fn expect_attribute(&self, method: &str) {
+        self.expect_tag(method);
+        self.check(
+            method,
+            self.has_attribute,
+            "before init_attribute was called on the current tag",
+        );
+    }
+
+    fn init_token(&mut self, method: &str, token: CurrentToken) {
+        self.expect_not_eof(method);
+        self.current_token = Some(token);
+        self.has_attribute = false;
+        self.has_public_identifier = false;
+        self.has_system_identifier = false;
+    }
+}
+
+impl<E: Emitter> Emitter for DebugValidateEmitter<E> {
+    type Token = E::Token;
+
+    fn set_last_start_tag(&mut self, last_start_tag: Option<&[u8]>) {
+        self.inner.set_last_start_tag(last_start_tag);
+    }
+
+    fn last_start_tag(&self) -> Option<&[u8]> {
+        self.inner.last_start_tag()
+    }
+
+    fn begin(&mut self) {
+        self.check("begin", !self.begun, "twice");
+        self.begun = true;
+        self.inner.begin();
+    }
+
+    fn emit_eof(&mut self) {
+        self.expect_not_eof("emit_eof");
+        self.check("emit_eof", !self.in_cdata, "before end_cdata");
+        self.eof = true;
+        self.inner.emit_eof();
+    }
+
+    fn finish(&mut self) {
+        self.check("finish", self.eof, "before emit_eof");
+        self.check("finish", !self.finished, "twice");
+        self.finished = true;
+        self.inner.finish();
+    }
+
+    fn reset(&mut self) {
+        // may be called at any time, and starts over before `begin`
+        self.current_token = None;
+        self.has_attribute = false;
+        self.attribute_value_start = None;
+        self.has_public_identifier = false;
+        self.has_system_identifier = false;
+        self.in_cdata = false;
+        self.begun = false;
+        self.eof = false;
+        self.finished = false;
+        self.inner.reset();
+    }
+
+    fn emit_error(&mut self, error: Error) {
+        self.inner.emit_error(error);
+    }
+
+    fn should_emit_errors(&mut self) -> bool {
+        self.inner.should_emit_errors()
+    }
+
+    fn pop_token(&mut self) -> Option<Self::Token> {
+        self.check("pop_token", !self.finished, "after finish");
+        self.inner.pop_token()
+    }
+
+    fn emit_string(&mut self, c: &[u8]) {
+        self.expect_not_eof("emit_string");
+        if self.utf8_strings && std::str::from_utf8(c).is_err() {
+            self.check(
+                "emit_string",
+                false,
+                &format!("with a partial character: {:?}", c),
+            );
+        }
+        self.inner.emit_string(c);
+    }
+
+    fn init_start_tag(&mut self) {
+        self.init_token("init_start_tag", CurrentToken::StartTag);
+        self.inner.init_start_tag();
+    }
+
+    fn init_end_tag(&mut self) {
+        self.init_token("init_end_tag", CurrentToken::EndTag);
+        self.inner.init_end_tag();
+    }
+
+    fn init_comment(&mut self) {
+        self.init_token("init_comment", CurrentToken::Comment);
+        self.inner.init_comment();
+    }
+
+    fn set_comment_kind(&mut self, kind: CommentKind) {
+        self.expect_token("set_comment_kind", &[CurrentToken::Comment]);
+        self.inner.set_comment_kind(kind);
+    }
+
+    fn emit_current_tag(&mut self) -> Option<State> {
+        self.expect_tag("emit_current_tag");
+        self.current_token = None;
+        self.inner.emit_current_tag()
+    }
+
+    fn should_skip_rawtext(&mut self, tag_name: &[u8]) -> bool {
+        self.expect_not_eof("should_skip_rawtext");
+        self.check(
+            "should_skip_rawtext",
+            self.current_token.is_none(),
+            "before the current token was emitted",
+        );
+        self.inner.should_skip_rawtext(tag_name)
+    }
+
+    fn emit_current_comment(&mut self) {
+        self.expect_token("emit_current_comment", &[CurrentToken::Comment]);
+        self.current_token = None;
+        self.inner.emit_current_comment();
+    }
+
+    fn emit_current_doctype(&mut self) {
+        self.expect_token("emit_current_doctype", &[CurrentToken::Doctype]);
+        self.current_token = None;
+        self.inner.emit_current_doctype();
+    }
+
+    fn set_self_closing(&mut self) {
+        self.expect_tag("set_self_closing");
+        self.inner.set_self_closing();
+    }
+
+    fn set_force_quirks(&mut self) {
+        self.expect_token("set_force_quirks", &[CurrentToken::Doctype]);
+        self.inner.set_force_quirks();
+    }
+
+    fn push_tag_name(&mut self, s: &[u8]) {
+        self.expect_tag("push_tag_name");
+        self.check(
+            "push_tag_name",
+            !self.has_attribute,
+            "after init_attribute was called on the current tag",
+        );
+        self.inner.push_tag_name(s);
+    }
+
+    fn push_tag_name_original(&mut self, s: &[u8]) {
+        self.expect_tag("push_tag_name_original");
+        self.inner.push_tag_name_original(s);
+    }
+
+    fn push_comment(&mut self, s: &[u8]) {
+        self.expect_token("push_comment", &[CurrentToken::Comment]);
+        self.inner.push_comment(s);
+    }
+
+    fn push_doctype_name(&mut self, s: &[u8]) {
+        self.expect_token("push_doctype_name", &[CurrentToken::Doctype]);
+        self.inner.push_doctype_name(s);
+    }
+
+    fn init_doctype(&mut self) {
+        self.init_token("init_doctype", CurrentToken::Doctype);
+        self.inner.init_doctype();
+    }
+
+    fn init_attribute(&mut self) {
+        self.expect_tag("init_attribute");
+        self.has_attribute = true;
+        self.attribute_value_start = None;
+        self.inner.init_attribute();
+    }
+
+    fn push_attribute_name(&mut self, s: &[u8]) {
+        self.expect_attribute("push_attribute_name");
+        self.inner.push_attribute_name(s);
+    }
+
+    fn push_attribute_name_original(&mut self, s: &[u8]) {
+        self.expect_attribute("push_attribute_name_original");
+        self.inner.push_attribute_name_original(s);
+    }
+
+    fn init_attribute_value(&mut self) {
+        self.expect_attribute("init_attribute_value");
+        self.inner.init_attribute_value();
+    }
+
+    fn push_attribute_value(&mut self, s: &[u8]) {
+        self.expect_attribute("push_attribute_value");
+        self.inner.push_attribute_value(s);
+    }
+
+    fn set_doctype_public_identifier(&mut self, value: &[u8]) {
+        self.expect_token("set_doctype_public_identifier", &[CurrentToken::Doctype]);
+        self.has_public_identifier = true;
+        self.inner.set_doctype_public_identifier(value);
+    }
+
+    fn set_doctype_system_identifier(&mut self, value: &[u8]) {
+        self.expect_token("set_doctype_system_identifier", &[CurrentToken::Doctype]);
+        self.has_system_identifier = true;
+        self.inner.set_doctype_system_identifier(value);
+    }
+
+    fn push_doctype_public_identifier(&mut self, s: &[u8]) {
+        self.expect_token("push_doctype_public_identifier", &[CurrentToken::Doctype]);
+        self.check(
+            "push_doctype_public_identifier",
+            self.has_public_identifier,
+            "before set_doctype_public_identifier",
+        );
+        self.inner.push_doctype_public_identifier(s);
+    }
+
+    fn push_doctype_system_identifier(&mut self, s: &[u8]) {
+        self.expect_token("push_doctype_system_identifier", &[CurrentToken::Doctype]);
+        self.check(
+            "push_doctype_system_identifier",
+            self.has_system_identifier,
+            "before set_doctype_system_identifier",
+        );
+        self.inner.push_doctype_system_identifier(s);
+    }
+
+    fn current_is_appropriate_end_tag_token(&mut self) -> bool {
+        self.expect_token(
+            "current_is_appropriate_end_tag_token",
+            &[CurrentToken::EndTag],
+        );
+        self.inner.current_is_appropriate_end_tag_token()
+    }
+
+    fn adjusted_current_node_present_but_not_in_html_namespace(&mut self) -> bool {
+        self.inner
+            .adjusted_current_node_present_but_not_in_html_namespace()
+    }
+
+    fn start_cdata(&mut self) {
+        self.expect_not_eof("start_cdata");
+        self.check(
+            "start_cdata",
+            self.current_token.is_none() && !self.in_cdata,
+            "inside of a token",
+        );
+        self.in_cdata = true;
+        self.inner.start_cdata();
+    }
+
+    fn end_cdata(&mut self) {
+        self.expect_not_eof("end_cdata");
+        self.check("end_cdata", self.in_cdata, "without start_cdata");
+        self.in_cdata = false;
+        self.inner.end_cdata();
+    }
+
+    fn set_token_start(&mut self, offset: usize) {
+        self.inner.set_token_start(offset)
+    }
+
+    fn set_attribute_value_start(&mut self, offset: usize) {
+        self.expect_attribute("set_attribute_value_start");
+        self.attribute_value_start = Some(offset);
+        self.inner.set_attribute_value_start(offset)
+    }
+
+    fn set_attribute_value_syntax(&mut self, syntax: AttributeValueSyntax) {
+        self.expect_attribute("set_attribute_value_syntax");
+        self.check(
+            "set_attribute_value_syntax",
+            self.attribute_value_start.is_some(),
+            "without a preceding set_attribute_value_start",
+        );
+        self.inner.set_attribute_value_syntax(syntax)
+    }
+
+    fn set_attribute_value_end(&mut self, offset: usize) {
+        self.expect_attribute("set_attribute_value_end");
+        self.check(
+            "set_attribute_value_end",
+            self.attribute_value_start
+                .is_some_and(|start| start <= offset),
+            "without a preceding set_attribute_value_start at or before its offset",
+        );
+        self.inner.set_attribute_value_end(offset)
+    }
+
+    fn set_position(&mut self, offset: usize) {
+        self.inner.set_position(offset)
+    }
+
+    fn set_line(&mut self, line: usize) {
+        self.inner.set_line(line)
+    }
+
+    fn is_aborted(&self) -> bool {
+        self.inner.is_aborted()
+    }
+}
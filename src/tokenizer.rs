@@ -1,19 +1,227 @@
 use std::convert::Infallible;
+use std::fmt::{self, Debug, Formatter};
+use std::fs::File;
+use std::io::{self, BufWriter, Read};
+use std::path::Path;
+use std::str::Utf8Error;
 
 use crate::char_validator::CharValidator;
-use crate::machine_helper::{ControlToken, MachineHelper};
+use crate::charref::{CharacterReferenceResolver, HtmlEntities};
+use crate::emitters::buffered::{BufferedEmitter, TokenRef};
+#[cfg(feature = "trace")]
+use crate::emitters::trace::{TraceEmitter, TraceEvent};
+use crate::emitters::utf8::Utf8Emitter;
+use crate::machine_helper::{state_ref, ControlToken, MachineHelper, MachineState};
 use crate::read_helper::ReadHelper;
 use crate::State;
-use crate::{DefaultEmitter, Emitter, Readable, Reader};
+use crate::{
+    DefaultEmitter, Emitter, HtmlString, IoReader, LineIndex, Readable, Reader, RecordingReader,
+    StringReader, Utf8Reader,
+};
 
-/// A HTML tokenizer. See crate-level docs for basic usage.
+/// The part of the tokenizer's state that is needed to start tokenizing in the middle of a
+/// document. See [`Tokenizer::resume_at`].
+///
+/// This does not include any emitter state other than the _last start tag_, and it is only valid
+/// right after a tag, i.e. not in the middle of a token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ResumeContext {
+    /// The state the tokenizer is in after the tag, for example [`State::RcData`] after `<title>`
+    /// if the emitter switches states.
+    pub state: State,
+    /// The name of the _last start tag_, used to find the end of RCDATA, RAWTEXT and script
+    /// data.
+    pub last_start_tag: Option<HtmlString>,
+    /// Whether the emitter considered the tokenizer to be in foreign content (SVG or MathML),
+    /// meaning that CDATA sections are allowed.
+    ///
+    /// After resuming, this value is used until the next tag is emitted. From then on, the emitter
+    /// is asked again.
+    pub in_foreign_content: bool,
+}
+
+/// A point to resume tokenizing from, possibly in another process. See
+/// [`Tokenizer::save_state`].
+///
+/// Snapshots are taken right after tags, where [`ResumeContext`] is enough to describe the
+/// tokenizer's state. Tokens that were returned after that tag are produced again when resuming,
+/// and skipped.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TokenizerSnapshot {
+    /// The offset into the input to resume at.
+    pub offset: usize,
+    /// The state of the tokenizer at `offset`.
+    pub context: ResumeContext,
+    /// How many tokens after `offset` have already been returned.
+    pub skip_tokens: usize,
+}
+
+/// The last point at which the tokenizer could have been resumed from.
+#[derive(Debug, Default)]
+struct Checkpoint {
+    offset: usize,
+    context: ResumeContext,
+    /// The number of tokens returned before `offset`.
+    tokens_returned: usize,
+}
+
+impl Default for ResumeContext {
+    fn default() -> Self {
+        ResumeContext {
+            state: State::Data,
+            last_start_tag: None,
+            in_foreign_content: false,
+        }
+    }
+}
+
+/// What is left of the input after tokenizing stopped, see [`Tokenizer::into_reader`].
+///
+/// The input consists of the first `consumed` bytes, followed by `read_ahead`, followed by
+/// whatever `reader` returns next.
 #[derive(Debug)]
-pub struct Tokenizer<R: Reader, E: Emitter = DefaultEmitter> {
+pub struct Remainder<R> {
+    /// The reader, which continues with the input that the tokenizer has not taken from it.
+    pub reader: R,
+    /// How many bytes of the input the tokenizer has consumed, see [`Tokenizer::consumed_bytes`].
+    pub consumed: usize,
+    /// Bytes that the tokenizer has taken from `reader`, but not consumed.
+    ///
+    /// This is a few bytes that are going to be reconsumed, at most four. If
+    /// [`Tokenizer::take_bytes`] cut off the input, it also contains the bytes after the limit
+    /// that were taken from the reader, which is at most one chunk of [`Reader::read_until`].
+    /// The same goes for a chunk that is not valid UTF-8, see [`Tokenizer::utf8_policy`].
+    pub read_ahead: Vec<u8>,
+}
+
+/// How to deal with binary garbage, such as long runs of NUL bytes, that sometimes precedes the
+/// actual HTML when crawling. See [`Tokenizer::binary_garbage_mode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GarbagePolicy {
+    /// Tokenize the input as the spec says, producing an error for every NUL or control
+    /// character.
+    #[default]
+    ErrorPerByte,
+    /// Like `ErrorPerByte`, but only produce one error per run of NUL characters, or of control
+    /// characters. Apart from errors, the tokens are the same.
+    CoalesceErrors,
+    /// Discard all input until something that looks like the start of an HTML document appears,
+    /// using the tag patterns from [MIME
+    /// sniffing](https://mimesniff.spec.whatwg.org/#identifying-a-resource-with-an-unknown-mime-type),
+    /// such as `<!DOCTYPE HTML`, `<html` or `<!--` followed by a space or `>`.
+    ///
+    /// If any input was discarded, a single [`crate::Error::SkippedBinaryGarbage`] is emitted, and
+    /// [`Tokenizer::skipped_garbage_len`] returns how many bytes were skipped. If no such tag
+    /// appears at all, the entire input is discarded.
+    SkipUntilPlausibleTag,
+}
+
+/// What to do with NUL characters in the input. See [`Tokenizer::null_handling`].
+///
+/// An [`crate::Error::UnexpectedNullCharacter`] is emitted for every NUL character regardless of
+/// this setting, in the same places as the spec says.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NullHandling {
+    /// Do as the spec says: replace NUL characters with U+FFFD REPLACEMENT CHARACTER, except in
+    /// text outside of RCDATA, RAWTEXT, script data and PLAINTEXT, where they are kept.
+    #[default]
+    Spec,
+    /// Keep all NUL characters, so that strings, names and values contain the same bytes as the
+    /// input.
+    Preserve,
+    /// Remove all NUL characters. A tag or attribute name that consists of nothing but NUL
+    /// characters becomes empty.
+    Strip,
+}
+
+impl NullHandling {
+    /// The bytes to use instead of a NUL character, where the spec says to use `spec`.
+    #[inline(always)]
+    pub(crate) fn replace(self, spec: &'static [u8]) -> &'static [u8] {
+        match self {
+            NullHandling::Spec => spec,
+            NullHandling::Preserve => b"\0",
+            NullHandling::Strip => b"",
+        }
+    }
+}
+
+/// What to do with input that is not valid UTF-8. See [`Tokenizer::utf8_policy`].
+///
+/// The tokenizer works on bytes, and only looks at ASCII characters to decide what to do, so
+/// invalid UTF-8 never changes which tokens there are, only what is in them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Utf8Policy {
+    /// Keep invalid sequences as they are, so that strings, names and values may not be valid
+    /// UTF-8.
+    #[default]
+    PassThrough,
+    /// Replace invalid sequences with U+FFFD REPLACEMENT CHARACTER, as browsers do when they
+    /// decode UTF-8, and as [`String::from_utf8_lossy`] does.
+    ///
+    /// Offsets such as those of [`crate::Span`] still refer to the input, so a replacement
+    /// character may span one to three bytes of the input.
+    ReplaceInvalid,
+    /// Keep invalid sequences as they are, like `PassThrough`, but emit a
+    /// [`crate::Error::InvalidUtf8InInputStream`] for each of them.
+    ///
+    /// To stop tokenizing at the first invalid byte instead, wrap the reader in a
+    /// [`crate::Utf8Reader`].
+    Error,
+}
+
+/// A HTML tokenizer. See crate-level docs for basic usage.
+///
+/// The `Debug` output is meant for troubleshooting and contains the current state, internal
+/// buffers (truncated) and the emitter, but not the reader. The names of states and the bytes
+/// that were read last are only available when debug assertions are enabled.
+///
+/// # Memory use
+///
+/// Besides the reader, the tokenizer itself only holds a temporary buffer of a few dozen bytes,
+/// regardless of the input. It is as large as the longest character reference name of the
+/// [`CharacterReferenceResolver`], or as the last start tag if that is longer. The latter only
+/// holds if the emitter implements [`Emitter::last_start_tag`], which all built-in emitters do.
+/// [`Tokenizer::track_lines`] additionally keeps one offset per line.
+///
+/// Everything else, such as tag names, attributes and text, is collected by the emitter. See
+/// [`crate::Limits`] for how to bound that for the built-in emitters.
+pub struct Tokenizer<
+    R: Reader,
+    E: Emitter = DefaultEmitter,
+    C: CharacterReferenceResolver = HtmlEntities,
+> {
+    /// Whether [`Emitter::begin`] was called.
+    begun: bool,
     eof: bool,
+    /// Whether [`Emitter::finish`] was called.
+    finished: bool,
+    /// See [`Tokenizer::take_tokens`].
+    remaining_tokens: usize,
+    tokens_truncated: bool,
+    /// See [`Tokenizer::save_state`].
+    tokens_returned: usize,
+    checkpoint: Checkpoint,
+    /// Tokens that are dropped, see [`Tokenizer::resume`].
+    skip_tokens: usize,
     pub(crate) validator: CharValidator,
     pub(crate) emitter: E,
     pub(crate) reader: ReadHelper<R>,
-    pub(crate) machine_helper: MachineHelper<R, E>,
+    pub(crate) machine_helper: MachineHelper<R, E, C>,
+    pub(crate) entity_resolver: C,
+}
+
+impl<R: Reader, E: Emitter + Debug, C: CharacterReferenceResolver> Debug for Tokenizer<R, E, C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tokenizer")
+            .field("eof", &self.eof)
+            .field("machine_helper", &self.machine_helper)
+            .field("reader", &self.reader)
+            .field("emitter", &self.emitter)
+            .finish_non_exhaustive()
+    }
 }
 
 impl<R: Reader> Tokenizer<R> {
@@ -27,6 +235,70 @@ impl<R: Reader> Tokenizer<R> {
     pub fn new<'a, S: Readable<'a, Reader = R>>(input: S) -> Self {
         Tokenizer::<S::Reader>::new_with_emitter(input, DefaultEmitter::default())
     }
+
+    /// Create a new tokenizer that skips the first `offset` bytes of the input and starts
+    /// tokenizing with the given context.
+    ///
+    /// See [`Tokenizer::resume_at_with_emitter`].
+    pub fn resume_at<'a, S: Readable<'a, Reader = R>>(
+        input: S,
+        offset: usize,
+        context: ResumeContext,
+    ) -> Result<Self, R::Error> {
+        Tokenizer::<S::Reader>::resume_at_with_emitter(
+            input,
+            offset,
+            context,
+            DefaultEmitter::default(),
+        )
+    }
+
+    /// Create a new tokenizer that continues where the one that `snapshot` was taken from left
+    /// off. The first token it returns is the one that would have come next.
+    ///
+    /// See [`Tokenizer::resume_with_emitter`].
+    pub fn resume<'a, S: Readable<'a, Reader = R>>(
+        input: S,
+        snapshot: TokenizerSnapshot,
+    ) -> Result<Self, R::Error> {
+        Tokenizer::<S::Reader>::resume_with_emitter(input, snapshot, DefaultEmitter::default())
+    }
+}
+
+impl<R: Read> Tokenizer<RecordingReader<IoReader<R>, BufWriter<File>>> {
+    /// Create a new tokenizer that reads from `reader` and records all input it consumes to a new
+    /// file at `path`, which is created or truncated.
+    ///
+    /// Tokenizing the file afterwards produces the same tokens. See [`RecordingReader`] for what
+    /// exactly is recorded. Writes are buffered, and the file is flushed when the end of the input
+    /// is reached or the tokenizer is dropped.
+    pub fn with_recording<P: AsRef<Path>>(reader: IoReader<R>, path: P) -> io::Result<Self> {
+        let writer = BufWriter::new(File::create(path)?);
+        Ok(Tokenizer::new(RecordingReader::new(reader, writer)))
+    }
+}
+
+impl<'a> Tokenizer<StringReader<'a>, Utf8Emitter> {
+    /// Create a new tokenizer that produces tokens with `String`s, see
+    /// [`crate::emitters::utf8`].
+    pub fn new_utf8(input: &'a str) -> Self {
+        Tokenizer::new_with_emitter(input, DefaultEmitter::with_token_type())
+    }
+
+    /// Like [`Tokenizer::new_utf8`], but for bytes that have yet to be validated.
+    ///
+    /// The input is validated up front, and rejected if it is not UTF-8.
+    pub fn new_utf8_bytes(input: &'a [u8]) -> Result<Self, Utf8Error> {
+        Ok(Tokenizer::new_utf8(std::str::from_utf8(input)?))
+    }
+}
+
+impl<R: Reader> Tokenizer<Utf8Reader<R>, Utf8Emitter> {
+    /// Like [`Tokenizer::new_utf8`], but for any reader, such as [`IoReader`]. The input is
+    /// validated while it is read using [`Utf8Reader`].
+    pub fn new_utf8_reader(reader: R) -> Self {
+        Tokenizer::new_with_emitter(Utf8Reader::new(reader), DefaultEmitter::with_token_type())
+    }
 }
 
 impl<R: Reader, E: Emitter> Tokenizer<R, E> {
@@ -35,18 +307,618 @@ impl<R: Reader, E: Emitter> Tokenizer<R, E> {
     /// Use this method over [`Tokenizer::new`] when you want to have more control over string allocation for
     /// tokens.
     pub fn new_with_emitter<'a, S: Readable<'a, Reader = R>>(input: S, emitter: E) -> Self {
+        Tokenizer::new_at(input.to_reader(), emitter, 0)
+    }
+
+    fn new_at(reader: R, mut emitter: E, position: usize) -> Self {
+        emitter.set_position(position);
         Tokenizer {
+            begun: false,
             eof: false,
+            finished: false,
+            remaining_tokens: usize::MAX,
+            tokens_truncated: false,
+            tokens_returned: 0,
+            checkpoint: Checkpoint {
+                offset: position,
+                ..Checkpoint::default()
+            },
+            skip_tokens: 0,
             validator: CharValidator::default(),
             emitter,
-            reader: ReadHelper::new(input.to_reader()),
+            reader: ReadHelper::new(reader, position),
             machine_helper: MachineHelper::default(),
+            entity_resolver: HtmlEntities,
+        }
+    }
+
+    /// Create a new tokenizer that skips the first `offset` bytes of the input and starts
+    /// tokenizing with the given context.
+    ///
+    /// This allows re-tokenizing only a part of a document that has been tokenized before, for
+    /// example everything after `<body>`. `context` has to be obtained from
+    /// [`Tokenizer::context_at_last_token`] right after the tag that ends at `offset`, and the
+    /// emitter has to be configured the same way as in that previous run. If `offset` is not right
+    /// after that tag, the resulting tokens are unspecified garbage.
+    ///
+    /// ```
+    /// use html5gum::{DefaultEmitter, Token, Tokenizer};
+    ///
+    /// let html = "<title>x</title><body class=main><p>hello</p>";
+    ///
+    /// let mut tokenizer = Tokenizer::new(html);
+    /// let mut context = None;
+    /// for token in &mut tokenizer {
+    ///     if let Ok(Token::StartTag(tag)) = token {
+    ///         if tag.name == b"body" {
+    ///             context = Some(tokenizer.context_at_last_token());
+    ///             break;
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let offset = html.find("<p>").unwrap();
+    /// let Ok(tokenizer) = Tokenizer::resume_at(html, offset, context.unwrap());
+    /// let tokens: Vec<_> = tokenizer.flatten().collect();
+    /// assert_eq!(tokens.len(), 3);
+    /// ```
+    pub fn resume_at_with_emitter<'a, S: Readable<'a, Reader = R>>(
+        input: S,
+        offset: usize,
+        context: ResumeContext,
+        emitter: E,
+    ) -> Result<Self, R::Error> {
+        let mut reader = input.to_reader();
+        reader.skip_bytes(offset)?;
+        Ok(Tokenizer::resume_with_reader(
+            reader, offset, context, emitter,
+        ))
+    }
+
+    /// Like [`Tokenizer::resume_at_with_emitter`], but for a reader that has already skipped the
+    /// first `offset` bytes.
+    pub(crate) fn resume_with_reader(
+        reader: R,
+        offset: usize,
+        context: ResumeContext,
+        mut emitter: E,
+    ) -> Self {
+        emitter.set_last_start_tag(context.last_start_tag.as_deref().map(Vec::as_slice));
+
+        let mut tokenizer = Tokenizer::new_at(reader, emitter, offset);
+        tokenizer.set_state(context.state);
+        tokenizer.machine_helper.in_foreign_content = Some(context.in_foreign_content);
+        tokenizer.checkpoint.context = context;
+        tokenizer
+    }
+
+    /// Create a new tokenizer that continues where the one that `snapshot` was taken from left
+    /// off, using [`Tokenizer::save_state`].
+    ///
+    /// `input` has to be the same input as before, from its beginning, and the emitter and
+    /// tokenizer have to be configured the same way. Up to `snapshot.offset` the input is skipped
+    /// using [`Reader::skip_bytes`]. After that, the tokens up to the one that would have been
+    /// returned next are tokenized again and dropped.
+    ///
+    /// ```
+    /// use html5gum::{Token, Tokenizer};
+    ///
+    /// let html = "<title>hello</title><p>world";
+    /// let mut tokenizer = Tokenizer::new(html);
+    /// tokenizer.next();
+    /// tokenizer.next();
+    /// let snapshot = tokenizer.save_state();
+    ///
+    /// let Ok(resumed) = Tokenizer::resume(html, snapshot);
+    /// assert_eq!(
+    ///     resumed.flatten().collect::<Vec<_>>(),
+    ///     tokenizer.flatten().collect::<Vec<_>>(),
+    /// );
+    /// ```
+    pub fn resume_with_emitter<'a, S: Readable<'a, Reader = R>>(
+        input: S,
+        snapshot: TokenizerSnapshot,
+        emitter: E,
+    ) -> Result<Self, R::Error> {
+        let mut tokenizer =
+            Tokenizer::resume_at_with_emitter(input, snapshot.offset, snapshot.context, emitter)?;
+        tokenizer.skip_tokens = snapshot.skip_tokens;
+        Ok(tokenizer)
+    }
+}
+
+impl<R: Reader, E: Emitter, C: CharacterReferenceResolver> Tokenizer<R, E, C> {
+    /// Return the context that is needed to resume tokenizing right after the most recently
+    /// emitted tag, using [`Tokenizer::resume_at`].
+    ///
+    /// This is only meaningful right after the tokenizer yielded a start or end tag. Character
+    /// tokens are usually emitted late, when the tokenizer has already consumed the next tag.
+    pub fn context_at_last_token(&mut self) -> ResumeContext {
+        ResumeContext {
+            state: self.machine_helper.last_tag_state,
+            last_start_tag: self.emitter.last_start_tag().map(|x| x.to_vec().into()),
+            in_foreign_content: self.in_foreign_content(),
+        }
+    }
+
+    fn in_foreign_content(&mut self) -> bool {
+        match self.machine_helper.in_foreign_content {
+            Some(x) => x,
+            None => self
+                .emitter
+                .adjusted_current_node_present_but_not_in_html_namespace(),
+        }
+    }
+
+    /// Return a snapshot of the tokenizer's progress, to continue later using
+    /// [`Tokenizer::resume`]. The snapshot can be serialized with the `serde` feature.
+    ///
+    /// This can be called at any time. Internally, the snapshot refers to the last tag that was
+    /// completely read, so resuming takes longer the more input came after it.
+    pub fn save_state(&self) -> TokenizerSnapshot {
+        TokenizerSnapshot {
+            offset: self.checkpoint.offset,
+            context: self.checkpoint.context.clone(),
+            skip_tokens: self.tokens_returned - self.checkpoint.tokens_returned,
+        }
+    }
+
+    /// Return how many bytes of the input have been consumed so far. For a tokenizer created with
+    /// [`Tokenizer::resume_at`], this includes the skipped bytes.
+    ///
+    /// Bytes are consumed as they are tokenized, which is ahead of the tokens that were returned:
+    /// strings are only returned once the next tag has been read, for example.
+    pub fn consumed_bytes(&self) -> usize {
+        self.reader.position
+    }
+
+    /// Return the offset right after the last tag that was emitted, or `None` if there was none.
+    ///
+    /// Unlike [`Tokenizer::consumed_bytes`], this does not include anything that was consumed
+    /// after the tag. Right after a tag has been returned by the iterator, both are the same.
+    ///
+    /// ```
+    /// use html5gum::{Token, Tokenizer};
+    ///
+    /// let html = "<head><title>x</title></head><body>";
+    /// let mut tokenizer = Tokenizer::new(html);
+    /// for token in &mut tokenizer {
+    ///     if let Ok(Token::EndTag(tag)) = token {
+    ///         if tag.name == b"head" {
+    ///             break;
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let end = tokenizer.last_tag_end().unwrap();
+    /// assert_eq!(&html[end..], "<body>");
+    /// ```
+    pub fn last_tag_end(&self) -> Option<usize> {
+        self.machine_helper.last_tag_end
+    }
+
+    /// Return how much of the input has been consumed so far, from `0.0` to `1.0`, or `None` if
+    /// the size of the input is unknown.
+    ///
+    /// This combines [`Tokenizer::consumed_bytes`] with the upper bound of
+    /// [`crate::Reader::size_hint`], so it is exact for strings and files, and limited by
+    /// [`Tokenizer::take_bytes`].
+    ///
+    /// ```rust
+    /// use html5gum::Tokenizer;
+    ///
+    /// let mut tokenizer = Tokenizer::new("<p>hello</p>");
+    /// assert_eq!(tokenizer.progress(), Some(0.0));
+    /// tokenizer.next();
+    /// assert_eq!(tokenizer.progress(), Some(0.25));
+    /// tokenizer.by_ref().for_each(drop);
+    /// assert_eq!(tokenizer.progress(), Some(1.0));
+    /// ```
+    #[allow(clippy::cast_precision_loss)]
+    pub fn progress(&self) -> Option<f32> {
+        let consumed = self.reader.position as u64;
+        let (_, remaining) = self.reader.size_hint();
+        let total = consumed + remaining?;
+        if total == 0 {
+            return Some(1.0);
+        }
+        Some(consumed as f32 / total as f32)
+    }
+
+    /// Remember the current position as the checkpoint for `save_state`, if it is right after a
+    /// tag and all tokens up to that tag have been returned.
+    fn update_checkpoint(&mut self) {
+        if self.machine_helper.checkpoint_at.take() == Some(self.reader.position) {
+            // Reuse the previous checkpoint's buffer instead of allocating for every tag.
+            let mut last_start_tag = self.checkpoint.context.last_start_tag.take();
+            match (self.emitter.last_start_tag(), &mut last_start_tag) {
+                (Some(name), Some(buf)) => {
+                    buf.clear();
+                    buf.extend(name);
+                }
+                (name, buf) => *buf = name.map(|x| x.to_vec().into()),
+            }
+
+            self.checkpoint = Checkpoint {
+                offset: self.reader.position,
+                context: ResumeContext {
+                    state: self.machine_helper.last_tag_state,
+                    last_start_tag,
+                    in_foreign_content: self.in_foreign_content(),
+                },
+                tokens_returned: self.tokens_returned,
+            };
         }
     }
 
-    /// Override internal state. Necessary for parsing partial documents ("fragment parsing")
+    /// Set how to deal with binary garbage at the start of the input. The default is
+    /// [`GarbagePolicy::ErrorPerByte`].
+    ///
+    /// This has to be called before the first token is read, and before [`Tokenizer::set_state`].
+    pub fn binary_garbage_mode(&mut self, policy: GarbagePolicy) {
+        self.validator.coalesce_errors = policy == GarbagePolicy::CoalesceErrors;
+        self.machine_helper.skip_garbage = policy == GarbagePolicy::SkipUntilPlausibleTag;
+        if self.machine_helper.skip_garbage {
+            self.machine_helper.state = state_ref!(SkipGarbage);
+        }
+    }
+
+    /// Set what to do with NUL characters in the input. The default is [`NullHandling::Spec`].
+    ///
+    /// ```
+    /// use html5gum::{NullHandling, Token, Tokenizer};
+    ///
+    /// let mut tokenizer = Tokenizer::new("<p title=\"a\0b\">c\0d");
+    /// tokenizer.null_handling(NullHandling::Preserve);
+    /// let tokens: Vec<_> = tokenizer
+    ///     .flatten()
+    ///     .filter(|token| !matches!(token, Token::Error(_)))
+    ///     .collect();
+    /// let Token::StartTag(tag) = &tokens[0] else {
+    ///     panic!();
+    /// };
+    /// assert_eq!(tag.attributes[b"title".as_slice()], b"a\0b".as_slice());
+    /// assert_eq!(tokens[1], Token::String(b"c\0d".to_vec().into()));
+    /// ```
+    pub fn null_handling(&mut self, policy: NullHandling) {
+        self.machine_helper.null_handling = policy;
+    }
+
+    /// Set what to do with input that is not valid UTF-8. The default is
+    /// [`Utf8Policy::PassThrough`].
+    ///
+    /// Invalid sequences are the ones the [UTF-8 decoder](https://encoding.spec.whatwg.org/#utf-8-decoder)
+    /// of the Encoding spec replaces with one U+FFFD each, such as a lone continuation byte, a
+    /// sequence that is cut off, an overlong encoding or an encoded surrogate.
+    ///
+    /// ```
+    /// use html5gum::{Token, Tokenizer, Utf8Policy};
+    ///
+    /// let mut tokenizer = Tokenizer::new(&b"<p title=\"a\xffb\">c\xe2\x82d"[..]);
+    /// tokenizer.utf8_policy(Utf8Policy::ReplaceInvalid);
+    /// let tokens: Vec<_> = tokenizer.flatten().collect();
+    /// let Token::StartTag(tag) = &tokens[0] else {
+    ///     panic!();
+    /// };
+    /// assert_eq!(tag.attributes[b"title".as_slice()], "a\u{fffd}b".as_bytes());
+    /// assert_eq!(tokens[1], Token::String("c\u{fffd}d".as_bytes().to_vec().into()));
+    /// ```
+    ///
+    /// This has to be called before the first token is read. Unless the policy is
+    /// `PassThrough`, [`Remainder::read_ahead`] may contain more bytes than usual.
+    pub fn utf8_policy(&mut self, policy: Utf8Policy) {
+        self.reader.set_utf8_policy(policy);
+    }
+
+    /// Keep track of where lines start, so that byte offsets can be converted to line and column
+    /// numbers using [`Tokenizer::line_index`]. The default is off.
+    ///
+    /// Byte offsets are reported to [`Emitter::set_position`] and [`Emitter::set_token_start`],
+    /// and are part of the tokens of [`DefaultEmitter::with_spans`]. With this option, the current
+    /// line is reported to [`Emitter::set_line`] as well. This has to be called before the first
+    /// token is read.
+    ///
+    /// ```
+    /// use html5gum::{DefaultEmitter, Tokenizer};
+    ///
+    /// let mut tokenizer = Tokenizer::new_with_emitter("<p>\r\n  <br>", DefaultEmitter::with_spans());
+    /// tokenizer.track_lines(true);
+    /// let tokens: Vec<_> = tokenizer.by_ref().flatten().collect();
+    /// let (_, br_span) = &tokens[2];
+    /// assert_eq!(tokenizer.line_index().unwrap().line_column(br_span.start), (2, 3));
+    /// ```
+    pub fn track_lines(&mut self, yes: bool) {
+        self.reader.line_index = if yes {
+            Some(LineIndex::new(self.reader.position))
+        } else {
+            None
+        };
+    }
+
+    /// Treat `<` and `</` followed by a non-ASCII character as the start of a tag. The default is
+    /// off.
+    ///
+    /// **This deviates from the spec**, and browsers do not do it: they render `<тег>` or `<日本語>`
+    /// as text, and so does html5gum by default. It is meant for extracting content from markup
+    /// that was written by people who expected such tags to work. The tag name continues until
+    /// the usual whitespace, `/` or `>`, and non-ASCII characters in it are not lowercased.
+    /// `<` followed by an ASCII character that cannot start a tag name, as in `a <= b` or `<3`,
+    /// is still text.
+    ///
+    /// ```
+    /// use html5gum::{Token, Tokenizer};
+    ///
+    /// let mut tokenizer = Tokenizer::new("<страница>");
+    /// tokenizer.lenient_tag_names(true);
+    /// let Some(Ok(Token::StartTag(tag))) = tokenizer.next() else {
+    ///     panic!();
+    /// };
+    /// assert_eq!(tag.name, "страница".as_bytes());
+    /// ```
+    pub fn lenient_tag_names(&mut self, yes: bool) {
+        self.machine_helper.lenient_tag_names = yes;
+    }
+
+    /// Stop reading after `n` more bytes of input, and tokenize as if the input ended there. This
+    /// is meant for generating previews of large documents.
+    ///
+    /// The tokens are the same as for the first `n` bytes of the input, so the last token may be
+    /// incomplete: for example, a tag that is cut off is dropped with an
+    /// [`crate::Error::EofInTag`], and a comment or string that is cut off is emitted up to the
+    /// limit. Use [`Tokenizer::is_truncated`] to find out whether there was more input.
+    ///
+    /// At most one byte after the limit is taken from the reader, but readers such as
+    /// [`IoReader`] may have buffered more than that from their source.
+    ///
+    /// ```
+    /// use html5gum::{Token, Tokenizer};
+    ///
+    /// let mut tokenizer = Tokenizer::new("<title>Hello world</title>");
+    /// tokenizer.take_bytes(12);
+    /// let tokens: Vec<_> = tokenizer.by_ref().flatten().collect();
+    /// assert_eq!(tokens[1], Token::String(b"Hello".to_vec().into()));
+    /// assert!(tokenizer.is_truncated());
+    /// ```
+    pub fn take_bytes(&mut self, n: usize) {
+        self.reader.take_bytes(n);
+    }
+
+    /// Stop after `n` more tokens have been returned by the iterator, including errors. No input
+    /// is read after the last of those tokens.
+    ///
+    /// Unlike with [`Tokenizer::take_bytes`], tokens are never cut off, but a single token may
+    /// contain a large part of the input.
+    pub fn take_tokens(&mut self, n: usize) {
+        self.remaining_tokens = n;
+        self.tokens_truncated = false;
+    }
+
+    /// Whether [`Tokenizer::take_bytes`], [`Tokenizer::take_tokens`] or
+    /// [`crate::Emitter::is_aborted`] stopped tokenizing before the end of the input. This is only
+    /// meaningful once the iterator has returned `None`.
+    ///
+    /// As `take_tokens` does not read ahead, it counts as truncated if the end of the input had
+    /// not been reached yet, even if the rest of the input would not have produced any tokens.
+    pub fn is_truncated(&self) -> bool {
+        self.tokens_truncated || self.reader.is_truncated()
+    }
+
+    /// Return the [`LineIndex`] of everything read so far, if [`Tokenizer::track_lines`] is on.
+    pub fn line_index(&self) -> Option<&LineIndex> {
+        self.reader.line_index.as_ref()
+    }
+
+    /// Return how many bytes at the start of the input have been discarded by
+    /// [`GarbagePolicy::SkipUntilPlausibleTag`].
+    pub fn skipped_garbage_len(&self) -> usize {
+        self.machine_helper.skipped_garbage_len
+    }
+
+    /// Get access to the emitter, for example to retrieve results after tokenizing.
+    pub fn emitter(&self) -> &E {
+        &self.emitter
+    }
+
+    /// Get mutable access to the emitter.
+    pub fn emitter_mut(&mut self) -> &mut E {
+        &mut self.emitter
+    }
+
+    /// Override internal state. Necessary for parsing partial documents ("fragment parsing"), or
+    /// when the tree builder knows better than the emitter which state the tokenizer should be in.
+    ///
+    /// This can be called before the first token is read, or between calls to `next()` right
+    /// after a tag, comment or doctype token. The new state applies to the input following that
+    /// token. Calling it after other tokens is not useful: strings are only emitted once the next
+    /// tag has been read, and errors are emitted in the middle of tokens.
+    ///
+    /// The end of RCDATA, RAWTEXT and script data is detected using the emitter's _last start
+    /// tag_. When switching to those states without a preceding start tag of the right name, set
+    /// it using `tokenizer.emitter_mut().set_last_start_tag(...)`.
+    ///
+    /// ```
+    /// use html5gum::{State, Token, Tokenizer};
+    ///
+    /// let mut tokenizer = Tokenizer::new("<script><b>--></script>");
+    /// assert!(matches!(tokenizer.next(), Some(Ok(Token::StartTag(_)))));
+    ///
+    /// // continue as if the script had started with `<!--`
+    /// tokenizer.set_state(State::ScriptDataEscaped);
+    /// let tokens: Vec<_> = tokenizer.flatten().collect();
+    /// assert_eq!(tokens[0], Token::String(b"<b>-->".to_vec().into()));
+    /// assert!(matches!(tokens[1], Token::EndTag(_)));
+    /// ```
     pub fn set_state(&mut self, state: State) {
-        self.machine_helper.state = state.into();
+        self.machine_helper.reset_state(state);
+        self.machine_helper.checkpoint_at = Some(self.reader.position);
+        if self.checkpoint.offset == self.reader.position {
+            self.checkpoint.context.state = state;
+        }
+    }
+
+    /// Use a custom resolver for named character references, instead of [`HtmlEntities`]. See
+    /// [`crate::charref`].
+    ///
+    /// This has to be called before the first token is read, and before
+    /// [`Tokenizer::binary_garbage_mode`].
+    pub fn set_entity_resolver<C2: CharacterReferenceResolver>(
+        self,
+        entity_resolver: C2,
+    ) -> Tokenizer<R, E, C2> {
+        Tokenizer {
+            begun: self.begun,
+            eof: self.eof,
+            finished: self.finished,
+            remaining_tokens: self.remaining_tokens,
+            tokens_truncated: self.tokens_truncated,
+            tokens_returned: self.tokens_returned,
+            checkpoint: self.checkpoint,
+            skip_tokens: self.skip_tokens,
+            validator: self.validator,
+            emitter: self.emitter,
+            reader: self.reader,
+            machine_helper: self.machine_helper.with_types(),
+            entity_resolver,
+        }
+    }
+
+    /// Call `hook` for every state transition, emitter call and chunk of input that the tokenizer
+    /// reads, to debug how a document is tokenized. Only available with the `trace` feature. See
+    /// [`crate::emitters::trace`].
+    ///
+    /// The emitter is wrapped in a [`TraceEmitter`], which reports the calls to it. Like
+    /// [`Tokenizer::set_entity_resolver`], this has to be called before the first token is read.
+    ///
+    /// ```
+    /// use html5gum::emitters::trace::TraceEvent;
+    /// use html5gum::Tokenizer;
+    ///
+    /// let mut states = Vec::new();
+    /// let tokenizer = Tokenizer::new("<p>").set_trace(|event| {
+    ///     if let TraceEvent::StateTransition { to, .. } = event {
+    ///         states.push(to);
+    ///     }
+    /// });
+    /// assert_eq!(tokenizer.count(), 1);
+    /// assert_eq!(states, ["TagOpen", "TagName", "Data"]);
+    /// ```
+    #[cfg(feature = "trace")]
+    pub fn set_trace<F: FnMut(TraceEvent)>(self, hook: F) -> Tokenizer<R, TraceEmitter<E, F>, C> {
+        Tokenizer {
+            begun: self.begun,
+            eof: self.eof,
+            finished: self.finished,
+            remaining_tokens: self.remaining_tokens,
+            tokens_truncated: self.tokens_truncated,
+            tokens_returned: self.tokens_returned,
+            checkpoint: self.checkpoint,
+            skip_tokens: self.skip_tokens,
+            validator: self.validator,
+            emitter: TraceEmitter::new(self.emitter, hook),
+            reader: self.reader,
+            machine_helper: self.machine_helper.with_types(),
+            entity_resolver: self.entity_resolver,
+        }
+    }
+
+    /// Start over with another input, reusing the emitter, the options and the memory that was
+    /// allocated for the previous input. This saves allocations when tokenizing many small
+    /// documents.
+    ///
+    /// The returned tokenizer behaves like a new one with the same options: it starts in the data
+    /// state, at offset 0, and with no _last start tag_. Anything that was read from the previous
+    /// input but not tokenized yet is dropped, and so are [`Tokenizer::take_bytes`] and
+    /// [`Tokenizer::take_tokens`]. The emitter is cleared using [`Emitter::reset`].
+    ///
+    /// As the new input may have a different lifetime or reader type, this returns a new
+    /// tokenizer. To keep one around between documents, reset it to an empty input:
+    ///
+    /// ```
+    /// use html5gum::Tokenizer;
+    ///
+    /// let documents = vec!["<title>a</title>".to_owned(), "<p>b".to_owned()];
+    ///
+    /// let mut counts = Vec::new();
+    /// let mut idle = Tokenizer::new("");
+    /// for document in documents {
+    ///     let mut tokenizer = idle.reset_with_reader(document.as_str());
+    ///     counts.push(tokenizer.by_ref().count());
+    ///     idle = tokenizer.reset_with_reader("");
+    /// }
+    /// assert_eq!(counts, [3, 2]);
+    /// ```
+    pub fn reset_with_reader<'a, S: Readable<'a>>(
+        mut self,
+        input: S,
+    ) -> Tokenizer<S::Reader, E, C> {
+        self.emitter.reset();
+        self.emitter.set_position(0);
+        let mut validator = CharValidator::default();
+        validator.coalesce_errors = self.validator.coalesce_errors;
+
+        Tokenizer {
+            begun: false,
+            eof: false,
+            finished: false,
+            remaining_tokens: usize::MAX,
+            tokens_truncated: false,
+            tokens_returned: 0,
+            checkpoint: Checkpoint::default(),
+            skip_tokens: 0,
+            validator,
+            emitter: self.emitter,
+            reader: self.reader.reset(input.to_reader()),
+            machine_helper: self.machine_helper.reset(),
+            entity_resolver: self.entity_resolver,
+        }
+    }
+
+    /// Stop tokenizing and return the reader, so that the rest of the input can be handed to other
+    /// code, for example after reading only the `<head>` of a document from a socket.
+    ///
+    /// The tokenizer reads ahead by a few bytes at most, which are returned in
+    /// [`Remainder::read_ahead`]. No input is lost this way, but the tokenizer may have consumed
+    /// input after the last token that was returned, such as the start of the next tag. Break
+    /// out of the iterator right after a tag, where [`Tokenizer::last_tag_end`] and
+    /// [`Tokenizer::consumed_bytes`] agree, and nothing is consumed after it. Readers may also
+    /// buffer input themselves, see [`IoReader::buffer`].
+    ///
+    /// ```
+    /// use html5gum::{Token, Tokenizer};
+    ///
+    /// let html = "<head><title>x</title></head><body>";
+    /// let mut tokenizer = Tokenizer::new(html);
+    /// for token in &mut tokenizer {
+    ///     if let Ok(Token::EndTag(tag)) = token {
+    ///         if tag.name == b"head" {
+    ///             break;
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let remainder = tokenizer.into_reader();
+    /// assert_eq!(&html[..remainder.consumed], "<head><title>x</title></head>");
+    /// assert_eq!(remainder.read_ahead, b"");
+    /// assert_eq!(remainder.reader.remaining(), b"<body>");
+    /// ```
+    pub fn into_reader(self) -> Remainder<R> {
+        let consumed = self.reader.position;
+        let (reader, read_ahead) = self.reader.into_reader();
+        Remainder {
+            reader,
+            consumed,
+            read_ahead,
+        }
+    }
+
+    /// Return the emitter, dropping the rest of the tokenizer.
+    pub(crate) fn into_emitter(self) -> E {
+        self.emitter
+    }
+
+    /// Drop the next `n` tokens instead of returning them, as when resuming from a snapshot.
+    pub(crate) fn skip_tokens(&mut self, n: usize) {
+        self.skip_tokens = n;
     }
 
     /// Test-internal function to override internal state.
@@ -58,7 +930,110 @@ impl<R: Reader, E: Emitter> Tokenizer<R, E> {
     }
 }
 
-impl<R: Reader, E: Emitter<Token = Infallible>> Tokenizer<R, E> {
+impl<R: Reader, E: Emitter, C: CharacterReferenceResolver> Tokenizer<R, E, C> {
+    /// Run one step of the state machine.
+    #[inline(always)]
+    fn step(&mut self) -> Result<(), R::Error> {
+        if !self.begun {
+            self.begun = true;
+            self.emitter.begin();
+        }
+
+        match (self.machine_helper.state.function)(self)? {
+            ControlToken::Continue => (),
+            ControlToken::SwitchTo(next_state) => {
+                self.switch_to(next_state);
+            }
+            ControlToken::Eof => {
+                self.validator.flush_character_error(&mut self.emitter);
+                self.eof = true;
+                self.emitter.emit_eof();
+            }
+        }
+
+        if !self.eof && self.emitter.is_aborted() {
+            self.reader.abort();
+        }
+
+        Ok(())
+    }
+
+    /// Switch the state machine to `state`. All transitions of the state machine go through here,
+    /// except for `Tokenizer::set_state` and the like.
+    #[inline(always)]
+    pub(crate) fn switch_to(&mut self, state: MachineState<R, E, C>) {
+        #[cfg(feature = "trace")]
+        self.emitter.trace(TraceEvent::StateTransition {
+            from: self.machine_helper.state.debug_name,
+            to: state.debug_name,
+            byte: self.reader.last_byte,
+        });
+        self.machine_helper.switch_to(state);
+    }
+
+    /// Ask the emitter whether to skip the content of the tag that was just emitted, if the
+    /// tokenizer is switching to `state` after it. See [`Emitter::should_skip_rawtext`].
+    #[inline(always)]
+    pub(crate) fn should_skip_content(&mut self, state: State) -> bool {
+        if !matches!(
+            state,
+            State::RcData | State::RawText | State::ScriptData | State::PlainText
+        ) {
+            return false;
+        }
+
+        // the temporary buffer is not in use between tags
+        let tag_name = &mut self.machine_helper.temporary_buffer;
+        tag_name.clear();
+        tag_name.extend_from_slice(self.emitter.last_start_tag().unwrap_or_default());
+        let skip = self.emitter.should_skip_rawtext(tag_name);
+        tag_name.clear();
+        skip
+    }
+
+    /// Call [`Emitter::finish`] once the last token has been popped.
+    fn finish_emitter(&mut self) {
+        debug_assert!(self.eof);
+        if !self.finished {
+            self.finished = true;
+            self.emitter.finish();
+        }
+    }
+}
+
+impl<R: Reader, C: CharacterReferenceResolver> Tokenizer<R, BufferedEmitter, C> {
+    /// Return the next token, which borrows from the emitter until this method is called again.
+    /// See [`crate::emitters::buffered`].
+    pub fn next_token(&mut self) -> Option<Result<TokenRef<'_>, R::Error>> {
+        loop {
+            if self.finished {
+                return None;
+            }
+            self.emitter.release_tokens();
+            while !self.emitter.has_token() {
+                if self.eof {
+                    self.finish_emitter();
+                    return None;
+                }
+                self.update_checkpoint();
+                if let Err(e) = self.step() {
+                    return Some(Err(e));
+                }
+            }
+
+            self.tokens_returned += 1;
+            if self.skip_tokens > 0 {
+                self.skip_tokens -= 1;
+                self.emitter.pop_token_ref();
+                continue;
+            }
+
+            return self.emitter.pop_token_ref().map(Ok);
+        }
+    }
+}
+
+impl<R: Reader, E: Emitter<Token = Infallible>, C: CharacterReferenceResolver> Tokenizer<R, E, C> {
     /// Some emitters don't ever produce any tokens and instead have other side effects. In those
     /// cases, you will find yourself writing code like this to handle errors:
     ///
@@ -97,29 +1072,141 @@ impl<R: Reader, E: Emitter<Token = Infallible>> Tokenizer<R, E> {
     }
 }
 
-impl<R: Reader, E: Emitter> Iterator for Tokenizer<R, E> {
+impl<R: Reader, E: Emitter, C: CharacterReferenceResolver> Tokenizer<R, E, C> {
+    /// Iterate over tokens without wrapping each of them in a `Result`. The iterator stops at the
+    /// first error of the reader, which is stored in `error`. Tokens that were completely read
+    /// before the error are still returned.
+    ///
+    /// ```no_run
+    /// use std::fs::File;
+    ///
+    /// use html5gum::{IoReader, Token, Tokenizer};
+    ///
+    /// let file = File::open("index.html")?;
+    /// let mut tokenizer = Tokenizer::new(IoReader::new(file));
+    ///
+    /// let mut error = Ok(());
+    /// for token in tokenizer.until_err(&mut error) {
+    ///     if let Token::StartTag(tag) = token {
+    ///         println!("{}", String::from_utf8_lossy(&tag.name));
+    ///     }
+    /// }
+    /// error?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn until_err<'a>(
+        &'a mut self,
+        error: &'a mut Result<(), R::Error>,
+    ) -> UntilErr<'a, R, E, C> {
+        UntilErr {
+            tokenizer: self,
+            error,
+        }
+    }
+}
+
+/// An iterator over the tokens of a [`Tokenizer`] that stops at the first error. See
+/// [`Tokenizer::until_err`].
+#[derive(Debug)]
+pub struct UntilErr<
+    'a,
+    R: Reader,
+    E: Emitter = DefaultEmitter,
+    C: CharacterReferenceResolver = HtmlEntities,
+> {
+    tokenizer: &'a mut Tokenizer<R, E, C>,
+    error: &'a mut Result<(), R::Error>,
+}
+
+impl<'a, R: Reader, E: Emitter, C: CharacterReferenceResolver> Iterator for UntilErr<'a, R, E, C> {
+    type Item = E::Token;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error.is_err() {
+            return None;
+        }
+
+        match self.tokenizer.next()? {
+            Ok(token) => Some(token),
+            Err(e) => {
+                *self.error = Err(e);
+                None
+            }
+        }
+    }
+}
+
+impl<R: Reader, E: Emitter, C: CharacterReferenceResolver> Iterator for Tokenizer<R, E, C> {
     type Item = Result<E::Token, R::Error>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining_tokens == 0 {
+            if !self.tokens_truncated {
+                self.tokens_truncated = !self.eof || self.emitter.pop_token().is_some();
+            }
+            return None;
+        }
+
+        if self.finished {
+            return None;
+        }
+
         loop {
             if let Some(token) = self.emitter.pop_token() {
+                self.tokens_returned += 1;
+                if self.skip_tokens > 0 {
+                    self.skip_tokens -= 1;
+                    continue;
+                }
+                self.remaining_tokens -= 1;
                 break Some(Ok(token));
             } else if !self.eof {
-                match (self.machine_helper.state.function)(self) {
-                    Ok(ControlToken::Continue) => (),
-                    Ok(ControlToken::SwitchTo(next_state)) => {
-                        self.machine_helper.switch_to(next_state);
-                    }
-                    Ok(ControlToken::Eof) => {
-                        self.validator.flush_character_error(&mut self.emitter);
-                        self.eof = true;
-                        self.emitter.emit_eof();
-                    }
-                    Err(e) => break Some(Err(e)),
+                self.update_checkpoint();
+                if let Err(e) = self.step() {
+                    break Some(Err(e));
                 }
             } else {
+                self.finish_emitter();
                 break None;
             }
         }
     }
 }
+
+#[test]
+#[cfg(debug_assertions)]
+fn test_debug_output() {
+    let mut emitter = DefaultEmitter::default();
+    emitter.naively_switch_states(true);
+    let mut tokenizer = Tokenizer::new_with_emitter("<script>if (a < b) {", emitter);
+    assert!(matches!(
+        tokenizer.next(),
+        Some(Ok(crate::Token::StartTag(_)))
+    ));
+
+    let debug = format!("{:?}", tokenizer);
+    assert!(debug.contains("state: ScriptData"), "{}", debug);
+    assert!(debug.contains("last_start_tag: b\"script\""), "{}", debug);
+    assert!(
+        debug.contains("b\"<script>\" (8 bytes read in total)"),
+        "{}",
+        debug
+    );
+}
+
+#[test]
+fn test_debug_output_is_bounded() {
+    let input = "x".repeat(100_000);
+    let mut tokenizer = Tokenizer::new(&input);
+    tokenizer.emitter.init_comment();
+    tokenizer.emitter.push_comment(input.as_bytes());
+    tokenizer.emitter.emit_string(input.as_bytes());
+    tokenizer
+        .machine_helper
+        .temporary_buffer
+        .extend(input.as_bytes());
+
+    let debug = format!("{:?}", tokenizer);
+    assert!(debug.contains("... (100000 bytes)"), "{}", debug);
+    assert!(debug.len() < 2000, "{}", debug);
+}
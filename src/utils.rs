@@ -1,3 +1,5 @@
+use std::fmt::{self, Debug, Formatter};
+
 macro_rules! surrogate_pat {
     () => {
         0xd800..=0xdfff
@@ -57,6 +59,180 @@ macro_rules! ctostr {
 
 pub(crate) use ctostr;
 
+/// Whether `name` is a [void element](https://html.spec.whatwg.org/#void-elements), i.e. one
+/// that never has an end tag, such as `img` or `br`.
+///
+/// This includes `param`, which is no longer part of the spec, but still parsed and serialized
+/// like a void element. `name` has to be in lowercase, as tag names are by default.
+///
+/// ```rust
+/// use html5gum::is_void_element;
+///
+/// assert!(is_void_element(b"br"));
+/// assert!(!is_void_element(b"div"));
+/// ```
+pub fn is_void_element(name: &[u8]) -> bool {
+    matches!(
+        name,
+        b"area"
+            | b"base"
+            | b"br"
+            | b"col"
+            | b"embed"
+            | b"hr"
+            | b"img"
+            | b"input"
+            | b"link"
+            | b"meta"
+            | b"param"
+            | b"source"
+            | b"track"
+            | b"wbr"
+    )
+}
+
+/// Whether a start tag with `name` closes all foreign elements up to the next HTML element or
+/// integration point.
+pub(crate) fn breaks_out_of_foreign_content(name: &[u8]) -> bool {
+    matches!(
+        name,
+        b"b" | b"big"
+            | b"blockquote"
+            | b"body"
+            | b"br"
+            | b"center"
+            | b"code"
+            | b"dd"
+            | b"div"
+            | b"dl"
+            | b"dt"
+            | b"em"
+            | b"embed"
+            | b"h1"
+            | b"h2"
+            | b"h3"
+            | b"h4"
+            | b"h5"
+            | b"h6"
+            | b"head"
+            | b"hr"
+            | b"i"
+            | b"img"
+            | b"li"
+            | b"listing"
+            | b"menu"
+            | b"meta"
+            | b"nobr"
+            | b"ol"
+            | b"p"
+            | b"pre"
+            | b"ruby"
+            | b"s"
+            | b"small"
+            | b"span"
+            | b"strong"
+            | b"strike"
+            | b"sub"
+            | b"sup"
+            | b"table"
+            | b"tt"
+            | b"u"
+            | b"ul"
+            | b"var"
+    )
+}
+
+/// Whether `name` is a [special](https://html.spec.whatwg.org/#special) HTML element. The MathML
+/// and SVG elements in that category are not included.
+pub(crate) fn is_special_element(name: &[u8]) -> bool {
+    matches!(
+        name,
+        b"address"
+            | b"applet"
+            | b"area"
+            | b"article"
+            | b"aside"
+            | b"base"
+            | b"basefont"
+            | b"bgsound"
+            | b"blockquote"
+            | b"body"
+            | b"br"
+            | b"button"
+            | b"caption"
+            | b"center"
+            | b"col"
+            | b"colgroup"
+            | b"dd"
+            | b"details"
+            | b"dir"
+            | b"div"
+            | b"dl"
+            | b"dt"
+            | b"embed"
+            | b"fieldset"
+            | b"figcaption"
+            | b"figure"
+            | b"footer"
+            | b"form"
+            | b"frame"
+            | b"frameset"
+            | b"h1"
+            | b"h2"
+            | b"h3"
+            | b"h4"
+            | b"h5"
+            | b"h6"
+            | b"head"
+            | b"header"
+            | b"hgroup"
+            | b"hr"
+            | b"html"
+            | b"iframe"
+            | b"img"
+            | b"input"
+            | b"keygen"
+            | b"li"
+            | b"link"
+            | b"listing"
+            | b"main"
+            | b"marquee"
+            | b"menu"
+            | b"meta"
+            | b"nav"
+            | b"noembed"
+            | b"noframes"
+            | b"noscript"
+            | b"object"
+            | b"ol"
+            | b"p"
+            | b"param"
+            | b"plaintext"
+            | b"pre"
+            | b"script"
+            | b"search"
+            | b"section"
+            | b"select"
+            | b"source"
+            | b"style"
+            | b"summary"
+            | b"table"
+            | b"tbody"
+            | b"td"
+            | b"template"
+            | b"textarea"
+            | b"tfoot"
+            | b"th"
+            | b"thead"
+            | b"title"
+            | b"tr"
+            | b"track"
+            | b"ul"
+            | b"wbr"
+            | b"xmp"
+    )
+}
+
 /// Repeatedly call `f` with chunks of lowercased characters from `s`.
 pub(crate) fn with_lowercase_str(s: &[u8], mut f: impl FnMut(&[u8])) {
     if s.iter().any(u8::is_ascii_uppercase) {
@@ -86,3 +262,38 @@ macro_rules! trace_log {
 }
 
 pub(crate) use trace_log;
+
+/// Formats a bytestring like [`crate::HtmlString`] does, without truncating it.
+pub(crate) struct DebugAllBytes<'a>(pub(crate) &'a [u8]);
+
+impl Debug for DebugAllBytes<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "b\"")?;
+        for &byte in self.0 {
+            for ch in std::ascii::escape_default(byte) {
+                write!(f, "{}", ch as char)?;
+            }
+        }
+        write!(f, "\"")
+    }
+}
+
+/// How many bytes of a buffer are shown by [`DebugBytes`].
+const MAX_DEBUG_BYTES: usize = 64;
+
+/// Formats a bytestring like [`crate::HtmlString`] does, but truncated to a fixed length, so that
+/// `Debug` output stays readable no matter how large the tokenizer's buffers grow.
+pub(crate) struct DebugBytes<'a>(pub(crate) &'a [u8]);
+
+impl Debug for DebugBytes<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let shown = &self.0[..self.0.len().min(MAX_DEBUG_BYTES)];
+        DebugAllBytes(shown).fmt(f)?;
+
+        if shown.len() < self.0.len() {
+            write!(f, "... ({} bytes)", self.0.len())?;
+        }
+
+        Ok(())
+    }
+}
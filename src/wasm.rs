@@ -0,0 +1,173 @@
+//! A streaming tokenizer for JavaScript, using `wasm-bindgen`.
+//!
+//! ```js
+//! import { JsTokenizer } from "./pkg/html5gum.js";
+//!
+//! const tokenizer = new JsTokenizer((event) => {
+//!     console.log(event.type, event.start, event.end);
+//! });
+//! tokenizer.write("<p class=a>hel");
+//! tokenizer.write(new TextEncoder().encode("lo</p>"));
+//! tokenizer.end();
+//! ```
+//!
+//! The callback is called with an object for every token, in the same order as
+//! [`crate::emitters::callback::CallbackEvent`]s, except that a start tag is a single event with
+//! all of its attributes. Every object has a `type`, and the byte offsets `start` and `end` of the
+//! token in the input:
+//!
+//! | `type` | Other fields |
+//! | --- | --- |
+//! | `"startTag"` | `name`, `attributes` as an array of `[name, value]` pairs, `selfClosing` |
+//! | `"endTag"` | `name` |
+//! | `"string"` | `value` |
+//! | `"comment"` | `value`, `kind` as `"regular"`, `"bogus"` or `"cdata"` |
+//! | `"doctype"` | `name`, `publicIdentifier` and `systemIdentifier` or `null`, `forceQuirks` |
+//! | `"error"` | `error`, the error code of the spec such as `"eof-in-tag"` |
+//!
+//! Offsets count bytes of the UTF-8 encoded input, so for non-ASCII text they differ from the
+//! indices of JavaScript strings. Strings that are written are encoded as UTF-8.
+//!
+//! Tokens are passed to the callback as soon as they are complete. Like
+//! [`crate::async_tokenizer`], the tokenizer starts over from the end of the last tag, comment or
+//! doctype whenever more input arrives, and only keeps the input after that point in memory.
+//!
+//! This module requires the `wasm` feature.
+use js_sys::{Array, Function, Object, Reflect, Uint8Array};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use crate::chunked::ChunkedTokenizer;
+use crate::{CommentKind, Span, Token};
+
+/// A tokenizer that input is written to in chunks, see the [module docs](self).
+#[wasm_bindgen]
+#[derive(Debug)]
+pub struct JsTokenizer {
+    callback: Function,
+    inner: ChunkedTokenizer,
+    ended: bool,
+}
+
+#[wasm_bindgen]
+impl JsTokenizer {
+    /// Create a tokenizer that calls `callback` with every token.
+    #[wasm_bindgen(constructor)]
+    pub fn new(callback: Function) -> JsTokenizer {
+        JsTokenizer {
+            callback,
+            inner: ChunkedTokenizer::new(),
+            ended: false,
+        }
+    }
+
+    /// Whether to use [`crate::naive_next_state`] to switch states automatically, see
+    /// [`crate::DefaultEmitter::naively_switch_states`].
+    ///
+    /// The default is off. This has to be called before the first chunk is written.
+    #[wasm_bindgen(js_name = naivelySwitchStates)]
+    pub fn naively_switch_states(&mut self, yes: bool) {
+        self.inner.naively_switch_states(yes);
+    }
+
+    /// Append a chunk, which is either a string or a `Uint8Array`, to the input. The callback is
+    /// called for all tokens that are complete afterwards.
+    ///
+    /// Errors thrown by the callback are passed on.
+    pub fn write(&mut self, chunk: &JsValue) -> Result<(), JsValue> {
+        if self.ended {
+            return Err(JsError::new("write after end").into());
+        }
+
+        if let Some(chunk) = chunk.as_string() {
+            self.inner.push(chunk.as_bytes());
+        } else if let Some(chunk) = chunk.dyn_ref::<Uint8Array>() {
+            self.inner.push(&chunk.to_vec());
+        } else {
+            return Err(JsError::new("expected a string or Uint8Array").into());
+        }
+
+        self.flush()
+    }
+
+    /// Mark the end of the input, and call the callback for all remaining tokens.
+    pub fn end(&mut self) -> Result<(), JsValue> {
+        if !self.ended {
+            self.ended = true;
+            self.inner.end();
+        }
+
+        self.flush()
+    }
+
+    fn flush(&mut self) -> Result<(), JsValue> {
+        while let Some((token, span)) = self.inner.next_token() {
+            let event = token_to_js(token, span)?;
+            self.callback.call1(&JsValue::NULL, &event)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn token_to_js(token: Token, span: Span) -> Result<Object, JsValue> {
+    let event = Object::new();
+    let set = |key: &str, value: JsValue| Reflect::set(&event, &key.into(), &value).map(drop);
+
+    match token {
+        Token::StartTag(tag) => {
+            set("type", "startTag".into())?;
+            set("name", string(&tag.name))?;
+            let attributes = Array::new();
+            for (name, value) in &tag.attributes {
+                attributes.push(&Array::of2(&string(name), &string(value)));
+            }
+            set("attributes", attributes.into())?;
+            set("selfClosing", tag.self_closing.into())?;
+        }
+        Token::EndTag(tag) => {
+            set("type", "endTag".into())?;
+            set("name", string(&tag.name))?;
+        }
+        Token::String(value) => {
+            set("type", "string".into())?;
+            set("value", string(&value))?;
+        }
+        Token::Comment(comment) => {
+            set("type", "comment".into())?;
+            set("value", string(&comment.value))?;
+            let kind = match comment.kind {
+                CommentKind::Regular => "regular",
+                CommentKind::Bogus => "bogus",
+                CommentKind::Cdata => "cdata",
+            };
+            set("kind", kind.into())?;
+        }
+        Token::Doctype(doctype) => {
+            set("type", "doctype".into())?;
+            set("name", string(&doctype.name))?;
+            let identifier = |id: Option<&[u8]>| id.map_or(JsValue::NULL, string);
+            set(
+                "publicIdentifier",
+                identifier(doctype.public_identifier.as_deref().map(Vec::as_slice)),
+            )?;
+            set(
+                "systemIdentifier",
+                identifier(doctype.system_identifier.as_deref().map(Vec::as_slice)),
+            )?;
+            set("forceQuirks", doctype.force_quirks.into())?;
+        }
+        Token::Error(error) => {
+            set("type", "error".into())?;
+            set("error", error.as_str().into())?;
+        }
+    }
+
+    set("start", span.start.into())?;
+    set("end", span.end.into())?;
+    Ok(event)
+}
+
+fn string(bytes: &[u8]) -> JsValue {
+    String::from_utf8_lossy(bytes).as_ref().into()
+}
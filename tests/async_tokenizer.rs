@@ -0,0 +1,152 @@
+use std::collections::VecDeque;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use html5gum::async_tokenizer::AsyncTokenizer;
+use html5gum::{DefaultEmitter, Token, Tokenizer};
+use pretty_assertions::assert_eq;
+use tokio::io::{AsyncRead, ReadBuf};
+
+const INPUT: &str = "<!DOCTYPE html><title>a <b> &amp title</title>\
+                     <script>if (a</b) { x = '</script' + '>' }</script>\
+                     <p class=\"x y\" id=z>h\u{e9}llo &notin; &#x1F600; &#128512 world</p>\
+                     <!-- comment -- --!><svg><![CDATA[ <x> ]]></svg>\
+                     <textarea>&lt;/textarea</textarea>\0\r\n<a b='&ampx'><!x>text";
+
+/// Returns the given chunks, with `Poll::Pending` before each of them.
+struct Chunks {
+    chunks: VecDeque<io::Result<Vec<u8>>>,
+    pending: bool,
+}
+
+impl Chunks {
+    fn new<I: IntoIterator<Item = io::Result<Vec<u8>>>>(chunks: I) -> Self {
+        Chunks {
+            chunks: chunks.into_iter().collect(),
+            pending: true,
+        }
+    }
+
+    fn split_at(input: &[u8], splits: &[usize]) -> Self {
+        let mut chunks = Vec::new();
+        let mut start = 0;
+        for &end in splits.iter().chain(Some(&input.len())) {
+            // an empty chunk would mean the end of the input
+            if end > start {
+                chunks.push(Ok(input[start..end].to_vec()));
+            }
+            start = end;
+        }
+        Chunks::new(chunks)
+    }
+}
+
+impl AsyncRead for Chunks {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        if self.pending {
+            self.pending = false;
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+
+        self.pending = true;
+        match self.chunks.pop_front() {
+            Some(Ok(chunk)) => {
+                assert!(chunk.len() <= buf.remaining());
+                buf.put_slice(&chunk);
+                Poll::Ready(Ok(()))
+            }
+            Some(Err(e)) => Poll::Ready(Err(e)),
+            None => Poll::Ready(Ok(())),
+        }
+    }
+}
+
+async fn tokenize<R: AsyncRead + Unpin>(reader: R, naively_switch_states: bool) -> Vec<Token> {
+    let mut tokenizer = AsyncTokenizer::new(reader);
+    tokenizer.naively_switch_states(naively_switch_states);
+    let mut tokens = Vec::new();
+    while let Some(token) = tokenizer.next_token().await {
+        tokens.push(token.unwrap());
+    }
+    tokens
+}
+
+fn tokenize_sync(input: &str, naively_switch_states: bool) -> Vec<Token> {
+    let mut emitter = DefaultEmitter::default();
+    emitter.naively_switch_states(naively_switch_states);
+    Tokenizer::new_with_emitter(input, emitter)
+        .map(Result::unwrap)
+        .collect()
+}
+
+#[tokio::test]
+async fn every_split() {
+    for naively_switch_states in [false, true] {
+        let expected = tokenize_sync(INPUT, naively_switch_states);
+
+        for i in 0..=INPUT.len() {
+            let chunks = Chunks::split_at(INPUT.as_bytes(), &[i]);
+            assert_eq!(
+                tokenize(chunks, naively_switch_states).await,
+                expected,
+                "split at {}",
+                i
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn single_bytes() {
+    for naively_switch_states in [false, true] {
+        let splits: Vec<_> = (1..INPUT.len()).collect();
+        let chunks = Chunks::split_at(INPUT.as_bytes(), &splits);
+        assert_eq!(
+            tokenize(chunks, naively_switch_states).await,
+            tokenize_sync(INPUT, naively_switch_states)
+        );
+    }
+}
+
+#[tokio::test]
+async fn large_input() {
+    let mut html = String::new();
+    for i in 0..2000 {
+        html.push_str(&format!("<p class=x{}>hello &amp; world</p>", i));
+    }
+    html.push_str(&"text without any tags ".repeat(2000));
+
+    assert_eq!(
+        tokenize(html.as_bytes(), false).await,
+        tokenize_sync(&html, false)
+    );
+}
+
+#[tokio::test]
+async fn reader_error() {
+    let chunks = Chunks::new([
+        Ok(b"<a>hel".to_vec()),
+        Err(io::Error::other("oops")),
+        Ok(b"lo</a>".to_vec()),
+    ]);
+    let mut tokenizer = AsyncTokenizer::new(chunks);
+    assert!(matches!(
+        tokenizer.next_token().await,
+        Some(Ok(Token::StartTag(_)))
+    ));
+    let error = tokenizer.next_token().await.unwrap().unwrap_err();
+    assert_eq!(error.to_string(), "oops");
+
+    // reading again continues after the error
+    let mut rest = Vec::new();
+    while let Some(token) = tokenizer.next_token().await {
+        rest.push(token.unwrap());
+    }
+    assert_eq!(rest, tokenize_sync("<a>hello</a>", false)[1..]);
+}
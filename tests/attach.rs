@@ -0,0 +1,208 @@
+//! Parsing a whole document has to build the same body as tokenizing up to some start tag and
+//! attaching the tree builder from there.
+use std::convert::Infallible;
+
+use html5ever::{namespace_url, ns, LocalName, QualName};
+use html5gum::emitters::html5ever::{parse_document, resume_document, AttachContext, AttachError};
+use html5gum::{DefaultEmitter, Token, Tokenizer};
+use markup5ever_rcdom::{Handle, NodeData, RcDom};
+use pretty_assertions::assert_eq;
+
+fn dump(handle: &Handle, depth: usize, buf: &mut String) {
+    let indent = "  ".repeat(depth);
+    match handle.data {
+        NodeData::Element {
+            ref name,
+            ref attrs,
+            ..
+        } => {
+            buf.push_str(&format!("{}<{:?} {}>\n", indent, name.ns, name.local));
+            for attr in attrs.borrow().iter() {
+                buf.push_str(&format!(
+                    "{}  {}={:?}\n",
+                    indent, attr.name.local, attr.value
+                ));
+            }
+        }
+        NodeData::Text { ref contents } => {
+            buf.push_str(&format!("{}{:?}\n", indent, contents.borrow()));
+        }
+        NodeData::Comment { ref contents } => {
+            buf.push_str(&format!("{}<!-- {} -->\n", indent, contents));
+        }
+        _ => {}
+    }
+
+    for child in handle.children.borrow().iter() {
+        dump(child, depth + 1, buf);
+    }
+}
+
+fn find_body(dom: &RcDom) -> String {
+    let children = dom.document.children.borrow();
+    let html = children
+        .iter()
+        .find(|x| matches!(x.data, NodeData::Element { .. }))
+        .unwrap();
+    let html_children = html.children.borrow();
+    let body = html_children
+        .iter()
+        .find(|x| matches!(x.data, NodeData::Element { ref name, .. } if &*name.local == "body"))
+        .unwrap();
+
+    let mut buf = String::new();
+    dump(body, 0, &mut buf);
+    buf
+}
+
+fn html_name(name: &str) -> QualName {
+    QualName::new(None, ns!(html), LocalName::from(name))
+}
+
+/// Tokenize `input` up to the first start tag named like the last of `open_elements`, then build
+/// a tree for the rest.
+fn attach(input: &str, open_elements: &[&str]) -> Result<RcDom, AttachError<Infallible>> {
+    let mut emitter = DefaultEmitter::with_spans();
+    emitter.naively_switch_states(true);
+    let mut tokenizer = Tokenizer::new_with_emitter(input, emitter);
+
+    let mut doctype = None;
+    let mut resume = None;
+    for (token, span) in tokenizer.by_ref().flatten() {
+        match token {
+            Token::Doctype(x) => doctype = Some(x),
+            Token::StartTag(tag) if tag.name == open_elements.last().unwrap().as_bytes() => {
+                resume = Some((span.end, tokenizer.context_at_last_token()));
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    let (offset, context) = resume.unwrap();
+    let attach = AttachContext {
+        doctype,
+        open_elements: open_elements.iter().map(|x| html_name(x)).collect(),
+    };
+    resume_document(
+        input,
+        offset,
+        context,
+        attach,
+        RcDom::default(),
+        Default::default(),
+    )
+}
+
+fn check(input: &str, open_elements: &[&str]) {
+    let Ok(expected) = parse_document(input, RcDom::default(), Default::default());
+    let actual = attach(input, open_elements).unwrap();
+
+    assert_eq!(
+        actual.quirks_mode.get(),
+        expected.quirks_mode.get(),
+        "{:?}",
+        input
+    );
+    assert_eq!(find_body(&actual), find_body(&expected), "{:?}", input);
+}
+
+#[test]
+fn body() {
+    let body = "<body><p>a<b>b<p>c</b>d<!--e--><table><tr><td>x</table>\
+        <svg><title>t</title><![CDATA[<x>]]></svg><textarea>&lt;</textarea>\
+        <script>if (a<b) c()</script><ul><li>1<li>2</ul>";
+
+    check(
+        &format!("<!DOCTYPE html><title>x</title>{}", body),
+        &["html", "body"],
+    );
+    check(
+        &format!(
+            "<!DOCTYPE html><html><head><meta charset=utf-8><style>p {{}}</style></head>{}",
+            body
+        ),
+        &["html", "body"],
+    );
+}
+
+#[test]
+fn quirks_mode() {
+    // in quirks mode, <table> does not close <p>
+    let body = "<body><p><table><tr><td>x</table>";
+    check(&format!("<title>x</title>{}", body), &["html", "body"]);
+    check(
+        &format!(
+            r#"<!DOCTYPE html PUBLIC "-//W3C//DTD HTML 4.01 Transitional//EN"><title>x</title>{}"#,
+            body
+        ),
+        &["html", "body"],
+    );
+    check(
+        &format!("<!DOCTYPE html><title>x</title>{}", body),
+        &["html", "body"],
+    );
+}
+
+#[test]
+fn nested() {
+    check(
+        "<!DOCTYPE html><body><div><ul><li><b>a</div>b",
+        &["html", "body", "div", "ul", "li", "b"],
+    );
+    check(
+        "<!DOCTYPE html><body><table><tbody><tr><td>a<td>b</table>c",
+        &["html", "body", "table", "tbody", "tr", "td"],
+    );
+    check(
+        "<!DOCTYPE html><body><form><input><form><input></form>x",
+        &["html", "body", "form"],
+    );
+}
+
+fn unsupported_element(result: Result<RcDom, AttachError<Infallible>>) -> String {
+    match result {
+        Ok(_) => panic!("attaching succeeded"),
+        Err(err @ AttachError::UnsupportedElement(_)) => err.to_string(),
+        Err(AttachError::Read(x)) => match x {},
+    }
+}
+
+#[test]
+fn unsupported() {
+    assert_eq!(
+        unsupported_element(attach(
+            "<head><template><body><p>",
+            &["html", "head", "template"]
+        )),
+        "cannot attach inside of <template>"
+    );
+    assert_eq!(
+        unsupported_element(attach("<table><tr><td>", &["html", "body", "table", "tr"])),
+        "cannot attach inside of <tr>"
+    );
+    assert_eq!(
+        unsupported_element(attach("<title>", &["html", "body", "title"])),
+        "cannot attach inside of <title>"
+    );
+    assert_eq!(
+        unsupported_element(attach("<body>", &["body"])),
+        "cannot attach inside of <body>"
+    );
+
+    let svg = QualName::new(None, ns!(svg), LocalName::from("svg"));
+    assert_eq!(
+        unsupported_element(resume_document(
+            "",
+            0,
+            Default::default(),
+            AttachContext {
+                doctype: None,
+                open_elements: vec![html_name("html"), html_name("body"), svg],
+            },
+            RcDom::default(),
+            Default::default(),
+        )),
+        "cannot attach inside of <svg>"
+    );
+}
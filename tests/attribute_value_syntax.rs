@@ -0,0 +1,74 @@
+use std::convert::Infallible;
+
+use html5gum::emitters::callback::{CallbackEmitter, CallbackEvent};
+use html5gum::{AttributeValueSyntax, Tokenizer};
+use pretty_assertions::assert_eq;
+
+use AttributeValueSyntax::{DoubleQuoted, Empty, SingleQuoted, Unquoted};
+
+fn paired(input: &str) -> Vec<(String, AttributeValueSyntax)> {
+    let mut attributes = Vec::new();
+    let mut emitter = CallbackEmitter::new(|event: CallbackEvent<'_>| -> Option<Infallible> {
+        if let CallbackEvent::Attribute { name, syntax, .. } = event {
+            attributes.push((String::from_utf8(name.to_vec()).unwrap(), syntax));
+        }
+        None
+    });
+    emitter.pair_attributes(true);
+    let Ok(()) = Tokenizer::new_with_emitter(input, emitter).finish();
+    attributes
+}
+
+#[test]
+fn all_syntaxes_in_one_tag() {
+    assert_eq!(
+        paired(r#"<input a="1" b='2' c=3 d e="" f='' g=>"#),
+        [
+            ("a".to_owned(), DoubleQuoted),
+            ("b".to_owned(), SingleQuoted),
+            ("c".to_owned(), Unquoted),
+            ("d".to_owned(), Empty),
+            ("e".to_owned(), DoubleQuoted),
+            ("f".to_owned(), SingleQuoted),
+            ("g".to_owned(), Unquoted),
+        ]
+    );
+}
+
+#[test]
+fn whitespace_and_end_of_input() {
+    // `e` is dropped together with the unfinished tag
+    assert_eq!(
+        paired("<a b = 'x' c\n=\n\"y\" d =z e"),
+        [
+            ("b".to_owned(), SingleQuoted),
+            ("c".to_owned(), DoubleQuoted),
+            ("d".to_owned(), Unquoted),
+        ]
+    );
+    // the syntax of one attribute does not carry over to the next tag
+    assert_eq!(
+        paired("<a b='x'><a b>"),
+        [("b".to_owned(), SingleQuoted), ("b".to_owned(), Empty)]
+    );
+}
+
+#[test]
+fn attribute_value_events() {
+    let mut values = Vec::new();
+    let emitter = CallbackEmitter::new(|event: CallbackEvent<'_>| -> Option<Infallible> {
+        if let CallbackEvent::AttributeValue { value, syntax } = event {
+            values.push((String::from_utf8(value.to_vec()).unwrap(), syntax));
+        }
+        None
+    });
+    let Ok(()) = Tokenizer::new_with_emitter(r#"<p a="x" b c=y d='&amp;' e="">"#, emitter).finish();
+    assert_eq!(
+        values,
+        [
+            ("x".to_owned(), DoubleQuoted),
+            ("y".to_owned(), Unquoted),
+            ("&".to_owned(), SingleQuoted),
+        ]
+    );
+}
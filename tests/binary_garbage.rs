@@ -0,0 +1,160 @@
+use html5gum::{Error, GarbagePolicy, Token, Tokenizer};
+use pretty_assertions::assert_eq;
+
+const DOCUMENT: &str = "<!DOCTYPE html>\n<html><head><title>hi</title></head>\
+                        <body><p class=x>hello\0world</p><!-- bye --></body></html>\n";
+
+/// 100 KB of pseudo-random bytes, 7/8 of which are NUL. `<` is left out unless `allow_lt` is set, because
+/// it could start a tag that swallows the actual document.
+fn garbage(allow_lt: bool) -> Vec<u8> {
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    (0..100_000)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            match (state >> 56) as u8 {
+                b'<' if !allow_lt => 0,
+                x if x < 224 => 0,
+                x => x,
+            }
+        })
+        .collect()
+}
+
+fn tokenize(input: &[u8], policy: GarbagePolicy) -> Vec<Token> {
+    let mut tokenizer = Tokenizer::new(input);
+    tokenizer.binary_garbage_mode(policy);
+    tokenizer.flatten().collect()
+}
+
+fn count_errors(tokens: &[Token], error: Error) -> usize {
+    tokens
+        .iter()
+        .filter(|token| matches!(token, Token::Error(e) if *e == error))
+        .count()
+}
+
+fn check_policy(policy: GarbagePolicy, allow_lt: bool) -> Vec<Token> {
+    let expected = tokenize(DOCUMENT.as_bytes(), policy);
+    let mut input = garbage(allow_lt);
+    input.extend(DOCUMENT.as_bytes());
+
+    let tokens = tokenize(&input, policy);
+    assert_eq!(tokens[tokens.len() - expected.len()..], expected[..]);
+    tokens
+}
+
+#[test]
+fn error_per_byte() {
+    let tokens = check_policy(GarbagePolicy::ErrorPerByte, false);
+    let nuls = garbage(false).iter().filter(|&&x| x == 0).count();
+    assert_eq!(
+        count_errors(&tokens, Error::UnexpectedNullCharacter),
+        nuls + 1
+    );
+    // all characters of the prefix are coalesced into one string, only errors are per byte
+    let count_strings = |tokens: &[Token]| {
+        tokens
+            .iter()
+            .filter(|token| matches!(token, Token::String(_)))
+            .count()
+    };
+    assert_eq!(
+        count_strings(&tokens),
+        count_strings(&tokenize(DOCUMENT.as_bytes(), GarbagePolicy::ErrorPerByte)) + 1
+    );
+}
+
+#[test]
+fn coalesce_errors() {
+    let tokens = check_policy(GarbagePolicy::CoalesceErrors, false);
+    let default_tokens = check_policy(GarbagePolicy::ErrorPerByte, false);
+
+    let nul_runs = garbage(false)
+        .split(|&x| x != 0)
+        .filter(|run| !run.is_empty())
+        .count();
+    assert_eq!(
+        count_errors(&tokens, Error::UnexpectedNullCharacter),
+        nul_runs + 1
+    );
+    assert!(tokens.len() * 4 < default_tokens.len());
+
+    let without_errors = |tokens: &[Token]| {
+        tokens
+            .iter()
+            .filter(|token| !matches!(token, Token::Error(_)))
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(without_errors(&tokens), without_errors(&default_tokens));
+}
+
+#[test]
+fn coalesce_errors_runs() {
+    let tokens = tokenize(b"\0\0\0a\0\0\x01\x01\x01", GarbagePolicy::CoalesceErrors);
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Error(Error::UnexpectedNullCharacter),
+            Token::Error(Error::UnexpectedNullCharacter),
+            Token::Error(Error::ControlCharacterInInputStream),
+            Token::String(b"\0\0\0a\0\0\x01\x01\x01".to_vec().into()),
+        ]
+    );
+}
+
+#[test]
+fn skip_until_plausible_tag() {
+    for allow_lt in [false, true] {
+        let tokens = check_policy(GarbagePolicy::SkipUntilPlausibleTag, allow_lt);
+        let expected_len = tokenize(DOCUMENT.as_bytes(), GarbagePolicy::ErrorPerByte).len();
+        assert_eq!(tokens.len(), expected_len + 1);
+        assert_eq!(tokens[0], Token::Error(Error::SkippedBinaryGarbage));
+    }
+
+    let input: &[u8] = b"\x89PNG\0\0<b\0<bx<!--x<b>bold</b>";
+    let mut tokenizer = Tokenizer::new(input);
+    tokenizer.binary_garbage_mode(GarbagePolicy::SkipUntilPlausibleTag);
+    let tokens: Vec<_> = (&mut tokenizer).flatten().collect();
+    assert_eq!(
+        tokenizer.skipped_garbage_len(),
+        input.len() - "<b>bold</b>".len()
+    );
+    assert_eq!(
+        tokens[1..],
+        tokenize(b"<b>bold</b>", GarbagePolicy::ErrorPerByte)[..]
+    );
+}
+
+#[test]
+fn skip_until_plausible_tag_patterns() {
+    for input in [
+        "<!doctype html>",
+        "<!DOCTYPE HTML >",
+        "<!-- x -->",
+        "<!-->",
+        "<HTML lang=en>",
+        "<body class=x>",
+        "<br>",
+        "<P>text",
+    ] {
+        let tokens = tokenize(input.as_bytes(), GarbagePolicy::SkipUntilPlausibleTag);
+        assert_eq!(
+            tokens,
+            tokenize(input.as_bytes(), GarbagePolicy::ErrorPerByte)
+        );
+    }
+
+    // not a plausible start, or nothing at all
+    for input in ["<span>x</span>", "<!doctype foo>", "", "garbage"] {
+        let tokens = tokenize(input.as_bytes(), GarbagePolicy::SkipUntilPlausibleTag);
+        let expected = if input.is_empty() {
+            vec![]
+        } else {
+            vec![Token::Error(Error::SkippedBinaryGarbage)]
+        };
+        assert_eq!(tokens, expected);
+    }
+}
@@ -0,0 +1,100 @@
+use std::convert::Infallible;
+
+use html5gum::emitters::buffered::{BufferedEmitter, TokenRef};
+use html5gum::emitters::callback::{CallbackEmitter, CallbackEvent};
+use html5gum::testutils::SlowReader;
+use html5gum::{Comment, DefaultEmitter, Doctype, EndTag, Readable, StartTag, Token, Tokenizer};
+use pretty_assertions::assert_eq;
+
+mod testutils;
+
+#[global_allocator]
+static ALLOC: testutils::CountingAlloc = testutils::CountingAlloc;
+
+fn to_owned(token: TokenRef<'_>) -> Token {
+    match token {
+        TokenRef::StartTag(tag) => Token::StartTag(StartTag {
+            self_closing: tag.self_closing,
+            name: tag.name.to_vec().into(),
+            attributes: tag
+                .attributes()
+                .map(|(name, value)| (name.to_vec().into(), value.to_vec().into()))
+                .collect(),
+        }),
+        TokenRef::EndTag(name) => Token::EndTag(EndTag {
+            name: name.to_vec().into(),
+        }),
+        TokenRef::String(s) => Token::String(s.to_vec().into()),
+        TokenRef::Comment(value, kind) => Token::Comment(Comment {
+            value: value.to_vec().into(),
+            kind,
+        }),
+        TokenRef::Doctype(doctype) => Token::Doctype(Doctype {
+            force_quirks: doctype.force_quirks,
+            name: doctype.name.to_vec().into(),
+            public_identifier: doctype.public_identifier.map(|x| x.to_vec().into()),
+            system_identifier: doctype.system_identifier.map(|x| x.to_vec().into()),
+        }),
+        TokenRef::Error(error) => Token::Error(error),
+    }
+}
+
+fn tokenize<'a, S: Readable<'a>>(input: S) -> Vec<Token> {
+    let mut emitter = BufferedEmitter::default();
+    emitter.naively_switch_states(true);
+    let mut tokenizer = Tokenizer::new_with_emitter(input, emitter);
+    let mut tokens = Vec::new();
+    while let Some(token) = tokenizer.next_token() {
+        tokens.push(to_owned(token.map_err(|_| ()).unwrap()));
+    }
+    tokens
+}
+
+#[test]
+fn same_as_default_emitter() {
+    for input in testutils::html5lib_inputs() {
+        let mut emitter = DefaultEmitter::default();
+        emitter.naively_switch_states(true);
+        let expected: Vec<_> = Tokenizer::new_with_emitter(&input, emitter)
+            .map(Result::unwrap)
+            .collect();
+
+        assert_eq!(tokenize(&input), expected, "{:?}", input);
+        assert_eq!(
+            tokenize(SlowReader(input.to_reader())),
+            expected,
+            "{:?}",
+            input
+        );
+    }
+}
+
+#[test]
+fn reuses_buffers() {
+    let input = "<p class=x id=y>lorem ipsum</p><!-- x -->".repeat(1000);
+
+    // debug builds allocate for logging, which is the same for all emitters
+    let ((), baseline) = testutils::count_allocations(|| {
+        let emitter = CallbackEmitter::new(|_: CallbackEvent<'_>| -> Option<Infallible> { None });
+        let Ok(()) = Tokenizer::new_with_emitter(&input, emitter).finish();
+    });
+
+    let ((), buffered) = testutils::count_allocations(|| {
+        let mut tokenizer = Tokenizer::new_with_emitter(&input, BufferedEmitter::default());
+        let mut count = 0;
+        while let Some(token) = tokenizer.next_token() {
+            if let Ok(TokenRef::StartTag(tag)) = token {
+                assert_eq!(tag.attribute(b"id"), Some(&b"y"[..]));
+            }
+            count += 1;
+        }
+        assert_eq!(count, 4000);
+    });
+
+    let ((), default) = testutils::count_allocations(|| {
+        assert_eq!(Tokenizer::new(&input).count(), 4000);
+    });
+
+    assert!(buffered < baseline + 50, "{} {}", buffered, baseline);
+    assert!(default > baseline + 4000, "{} {}", default, baseline);
+}
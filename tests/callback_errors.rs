@@ -0,0 +1,79 @@
+use std::cell::Cell;
+use std::convert::Infallible;
+use std::io::{self, Read};
+use std::rc::Rc;
+
+use html5gum::emitters::callback::{CallbackEmitter, CallbackEvent};
+use html5gum::{IoReader, Tokenizer};
+use pretty_assertions::assert_eq;
+
+/// Returns the data one byte at a time, and counts the calls to `read`.
+struct CountingReader<'a> {
+    input: &'a [u8],
+    reads: Rc<Cell<usize>>,
+}
+
+impl Read for CountingReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reads.set(self.reads.get() + 1);
+        match self.input.split_first() {
+            Some((&x, rest)) if !buf.is_empty() => {
+                buf[0] = x;
+                self.input = rest;
+                Ok(1)
+            }
+            _ => Ok(0),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct TooManyEvents {
+    reads: usize,
+}
+
+#[test]
+fn error_stops_reading() {
+    let input = "<p class=x>hello</p>".repeat(1000);
+    let reads = Rc::new(Cell::new(0));
+    let reader = IoReader::new_with_buffer_size::<32>(CountingReader {
+        input: input.as_bytes(),
+        reads: reads.clone(),
+    });
+
+    let mut events = 0;
+    let reads2 = reads.clone();
+    let emitter = CallbackEmitter::new_try(move |_: CallbackEvent<'_>| {
+        events += 1;
+        if events == 3 {
+            return Err(TooManyEvents {
+                reads: reads2.get(),
+            });
+        }
+        Ok(Some(events))
+    });
+
+    let mut tokenizer = Tokenizer::new_with_emitter(reader, emitter);
+    let tokens: Vec<_> = tokenizer.by_ref().map(Result::unwrap).collect();
+    assert_eq!(
+        tokens,
+        [Ok(1), Ok(2), Err(TooManyEvents { reads: reads.get() })]
+    );
+    assert!(tokenizer.is_truncated());
+    assert!(tokenizer.next().is_none());
+    // the input was not read to the end
+    assert!(reads.get() < 32, "{}", reads.get());
+}
+
+#[test]
+fn no_error() {
+    let emitter = CallbackEmitter::new_try(|event: CallbackEvent<'_>| match event {
+        CallbackEvent::String { value } => Ok::<_, Infallible>(Some(value.to_vec())),
+        _ => Ok(None),
+    });
+
+    let mut tokenizer = Tokenizer::new_with_emitter("<p>hello</p>world", emitter);
+    let tokens: Vec<_> = tokenizer.by_ref().map(Result::unwrap).collect();
+    assert_eq!(tokens, [Ok(b"hello".to_vec()), Ok(b"world".to_vec())]);
+    assert!(!tokenizer.is_truncated());
+}
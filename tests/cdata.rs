@@ -0,0 +1,257 @@
+use std::convert::Infallible;
+
+use html5gum::emitters::callback::{CallbackEmitter, CallbackEvent};
+use html5gum::push::PushTokenizer;
+use html5gum::testutils::SlowReader;
+use html5gum::{ContentStateSwitcher, Emitter, Error, Readable, Reader, State, Tokenizer};
+use pretty_assertions::assert_eq;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Text(String),
+    CdataStart,
+    CdataEnd,
+    Error(Error),
+}
+
+fn text(s: &str) -> Piece {
+    Piece::Text(s.to_owned())
+}
+
+/// An emitter that is always in foreign content, and only records text and the bounds of CDATA
+/// sections.
+#[derive(Default)]
+struct ForeignContent {
+    queue: Vec<Piece>,
+}
+
+impl ForeignContent {
+    fn push(&mut self, piece: Piece) {
+        self.queue.push(piece);
+    }
+}
+
+impl Emitter for ForeignContent {
+    type Token = Piece;
+
+    fn set_last_start_tag(&mut self, _last_start_tag: Option<&[u8]>) {}
+    fn emit_eof(&mut self) {}
+    fn emit_error(&mut self, error: Error) {
+        self.push(Piece::Error(error));
+    }
+    fn pop_token(&mut self) -> Option<Piece> {
+        if self.queue.is_empty() {
+            None
+        } else {
+            Some(self.queue.remove(0))
+        }
+    }
+    fn emit_string(&mut self, c: &[u8]) {
+        let c = String::from_utf8(c.to_vec()).unwrap();
+        // combine text, like the DefaultEmitter does
+        if let Some(Piece::Text(s)) = self.queue.last_mut() {
+            s.push_str(&c);
+        } else {
+            self.push(Piece::Text(c));
+        }
+    }
+    fn init_start_tag(&mut self) {}
+    fn init_end_tag(&mut self) {}
+    fn init_comment(&mut self) {}
+    fn emit_current_tag(&mut self) -> Option<State> {
+        None
+    }
+    fn emit_current_comment(&mut self) {}
+    fn emit_current_doctype(&mut self) {}
+    fn set_self_closing(&mut self) {}
+    fn set_force_quirks(&mut self) {}
+    fn push_tag_name(&mut self, _s: &[u8]) {}
+    fn push_comment(&mut self, _s: &[u8]) {}
+    fn push_doctype_name(&mut self, _s: &[u8]) {}
+    fn init_doctype(&mut self) {}
+    fn init_attribute(&mut self) {}
+    fn push_attribute_name(&mut self, _s: &[u8]) {}
+    fn push_attribute_value(&mut self, _s: &[u8]) {}
+    fn set_doctype_public_identifier(&mut self, _value: &[u8]) {}
+    fn set_doctype_system_identifier(&mut self, _value: &[u8]) {}
+    fn push_doctype_public_identifier(&mut self, _s: &[u8]) {}
+    fn push_doctype_system_identifier(&mut self, _s: &[u8]) {}
+    fn current_is_appropriate_end_tag_token(&mut self) -> bool {
+        false
+    }
+    fn adjusted_current_node_present_but_not_in_html_namespace(&mut self) -> bool {
+        true
+    }
+    fn start_cdata(&mut self) {
+        self.push(Piece::CdataStart);
+    }
+    fn end_cdata(&mut self) {
+        self.push(Piece::CdataEnd);
+    }
+    fn reset(&mut self) {
+        self.queue.clear();
+    }
+}
+
+fn pieces<R: Reader<Error = Infallible>>(reader: R) -> Vec<Piece> {
+    let tokenizer = Tokenizer::new_with_emitter(reader, ForeignContent::default());
+    let mut pieces: Vec<Piece> = Vec::new();
+    for piece in tokenizer.flatten() {
+        // text is combined across `emit_string` calls, but not across `pop_token`
+        match (pieces.last_mut(), piece) {
+            (Some(Piece::Text(s)), Piece::Text(t)) => s.push_str(&t),
+            (_, piece) => pieces.push(piece),
+        }
+    }
+    pieces
+}
+
+/// Tokenize `input` in one go, byte by byte, and pushed in two chunks split at every byte, and
+/// check that the result is always the same.
+fn check(input: &str, expected: &[Piece]) {
+    assert_eq!(pieces(input.to_reader()), expected, "{:?}", input);
+    assert_eq!(
+        pieces(SlowReader(input.to_reader())),
+        expected,
+        "{:?} byte by byte",
+        input
+    );
+
+    for i in 0..=input.len() {
+        let mut tokenizer = PushTokenizer::new(ForeignContent::default());
+        let mut pushed: Vec<_> = tokenizer.push(&input.as_bytes()[..i]).collect();
+        pushed.extend(tokenizer.push(&input.as_bytes()[i..]));
+        pushed.extend(tokenizer.finish());
+        let mut combined: Vec<Piece> = Vec::new();
+        for piece in pushed {
+            match (combined.last_mut(), piece) {
+                (Some(Piece::Text(s)), Piece::Text(t)) => s.push_str(&t),
+                (_, piece) => combined.push(piece),
+            }
+        }
+        assert_eq!(combined, expected, "{:?} split at {}", input, i);
+    }
+}
+
+#[test]
+fn simple() {
+    check(
+        "a<![CDATA[x<y&amp;]]>b",
+        &[
+            text("a"),
+            Piece::CdataStart,
+            text("x<y&amp;"),
+            Piece::CdataEnd,
+            text("b"),
+        ],
+    );
+    check("<![CDATA[]]>", &[Piece::CdataStart, Piece::CdataEnd]);
+}
+
+#[test]
+fn brackets() {
+    check(
+        "<![CDATA[]]]>",
+        &[Piece::CdataStart, text("]"), Piece::CdataEnd],
+    );
+    check(
+        "<![CDATA[]]]]>",
+        &[Piece::CdataStart, text("]]"), Piece::CdataEnd],
+    );
+    check(
+        "<![CDATA[a]b]]c]]]>d",
+        &[
+            Piece::CdataStart,
+            text("a]b]]c]"),
+            Piece::CdataEnd,
+            text("d"),
+        ],
+    );
+    check(
+        "<![CDATA[]>]] >]]>",
+        &[Piece::CdataStart, text("]>]] >"), Piece::CdataEnd],
+    );
+}
+
+#[test]
+fn end_of_input() {
+    for (input, content) in [
+        ("<![CDATA[x", "x"),
+        ("<![CDATA[x]", "x]"),
+        ("<![CDATA[x]]", "x]]"),
+        ("<![CDATA[x]]]", "x]]]"),
+    ] {
+        check(
+            input,
+            &[
+                Piece::CdataStart,
+                text(content),
+                Piece::Error(Error::EofInCdata),
+                Piece::CdataEnd,
+            ],
+        );
+    }
+    check(
+        "<![CDATA[",
+        &[
+            Piece::CdataStart,
+            Piece::Error(Error::EofInCdata),
+            Piece::CdataEnd,
+        ],
+    );
+}
+
+fn callback_events(input: &str, mark_cdata_sections: bool) -> Vec<String> {
+    let mut events = Vec::new();
+    let mut emitter = CallbackEmitter::new(|event: CallbackEvent<'_>| -> Option<Infallible> {
+        events.push(match event {
+            CallbackEvent::OpenStartTag { name } | CallbackEvent::EndTag { name } => {
+                String::from_utf8(name.to_vec()).unwrap()
+            }
+            CallbackEvent::String { value } => {
+                format!("{:?}", String::from_utf8(value.to_vec()).unwrap())
+            }
+            CallbackEvent::CdataStart => "CdataStart".to_owned(),
+            CallbackEvent::CdataEnd => "CdataEnd".to_owned(),
+            CallbackEvent::Comment { .. } => "comment".to_owned(),
+            CallbackEvent::Error(error) => format!("{:?}", error),
+            _ => return None,
+        });
+        None
+    });
+    emitter.switch_states(Some(ContentStateSwitcher::default()));
+    emitter.mark_cdata_sections(mark_cdata_sections);
+    let Ok(()) = Tokenizer::new_with_emitter(input, emitter).finish();
+    events
+}
+
+#[test]
+fn callback_emitter() {
+    let input = "<svg>a<![CDATA[b&amp;]]]>c</svg><![CDATA[d]]>";
+    assert_eq!(
+        callback_events(input, true),
+        [
+            "svg",
+            "\"a\"",
+            "CdataStart",
+            "\"b&amp;]\"",
+            "CdataEnd",
+            "\"c\"",
+            "svg",
+            // not in foreign content anymore
+            "CdataInHtmlContent",
+            "comment",
+        ]
+    );
+    // without marking, the content is just text
+    assert_eq!(
+        callback_events(input, false),
+        [
+            "svg",
+            "\"ab&amp;]c\"",
+            "svg",
+            "CdataInHtmlContent",
+            "comment",
+        ]
+    );
+}
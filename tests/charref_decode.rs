@@ -0,0 +1,105 @@
+use std::borrow::Cow;
+
+use html5gum::charref::{decode, lookup_named};
+use html5gum::{Token, Tokenizer};
+use pretty_assertions::assert_eq;
+
+fn decoded(input: &str, in_attribute: bool) -> String {
+    String::from_utf8(decode(input.as_bytes(), in_attribute).into_owned()).unwrap()
+}
+
+#[test]
+fn whatwg_examples() {
+    // https://html.spec.whatwg.org/#named-character-reference-state
+    assert_eq!(
+        decoded("I'm &notit; I tell you", false),
+        "I'm ¬it; I tell you"
+    );
+    assert_eq!(decoded("I'm &notin; I tell you", false), "I'm ∉ I tell you");
+    assert_eq!(decoded("&amp=", false), "&=");
+    assert_eq!(decoded("&amp=", true), "&amp=");
+    assert_eq!(decoded("&#x80;", false), "€");
+    assert_eq!(decoded("&#128;", true), "€");
+}
+
+#[test]
+fn numeric() {
+    assert_eq!(decoded("&#65;&#x42;&#X43;&#0068", false), "ABCD");
+    assert_eq!(
+        decoded("&#0;&#xd800;&#x110000;&#99999999999;", false),
+        "\u{fffd}".repeat(4)
+    );
+    assert_eq!(decoded("&#x81;&#x9;&#xFDD0;", false), "\u{81}\t\u{fdd0}");
+    // no digits
+    assert_eq!(decoded("&#;&#x;&#xg;&#", false), "&#;&#x;&#xg;&#");
+}
+
+#[test]
+fn named() {
+    assert_eq!(decoded("&lt&gt;&unknown;&", false), "<>&unknown;&");
+    assert_eq!(decoded("&ltx &ltx", true), "&ltx &ltx");
+    assert_eq!(decoded("&ltx &ltx", false), "<x <x");
+    assert_eq!(decoded("&lt;x&lt", true), "<x<");
+    assert_eq!(
+        decoded("&CounterClockwiseContourIntegral;", false),
+        "\u{2233}"
+    );
+    assert_eq!(decoded("&&amp;&", false), "&&&");
+}
+
+#[test]
+fn no_references() {
+    assert!(matches!(decode(b"a < b", false), Cow::Borrowed(b"a < b")));
+    assert!(matches!(decode(b"", true), Cow::Borrowed(b"")));
+}
+
+#[test]
+fn lookup() {
+    assert_eq!(lookup_named(b"notin;"), Some("\u{2209}"));
+    assert_eq!(lookup_named(b"not"), Some("\u{ac}"));
+    assert_eq!(lookup_named(b"notin"), None);
+    assert_eq!(lookup_named(b"noti"), None);
+    assert_eq!(lookup_named(b"&amp;"), None);
+    assert_eq!(lookup_named(b""), None);
+}
+
+/// `decode` has to agree with the tokenizer, in text and in attribute values.
+#[test]
+fn same_as_tokenizer() {
+    for input in [
+        "&amp;&amp&ampx&amp=&AMP;&Amp;",
+        "&notit;&notin;&notin&not",
+        "&#x80;&#x80&#128&#x;&#;&#a",
+        "&#0;&#x10FFFF;&#x110000;&#xD800;&#x1F600;&#13;&#x7f;",
+        "&;&&#&x&1&=&ltlt;&lt=3&lt;=3",
+        "&CounterClockwiseContourIntegral;&CounterClockwiseContourIntegralx",
+        "a&b&c;d&e=f&g",
+    ] {
+        let mut text = Vec::new();
+        for token in Tokenizer::new(input).flatten() {
+            if let Token::String(s) = token {
+                text.extend_from_slice(&s);
+            }
+        }
+        assert_eq!(
+            decoded(input, false),
+            String::from_utf8(text).unwrap(),
+            "{:?}",
+            input
+        );
+
+        let tag = format!("<a b=\"{}\">", input);
+        let Some(Token::StartTag(tag)) = Tokenizer::new(&tag)
+            .flatten()
+            .find(|token| matches!(token, Token::StartTag(_)))
+        else {
+            panic!("{:?}", input);
+        };
+        assert_eq!(
+            decoded(input, true),
+            String::from_utf8(tag.attributes[b"b".as_slice()].to_vec()).unwrap(),
+            "{:?} in an attribute",
+            input
+        );
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+
+use html5gum::charref::{CharacterReferenceResolver, HtmlEntities};
+use html5gum::{Error, Token, Tokenizer};
+use pretty_assertions::assert_eq;
+
+mod testutils;
+
+use testutils::string;
+
+/// Only the entities that are safe to decode everywhere.
+struct Allowlist;
+
+impl CharacterReferenceResolver for Allowlist {
+    const MAX_NAME_LEN: usize = 5;
+
+    fn resolve(&self, name: &[u8], _in_attribute: bool) -> Option<&str> {
+        match name {
+            b"amp;" => Some("&"),
+            b"lt;" => Some("<"),
+            b"gt;" => Some(">"),
+            b"quot;" => Some("\""),
+            b"apos;" => Some("'"),
+            _ => None,
+        }
+    }
+}
+
+/// The HTML entities, plus some of our own.
+struct Custom(HashMap<&'static [u8], &'static str>);
+
+impl CharacterReferenceResolver for Custom {
+    fn resolve(&self, name: &[u8], in_attribute: bool) -> Option<&str> {
+        match self.0.get(name) {
+            Some(characters) => Some(characters),
+            None => HtmlEntities.resolve(name, in_attribute),
+        }
+    }
+}
+
+fn custom() -> Custom {
+    Custom(HashMap::from([
+        (b"myent;".as_slice(), "my entity"),
+        (b"myent".as_slice(), "my entity without semicolon"),
+        (b"percnt;".as_slice(), "%"),
+    ]))
+}
+
+fn tokenize<C: CharacterReferenceResolver>(input: &str, resolver: C) -> Vec<Token> {
+    Tokenizer::new(input)
+        .set_entity_resolver(resolver)
+        .flatten()
+        .collect()
+}
+
+fn title(tokens: &[Token]) -> &str {
+    tokens
+        .iter()
+        .find_map(|token| match token {
+            Token::StartTag(tag) => {
+                Some(std::str::from_utf8(&tag.attributes[b"title".as_slice()]).unwrap())
+            }
+            _ => None,
+        })
+        .unwrap()
+}
+
+#[test]
+fn allowlist() {
+    assert_eq!(
+        tokenize("&amp;&nbsp;&lt&quot;&apos;&copy", Allowlist),
+        [
+            Token::Error(Error::UnknownNamedCharacterReference),
+            string("&&nbsp;&lt\"'&copy"),
+        ]
+    );
+
+    let tokens = tokenize("<a title='&amp;&nbsp;&amp=&ampx&notin;'>", Allowlist);
+    assert_eq!(title(&tokens), "&&nbsp;&amp=&ampx&notin;");
+
+    // the default resolver decodes everything
+    assert_eq!(
+        tokenize("&amp;&nbsp;&lt&quot;&apos;&copy", HtmlEntities),
+        [
+            Token::Error(Error::MissingSemicolonAfterCharacterReference),
+            Token::Error(Error::MissingSemicolonAfterCharacterReference),
+            string("&\u{a0}<\"'\u{a9}"),
+        ]
+    );
+}
+
+#[test]
+fn custom_entities() {
+    assert_eq!(
+        tokenize("&myent;, &percnt;, &ndash; and &myentx", custom()),
+        [
+            Token::Error(Error::MissingSemicolonAfterCharacterReference),
+            string("my entity, %, \u{2013} and my entity without semicolonx"),
+        ]
+    );
+
+    // names without semicolon followed by alphanumeric characters are left alone in attributes
+    let tokens = tokenize("<a title='&myent;&myentx&myent=&myent'>", custom());
+    assert_eq!(
+        title(&tokens),
+        "my entity&myentx&myent=my entity without semicolon"
+    );
+}
+
+#[test]
+fn longest_match() {
+    // `&notit;` is `&not` followed by `it;`
+    assert_eq!(
+        tokenize("&notit;&notin;", custom()),
+        [
+            Token::Error(Error::MissingSemicolonAfterCharacterReference),
+            string("\u{ac}it;\u{2209}"),
+        ]
+    );
+}
+
+#[test]
+fn long_names() {
+    let long_name = "a".repeat(100);
+    assert_eq!(
+        tokenize(&format!("&{};&amp;", long_name), Allowlist),
+        [
+            Token::Error(Error::UnknownNamedCharacterReference),
+            string(&format!("&{};&", long_name)),
+        ]
+    );
+
+    assert_eq!(
+        tokenize("&CounterClockwiseContourIntegral;", HtmlEntities),
+        [string("\u{2233}")]
+    );
+}
+
+#[test]
+fn state_is_kept() {
+    let mut tokenizer = Tokenizer::new("<title>&amp;&nbsp;<b></title>");
+    assert!(matches!(tokenizer.next(), Some(Ok(Token::StartTag(_)))));
+    tokenizer.set_state(html5gum::State::RcData);
+    let tokens: Vec<_> = tokenizer.set_entity_resolver(Allowlist).flatten().collect();
+    assert_eq!(
+        tokens[0],
+        Token::Error(Error::UnknownNamedCharacterReference)
+    );
+    assert_eq!(tokens[1], string("&&nbsp;<b>"));
+    assert!(matches!(tokens[2], Token::EndTag(_)));
+}
@@ -0,0 +1,103 @@
+use std::convert::Infallible;
+use std::ops::Range;
+
+use html5gum::emitters::callback::{Callback, CallbackEmitter, CallbackEvent};
+use html5gum::Tokenizer;
+use pretty_assertions::assert_eq;
+
+/// Records all events except errors, with the span of each string.
+#[derive(Debug, Default)]
+struct Recorder {
+    position: usize,
+    previous_end: usize,
+    events: Vec<(String, Option<Range<usize>>)>,
+}
+
+impl Callback<Infallible> for Recorder {
+    fn handle_event(&mut self, event: CallbackEvent<'_>) -> Option<Infallible> {
+        match event {
+            CallbackEvent::String { value } => {
+                self.events.push((
+                    String::from_utf8_lossy(value).into_owned(),
+                    Some(self.previous_end..self.position),
+                ));
+            }
+            CallbackEvent::Error(_) => return None,
+            event => self.events.push((format!("{:?}", event), None)),
+        }
+        self.previous_end = self.position;
+        None
+    }
+
+    fn set_position(&mut self, offset: usize) {
+        self.position = offset;
+    }
+}
+
+fn events(input: &str, coalesce: bool) -> Vec<(String, Option<Range<usize>>)> {
+    let mut emitter = CallbackEmitter::new(Recorder::default());
+    emitter.naively_switch_states(true);
+    emitter.coalesce_strings(coalesce);
+    let mut tokenizer = Tokenizer::new_with_emitter(input, emitter);
+    for _ in tokenizer.by_ref() {}
+    tokenizer.emitter_mut().callback_mut().events.split_off(0)
+}
+
+#[test]
+fn character_reference() {
+    assert_eq!(
+        events("a&amp;b", false),
+        [
+            ("a".to_owned(), Some(0..1)),
+            ("&".to_owned(), Some(1..6)),
+            ("b".to_owned(), Some(6..7)),
+        ]
+    );
+    assert_eq!(events("a&amp;b", true), [("a&b".to_owned(), Some(0..7))]);
+}
+
+#[test]
+fn same_text() {
+    let input = "<title>a &lt; b</title>\r\n<p class=x>hello &amp world &#x41;</p>\
+                 <script>if (a < b) {}</script><!-- c --><p>&notit; &unknown;\0 x";
+
+    // joining consecutive strings gives the same events as coalescing them
+    let mut joined: Vec<(String, Option<Range<usize>>)> = Vec::new();
+    for (value, span) in events(input, false) {
+        match (joined.last_mut(), span) {
+            (Some((last_value, Some(last_span))), Some(span)) => {
+                assert_eq!(last_span.end, span.start, "{:?}", value);
+                last_value.push_str(&value);
+                last_span.end = span.end;
+            }
+            (_, span) => joined.push((value, span)),
+        }
+    }
+
+    let strings = |events: &[(String, Option<Range<usize>>)]| -> Vec<String> {
+        events
+            .iter()
+            .filter(|(_, span)| span.is_some())
+            .map(|(value, _)| value.clone())
+            .collect()
+    };
+    assert_eq!(strings(&joined), strings(&events(input, true)));
+
+    // the text between the tags, which only differs from the input where characters were
+    // replaced. The `\n` of `\r\n` is only skipped when the tokenizer reads on, after the
+    // string has been emitted.
+    let texts: Vec<&str> = joined
+        .iter()
+        .filter_map(|(_, span)| Some(&input[span.clone()?]))
+        .collect();
+    assert_eq!(
+        texts,
+        [
+            "a &lt; b",
+            "\r",
+            "hello &amp world &#x41;",
+            "if (a < b) {}",
+            "&notit; &unknown;\0 x",
+        ]
+    );
+}
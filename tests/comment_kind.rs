@@ -0,0 +1,76 @@
+use std::convert::Infallible;
+
+use html5gum::emitters::callback::{CallbackEmitter, CallbackEvent};
+use html5gum::{Comment, CommentKind, Token, Tokenizer};
+use pretty_assertions::assert_eq;
+
+fn comments(input: &str) -> Vec<(String, CommentKind)> {
+    let tokens: Vec<(String, CommentKind)> = Tokenizer::new(input)
+        .filter_map(|token| match token.unwrap() {
+            Token::Comment(Comment { value, kind }) => {
+                Some((String::from_utf8(value.0).unwrap(), kind))
+            }
+            _ => None,
+        })
+        .collect();
+
+    let mut events = Vec::new();
+    let emitter = CallbackEmitter::new(|event: CallbackEvent<'_>| -> Option<Infallible> {
+        if let CallbackEvent::Comment { value, kind } = event {
+            events.push((String::from_utf8(value.to_vec()).unwrap(), kind));
+        }
+        None
+    });
+    let Ok(()) = Tokenizer::new_with_emitter(input, emitter).finish();
+    assert_eq!(tokens, events);
+
+    tokens
+}
+
+fn comment(value: &str, kind: CommentKind) -> (String, CommentKind) {
+    (value.to_owned(), kind)
+}
+
+#[test]
+fn regular() {
+    assert_eq!(
+        comments("<!-- a --><!----><!--->"),
+        [
+            comment(" a ", CommentKind::Regular),
+            comment("", CommentKind::Regular),
+            comment("", CommentKind::Regular),
+        ]
+    );
+}
+
+#[test]
+fn bogus() {
+    assert_eq!(
+        comments(r#"<?xml version="1.0"?><!x></ x><!-x>"#),
+        [
+            comment(r#"?xml version="1.0"?"#, CommentKind::Bogus),
+            comment("x", CommentKind::Bogus),
+            comment(" x", CommentKind::Bogus),
+            comment("-x", CommentKind::Bogus),
+        ]
+    );
+}
+
+#[test]
+fn cdata() {
+    assert_eq!(
+        comments("<![CDATA[x]]>"),
+        [comment("[CDATA[x]]", CommentKind::Cdata)]
+    );
+}
+
+#[test]
+fn regular_after_bogus() {
+    assert_eq!(
+        comments("<?x><!--y-->"),
+        [
+            comment("?x", CommentKind::Bogus),
+            comment("y", CommentKind::Regular),
+        ]
+    );
+}
@@ -0,0 +1,144 @@
+use html5gum::conditional_comments::{ConditionalComment, ExpandConditionalComments};
+use html5gum::{Token, Tokenizer};
+use pretty_assertions::assert_eq;
+
+use ConditionalComment::{Hidden, RevealedEnd, RevealedStart};
+
+/// Every comment of `html`, parsed.
+fn comments(html: &str) -> Vec<Option<ConditionalComment<'static>>> {
+    Tokenizer::new(html)
+        .flatten()
+        .filter_map(|token| match token {
+            Token::Comment(comment) => Some(leak(comment.value.to_vec())),
+            _ => None,
+        })
+        .map(ConditionalComment::parse)
+        .collect()
+}
+
+fn leak(bytes: Vec<u8>) -> &'static [u8] {
+    Box::leak(bytes.into_boxed_slice())
+}
+
+fn hidden(condition: &'static str, content: &'static str) -> Option<ConditionalComment<'static>> {
+    Some(Hidden {
+        condition: condition.as_bytes(),
+        content: content.as_bytes(),
+    })
+}
+
+fn revealed(condition: &'static str) -> Option<ConditionalComment<'static>> {
+    Some(RevealedStart {
+        condition: condition.as_bytes(),
+    })
+}
+
+#[test]
+fn html5_boilerplate() {
+    let html = r#"<!DOCTYPE html>
+<!--[if lt IE 7]>      <html class="no-js lt-ie9 lt-ie8 lt-ie7"> <![endif]-->
+<!--[if IE 7]>         <html class="no-js lt-ie9 lt-ie8"> <![endif]-->
+<!--[if IE 8]>         <html class="no-js lt-ie9"> <![endif]-->
+<!--[if gt IE 8]><!--> <html class="no-js"> <!--<![endif]-->
+<head>
+<!--[if lt IE 9]>
+  <script src="js/vendor/html5shiv.js"></script>
+<![endif]-->
+</head>"#;
+    assert_eq!(
+        comments(html),
+        [
+            hidden(
+                "lt IE 7",
+                r#"      <html class="no-js lt-ie9 lt-ie8 lt-ie7"> "#
+            ),
+            hidden("IE 7", r#"         <html class="no-js lt-ie9 lt-ie8"> "#),
+            hidden("IE 8", r#"         <html class="no-js lt-ie9"> "#),
+            revealed("gt IE 8"),
+            Some(RevealedEnd),
+            hidden(
+                "lt IE 9",
+                "\n  <script src=\"js/vendor/html5shiv.js\"></script>\n"
+            ),
+        ]
+    );
+}
+
+#[test]
+fn syntax_variants() {
+    assert_eq!(
+        comments(
+            "<![if !IE]><p>not IE</p><![endif]>\
+             <!--[IF (gt IE 5)&(lt IE 7)]><![ENDIF]-->\
+             <!-- [ if  IE ] > x <![endif] -->\
+             <!--[if IE]><!--><!--<![ endif ]-->"
+        ),
+        [
+            revealed("!IE"),
+            Some(RevealedEnd),
+            hidden("(gt IE 5)&(lt IE 7)", ""),
+            hidden("IE", " x "),
+            revealed("IE"),
+            Some(RevealedEnd),
+        ]
+    );
+}
+
+#[test]
+fn not_conditional() {
+    assert_eq!(
+        comments(
+            "<!-- regular --><!--[if IE]> unterminated --><!--[iffy]><![endif]-->\
+             <!--[if IE]> x <![endif] y--><!--[endif--><!--[if IE>x<![endif]-->\
+             <![CDATA[if IE]]><!---->"
+        ),
+        [None, None, None, None, None, None, None, None]
+    );
+}
+
+#[test]
+fn expand() {
+    let html = "<p>a<!--[if IE 6]><b>old</b><![endif]--><!-- c -->\
+        <!--[if IE]><script>'<p>'</script><![endif]--><![if !IE]><i>new</i><![endif]>";
+    let tokens = Tokenizer::new(html)
+        .flatten()
+        .filter(|token| !matches!(token, Token::Error(_)));
+    let tokens: Vec<_> = ExpandConditionalComments::new(tokens)
+        .map(|token| {
+            let in_conditional_comment = token.in_conditional_comment();
+            let condition = token
+                .condition
+                .map(|c| String::from_utf8(c.to_vec()).unwrap());
+            assert_eq!(in_conditional_comment, condition.is_some());
+            let token = match token.token {
+                Token::StartTag(tag) => format!("<{}>", String::from_utf8_lossy(&tag.name)),
+                Token::EndTag(tag) => format!("</{}>", String::from_utf8_lossy(&tag.name)),
+                Token::String(s) => String::from_utf8_lossy(&s).into_owned(),
+                Token::Comment(c) => format!("<!--{}-->", String::from_utf8_lossy(&c.value)),
+                token => panic!("{:?}", token),
+            };
+            (token, condition)
+        })
+        .collect();
+
+    let ie = |c: &str| Some(c.to_owned());
+    assert_eq!(
+        tokens,
+        [
+            ("<p>".to_owned(), None),
+            ("a".to_owned(), None),
+            ("<b>".to_owned(), ie("IE 6")),
+            ("old".to_owned(), ie("IE 6")),
+            ("</b>".to_owned(), ie("IE 6")),
+            ("<!-- c -->".to_owned(), None),
+            ("<script>".to_owned(), ie("IE")),
+            ("'<p>'".to_owned(), ie("IE")),
+            ("</script>".to_owned(), ie("IE")),
+            ("<!--[if !IE]-->".to_owned(), None),
+            ("<i>".to_owned(), None),
+            ("new".to_owned(), None),
+            ("</i>".to_owned(), None),
+            ("<!--[endif]-->".to_owned(), None),
+        ]
+    );
+}
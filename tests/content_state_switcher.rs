@@ -0,0 +1,177 @@
+use html5gum::{
+    naive_next_state, Comment, CommentKind, ContentStateSwitcher, DefaultEmitter, EndTag, StartTag,
+    State, Token, Tokenizer,
+};
+use pretty_assertions::assert_eq;
+
+mod testutils;
+
+use testutils::string;
+
+fn tokenize(input: &str, scripting_enabled: bool) -> Vec<Token> {
+    let mut switcher = ContentStateSwitcher::default();
+    switcher.scripting_enabled(scripting_enabled);
+    let mut emitter = DefaultEmitter::default();
+    emitter.switch_states(Some(switcher));
+    emitter.suppress_errors(true);
+    Tokenizer::new_with_emitter(input, emitter)
+        .map(Result::unwrap)
+        .collect()
+}
+
+fn start_tag(name: &str) -> Token {
+    Token::StartTag(StartTag {
+        self_closing: false,
+        name: name.as_bytes().to_vec().into(),
+        attributes: Default::default(),
+    })
+}
+
+fn end_tag(name: &str) -> Token {
+    Token::EndTag(EndTag {
+        name: name.as_bytes().to_vec().into(),
+    })
+}
+
+#[test]
+fn svg_title() {
+    assert_eq!(
+        tokenize(
+            "<svg><title><rect></title></svg><title><rect></title>",
+            false
+        ),
+        [
+            start_tag("svg"),
+            start_tag("title"),
+            start_tag("rect"),
+            end_tag("title"),
+            end_tag("svg"),
+            start_tag("title"),
+            string("<rect>"),
+            end_tag("title"),
+        ]
+    );
+}
+
+#[test]
+fn integration_points() {
+    assert_eq!(
+        tokenize(
+            "<svg><style><g></style><foreignObject><style><g></style>",
+            false
+        ),
+        [
+            start_tag("svg"),
+            start_tag("style"),
+            start_tag("g"),
+            end_tag("style"),
+            start_tag("foreignobject"),
+            start_tag("style"),
+            string("<g>"),
+            end_tag("style"),
+        ]
+    );
+
+    // <p> leaves foreign content
+    assert_eq!(
+        tokenize("<math><mrow><p><textarea><b></textarea>", false),
+        [
+            start_tag("math"),
+            start_tag("mrow"),
+            start_tag("p"),
+            start_tag("textarea"),
+            string("<b>"),
+            end_tag("textarea"),
+        ]
+    );
+}
+
+#[test]
+fn cdata() {
+    assert_eq!(
+        tokenize("<svg><![CDATA[<b>]]></svg><![CDATA[<b>]]>", false),
+        [
+            start_tag("svg"),
+            string("<b>"),
+            end_tag("svg"),
+            Token::Comment(Comment {
+                value: b"[CDATA[<b".to_vec().into(),
+                kind: CommentKind::Cdata,
+            }),
+            string("]]>"),
+        ]
+    );
+}
+
+#[test]
+fn self_closing_script() {
+    // browsers ignore the self-closing flag on HTML elements
+    assert_eq!(
+        tokenize("<script/><b></script><svg><script/><b>", false),
+        [
+            Token::StartTag(StartTag {
+                self_closing: true,
+                name: b"script".to_vec().into(),
+                attributes: Default::default(),
+            }),
+            string("<b>"),
+            end_tag("script"),
+            start_tag("svg"),
+            Token::StartTag(StartTag {
+                self_closing: true,
+                name: b"script".to_vec().into(),
+                attributes: Default::default(),
+            }),
+            start_tag("b"),
+        ]
+    );
+}
+
+#[test]
+fn noscript() {
+    let input = "<noscript><p>hi</p></noscript>";
+    assert_eq!(
+        tokenize(input, false),
+        [
+            start_tag("noscript"),
+            start_tag("p"),
+            string("hi"),
+            end_tag("p"),
+            end_tag("noscript"),
+        ]
+    );
+    assert_eq!(
+        tokenize(input, true),
+        [
+            start_tag("noscript"),
+            string("<p>hi</p>"),
+            end_tag("noscript"),
+        ]
+    );
+}
+
+#[test]
+fn html_elements_like_naive_next_state() {
+    for name in [
+        "textarea",
+        "title",
+        "plaintext",
+        "script",
+        "style",
+        "iframe",
+        "xmp",
+        "noembed",
+        "noframes",
+        "div",
+    ] {
+        let mut switcher = ContentStateSwitcher::default();
+        assert_eq!(
+            switcher.start_tag(name.as_bytes(), false),
+            naive_next_state(name.as_bytes()),
+            "{}",
+            name
+        );
+    }
+
+    assert_eq!(naive_next_state(b"noframes"), Some(State::RawText));
+}
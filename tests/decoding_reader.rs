@@ -0,0 +1,120 @@
+use std::io::{self, Read};
+
+use encoding_rs::{Encoding, SHIFT_JIS, UTF_16LE, UTF_8, WINDOWS_1252};
+use html5gum::{DecodingReader, Token, Tokenizer};
+use pretty_assertions::assert_eq;
+
+/// The text of all `Token::String`s, which have to be valid UTF-8.
+fn text(reader: DecodingReader<impl Read>) -> Vec<String> {
+    Tokenizer::new(reader)
+        .map(|token| token.unwrap())
+        .filter_map(|token| match token {
+            Token::String(s) => Some(String::from_utf8(s.0).unwrap()),
+            _ => None,
+        })
+        .filter(|s| !s.trim().is_empty())
+        .collect()
+}
+
+fn encoding_of(input: &[u8]) -> &'static Encoding {
+    DecodingReader::new(input).unwrap().encoding()
+}
+
+#[test]
+fn windows_1252_fixture() {
+    let input = include_bytes!("fixtures/windows-1252.html");
+    let reader = DecodingReader::new(&input[..]).unwrap();
+    assert_eq!(reader.encoding(), WINDOWS_1252);
+    assert_eq!(text(reader), ["Café", "Crème brûlée € 5"]);
+}
+
+#[test]
+fn byte_order_mark() {
+    let input = b"\xef\xbb\xbf<meta charset=windows-1252><p>caf\xc3\xa9";
+    let reader = DecodingReader::new(&input[..]).unwrap();
+    assert_eq!(reader.encoding(), UTF_8);
+    assert_eq!(text(reader), ["café"]);
+
+    let input: Vec<u8> = b"\xff\xfe"
+        .iter()
+        .copied()
+        .chain("<p>café".encode_utf16().flat_map(u16::to_le_bytes))
+        .collect();
+    let reader = DecodingReader::new(&input[..]).unwrap();
+    assert_eq!(reader.encoding(), UTF_16LE);
+    assert_eq!(text(reader), ["café"]);
+}
+
+#[test]
+fn prescan() {
+    assert_eq!(encoding_of(b"<meta charset=shift_jis>"), SHIFT_JIS);
+    assert_eq!(encoding_of(b"<META CHARSET='Shift_JIS'>"), SHIFT_JIS);
+    assert_eq!(
+        encoding_of(b"<meta http-equiv=Content-Type content='text/html; charset=shift_jis'>"),
+        SHIFT_JIS
+    );
+    // the content attribute alone does not count
+    assert_eq!(
+        encoding_of(b"<meta content='text/html; charset=shift_jis'>"),
+        UTF_8
+    );
+    // neither do declarations in comments, other tags or attribute values
+    assert_eq!(
+        encoding_of(b"<!-- <meta charset=shift_jis> --><p title='<meta charset=shift_jis>'>"),
+        UTF_8
+    );
+    // UTF-16 can not be declared in a document that was read as ASCII
+    assert_eq!(encoding_of(b"<meta charset=utf-16le>"), UTF_8);
+    assert_eq!(encoding_of(b"<meta charset=x-user-defined>"), WINDOWS_1252);
+    // an unfinished tag at the end of the prescan is ignored
+    assert_eq!(encoding_of(b"<meta charset=shift_jis"), UTF_8);
+}
+
+#[test]
+fn fallback() {
+    assert_eq!(encoding_of("<p>café".as_bytes()), UTF_8);
+    assert_eq!(encoding_of(b"<p>caf\xe9"), WINDOWS_1252);
+
+    let reader = DecodingReader::new_with_fallback("<p>café".as_bytes(), SHIFT_JIS).unwrap();
+    assert_eq!(reader.encoding(), SHIFT_JIS);
+}
+
+#[test]
+fn late_declaration() {
+    // after 1024 bytes, the encoding is already fixed
+    let mut input = "<p>".to_owned();
+    input.push_str(&"x".repeat(1024));
+    input.push_str("<meta charset=windows-1252><p>café");
+    let reader = DecodingReader::new(input.as_bytes()).unwrap();
+    assert_eq!(reader.encoding(), UTF_8);
+    assert_eq!(text(reader).last().unwrap(), "café");
+}
+
+/// Returns the data one byte at a time, so that multi-byte characters are split between reads.
+struct ByteByByte<'a>(&'a [u8]);
+
+impl Read for ByteByByte<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.0.split_first() {
+            Some((&x, rest)) if !buf.is_empty() => {
+                buf[0] = x;
+                self.0 = rest;
+                Ok(1)
+            }
+            _ => Ok(0),
+        }
+    }
+}
+
+#[test]
+fn split_characters() {
+    let mut input = "<meta charset=shift_jis>".as_bytes().to_vec();
+    let long_text = "日本語のテキスト".repeat(1000);
+    input.extend_from_slice(&SHIFT_JIS.encode(&long_text).0);
+    // an incomplete character at the end of the input
+    input.push(0x93);
+
+    let reader = DecodingReader::new(ByteByByte(&input)).unwrap();
+    assert_eq!(reader.encoding(), SHIFT_JIS);
+    assert_eq!(text(reader), [format!("{}\u{fffd}", long_text)]);
+}
@@ -0,0 +1,170 @@
+//! Doctype identifiers are `None` when they are missing, and `Some` as soon as the tokenizer
+//! reaches their opening quote, even if they are empty or never closed.
+use std::convert::Infallible;
+
+use html5gum::emitters::buffered::{BufferedEmitter, TokenRef};
+use html5gum::emitters::callback::{CallbackEmitter, CallbackEvent};
+use html5gum::{Doctype, Error, Token, Tokenizer};
+use pretty_assertions::assert_eq;
+
+type Identifiers = (Option<&'static str>, Option<&'static str>);
+
+/// Input, identifiers, force-quirks flag and errors.
+const CASES: &[(&str, Identifiers, bool, &[Error])] = &[
+    ("<!DOCTYPE html>", (None, None), false, &[]),
+    ("<!DOCTYPE html PUBLIC \"\">", (Some(""), None), false, &[]),
+    ("<!DOCTYPE html SYSTEM ''>", (None, Some("")), false, &[]),
+    (
+        "<!DOCTYPE html PUBLIC \"\" ''>",
+        (Some(""), Some("")),
+        false,
+        &[],
+    ),
+    (
+        "<!DOCTYPE html PUBLIC \"\"''>",
+        (Some(""), Some("")),
+        false,
+        &[Error::MissingWhitespaceBetweenDoctypePublicAndSystemIdentifiers],
+    ),
+    (
+        "<!doctype html public>",
+        (None, None),
+        true,
+        &[Error::MissingDoctypePublicIdentifier],
+    ),
+    (
+        "<!doctype html system>",
+        (None, None),
+        true,
+        &[Error::MissingDoctypeSystemIdentifier],
+    ),
+    (
+        "<!doctype html public x>",
+        (None, None),
+        true,
+        &[Error::MissingQuoteBeforeDoctypePublicIdentifier],
+    ),
+    (
+        "<!doctype html public \"\" x>",
+        (Some(""), None),
+        true,
+        &[Error::MissingQuoteBeforeDoctypeSystemIdentifier],
+    ),
+    (
+        "<!DOCTYPE html PUBLIC \"",
+        (Some(""), None),
+        true,
+        &[Error::EofInDoctype],
+    ),
+    (
+        "<!DOCTYPE html PUBLIC \">",
+        (Some(""), None),
+        true,
+        &[Error::AbruptDoctypePublicIdentifier],
+    ),
+    (
+        "<!DOCTYPE html SYSTEM '>",
+        (None, Some("")),
+        true,
+        &[Error::AbruptDoctypeSystemIdentifier],
+    ),
+    (
+        "<!DOCTYPE html PUBLIC \"a\" \"",
+        (Some("a"), Some("")),
+        true,
+        &[Error::EofInDoctype],
+    ),
+    ("<!DOCTYPE html", (None, None), true, &[Error::EofInDoctype]),
+];
+
+fn expected_doctype(identifiers: Identifiers, force_quirks: bool) -> Doctype {
+    let (public_identifier, system_identifier) = identifiers;
+    Doctype {
+        force_quirks,
+        name: b"html".to_vec().into(),
+        public_identifier: public_identifier.map(|x| x.as_bytes().to_vec().into()),
+        system_identifier: system_identifier.map(|x| x.as_bytes().to_vec().into()),
+    }
+}
+
+#[test]
+fn default_emitter() {
+    for &(input, identifiers, force_quirks, errors) in CASES {
+        let tokens: Vec<_> = Tokenizer::new(input).flatten().collect();
+        let expected: Vec<_> = errors
+            .iter()
+            .map(|&error| Token::Error(error))
+            .chain([Token::Doctype(expected_doctype(identifiers, force_quirks))])
+            .collect();
+        assert_eq!(tokens, expected, "{:?}", input);
+    }
+}
+
+#[test]
+fn buffered_emitter() {
+    for &(input, identifiers, force_quirks, _) in CASES {
+        let mut tokenizer = Tokenizer::new_with_emitter(input, BufferedEmitter::default());
+        let mut doctypes = Vec::new();
+        while let Some(token) = tokenizer.next_token() {
+            if let Ok(TokenRef::Doctype(doctype)) = token {
+                doctypes.push(Doctype {
+                    force_quirks: doctype.force_quirks,
+                    name: doctype.name.to_vec().into(),
+                    public_identifier: doctype.public_identifier.map(|x| x.to_vec().into()),
+                    system_identifier: doctype.system_identifier.map(|x| x.to_vec().into()),
+                });
+            }
+        }
+        assert_eq!(
+            doctypes,
+            [expected_doctype(identifiers, force_quirks)],
+            "{:?}",
+            input
+        );
+    }
+}
+
+#[test]
+fn callback_emitter() {
+    for &(input, identifiers, force_quirks, _) in CASES {
+        let mut doctypes = Vec::new();
+        let emitter = CallbackEmitter::new(|event: CallbackEvent<'_>| -> Option<Infallible> {
+            if let CallbackEvent::Doctype {
+                name,
+                public_identifier,
+                system_identifier,
+                force_quirks,
+            } = event
+            {
+                doctypes.push(Doctype {
+                    force_quirks,
+                    name: name.to_vec().into(),
+                    public_identifier: public_identifier.map(|x| x.to_vec().into()),
+                    system_identifier: system_identifier.map(|x| x.to_vec().into()),
+                });
+            }
+            None
+        });
+        let Ok(()) = Tokenizer::new_with_emitter(input, emitter).finish();
+        assert_eq!(
+            doctypes,
+            [expected_doctype(identifiers, force_quirks)],
+            "{:?}",
+            input
+        );
+    }
+}
+
+#[test]
+fn identifiers_are_reset() {
+    let tokens: Vec<_> = Tokenizer::new("<!DOCTYPE html PUBLIC \"a\" \"b\"><!DOCTYPE html>")
+        .flatten()
+        .collect();
+    assert_eq!(
+        tokens,
+        [
+            Token::Doctype(expected_doctype((Some("a"), Some("b")), false)),
+            Token::Doctype(expected_doctype((None, None), false)),
+        ]
+    );
+}
@@ -0,0 +1,211 @@
+use html5gum::elements_iter::{ElementIter, FlatElement};
+use html5gum::{IoReader, Readable};
+use pretty_assertions::assert_eq;
+
+const MESSY: &str = r#"<!DOCTYPE html>
+<title>Not a <h1> heading</title>
+<H1 class="main">Main &amp; only</h1>
+<p>intro <script>var x = "<h2>not a heading</h2>";</script>
+<ul>
+  <li>first
+  <li>second <b>bold
+  <li><h2 id=nested>Nested heading</h2>
+</ul>
+<div><h3>Unclosed <!-- comment --> heading
+</div>
+<h4>Misnested <i>heading</h4></i>
+<h5>Stray end tags </em></h5>
+<h6>Last heading"#;
+
+fn collect(input: &str, tags: &[&str], include_nested_text: bool) -> Vec<FlatElement> {
+    let mut iter = ElementIter::new(input, tags);
+    iter.include_nested_text(include_nested_text);
+    let elements: Vec<_> = iter.map(Result::unwrap).collect();
+
+    // the span does not depend on the reader
+    let mut iter = ElementIter::new(IoReader::new(input.as_bytes()).to_reader(), tags);
+    iter.include_nested_text(include_nested_text);
+    assert_eq!(iter.map(Result::unwrap).collect::<Vec<_>>(), elements);
+
+    elements
+}
+
+fn texts(elements: &[FlatElement]) -> Vec<(&str, &str)> {
+    elements
+        .iter()
+        .map(|element| {
+            (
+                std::str::from_utf8(&element.name).unwrap(),
+                std::str::from_utf8(&element.text).unwrap(),
+            )
+        })
+        .collect()
+}
+
+fn source<'a>(input: &'a str, element: &FlatElement) -> &'a str {
+    &input[element.span.clone()]
+}
+
+#[test]
+fn headings() {
+    let elements = collect(MESSY, &["h1", "h2", "h3", "h4", "h5", "h6"], false);
+    assert_eq!(
+        texts(&elements),
+        [
+            ("h1", "Main & only"),
+            ("h2", "Nested heading"),
+            ("h3", "Unclosed  heading\n"),
+            ("h4", "Misnested heading"),
+            ("h5", "Stray end tags "),
+            ("h6", "Last heading"),
+        ]
+    );
+
+    assert_eq!(elements[0].attributes[b"class".as_slice()], b"main");
+    assert_eq!(elements[1].attributes[b"id".as_slice()], b"nested");
+
+    let sources: Vec<_> = elements.iter().map(|e| source(MESSY, e)).collect();
+    assert_eq!(
+        sources,
+        [
+            r#"<H1 class="main">Main &amp; only</h1>"#,
+            "<h2 id=nested>Nested heading</h2>",
+            "<h3>Unclosed <!-- comment --> heading\n",
+            "<h4>Misnested <i>heading</h4>",
+            "<h5>Stray end tags </em></h5>",
+            "<h6>Last heading",
+        ]
+    );
+}
+
+#[test]
+fn unclosed_list_items() {
+    let elements = collect(MESSY, &["li"], false);
+    assert_eq!(
+        texts(&elements),
+        [
+            ("li", "first\n  "),
+            ("li", "second bold\n  "),
+            ("li", "Nested heading\n"),
+        ]
+    );
+
+    let sources: Vec<_> = elements.iter().map(|e| source(MESSY, e)).collect();
+    assert_eq!(
+        sources,
+        [
+            "<li>first\n  ",
+            "<li>second <b>bold\n  <li><h2 id=nested>Nested heading</h2>\n",
+            "<li><h2 id=nested>Nested heading</h2>\n",
+        ]
+    );
+}
+
+const TABLES: &str = "<table>\
+<tr><td>a<td>b<table><tr><td>c<td>d</table>e</td><td>f\
+</table>";
+
+#[test]
+fn nested_tables() {
+    let elements = collect(TABLES, &["td"], false);
+    assert_eq!(
+        texts(&elements),
+        [
+            ("td", "a"),
+            ("td", "be"),
+            ("td", "c"),
+            ("td", "d"),
+            ("td", "f")
+        ]
+    );
+
+    let elements = collect(TABLES, &["td"], true);
+    assert_eq!(
+        texts(&elements),
+        [
+            ("td", "a"),
+            ("td", "bcde"),
+            ("td", "c"),
+            ("td", "d"),
+            ("td", "f")
+        ]
+    );
+
+    let sources: Vec<_> = elements.iter().map(|e| source(TABLES, e)).collect();
+    assert_eq!(
+        sources,
+        [
+            "<td>a",
+            "<td>b<table><tr><td>c<td>d</table>e</td>",
+            "<td>c",
+            "<td>d",
+            "<td>f",
+        ]
+    );
+
+    // the outer table is only yielded after all cells in it
+    let elements = collect(TABLES, &["table", "td"], true);
+    assert_eq!(
+        texts(&elements)[..3],
+        [("table", "abcdef"), ("td", "a"), ("td", "bcde")]
+    );
+    assert_eq!(source(TABLES, &elements[0]), TABLES);
+}
+
+#[test]
+fn spans() {
+    let input = "<p>a &lt;\r\nb</p>\0<img src=x.png><P\n>\u{e4}\r<Br/></p >";
+    let elements = collect(input, &["P", "img", "br"], false);
+
+    let sources: Vec<_> = elements.iter().map(|e| source(input, e)).collect();
+    assert_eq!(
+        sources,
+        [
+            "<p>a &lt;\r\nb</p>",
+            "<img src=x.png>",
+            "<P\n>\u{e4}\r<Br/></p >",
+            "<Br/>"
+        ]
+    );
+    assert_eq!(elements[0].text, b"a <\nb");
+    assert_eq!(elements[2].text.as_slice(), "\u{e4}\n".as_bytes());
+}
+
+#[test]
+fn links_in_page() {
+    let page = include_str!("fixtures/links.html");
+    let links: Vec<_> = collect(page, &["a"], false)
+        .into_iter()
+        .map(|element| {
+            (
+                String::from_utf8(element.attributes[b"href".as_slice()].to_vec()).unwrap(),
+                String::from_utf8(element.text.to_vec()).unwrap(),
+            )
+        })
+        .collect();
+
+    assert_eq!(
+        links,
+        [
+            ("/", "Home"),
+            ("/docs/", "Docs"),
+            ("/blog/?page=2&sort=new", "Blog \u{bb}"),
+            ("https://example.com/changelog", "full changelog"),
+            ("/feed.xml", ""),
+            ("#top", "Top"),
+            ("/outer", "outer "),
+            ("/inner", "inner"),
+            ("/about", "About us\n"),
+        ]
+        .map(|(href, text)| (href.to_owned(), text.to_owned()))
+    );
+}
+
+#[test]
+fn script_and_style_text() {
+    let input = "<p>a<script>b</script><style>c</style>d</p><script>e</script>";
+    assert_eq!(
+        texts(&collect(input, &["p", "script"], true)),
+        [("p", "ad"), ("script", "b"), ("script", "e")]
+    );
+}
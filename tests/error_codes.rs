@@ -0,0 +1,272 @@
+use html5gum::{Error, ErrorCategory, State, Token, Tokenizer};
+use pretty_assertions::assert_eq;
+
+fn errors(input: &str) -> Vec<Error> {
+    errors_in_state(input.as_bytes(), State::Data)
+}
+
+fn errors_in_state(input: &[u8], state: State) -> Vec<Error> {
+    let mut tokenizer = Tokenizer::new(input);
+    tokenizer.set_state(state);
+    tokenizer
+        .filter_map(|token| match token.unwrap() {
+            Token::Error(error) => Some(error),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn codes() {
+    assert_eq!(
+        errors("<a x x>\0</a x>"),
+        [
+            Error::DuplicateAttribute,
+            Error::UnexpectedNullCharacter,
+            Error::EndTagWithAttributes,
+        ]
+    );
+
+    assert_eq!(Error::AbruptClosingOfEmptyComment.code(), 0);
+    assert_eq!(Error::DuplicateAttribute.code(), 46);
+    assert_ne!(
+        Error::LimitExceeded.code(),
+        Error::SkippedBinaryGarbage.code()
+    );
+}
+
+/// An input for every error of the spec that triggers it.
+const SPEC_ERRORS: &[(Error, State, &[u8])] = &[
+    (Error::AbruptClosingOfEmptyComment, State::Data, b"<!-->"),
+    (
+        Error::AbruptDoctypePublicIdentifier,
+        State::Data,
+        b"<!DOCTYPE html PUBLIC 'x>",
+    ),
+    (
+        Error::AbruptDoctypeSystemIdentifier,
+        State::Data,
+        b"<!DOCTYPE html SYSTEM 'x>",
+    ),
+    (
+        Error::AbsenceOfDigitsInNumericCharacterReference,
+        State::Data,
+        b"&#x;",
+    ),
+    (Error::CdataInHtmlContent, State::Data, b"<![CDATA[x]]>"),
+    (
+        Error::CharacterReferenceOutsideUnicodeRange,
+        State::Data,
+        b"&#x110000;",
+    ),
+    (Error::ControlCharacterReference, State::Data, b"&#x1;"),
+    (Error::EndTagWithAttributes, State::Data, b"</a x>"),
+    (Error::EndTagWithTrailingSolidus, State::Data, b"</a/>"),
+    (Error::EofBeforeTagName, State::Data, b"<"),
+    (Error::EofInCdata, State::CdataSection, b"x"),
+    (Error::EofInComment, State::Data, b"<!--"),
+    (Error::EofInDoctype, State::Data, b"<!DOCTYPE"),
+    (
+        Error::EofInScriptHtmlCommentLikeText,
+        State::ScriptData,
+        b"<!--",
+    ),
+    (Error::EofInTag, State::Data, b"<a"),
+    (Error::IncorrectlyClosedComment, State::Data, b"<!--x--!>"),
+    (Error::IncorrectlyOpenedComment, State::Data, b"<!x>"),
+    (
+        Error::InvalidCharacterSequenceAfterDoctypeName,
+        State::Data,
+        b"<!DOCTYPE html x>",
+    ),
+    (Error::InvalidFirstCharacterOfTagName, State::Data, b"<1"),
+    (Error::MissingAttributeValue, State::Data, b"<a x=>"),
+    (Error::MissingDoctypeName, State::Data, b"<!DOCTYPE>"),
+    (
+        Error::MissingDoctypePublicIdentifier,
+        State::Data,
+        b"<!DOCTYPE html PUBLIC>",
+    ),
+    (
+        Error::MissingDoctypeSystemIdentifier,
+        State::Data,
+        b"<!DOCTYPE html SYSTEM>",
+    ),
+    (Error::MissingEndTagName, State::Data, b"</>"),
+    (
+        Error::MissingQuoteBeforeDoctypePublicIdentifier,
+        State::Data,
+        b"<!DOCTYPE html PUBLIC x>",
+    ),
+    (
+        Error::MissingQuoteBeforeDoctypeSystemIdentifier,
+        State::Data,
+        b"<!DOCTYPE html SYSTEM x>",
+    ),
+    (
+        Error::MissingSemicolonAfterCharacterReference,
+        State::Data,
+        b"&amp",
+    ),
+    (
+        Error::MissingWhitespaceAfterDoctypePublicKeyword,
+        State::Data,
+        b"<!DOCTYPE html PUBLIC'x'>",
+    ),
+    (
+        Error::MissingWhitespaceAfterDoctypeSystemKeyword,
+        State::Data,
+        b"<!DOCTYPE html SYSTEM'x'>",
+    ),
+    (
+        Error::MissingWhitespaceBeforeDoctypeName,
+        State::Data,
+        b"<!DOCTYPEhtml>",
+    ),
+    (
+        Error::MissingWhitespaceBetweenAttributes,
+        State::Data,
+        b"<a x='y'z>",
+    ),
+    (
+        Error::MissingWhitespaceBetweenDoctypePublicAndSystemIdentifiers,
+        State::Data,
+        b"<!DOCTYPE html PUBLIC 'x''y'>",
+    ),
+    (Error::NestedComment, State::Data, b"<!--<!--x-->"),
+    (
+        Error::NoncharacterCharacterReference,
+        State::Data,
+        b"&#xffff;",
+    ),
+    (
+        Error::NoncharacterInInputStream,
+        State::Data,
+        b"\xef\xbf\xbf",
+    ),
+    (Error::NullCharacterReference, State::Data, b"&#0;"),
+    (Error::SurrogateCharacterReference, State::Data, b"&#xd800;"),
+    (Error::SurrogateInInputStream, State::Data, b"\xed\xa0\x80"),
+    (
+        Error::UnexpectedCharacterAfterDoctypeSystemIdentifier,
+        State::Data,
+        b"<!DOCTYPE html SYSTEM 'x' y>",
+    ),
+    (
+        Error::UnexpectedCharacterInAttributeName,
+        State::Data,
+        b"<a x'=y>",
+    ),
+    (
+        Error::UnexpectedCharacterInUnquotedAttributeValue,
+        State::Data,
+        b"<a x=y'>",
+    ),
+    (
+        Error::UnexpectedEqualsSignBeforeAttributeName,
+        State::Data,
+        b"<a =x>",
+    ),
+    (Error::UnexpectedNullCharacter, State::Data, b"<a\0>"),
+    (
+        Error::UnexpectedQuestionMarkInsteadOfTagName,
+        State::Data,
+        b"<?xml?>",
+    ),
+    (Error::UnexpectedSolidusInTag, State::Data, b"<a / x>"),
+    (Error::UnknownNamedCharacterReference, State::Data, b"&xyz;"),
+    (Error::DuplicateAttribute, State::Data, b"<a x x>"),
+    (Error::ControlCharacterInInputStream, State::Data, b"\x01"),
+];
+
+#[test]
+fn every_spec_error_is_emitted() {
+    for &(error, state, input) in SPEC_ERRORS {
+        assert_eq!(
+            errors_in_state(input, state),
+            [error],
+            "{:?}",
+            String::from_utf8_lossy(input)
+        );
+    }
+
+    let covered: Vec<Error> = SPEC_ERRORS.iter().map(|&(error, _, _)| error).collect();
+    for error in Error::ALL {
+        assert_eq!(
+            covered.contains(error),
+            !matches!(
+                error,
+                Error::SkippedBinaryGarbage
+                    | Error::LimitExceeded
+                    | Error::InvalidUtf8InInputStream
+            ),
+            "{:?}",
+            error
+        );
+    }
+}
+
+#[test]
+fn surrogates_in_input_stream() {
+    // U+D7FF and U+E000 are right next to the surrogates
+    assert_eq!(errors("\u{d7ff}\u{e000}"), []);
+    assert_eq!(
+        errors_in_state(b"a\xed\xbf\xbfb<p \xed\xa0\x80>", State::Data),
+        [Error::SurrogateInInputStream, Error::SurrogateInInputStream]
+    );
+}
+
+#[test]
+fn categories() {
+    assert_eq!(Error::EofInTag.category(), ErrorCategory::Tag);
+    assert_eq!(Error::DuplicateAttribute.category(), ErrorCategory::Tag);
+    assert_eq!(Error::NestedComment.category(), ErrorCategory::Comment);
+    assert_eq!(Error::MissingDoctypeName.category(), ErrorCategory::Doctype);
+    assert_eq!(
+        Error::UnknownNamedCharacterReference.category(),
+        ErrorCategory::CharacterReference
+    );
+    assert_eq!(
+        Error::ControlCharacterInInputStream.category(),
+        ErrorCategory::InputStream
+    );
+    assert_eq!(Error::LimitExceeded.category(), ErrorCategory::Other);
+}
+
+#[test]
+#[cfg(feature = "error-strings")]
+fn strings() {
+    assert_eq!(Error::DuplicateAttribute.to_string(), "duplicate-attribute");
+    assert_eq!("eof-in-tag".parse(), Ok(Error::EofInTag));
+    assert_eq!("EOF-IN-TAG".parse::<Error>(), Err(()));
+
+    for &error in Error::ALL {
+        let s = error.to_string();
+        assert_eq!(s, error.as_str());
+        assert_eq!(s.parse(), Ok(error));
+        assert!(
+            s.bytes()
+                .all(|x| x.is_ascii_lowercase() || x.is_ascii_digit() || x == b'-'),
+            "{}",
+            s
+        );
+    }
+}
+
+#[test]
+#[cfg(not(feature = "error-strings"))]
+fn no_strings() {
+    use std::fmt::Display;
+    use std::str::FromStr;
+
+    // Referring to `some_item` is ambiguous if both impls apply, so this only compiles if `Error`
+    // implements neither `Display` nor `FromStr`.
+    trait AmbiguousIfImpl<A> {
+        fn some_item() {}
+    }
+    impl<T> AmbiguousIfImpl<()> for T {}
+    impl<T: Display> AmbiguousIfImpl<u8> for T {}
+    impl<T: FromStr> AmbiguousIfImpl<u16> for T {}
+
+    <Error as AmbiguousIfImpl<_>>::some_item();
+}
@@ -0,0 +1,165 @@
+use std::convert::Infallible;
+
+use html5gum::emitters::callback::{Callback, CallbackEmitter, CallbackEvent, ErrorContext};
+use html5gum::{DefaultEmitter, EndTag, Error, ErrorDetails, Token, Tokenizer};
+use pretty_assertions::assert_eq;
+
+fn details(input: &str) -> Vec<ErrorDetails> {
+    let mut emitter = DefaultEmitter::default();
+    emitter.collect_error_details(true);
+    emitter.suppress_errors(true);
+    let mut tokenizer = Tokenizer::new_with_emitter(input, emitter);
+    assert!(!tokenizer
+        .by_ref()
+        .flatten()
+        .any(|token| matches!(token, Token::Error(_))));
+    tokenizer.emitter_mut().take_error_details()
+}
+
+fn end_tag_error(error: Error, tag_name: &str, attribute_names: &[&str]) -> ErrorDetails {
+    ErrorDetails {
+        error,
+        tag_name: tag_name.as_bytes().to_vec().into(),
+        attribute_names: attribute_names
+            .iter()
+            .map(|name| name.as_bytes().to_vec().into())
+            .collect(),
+    }
+}
+
+#[test]
+fn end_tag_with_attributes() {
+    assert_eq!(
+        details("<p></p class=x>"),
+        [end_tag_error(Error::EndTagWithAttributes, "p", &["class"])]
+    );
+    assert_eq!(
+        details("</DIV ID=a Class ID=b>"),
+        [end_tag_error(
+            Error::EndTagWithAttributes,
+            "div",
+            &["id", "class"]
+        )]
+    );
+}
+
+#[test]
+fn end_tag_with_trailing_solidus() {
+    assert_eq!(
+        details("<br></br/>"),
+        [end_tag_error(Error::EndTagWithTrailingSolidus, "br", &[])]
+    );
+    assert_eq!(
+        details("</a b/>"),
+        [
+            end_tag_error(Error::EndTagWithTrailingSolidus, "a", &["b"]),
+            end_tag_error(Error::EndTagWithAttributes, "a", &["b"]),
+        ]
+    );
+}
+
+/// The text before an end tag is emitted before the errors about the end tag, also when the
+/// tokenizer has to look at the end tag's name to tell whether it ends RCDATA, RAWTEXT or script
+/// data.
+#[test]
+fn end_tag_errors_after_text() {
+    let tokenize = |input: &str| -> Vec<Token> {
+        let mut emitter = DefaultEmitter::default();
+        emitter.naively_switch_states(true);
+        Tokenizer::new_with_emitter(input, emitter)
+            .map(Result::unwrap)
+            .collect()
+    };
+    let string = |s: &str| Token::String(s.as_bytes().to_vec().into());
+    let end_tag = |name: &str| {
+        Token::EndTag(EndTag {
+            name: name.as_bytes().to_vec().into(),
+        })
+    };
+
+    for (input, name) in [
+        ("<title>x</title/>", "title"),
+        ("<style>x</style/>", "style"),
+        ("<script>x</script/>", "script"),
+        ("<p>x</p/>", "p"),
+    ] {
+        assert_eq!(
+            tokenize(input)[1..],
+            [
+                string("x"),
+                Token::Error(Error::EndTagWithTrailingSolidus),
+                end_tag(name)
+            ],
+            "{}",
+            input
+        );
+        let input = input.replace("/>", " a>");
+        assert_eq!(
+            tokenize(&input)[1..],
+            [
+                string("x"),
+                Token::Error(Error::EndTagWithAttributes),
+                end_tag(name)
+            ],
+            "{}",
+            input
+        );
+    }
+
+    assert_eq!(tokenize("<title>x</titl/>y")[1..], [string("x</titl/>y")]);
+}
+
+#[test]
+fn other_errors_are_not_recorded() {
+    assert_eq!(details("<a b b>\0</>"), []);
+
+    let mut emitter = DefaultEmitter::default();
+    emitter.collect_error_details(true);
+    let mut tokenizer = Tokenizer::new_with_emitter("</p a></p b>", emitter);
+    assert_eq!(
+        tokenizer.next().unwrap().unwrap(),
+        Token::Error(Error::EndTagWithAttributes)
+    );
+    assert_eq!(tokenizer.emitter_mut().take_error_details().len(), 1);
+    tokenizer.by_ref().for_each(drop);
+    assert_eq!(
+        tokenizer.emitter_mut().take_error_details(),
+        [end_tag_error(Error::EndTagWithAttributes, "p", &["b"])]
+    );
+}
+
+#[test]
+fn callback() {
+    #[derive(Default)]
+    struct Errors(Vec<String>);
+
+    impl Callback<Infallible> for Errors {
+        fn handle_event(&mut self, event: CallbackEvent<'_>) -> Option<Infallible> {
+            if let CallbackEvent::Error(error) = event {
+                self.0.push(format!("{:?}", error));
+            }
+            None
+        }
+
+        fn set_error_context(&mut self, context: ErrorContext<'_>) {
+            let names: Vec<_> = context
+                .attribute_names()
+                .map(String::from_utf8_lossy)
+                .collect();
+            self.0.push(format!(
+                "</{} {}>",
+                String::from_utf8_lossy(context.tag_name),
+                names.join(" ")
+            ));
+        }
+    }
+
+    let mut emitter = CallbackEmitter::new(Errors::default());
+    emitter.preserve_case(true);
+    let mut tokenizer = Tokenizer::new_with_emitter("</Td Align=left>", emitter);
+    tokenizer.by_ref().for_each(drop);
+    assert_eq!(
+        tokenizer.emitter().callback().0,
+        ["</Td align>", "EndTagWithAttributes"]
+    );
+}
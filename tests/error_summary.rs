@@ -0,0 +1,115 @@
+use html5gum::{DefaultEmitter, Error, ErrorSummary, Token, Tokenizer};
+use pretty_assertions::assert_eq;
+
+const FIXTURE: &str = "<!DOCTYPE html>\n\
+<p a=1 a=2 b=3 b=4>\0&unknown;&#0;\0\0</p x>\n\
+<!-- <!-- nested --!>\n\
+<div class=\"x\"id=y></div/>\n\
+<img src=a.png alt=\"\">&amp\n\
+</a";
+
+fn tokenize(
+    collect_error_summary: bool,
+    suppress_errors: bool,
+) -> (Vec<Token>, Option<ErrorSummary>) {
+    let mut emitter = DefaultEmitter::default();
+    emitter.collect_error_summary(collect_error_summary);
+    emitter.suppress_errors(suppress_errors);
+    let mut tokenizer = Tokenizer::new_with_emitter(FIXTURE, emitter);
+    let tokens = tokenizer.by_ref().map(Result::unwrap).collect();
+    (tokens, tokenizer.emitter().error_summary().cloned())
+}
+
+fn errors(tokens: &[Token]) -> Vec<Error> {
+    tokens
+        .iter()
+        .filter_map(|token| match token {
+            Token::Error(error) => Some(*error),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn exact_counts() {
+    let (tokens, summary) = tokenize(true, false);
+    let summary = summary.unwrap();
+
+    let expected = [
+        (Error::DuplicateAttribute, 2),
+        (Error::UnexpectedNullCharacter, 3),
+        (Error::UnknownNamedCharacterReference, 1),
+        (Error::NullCharacterReference, 1),
+        (Error::EndTagWithAttributes, 1),
+        (Error::NestedComment, 1),
+        (Error::IncorrectlyClosedComment, 1),
+        (Error::MissingWhitespaceBetweenAttributes, 1),
+        (Error::EndTagWithTrailingSolidus, 1),
+        (Error::MissingSemicolonAfterCharacterReference, 1),
+        (Error::EofInTag, 1),
+    ];
+    assert_eq!(summary.iter().collect::<Vec<_>>(), expected);
+    assert_eq!(summary.total(), 14);
+    assert_eq!(summary.first_error(), Some(Error::DuplicateAttribute));
+    assert_eq!(summary.count(Error::EofInTag), 1);
+    assert_eq!(summary.count(Error::EofInComment), 0);
+    assert_eq!(summary.first_occurrence(Error::DuplicateAttribute), Some(0));
+    assert_eq!(
+        summary.first_occurrence(Error::UnexpectedNullCharacter),
+        Some(2)
+    );
+    assert_eq!(summary.first_occurrence(Error::EofInTag), Some(13));
+    assert_eq!(summary.first_occurrence(Error::EofInComment), None);
+
+    // the summary agrees with the error tokens
+    let errors = errors(&tokens);
+    assert_eq!(errors.len(), summary.total());
+    for (error, count) in summary.iter() {
+        assert_eq!(errors.iter().filter(|&&e| e == error).count(), count);
+    }
+
+    assert_eq!(
+        summary.to_string(),
+        "14 errors
+  2 duplicate-attribute
+  3 unexpected-null-character
+  1 unknown-named-character-reference
+  1 null-character-reference
+  1 end-tag-with-attributes
+  1 nested-comment
+  1 incorrectly-closed-comment
+  1 missing-whitespace-between-attributes
+  1 end-tag-with-trailing-solidus
+  1 missing-semicolon-after-character-reference
+  1 eof-in-tag"
+    );
+}
+
+#[test]
+fn suppressed_errors() {
+    let (tokens, summary) = tokenize(true, false);
+    let (suppressed_tokens, suppressed_summary) = tokenize(true, true);
+
+    assert_eq!(errors(&suppressed_tokens), []);
+    assert_eq!(
+        suppressed_tokens,
+        tokens
+            .into_iter()
+            .filter(|token| !matches!(token, Token::Error(_)))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(suppressed_summary, summary);
+}
+
+#[test]
+fn disabled() {
+    let (tokens, summary) = tokenize(false, false);
+    assert_eq!(summary, None);
+    assert_eq!(errors(&tokens).len(), 14);
+
+    let (tokens, summary) = tokenize(false, true);
+    assert_eq!(summary, None);
+    assert_eq!(errors(&tokens), []);
+
+    assert_eq!(ErrorSummary::default().to_string(), "no errors");
+}
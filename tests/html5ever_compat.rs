@@ -0,0 +1,236 @@
+//! html5gum's tokenizer together with html5ever's tree builder has to build the same DOM as
+//! html5ever's own tokenizer.
+use std::cell::RefCell;
+use std::convert::Infallible;
+use std::fs;
+
+use glob::glob;
+use html5ever::tendril::{StrTendril, TendrilSink};
+use html5ever::tokenizer::{
+    BufferQueue, Tag, TagKind, Token, TokenSink, TokenSinkResult, Tokenizer as Html5everTokenizer,
+};
+use html5gum::emitters::html5ever::{parse_document, Html5everEmitter};
+use html5gum::Tokenizer;
+use markup5ever_rcdom::{Handle, NodeData, RcDom};
+use pretty_assertions::assert_eq;
+
+/// A handful of fixture files that cover most insertion modes.
+const FIXTURES: &[&str] = &[
+    "tests/custom-html5lib-tests/tree-construction/custom.dat",
+    "tests/html5lib-tests/tree-construction/adoption01.dat",
+    "tests/html5lib-tests/tree-construction/comments01.dat",
+    "tests/html5lib-tests/tree-construction/doctype01.dat",
+    "tests/html5lib-tests/tree-construction/entities01.dat",
+    "tests/html5lib-tests/tree-construction/tests1.dat",
+    "tests/html5lib-tests/tree-construction/tests2.dat",
+    "tests/html5lib-tests/tree-construction/tests3.dat",
+    "tests/html5lib-tests/tree-construction/tables01.dat",
+    "tests/html5lib-tests/tree-construction/plain-text-unsafe.dat",
+];
+
+/// The `#data` of all document tests in a `.dat` file, skipping fragment tests.
+fn documents(path: &str) -> Vec<String> {
+    let content = fs::read_to_string(path).unwrap();
+    let mut documents = Vec::new();
+    for test in content.split("\n#data\n") {
+        let test = test.strip_prefix("#data\n").unwrap_or(test);
+        if test.contains("\n#document-fragment\n") {
+            continue;
+        }
+        let data = &test[..test.find("\n#errors").unwrap()];
+        documents.push(data.to_owned());
+    }
+    documents
+}
+
+fn dump(handle: &Handle, depth: usize, buf: &mut String) {
+    let indent = "  ".repeat(depth);
+    match handle.data {
+        NodeData::Document => {}
+        NodeData::Doctype {
+            ref name,
+            ref public_id,
+            ref system_id,
+        } => {
+            buf.push_str(&format!(
+                "{}<!DOCTYPE {} {:?} {:?}>\n",
+                indent, name, public_id, system_id
+            ));
+        }
+        NodeData::Element {
+            ref name,
+            ref attrs,
+            ref template_contents,
+            ..
+        } => {
+            buf.push_str(&format!("{}<{:?} {}>\n", indent, name.ns, name.local));
+            for attr in attrs.borrow().iter() {
+                buf.push_str(&format!(
+                    "{}  {:?} {}={:?}\n",
+                    indent, attr.name.ns, attr.name.local, attr.value
+                ));
+            }
+            if let Some(ref content) = *template_contents.borrow() {
+                dump(content, depth + 1, buf);
+            }
+        }
+        NodeData::Text { ref contents } => {
+            buf.push_str(&format!("{}{:?}\n", indent, contents.borrow()));
+        }
+        NodeData::Comment { ref contents } => {
+            buf.push_str(&format!("{}<!-- {:?} -->\n", indent, contents));
+        }
+        NodeData::ProcessingInstruction { .. } => unreachable!(),
+    }
+
+    for child in handle.children.borrow().iter() {
+        dump(child, depth + 1, buf);
+    }
+}
+
+fn dump_dom(dom: &RcDom) -> String {
+    let mut buf = format!("{:?}\n", dom.quirks_mode.get());
+    dump(&dom.document, 0, &mut buf);
+    buf
+}
+
+#[test]
+fn same_dom() {
+    let mut count = 0;
+    for path in FIXTURES {
+        if fs::metadata(path).is_err() {
+            // the html5lib-tests submodule is not checked out
+            continue;
+        }
+
+        for input in documents(path) {
+            let expected = html5ever::parse_document(RcDom::default(), Default::default())
+                .from_utf8()
+                .one(input.as_bytes());
+            let Ok(actual) = parse_document(&*input, RcDom::default(), Default::default());
+            assert_eq!(
+                dump_dom(&actual),
+                dump_dom(&expected),
+                "{}: {:?}",
+                path,
+                input
+            );
+            count += 1;
+        }
+    }
+
+    assert!(count > 0);
+}
+
+#[test]
+fn fixtures_exist() {
+    // catch typos in FIXTURES when running from a full checkout
+    let available: Vec<_> = glob("tests/*html5lib-tests/tree-construction/*.dat")
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+    if available.len() > 1 {
+        for path in FIXTURES {
+            assert!(fs::metadata(path).is_ok(), "{}", path);
+        }
+    }
+}
+
+/// Records the line number of every tag, comment and doctype.
+#[derive(Default)]
+struct LineRecorder {
+    lines: RefCell<Vec<(String, u64)>>,
+}
+
+impl TokenSink for LineRecorder {
+    type Handle = Infallible;
+
+    fn process_token(&self, token: Token, line_number: u64) -> TokenSinkResult<Infallible> {
+        let name = match token {
+            Token::TagToken(Tag {
+                kind: TagKind::StartTag,
+                name,
+                ..
+            }) => format!("<{}>", name),
+            Token::TagToken(Tag {
+                kind: TagKind::EndTag,
+                name,
+                ..
+            }) => format!("</{}>", name),
+            Token::CommentToken(value) => format!("<!--{}-->", value),
+            Token::DoctypeToken(_) => "<!DOCTYPE>".to_owned(),
+            Token::EOFToken => "EOF".to_owned(),
+            _ => return TokenSinkResult::Continue,
+        };
+        self.lines.borrow_mut().push((name, line_number));
+        TokenSinkResult::Continue
+    }
+}
+
+#[test]
+fn line_numbers() {
+    let input = "<!DOCTYPE html>\n<p\n  class=x>a\nb</p>\r\n<!--\n-->\r<br>\n\n";
+
+    let expected = Html5everTokenizer::new(LineRecorder::default(), Default::default());
+    let queue = BufferQueue::default();
+    queue.push_back(StrTendril::from(input));
+    let _ = expected.feed(&queue);
+    expected.end();
+
+    let mut actual = LineRecorder::default();
+    let mut tokenizer = Tokenizer::new_with_emitter(input, Html5everEmitter::new(&mut actual));
+    tokenizer.track_lines(true);
+    let Ok(()) = tokenizer.finish();
+
+    assert_eq!(actual.lines.into_inner(), expected.sink.lines.into_inner());
+}
+
+/// Records the identifiers of every doctype.
+#[derive(Default)]
+struct DoctypeRecorder {
+    identifiers: RefCell<Vec<(Option<StrTendril>, Option<StrTendril>)>>,
+}
+
+impl TokenSink for DoctypeRecorder {
+    type Handle = Infallible;
+
+    fn process_token(&self, token: Token, _line_number: u64) -> TokenSinkResult<Infallible> {
+        if let Token::DoctypeToken(doctype) = token {
+            self.identifiers
+                .borrow_mut()
+                .push((doctype.public_id, doctype.system_id));
+        }
+        TokenSinkResult::Continue
+    }
+}
+
+#[test]
+fn doctype_identifiers() {
+    for input in [
+        "<!DOCTYPE html>",
+        "<!DOCTYPE html PUBLIC \"\">",
+        "<!DOCTYPE html SYSTEM ''>",
+        "<!DOCTYPE html PUBLIC \"\" ''>",
+        "<!doctype html public>",
+        "<!doctype html public \"\" x>",
+        "<!DOCTYPE html PUBLIC \"",
+        "<!DOCTYPE html SYSTEM '>",
+    ] {
+        let expected = Html5everTokenizer::new(DoctypeRecorder::default(), Default::default());
+        let queue = BufferQueue::default();
+        queue.push_back(StrTendril::from(input));
+        let _ = expected.feed(&queue);
+        expected.end();
+
+        let mut actual = DoctypeRecorder::default();
+        let Ok(()) =
+            Tokenizer::new_with_emitter(input, Html5everEmitter::new(&mut actual)).finish();
+
+        assert_eq!(
+            actual.identifiers.into_inner(),
+            expected.sink.identifiers.into_inner(),
+            "{:?}",
+            input
+        );
+    }
+}
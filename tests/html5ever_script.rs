@@ -0,0 +1,160 @@
+//! Scripts suspend html5ever's tokenizer. html5gum has to produce the same tokens around them, and
+//! markup from `Html5everEmitter::on_script` has to appear right where the script ends.
+use std::cell::RefCell;
+
+use html5ever::tendril::StrTendril;
+use html5ever::tokenizer::states::RawKind;
+use html5ever::tokenizer::{
+    BufferQueue, TagKind, Token, TokenSink, TokenSinkResult, Tokenizer as Html5everTokenizer,
+    TokenizerResult,
+};
+use html5gum::emitters::html5ever::{Html5everEmitter, ScriptInfo};
+use html5gum::Tokenizer;
+use pretty_assertions::assert_eq;
+
+/// Records tokens, and asks to run a script at every `</script>` like the tree builder does.
+#[derive(Default)]
+struct ScriptSink {
+    tokens: RefCell<Vec<String>>,
+}
+
+impl ScriptSink {
+    fn push(&self, token: String) {
+        let mut tokens = self.tokens.borrow_mut();
+        match (tokens.last_mut(), token.strip_prefix('"')) {
+            // character tokens are split differently
+            (Some(last), Some(text)) if last.starts_with('"') => {
+                last.pop();
+                last.push_str(text);
+            }
+            _ => tokens.push(token),
+        }
+    }
+}
+
+impl TokenSink for ScriptSink {
+    type Handle = ();
+
+    fn process_token(&self, token: Token, _line_number: u64) -> TokenSinkResult<()> {
+        match token {
+            Token::TagToken(tag) => {
+                let start = tag.kind == TagKind::StartTag;
+                self.push(format!("<{}{}>", if start { "" } else { "/" }, tag.name));
+                match &*tag.name {
+                    "script" if start => return TokenSinkResult::RawData(RawKind::ScriptData),
+                    "script" => return TokenSinkResult::Script(()),
+                    _ => {}
+                }
+            }
+            Token::CharacterTokens(s) => self.push(format!("\"{}\"", s)),
+            Token::NullCharacterToken => self.push("\"\0\"".to_owned()),
+            Token::CommentToken(s) => self.push(format!("<!--{}-->", s)),
+            Token::DoctypeToken(_) => self.push("<!DOCTYPE>".to_owned()),
+            Token::EOFToken => self.push("EOF".to_owned()),
+            Token::ParseError(_) => {}
+        }
+        TokenSinkResult::Continue
+    }
+}
+
+fn html5ever_tokens(input: &str) -> Vec<String> {
+    let tokenizer = Html5everTokenizer::new(ScriptSink::default(), Default::default());
+    let queue = BufferQueue::default();
+    queue.push_back(StrTendril::from(input));
+    while let TokenizerResult::Script(()) = tokenizer.feed(&queue) {
+        tokenizer.sink.push("SCRIPT".to_owned());
+    }
+    tokenizer.end();
+    tokenizer.sink.tokens.into_inner()
+}
+
+fn html5gum_tokens(
+    input: &str,
+    on_script: &mut dyn FnMut(&ScriptInfo<'_>) -> Option<Vec<u8>>,
+) -> Vec<String> {
+    let mut sink = ScriptSink::default();
+    let mut emitter = Html5everEmitter::new(&mut sink);
+    emitter.on_script(on_script);
+    let Ok(()) = Tokenizer::new_with_emitter(input, emitter).finish();
+    sink.tokens.into_inner()
+}
+
+const PAGE: &str = "<!DOCTYPE html><title>x</title><p>a<script type=module>if (a < b) \
+    x()</script>b<!--c--><script src=y.js></script><script>\0</script>d";
+
+#[test]
+fn same_order_as_html5ever() {
+    let expected = html5ever_tokens(PAGE);
+    assert_eq!(
+        html5gum_tokens(PAGE, &mut |_| None),
+        expected
+            .into_iter()
+            .filter(|x| x != "SCRIPT")
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn injected_markup() {
+    let mut scripts = Vec::new();
+    let tokens = html5gum_tokens(PAGE, &mut |script| {
+        let attrs: Vec<_> = script
+            .attrs
+            .iter()
+            .map(|attr| format!("{}={}", attr.name.local, attr.value))
+            .collect();
+        scripts.push((attrs, script.text.to_owned()));
+        Some(format!("<i>{}</i>", scripts.len()).into_bytes())
+    });
+
+    // the suspension points are the same as html5ever's
+    let expected: Vec<_> = html5ever_tokens(PAGE)
+        .into_iter()
+        .scan(0, |count, token| {
+            if token == "SCRIPT" {
+                *count += 1;
+                Some(vec![
+                    "<i>".to_owned(),
+                    format!("\"{}\"", count),
+                    "</i>".to_owned(),
+                ])
+            } else {
+                Some(vec![token])
+            }
+        })
+        .flatten()
+        .collect();
+    assert_eq!(tokens, expected);
+
+    assert_eq!(
+        scripts,
+        [
+            (vec!["type=module".to_owned()], "if (a < b) x()".to_owned()),
+            (vec!["src=y.js".to_owned()], String::new()),
+            (vec![], "\u{fffd}".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn nested_scripts() {
+    // markup that is written by a script can contain scripts itself
+    let tokens = html5gum_tokens("<script>1</script>x", &mut |script| match script.text {
+        "1" => Some(b"<script>2</script>a".to_vec()),
+        "2" => Some(b"b".to_vec()),
+        _ => None,
+    });
+    assert_eq!(
+        tokens,
+        [
+            "<script>",
+            "\"1\"",
+            "</script>",
+            "<script>",
+            "\"2\"",
+            "</script>",
+            "\"bax\"",
+            "EOF"
+        ]
+    );
+}
@@ -0,0 +1,198 @@
+//! Spans recorded by the html5ever emitter have to point at the tokens that the tree builder
+//! received.
+use std::cell::{Cell, RefCell};
+use std::fs;
+
+use html5ever::tokenizer::{Tag, TagKind, Token, TokenSink, TokenSinkResult};
+use html5ever::tree_builder::TreeBuilder;
+use html5gum::emitters::html5ever::{Html5everEmitter, ScriptInfo};
+use html5gum::{Span, Tokenizer};
+use markup5ever_rcdom::{Handle, NodeData, RcDom};
+use pretty_assertions::assert_eq;
+
+/// The parts of a token that the tests look at.
+#[derive(Debug, PartialEq)]
+enum Recorded {
+    Markup,
+    StartTag(String),
+    Text(String),
+    Null,
+    Error,
+    Eof,
+}
+
+/// Passes tokens on to a tree builder, and records them to correlate them with spans.
+struct Recorder<S> {
+    inner: S,
+    tokens: RefCell<Vec<Recorded>>,
+    ended: Cell<bool>,
+}
+
+impl<S: TokenSink> TokenSink for Recorder<S> {
+    type Handle = S::Handle;
+
+    fn process_token(&self, token: Token, line_number: u64) -> TokenSinkResult<S::Handle> {
+        self.tokens.borrow_mut().push(match token {
+            Token::TagToken(Tag {
+                kind: TagKind::StartTag,
+                ref name,
+                ..
+            }) => Recorded::StartTag(name.to_string()),
+            Token::TagToken(_) | Token::DoctypeToken(_) | Token::CommentToken(_) => {
+                Recorded::Markup
+            }
+            Token::CharacterTokens(ref text) => Recorded::Text(text.to_string()),
+            Token::NullCharacterToken => Recorded::Null,
+            Token::ParseError(_) => Recorded::Error,
+            Token::EOFToken => Recorded::Eof,
+        });
+        self.inner.process_token(token, line_number)
+    }
+
+    fn end(&self) {
+        self.ended.set(true);
+        self.inner.end();
+    }
+
+    fn adjusted_current_node_present_but_not_in_html_namespace(&self) -> bool {
+        self.inner
+            .adjusted_current_node_present_but_not_in_html_namespace()
+    }
+}
+
+fn parse(html: &str, on_script: Option<&str>) -> (RcDom, Vec<Recorded>, Vec<Span>) {
+    let mut recorder = Recorder {
+        inner: TreeBuilder::new(RcDom::default(), Default::default()),
+        tokens: RefCell::default(),
+        ended: Cell::new(false),
+    };
+    let mut hook = |_: &ScriptInfo<'_>| on_script.map(|markup| markup.as_bytes().to_vec());
+    let mut emitter = Html5everEmitter::new(&mut recorder);
+    emitter.collect_spans(true);
+    emitter.on_script(&mut hook);
+    let mut tokenizer = Tokenizer::new_with_emitter(html, emitter);
+    while tokenizer.next().is_some() {}
+    let spans = tokenizer.emitter_mut().take_spans();
+    drop(tokenizer);
+
+    assert!(recorder.ended.get());
+    let tokens = recorder.tokens.into_inner();
+    assert_eq!(tokens.len(), spans.len());
+    (recorder.inner.sink, tokens, spans)
+}
+
+fn find_element(handle: &Handle, id: &str) -> Option<Handle> {
+    if let NodeData::Element { ref attrs, .. } = handle.data {
+        if attrs
+            .borrow()
+            .iter()
+            .any(|attr| &*attr.name.local == "id" && &*attr.value == id)
+        {
+            return Some(handle.clone());
+        }
+    }
+    handle
+        .children
+        .borrow()
+        .iter()
+        .find_map(|child| find_element(child, id))
+}
+
+fn start_tag_index(tokens: &[Recorded], name: &str) -> usize {
+    tokens
+        .iter()
+        .position(|token| *token == Recorded::StartTag(name.to_owned()))
+        .unwrap()
+}
+
+#[test]
+fn div_in_fixture() {
+    let html = fs::read_to_string("tests/fixtures/spans.html").unwrap();
+    let (dom, tokens, spans) = parse(&html, None);
+    assert!(find_element(&dom.document, "x").is_some());
+
+    let span = spans[start_tag_index(&tokens, "div")];
+    let start = html.find("<div id=x>").unwrap();
+    assert_eq!(
+        span,
+        Span {
+            start,
+            end: start + "<div id=x>".len()
+        }
+    );
+}
+
+#[test]
+fn all_tokens_in_fixture() {
+    let html = fs::read_to_string("tests/fixtures/spans.html").unwrap();
+    let (_, tokens, spans) = parse(&html, None);
+
+    let mut sources = Vec::new();
+    for (token, span) in tokens.iter().zip(&spans) {
+        let source = &html[span.start..span.end];
+        match token {
+            Recorded::Markup | Recorded::StartTag(_) => sources.push(source),
+            Recorded::Null => assert_eq!(source, "text&#0;more"),
+            Recorded::Text(text) if text == "before & " => assert_eq!(source, "before &amp; "),
+            Recorded::Text(_) => {}
+            Recorded::Error => assert_eq!(span.start, span.end),
+            Recorded::Eof => assert_eq!((span.start, span.end), (html.len(), html.len())),
+        }
+    }
+    assert_eq!(
+        sources,
+        [
+            "<!DOCTYPE html>",
+            "<html>",
+            "<head>",
+            "<title>",
+            "</title>",
+            "<script>",
+            "</script>",
+            "</head>",
+            "<body>",
+            "<!-- the element that the test looks for -->",
+            "<p>",
+            "<b>",
+            "</b>",
+            "<div id=x>",
+            "</div>",
+            "</body>",
+            "</html>",
+        ]
+    );
+}
+
+#[test]
+fn text_spans_are_contiguous() {
+    let html = "a<b>c&lt;d</b>e";
+    let (_, tokens, spans) = parse(html, None);
+    let texts: Vec<_> = tokens
+        .iter()
+        .zip(&spans)
+        .filter_map(|(token, span)| match token {
+            Recorded::Text(text) => Some((text.clone(), &html[span.start..span.end])),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        texts,
+        [
+            ("a".to_owned(), "a"),
+            ("c<d".to_owned(), "c&lt;d"),
+            ("e".to_owned(), "e"),
+        ]
+    );
+}
+
+#[test]
+fn script_output_gets_span_of_end_tag() {
+    let html = "<script>x</script><div id=x>";
+    let (dom, tokens, spans) = parse(html, Some("<p id=y>"));
+    assert!(find_element(&dom.document, "y").is_some());
+
+    let p = spans[start_tag_index(&tokens, "p")];
+    assert_eq!(&html[p.start..p.end], "</script>");
+    let div = spans[start_tag_index(&tokens, "div")];
+    assert_eq!(&html[div.start..div.end], "<div id=x>");
+}
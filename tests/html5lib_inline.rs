@@ -0,0 +1,103 @@
+use html5gum::test_case;
+use html5gum::testing::html5lib::{run_case, Test};
+
+#[test]
+fn empty_comment_with_three_dashes() {
+    test_case!({
+        "input": "<!--->",
+        "output": [["Comment", ""]],
+        "errors": [{"code": "abrupt-closing-of-empty-comment"}]
+    });
+}
+
+#[test]
+fn end_tag_in_rcdata() {
+    test_case!({
+        "description": "only the last start tag ends RCDATA",
+        "initialStates": ["RCDATA state", "RAWTEXT state"],
+        "lastStartTag": "title",
+        "input": "a</b></title x>",
+        "output": [["Character", "a</b>"], ["EndTag", "title"]],
+        "errors": [{"code": "end-tag-with-attributes"}]
+    });
+}
+
+#[test]
+fn end_tag_with_trailing_solidus_in_rcdata() {
+    test_case!({
+        "initialStates": ["RCDATA state", "RAWTEXT state", "Script data state"],
+        "lastStartTag": "title",
+        "input": "x</title/>y",
+        "output": [["Character", "x"], ["EndTag", "title"], ["Character", "y"]],
+        "errors": [{"code": "end-tag-with-trailing-solidus"}]
+    });
+    test_case!({
+        "description": "end tags that are not appropriate are text, including their solidus",
+        "initialStates": ["RCDATA state", "RAWTEXT state", "Script data state"],
+        "lastStartTag": "title",
+        "input": "x</titl/>y</titlex/>z</title/ >",
+        "output": [["Character", "x</titl/>y</titlex/>z"], ["EndTag", "title"]],
+        "errors": [{"code": "unexpected-solidus-in-tag"}]
+    });
+}
+
+#[test]
+fn double_escaped() {
+    // attribute values are not unescaped, just like in html5lib-tests
+    test_case!({
+        "doubleEscaped": true,
+        "input": "\\u0000<a b=\\u0000>",
+        "output": [["Character", "\\u0000"], ["StartTag", "a", {"b": "\u{fffd}"}]],
+        "errors": [
+            {"code": "unexpected-null-character"},
+            {"code": "unexpected-null-character"}
+        ]
+    });
+}
+
+#[test]
+fn long_numeric_character_references() {
+    for reference in [
+        "&#9999999999999999999999999;",
+        "&#x1234567890abcdefABCDEF123;",
+    ] {
+        test_case!({
+            "input": format!("{0}<a b='{0}'>", reference),
+            "output": [["Character", "\u{fffd}"], ["StartTag", "a", {"b": "\u{fffd}"}]],
+            "errors": [
+                {"code": "character-reference-outside-unicode-range"},
+                {"code": "character-reference-outside-unicode-range"}
+            ]
+        });
+
+        // without semicolon
+        let reference = reference.trim_end_matches(';');
+        test_case!({
+            "input": format!("{0} <a b='{0}'>", reference),
+            "output": [["Character", "\u{fffd} "], ["StartTag", "a", {"b": "\u{fffd}"}]],
+            "errors": [
+                {"code": "missing-semicolon-after-character-reference"},
+                {"code": "character-reference-outside-unicode-range"},
+                {"code": "missing-semicolon-after-character-reference"},
+                {"code": "character-reference-outside-unicode-range"}
+            ]
+        });
+    }
+}
+
+#[test]
+fn failure() {
+    let test = Test::from_json(serde_json::json!({
+        "description": "wrong on purpose",
+        "input": "<a>",
+        "output": [["StartTag", "b", {}]]
+    }))
+    .unwrap();
+
+    let result = run_case(&test);
+    assert!(!result.is_ok());
+    // once per reader kind
+    assert_eq!(result.failures.len(), 5);
+    let message = result.failures[0].to_string();
+    assert!(message.starts_with("test case \"wrong on purpose\" failed"));
+}
@@ -0,0 +1,93 @@
+use std::io::Read;
+
+use html5gum::{IoReader, Remainder, Token, Tokenizer};
+use pretty_assertions::assert_eq;
+
+const HTML: &str = "<!DOCTYPE html>\r\n<html><head><title>a &amp; b</title>\
+    <meta charset=utf-8><script>if (a</b) {}</script></head>\r\n<body><p>hello</p>";
+
+fn until_head_end<R: html5gum::Reader>(tokenizer: &mut Tokenizer<R>) {
+    for token in tokenizer {
+        if let Ok(Token::EndTag(tag)) = token {
+            if tag.name == b"head" {
+                return;
+            }
+        }
+    }
+    panic!("no </head>");
+}
+
+fn io_rest<R: Read>(mut remainder: Remainder<IoReader<R>>) -> Vec<u8> {
+    let mut rest = remainder.read_ahead;
+    rest.extend(remainder.reader.buffer());
+    remainder
+        .reader
+        .into_inner()
+        .read_to_end(&mut rest)
+        .unwrap();
+    rest
+}
+
+#[test]
+fn string_reader() {
+    let mut tokenizer = Tokenizer::new(HTML);
+    until_head_end(&mut tokenizer);
+    let end = tokenizer.last_tag_end().unwrap();
+    assert_eq!(end, tokenizer.consumed_bytes());
+    assert!(HTML[..end].ends_with("</head>"));
+
+    let remainder = tokenizer.into_reader();
+    assert_eq!(remainder.consumed, end);
+    assert_eq!(remainder.read_ahead, b"");
+    assert_eq!(remainder.reader.remaining(), &HTML.as_bytes()[end..]);
+}
+
+#[test]
+fn io_reader() {
+    for capacity in [32, 33, 50, 1000] {
+        let mut tokenizer = Tokenizer::new(IoReader::with_capacity(HTML.as_bytes(), capacity));
+        until_head_end(&mut tokenizer);
+        let end = tokenizer.last_tag_end().unwrap();
+
+        let remainder = tokenizer.into_reader();
+        assert_eq!(remainder.consumed, end);
+        assert_eq!(io_rest(remainder), &HTML.as_bytes()[end..], "{}", capacity);
+    }
+}
+
+#[test]
+fn reconsumed_bytes() {
+    // both errors are emitted right after the tokenizer decided to reconsume a byte
+    for (html, consumed, read_ahead) in
+        [("a<\r\nb", 2, "\r"), ("a<\rb", 2, "\r"), ("a</ b", 3, " ")]
+    {
+        let mut tokenizer = Tokenizer::new(IoReader::with_capacity(html.as_bytes(), 32));
+        assert!(matches!(tokenizer.next(), Some(Ok(Token::Error(_)))));
+
+        let remainder = tokenizer.into_reader();
+        assert_eq!(remainder.consumed, consumed);
+        assert_eq!(remainder.read_ahead, read_ahead.as_bytes());
+        let mut input = html.as_bytes()[..consumed].to_vec();
+        input.extend(io_rest(remainder));
+        assert_eq!(input, html.as_bytes());
+    }
+}
+
+#[test]
+fn take_bytes() {
+    let mut tokenizer = Tokenizer::new("<p>hello world</p>");
+    tokenizer.take_bytes(5);
+    tokenizer.by_ref().for_each(drop);
+    let remainder = tokenizer.into_reader();
+    assert_eq!(remainder.consumed, 5);
+    assert_eq!(remainder.read_ahead, b"llo world");
+    assert_eq!(remainder.reader.remaining(), b"</p>");
+
+    let mut tokenizer = Tokenizer::new("<p>hello");
+    tokenizer.take_bytes(3);
+    tokenizer.by_ref().for_each(drop);
+    let remainder = tokenizer.into_reader();
+    assert_eq!(remainder.consumed, 3);
+    assert_eq!(remainder.read_ahead, b"h");
+    assert_eq!(remainder.reader.remaining(), b"ello");
+}
@@ -0,0 +1,65 @@
+use html5gum::{Comment, CommentKind, EndTag, Error, StartTag, Token, Tokenizer};
+use pretty_assertions::assert_eq;
+
+mod testutils;
+
+use testutils::string;
+
+const INPUT: &str = "<тег атрибут=\"значение\">текст</тег>";
+
+fn tokenize(input: &str, lenient: bool) -> Vec<Token> {
+    let mut tokenizer = Tokenizer::new(input);
+    tokenizer.lenient_tag_names(lenient);
+    tokenizer.map(Result::unwrap).collect()
+}
+
+#[test]
+fn lenient() {
+    assert_eq!(
+        tokenize(INPUT, true),
+        [
+            Token::StartTag(StartTag {
+                self_closing: false,
+                name: "тег".as_bytes().to_vec().into(),
+                attributes: [(
+                    "атрибут".as_bytes().to_vec().into(),
+                    "значение".as_bytes().to_vec().into()
+                )]
+                .into(),
+            }),
+            string("текст"),
+            Token::EndTag(EndTag {
+                name: "тег".as_bytes().to_vec().into(),
+            }),
+        ]
+    );
+
+    // non-ASCII characters are not lowercased
+    assert_eq!(
+        tokenize("<ÄB/>", true),
+        [Token::StartTag(StartTag {
+            self_closing: true,
+            name: "Äb".as_bytes().to_vec().into(),
+            attributes: Default::default(),
+        })]
+    );
+
+    // ASCII characters that cannot start a tag name still can't
+    assert_eq!(tokenize("<3 a <= b", true), tokenize("<3 a <= b", false));
+}
+
+#[test]
+fn default() {
+    assert_eq!(
+        tokenize(INPUT, false),
+        [
+            Token::Error(Error::InvalidFirstCharacterOfTagName),
+            string("<тег атрибут=\"значение\">текст"),
+            Token::Error(Error::InvalidFirstCharacterOfTagName),
+            Token::Comment(Comment {
+                value: "тег".as_bytes().to_vec().into(),
+                kind: CommentKind::Bogus,
+            }),
+        ]
+    );
+}
@@ -0,0 +1,192 @@
+//! The order in which the tokenizer calls [`Emitter::begin`], [`Emitter::emit_eof`] and
+//! [`Emitter::finish`].
+use std::cell::RefCell;
+use std::convert::Infallible;
+use std::rc::Rc;
+
+use html5gum::{Emitter, Error, State, Tokenizer};
+use pretty_assertions::assert_eq;
+
+/// Records the calls that matter here into `log`. Every tag and the end of the file produce a
+/// token if `make_token` returns one.
+struct Recorder<T> {
+    log: Rc<RefCell<Vec<&'static str>>>,
+    tokens: Vec<T>,
+    make_token: fn() -> Option<T>,
+    eof: bool,
+}
+
+impl<T> Recorder<T> {
+    fn new(make_token: fn() -> Option<T>) -> (Self, Rc<RefCell<Vec<&'static str>>>) {
+        let log = Rc::default();
+        let recorder = Recorder {
+            log: Rc::clone(&log),
+            tokens: Vec::new(),
+            make_token,
+            eof: false,
+        };
+        (recorder, log)
+    }
+
+    fn record(&mut self, call: &'static str) {
+        self.log.borrow_mut().push(call);
+    }
+
+    fn push_token(&mut self) {
+        self.tokens.extend((self.make_token)());
+    }
+}
+
+impl<T> Emitter for Recorder<T> {
+    type Token = T;
+
+    fn set_last_start_tag(&mut self, _last_start_tag: Option<&[u8]>) {}
+
+    fn begin(&mut self) {
+        self.record("begin");
+    }
+
+    fn emit_eof(&mut self) {
+        self.record("emit_eof");
+        self.eof = true;
+        self.push_token();
+    }
+
+    fn finish(&mut self) {
+        self.record("finish");
+    }
+
+    fn emit_error(&mut self, _error: Error) {}
+
+    fn pop_token(&mut self) -> Option<Self::Token> {
+        let token = self.tokens.pop();
+        // before the end of the file, pop_token is called after every state
+        if self.eof {
+            self.record(if token.is_some() {
+                "pop_token"
+            } else {
+                "pop_token (none)"
+            });
+        }
+        token
+    }
+
+    fn emit_string(&mut self, _c: &[u8]) {
+        self.record("emit_string");
+    }
+
+    fn init_start_tag(&mut self) {}
+
+    fn init_end_tag(&mut self) {}
+
+    fn init_comment(&mut self) {}
+
+    fn emit_current_tag(&mut self) -> Option<State> {
+        self.record("emit_current_tag");
+        self.push_token();
+        None
+    }
+
+    fn emit_current_comment(&mut self) {}
+
+    fn emit_current_doctype(&mut self) {}
+
+    fn set_self_closing(&mut self) {}
+
+    fn set_force_quirks(&mut self) {}
+
+    fn push_tag_name(&mut self, _s: &[u8]) {}
+
+    fn push_comment(&mut self, _s: &[u8]) {}
+
+    fn push_doctype_name(&mut self, _s: &[u8]) {}
+
+    fn init_doctype(&mut self) {}
+
+    fn init_attribute(&mut self) {}
+
+    fn push_attribute_name(&mut self, _s: &[u8]) {}
+
+    fn push_attribute_value(&mut self, _s: &[u8]) {}
+
+    fn set_doctype_public_identifier(&mut self, _value: &[u8]) {}
+
+    fn set_doctype_system_identifier(&mut self, _value: &[u8]) {}
+
+    fn push_doctype_public_identifier(&mut self, _s: &[u8]) {}
+
+    fn push_doctype_system_identifier(&mut self, _s: &[u8]) {}
+
+    fn current_is_appropriate_end_tag_token(&mut self) -> bool {
+        false
+    }
+}
+
+#[test]
+fn iterator() {
+    let (recorder, log) = Recorder::new(|| Some(()));
+    let mut tokenizer = Tokenizer::new_with_emitter("<p>a</p>", recorder);
+    assert_eq!(log.borrow().len(), 0);
+
+    assert_eq!(tokenizer.by_ref().count(), 3);
+    assert_eq!(
+        *log.borrow(),
+        [
+            "begin",
+            "emit_current_tag",
+            "emit_string",
+            "emit_current_tag",
+            "emit_eof",
+            "pop_token",
+            "pop_token (none)",
+            "finish"
+        ]
+    );
+
+    // the emitter is not called again once it is finished
+    assert!(tokenizer.next().is_none());
+    assert_eq!(log.borrow().len(), 8);
+}
+
+#[test]
+fn empty_input() {
+    let (recorder, log) = Recorder::new(|| Some(()));
+    assert_eq!(Tokenizer::new_with_emitter("", recorder).count(), 1);
+    assert_eq!(
+        *log.borrow(),
+        [
+            "begin",
+            "emit_eof",
+            "pop_token",
+            "pop_token (none)",
+            "finish"
+        ]
+    );
+}
+
+#[test]
+fn infallible() {
+    let (recorder, log) = Recorder::<Infallible>::new(|| None);
+    let Ok(()) = Tokenizer::new_with_emitter("<p>a", recorder).finish();
+    assert_eq!(
+        *log.borrow(),
+        [
+            "begin",
+            "emit_current_tag",
+            "emit_string",
+            "emit_eof",
+            "pop_token (none)",
+            "finish"
+        ]
+    );
+}
+
+#[test]
+fn take_tokens() {
+    // tokenization stops before the end, so the emitter is never finished
+    let (recorder, log) = Recorder::new(|| Some(()));
+    let mut tokenizer = Tokenizer::new_with_emitter("<p>a</p>", recorder);
+    tokenizer.take_tokens(1);
+    assert_eq!(tokenizer.count(), 1);
+    assert_eq!(*log.borrow(), ["begin", "emit_current_tag"]);
+}
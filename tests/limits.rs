@@ -0,0 +1,189 @@
+use std::convert::Infallible;
+use std::io::{self, Read};
+
+use html5gum::emitters::callback::{CallbackEmitter, CallbackEvent};
+use html5gum::{DefaultEmitter, Doctype, Error, IoReader, Limits, StartTag, Token, Tokenizer};
+use pretty_assertions::assert_eq;
+
+mod testutils;
+
+#[global_allocator]
+static ALLOC: testutils::CountingAlloc = testutils::CountingAlloc;
+
+const LIMITS: Limits = Limits {
+    max_attributes: 2,
+    max_name_len: 3,
+    max_value_len: 4,
+    max_comment_len: 5,
+    max_string_len: 6,
+};
+
+fn tokenize(input: &str) -> Vec<Token> {
+    let mut emitter = DefaultEmitter::default();
+    emitter.limits(LIMITS);
+    Tokenizer::new_with_emitter(input, emitter)
+        .map(Result::unwrap)
+        .collect()
+}
+
+fn start_tag(name: &str, attributes: &[(&str, &str)]) -> Token {
+    Token::StartTag(StartTag {
+        self_closing: false,
+        name: name.as_bytes().to_vec().into(),
+        attributes: attributes
+            .iter()
+            .map(|(name, value)| {
+                (
+                    name.as_bytes().to_vec().into(),
+                    value.as_bytes().to_vec().into(),
+                )
+            })
+            .collect(),
+    })
+}
+
+#[test]
+fn tags() {
+    assert_eq!(
+        tokenize("<abcdef ghijkl=mnopqr>"),
+        [
+            Token::Error(Error::LimitExceeded),
+            start_tag("abc", &[("ghi", "mnop")]),
+        ]
+    );
+
+    // attributes beyond the limit are dropped entirely, and do not count as duplicates
+    assert_eq!(
+        tokenize("<p a=1 a=2 b=3 b=4><p a=1 b=2>"),
+        [
+            Token::Error(Error::DuplicateAttribute),
+            Token::Error(Error::LimitExceeded),
+            start_tag("p", &[("a", "1")]),
+            start_tag("p", &[("a", "1"), ("b", "2")]),
+        ]
+    );
+
+    assert_eq!(
+        tokenize("</abcdef>"),
+        [
+            Token::Error(Error::LimitExceeded),
+            Token::EndTag(html5gum::EndTag {
+                name: b"abc".to_vec().into()
+            }),
+        ]
+    );
+}
+
+#[test]
+fn strings_and_comments() {
+    assert_eq!(
+        tokenize("hello world<!--hello world--><!---->"),
+        [
+            Token::Error(Error::LimitExceeded),
+            Token::String(b"hello ".to_vec().into()),
+            Token::Error(Error::LimitExceeded),
+            Token::Comment(b"hello".to_vec().into()),
+            Token::Comment(b"".to_vec().into()),
+        ]
+    );
+
+    // the limit applies to the entire run of characters, not each chunk that is read
+    assert_eq!(
+        tokenize("a&amp;b&amp;c&amp;d"),
+        [
+            Token::Error(Error::LimitExceeded),
+            Token::String(b"a&b&c&".to_vec().into()),
+        ]
+    );
+}
+
+#[test]
+fn doctype() {
+    assert_eq!(
+        tokenize(r#"<!DOCTYPE htmlx PUBLIC "abcdef" "ghijkl">"#),
+        [
+            Token::Error(Error::LimitExceeded),
+            Token::Doctype(Doctype {
+                force_quirks: false,
+                name: b"htm".to_vec().into(),
+                public_identifier: Some(b"abcd".to_vec().into()),
+                system_identifier: Some(b"ghij".to_vec().into()),
+            }),
+        ]
+    );
+}
+
+#[test]
+fn callback_emitter() {
+    let mut events = Vec::new();
+    let mut emitter = CallbackEmitter::new(|event: CallbackEvent<'_>| -> Option<Infallible> {
+        events.push(format!("{:?}", event));
+        None
+    });
+    emitter.limits(LIMITS);
+    emitter.pair_attributes(true);
+
+    let Ok(()) = Tokenizer::new_with_emitter("<p abcd a b>", emitter).finish();
+    assert_eq!(
+        events,
+        [
+            "OpenStartTag { name: [112] }",
+            "Error(LimitExceeded)",
+            "Attribute { name: [97, 98, 99], value: None, syntax: Empty }",
+            "Attribute { name: [97], value: None, syntax: Empty }",
+            "CloseStartTag { self_closing: false, is_void: false }",
+        ]
+    );
+}
+
+/// Produces an unclosed comment of the given length, without holding it in memory.
+struct UnclosedComment {
+    prefix: &'static [u8],
+    remaining: usize,
+}
+
+impl Read for UnclosedComment {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.prefix.is_empty() {
+            let len = self.prefix.read(buf)?;
+            return Ok(len);
+        }
+
+        let len = buf.len().min(self.remaining);
+        buf[..len].fill(b'x');
+        self.remaining -= len;
+        Ok(len)
+    }
+}
+
+#[test]
+fn bounded_memory() {
+    const LEN: usize = 100 * 1024 * 1024;
+    const LIMIT: usize = 1024;
+
+    let reader = UnclosedComment {
+        prefix: b"<!--",
+        remaining: LEN,
+    };
+    let mut emitter = DefaultEmitter::default();
+    emitter.limits(Limits {
+        max_comment_len: LIMIT,
+        ..Limits::default()
+    });
+
+    let (tokens, peak) = testutils::peak_memory(|| {
+        Tokenizer::new_with_emitter(IoReader::new(reader), emitter)
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+    });
+
+    assert_eq!(
+        tokens,
+        [
+            Token::Error(Error::LimitExceeded),
+            Token::Error(Error::EofInComment),
+            Token::Comment(vec![b'x'; LIMIT].into()),
+        ]
+    );
+    assert!(peak < 1024 * 1024, "{}", peak);
+}
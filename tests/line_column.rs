@@ -0,0 +1,144 @@
+use std::convert::Infallible;
+
+use html5gum::emitters::callback::{Callback, CallbackEmitter, CallbackEvent};
+use html5gum::{DefaultEmitter, Tokenizer};
+use pretty_assertions::assert_eq;
+
+/// Records the start offset of every tag and the offset of every error.
+#[derive(Default)]
+struct Recorder {
+    token_start: usize,
+    position: usize,
+    events: Vec<(String, usize)>,
+}
+
+impl Callback<Infallible> for Recorder {
+    fn handle_event(&mut self, event: CallbackEvent<'_>) -> Option<Infallible> {
+        match event {
+            CallbackEvent::OpenStartTag { name } => self.events.push((
+                format!("<{}>", String::from_utf8_lossy(name)),
+                self.token_start,
+            )),
+            CallbackEvent::EndTag { name } => self.events.push((
+                format!("</{}>", String::from_utf8_lossy(name)),
+                self.token_start,
+            )),
+            CallbackEvent::Error(error) => {
+                self.events.push((format!("{:?}", error), self.position))
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn set_token_start(&mut self, offset: usize) {
+        self.token_start = offset;
+    }
+
+    fn set_position(&mut self, offset: usize) {
+        self.position = offset;
+    }
+}
+
+fn line_columns(input: &str) -> Vec<(String, (usize, usize))> {
+    let mut tokenizer =
+        Tokenizer::new_with_emitter(input, CallbackEmitter::new(Recorder::default()));
+    tokenizer.track_lines(true);
+    assert!(tokenizer.next().is_none());
+
+    let line_index = tokenizer.line_index().unwrap();
+    tokenizer
+        .emitter()
+        .callback()
+        .events
+        .iter()
+        .map(|(event, offset)| (event.clone(), line_index.line_column(*offset)))
+        .collect()
+}
+
+fn event(event: &str, line: usize, column: usize) -> (String, (usize, usize)) {
+    (event.to_owned(), (line, column))
+}
+
+#[test]
+fn tags_and_errors() {
+    let input = "<html>\n\
+                 <body>\r\n\
+                 \x20 <p a=1 a=2>x</p>\r\
+                 <div>\n\
+                 \n\
+                 \u{e9}\u{e9}<br/ >\r\n\
+                 </div\0>";
+
+    assert_eq!(
+        line_columns(input),
+        [
+            event("<html>", 1, 1),
+            event("<body>", 2, 1),
+            event("<p>", 3, 3),
+            event("DuplicateAttribute", 3, 13),
+            event("</p>", 3, 15),
+            event("<div>", 4, 1),
+            event("UnexpectedSolidusInTag", 6, 10),
+            event("<br>", 6, 5),
+            event("UnexpectedNullCharacter", 7, 7),
+            event("</div\u{fffd}>", 7, 1),
+        ]
+    );
+}
+
+#[test]
+fn newlines() {
+    // \n, \r\n and a lone \r are all one newline
+    for newline in ["\n", "\r\n", "\r"] {
+        let input = format!("<a>{0}<b>{0}{0}  <c>{0}", newline);
+        assert_eq!(
+            line_columns(&input),
+            [event("<a>", 1, 1), event("<b>", 2, 1), event("<c>", 4, 3)],
+            "newline: {:?}",
+            newline
+        );
+    }
+
+    // \n\r is two newlines
+    assert_eq!(line_columns("\n\r<a>"), [event("<a>", 3, 1)]);
+}
+
+#[test]
+fn spans() {
+    let input = "<!DOCTYPE html>\r\n<title>a\r\nb</title>\n<!-- c -->";
+    let mut tokenizer = Tokenizer::new_with_emitter(input, DefaultEmitter::with_spans());
+    tokenizer.track_lines(true);
+    let spans: Vec<_> = tokenizer.by_ref().flatten().map(|(_, span)| span).collect();
+    let line_index = tokenizer.line_index().unwrap();
+    let positions: Vec<_> = spans
+        .iter()
+        .map(|span| {
+            (
+                line_index.line_column(span.start),
+                line_index.line_column(span.end),
+            )
+        })
+        .collect();
+    assert_eq!(
+        positions,
+        [
+            ((1, 1), (1, 16)),
+            ((1, 16), (2, 1)),
+            ((2, 1), (2, 8)),
+            ((2, 8), (3, 2)),
+            ((3, 2), (3, 10)),
+            ((3, 10), (4, 1)),
+            ((4, 1), (4, 11)),
+        ]
+    );
+    assert_eq!(line_index.line_count(), 4);
+}
+
+#[test]
+fn disabled() {
+    let mut tokenizer = Tokenizer::new("a\nb");
+    assert!(tokenizer.line_index().is_none());
+    assert_eq!(tokenizer.by_ref().count(), 1);
+    assert!(tokenizer.line_index().is_none());
+}
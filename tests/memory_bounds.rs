@@ -0,0 +1,71 @@
+//! The tokenizer's own memory use must not depend on the input. This runs the same checks as the
+//! fuzz target in `fuzz/` with `FUZZ_MEMORY_BOUNDS=1` over large adversarial inputs.
+use html5gum::testing::memory::check_memory_bounds;
+use html5gum::{DefaultEmitter, Token, Tokenizer};
+use pretty_assertions::assert_eq;
+
+fn check(prefix: &str, repeated: &str) {
+    check_len(prefix, repeated, 64 << 10);
+}
+
+fn check_len(prefix: &str, repeated: &str, len: usize) {
+    let input = format!("{}{}", prefix, repeated.repeat(len / repeated.len()));
+    if let Err(violation) = check_memory_bounds(input.as_bytes()) {
+        panic!(
+            "{}\ninput: {:?} followed by {:?}",
+            violation, prefix, repeated
+        );
+    }
+}
+
+#[test]
+fn multi_megabyte() {
+    check_len("", "&", 2 << 20);
+    check_len("", "&notareference", 2 << 20);
+}
+
+#[test]
+fn character_references() {
+    check("&", "a");
+    check("&#", "9");
+    check("&#x", "f");
+    check("<a href=&", "b");
+    check("<a href='", "&amp");
+}
+
+#[test]
+fn end_tag_names() {
+    check("<title></", "a");
+    check("<style></", "a");
+    check("<script></", "a");
+    check("<script><!--</", "a");
+    check("", "</scrip");
+}
+
+#[test]
+fn script_double_escape() {
+    check("<script><!--<", "a");
+    check("<script><!--<script></", "a");
+}
+
+#[test]
+fn long_end_tag_names_are_text() {
+    let mut emitter = DefaultEmitter::default();
+    emitter.naively_switch_states(true);
+    let tokens: Vec<_> = Tokenizer::new_with_emitter("<title></titlex></TITLE>", emitter)
+        .flatten()
+        .map(|token| match token {
+            Token::StartTag(tag) => Token::String(tag.name),
+            Token::EndTag(tag) => Token::String(tag.name),
+            token => token,
+        })
+        .collect();
+    assert_eq!(
+        tokens,
+        [
+            Token::String(b"title".to_vec().into()),
+            Token::String(b"</titlex>".to_vec().into()),
+            Token::String(b"title".to_vec().into()),
+        ]
+    );
+}
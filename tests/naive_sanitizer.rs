@@ -0,0 +1,144 @@
+use html5gum::naive_sanitizer::{sanitize, Sanitizer};
+use pretty_assertions::assert_eq;
+
+fn check(input: &str) -> String {
+    let Ok(html) = sanitize(input);
+    String::from_utf8(html).unwrap()
+}
+
+#[test]
+fn basic() {
+    assert_eq!(
+        check("<p onclick=x id=y>hi <b>there</b><script>alert(1)</script></p>"),
+        "<p>hi <b>there</b></p>"
+    );
+    assert_eq!(check("a<!-- <script> -->b<!DOCTYPE html>"), "ab");
+    assert_eq!(check("<div><span>a</span></div>"), "a");
+}
+
+#[test]
+fn raw_text_elements() {
+    assert_eq!(
+        check("a<style>p { color: red }</style>b<iframe><p>c</p></iframe>d"),
+        "abd"
+    );
+    assert_eq!(check("<script>a</p>b</script>c"), "c");
+    assert_eq!(check("a<plaintext><b>x</b>"), "a");
+    // unclosed, so everything until the end is dropped
+    assert_eq!(check("a<script>b<p>c"), "a");
+
+    // can not be allowed
+    let mut sanitizer = Sanitizer::new();
+    sanitizer.allow_tag("script", &[]);
+    let Ok(html) = sanitizer.sanitize("<script>alert(1)</script>");
+    assert_eq!(html, b"");
+}
+
+#[test]
+fn broken_up_tags() {
+    assert_eq!(
+        check("<scr<script>ipt>alert(1)</script>"),
+        "ipt&gt;alert(1)"
+    );
+    assert_eq!(
+        check("<<script>script>alert(1)<</script>/script>"),
+        "&lt;/script&gt;"
+    );
+    assert_eq!(check("<p<script>>x"), "&gt;x");
+    assert_eq!(check("<scr\0ipt>alert(1)</scr\0ipt>"), "alert(1)");
+}
+
+#[test]
+fn javascript_urls() {
+    for url in [
+        "javascript:alert(1)",
+        "JaVaScRiPt:alert(1)",
+        "java\tscript:alert(1)",
+        "java\nscr\ript:alert(1)",
+        "java&#x09;script:alert(1)",
+        "java&Tab;script:alert(1)",
+        " \x01javascript:alert(1)",
+        "&#10;javascript:alert(1)",
+        "javascript&colon;alert(1)",
+        "vbscript:msgbox(1)",
+        "data:text/html,<script>alert(1)</script>",
+    ] {
+        assert_eq!(
+            check(&format!("<a href=\"{}\" title=x>a</a>", url)),
+            "<a title=\"x\">a</a>",
+            "{:?}",
+            url
+        );
+    }
+
+    for url in [
+        "https://example.com/",
+        "HTTP://example.com/",
+        "mailto:a@example.com",
+        "/javascript:alert(1)",
+        "./javascript:alert(1)",
+        "?javascript:alert(1)",
+        "#javascript:alert(1)",
+        "java\u{fffd}script:alert(1)",
+    ] {
+        assert_eq!(
+            check(&format!("<a href=\"{}\">a</a>", url)),
+            format!("<a href=\"{}\">a</a>", url),
+            "{:?}",
+            url
+        );
+    }
+
+    let mut sanitizer = Sanitizer::default();
+    sanitizer.allow_url_scheme("FTP");
+    let Ok(html) = sanitizer.sanitize("<img src=ftp://example.com/a.png>");
+    assert_eq!(html, b"<img src=\"ftp://example.com/a.png\">");
+}
+
+#[test]
+fn rcdata() {
+    let input = "<textarea><script>alert(1)</script></textarea><title><b>x</b></title>";
+    assert_eq!(
+        check(input),
+        "&lt;script&gt;alert(1)&lt;/script&gt;&lt;b&gt;x&lt;/b&gt;"
+    );
+
+    let mut sanitizer = Sanitizer::new();
+    sanitizer.allow_tag("textarea", &[]);
+    let Ok(html) = sanitizer.sanitize(input);
+    assert_eq!(
+        html,
+        b"<textarea>&lt;script&gt;alert(1)&lt;/script&gt;</textarea>&lt;b&gt;x&lt;/b&gt;"
+    );
+}
+
+#[test]
+fn escaping() {
+    assert_eq!(
+        check("<a title='\"><script>alert(1)</script>' href='/?a=1&amp;b=2'>&lt;b&gt; &amp;amp;</a>"),
+        "<a title=\"&quot;&gt;&lt;script&gt;alert(1)&lt;/script&gt;\" href=\"/?a=1&amp;b=2\">&lt;b&gt; &amp;amp;</a>"
+    );
+    assert_eq!(check("<img alt>"), "<img alt>");
+}
+
+#[test]
+fn self_closing_and_unclosed_tags() {
+    assert_eq!(check("a<br/>b<hr>c</br>"), "a<br>b<hr>c");
+    assert_eq!(check("<p/>x"), "<p>x</p>");
+    assert_eq!(check("<b>bold<i>both"), "<b>bold<i>both</i></b>");
+    assert_eq!(check("<b><i>x</b>y</i>"), "<b><i>x</i></b>y");
+    assert_eq!(check("</p></b>x</ul>"), "x");
+    assert_eq!(
+        check("<ul><li>a<li>b</ul>"),
+        "<ul><li>a<li>b</li></li></ul>"
+    );
+}
+
+#[test]
+fn custom_tags() {
+    let mut sanitizer = Sanitizer::new();
+    sanitizer.allow_tag("SPAN", &["Class", "onclick"]);
+    sanitizer.allow_tag("span", &["lang"]);
+    let Ok(html) = sanitizer.sanitize("<span class=a lang=en onclick=x title=y><p>b</span>");
+    assert_eq!(html, b"<span class=\"a\" lang=\"en\">b</span>");
+}
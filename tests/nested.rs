@@ -0,0 +1,69 @@
+use html5gum::charref::decode;
+use html5gum::emitters::callback::{CallbackEmitter, OwnedCallbackEvent, RecordingCallback};
+use html5gum::nested::parse_srcdoc;
+use html5gum::{Token, Tokenizer};
+use pretty_assertions::assert_eq;
+
+/// The spans of `srcdoc` attribute values index the outer document, and cover the raw attribute
+/// text that the value was decoded from.
+#[test]
+fn srcdoc_spans() {
+    let outer = "<p title=\"&amp;\">a &amp; b</p>\n\
+                 <iframe title=x srcdoc=\"<p>&amp;lt;</p>&quot;\"></iframe>\
+                 <IFRAME SRCDOC='<a href=&quot;&amp;amp;&quot;>'></IFRAME>\
+                 <iframe srcdoc=&lt;b&gt;&amp;gt;></iframe>";
+
+    let mut tokenizer =
+        Tokenizer::new_with_emitter(outer, CallbackEmitter::new(RecordingCallback::default()));
+    for _ in tokenizer.by_ref() {}
+    let events = tokenizer.emitter().callback().events();
+
+    let mut srcdocs = Vec::new();
+    for pair in events.windows(2) {
+        if let [(OwnedCallbackEvent::AttributeName { name }, _), (OwnedCallbackEvent::AttributeValue { value, .. }, span)] =
+            pair
+        {
+            if name == b"srcdoc" {
+                let raw = &outer.as_bytes()[span.start..span.end];
+                assert_eq!(decode(raw, true), value.as_slice());
+                srcdocs.push((
+                    String::from_utf8(raw.to_vec()).unwrap(),
+                    String::from_utf8(value.clone()).unwrap(),
+                ));
+            }
+        }
+    }
+
+    assert_eq!(
+        srcdocs,
+        [
+            (
+                "<p>&amp;lt;</p>&quot;".to_owned(),
+                "<p>&lt;</p>\"".to_owned()
+            ),
+            (
+                "<a href=&quot;&amp;amp;&quot;>".to_owned(),
+                "<a href=\"&amp;\">".to_owned()
+            ),
+            ("&lt;b&gt;&amp;gt;".to_owned(), "<b>&gt;".to_owned()),
+        ]
+    );
+
+    // the inner documents see what a browser's inner document would
+    let inner: Vec<Vec<Token>> = srcdocs
+        .iter()
+        .map(|(_, value)| {
+            parse_srcdoc(&value.as_bytes().to_vec().into())
+                .flatten()
+                .filter(|token| !matches!(token, Token::Error(_)))
+                .collect()
+        })
+        .collect();
+    assert_eq!(inner[0][1], Token::String(b"<".to_vec().into()));
+    assert_eq!(inner[0][3], Token::String(b"\"".to_vec().into()));
+    let Token::StartTag(a) = &inner[1][0] else {
+        panic!("{:?}", inner[1]);
+    };
+    assert_eq!(a.attributes[b"href".as_slice()], b"&".as_slice());
+    assert_eq!(inner[2][1], Token::String(b">".to_vec().into()));
+}
@@ -0,0 +1,76 @@
+//! `\r\n` and lone `\r` are normalized to `\n` before tokenization, as the spec requires. Spans
+//! still refer to the original input, see `tests/spans.rs`.
+use std::io::{self, Read};
+
+use html5gum::{Comment, IoReader, Readable, Reader, StartTag, Token, Tokenizer};
+use pretty_assertions::assert_eq;
+
+const INPUT: &str = "a\r\nb\r<p title=\"x\r\ny\rz\" alt=a\r\r\n>\r\n\r<!--\r\n-->\r";
+
+fn tokenize<'a, R: Reader>(input: impl Readable<'a, Reader = R>) -> Vec<Token> {
+    Tokenizer::new(input)
+        .map(|x| x.unwrap_or_else(|_| panic!("reader failed")))
+        .collect()
+}
+
+fn expected() -> Vec<Token> {
+    vec![
+        Token::String(b"a\nb\n".to_vec().into()),
+        Token::StartTag(StartTag {
+            self_closing: false,
+            name: b"p".to_vec().into(),
+            attributes: [
+                (b"title".to_vec().into(), b"x\ny\nz".to_vec().into()),
+                (b"alt".to_vec().into(), b"a".to_vec().into()),
+            ]
+            .into(),
+        }),
+        Token::String(b"\n\n".to_vec().into()),
+        Token::Comment(Comment::from(b"\n".to_vec())),
+        Token::String(b"\n".to_vec().into()),
+    ]
+}
+
+#[test]
+fn normalized() {
+    assert_eq!(tokenize(INPUT), expected());
+}
+
+/// Returns the data one byte at a time, so that `\r` and `\n` end up in different reads.
+struct ByteByByte<'a>(&'a [u8]);
+
+impl Read for ByteByByte<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.0.split_first() {
+            Some((&x, rest)) if !buf.is_empty() => {
+                buf[0] = x;
+                self.0 = rest;
+                Ok(1)
+            }
+            _ => Ok(0),
+        }
+    }
+}
+
+#[test]
+fn cr_at_chunk_boundary() {
+    assert_eq!(
+        tokenize(IoReader::new(ByteByByte(INPUT.as_bytes()))),
+        expected()
+    );
+
+    // with a small buffer, \r\n is split between buffer refills at every offset
+    for skip in 0..32 {
+        let input = format!("{}{}", "x".repeat(skip), INPUT);
+        let mut expected = expected();
+        if let Token::String(ref mut s) = expected[0] {
+            s.0.splice(0..0, "x".repeat(skip).into_bytes());
+        }
+        assert_eq!(
+            tokenize(IoReader::new_with_buffer_size::<32>(input.as_bytes())),
+            expected,
+            "{}",
+            skip
+        );
+    }
+}
@@ -0,0 +1,173 @@
+use html5gum::{DefaultEmitter, Error, NullHandling, Token, Tokenizer};
+use pretty_assertions::assert_eq;
+
+const POLICIES: [NullHandling; 3] = [
+    NullHandling::Spec,
+    NullHandling::Preserve,
+    NullHandling::Strip,
+];
+
+/// Tokenize `input`, and describe each token as a string. Instead of describing
+/// [`Error::UnexpectedNullCharacter`], return how often it was emitted.
+fn tokenize(input: &str, policy: NullHandling) -> (Vec<String>, usize) {
+    let mut emitter = DefaultEmitter::default();
+    emitter.naively_switch_states(true);
+    let mut tokenizer = Tokenizer::new_with_emitter(input, emitter);
+    tokenizer.null_handling(policy);
+
+    let s = |x: &[u8]| String::from_utf8(x.to_vec()).unwrap();
+    let mut tokens = Vec::new();
+    let mut errors = 0;
+    for token in tokenizer {
+        tokens.push(match token.unwrap() {
+            Token::StartTag(tag) => {
+                let mut attributes: Vec<_> = tag
+                    .attributes
+                    .iter()
+                    .map(|(name, value)| format!(" {}={}", s(name), s(value)))
+                    .collect();
+                attributes.sort();
+                format!("<{}{}>", s(&tag.name), attributes.concat())
+            }
+            Token::EndTag(tag) => format!("</{}>", s(&tag.name)),
+            Token::String(value) => s(&value),
+            Token::Comment(comment) => format!("<!--{}-->", s(&comment.value)),
+            Token::Doctype(doctype) => format!(
+                "<!DOCTYPE {} {:?}>",
+                s(&doctype.name),
+                doctype.public_identifier.as_deref().map(|x| s(x))
+            ),
+            Token::Error(Error::UnexpectedNullCharacter) => {
+                errors += 1;
+                continue;
+            }
+            Token::Error(error) => format!("{:?}", error),
+        });
+    }
+    (tokens, errors)
+}
+
+/// Check the tokens of `input` with each policy, with the same number of errors for all of them.
+fn check(input: &str, expected: [&[&str]; 3]) {
+    let expected_errors = input.matches('\0').count();
+    for (&policy, expected) in POLICIES.iter().zip(expected) {
+        let (tokens, errors) = tokenize(input, policy);
+        assert_eq!(tokens, expected, "{:?}", policy);
+        assert_eq!(errors, expected_errors, "{:?}", policy);
+    }
+}
+
+#[test]
+fn data() {
+    // the spec keeps NUL characters here
+    check("a\0b", [&["a\0b"], &["a\0b"], &["ab"]]);
+}
+
+#[test]
+fn rcdata() {
+    check(
+        "<title>a\0b</title>",
+        [
+            &["<title>", "a\u{fffd}b", "</title>"],
+            &["<title>", "a\0b", "</title>"],
+            &["<title>", "ab", "</title>"],
+        ],
+    );
+}
+
+#[test]
+fn script_data_and_plaintext() {
+    check(
+        "<script>\0<!--\0-\0--\0</script><plaintext>\0",
+        [
+            &[
+                "<script>",
+                "\u{fffd}<!--\u{fffd}-\u{fffd}--\u{fffd}",
+                "</script>",
+                "<plaintext>",
+                "\u{fffd}",
+            ],
+            &[
+                "<script>",
+                "\0<!--\0-\0--\0",
+                "</script>",
+                "<plaintext>",
+                "\0",
+            ],
+            &["<script>", "<!-----", "</script>", "<plaintext>"],
+        ],
+    );
+}
+
+#[test]
+fn attribute_value_double_quoted() {
+    check(
+        "<p title=\"a\0b\">",
+        [
+            &["<p title=a\u{fffd}b>"],
+            &["<p title=a\0b>"],
+            &["<p title=ab>"],
+        ],
+    );
+}
+
+#[test]
+fn attribute_values_and_names() {
+    check(
+        "<p a\0='\0' b=\0 c\0=x>",
+        [
+            &["<p a\u{fffd}=\u{fffd} b=\u{fffd} c\u{fffd}=x>"],
+            &["<p a\0=\0 b=\0 c\0=x>"],
+            &["<p a= b= c=x>"],
+        ],
+    );
+}
+
+#[test]
+fn tag_names() {
+    check(
+        "<a\0b></a\0b>",
+        [
+            &["<a\u{fffd}b>", "</a\u{fffd}b>"],
+            &["<a\0b>", "</a\0b>"],
+            &["<ab>", "</ab>"],
+        ],
+    );
+}
+
+#[test]
+fn comment() {
+    check(
+        "<!--a\0b--><!--\0-->",
+        [
+            &["<!--a\u{fffd}b-->", "<!--\u{fffd}-->"],
+            &["<!--a\0b-->", "<!--\0-->"],
+            &["<!--ab-->", "<!---->"],
+        ],
+    );
+}
+
+#[test]
+fn doctype() {
+    check(
+        "<!DOCTYPE \0a\0 PUBLIC \"\0\">",
+        [
+            &["<!DOCTYPE \u{fffd}a\u{fffd} Some(\"\u{fffd}\")>"],
+            &["<!DOCTYPE \0a\0 Some(\"\\0\")>"],
+            &["<!DOCTYPE a Some(\"\")>"],
+        ],
+    );
+}
+
+#[test]
+fn character_references_are_unaffected() {
+    for policy in POLICIES {
+        assert_eq!(
+            tokenize("&#0;", policy),
+            (
+                vec!["NullCharacterReference".to_owned(), "\u{fffd}".to_owned()],
+                0
+            )
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use html5gum::outline::{count_blocks, BlockCounts};
+use pretty_assertions::assert_eq;
+use scraper::{Html, Node};
+
+/// Documents that rely heavily on omitted tags.
+const FIXTURES: &[&str] = &[
+    "<p>a<p>b<p>c",
+    "<p>a<div>b</div>c<p>d",
+    "</p></p><p></p>",
+    "<p><button><p>a</button>b</p>",
+    "<ul><li>a<li>b<ul><li>c<li>d</ul><li>e</ul>",
+    "<ol><li>a<div><li>b</div></ol><li>c",
+    "<li>a<p>b<li>c",
+    "<dl><dt>a<dd>b<dt>c<dt>d<dd>e<div><dd>f</div></dl>",
+    "<table><td>a<td>b<tr><td>c<th>d</table>",
+    "<table><tr><td>a<tr><td>b<tbody><tr><td>c</tbody><tfoot><td>d</table>",
+    "<table><caption>a<td>b</table>",
+    "<table><td><table><td>a</table><td>b</table>",
+    "<td>a</td><tr><th>b</th></tr>",
+    "<table><td>a</td></tr><td>b</table>",
+    "<table><td><p>a<td><p>b<li>c</table><p>d",
+    "<!DOCTYPE html><p>a<table><td>b</table>",
+    "<!DOCTYPE html PUBLIC '-//W3C//DTD XHTML 1.0 Transitional//EN'><p>a<table><td>b</table>",
+    "<!DOCTYPE html PUBLIC '-//w3c//dtd html 4.01 transitional//en'><p>a<table><td>b</table>",
+    "<!DOCTYPE html SYSTEM 'about:legacy-compat'><p>a<table><td>b</table>",
+    "<p>a<table><td>b</table>c",
+    "<select><option>a<option>b<optgroup><option>c</select><option>d",
+    "<select><p>a<li>b<option>c</select>",
+    "<table><td><select><option>a<td>b</table>",
+    "<select><option>a<input><option>b",
+    "<p>a<svg><p>b</svg>c",
+    "<p>a<svg><li><foreignObject><p>b<li>c</foreignObject></svg>",
+    "<math><mi><li>a</mi><mo><p>b</math>",
+    "<svg><desc><p>a<tr><td>b</desc></svg>",
+    "<h1>a<h2>b</h1><p>c<h3>d<p>e",
+    "<p>a<hr>b<p>c<pre>d</pre>",
+    "<div><p>a</div>b</p>",
+    "<p>a<b>b<p>c</b>d",
+    "<script><p>a</script><style><li>b</style><p>c",
+    "<textarea><p>a</textarea><title><li>b</title><p>c",
+    "<ruby>a<rb>b<rt>c<rp>d</ruby><p>e",
+    "<p><form><p>a</form>b",
+];
+
+/// The counts of the elements in a tree built by html5ever.
+fn tree_counts(input: &str) -> BlockCounts {
+    let html = Html::parse_document(input);
+    let mut counts = BlockCounts::default();
+    for node in html.tree.root().descendants() {
+        let Node::Element(element) = node.value() else {
+            continue;
+        };
+
+        if &*element.name.ns != "http://www.w3.org/1999/xhtml" {
+            continue;
+        }
+
+        match element.name() {
+            "p" => counts.paragraphs += 1,
+            "li" => counts.list_items += 1,
+            "dt" => counts.description_terms += 1,
+            "dd" => counts.description_details += 1,
+            "tr" => counts.table_rows += 1,
+            "td" | "th" => counts.table_cells += 1,
+            "option" => counts.options += 1,
+            _ => {}
+        }
+    }
+    counts
+}
+
+fn counts(input: &str) -> BlockCounts {
+    let Ok(counts) = count_blocks(input);
+    counts
+}
+
+#[test]
+fn same_as_tree_builder() {
+    for input in FIXTURES {
+        let expected = tree_counts(input);
+        let actual = BlockCounts {
+            text_blocks: 0,
+            ..counts(input)
+        };
+        assert_eq!(actual, expected, "input: {:?}", input);
+    }
+}
+
+#[test]
+fn text_blocks() {
+    for (input, expected) in [
+        ("", 0),
+        (" \n ", 0),
+        ("a", 1),
+        ("a<b>b</b>c", 1),
+        ("a<br>b", 2),
+        ("<p>a<p>b", 2),
+        ("<p>a</p> \n <p>b", 2),
+        ("<div>a<span>b</span></div>c", 2),
+        ("<ul><li>a<li> <li>b</ul>", 2),
+        ("<script>a</script><style>b</style><title>c</title>", 0),
+        ("a<script>b</script>c", 1),
+        ("<table><td>a<td>b</table>", 2),
+        ("<select><option>a<option>b</select>", 2),
+    ] {
+        assert_eq!(counts(input).text_blocks, expected, "input: {:?}", input);
+    }
+}
+
+#[test]
+fn fixture() {
+    let input = "<!DOCTYPE html>
+<title>Fixture</title>
+<p>First paragraph
+<p>Second paragraph, with a list:
+<ul>
+  <li>one
+  <li>two
+</ul>
+<dl><dt>term<dd>details<dd>more details</dl>
+<table>
+  <tr><th>a<th>b
+  <tr><td>1<td>2
+  <td>3
+</table>
+<select><option>x<option selected>y</select>
+</p>";
+
+    assert_eq!(
+        counts(input),
+        BlockCounts {
+            paragraphs: 3,
+            list_items: 2,
+            description_terms: 1,
+            description_details: 2,
+            table_rows: 2,
+            table_cells: 5,
+            options: 2,
+            text_blocks: 14,
+        }
+    );
+    assert_eq!(
+        tree_counts(input),
+        BlockCounts {
+            text_blocks: 0,
+            ..counts(input)
+        }
+    );
+}
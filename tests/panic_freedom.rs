@@ -0,0 +1,60 @@
+//! The tokenizer must not panic on any input, with any emitter or option. This runs the same
+//! checks as the fuzz target in `fuzz/` over the inputs of all test suites.
+use std::fs::{self, File};
+use std::io::BufReader;
+
+use glob::glob;
+use html5gum::reader::conformance::DOCUMENTS;
+use html5gum::testing::exhaustive;
+use html5gum::testing::html5lib::TestFile;
+use proptest::prelude::*;
+
+#[test]
+fn tokenizer_tests() {
+    let mut count = 0;
+    for path in glob("tests/*html5lib-tests/tokenizer/*.test").unwrap() {
+        let path = path.unwrap();
+        let TestFile { tests } =
+            serde_json::from_reader(BufReader::new(File::open(&path).unwrap()))
+                .unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        for test in tests {
+            exhaustive::run(&test.input);
+            count += 1;
+        }
+    }
+    assert!(count > 0);
+}
+
+#[test]
+fn tree_construction_tests() {
+    let mut count = 0;
+    for path in glob("tests/*html5lib-tests/tree-construction/*.dat").unwrap() {
+        let content = fs::read_to_string(path.unwrap()).unwrap();
+        for test in content.split("\n#data\n") {
+            let test = test.strip_prefix("#data\n").unwrap_or(test);
+            let data = test.find("\n#errors").map_or(test, |end| &test[..end]);
+            exhaustive::run(data.as_bytes());
+            count += 1;
+        }
+    }
+    assert!(count > 0);
+}
+
+#[test]
+fn conformance_documents() {
+    // every prefix, so that every kind of token is cut off at the end of the input
+    for document in DOCUMENTS {
+        for end in 0..=document.len() {
+            exhaustive::run(&document[..end]);
+        }
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn arbitrary_bytes(input in prop::collection::vec(any::<u8>(), 0..256)) {
+        exhaustive::run(&input);
+    }
+}
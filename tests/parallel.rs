@@ -0,0 +1,124 @@
+use html5gum::parallel::find_split_points;
+use html5gum::Token;
+use pretty_assertions::assert_eq;
+
+mod testutils;
+
+fn tokenize_split(input: &[u8], points: &[usize]) -> Vec<Token> {
+    let mut rv = Vec::new();
+    let mut start = 0;
+    for &end in points.iter().chain(Some(&input.len())) {
+        assert!(start < end || input.is_empty());
+        rv.extend(testutils::tokenize(&input[start..end]));
+        start = end;
+    }
+    rv
+}
+
+fn assert_split_equivalent(input: &[u8], n: usize) -> Vec<usize> {
+    let points = find_split_points(input, n);
+    assert!(points.len() < n.max(1));
+    assert_eq!(tokenize_split(input, &points), testutils::tokenize(input));
+    points
+}
+
+fn generated_document(records: usize) -> String {
+    let mut rv = String::from("<!DOCTYPE html><html><head><title>export</title></head><body>");
+    for i in 0..records {
+        rv.push_str(&format!(
+            "<div class=\"record\" data-id={i} title='a > b'><h2>Record &amp; {i}</h2>\
+             <p>some <b>bold</b> text &lt;not a tag&gt; a > b</p>",
+        ));
+        match i % 5 {
+            0 => rv.push_str("<!-- <p> commented out </p> -->"),
+            1 => rv.push_str("<script>if (a<b) { x = '<div>'; }</script>"),
+            2 => rv.push_str("<textarea><p>not a tag</p></textarea>"),
+            3 => rv.push_str("<style>p > b { color: red }</style>"),
+            _ => {}
+        }
+        rv.push_str("</div>\n");
+    }
+    rv.push_str("</body></html>");
+    rv
+}
+
+#[test]
+fn generated() {
+    let input = generated_document(1000);
+    let points = assert_split_equivalent(input.as_bytes(), 16);
+    assert_eq!(points.len(), 15);
+}
+
+#[test]
+fn small_inputs() {
+    for input in [
+        &b""[..],
+        b"<",
+        b"<a>",
+        b"<a></a>",
+        b"hello",
+        b"<a><b></b></a>",
+    ] {
+        for n in 0..4 {
+            assert_split_equivalent(input, n);
+        }
+    }
+}
+
+#[test]
+fn tokenizer_testcases() {
+    for entry in glob::glob("tests/custom-html5lib-tests/tokenizer/*.test").unwrap() {
+        let testcases: serde_json::Value =
+            serde_json::from_reader(std::fs::File::open(entry.unwrap()).unwrap()).unwrap();
+        for testcase in testcases["tests"].as_array().unwrap() {
+            let input = testcase["input"].as_str().unwrap().repeat(100);
+            assert_split_equivalent(input.as_bytes(), 8);
+        }
+    }
+}
+
+#[test]
+fn huge_script() {
+    let mut input = String::from("<html><body><script>");
+    for i in 0..10_000 {
+        input.push_str(&format!("document.write('<div id={i}></div>');\n"));
+    }
+    input.push_str("</script></body></html>");
+    let script_end = input.find("</script>").unwrap();
+    let points = assert_split_equivalent(input.as_bytes(), 8);
+    assert!(points.iter().all(|&point| point > script_end));
+}
+
+#[test]
+fn escaped_script() {
+    // `</script>` ends the script even after `<!--`, unless there is a `<script>` in between
+    let input = format!(
+        "<script><!--{}</script><style>--></script><p>a</style>",
+        "a".repeat(100)
+    );
+    assert_split_equivalent(input.as_bytes(), 2);
+
+    for script in [
+        "<!--</script>",
+        "<!--<script></script>--></script>",
+        "<!--<script></script></script>",
+        "<!--<script>--></script>",
+        "<!--<SCRIPT/></Script >-></script>",
+        "<!--<scripts></script>",
+        "<!--<sc<script></script>",
+        "<!-->--></script>",
+        "<!--->--></script>",
+        "<!-x--></script>",
+        "<!--<script>-<!-->--></script>",
+        "<!--<script></scripty>--></script>",
+        "<!--</scrip--></script>",
+    ] {
+        let input = format!(
+            "{}<style>--></script><p>a</style><b>b</b>",
+            format!("<p><script>{}<i>x</i></p>", script).repeat(20)
+        );
+        for n in 2..10 {
+            assert_split_equivalent(input.as_bytes(), n);
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use html5gum::emitters::callback::{CallbackEmitter, CallbackEvent};
+use html5gum::{Error, Token, Tokenizer};
+use pretty_assertions::assert_eq;
+
+/// Tokenize `input` with a [`CallbackEmitter`], and describe the events that have a name or value.
+fn events(input: &str, preserve_case: bool, pair_attributes: bool) -> Vec<String> {
+    let mut emitter = CallbackEmitter::new(|event: CallbackEvent<'_>| {
+        let s = |x: &[u8]| String::from_utf8_lossy(x).into_owned();
+        Some(match event {
+            CallbackEvent::OpenStartTag { name } => format!("<{}", s(name)),
+            CallbackEvent::AttributeName { name } => format!("name {}", s(name)),
+            CallbackEvent::AttributeValue { value, .. } => format!("value {}", s(value)),
+            CallbackEvent::Attribute { name, value, .. } => {
+                format!("{}={:?}", s(name), value.map(s))
+            }
+            CallbackEvent::EndTag { name } => format!("</{}", s(name)),
+            CallbackEvent::String { value } => s(value),
+            CallbackEvent::Error(error) => format!("{:?}", error),
+            _ => return None,
+        })
+    });
+    emitter.preserve_case(preserve_case);
+    emitter.pair_attributes(pair_attributes);
+    emitter.naively_switch_states(true);
+    Tokenizer::new_with_emitter(input, emitter)
+        .map(Result::unwrap)
+        .collect()
+}
+
+#[test]
+fn tags_and_attributes() {
+    let input = "<DIV Class=\"X\" data-FOO>a</Div>";
+    assert_eq!(
+        events(input, true, false),
+        [
+            "<DIV",
+            "name Class",
+            "value X",
+            "name data-FOO",
+            "a",
+            "</Div"
+        ]
+    );
+    assert_eq!(
+        events(input, false, false),
+        [
+            "<div",
+            "name class",
+            "value X",
+            "name data-foo",
+            "a",
+            "</div"
+        ]
+    );
+    assert_eq!(
+        events(input, true, true),
+        ["<DIV", "Class=Some(\"X\")", "data-FOO=None", "a", "</Div"]
+    );
+}
+
+#[test]
+fn duplicate_attributes() {
+    let duplicate = format!("{:?}", Error::DuplicateAttribute);
+    assert_eq!(
+        events("<a HREF=1 href=2 Href>", true, false),
+        ["<a", "name HREF", "value 1", &duplicate, &duplicate]
+    );
+    assert_eq!(
+        events("<a HREF=1 href=2>", true, true),
+        ["<a", "HREF=Some(\"1\")", &duplicate, "href=Some(\"2\")"]
+    );
+}
+
+#[test]
+fn appropriate_end_tags() {
+    // the end tag is found regardless of its case, but not in the case of other tags
+    assert_eq!(
+        events(
+            "<SCRIPT>a</b></scripT><TITLE>b</TITLEx></Title>",
+            true,
+            false
+        ),
+        [
+            "<SCRIPT",
+            "a</b>",
+            "</scripT",
+            "<TITLE",
+            "b</TITLEx>",
+            "</Title"
+        ]
+    );
+    assert_eq!(
+        events("<SCRIPT>a</b></scripT>", false, false),
+        ["<script", "a</b>", "</script"]
+    );
+}
+
+#[test]
+fn null_characters() {
+    let null = format!("{:?}", Error::UnexpectedNullCharacter);
+    assert_eq!(
+        events("<A\0b C\0d>", true, false),
+        [&null, "<A\u{fffd}b", &null, "name C\u{fffd}d"]
+    );
+}
+
+#[test]
+fn default_emitter_lowercases() {
+    let mut tokenizer = Tokenizer::new("<DIV Class=x>");
+    let Some(Ok(Token::StartTag(tag))) = tokenizer.next() else {
+        panic!();
+    };
+    assert_eq!(tag.name, b"div".as_slice());
+    assert!(tag.attributes.contains_key(b"class".as_slice()));
+}
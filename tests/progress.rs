@@ -0,0 +1,78 @@
+use std::fs::{self, File};
+
+use html5gum::{IoReader, Readable, Reader, Tokenizer};
+use pretty_assertions::assert_eq;
+
+fn document() -> String {
+    let mut html = String::from("<!DOCTYPE html><title>a &amp; b</title>\r\n");
+    while html.len() < 10 * 1024 {
+        html.push_str("<p class=x>hello<br/>&#x41; world</p><!-- c -->\r\n");
+    }
+    html
+}
+
+/// Return the progress before the first and after every token.
+fn progress<R: Reader>(mut tokenizer: Tokenizer<R>) -> Vec<f32> {
+    let mut rv = vec![tokenizer.progress().unwrap()];
+    while tokenizer.next().is_some() {
+        rv.push(tokenizer.progress().unwrap());
+    }
+    rv
+}
+
+fn assert_monotonic(progress: &[f32]) {
+    assert!(progress.len() > 100, "{}", progress.len());
+    assert_eq!(progress[0], 0.0);
+    for window in progress.windows(2) {
+        assert!(window[0] <= window[1], "{:?}", window);
+    }
+    assert_eq!(progress.last(), Some(&1.0));
+}
+
+#[test]
+fn string() {
+    let html = document();
+    assert_eq!(
+        html.to_reader().size_hint(),
+        (html.len() as u64, Some(html.len() as u64))
+    );
+    assert_monotonic(&progress(Tokenizer::new(&html)));
+}
+
+#[test]
+fn file() {
+    let html = document();
+    let path = std::env::temp_dir().join(format!("html5gum-progress-{}.html", std::process::id()));
+    fs::write(&path, &html).unwrap();
+    let progress = progress(Tokenizer::new(File::open(&path).unwrap()));
+    fs::remove_file(&path).unwrap();
+    assert_monotonic(&progress);
+}
+
+#[test]
+fn io_reader() {
+    let html = document();
+    let reader = IoReader::new_with_buffer_size::<64>(html.as_bytes());
+    assert_eq!(reader.size_hint(), (0, None));
+    assert_eq!(Tokenizer::new(reader).progress(), None);
+
+    let reader =
+        IoReader::new_with_buffer_size::<64>(html.as_bytes()).with_input_len(html.len() as u64);
+    assert_monotonic(&progress(Tokenizer::new(reader)));
+}
+
+#[test]
+fn take_bytes() {
+    let html = document();
+    let mut tokenizer = Tokenizer::new(&html);
+    tokenizer.take_bytes(1000);
+    assert_eq!(tokenizer.progress(), Some(0.0));
+    tokenizer.by_ref().for_each(drop);
+    assert_eq!(tokenizer.consumed_bytes(), 1000);
+    assert_eq!(tokenizer.progress(), Some(1.0));
+}
+
+#[test]
+fn empty() {
+    assert_eq!(Tokenizer::new("").progress(), Some(1.0));
+}
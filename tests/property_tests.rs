@@ -0,0 +1,267 @@
+//! Randomized tests for invariants that should hold for any input.
+//!
+//! The fuzzers in `fuzz/` do a more thorough job, but they do not run as part of `cargo test`.
+use std::convert::Infallible;
+
+use html5gum::emitters::callback::{CallbackEmitter, CallbackEvent};
+use html5gum::emitters::validate::ValidateEmitter;
+use html5gum::testutils::DebugValidateEmitter;
+use html5gum::{Comment, DefaultEmitter, Doctype, EndTag, Span, StartTag, Token, Tokenizer};
+use proptest::prelude::*;
+
+/// Arbitrary bytes, biased towards the ones that are interesting to the tokenizer so that
+/// failures shrink into something readable.
+fn html_bytes() -> impl Strategy<Value = Vec<u8>> {
+    let interesting = prop::sample::select(&b"<>/!?-=&#;'\" \t\n\r\0abcxyzABCXYZ019[]"[..]);
+    prop::collection::vec(prop_oneof![4 => interesting, 1 => any::<u8>()], 0..2048)
+}
+
+/// Like `html_bytes`, but restricted to HTML-ish words so that tags and attributes are generated
+/// more often.
+fn html_words() -> impl Strategy<Value = Vec<u8>> {
+    let word = prop::sample::select(vec![
+        "<",
+        ">",
+        "</",
+        "/>",
+        "<!--",
+        "-->",
+        "<!DOCTYPE ",
+        "<![CDATA[",
+        "]]>",
+        "&amp;",
+        "&#x",
+        "&",
+        ";",
+        "=",
+        "\"",
+        "'",
+        " ",
+        "\r\n",
+        "\r",
+        "div",
+        "DIV",
+        "script",
+        "a",
+        "B",
+        "href",
+        "x",
+        "title",
+        "plaintext",
+        "--!>",
+        "/",
+        "\u{1F600}",
+        "\0",
+    ]);
+    prop::collection::vec(word, 0..256).prop_map(|words| words.concat().into_bytes())
+}
+
+fn default_tokens(input: &[u8]) -> Vec<Token> {
+    let emitter = DebugValidateEmitter::new(DefaultEmitter::default());
+    Tokenizer::new_with_emitter(input, emitter)
+        .flatten()
+        .collect()
+}
+
+/// Build [`Token`]s from the events of a [`CallbackEmitter`], without using [`html5gum::DefaultEmitter`].
+fn callback_tokens(input: &[u8], pair_attributes: bool) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut start_tag = StartTag::default();
+    let mut attribute_name = None;
+
+    let mut emitter = CallbackEmitter::new(|event: CallbackEvent<'_>| -> Option<Infallible> {
+        match event {
+            CallbackEvent::OpenStartTag { name } => {
+                start_tag = StartTag {
+                    name: name.to_vec().into(),
+                    ..Default::default()
+                };
+                attribute_name = None;
+            }
+            CallbackEvent::AttributeName { name } => {
+                // duplicates are reported as errors by the emitter itself
+                assert!(!start_tag.attributes.contains_key(name));
+                start_tag
+                    .attributes
+                    .insert(name.to_vec().into(), Default::default());
+                attribute_name = Some(name.to_vec());
+            }
+            CallbackEvent::AttributeValue { value, .. } => {
+                if let Some(ref name) = attribute_name {
+                    start_tag
+                        .attributes
+                        .get_mut(name.as_slice())
+                        .unwrap()
+                        .extend(value);
+                }
+            }
+            CallbackEvent::Attribute { name, value, .. } => {
+                // duplicates are reported, but the first occurrence wins
+                if !start_tag.attributes.contains_key(name) {
+                    start_tag.attributes.insert(
+                        name.to_vec().into(),
+                        value.unwrap_or_default().to_vec().into(),
+                    );
+                }
+            }
+            CallbackEvent::CloseStartTag { self_closing, .. } => {
+                start_tag.self_closing = self_closing;
+                tokens.push(Token::StartTag(std::mem::take(&mut start_tag)));
+            }
+            CallbackEvent::EndTag { name } => {
+                start_tag.attributes.clear();
+                tokens.push(Token::EndTag(EndTag {
+                    name: name.to_vec().into(),
+                }));
+            }
+            CallbackEvent::String { value } => tokens.push(Token::String(value.to_vec().into())),
+            CallbackEvent::Comment { value, kind } => tokens.push(Token::Comment(Comment {
+                value: value.to_vec().into(),
+                kind,
+            })),
+            CallbackEvent::Doctype {
+                name,
+                public_identifier,
+                system_identifier,
+                force_quirks,
+            } => tokens.push(Token::Doctype(Doctype {
+                force_quirks,
+                name: name.to_vec().into(),
+                public_identifier: public_identifier.map(|x| x.to_vec().into()),
+                system_identifier: system_identifier.map(|x| x.to_vec().into()),
+            })),
+            CallbackEvent::Error(error) => tokens.push(Token::Error(error)),
+            CallbackEvent::CdataStart | CallbackEvent::CdataEnd => {
+                unreachable!("mark_cdata_sections is off")
+            }
+        }
+        None
+    });
+
+    emitter.pair_attributes(pair_attributes);
+    Tokenizer::new_with_emitter(input, emitter)
+        .finish()
+        .unwrap();
+    tokens
+}
+
+fn check_invariants(input: &[u8]) {
+    let tokens = default_tokens(input);
+
+    // CR is normalized away during input stream preprocessing, but numeric character references
+    // such as `&#xd;` can still produce one.
+    let cr_allowed = input.windows(2).any(|w| w == b"&#");
+    let check_cr = |s: &[u8]| assert!(cr_allowed || !s.contains(&b'\r'), "CR in {:?}", s);
+
+    for token in &tokens {
+        match token {
+            Token::String(s) => {
+                check_cr(s);
+            }
+            Token::Comment(comment) => {
+                check_cr(&comment.value);
+            }
+            Token::StartTag(tag) => {
+                assert!(!tag.name.is_empty());
+                check_cr(&tag.name);
+                if input.is_ascii() {
+                    assert!(!tag.name.iter().any(u8::is_ascii_uppercase));
+                }
+                for (name, value) in &tag.attributes {
+                    check_cr(name);
+                    check_cr(value);
+                }
+            }
+            Token::EndTag(tag) => {
+                assert!(!tag.name.is_empty());
+                if input.is_ascii() {
+                    assert!(!tag.name.iter().any(u8::is_ascii_uppercase));
+                }
+            }
+            Token::Doctype(_) | Token::Error(_) => {}
+        }
+    }
+
+    // consecutive character tokens are always folded into one string
+    assert!(!tokens
+        .windows(2)
+        .any(|pair| matches!(pair, [Token::String(_), Token::String(_)])));
+
+    assert_eq!(tokens, callback_tokens(input, false));
+
+    // with paired attributes, duplicate attributes are reported a bit later
+    let without_errors = |tokens: Vec<Token>| {
+        tokens
+            .into_iter()
+            .filter(|token| !matches!(token, Token::Error(_)))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        without_errors(tokens),
+        without_errors(callback_tokens(input, true))
+    );
+}
+
+proptest! {
+    #[test]
+    fn invariants_arbitrary_bytes(input in html_bytes()) {
+        check_invariants(&input);
+    }
+
+    #[test]
+    fn invariants_html_words(input in html_words()) {
+        check_invariants(&input);
+    }
+
+    /// The spans of the callback emitter, and of the `DefaultEmitter` built on top of it, satisfy
+    /// the same invariants as the span fuzzer checks.
+    #[test]
+    #[cfg(feature = "testing")]
+    fn span_invariants_arbitrary_bytes(input in html_bytes()) {
+        let result = html5gum::testing::spans::check_span_invariants(&input);
+        prop_assert!(result.is_ok(), "{}", result.unwrap_err());
+    }
+
+    #[test]
+    #[cfg(feature = "testing")]
+    fn span_invariants_html_words(input in html_words()) {
+        let result = html5gum::testing::spans::check_span_invariants(&input);
+        prop_assert!(result.is_ok(), "{}", result.unwrap_err());
+    }
+
+    #[test]
+    fn validate_emitter_errors(input in html_words()) {
+        let mut emitter = DefaultEmitter::<(Token, Span)>::with_spans();
+        emitter.naively_switch_states(true);
+        let expected: Vec<_> = Tokenizer::new_with_emitter(&input, emitter)
+            .flatten()
+            .filter_map(|(token, span)| match token {
+                Token::Error(error) => Some((error, span)),
+                _ => None,
+            })
+            .collect();
+
+        let emitter = DebugValidateEmitter::new(ValidateEmitter::new());
+        let errors: Vec<_> = Tokenizer::new_with_emitter(&input, emitter).flatten().collect();
+        prop_assert_eq!(errors, expected);
+    }
+
+    #[test]
+    fn parallel_split_points(input in html_words(), n in 1usize..8) {
+        let tokenize = |input: &[u8]| {
+            let mut emitter = DefaultEmitter::default();
+            emitter.naively_switch_states(true);
+            Tokenizer::new_with_emitter(input, emitter).flatten().collect::<Vec<_>>()
+        };
+
+        let mut split_tokens = Vec::new();
+        let mut start = 0;
+        for end in html5gum::parallel::find_split_points(&input, n) {
+            split_tokens.extend(tokenize(&input[start..end]));
+            start = end;
+        }
+        split_tokens.extend(tokenize(&input[start..]));
+
+        prop_assert_eq!(split_tokens, tokenize(&input));
+    }
+}
@@ -0,0 +1,112 @@
+use std::convert::Infallible;
+
+use html5gum::emitters::callback::{CallbackEmitter, CallbackEvent};
+use html5gum::push::PushTokenizer;
+use html5gum::{DefaultEmitter, Span, Token, Tokenizer};
+use pretty_assertions::assert_eq;
+
+mod testutils;
+
+fn emitter() -> DefaultEmitter<(Token, Span)> {
+    let mut emitter = DefaultEmitter::with_spans();
+    emitter.naively_switch_states(true);
+    emitter
+}
+
+/// Push the input in two chunks split at every byte, and compare with a straight run.
+fn check(input: &[u8]) {
+    let expected: Vec<_> = Tokenizer::new_with_emitter(input, emitter())
+        .flatten()
+        .collect();
+
+    for i in 0..=input.len() {
+        let mut tokenizer = PushTokenizer::new(emitter());
+        let mut tokens: Vec<_> = tokenizer.push(&input[..i]).collect();
+        tokens.extend(tokenizer.push(&input[i..]));
+        tokens.extend(tokenizer.finish());
+        assert_eq!(
+            tokens,
+            expected,
+            "{:?} split at {}",
+            String::from_utf8_lossy(input),
+            i
+        );
+    }
+}
+
+#[test]
+fn html5lib_inputs() {
+    for input in testutils::html5lib_inputs() {
+        check(input.as_bytes());
+    }
+}
+
+#[test]
+fn byte_by_byte() {
+    let input = br#"<!DOCTYPE html>
+<title>a <b> title</title>
+<script>if (a < b) { document.write("<!--<script></script>") }</script>
+<p class="main" data-x='>'>hello &amp; &notin &#x41; <![CDATA[x]]>
+<svg><![CDATA[y]]></svg><!-- a comment --><b"#;
+    let expected: Vec<_> = Tokenizer::new_with_emitter(&input[..], emitter())
+        .flatten()
+        .collect();
+
+    let mut tokenizer = PushTokenizer::new(emitter());
+    let mut tokens = Vec::new();
+    for byte in input.chunks(1) {
+        tokens.extend(tokenizer.push(byte));
+    }
+    tokens.extend(tokenizer.finish());
+    assert_eq!(tokens, expected);
+}
+
+#[test]
+fn tokens_are_returned_when_complete() {
+    let mut tokenizer = PushTokenizer::new(DefaultEmitter::default());
+    assert_eq!(tokenizer.push(b"<p>hel").count(), 1);
+    assert_eq!(tokenizer.push(b"lo <!DOC").count(), 0);
+    let mut expected = vec![Token::String(b"hello ".to_vec().into())];
+    expected.extend(Tokenizer::new("<!DOCTYPE html>").flatten());
+    assert_eq!(tokenizer.push(b"TYPE html>").collect::<Vec<_>>(), expected);
+    assert_eq!(tokenizer.push(b"").count(), 0);
+    assert_eq!(tokenizer.finish().collect::<Vec<_>>(), Vec::<Token>::new());
+}
+
+#[test]
+fn callback_emitter() {
+    let mut events = Vec::new();
+    let emitter = CallbackEmitter::new(|event: CallbackEvent<'_>| -> Option<Infallible> {
+        if let CallbackEvent::String { value } = event {
+            events.push(String::from_utf8(value.to_vec()).unwrap());
+        }
+        None
+    });
+    let mut tokenizer = PushTokenizer::new(emitter);
+    for chunk in ["a<b>c", "&a", "mp;d</b", ">e"] {
+        tokenizer.push(chunk.as_bytes()).for_each(drop);
+    }
+    tokenizer.finish().for_each(drop);
+    assert_eq!(events, ["a", "c&d", "e"]);
+}
+
+#[test]
+fn long_text_is_not_tokenized_on_every_push() {
+    let mut tokenizer = PushTokenizer::new(DefaultEmitter::default());
+    assert_eq!(tokenizer.push(b"<p>").count(), 1);
+    tokenizer.push(&[b'a'; 400]).for_each(drop);
+    // too little input to tokenize the kept text again
+    assert_eq!(tokenizer.push(b"<br>").count(), 0);
+    let expected = vec![
+        Token::String(vec![b'a'; 400].into()),
+        Token::StartTag(html5gum::StartTag {
+            name: b"br".to_vec().into(),
+            ..Default::default()
+        }),
+    ];
+    assert_eq!(tokenizer.push(&[b' '; 100]).collect::<Vec<_>>(), expected);
+    assert_eq!(
+        tokenizer.finish().collect::<Vec<_>>(),
+        vec![Token::String(vec![b' '; 100].into())]
+    );
+}
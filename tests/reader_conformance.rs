@@ -0,0 +1,110 @@
+use std::fs;
+use std::io::{self, Read};
+
+use html5gum::reader::conformance::{check, check_documents, DOCUMENTS};
+use html5gum::testutils::SlowReader;
+use html5gum::{CharIterReader, IoReader, Readable, SliceIterReader};
+
+use glob::glob;
+
+#[test]
+fn string_reader() {
+    check(|input| input.to_reader());
+    check(|input| SlowReader(input.to_reader()));
+}
+
+/// Returns one byte per call, and fails with `Interrupted` every `interrupt_every` calls.
+struct AdversarialRead {
+    input: &'static [u8],
+    interrupt_every: Option<usize>,
+    calls: usize,
+}
+
+impl AdversarialRead {
+    fn new(input: &'static [u8], interrupt_every: Option<usize>) -> Self {
+        AdversarialRead {
+            input,
+            interrupt_every,
+            calls: 0,
+        }
+    }
+}
+
+impl Read for AdversarialRead {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.calls += 1;
+        if self
+            .interrupt_every
+            .is_some_and(|n| self.calls.is_multiple_of(n))
+        {
+            return Err(io::ErrorKind::Interrupted.into());
+        }
+
+        let len = buf.len().min(self.input.len()).min(1);
+        buf[..len].copy_from_slice(&self.input[..len]);
+        self.input = &self.input[len..];
+        Ok(len)
+    }
+}
+
+#[test]
+fn io_reader() {
+    check(IoReader::new);
+    check(IoReader::new_with_buffer_size::<32>);
+    for capacity in [0, 32, 33, 100] {
+        check(|input| IoReader::with_capacity(input, capacity));
+    }
+}
+
+#[test]
+fn io_reader_adversarial() {
+    for capacity in [32, 16384] {
+        check(|input| IoReader::with_capacity(AdversarialRead::new(input, None), capacity));
+        for n in [2, 3, 7] {
+            check(|input| IoReader::with_capacity(AdversarialRead::new(input, Some(n)), capacity));
+        }
+    }
+}
+
+#[test]
+fn char_iter_reader() {
+    // only valid UTF-8 can be represented as chars
+    check_documents(
+        |input| CharIterReader::new(std::str::from_utf8(input).unwrap().chars()),
+        DOCUMENTS
+            .iter()
+            .copied()
+            .filter(|input| std::str::from_utf8(input).is_ok()),
+    );
+}
+
+#[test]
+fn slice_iter_reader() {
+    for chunk_size in [1, 2, 3, 7, 64] {
+        check(|input| SliceIterReader::new(input.chunks(chunk_size)));
+    }
+}
+
+#[test]
+fn html5lib_corpus() {
+    let mut documents = Vec::new();
+    let paths = glob("tests/html5lib-tests/tokenizer/*.test")
+        .unwrap()
+        .chain(glob("tests/custom-html5lib-tests/tokenizer/*.test").unwrap());
+    for path in paths {
+        let tests: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(path.unwrap()).unwrap()).unwrap();
+        for test in tests["tests"].as_array().into_iter().flatten() {
+            // escaped inputs contain lone surrogates, which the other tests take care of
+            if test["doubleEscaped"].as_bool() != Some(true) {
+                documents.push(test["input"].as_str().unwrap().to_owned());
+            }
+        }
+    }
+
+    assert!(!documents.is_empty());
+    check_documents(
+        |input| input.to_reader(),
+        documents.iter().map(String::as_bytes),
+    );
+}
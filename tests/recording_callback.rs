@@ -0,0 +1,191 @@
+use html5gum::emitters::callback::{CallbackEmitter, OwnedCallbackEvent, RecordingCallback};
+use html5gum::{
+    AttributeValueSyntax, ContentStateSwitcher, DefaultEmitter, Error, Span, Token, Tokenizer,
+};
+use pretty_assertions::assert_eq;
+
+fn record(
+    input: &str,
+    configure: impl FnOnce(&mut CallbackEmitter<RecordingCallback>),
+) -> Vec<(OwnedCallbackEvent, Span)> {
+    let mut emitter = CallbackEmitter::new(RecordingCallback::default());
+    emitter.naively_switch_states(true);
+    configure(&mut emitter);
+    let mut tokenizer = Tokenizer::new_with_emitter(input, emitter);
+    for _ in tokenizer.by_ref() {}
+    tokenizer.emitter().callback().events().to_vec()
+}
+
+/// Events that end a token have the same span as the token in [`DefaultEmitter::with_spans`].
+#[test]
+fn same_spans_as_default_emitter() {
+    for input in [
+        "<a href=x>hi</a>",
+        "hello",
+        "<!DOCTYPE html><!-- c --><p class='a' id=\"b\" hidden>x &amp; y<br/>z",
+        "<script>a</b></script><title>&lt;</title>trailing",
+        "<p>\r\n</p\r\n>\r\n<!--",
+        "</p a=b><?xml?>",
+        "<a a=1 a=2 b>",
+    ] {
+        let events = record(input, |_| {});
+        let recorded: Vec<Span> = events
+            .iter()
+            .filter(|(event, _)| {
+                matches!(
+                    event,
+                    OwnedCallbackEvent::CloseStartTag { .. }
+                        | OwnedCallbackEvent::EndTag { .. }
+                        | OwnedCallbackEvent::String { .. }
+                        | OwnedCallbackEvent::Comment { .. }
+                        | OwnedCallbackEvent::Doctype { .. }
+                )
+            })
+            .map(|&(_, span)| span)
+            .collect();
+
+        let mut emitter = DefaultEmitter::with_spans();
+        emitter.naively_switch_states(true);
+        let expected: Vec<Span> = Tokenizer::new_with_emitter(input, emitter)
+            .flatten()
+            .filter(|(token, _)| !matches!(token, Token::Error(_)))
+            .map(|(_, span)| span)
+            .collect();
+
+        assert_eq!(recorded, expected, "{:?}", input);
+    }
+}
+
+#[test]
+fn paired_attributes_and_errors() {
+    let events = record("<a b='c' b d>", |emitter| emitter.pair_attributes(true));
+    assert_eq!(
+        events,
+        [
+            (
+                OwnedCallbackEvent::OpenStartTag {
+                    name: b"a".to_vec()
+                },
+                Span { start: 0, end: 4 }
+            ),
+            (
+                OwnedCallbackEvent::Attribute {
+                    name: b"b".to_vec(),
+                    value: Some(b"c".to_vec()),
+                    syntax: AttributeValueSyntax::SingleQuoted,
+                },
+                Span { start: 6, end: 7 }
+            ),
+            (
+                OwnedCallbackEvent::Error(Error::DuplicateAttribute),
+                Span { start: 12, end: 12 }
+            ),
+            (
+                OwnedCallbackEvent::Attribute {
+                    name: b"b".to_vec(),
+                    value: None,
+                    syntax: AttributeValueSyntax::Empty,
+                },
+                Span { start: 0, end: 12 }
+            ),
+            (
+                OwnedCallbackEvent::Attribute {
+                    name: b"d".to_vec(),
+                    value: None,
+                    syntax: AttributeValueSyntax::Empty,
+                },
+                Span { start: 0, end: 13 }
+            ),
+            (
+                OwnedCallbackEvent::CloseStartTag {
+                    self_closing: false,
+                    is_void: false,
+                },
+                Span { start: 0, end: 13 }
+            ),
+        ]
+    );
+}
+
+#[test]
+fn cdata() {
+    let input = "<svg><![CDATA[a<b]]>c</svg>";
+    let events = record(input, |emitter| {
+        emitter.mark_cdata_sections(true);
+        emitter.switch_states(Some(ContentStateSwitcher::default()));
+    });
+    let pieces: Vec<_> = events
+        .iter()
+        .map(|(event, span)| (event.clone(), &input[span.start..span.end]))
+        .collect();
+    assert_eq!(
+        pieces,
+        [
+            (
+                OwnedCallbackEvent::OpenStartTag {
+                    name: b"svg".to_vec()
+                },
+                "<svg>"
+            ),
+            (
+                OwnedCallbackEvent::CloseStartTag {
+                    self_closing: false,
+                    is_void: false
+                },
+                "<svg>"
+            ),
+            (OwnedCallbackEvent::CdataStart, "<![CDATA["),
+            (
+                OwnedCallbackEvent::String {
+                    value: b"a<b".to_vec()
+                },
+                "a<b"
+            ),
+            (OwnedCallbackEvent::CdataEnd, "]]>"),
+            (
+                OwnedCallbackEvent::String {
+                    value: b"c".to_vec()
+                },
+                "c"
+            ),
+            (
+                OwnedCallbackEvent::EndTag {
+                    name: b"svg".to_vec()
+                },
+                "</svg>"
+            ),
+        ]
+    );
+}
+
+#[test]
+fn reset() {
+    let emitter = CallbackEmitter::new(RecordingCallback::default());
+    let mut tokenizer = Tokenizer::new_with_emitter("<a>", emitter);
+    for _ in tokenizer.by_ref() {}
+    let mut tokenizer = tokenizer.reset_with_reader("b");
+    for _ in tokenizer.by_ref() {}
+    assert_eq!(
+        tokenizer.emitter().callback().clone().into_events(),
+        [(
+            OwnedCallbackEvent::String {
+                value: b"b".to_vec()
+            },
+            Span { start: 0, end: 1 }
+        )]
+    );
+}
+
+#[test]
+fn readable_debug() {
+    let event = OwnedCallbackEvent::Doctype {
+        name: b"html".to_vec(),
+        public_identifier: Some(b"\xff\"".to_vec()),
+        system_identifier: None,
+        force_quirks: false,
+    };
+    assert_eq!(
+        format!("{:?}", event),
+        r#"Doctype { name: b"html", public_identifier: Some(b"\xff\""), system_identifier: None, force_quirks: false }"#
+    );
+}
@@ -0,0 +1,105 @@
+use std::fs;
+use std::io::{self, Write};
+
+use html5gum::{
+    IoReader, Readable, RecordingError, RecordingReader, ResumeContext, Token, Tokenizer,
+};
+use pretty_assertions::assert_eq;
+
+mod testutils;
+
+#[test]
+fn replay() {
+    for input in testutils::html5lib_inputs() {
+        let mut recording = Vec::new();
+        let tokens =
+            testutils::tokenize_with_spans(RecordingReader::new(input.to_reader(), &mut recording));
+
+        assert_eq!(
+            testutils::tokenize_with_spans(recording.as_slice().to_reader()),
+            tokens,
+            "{:?}",
+            input
+        );
+        assert_eq!(recording.len(), input.len(), "{:?}", input);
+        // keywords such as DOCTYPE are recorded in the tokenizer's spelling
+        assert!(
+            recording.eq_ignore_ascii_case(input.as_bytes()),
+            "{:?}",
+            input
+        );
+    }
+}
+
+#[test]
+fn case_insensitive_keywords() {
+    let mut recording = Vec::new();
+    let reader = RecordingReader::new(r#"<!doctype html public "x">"#.to_reader(), &mut recording);
+    assert_eq!(Tokenizer::new(reader).count(), 1);
+    assert_eq!(recording, br#"<!doctype html public "x">"#);
+
+    let mut recording = Vec::new();
+    let reader = RecordingReader::new(r#"<!DOCTYPE html PUBLIC "x">"#.to_reader(), &mut recording);
+    assert_eq!(Tokenizer::new(reader).count(), 1);
+    assert_eq!(recording, br#"<!Doctype html Public "x">"#);
+}
+
+#[test]
+fn stop_early() {
+    let mut recording = Vec::new();
+    let reader = RecordingReader::new("<p>hello</p><div>".to_reader(), &mut recording);
+    let mut tokenizer = Tokenizer::new(reader);
+    assert!(matches!(tokenizer.next(), Some(Ok(Token::StartTag(_)))));
+    drop(tokenizer);
+    assert_eq!(recording, b"<p>");
+}
+
+#[test]
+fn resume_at() {
+    let html = "<title>x</title><p>hello</p>";
+    let offset = html.find("<p>").unwrap();
+    let mut recording = Vec::new();
+    let reader = RecordingReader::new(html.to_reader(), &mut recording);
+    let tokenizer = Tokenizer::resume_at(reader, offset, ResumeContext::default()).unwrap();
+    assert_eq!(tokenizer.count(), 3);
+    assert_eq!(recording, html.as_bytes());
+}
+
+#[test]
+fn with_recording() {
+    let path = std::env::temp_dir().join(format!("html5gum-recording-{}.html", std::process::id()));
+    let html = "<p class=a>hello</p><!-- bye -->";
+
+    let tokenizer = Tokenizer::with_recording(IoReader::new(html.as_bytes()), &path).unwrap();
+    let tokens: Vec<_> = tokenizer.map(Result::unwrap).collect();
+    let recording = fs::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(recording, html.as_bytes());
+    assert_eq!(
+        Tokenizer::new(&recording)
+            .map(Result::unwrap)
+            .collect::<Vec<_>>(),
+        tokens
+    );
+}
+
+struct FailingWriter;
+
+impl Write for FailingWriter {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        Err(io::Error::other("disk full"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn write_error() {
+    let reader = RecordingReader::new("<p>".to_reader(), FailingWriter);
+    let error = Tokenizer::new(reader).next().unwrap().unwrap_err();
+    assert!(matches!(error, RecordingError::Write(_)));
+    assert_eq!(error.to_string(), "failed to write recording: disk full");
+}
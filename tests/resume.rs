@@ -0,0 +1,161 @@
+use html5gum::{
+    Comment, CommentKind, DefaultEmitter, IoReader, Readable, ResumeContext, State, Token,
+    Tokenizer,
+};
+use pretty_assertions::assert_eq;
+
+fn emitter() -> DefaultEmitter {
+    let mut emitter = DefaultEmitter::default();
+    emitter.naively_switch_states(true);
+    emitter
+}
+
+/// Tokenize `input` and capture the context right after the first occurrence of `tag`. Return
+/// that context along with all tokens after `tag`.
+fn full_run(input: &str, tag: &str) -> (ResumeContext, Vec<Token>) {
+    let mut tokenizer = Tokenizer::new_with_emitter(input, emitter());
+    let mut context = None;
+    let mut tokens = Vec::new();
+
+    while let Some(Ok(token)) = tokenizer.next() {
+        if context.is_some() {
+            tokens.push(token);
+        } else if matches!(&token, Token::StartTag(t) if t.name == tag.as_bytes()) {
+            context = Some(tokenizer.context_at_last_token());
+        }
+    }
+
+    (context.unwrap(), tokens)
+}
+
+/// Resume right after `tag_source` (the literal source text of the first `tag`) and compare with
+/// the tokens of a full run.
+fn assert_resume_equivalent(input: &str, tag: &str, tag_source: &str) -> ResumeContext {
+    let (context, expected) = full_run(input, tag);
+    let offset = input.find(tag_source).unwrap() + tag_source.len();
+
+    let tokenizer =
+        Tokenizer::resume_at_with_emitter(input, offset, context.clone(), emitter()).unwrap();
+    assert_eq!(tokenizer.flatten().collect::<Vec<_>>(), expected);
+
+    // same thing, but with a reader that skips bytes using the default implementation
+    let tokenizer = Tokenizer::resume_at_with_emitter(
+        IoReader::new(input.as_bytes()).to_reader(),
+        offset,
+        context.clone(),
+        emitter(),
+    )
+    .unwrap();
+    assert_eq!(tokenizer.flatten().collect::<Vec<_>>(), expected);
+
+    context
+}
+
+const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<title>a <b> title</title>
+<script>if (a </b> b) { document.write("<script></script>") }</script>
+<style>p > a { color: red }</style>
+</head>
+<body class="main" data-x='>'>
+<p>hello <a href="/">world</a></p>
+<textarea><p>not a tag</p></textarea>
+<!-- a comment -->
+</body>
+</html>
+"#;
+
+#[test]
+fn body() {
+    let context = assert_resume_equivalent(PAGE, "body", r#"<body class="main" data-x='>'>"#);
+    assert_eq!(
+        context,
+        ResumeContext {
+            state: State::Data,
+            last_start_tag: Some(b"body".to_vec().into()),
+            in_foreign_content: false,
+        }
+    );
+}
+
+#[test]
+fn rcdata() {
+    let context = assert_resume_equivalent(PAGE, "title", "<title>");
+    assert_eq!(context.state, State::RcData);
+    assert_eq!(context.last_start_tag.unwrap(), b"title");
+
+    let context = assert_resume_equivalent(PAGE, "textarea", "<textarea>");
+    assert_eq!(context.state, State::RcData);
+}
+
+#[test]
+fn script_and_rawtext() {
+    let context = assert_resume_equivalent(PAGE, "script", "<script>");
+    assert_eq!(context.state, State::ScriptData);
+
+    let context = assert_resume_equivalent(PAGE, "style", "<style>");
+    assert_eq!(context.state, State::RawText);
+}
+
+#[test]
+fn every_start_tag() {
+    // resume after each start tag in turn
+    let mut pos = 0;
+    while let Some(start) = PAGE[pos..].find('<').map(|x| x + pos) {
+        pos = start + 1;
+        let source_end = PAGE[start..].find('>').unwrap() + start + 1;
+        let tag_source = &PAGE[start..source_end];
+        let name = tag_source[1..tag_source.len() - 1]
+            .split(' ')
+            .next()
+            .unwrap();
+
+        let is_start_tag = name.bytes().all(|c| c.is_ascii_alphabetic());
+        // only look at the first occurrence of a tag, and skip the fake tags in RCDATA. <body>
+        // contains a quoted `>`, which is tested above.
+        if is_start_tag
+            && PAGE.find(tag_source) == Some(start)
+            && !matches!(name, "p" | "b" | "a" | "body")
+        {
+            assert_resume_equivalent(PAGE, name, tag_source);
+        }
+    }
+}
+
+#[test]
+fn foreign_content() {
+    let input = "<![CDATA[<b>]]>";
+    let context = ResumeContext {
+        in_foreign_content: true,
+        ..ResumeContext::default()
+    };
+
+    let tokens: Vec<Token> = Tokenizer::resume_at(input, 0, context)
+        .unwrap()
+        .flatten()
+        .collect();
+    assert_eq!(tokens, vec![Token::String(b"<b>".to_vec().into())]);
+
+    // the override ends with the next tag
+    let input = "<![CDATA[x]]><p><![CDATA[x]]>";
+    let context = ResumeContext {
+        in_foreign_content: true,
+        ..ResumeContext::default()
+    };
+    let tokens: Vec<Token> = Tokenizer::resume_at(input, 0, context)
+        .unwrap()
+        .flatten()
+        .filter(|token| !matches!(token, Token::Error(_) | Token::StartTag(_)))
+        .collect();
+    assert_eq!(
+        tokens,
+        vec![
+            Token::String(b"x".to_vec().into()),
+            Token::Comment(Comment {
+                value: b"[CDATA[x]]".to_vec().into(),
+                kind: CommentKind::Cdata,
+            })
+        ]
+    );
+}
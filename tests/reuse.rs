@@ -0,0 +1,144 @@
+use html5gum::emitters::buffered::{BufferedEmitter, TokenRef};
+use html5gum::{DefaultEmitter, EndTag, Error, GarbagePolicy, Reader, Span, Token, Tokenizer};
+use pretty_assertions::assert_eq;
+
+mod testutils;
+
+#[global_allocator]
+static ALLOC: testutils::CountingAlloc = testutils::CountingAlloc;
+
+const DOCUMENTS: &[&str] = &[
+    "",
+    "<!DOCTYPE html><title>a &amp; b</title><p class=x id='y'>hello</p>",
+    "<script>if (a </s) { b() }</script><!-- x -->",
+    "<textarea>unclosed",
+    "<a href=\"unclosed",
+    "<!-- unclosed",
+    "<svg><![CDATA[x]]></svg>",
+    "</script>still text?<p a=1 a=2>\0&notit;",
+    "<!DOCTYPE html PUBLIC \"-//W3C//DTD HTML 4.01//EN\"",
+];
+
+fn emitter() -> DefaultEmitter<(Token, Span)> {
+    let mut emitter = DefaultEmitter::with_spans();
+    emitter.naively_switch_states(true);
+    emitter.collect_error_summary(true);
+    emitter
+}
+
+fn fresh(input: &str) -> Vec<(Token, Span)> {
+    Tokenizer::new_with_emitter(input, emitter())
+        .map(Result::unwrap)
+        .collect()
+}
+
+/// Tokenize the first `n` tokens of `first`, then reuse the tokenizer for `second`.
+fn reused(first: &str, n: usize, second: &str) -> Vec<(Token, Span)> {
+    let mut tokenizer = Tokenizer::new_with_emitter(first, emitter());
+    tokenizer.by_ref().take(n).for_each(drop);
+    tokenizer
+        .reset_with_reader(second)
+        .map(Result::unwrap)
+        .collect()
+}
+
+#[test]
+fn same_tokens_as_fresh_tokenizer() {
+    for &first in DOCUMENTS {
+        let len = fresh(first).len();
+        for &second in DOCUMENTS {
+            let expected = fresh(second);
+            // stop before, in the middle of, and after the first document
+            for n in [0, len / 2, len, usize::MAX] {
+                assert_eq!(
+                    reused(first, n, second),
+                    expected,
+                    "{:?} after {} tokens of {:?}",
+                    second,
+                    n,
+                    first
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn leaves_script_state() {
+    let mut tokenizer = Tokenizer::new_with_emitter("<script>foo", emitter());
+    assert_eq!(tokenizer.by_ref().take(2).count(), 2);
+
+    let tokens: Vec<_> = tokenizer
+        .reset_with_reader("</script>")
+        .map(|x| x.unwrap().0)
+        .collect();
+    assert_eq!(
+        tokens,
+        [Token::EndTag(EndTag {
+            name: b"script".to_vec().into()
+        })]
+    );
+}
+
+#[test]
+fn keeps_options() {
+    let mut emitter = DefaultEmitter::default();
+    emitter.suppress_errors(true);
+    emitter.collect_error_summary(true);
+    let mut tokenizer = Tokenizer::new_with_emitter("<a b b>", emitter);
+    tokenizer.binary_garbage_mode(GarbagePolicy::SkipUntilPlausibleTag);
+    assert_eq!(tokenizer.by_ref().count(), 1);
+    let summary = tokenizer.emitter().error_summary().unwrap();
+    assert_eq!(summary.count(Error::DuplicateAttribute), 1);
+
+    let mut tokenizer = tokenizer.reset_with_reader("\0\0\0<html>\0");
+    assert_eq!(tokenizer.by_ref().count(), 2);
+    assert_eq!(tokenizer.skipped_garbage_len(), 3);
+    let summary = tokenizer.emitter().error_summary().unwrap();
+    assert_eq!(summary.count(Error::DuplicateAttribute), 0);
+    assert_eq!(summary.count(Error::SkippedBinaryGarbage), 1);
+    assert_eq!(summary.count(Error::UnexpectedNullCharacter), 1);
+}
+
+/// Tokenize `input` with `tokenizer`, without allocating for the tokens.
+fn tokenize<R: Reader>(tokenizer: &mut Tokenizer<R, BufferedEmitter>) -> usize {
+    let mut count = 0;
+    while let Some(token) = tokenizer.next_token() {
+        if let Ok(TokenRef::StartTag(tag)) = token {
+            assert_eq!(tag.attribute(b"id"), Some(&b"y"[..]));
+        }
+        count += 1;
+    }
+    count
+}
+
+#[test]
+fn reuses_allocations() {
+    let input = "<p class=x id=y>lorem &amp; ipsum</p><!-- x --><!DOCTYPE html>".repeat(100);
+
+    let (mut tokenizer, fresh) = testutils::count_allocations(|| {
+        let mut tokenizer = Tokenizer::new_with_emitter(input.as_str(), BufferedEmitter::default());
+        assert_eq!(tokenize(&mut tokenizer), 500);
+        tokenizer
+    });
+
+    let mut reused = Vec::new();
+    for _ in 0..3 {
+        let allocations;
+        (tokenizer, allocations) = testutils::count_allocations(|| {
+            let mut tokenizer = tokenizer.reset_with_reader(input.as_str());
+            assert_eq!(tokenize(&mut tokenizer), 500);
+            tokenizer
+        });
+        reused.push(allocations);
+    }
+
+    // debug builds allocate for logging, which is the same every time
+    for &allocations in &reused {
+        assert!(allocations + 10 < fresh, "{} {:?}", fresh, reused);
+    }
+    if !cfg!(debug_assertions) {
+        // only the checkpoint for `save_state` after the first tag
+        assert!(reused.iter().all(|&x| x <= 1), "{:?}", reused);
+    }
+}
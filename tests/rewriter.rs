@@ -0,0 +1,127 @@
+use std::cell::RefCell;
+use std::io::ErrorKind;
+
+use html5gum::rewriter::{Rewriter, Tag};
+use pretty_assertions::assert_eq;
+
+fn rewrite(input: &str, name: &str, handler: impl FnMut(&mut Tag)) -> String {
+    let mut rewriter = Rewriter::new(Vec::new()).on_tag(name.as_bytes(), handler);
+    rewriter.rewrite(input.as_bytes()).unwrap();
+    String::from_utf8(rewriter.into_inner()).unwrap()
+}
+
+#[test]
+fn untouched_tags_are_copied() {
+    let input = "<!doctype HTML><P CLASS = x>a &amp b<img src=a.png/><!-- <img> -->\r\n</p >";
+    assert_eq!(rewrite(input, "img", |_| {}), input);
+    assert_eq!(
+        rewrite(input, "p", |tag| tag.set_name(b"div")),
+        "<!doctype HTML><div class=\"x\">a &amp b<img src=a.png/><!-- <img> -->\r\n</p >"
+    );
+}
+
+#[test]
+fn attributes() {
+    assert_eq!(
+        rewrite("<a href=x title=y TITLE=z>", "a", |tag| {
+            assert_eq!(tag.get_attribute(b"Title"), Some(b"y".as_slice()));
+            assert_eq!(tag.remove_attribute(b"href").unwrap(), b"x");
+            tag.set_attribute(b"rel", b"\"no\" & follow");
+        }),
+        "<a title=\"y\" rel=\"&quot;no&quot; &amp; follow\">"
+    );
+    assert_eq!(
+        rewrite("<input disabled/>", "input", |tag| {
+            assert!(tag.self_closing());
+            tag.set_attribute(b"value", b"");
+        }),
+        "<input disabled=\"\" value=\"\"/>"
+    );
+    // removing an attribute that is not there leaves the tag as it is
+    assert_eq!(
+        rewrite("<a  href=x>", "a", |tag| {
+            tag.remove_attribute(b"title");
+        }),
+        "<a  href=x>"
+    );
+}
+
+#[test]
+fn remove() {
+    assert_eq!(
+        rewrite("a<script>x</p>y</script >b<script>c", "script", Tag::remove),
+        "ab"
+    );
+    assert_eq!(
+        rewrite(
+            "a<style>x</style><title>&amp;</title>b",
+            "style",
+            Tag::remove
+        ),
+        "a<title>&amp;</title>b"
+    );
+    // the end tag of other elements is kept
+    assert_eq!(rewrite("a<b>x</b>c", "b", Tag::remove), "ax</b>c");
+    // tags inside of <script> are not tokenized
+    assert_eq!(
+        rewrite("<script>'<b>'</script><b>", "b", Tag::remove),
+        "<script>'<b>'</script>"
+    );
+}
+
+#[test]
+fn several_handlers() {
+    let calls = RefCell::new(Vec::new());
+    let mut rewriter = Rewriter::new(Vec::new())
+        .on_tag(b"IMG", |tag| {
+            calls.borrow_mut().push(1);
+            if tag.get_attribute(b"src") == Some(b"ad.png") {
+                tag.remove();
+            }
+        })
+        .on_tag(b"img", |tag| {
+            calls.borrow_mut().push(2);
+            tag.set_attribute(b"loading", b"lazy");
+        });
+    rewriter
+        .rewrite(b"<img src=ad.png><img src=cat.png>")
+        .unwrap();
+    assert_eq!(
+        rewriter.into_inner(),
+        b"<img src=\"cat.png\" loading=\"lazy\">"
+    );
+    assert_eq!(calls.into_inner(), [1, 1, 2]);
+}
+
+#[test]
+fn invalid_name() {
+    let mut rewriter = Rewriter::new(Vec::new()).on_tag(b"b", |tag| tag.set_attribute(b"a b", b""));
+    let error = rewriter.rewrite(b"a<b>c<b>").unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    assert_eq!(rewriter.into_inner(), b"a");
+}
+
+#[test]
+fn large_document() {
+    let chunk = "<!DOCTYPE html><DIV Class='a'  id=\"b\">text &amp; &lt;entities&gt; &unknown;\r\n\
+        <img src=cat.png alt=x><script>if (a<b) document.write('<img src=x>')</script>\
+        <!-- <img src=x> --><textarea><img></textarea><br/></div >\n";
+    let target = "<img src=cat.png id=target>";
+    let mut input = String::new();
+    while input.len() < 1 << 20 {
+        if input.len() == 1 << 19 {
+            input.push_str(target);
+        }
+        input.push_str(chunk);
+    }
+
+    let output = rewrite(&input, "img", |tag| {
+        if tag.get_attribute(b"id") == Some(b"target") {
+            tag.set_attribute(b"src", b"dog.png");
+        }
+    });
+    assert_eq!(
+        output,
+        input.replace(target, "<img src=\"dog.png\" id=\"target\">")
+    );
+}
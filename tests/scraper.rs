@@ -0,0 +1,83 @@
+//! Building a [`scraper::Html`] through html5gum has to give the same results as scraper's own
+//! parser, see `examples/scraper.rs`.
+use html5ever::interface::tree_builder::TreeSink;
+use html5gum::emitters::html5ever::parse_document;
+use pretty_assertions::assert_eq;
+use scraper::{ElementRef, Html, HtmlTreeSink, Node, Selector};
+
+const FIXTURE: &str = r#"<!DOCTYPE html>
+<title>Fixture &amp; friends</title>
+<!-- a comment -->
+<ul id=list class="items main">
+    <li class=item data-x=1>one
+    <li class=item>two <b>bold</b>
+    <li class="item last">three
+</ul>
+<table><tr><td>cell<td>other cell</table>
+<p>before<svg viewBox="0 0 1 1"><circle r=1 /><foreignObject><p>inside</p></foreignObject></svg>
+<a href="/x" title='quoted "title"'>link</a><p>after
+"#;
+
+fn html5gum_html(input: &str) -> Html {
+    let tree_sink = HtmlTreeSink::new(Html::new_document());
+    let Ok(tree_sink) = parse_document(input, tree_sink, Default::default());
+    tree_sink.finish()
+}
+
+fn describe(element: ElementRef<'_>) -> String {
+    let mut attrs: Vec<_> = element.value().attrs().collect();
+    attrs.sort_unstable();
+    format!(
+        "{} {:?} {:?} {:?}",
+        element.value().name(),
+        element.value().name.ns,
+        attrs,
+        element.text().collect::<String>()
+    )
+}
+
+/// All nodes in document order, with their depth. Attributes are sorted because scraper doesn't
+/// preserve their order.
+fn nodes(html: &Html) -> Vec<(usize, String)> {
+    html.tree
+        .root()
+        .descendants()
+        .map(|node| {
+            let description = match node.value() {
+                Node::Element(_) => describe(ElementRef::wrap(node).unwrap()),
+                other => format!("{:?}", other),
+            };
+            (node.ancestors().count(), description)
+        })
+        .collect()
+}
+
+#[test]
+fn same_as_scraper() {
+    let expected = Html::parse_document(FIXTURE);
+    let actual = html5gum_html(FIXTURE);
+
+    for selector in [
+        "*",
+        "title",
+        "li.item",
+        "#list > li:last-child",
+        "li[data-x]",
+        "ul.items.main b",
+        "td",
+        "tr > td + td",
+        "p",
+        "circle",
+        "a[href^='/']",
+    ] {
+        let selector = Selector::parse(selector).unwrap();
+        assert_eq!(
+            actual.select(&selector).map(describe).collect::<Vec<_>>(),
+            expected.select(&selector).map(describe).collect::<Vec<_>>(),
+            "selector: {:?}",
+            selector
+        );
+    }
+
+    assert_eq!(nodes(&actual), nodes(&expected));
+}
@@ -0,0 +1,125 @@
+use std::io::ErrorKind;
+
+use html5gum::serializer::{serialize_tokens, Serializer};
+use html5gum::{DefaultEmitter, Doctype, StartTag, Token, Tokenizer};
+use pretty_assertions::assert_eq;
+
+mod testutils;
+
+/// Tokenize with state switching, without errors, and with adjacent strings merged.
+fn tokenize(input: &[u8]) -> Vec<Token> {
+    let mut emitter = DefaultEmitter::default();
+    emitter.naively_switch_states(true);
+    let mut tokens = Vec::new();
+    for token in Tokenizer::new_with_emitter(input, emitter).flatten() {
+        match (token, tokens.last_mut()) {
+            (Token::Error(_), _) => {}
+            (Token::String(s), Some(Token::String(last))) => last.extend(s.iter()),
+            (token, _) => tokens.push(token),
+        }
+    }
+    tokens
+}
+
+fn serialize(tokens: &[Token]) -> Vec<u8> {
+    let mut serializer = Serializer::new(Vec::new());
+    serializer.self_closing(true);
+    serializer.raw_text(true);
+    serializer.void_end_tags(true);
+    for token in tokens {
+        serializer.write_token(token).unwrap();
+    }
+    serializer.into_inner()
+}
+
+#[test]
+fn round_trip() {
+    for input in testutils::html5lib_inputs() {
+        let tokens = tokenize(input.as_bytes());
+        let output = serialize(&tokens);
+        assert_eq!(
+            tokenize(&output),
+            tokens,
+            "input: {:?}, output: {:?}",
+            input,
+            String::from_utf8_lossy(&output)
+        );
+    }
+}
+
+#[test]
+fn defaults() {
+    let tokens = tokenize(
+        b"<p class=a\"b data-x=\xc2\xa0>a&amp;b<br/></br></img><script>1<2</script><!--c-->",
+    );
+    let mut output = Vec::new();
+    serialize_tokens(&tokens, &mut output).unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "<p class=\"a&quot;b\" data-x=\"&nbsp;\">a&amp;b<br><br><script>1&lt;2</script><!--c-->"
+    );
+}
+
+#[test]
+fn doctypes() {
+    for input in [
+        "<!DOCTYPE html>",
+        "<!DOCTYPE>",
+        "<!doctype html bogus>",
+        "<!DOCTYPE html PUBLIC \"a'b\">",
+        "<!DOCTYPE html PUBLIC 'a\"b' 'c'>",
+        "<!DOCTYPE html PUBLIC \"a\" bogus>",
+        "<!DOCTYPE html SYSTEM \"a\">",
+        "<!DOCTYPE html SYSTEM \"a>",
+        "<!DOCTYPE html PUBLIC \"a\" \"b>",
+    ] {
+        let tokens = tokenize(input.as_bytes());
+        assert_eq!(tokenize(&serialize(&tokens)), tokens, "{}", input);
+    }
+
+    let mut output = Vec::new();
+    serialize_tokens(&tokenize(b"<!doctype HTML>"), &mut output).unwrap();
+    assert_eq!(output, b"<!DOCTYPE html>");
+}
+
+#[test]
+fn invalid_tokens() {
+    let invalid = [
+        Token::Comment(b"a-->b".to_vec().into()),
+        Token::Comment(b"a--!>b".to_vec().into()),
+        Token::Comment(b"->".to_vec().into()),
+        Token::StartTag(StartTag {
+            name: b"a b".to_vec().into(),
+            ..StartTag::default()
+        }),
+        Token::StartTag(StartTag {
+            name: b"a".to_vec().into(),
+            attributes: [(b"b=c".to_vec().into(), b"".to_vec().into())].into(),
+            ..StartTag::default()
+        }),
+        Token::StartTag(StartTag::default()),
+        Token::Doctype(Doctype {
+            name: b"html".to_vec().into(),
+            force_quirks: false,
+            public_identifier: Some(b"\"'".to_vec().into()),
+            system_identifier: None,
+        }),
+        Token::Doctype(Doctype {
+            name: b"".to_vec().into(),
+            force_quirks: false,
+            public_identifier: None,
+            system_identifier: None,
+        }),
+    ];
+
+    for token in &invalid {
+        let mut output = Vec::new();
+        let error = serialize_tokens([token], &mut output).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput, "{:?}", token);
+        assert_eq!(output, b"", "{:?}", token);
+    }
+
+    // a leading `=` is part of the attribute name
+    let tokens = tokenize(b"<a =b>");
+    assert_eq!(tokenize(&serialize(&tokens)), tokens);
+}
@@ -0,0 +1,205 @@
+//! Switching states with [`Tokenizer::set_state`] in between tokens, from outside of the emitter.
+use html5gum::testutils::SlowReader;
+use html5gum::{DefaultEmitter, Emitter, EndTag, Readable, Reader, State, Token, Tokenizer};
+use pretty_assertions::assert_eq;
+
+mod testutils;
+
+use testutils::string;
+
+/// Tokenize `input` without any automatic state switching, and switch to `state` right after the
+/// first start tag named `tag`.
+fn switch_after<'a, R: Reader>(
+    input: impl Readable<'a, Reader = R>,
+    tag: &str,
+    state: State,
+) -> Vec<Token> {
+    let mut tokenizer = Tokenizer::new(input);
+    let mut tokens = Vec::new();
+    let mut switched = false;
+
+    while let Some(token) = tokenizer.next() {
+        let token = token.unwrap_or_else(|_| panic!("reader failed"));
+        if !switched && matches!(&token, Token::StartTag(t) if t.name == tag.as_bytes()) {
+            tokenizer.set_state(state);
+            switched = true;
+        }
+        tokens.push(token);
+    }
+
+    assert!(switched);
+    tokens
+}
+
+/// What a browser produces for `input`.
+fn browser(input: &str) -> Vec<Token> {
+    let mut emitter = DefaultEmitter::default();
+    emitter.naively_switch_states(true);
+    Tokenizer::new_with_emitter(input, emitter)
+        .flatten()
+        .collect()
+}
+
+/// Strip `prefix` from the first string token.
+fn strip_prefix(mut tokens: Vec<Token>, prefix: &str) -> Vec<Token> {
+    for token in &mut tokens {
+        if let Token::String(s) = token {
+            assert!(s.starts_with(prefix.as_bytes()));
+            *s = s[prefix.len()..].to_vec().into();
+            break;
+        }
+    }
+    tokens
+}
+
+fn check(input: &str, tag: &str, state: State, expected: &[Token]) {
+    assert_eq!(
+        switch_after(input, tag, state),
+        expected,
+        "input: {:?}",
+        input
+    );
+    assert_eq!(
+        switch_after(SlowReader(input.to_reader()), tag, state),
+        expected,
+        "SlowReader, input: {:?}",
+        input
+    );
+}
+
+#[test]
+fn document_write_comment_start() {
+    // `document.write("<script><!--")` followed by the rest of the document, which is tokenized
+    // as if it was preceded by `<!--`
+    for rest in [
+        "a<b>-->c</script>d",
+        "a</script>b",
+        "<script>x</script>y-->z</script>",
+        "<script>x</script>y</script>z",
+        "a<!--b-->c</script>",
+    ] {
+        let expected = strip_prefix(browser(&format!("<script><!--{}", rest)), "<!--");
+        check(
+            &format!("<script>{}", rest),
+            "script",
+            State::ScriptDataEscaped,
+            &expected,
+        );
+    }
+}
+
+#[test]
+fn document_write_nested_script() {
+    // `document.write("<script><!--<script>")` followed by the rest of the document
+    for rest in [
+        "x</script>y</script>z",
+        "x-->y</script>z",
+        "x<!--y</script>z",
+    ] {
+        let expected = strip_prefix(
+            browser(&format!("<script><!--<script>{}", rest)),
+            "<!--<script>",
+        );
+        check(
+            &format!("<script>{}", rest),
+            "script",
+            State::ScriptDataDoubleEscaped,
+            &expected,
+        );
+    }
+}
+
+#[test]
+fn text_states() {
+    let input = "<div><b>&amp;</div><b>";
+    let expected_end = Token::EndTag(EndTag {
+        name: b"div".to_vec().into(),
+    });
+
+    let tokens = switch_after(input, "div", State::RawText);
+    assert_eq!(tokens[1..3], [string("<b>&amp;"), expected_end.clone()]);
+    assert!(matches!(tokens[3], Token::StartTag(_)));
+
+    let tokens = switch_after(input, "div", State::RcData);
+    assert_eq!(tokens[1..3], [string("<b>&"), expected_end]);
+
+    let tokens = switch_after(input, "div", State::PlainText);
+    assert_eq!(tokens[1..], [string("<b>&amp;</div><b>")]);
+}
+
+#[test]
+fn override_emitter() {
+    // the state is switched after the emitter has already switched to RCDATA for the title
+    let mut emitter = DefaultEmitter::default();
+    emitter.naively_switch_states(true);
+    let mut tokenizer = Tokenizer::new_with_emitter("<title><b></title>&amp;", emitter);
+    assert!(matches!(tokenizer.next(), Some(Ok(Token::StartTag(_)))));
+    assert_eq!(tokenizer.context_at_last_token().state, State::RcData);
+
+    tokenizer.set_state(State::Data);
+    assert_eq!(tokenizer.context_at_last_token().state, State::Data);
+
+    let tokens: Vec<_> = tokenizer.flatten().collect();
+    assert_eq!(tokens.len(), 3);
+    assert!(matches!(&tokens[0], Token::StartTag(t) if t.name == b"b"));
+    assert!(matches!(&tokens[1], Token::EndTag(t) if t.name == b"title"));
+    assert_eq!(tokens[2], string("&"));
+}
+
+#[test]
+fn without_start_tag() {
+    // a tree builder that knows it is inside of a style element
+    let mut tokenizer = Tokenizer::new("a<b></style>c");
+    tokenizer
+        .emitter_mut()
+        .set_last_start_tag(Some(b"style".as_slice()));
+    tokenizer.set_state(State::RawText);
+    let tokens: Vec<_> = tokenizer.flatten().collect();
+    assert_eq!(
+        tokens,
+        [
+            string("a<b>"),
+            Token::EndTag(EndTag {
+                name: b"style".to_vec().into(),
+            }),
+            string("c"),
+        ]
+    );
+}
+
+#[test]
+fn after_end_tag() {
+    // End tags don't reset the last start tag, so switching back into RCDATA after the end tag
+    // still finds the next `</title>`.
+    let mut tokenizer = Tokenizer::new("<title>a</title>b</p></title>c");
+    let tokens: Vec<_> = tokenizer.by_ref().take(3).flatten().collect();
+    assert!(matches!(&tokens[2], Token::EndTag(t) if t.name == b"title"));
+    assert_eq!(
+        tokenizer.context_at_last_token().last_start_tag,
+        Some(b"title".to_vec().into())
+    );
+
+    tokenizer.set_state(State::RcData);
+    let tokens: Vec<_> = tokenizer.flatten().collect();
+    assert_eq!(
+        tokens,
+        [
+            string("b</p>"),
+            Token::EndTag(EndTag {
+                name: b"title".to_vec().into(),
+            }),
+            string("c"),
+        ]
+    );
+}
+
+#[test]
+fn naive_switching_after_end_tag() {
+    // other end tags are text, and the real end tag does not switch back into RCDATA
+    let tokens = browser("<textarea>a</div>b</textarea>c<b>d</b>");
+    assert_eq!(tokens.len(), 7);
+    assert_eq!(tokens[1], string("a</div>b"));
+    assert!(matches!(&tokens[2], Token::EndTag(t) if t.name == b"textarea"));
+    assert_eq!(tokens[3], string("c"));
+    assert!(matches!(&tokens[4], Token::StartTag(t) if t.name == b"b"));
+}
@@ -0,0 +1,135 @@
+use html5gum::testutils::DebugValidateEmitter;
+use html5gum::{DefaultEmitter, IoReader, Readable, Reader, Span, Token, Tokenizer};
+use pretty_assertions::assert_eq;
+
+fn tokenize<'a, R: Reader>(input: impl Readable<'a, Reader = R>, skip: bool) -> Vec<Token> {
+    let mut inner = DefaultEmitter::default();
+    inner.naively_switch_states(true);
+    inner.skip_rawtext(skip);
+    Tokenizer::new_with_emitter(input, DebugValidateEmitter::new(inner))
+        .map(|token| token.unwrap_or_else(|_| panic!("reader failed")))
+        .filter(|token| !matches!(token, Token::Error(_)))
+        .collect()
+}
+
+/// Tag names, with a `/` in front of end tags, and strings.
+fn describe(tokens: &[Token]) -> Vec<String> {
+    tokens
+        .iter()
+        .map(|token| match token {
+            Token::StartTag(tag) => String::from_utf8(tag.name.to_vec()).unwrap(),
+            Token::EndTag(tag) => format!("/{}", String::from_utf8(tag.name.to_vec()).unwrap()),
+            Token::String(s) => format!("{:?}", String::from_utf8(s.to_vec()).unwrap()),
+            token => format!("{:?}", token),
+        })
+        .collect()
+}
+
+#[test]
+fn script_style_and_title() {
+    let html = r#"<script>if (a < b) { x = "</div>"; }</script><style>p{}</style ><title>a &amp; b</title><p>x</p>"#;
+    assert_eq!(
+        describe(&tokenize(html, true)),
+        ["script", "/script", "style", "/style", "title", "/title", "p", "\"x\"", "/p"]
+    );
+    assert_eq!(
+        describe(&tokenize(html, false)),
+        [
+            "script",
+            r#""if (a < b) { x = \"</div>\"; }""#,
+            "/script",
+            "style",
+            "\"p{}\"",
+            "/style",
+            "title",
+            "\"a & b\"",
+            "/title",
+            "p",
+            "\"x\"",
+            "/p"
+        ]
+    );
+}
+
+#[test]
+fn appropriate_end_tags() {
+    for (html, expected) in [
+        ("<script></SCRIPT\n>x", &["script", "/script", "\"x\""][..]),
+        ("<script></script/>x", &["script", "/script", "\"x\""]),
+        ("<script></script a=b>x", &["script", "/script", "\"x\""]),
+        (
+            "<script></scriptx></scrip></script>x",
+            &["script", "/script", "\"x\""],
+        ),
+        ("<script><</<script></ script>", &["script"]),
+        ("<style></style", &["style"]),
+        ("<textarea>a", &["textarea"]),
+    ] {
+        assert_eq!(
+            describe(&tokenize(html, true)),
+            expected,
+            "input: {:?}",
+            html
+        );
+    }
+}
+
+#[test]
+fn escaped_script_data() {
+    // from `<!--` on, the script is tokenized as usual
+    assert_eq!(
+        describe(&tokenize(
+            "<script>a<!x<!--<script></script>b--></script>c",
+            true
+        )),
+        [
+            "script",
+            "\"<!<!--<script></script>b-->\"",
+            "/script",
+            "\"c\""
+        ]
+    );
+}
+
+#[test]
+fn plaintext() {
+    assert_eq!(
+        describe(&tokenize("<plaintext>a</plaintext><p>b\r\n\0", true)),
+        ["plaintext"]
+    );
+}
+
+#[test]
+fn same_tags_as_without_skipping() {
+    for html in [
+        "<script>é€😀<é</é</script><p>",
+        "<textarea>\r\n<b>&amp;</textarea\t><p>",
+        "<style>\0</style>\r\n<title></TITLE>",
+        "<xmp><</</x</xmp><iframe></iframe><noembed>x</noembed>",
+    ] {
+        let tags = |tokens: Vec<Token>| -> Vec<Token> {
+            tokens
+                .into_iter()
+                .filter(|token| !matches!(token, Token::String(_)))
+                .collect()
+        };
+        let expected = tags(tokenize(html, false));
+        assert_eq!(tags(tokenize(html, true)), expected, "input: {:?}", html);
+        // the reader's buffer is smaller than the content
+        let reader = IoReader::new_with_buffer_size::<16>(html.as_bytes());
+        assert_eq!(tags(tokenize(reader, true)), expected, "input: {:?}", html);
+    }
+}
+
+#[test]
+fn spans() {
+    let html = "<script>\r\nlet x = '</a>';\n</script><p>";
+    let mut emitter = DefaultEmitter::with_spans();
+    emitter.naively_switch_states(true);
+    emitter.skip_rawtext(true);
+    let spans: Vec<_> = Tokenizer::new_with_emitter(html, emitter)
+        .flatten()
+        .map(|(_, span): (Token, Span)| &html[span.start..span.end])
+        .collect();
+    assert_eq!(spans, ["<script>", "</script>", "<p>"]);
+}
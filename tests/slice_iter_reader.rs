@@ -0,0 +1,76 @@
+use html5gum::{SliceIterReader, Tokenizer};
+use pretty_assertions::assert_eq;
+
+mod testutils;
+
+#[test]
+fn two_chunks() {
+    for input in testutils::html5lib_inputs() {
+        let input = input.as_bytes();
+        let expected = testutils::tokenize_with_spans(input);
+        for i in 0..=input.len() {
+            let (a, b) = input.split_at(i);
+            let chunks: &[&[u8]] = &[a, b];
+            assert_eq!(
+                testutils::tokenize_with_spans(chunks),
+                expected,
+                "input {:?} split at {}",
+                input,
+                i
+            );
+        }
+    }
+}
+
+#[test]
+fn small_chunks() {
+    for input in testutils::html5lib_inputs() {
+        let input = input.as_bytes();
+        let expected = testutils::tokenize_with_spans(input);
+        for chunk_size in 1..4 {
+            let reader = SliceIterReader::new(input.chunks(chunk_size));
+            assert_eq!(
+                testutils::tokenize_with_spans(reader),
+                expected,
+                "input {:?}",
+                input
+            );
+        }
+    }
+}
+
+#[test]
+fn empty_chunks() {
+    let chunks: &[&[u8]] = &[b"", b"<!", b"", b"", b"DOC", b"", b"type html>", b""];
+    assert_eq!(
+        testutils::tokenize_with_spans(chunks),
+        testutils::tokenize_with_spans("<!DOCTYPE html>")
+    );
+
+    let chunks: &[&[u8]] = &[];
+    assert_eq!(testutils::tokenize_with_spans(chunks), []);
+}
+
+#[test]
+fn resume() {
+    let input = "<title>a</title><p>b<!--c--></p>";
+    let context = {
+        let mut tokenizer = Tokenizer::new(input);
+        tokenizer.next();
+        tokenizer.next();
+        tokenizer.next();
+        tokenizer.context_at_last_token()
+    };
+
+    let expected: Vec<_> = Tokenizer::resume_at(input, 16, context.clone())
+        .unwrap()
+        .collect();
+    for i in 0..=input.len() {
+        let (a, b) = input.as_bytes().split_at(i);
+        let chunks: &[&[u8]] = &[a, b];
+        let actual: Vec<_> = Tokenizer::resume_at(chunks, 16, context.clone())
+            .unwrap()
+            .collect();
+        assert_eq!(actual, expected, "split at {}", i);
+    }
+}
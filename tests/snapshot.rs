@@ -0,0 +1,121 @@
+use html5gum::emitters::buffered::BufferedEmitter;
+use html5gum::{DefaultEmitter, Token, Tokenizer, TokenizerSnapshot};
+use pretty_assertions::assert_eq;
+
+mod testutils;
+
+fn emitter() -> DefaultEmitter {
+    let mut emitter = DefaultEmitter::default();
+    emitter.naively_switch_states(true);
+    emitter
+}
+
+/// Snapshot after every token, resume from a serialized snapshot, and compare with a straight run.
+fn check(input: &[u8]) {
+    let expected: Vec<Token> = Tokenizer::new_with_emitter(input, emitter())
+        .flatten()
+        .collect();
+
+    for i in 0..=expected.len() {
+        let mut tokenizer = Tokenizer::new_with_emitter(input, emitter());
+        let mut tokens: Vec<Token> = tokenizer.by_ref().take(i).flatten().collect();
+
+        let json = serde_json::to_string(&tokenizer.save_state()).unwrap();
+        let snapshot: TokenizerSnapshot = serde_json::from_str(&json).unwrap();
+        let Ok(resumed) = Tokenizer::resume_with_emitter(input, snapshot, emitter());
+        tokens.extend(resumed.flatten());
+
+        assert_eq!(
+            tokens,
+            expected,
+            "{:?} {}",
+            String::from_utf8_lossy(input),
+            i
+        );
+    }
+}
+
+#[test]
+fn html5lib_inputs() {
+    for input in testutils::html5lib_inputs() {
+        check(input.as_bytes());
+    }
+}
+
+#[test]
+fn page() {
+    check(
+        br#"<!DOCTYPE html>
+<html>
+<head>
+<title>a <b> title</title>
+<script>if (a </b> b) { document.write("<script></script>") }</script>
+<style>p > a { color: red }</style>
+</head>
+<body class="main" data-x='>'>
+<p>hello &amp; <a href="/">world</a></p>
+<textarea><p>not a tag</p></textarea>
+<svg><![CDATA[x]]></svg>
+<!-- a comment -->
+</body>
+</html>
+"#,
+    );
+}
+
+#[test]
+fn snapshot_after_tag() {
+    let input = "<p>hello<b>world";
+    let mut tokenizer = Tokenizer::new(input);
+    assert_eq!(tokenizer.consumed_bytes(), 0);
+    tokenizer.next();
+    assert_eq!(tokenizer.consumed_bytes(), 3);
+    tokenizer.next();
+    // the string is only returned once `<b>` has been read
+    assert_eq!(tokenizer.consumed_bytes(), 11);
+
+    let snapshot = tokenizer.save_state();
+    assert_eq!(snapshot.offset, 3);
+    assert_eq!(snapshot.skip_tokens, 1);
+    assert_eq!(
+        snapshot.context.last_start_tag.as_deref(),
+        Some(&b"p".to_vec())
+    );
+
+    tokenizer.next();
+    tokenizer.next();
+    let snapshot = tokenizer.save_state();
+    assert_eq!(snapshot.offset, 11);
+    assert_eq!(snapshot.skip_tokens, 1);
+}
+
+#[test]
+fn buffered_emitter() {
+    let input = "<p class=x>hello<b>world</b><!--x-->";
+    let tokens = |tokenizer: &mut Tokenizer<_, BufferedEmitter>, n: usize| {
+        let mut tokens = Vec::new();
+        while let Some(Ok(token)) = tokenizer.next_token() {
+            tokens.push(format!("{:?}", token));
+            if tokens.len() == n {
+                break;
+            }
+        }
+        tokens
+    };
+
+    let expected = tokens(
+        &mut Tokenizer::new_with_emitter(input, BufferedEmitter::default()),
+        usize::MAX,
+    );
+    for i in 1..expected.len() {
+        let mut tokenizer = Tokenizer::new_with_emitter(input, BufferedEmitter::default());
+        let mut actual = tokens(&mut tokenizer, i);
+        let Ok(mut resumed) = Tokenizer::resume_with_emitter(
+            input,
+            tokenizer.save_state(),
+            BufferedEmitter::default(),
+        );
+        actual.extend(tokens(&mut resumed, usize::MAX));
+        assert_eq!(actual, expected, "{}", i);
+    }
+}
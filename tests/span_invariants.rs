@@ -0,0 +1,84 @@
+//! Spans must match the input they were reported for. This runs the same checks as the fuzz
+//! target in `fuzz/` with `FUZZ_SPANS=1` over the inputs of all test suites.
+use std::fs::{self, File};
+use std::io::BufReader;
+
+use glob::glob;
+use html5gum::reader::conformance::DOCUMENTS;
+use html5gum::testing::html5lib::TestFile;
+use html5gum::testing::spans::{check_span_invariants, SpanViolation};
+use html5gum::Span;
+use proptest::prelude::*;
+
+fn check(input: &[u8]) {
+    if let Err(violation) = check_span_invariants(input) {
+        panic!("{}\ninput: {:?}", violation, String::from_utf8_lossy(input));
+    }
+}
+
+#[test]
+fn tokenizer_tests() {
+    let mut count = 0;
+    for path in glob("tests/*html5lib-tests/tokenizer/*.test").unwrap() {
+        let path = path.unwrap();
+        let TestFile { tests } =
+            serde_json::from_reader(BufReader::new(File::open(&path).unwrap()))
+                .unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        for test in tests {
+            check(&test.input);
+            count += 1;
+        }
+    }
+    assert!(count > 0);
+}
+
+#[test]
+fn tree_construction_tests() {
+    let mut count = 0;
+    for path in glob("tests/*html5lib-tests/tree-construction/*.dat").unwrap() {
+        let content = fs::read_to_string(path.unwrap()).unwrap();
+        for test in content.split("\n#data\n") {
+            let test = test.strip_prefix("#data\n").unwrap_or(test);
+            let data = test.find("\n#errors").map_or(test, |end| &test[..end]);
+            check(data.as_bytes());
+            count += 1;
+        }
+    }
+    assert!(count > 0);
+}
+
+#[test]
+fn conformance_documents() {
+    for document in DOCUMENTS {
+        for end in 0..=document.len() {
+            check(&document[..end]);
+        }
+    }
+}
+
+#[test]
+fn display() {
+    let violation = SpanViolation {
+        span: Span { start: 1, end: 3 },
+        message: "string span does not decode to the string".to_owned(),
+        source: "ab".to_owned(),
+    };
+    assert_eq!(
+        violation.to_string(),
+        "string span does not decode to the string at 1..3: \"ab\""
+    );
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(256))]
+
+    #[test]
+    fn arbitrary_bytes(input in prop::collection::vec(any::<u8>(), 0..256)) {
+        check(&input);
+    }
+
+    #[test]
+    fn arbitrary_markup(input in "([<>/=\"' a-cA-C&#;!-]|&amp|&lt|\r\n|\0){0,64}") {
+        check(input.as_bytes());
+    }
+}
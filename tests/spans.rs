@@ -0,0 +1,300 @@
+use std::convert::Infallible;
+
+use html5gum::emitters::callback::{Callback, CallbackEmitter, CallbackEvent};
+use html5gum::testutils::SlowReader;
+use html5gum::{DefaultEmitter, Readable, Reader, Span, Token, Tokenizer};
+use pretty_assertions::assert_eq;
+
+mod testutils;
+
+/// Tokenize `input` and check that all spans make sense. Returns the input slices of all
+/// non-error tokens.
+fn check(input: &str) -> Vec<&str> {
+    let tokens = testutils::tokenize_with_spans(input);
+    assert_eq!(
+        testutils::tokenize_with_spans(SlowReader(input.to_reader())),
+        tokens
+    );
+
+    let mut slices = Vec::new();
+    let mut end = 0;
+    for (token, span) in &tokens {
+        assert!(span.start <= span.end, "{:?} {:?}", token, span);
+        assert!(span.end <= input.len(), "{:?} {:?}", token, span);
+        let slice = &input[span.start..span.end];
+
+        match token {
+            Token::Error(_) => {
+                assert!(span.is_empty());
+                continue;
+            }
+            Token::StartTag(_) | Token::EndTag(_) | Token::Comment(_) | Token::Doctype(_) => {
+                assert!(slice.starts_with('<'), "{:?} {:?}", token, slice);
+            }
+            Token::String(_) => {}
+        }
+
+        // tokens do not overlap, and only skip over markup that does not produce tokens
+        assert!(span.start >= end, "{:?} {:?}", token, span);
+        assert!(!input[end..span.start].contains(char::is_alphanumeric));
+        end = span.end;
+        slices.push(slice);
+    }
+
+    slices
+}
+
+#[test]
+fn basic() {
+    assert_eq!(
+        check("<!DOCTYPE html><p class=a>hello</p><!-- world -->"),
+        [
+            "<!DOCTYPE html>",
+            "<p class=a>",
+            "hello",
+            "</p>",
+            "<!-- world -->"
+        ]
+    );
+}
+
+#[test]
+fn multibyte() {
+    assert_eq!(
+        check("<p title='ä€𝄞'>ä€𝄞</p>𝄞"),
+        ["<p title='ä€𝄞'>", "ä€𝄞", "</p>", "𝄞"]
+    );
+}
+
+#[test]
+fn character_references() {
+    let input = "<a title=\"&amp;é&#x41;&lt\">&eacute;&#128512;</a>";
+    match testutils::tokenize_with_spans(input).remove(1) {
+        (Token::StartTag(tag), span) => {
+            assert_eq!(tag.attributes[b"title".as_slice()], "&éA<".as_bytes());
+            assert_eq!(
+                &input[span.start..span.end],
+                "<a title=\"&amp;é&#x41;&lt\">"
+            );
+        }
+        other => panic!("unexpected token {:?}", other),
+    }
+
+    assert_eq!(
+        check(input),
+        ["<a title=\"&amp;é&#x41;&lt\">", "&eacute;&#128512;", "</a>"]
+    );
+}
+
+#[test]
+fn newlines() {
+    assert_eq!(
+        check("a\r\n<b\r\nc=\"\r\n\">\r\r\n<!--\r-->\r"),
+        ["a\r\n", "<b\r\nc=\"\r\n\">", "\r\r\n", "<!--\r-->", "\r"]
+    );
+}
+
+#[test]
+fn errors() {
+    let input = "<p a=1 a=2>\0</p x>";
+    let tokens = testutils::tokenize_with_spans(input);
+    assert!(tokens
+        .iter()
+        .any(|(token, _)| matches!(token, Token::Error(_))));
+    assert_eq!(check(input), ["<p a=1 a=2>", "\0", "</p x>"]);
+}
+
+#[test]
+fn eof() {
+    // unterminated tags are dropped
+    assert_eq!(check("a<b c"), ["a<b c"]);
+    assert_eq!(check("a<!-- b"), ["a", "<!-- b"]);
+    assert_eq!(check("a<!DOCTYPE"), ["a", "<!DOCTYPE"]);
+    assert_eq!(check("a<"), ["a<"]);
+    assert_eq!(check("a</"), ["a</"]);
+    assert_eq!(check(""), Vec::<&str>::new());
+}
+
+#[test]
+fn markup_without_tokens() {
+    assert_eq!(check("a</>b"), ["a</>b"]);
+    assert_eq!(check("a<3b"), ["a<3b"]);
+    assert_eq!(check("a<?b>c"), ["a", "<?b>", "c"]);
+}
+
+#[test]
+fn text_states() {
+    assert_eq!(
+        check("<script>a<b></c></script><title>&amp;<</title>"),
+        [
+            "<script>",
+            "a<b></c>",
+            "</script>",
+            "<title>",
+            "&amp;<",
+            "</title>"
+        ]
+    );
+    assert_eq!(
+        check("<script><!--<script></script>--></script>x"),
+        ["<script>", "<!--<script></script>-->", "</script>", "x"]
+    );
+    assert_eq!(
+        check("<style>a</styl</style>"),
+        ["<style>", "a</styl", "</style>"]
+    );
+}
+
+#[test]
+fn resume() {
+    let input = "<html><body>a<b>c</b>";
+    let mut tokenizer = Tokenizer::new_with_emitter(input, DefaultEmitter::with_spans());
+    let (_, body) = tokenizer.nth(1).unwrap().unwrap();
+    let context = tokenizer.context_at_last_token();
+
+    let tokenizer =
+        Tokenizer::resume_at_with_emitter(input, body.end, context, DefaultEmitter::with_spans())
+            .unwrap();
+    let slices: Vec<_> = tokenizer
+        .flatten()
+        .map(|(_, span)| &input[span.start..span.end])
+        .collect();
+    assert_eq!(slices, ["a", "<b>", "c", "</b>"]);
+}
+
+/// Collects the decoded value of every attribute, together with its span.
+#[derive(Default)]
+struct AttributeValues {
+    span: Option<Span>,
+    values: Vec<(String, Span)>,
+}
+
+impl Callback<Infallible> for AttributeValues {
+    fn handle_event(&mut self, event: CallbackEvent<'_>) -> Option<Infallible> {
+        let value = match event {
+            CallbackEvent::AttributeValue { value, .. } => value,
+            CallbackEvent::Attribute {
+                value: Some(value), ..
+            } => value,
+            _ => return None,
+        };
+        let span = self.span.take().expect("no span for attribute value");
+        self.values
+            .push((String::from_utf8(value.to_vec()).unwrap(), span));
+        None
+    }
+
+    fn set_attribute_value_span(&mut self, span: Span) {
+        self.span = Some(span);
+    }
+}
+
+/// Return the decoded value and the source of every attribute value in `input`.
+fn attribute_values(input: &str) -> Vec<(String, &str)> {
+    fn collect<R: Reader>(reader: R, pair_attributes: bool) -> Vec<(String, Span)> {
+        let mut emitter = CallbackEmitter::new(AttributeValues::default());
+        emitter.pair_attributes(pair_attributes);
+        let mut tokenizer = Tokenizer::new_with_emitter(reader, emitter);
+        assert!(tokenizer.next().is_none());
+        std::mem::take(&mut tokenizer.emitter_mut().callback_mut().values)
+    }
+
+    let mut results = Vec::new();
+    for pair_attributes in [false, true] {
+        let values = collect(input.to_reader(), pair_attributes);
+        assert_eq!(
+            collect(SlowReader(input.to_reader()), pair_attributes),
+            values
+        );
+        results.push(
+            values
+                .into_iter()
+                .map(|(value, span)| (value, &input[span.start..span.end]))
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    let paired = results.pop().unwrap();
+    let unpaired = results.pop().unwrap();
+    // empty values are only visited with pair_attributes
+    assert_eq!(
+        paired
+            .iter()
+            .filter(|(value, _)| !value.is_empty())
+            .collect::<Vec<_>>(),
+        unpaired.iter().collect::<Vec<_>>()
+    );
+    paired
+}
+
+#[test]
+fn attribute_value_spans() {
+    let pairs = |pairs: &[(&str, &str)]| -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(value, source)| ((*value).to_owned(), (*source).to_owned()))
+            .collect()
+    };
+    let check = |input: &str, expected: &[(&str, &str)]| {
+        let actual: Vec<_> = attribute_values(input)
+            .into_iter()
+            .map(|(value, source)| (value, source.to_owned()))
+            .collect();
+        assert_eq!(actual, pairs(expected), "{:?}", input);
+    };
+
+    // named references, with and without semicolon, at the start, middle and end of the value
+    check(
+        "<a href=\"a&amp;b\" c='&amp' d=&amp; e=\"x&lt\">",
+        &[
+            ("a&b", "a&amp;b"),
+            ("&", "&amp"),
+            ("&", "&amp;"),
+            ("x<", "x&lt"),
+        ],
+    );
+    // numeric references
+    check(
+        "<a b=\"&#65;&#x42;\" c=&#x43 d='&#0;'>",
+        &[("AB", "&#65;&#x42;"), ("C", "&#x43"), ("\u{fffd}", "&#0;")],
+    );
+    // ambiguous ampersands and references that are not decoded in attribute values
+    check(
+        "<a b=\"&ampx\" c=\"&notit;\" d=&unknown; e='& &#'>",
+        &[
+            ("&ampx", "&ampx"),
+            ("&notit;", "&notit;"),
+            ("&unknown;", "&unknown;"),
+            ("& &#", "& &#"),
+        ],
+    );
+    // empty and missing values, newlines and multibyte characters
+    check(
+        "<a b=\"\" c='' d=\"\r\n&eacute;\r\"\r\ne=\u{e4}&amp;\r\n>",
+        &[
+            ("", ""),
+            ("", ""),
+            ("\n\u{e9}\n", "\r\n&eacute;\r"),
+            ("\u{e4}&", "\u{e4}&amp;"),
+        ],
+    );
+    check("<a b=>", &[("", "")]);
+    check("<a b= c>", &[("c", "c")]);
+    check("<a b=\"c\"d=e>", &[("c", "c"), ("e", "e")]);
+    check("<a b=c/>", &[("c/", "c/")]);
+}
+
+#[test]
+fn string_spans_with_character_references() {
+    for input in [
+        "a&amp;b",
+        "&#x41;x",
+        "a&unknown;",
+        "&ampx&notit;",
+        "x&lt",
+        "&#0;&",
+    ] {
+        assert_eq!(check(input), [input]);
+    }
+    assert_eq!(check("&amp<b>&#65"), ["&amp", "<b>", "&#65"]);
+}
@@ -0,0 +1,308 @@
+//! The state returned by [`Emitter::emit_current_tag`] has to take precedence over the state the
+//! tokenizer would switch to otherwise, no matter through which chain of (inlined) states the end
+//! of the tag was reached.
+use std::io::Read;
+
+use html5gum::testutils::SlowReader;
+use html5gum::{
+    DefaultEmitter, Emitter, EndTag, Error, IoReader, Readable, Reader, State, Token, Tokenizer,
+};
+use pretty_assertions::assert_eq;
+
+mod testutils;
+
+use testutils::string;
+
+/// Switches to a custom state after specific start and end tags, otherwise behaves like the
+/// [`DefaultEmitter`].
+#[derive(Debug, Default)]
+struct OverrideEmitter {
+    inner: DefaultEmitter,
+    tag_name: Vec<u8>,
+    is_end_tag: bool,
+}
+
+impl Emitter for OverrideEmitter {
+    type Token = Token;
+
+    fn set_last_start_tag(&mut self, last_start_tag: Option<&[u8]>) {
+        self.inner.set_last_start_tag(last_start_tag)
+    }
+
+    fn last_start_tag(&self) -> Option<&[u8]> {
+        self.inner.last_start_tag()
+    }
+
+    fn emit_eof(&mut self) {
+        self.inner.emit_eof()
+    }
+
+    fn emit_error(&mut self, error: Error) {
+        self.inner.emit_error(error)
+    }
+
+    fn should_emit_errors(&mut self) -> bool {
+        false
+    }
+
+    fn pop_token(&mut self) -> Option<Self::Token> {
+        self.inner.pop_token()
+    }
+
+    fn emit_string(&mut self, c: &[u8]) {
+        self.inner.emit_string(c)
+    }
+
+    fn init_start_tag(&mut self) {
+        self.tag_name.clear();
+        self.is_end_tag = false;
+        self.inner.init_start_tag()
+    }
+
+    fn init_end_tag(&mut self) {
+        self.tag_name.clear();
+        self.is_end_tag = true;
+        self.inner.init_end_tag()
+    }
+
+    fn init_comment(&mut self) {
+        self.inner.init_comment()
+    }
+
+    fn emit_current_tag(&mut self) -> Option<State> {
+        assert_eq!(self.inner.emit_current_tag(), None);
+        match (self.is_end_tag, &self.tag_name[..]) {
+            (false, b"xplain") => Some(State::PlainText),
+            (false, b"xrc") => Some(State::RcData),
+            (false, b"xraw") => Some(State::RawText),
+            (false, b"xscript") => Some(State::ScriptData),
+            (true, b"xrc" | b"xraw" | b"xscript" | b"xend") => Some(State::PlainText),
+            _ => None,
+        }
+    }
+
+    fn emit_current_comment(&mut self) {
+        self.inner.emit_current_comment()
+    }
+
+    fn emit_current_doctype(&mut self) {
+        self.inner.emit_current_doctype()
+    }
+
+    fn set_self_closing(&mut self) {
+        self.inner.set_self_closing()
+    }
+
+    fn set_force_quirks(&mut self) {
+        self.inner.set_force_quirks()
+    }
+
+    fn push_tag_name(&mut self, s: &[u8]) {
+        self.tag_name.extend(s);
+        self.inner.push_tag_name(s)
+    }
+
+    fn push_comment(&mut self, s: &[u8]) {
+        self.inner.push_comment(s)
+    }
+
+    fn push_doctype_name(&mut self, s: &[u8]) {
+        self.inner.push_doctype_name(s)
+    }
+
+    fn init_doctype(&mut self) {
+        self.inner.init_doctype()
+    }
+
+    fn init_attribute(&mut self) {
+        self.inner.init_attribute()
+    }
+
+    fn push_attribute_name(&mut self, s: &[u8]) {
+        self.inner.push_attribute_name(s)
+    }
+
+    fn init_attribute_value(&mut self) {
+        self.inner.init_attribute_value()
+    }
+
+    fn push_attribute_value(&mut self, s: &[u8]) {
+        self.inner.push_attribute_value(s)
+    }
+
+    fn set_doctype_public_identifier(&mut self, value: &[u8]) {
+        self.inner.set_doctype_public_identifier(value)
+    }
+
+    fn set_doctype_system_identifier(&mut self, value: &[u8]) {
+        self.inner.set_doctype_system_identifier(value)
+    }
+
+    fn push_doctype_public_identifier(&mut self, s: &[u8]) {
+        self.inner.push_doctype_public_identifier(s)
+    }
+
+    fn push_doctype_system_identifier(&mut self, s: &[u8]) {
+        self.inner.push_doctype_system_identifier(s)
+    }
+
+    fn current_is_appropriate_end_tag_token(&mut self) -> bool {
+        self.inner.current_is_appropriate_end_tag_token()
+    }
+
+    fn adjusted_current_node_present_but_not_in_html_namespace(&mut self) -> bool {
+        self.inner
+            .adjusted_current_node_present_but_not_in_html_namespace()
+    }
+    fn set_token_start(&mut self, offset: usize) {
+        self.inner.set_token_start(offset)
+    }
+
+    fn set_position(&mut self, offset: usize) {
+        self.inner.set_position(offset)
+    }
+}
+
+/// A `Read` that returns one byte per call, so that the [`IoReader`] has to refill its buffer all
+/// the time.
+struct OneByteAtATime<'a>(&'a [u8]);
+
+impl Read for OneByteAtATime<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.0.is_empty() || buf.is_empty() {
+            return Ok(0);
+        }
+        buf[0] = self.0[0];
+        self.0 = &self.0[1..];
+        Ok(1)
+    }
+}
+
+fn tokenize<'a, R: Reader>(input: impl Readable<'a, Reader = R>) -> Vec<Token> {
+    Tokenizer::new_with_emitter(input, OverrideEmitter::default())
+        .map(|token| token.unwrap_or_else(|_| panic!("reader failed")))
+        .collect()
+}
+
+fn end_tag(name: &str) -> Token {
+    Token::EndTag(EndTag {
+        name: name.as_bytes().to_vec().into(),
+    })
+}
+
+/// Tokenize `markup` followed by `rest`, with various readers and alignments, and check that
+/// `rest` produces `expected`.
+fn check(markup: &str, rest: &str, expected: &[Token]) {
+    for padding in 0..8 {
+        let input = format!("{}{}{}", "p".repeat(padding), markup, rest);
+        let mut expected_tokens = tokenize(&format!("{}{}", "p".repeat(padding), markup));
+        expected_tokens.extend(expected.iter().cloned());
+
+        let readers: [(&str, Vec<Token>); 3] = [
+            ("StringReader", tokenize(input.as_str())),
+            (
+                "SlowReader",
+                tokenize(SlowReader(input.as_str().to_reader())),
+            ),
+            (
+                "IoReader",
+                tokenize(IoReader::new_with_buffer_size::<16>(OneByteAtATime(
+                    input.as_bytes(),
+                ))),
+            ),
+        ];
+
+        for (reader, tokens) in readers {
+            assert_eq!(
+                tokens, expected_tokens,
+                "reader: {}, input: {:?}",
+                reader, input
+            );
+        }
+    }
+}
+
+#[test]
+fn start_tags() {
+    for markup in [
+        // TagName
+        "<xplain>",
+        // SelfClosingStartTag
+        "<xplain/>",
+        // BeforeAttributeName
+        "<xplain >",
+        "<xplain / >",
+        // AttributeName, inlined into AfterAttributeName
+        "<xplain a>",
+        "<xplain a/>",
+        // AfterAttributeName
+        "<xplain a >",
+        // AttributeValueDoubleQuoted, inlined into AfterAttributeValueQuoted
+        "<xplain a=\"1\">",
+        "<xplain a=\"1\"/>",
+        // AttributeValueSingleQuoted, inlined into AfterAttributeValueQuoted
+        "<xplain a='1'>",
+        "<xplain a='1' >",
+        // AttributeValueUnquoted
+        "<xplain a=1>",
+        "<xplain a=&amp;>",
+        "<xplain a=1 b=\"2\" c>",
+    ] {
+        check(markup, "<b>&amp;</xplain>", &[string("<b>&amp;</xplain>")]);
+
+        let markup = markup.replace("xplain", "xrc");
+        check(
+            &markup,
+            "<b>&amp;</xrc>rest",
+            &[string("<b>&"), end_tag("xrc"), string("rest")],
+        );
+    }
+}
+
+#[test]
+fn end_tags() {
+    for markup in [
+        "</xend>",
+        "</xend/>",
+        "</xend >",
+        "</xend a>",
+        "</xend a='1'>",
+    ] {
+        check(markup, "<b>&amp;</b>", &[string("<b>&amp;</b>")]);
+    }
+}
+
+#[test]
+fn end_tags_in_text_states() {
+    // RcDataEndTagName, RawTextEndTagName, ScriptDataEndTagName and
+    // ScriptDataEscapedEndTagName, followed by the states of the end tag
+    for (start, name, content) in [
+        ("<xrc>", "xrc", "a&amp;"),
+        ("<xraw>", "xraw", "a&amp;"),
+        ("<xscript>", "xscript", "a&amp;"),
+        ("<xscript>", "xscript", "<!-- a&amp;"),
+    ] {
+        for end in [
+            "</NAME>",
+            "</NAME/>",
+            "</NAME >",
+            "</NAME a='1'>",
+            "</NAME a>",
+        ] {
+            let markup = format!("{}{}{}", start, content, end.replace("NAME", name));
+            check(&markup, "<b>&amp;</b>", &[string("<b>&amp;</b>")]);
+        }
+    }
+}
+
+#[test]
+fn no_override() {
+    let tokens = tokenize("<p a='1'><i>x</i>");
+    assert_eq!(tokens.len(), 4);
+    assert_eq!(tokens[2..], [string("x"), end_tag("i")]);
+
+    // only the end tag of xend has an override
+    let tokens = tokenize("<xend>a<b></xend><b>");
+    assert_eq!(tokens.len(), 5);
+    assert_eq!(tokens[3..], [end_tag("xend"), string("<b>")]);
+}
@@ -0,0 +1,64 @@
+use html5gum::stats::{document_stats, Stats};
+use pretty_assertions::assert_eq;
+
+fn stats(input: &str) -> Stats {
+    let Ok(stats) = document_stats(input);
+    stats
+}
+
+#[test]
+fn empty() {
+    assert_eq!(stats(""), Stats::default());
+    assert_eq!(stats("<!DOCTYPE html>"), Stats::default());
+}
+
+#[test]
+fn text() {
+    // character references count after decoding, errors don't split the text
+    let s = stats("<p>abc</p>&amp;&notin;\0xy<br>a<!---->ab");
+    assert_eq!(s.longest_text, "&\u{2209}\0xy".len());
+
+    // the contents of raw text elements are text, too
+    let s = stats("<p>a</p><script>if (a < b) { c() }</script><style>b</style>");
+    assert_eq!(s.longest_text, "if (a < b) { c() }".len());
+
+    let long = "x".repeat(100_000);
+    let s = stats(&format!(
+        "<title>{0}</title><textarea>{0}{0}</textarea>",
+        long
+    ));
+    assert_eq!(s.longest_text, 200_000);
+}
+
+#[test]
+fn attributes() {
+    let s = stats("<a href=/foo title='a &amp; b' download><img src=x alt=\"\">");
+    assert_eq!(s.longest_attribute_value, "a & b".len());
+    assert_eq!(s.max_attributes, 3);
+
+    // duplicate attributes and attributes of end tags are not counted
+    let s = stats("<a x=1 x=22222 y=3></a z=4444444 w v u>");
+    assert_eq!(s.longest_attribute_value, 1);
+    assert_eq!(s.max_attributes, 2);
+}
+
+#[test]
+fn comments() {
+    let s = stats("<!-- a --><!--abcdef--><!x><!-- unterminated");
+    assert_eq!(s.longest_comment, " unterminated".len());
+    assert_eq!(s.longest_text, 0);
+}
+
+#[test]
+fn depth() {
+    assert_eq!(stats("<div><p><b>a</b></p><p>b</p></div>").max_depth, 3);
+
+    // void elements and self-closing tags don't open an element
+    assert_eq!(stats("<p><br><img><input><svg><path/></svg>").max_depth, 2);
+
+    // stray end tags don't make the depth negative
+    assert_eq!(stats("</a></b></c><div><div></div></div>").max_depth, 2);
+
+    // implicitly closed elements stay open
+    assert_eq!(stats("<ul><li>a<li>b<li>c</ul>").max_depth, 4);
+}
@@ -0,0 +1,138 @@
+use std::future::poll_fn;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+
+use futures_core::Stream;
+use html5gum::stream::{tokens, StreamOptions, TokenStream};
+use html5gum::{DefaultEmitter, Token, Tokenizer};
+use pretty_assertions::assert_eq;
+
+fn document() -> String {
+    let mut html = String::from("<!DOCTYPE html><title>a <b> title</title>");
+    for i in 0..500 {
+        html.push_str(&format!(
+            "<p class=x{}>hello &amp; world</p><!-- {} -->\0",
+            i, i
+        ));
+    }
+    html
+}
+
+async fn collect<S: Stream + Unpin>(mut stream: S) -> Vec<S::Item> {
+    let mut rv = Vec::new();
+    while let Some(item) = poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await {
+        rv.push(item);
+    }
+    rv
+}
+
+#[tokio::test]
+async fn same_as_sync() {
+    let html = document();
+    let expected: Vec<_> = Tokenizer::new(&html).collect();
+
+    for options in [
+        StreamOptions::default(),
+        StreamOptions {
+            tokens_per_yield: 1,
+            bytes_per_yield: usize::MAX,
+        },
+        StreamOptions {
+            tokens_per_yield: 0,
+            bytes_per_yield: 0,
+        },
+    ] {
+        assert_eq!(collect(tokens(&html, options)).await, expected);
+    }
+
+    let mut emitter = DefaultEmitter::default();
+    emitter.naively_switch_states(true);
+    let stream = TokenStream::new(
+        Tokenizer::new_with_emitter(&html, emitter),
+        StreamOptions::default(),
+    );
+    let tokens = collect(stream).await;
+    assert_eq!(
+        tokens[1].as_ref().unwrap(),
+        &Token::StartTag(html5gum::StartTag {
+            name: b"title".to_vec().into(),
+            ..Default::default()
+        })
+    );
+    assert_eq!(
+        tokens[2].as_ref().unwrap(),
+        &Token::String(b"a <b> title".to_vec().into())
+    );
+}
+
+#[derive(Default)]
+struct CountingWaker(AtomicUsize);
+
+impl Wake for CountingWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// Poll the stream to completion, returning the number of tokens between each `Poll::Pending`.
+fn poll_runs(mut stream: impl Stream + Unpin) -> Vec<usize> {
+    let counter = Arc::new(CountingWaker::default());
+    let waker = Waker::from(counter.clone());
+    let mut cx = Context::from_waker(&waker);
+
+    let mut runs = vec![0];
+    loop {
+        match Pin::new(&mut stream).poll_next(&mut cx) {
+            Poll::Ready(Some(_)) => *runs.last_mut().unwrap() += 1,
+            Poll::Ready(None) => break,
+            Poll::Pending => {
+                // the stream has to wake itself up, otherwise the executor would never poll it
+                // again
+                assert_eq!(counter.0.load(Ordering::SeqCst), runs.len());
+                runs.push(0);
+            }
+        }
+    }
+
+    runs
+}
+
+#[test]
+fn yields_every_n_tokens() {
+    let html = document();
+    let total = Tokenizer::new(&html).count();
+
+    let runs = poll_runs(tokens(
+        &html,
+        StreamOptions {
+            tokens_per_yield: 7,
+            bytes_per_yield: usize::MAX,
+        },
+    ));
+    assert_eq!(runs.iter().sum::<usize>(), total);
+    assert!(runs.iter().all(|&run| run <= 7));
+    assert_eq!(runs.len(), total / 7 + 1);
+}
+
+#[test]
+fn yields_every_m_bytes() {
+    let html = "<p>".to_owned() + &"x".repeat(1000) + &"<br>".repeat(100);
+    let runs = poll_runs(tokens(
+        html.as_str(),
+        StreamOptions {
+            tokens_per_yield: usize::MAX,
+            bytes_per_yield: 20,
+        },
+    ));
+
+    // the large string exceeds the limit on its own, the <br>s are 2 bytes each
+    assert_eq!(runs[..2], [2, 10]);
+    assert!(runs[2..].iter().all(|&run| run <= 10));
+    assert_eq!(runs.iter().sum::<usize>(), 102);
+}
@@ -0,0 +1,97 @@
+use std::convert::Infallible;
+
+use html5gum::emitters::callback::{CallbackEmitter, CallbackEvent};
+use html5gum::emitters::strict::{StrictEmitter, StrictError};
+use html5gum::{DefaultEmitter, Error, Token, Tokenizer};
+use pretty_assertions::assert_eq;
+
+const VALID: &str = "<!DOCTYPE html><title>a &amp; b</title>\n\
+                     <p class=x id='y'>hello <br/>world &#x41;</p><!-- c -->";
+
+const INVALID: &str = "<!DOCTYPE html><p class=x>fish &amp chips</p><p>more</p>";
+
+/// The error is emitted once the character after `&amp` has been read.
+fn invalid_offset() -> usize {
+    INVALID.find(" chips").unwrap() + 1
+}
+
+fn strict_tokens(input: &str) -> (Vec<Result<Token, StrictError>>, bool) {
+    let emitter = StrictEmitter::new(DefaultEmitter::default());
+    let mut tokenizer = Tokenizer::new_with_emitter(input, emitter);
+    let tokens = tokenizer.by_ref().map(|token| token.unwrap()).collect();
+    (tokens, tokenizer.is_truncated())
+}
+
+#[test]
+fn valid_document() {
+    let expected: Vec<_> = Tokenizer::new(VALID).flatten().map(Ok).collect();
+    assert!(expected.len() > 10);
+    assert_eq!(strict_tokens(VALID), (expected, false));
+}
+
+#[test]
+fn missing_semicolon() {
+    let expected: Vec<_> = Tokenizer::new(INVALID).flatten().take(2).map(Ok).collect();
+    assert_eq!(
+        strict_tokens(INVALID),
+        (
+            expected
+                .into_iter()
+                .chain([Err(StrictError {
+                    error: Error::MissingSemicolonAfterCharacterReference,
+                    offset: invalid_offset(),
+                })])
+                .collect(),
+            true
+        )
+    );
+}
+
+#[test]
+fn first_error_only() {
+    let (tokens, truncated) = strict_tokens("</>x<p a a>");
+    assert_eq!(
+        tokens,
+        [Err(StrictError {
+            error: Error::MissingEndTagName,
+            offset: 3,
+        })]
+    );
+    assert!(truncated);
+}
+
+#[test]
+fn callback_emitter() {
+    let callback = |event: CallbackEvent<'_>| -> Option<Result<String, Infallible>> {
+        match event {
+            CallbackEvent::Error(error) => panic!("got {:?}", error),
+            CallbackEvent::OpenStartTag { name } => {
+                Some(Ok(String::from_utf8_lossy(name).into_owned()))
+            }
+            _ => None,
+        }
+    };
+
+    let run = |input: &str| -> Vec<Result<Result<String, Infallible>, StrictError>> {
+        let mut emitter = CallbackEmitter::new(callback);
+        emitter.naively_switch_states(true);
+        Tokenizer::new_with_emitter(input, StrictEmitter::new(emitter))
+            .map(|token| token.unwrap())
+            .collect()
+    };
+
+    assert_eq!(
+        run(VALID),
+        ["title", "p", "br"].map(|x| Ok(Ok(x.to_owned())))
+    );
+    assert_eq!(
+        run(INVALID),
+        [
+            Ok(Ok("p".to_owned())),
+            Err(StrictError {
+                error: Error::MissingSemicolonAfterCharacterReference,
+                offset: invalid_offset(),
+            })
+        ]
+    );
+}
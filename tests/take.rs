@@ -0,0 +1,97 @@
+use std::io::{self, Read};
+
+use html5gum::{IoReader, Tokenizer};
+use pretty_assertions::assert_eq;
+
+mod testutils;
+
+#[test]
+fn take_bytes_is_prefix() {
+    for input in testutils::html5lib_inputs() {
+        let input = input.as_bytes();
+        for n in 0..=input.len() {
+            let expected = testutils::tokenize_with_spans(&input[..n]);
+
+            let mut tokenizer = testutils::tokenizer_with_spans(input);
+            tokenizer.take_bytes(n);
+            let tokens: Vec<_> = tokenizer.by_ref().flatten().collect();
+            assert_eq!(tokens, expected, "{:?} {}", input, n);
+            assert_eq!(
+                tokenizer.is_truncated(),
+                n < input.len(),
+                "{:?} {}",
+                input,
+                n
+            );
+        }
+    }
+}
+
+/// Counts how many bytes the tokenizer pulls from the source.
+struct CountingReader<'a> {
+    input: &'a [u8],
+    read: usize,
+}
+
+impl Read for CountingReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.input.read(buf)?;
+        self.read += len;
+        Ok(len)
+    }
+}
+
+#[test]
+fn take_bytes_stops_reading() {
+    const BUF_SIZE: usize = 64;
+
+    let input = format!("<title>preview</title>{}", "lorem ipsum ".repeat(10_000));
+    let mut source = CountingReader {
+        input: input.as_bytes(),
+        read: 0,
+    };
+
+    for n in [0, 10, 100, 1000] {
+        source.input = input.as_bytes();
+        source.read = 0;
+
+        let mut tokenizer = Tokenizer::new(IoReader::new_with_buffer_size::<BUF_SIZE>(&mut source));
+        tokenizer.take_bytes(n);
+        let tokens: Vec<_> = tokenizer.by_ref().map(Result::unwrap).collect();
+        assert!(tokenizer.is_truncated());
+        drop(tokenizer);
+
+        assert_eq!(
+            tokens,
+            Tokenizer::new(&input.as_bytes()[..n])
+                .map(Result::unwrap)
+                .collect::<Vec<_>>()
+        );
+        assert!(source.read <= n + BUF_SIZE, "{} {}", n, source.read);
+    }
+}
+
+#[test]
+fn take_tokens() {
+    let input = "<title>preview</title><p>hello";
+
+    let mut tokenizer = Tokenizer::new(input);
+    tokenizer.take_tokens(2);
+    let tokens: Vec<_> = tokenizer.by_ref().map(Result::unwrap).collect();
+    assert_eq!(
+        tokens,
+        Tokenizer::new(input)
+            .take(2)
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+    );
+    assert!(tokenizer.is_truncated());
+    assert!(tokenizer.next().is_none());
+
+    for n in 5..7 {
+        let mut tokenizer = Tokenizer::new(input);
+        tokenizer.take_tokens(n);
+        assert_eq!(tokenizer.by_ref().count(), 5);
+        assert!(!tokenizer.is_truncated());
+    }
+}
@@ -1,11 +1,12 @@
-use std::backtrace::BacktraceStatus;
-use std::ops::Deref;
-use std::panic::{self, UnwindSafe};
-use std::sync::Once;
+// Not every test uses every helper.
+#![allow(dead_code)]
 
-use libtest_mimic::Failed;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::fs;
 
-use html5gum::testutils::OUTPUT;
+use glob::glob;
+use html5gum::{DefaultEmitter, Readable, Span, Token, Tokenizer};
 
 /// Improved panic messages for the html5lib-tests test suite.
 ///
@@ -18,7 +19,17 @@ use html5gum::testutils::OUTPUT;
 /// our own "log buffer", and append it to the error message in case of test failure. OUTPUT is the
 /// log buffer -- it is bound in size and compiled out in release mode. Code can use the
 /// `crate::trace_log` macro to write lines to it.
-pub fn catch_unwind_and_report(f: impl FnOnce() + UnwindSafe) -> Result<(), Failed> {
+#[cfg(any(debug_assertions, feature = "testing"))]
+pub fn catch_unwind_and_report(
+    f: impl FnOnce() + std::panic::UnwindSafe,
+) -> Result<(), libtest_mimic::Failed> {
+    use std::backtrace::BacktraceStatus;
+    use std::ops::Deref;
+    use std::panic;
+    use std::sync::Once;
+
+    use html5gum::testutils::OUTPUT;
+
     static PANIC_HOOK: Once = Once::new();
     PANIC_HOOK.call_once(|| {
         panic::set_hook(Box::new(|_info| {
@@ -76,3 +87,102 @@ pub fn catch_unwind_and_report(f: impl FnOnce() + UnwindSafe) -> Result<(), Fail
         }
     }
 }
+
+/// The inputs of all tokenizer tests in html5lib-tests and custom-html5lib-tests, except for the
+/// double-escaped ones.
+pub fn html5lib_inputs() -> Vec<String> {
+    let mut inputs = Vec::new();
+    let paths = glob("tests/html5lib-tests/tokenizer/*.test")
+        .unwrap()
+        .chain(glob("tests/custom-html5lib-tests/tokenizer/*.test").unwrap());
+    for path in paths {
+        let tests: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(path.unwrap()).unwrap()).unwrap();
+        for test in tests["tests"].as_array().into_iter().flatten() {
+            if test["doubleEscaped"].as_bool() != Some(true) {
+                inputs.push(test["input"].as_str().unwrap().to_owned());
+            }
+        }
+    }
+
+    assert!(!inputs.is_empty());
+    inputs
+}
+
+/// A string token.
+pub fn string(s: &str) -> Token {
+    Token::String(s.as_bytes().to_vec().into())
+}
+
+/// Tokenize `input`, switching states like the html5lib tests expect.
+pub fn tokenize<'a, R: Readable<'a>>(input: R) -> Vec<Token> {
+    let mut emitter = DefaultEmitter::default();
+    emitter.naively_switch_states(true);
+    Tokenizer::new_with_emitter(input, emitter)
+        .map(|token| token.unwrap_or_else(|_| panic!("reader failed")))
+        .collect()
+}
+
+/// A tokenizer for `input` that reports spans, and switches states like the html5lib tests expect.
+pub fn tokenizer_with_spans<'a, R: Readable<'a>>(
+    input: R,
+) -> Tokenizer<R::Reader, DefaultEmitter<(Token, Span)>> {
+    let mut emitter = DefaultEmitter::with_spans();
+    emitter.naively_switch_states(true);
+    Tokenizer::new_with_emitter(input, emitter)
+}
+
+/// Tokenize `input` with [`tokenizer_with_spans`].
+pub fn tokenize_with_spans<'a, R: Readable<'a>>(input: R) -> Vec<(Token, Span)> {
+    tokenizer_with_spans(input)
+        .map(|token| token.unwrap_or_else(|_| panic!("reader failed")))
+        .collect()
+}
+
+/// Counts allocations and allocated bytes per thread, so that tests running concurrently don't
+/// affect each other. Tests that use [`count_allocations`] or [`peak_memory`] have to install it:
+///
+/// ```ignore
+/// #[global_allocator]
+/// static ALLOC: testutils::CountingAlloc = testutils::CountingAlloc;
+/// ```
+pub struct CountingAlloc;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    // signed, because memory can be freed on another thread than it was allocated on
+    static CURRENT_BYTES: Cell<isize> = const { Cell::new(0) };
+    static PEAK_BYTES: Cell<isize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|x| x.set(x.get() + 1));
+        let current = CURRENT_BYTES.with(|x| {
+            x.set(x.get() + layout.size() as isize);
+            x.get()
+        });
+        PEAK_BYTES.with(|x| x.set(x.get().max(current)));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        CURRENT_BYTES.with(|x| x.set(x.get() - layout.size() as isize));
+        System.dealloc(ptr, layout)
+    }
+}
+
+/// Run `f`, and return how many allocations it made.
+pub fn count_allocations<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATIONS.with(Cell::get);
+    let rv = f();
+    (rv, ALLOCATIONS.with(Cell::get) - before)
+}
+
+/// Run `f`, and return by how many bytes its memory usage peaked above the memory usage before.
+pub fn peak_memory<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = CURRENT_BYTES.with(Cell::get);
+    PEAK_BYTES.with(|x| x.set(before));
+    let rv = f();
+    (rv, (PEAK_BYTES.with(Cell::get) - before) as usize)
+}
@@ -0,0 +1,111 @@
+use html5gum::emitters::trace::TraceEvent;
+use html5gum::{State, Token, Tokenizer};
+use pretty_assertions::assert_eq;
+
+fn trace(input: &str) -> Vec<TraceEvent> {
+    let mut events = Vec::new();
+    Tokenizer::new(input)
+        .set_trace(|event| events.push(event))
+        .for_each(drop);
+    events
+}
+
+fn transitions(input: &str) -> Vec<(&'static str, &'static str, Option<u8>)> {
+    trace(input)
+        .into_iter()
+        .filter_map(|event| match event {
+            TraceEvent::StateTransition { from, to, byte } => Some((from, to, byte)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn start_tag_with_attribute() {
+    assert_eq!(
+        transitions("<a href=x>"),
+        [
+            ("Data", "TagOpen", Some(b'<')),
+            ("TagOpen", "TagName", Some(b'a')),
+            ("TagName", "BeforeAttributeName", Some(b' ')),
+            ("BeforeAttributeName", "AttributeName", Some(b'h')),
+            ("AttributeName", "BeforeAttributeValue", Some(b'=')),
+            ("BeforeAttributeValue", "AttributeValueUnquoted", Some(b'x')),
+            ("AttributeValueUnquoted", "Data", Some(b'>')),
+        ]
+    );
+}
+
+#[test]
+fn emitter_calls() {
+    let calls: Vec<_> = trace("<a href=x>")
+        .into_iter()
+        .filter_map(|event| match event {
+            TraceEvent::EmitterCall { name } => Some(name),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        calls,
+        [
+            "begin",
+            "set_token_start",
+            "init_start_tag",
+            "push_tag_name",
+            "push_tag_name_original",
+            "init_attribute",
+            "push_attribute_name",
+            "push_attribute_name_original",
+            "push_attribute_name",
+            "push_attribute_name_original",
+            "init_attribute_value",
+            "set_attribute_value_start",
+            "set_attribute_value_syntax",
+            "push_attribute_value",
+            "set_attribute_value_end",
+            "emit_current_tag",
+            "adjusted_current_node_present_but_not_in_html_namespace",
+            "emit_eof",
+            "finish",
+        ]
+    );
+}
+
+#[test]
+fn reads() {
+    let reads: Vec<_> = trace("hello <b>world</b>")
+        .into_iter()
+        .filter_map(|event| match event {
+            TraceEvent::Read { bytes } => Some(bytes),
+            _ => None,
+        })
+        .collect();
+    // `hello `, `b`, `>`, `world`, `b`, `>`
+    assert_eq!(reads, [6, 1, 1, 5, 1, 1]);
+}
+
+#[test]
+fn character_reference() {
+    // the named character reference is only known to be over after reading `<`
+    assert_eq!(
+        transitions("&amp;<"),
+        [
+            ("Data", "CharacterReference", Some(b'&')),
+            ("CharacterReference", "NamedCharacterReference", Some(b'a')),
+            ("NamedCharacterReference", "Data", Some(b'<')),
+            ("Data", "TagOpen", Some(b'<')),
+        ]
+    );
+}
+
+#[test]
+fn tokens_are_unchanged() {
+    let html = "<!DOCTYPE html><p class=a>x&amp;y<!-- c --></p>";
+    let traced: Vec<_> = Tokenizer::new(html).set_trace(|_| ()).flatten().collect();
+    let plain: Vec<Token> = Tokenizer::new(html).flatten().collect();
+    assert_eq!(traced, plain);
+
+    let mut tokenizer = Tokenizer::new("<script>a</script>");
+    tokenizer.set_state(State::Data);
+    assert_eq!(tokenizer.set_trace(|_| ()).count(), 3);
+}
@@ -0,0 +1,136 @@
+//! Character tokens before `<html>` and `<head>`, in the modes where html5ever drops leading
+//! whitespace and reprocesses the rest. The resulting trees are covered by
+//! `custom-html5lib-tests/tree-construction/custom.dat`, this checks the quirks mode and that the
+//! text is split up correctly across all readers.
+use html5ever::tree_builder::QuirksMode;
+use html5ever::{namespace_url, ns, LocalName, QualName};
+use html5gum::emitters::html5ever::{parse_document, parse_fragment};
+use html5gum::testutils::SlowReader;
+use html5gum::Readable;
+use markup5ever_rcdom::{Handle, NodeData, RcDom};
+use pretty_assertions::assert_eq;
+
+fn text_content(handle: &Handle, buf: &mut String) {
+    if let NodeData::Text { ref contents } = handle.data {
+        buf.push_str(&contents.borrow());
+    }
+
+    for child in handle.children.borrow().iter() {
+        text_content(child, buf);
+    }
+}
+
+fn element_names(handle: &Handle, buf: &mut Vec<String>) {
+    if let NodeData::Element { ref name, .. } = handle.data {
+        buf.push(name.local.to_string());
+    }
+
+    for child in handle.children.borrow().iter() {
+        element_names(child, buf);
+    }
+}
+
+fn check(input: &str, expected_quirks_mode: QuirksMode, expected_text: &str) {
+    let doms = [
+        parse_document(input, RcDom::default(), Default::default()).unwrap(),
+        parse_document(
+            SlowReader(input.to_reader()),
+            RcDom::default(),
+            Default::default(),
+        )
+        .unwrap(),
+    ];
+
+    for dom in doms {
+        let mut text = String::new();
+        text_content(&dom.document, &mut text);
+        assert_eq!(
+            dom.quirks_mode.get(),
+            expected_quirks_mode,
+            "input: {:?}",
+            input
+        );
+        assert_eq!(text, expected_text, "input: {:?}", input);
+    }
+}
+
+#[test]
+fn initial() {
+    check("", QuirksMode::Quirks, "");
+    check(" \n\t", QuirksMode::Quirks, "");
+    check(" \nx", QuirksMode::Quirks, "x");
+    check("x \n", QuirksMode::Quirks, "x \n");
+    check(" \0x", QuirksMode::Quirks, "x");
+    check(" \n<!DOCTYPE html>x", QuirksMode::NoQuirks, "x");
+}
+
+#[test]
+fn before_html() {
+    check("<!DOCTYPE html> \n", QuirksMode::NoQuirks, "");
+    check("<!DOCTYPE html> \nx ", QuirksMode::NoQuirks, "x ");
+    check("<!DOCTYPE html>x", QuirksMode::NoQuirks, "x");
+    check("<!DOCTYPE html> \0x", QuirksMode::NoQuirks, "x");
+}
+
+#[test]
+fn before_head() {
+    check("<!DOCTYPE html><html> \n", QuirksMode::NoQuirks, "");
+    check("<!DOCTYPE html><html> \nx ", QuirksMode::NoQuirks, "x ");
+    check("<!DOCTYPE html><html>x", QuirksMode::NoQuirks, "x");
+    check("<html> \nx", QuirksMode::Quirks, "x");
+
+    // whitespace after the head is kept
+    check(
+        "<!DOCTYPE html><head></head> \nx",
+        QuirksMode::NoQuirks,
+        " \nx",
+    );
+}
+
+#[test]
+fn fragment() {
+    fn check_fragment(context: &str, input: &str, expected_elements: &[&str], expected_text: &str) {
+        for dom in [
+            parse_fragment(
+                input,
+                RcDom::default(),
+                Default::default(),
+                QualName::new(None, ns!(html), LocalName::from(context)),
+                Vec::new(),
+            )
+            .unwrap(),
+            parse_fragment(
+                SlowReader(input.to_reader()),
+                RcDom::default(),
+                Default::default(),
+                QualName::new(None, ns!(html), LocalName::from(context)),
+                Vec::new(),
+            )
+            .unwrap(),
+        ] {
+            let mut elements = Vec::new();
+            element_names(&dom.document, &mut elements);
+            let mut text = String::new();
+            text_content(&dom.document, &mut text);
+            // the fragment is put into a single <html> root
+            assert_eq!(elements[0], "html", "context: {}", context);
+            assert_eq!(elements[1..], *expected_elements, "context: {}", context);
+            assert_eq!(text, expected_text, "context: {}", context);
+        }
+    }
+
+    check_fragment("td", "<b>a</b><td>b</td><tr>c", &["b"], "abc");
+    check_fragment(
+        "select",
+        "<option>a<optgroup><option>b<div>c</div><input><p>d",
+        &["option", "optgroup", "option"],
+        "abcd",
+    );
+    check_fragment("template", "<tr><td>a</td></tr>", &["tr", "td"], "a");
+    check_fragment("title", "a</title><b>&amp;", &[], "a</title><b>&");
+    check_fragment("textarea", "a</textarea>&lt;", &[], "a</textarea><");
+    check_fragment("style", "a</style><b>&amp;", &[], "a</style><b>&amp;");
+    check_fragment("script", "a</script><!--b", &[], "a</script><!--b");
+    check_fragment("plaintext", "a</plaintext><b>", &[], "a</plaintext><b>");
+    check_fragment("div", "a</div><b>c", &["b"], "ac");
+}
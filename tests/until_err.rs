@@ -0,0 +1,66 @@
+use std::io::{self, Read};
+
+use html5gum::{EndTag, IoReader, StartTag, Token, Tokenizer};
+use pretty_assertions::assert_eq;
+
+/// Returns `data` in small chunks, then fails.
+struct FailingRead<'a> {
+    data: &'a [u8],
+}
+
+impl Read for FailingRead<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.data.is_empty() {
+            return Err(io::Error::other("disk on fire"));
+        }
+
+        let len = buf.len().min(self.data.len()).min(3);
+        buf[..len].copy_from_slice(&self.data[..len]);
+        self.data = &self.data[len..];
+        Ok(len)
+    }
+}
+
+fn start_tag(name: &str) -> Token {
+    Token::StartTag(StartTag {
+        self_closing: false,
+        name: name.as_bytes().to_vec().into(),
+        attributes: Default::default(),
+    })
+}
+
+#[test]
+fn stops_at_error() {
+    // the tokenizer looks ahead a few bytes, so the error is reached while reading the text
+    let reader = FailingRead {
+        data: b"<p>hello</p><b>world, this text is long enough to not be reached</b>",
+    };
+    let mut tokenizer = Tokenizer::new(IoReader::new(reader));
+
+    let mut error = Ok(());
+    let tokens: Vec<_> = tokenizer.until_err(&mut error).collect();
+    assert_eq!(
+        tokens,
+        [
+            start_tag("p"),
+            Token::String(b"hello".to_vec().into()),
+            Token::EndTag(EndTag {
+                name: b"p".to_vec().into(),
+            }),
+            start_tag("b"),
+        ]
+    );
+    assert_eq!(error.unwrap_err().to_string(), "disk on fire");
+}
+
+#[test]
+fn no_error() {
+    let mut tokenizer = Tokenizer::new("<p>hello");
+    let mut error = Ok(());
+    let tokens: Vec<_> = tokenizer.until_err(&mut error).collect();
+    assert_eq!(
+        tokens,
+        [start_tag("p"), Token::String(b"hello".to_vec().into())]
+    );
+    let Ok(()) = error;
+}
@@ -0,0 +1,116 @@
+//! `Emitter::emit_string` is only ever called with whole characters, no matter where the reader
+//! splits the input.
+use std::io::Read;
+
+use html5gum::testutils::DebugValidateEmitter;
+use html5gum::{
+    CharIterReader, DefaultEmitter, EndTag, IoReader, Readable, Reader, SliceIterReader, StartTag,
+    Token, Tokenizer,
+};
+use pretty_assertions::assert_eq;
+
+const INPUTS: &[&str] = &[
+    "é€😀",
+    "<p>é€😀</p>",
+    "<é€😀",
+    "</é€😀",
+    "&é€😀 &amp;€",
+    "a\r\né\r€\r\n😀\r",
+    "<title>é€😀</title>",
+    "<textarea><é</é</textarea>",
+    "<style>é€😀<é</style>",
+    "<script>é€😀<é</é</script>",
+    "<script><!--é-é--é<é</é<script>é-é--é<é</é</script>é</script>",
+    "<plaintext>é€😀<é</é",
+    "<!--é€😀-->é",
+    "é\u{fffe}€\u{1}😀",
+];
+
+struct NBytesAtATime<'a>(&'a [u8], usize);
+
+impl Read for NBytesAtATime<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.0.len().min(buf.len()).min(self.1);
+        buf[..n].copy_from_slice(&self.0[..n]);
+        self.0 = &self.0[n..];
+        Ok(n)
+    }
+}
+
+fn tokenize<'a, R: Reader>(input: impl Readable<'a, Reader = R>) -> Vec<Token> {
+    let mut inner = DefaultEmitter::default();
+    inner.naively_switch_states(true);
+    let mut emitter = DebugValidateEmitter::new(inner);
+    emitter.expect_utf8_strings(true);
+    Tokenizer::new_with_emitter(input, emitter)
+        .map(|token| token.unwrap_or_else(|_| panic!("reader failed")))
+        .collect()
+}
+
+#[test]
+fn io_reader() {
+    for input in INPUTS {
+        // straddle every offset of the reader's buffer
+        for padding in 0..16 {
+            let input = format!("{}{}", "p".repeat(padding), input);
+            let expected = tokenize(input.as_str());
+            for n in 1..5 {
+                let reader =
+                    IoReader::new_with_buffer_size::<16>(NBytesAtATime(input.as_bytes(), n));
+                assert_eq!(tokenize(reader), expected, "input: {:?}, n: {}", input, n);
+            }
+        }
+    }
+}
+
+#[test]
+fn slice_iter_reader() {
+    for input in INPUTS {
+        let expected = tokenize(*input);
+        for chunk_size in 1..5 {
+            let reader = SliceIterReader::new(input.as_bytes().chunks(chunk_size));
+            assert_eq!(tokenize(reader), expected, "input: {:?}", input);
+        }
+    }
+}
+
+#[test]
+fn char_iter_reader() {
+    for input in INPUTS {
+        assert_eq!(
+            tokenize(CharIterReader::new(input.chars())),
+            tokenize(*input),
+            "input: {:?}",
+            input
+        );
+    }
+}
+
+#[test]
+fn char_iter_reader_error() {
+    // the characters after the error are buffered before it is returned, but the error still
+    // comes after the tokens before it
+    let chars = "<p>hello"
+        .chars()
+        .map(Ok)
+        .chain(std::iter::once(Err(std::io::Error::other("broken"))))
+        .chain("</p>".chars().map(Ok));
+    let results: Vec<_> = Tokenizer::new(CharIterReader::new(chars))
+        .map(|result| result.map_err(|e| e.to_string()))
+        .collect();
+
+    assert_eq!(
+        results,
+        [
+            Ok(Token::StartTag(StartTag {
+                name: b"p".to_vec().into(),
+                ..StartTag::default()
+            })),
+            Err("broken".to_owned()),
+            Ok(Token::String(b"hello".to_vec().into())),
+            Ok(Token::EndTag(EndTag {
+                name: b"p".to_vec().into()
+            })),
+        ]
+    );
+}
@@ -0,0 +1,160 @@
+use html5gum::emitters::utf8::{self, Token};
+use html5gum::testutils::SlowReader;
+use html5gum::{IoReader, Readable, Reader, SliceIterReader, Tokenizer, Utf8ReaderError};
+use pretty_assertions::assert_eq;
+
+mod testutils;
+
+fn lossy(s: &[u8]) -> String {
+    String::from_utf8_lossy(s).into_owned()
+}
+
+/// Convert the tokens of the default emitter, for comparison.
+fn tokenize_lossy(input: &str) -> Vec<Token> {
+    Tokenizer::new(input)
+        .flatten()
+        .map(|token| match token {
+            html5gum::Token::StartTag(tag) => Token::StartTag(utf8::StartTag {
+                self_closing: tag.self_closing,
+                name: lossy(&tag.name),
+                attributes: tag
+                    .attributes
+                    .iter()
+                    .map(|(name, value)| (lossy(name), lossy(value)))
+                    .collect(),
+            }),
+            html5gum::Token::EndTag(tag) => Token::EndTag(utf8::EndTag {
+                name: lossy(&tag.name),
+            }),
+            html5gum::Token::String(s) => Token::String(lossy(&s)),
+            html5gum::Token::Comment(comment) => Token::Comment(utf8::Comment {
+                value: lossy(&comment.value),
+                kind: comment.kind,
+            }),
+            html5gum::Token::Doctype(doctype) => Token::Doctype(utf8::Doctype {
+                force_quirks: doctype.force_quirks,
+                name: lossy(&doctype.name),
+                public_identifier: doctype.public_identifier.as_deref().map(|s| lossy(s)),
+                system_identifier: doctype.system_identifier.as_deref().map(|s| lossy(s)),
+            }),
+            html5gum::Token::Error(error) => Token::Error(error),
+        })
+        .collect()
+}
+
+#[test]
+fn same_as_default_emitter() {
+    for input in testutils::html5lib_inputs() {
+        let expected = tokenize_lossy(&input);
+
+        let tokens: Vec<_> = Tokenizer::new_utf8(&input).flatten().collect();
+        assert_eq!(tokens, expected, "{:?}", input);
+
+        let tokens: Vec<_> = Tokenizer::new_utf8_bytes(input.as_bytes())
+            .unwrap()
+            .flatten()
+            .collect();
+        assert_eq!(tokens, expected, "{:?}", input);
+
+        let reader = IoReader::new_with_buffer_size::<32>(input.as_bytes());
+        let tokens: Vec<_> = Tokenizer::new_utf8_reader(reader)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(tokens, expected, "{:?}", input);
+
+        let tokens: Vec<_> = Tokenizer::new_utf8_reader(SlowReader(input.to_reader()))
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(tokens, expected, "{:?}", input);
+    }
+}
+
+#[test]
+fn charrefs_and_replacement_characters() {
+    let tokens: Vec<_> = Tokenizer::new_utf8("<a b='&#xd800;&#x110000;\0'>&notin;&#0;&#128;")
+        .flatten()
+        .filter(|token| !matches!(token, Token::Error(_)))
+        .collect();
+    assert_eq!(
+        tokens,
+        [
+            Token::StartTag(utf8::StartTag {
+                self_closing: false,
+                name: "a".to_owned(),
+                attributes: vec![("b".to_owned(), "\u{fffd}\u{fffd}\u{fffd}".to_owned())],
+            }),
+            Token::String("∉\u{fffd}€".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn invalid_bytes() {
+    let error = Tokenizer::new_utf8_bytes(b"<p>caf\xe9</p>").unwrap_err();
+    assert_eq!(error.valid_up_to(), 6);
+}
+
+fn first_error<R: Reader>(reader: R) -> Option<Utf8ReaderError<R::Error>> {
+    Tokenizer::new_utf8_reader(reader).find_map(Result::err)
+}
+
+#[test]
+fn invalid_reader_input() {
+    let cases: &[(&[u8], usize)] = &[
+        (b"<p>\xff", 3),
+        (b"<p>\xc3", 3),
+        (b"<p>\xc3<", 3),
+        (b"ab\xc0\x80", 2),
+        (b"ab\xe0\x80\x80", 2),
+        (b"ab\xed\xa0\x80", 2),
+        (b"ab\xf4\x90\x80\x80", 2),
+        (b"ab\xf0\x9f\x98", 2),
+        (b"<a b=\"\xe2\x82\">", 6),
+        (b"<!--\xe2\x82\xac\x80-->", 7),
+    ];
+
+    for &(input, offset) in cases {
+        assert!(std::str::from_utf8(input).is_err());
+        for reader_error in [
+            first_error(input.to_reader()),
+            first_error(SlowReader(input.to_reader())),
+        ] {
+            match reader_error {
+                Some(Utf8ReaderError::InvalidUtf8 { offset: actual }) => {
+                    assert_eq!(actual, offset, "{:?}", input)
+                }
+                other => panic!("{:?}: unexpected {:?}", input, other),
+            }
+        }
+    }
+
+    let error = first_error(b"\xff".to_reader()).unwrap();
+    assert_eq!(error.to_string(), "invalid UTF-8 at byte offset 0");
+}
+
+#[test]
+fn split_sequences() {
+    let input = "<p title='€'>ä😀\u{10ffff}</p>".as_bytes();
+    let expected: Vec<_> = Tokenizer::new_utf8_bytes(input)
+        .unwrap()
+        .flatten()
+        .collect();
+
+    for i in 0..=input.len() {
+        for j in i..=input.len() {
+            let chunks = [&input[..i], &input[i..j], &input[j..]];
+            let reader = SliceIterReader::new(chunks.iter().copied());
+            let tokens: Vec<_> = Tokenizer::new_utf8_reader(reader)
+                .map(Result::unwrap)
+                .collect();
+            assert_eq!(tokens, expected, "{} {}", i, j);
+        }
+    }
+}
+
+#[test]
+fn emitter_options() {
+    let mut tokenizer = Tokenizer::new_utf8("<script><b></script>");
+    tokenizer.emitter_mut().naively_switch_states(true);
+    assert_eq!(tokenizer.flatten().count(), 3);
+}
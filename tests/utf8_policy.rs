@@ -0,0 +1,161 @@
+use std::io::Read;
+
+use html5gum::testutils::SlowReader;
+use html5gum::{Error, IoReader, Readable, Reader, Span, Token, Tokenizer, Utf8Policy};
+use pretty_assertions::assert_eq;
+
+mod testutils;
+
+const INPUTS: &[&[u8]] = &[
+    // valid
+    "<p>é€😀</p>".as_bytes(),
+    // lone continuation bytes, and bytes that are never valid
+    b"a\x80b\xbf\xc0\xc1\xf5\xff",
+    // overlong encodings of `/`
+    b"<p>\xc0\xaf \xe0\x80\xaf \xf0\x80\x80\xaf</p>",
+    // surrogates encoded as in WTF-8, and a code point above U+10FFFF
+    b"\xed\xa0\x80\xed\xbf\xbf\xed\x9f\xbf \xf4\x90\x80\x80",
+    // sequences that are cut off by ASCII, by another sequence, and by the end of the input
+    b"\xe2\x82a\xe2\x82\xe2\x82\xac\xf0\x9f\x98<p>\xf0\x9f\x98",
+    b"<\xe2\x82a \xff\xe2=\xe2\x82 b='\xff' c=\"\xe2\x82\" d=\xf0\x9f>",
+    b"</\xc3 \xc3>",
+    b"<!--\xff\xe2\x82-->\xc3",
+    b"<!DOCTYPE \xff PUBLIC '\xe2\x82' \"\xff\"><!\xffDOCTYPE>",
+    b"&amp\xff&\xe2\x82;&#x80\xff&not\xe2\x82",
+    b"\xe2\r\n\x82\r\xff\n\xc3\r",
+    b"<title>\xff</tit\xffle></title><script>\xe2\x82</scr\xe2ipt></script>\xed",
+    b"<svg><![CDATA[\xff]]\xe2]]>\xe2\x82",
+];
+
+struct NBytesAtATime<'a>(&'a [u8], usize);
+
+impl Read for NBytesAtATime<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.0.len().min(buf.len()).min(self.1);
+        buf[..n].copy_from_slice(&self.0[..n]);
+        self.0 = &self.0[n..];
+        Ok(n)
+    }
+}
+
+fn tokenize<'a, R: Reader>(
+    input: impl Readable<'a, Reader = R>,
+    policy: Utf8Policy,
+) -> Vec<(Token, Span)> {
+    let mut tokenizer = testutils::tokenizer_with_spans(input);
+    tokenizer.utf8_policy(policy);
+    tokenizer.map(|token| token.unwrap()).collect()
+}
+
+/// Tokenize `input` with all kinds of readers, and check that the result is always the same.
+fn tokenize_everywhere(input: &[u8], policy: Utf8Policy) -> Vec<(Token, Span)> {
+    let expected = tokenize(input, policy);
+    assert_eq!(tokenize(SlowReader(input.to_reader()), policy), expected);
+    for n in 1..=input.len() {
+        let reader = IoReader::with_capacity(NBytesAtATime(input, n), 32);
+        assert_eq!(tokenize(reader, policy), expected, "{} bytes at a time", n);
+    }
+    expected
+}
+
+fn without_errors(tokens: &[(Token, Span)]) -> Vec<&(Token, Span)> {
+    tokens
+        .iter()
+        .filter(|(token, _)| !matches!(token, Token::Error(_)))
+        .collect()
+}
+
+#[test]
+fn replace_invalid() {
+    for &input in INPUTS {
+        let tokens = tokenize_everywhere(input, Utf8Policy::ReplaceInvalid);
+
+        // the same as decoding first, except for spans
+        let lossy = String::from_utf8_lossy(input);
+        let expected: Vec<Token> = tokenize(lossy.as_bytes(), Utf8Policy::PassThrough)
+            .into_iter()
+            .map(|(token, _)| token)
+            .collect();
+        let tokens_without_spans: Vec<Token> =
+            tokens.iter().map(|(token, _)| token.clone()).collect();
+        assert_eq!(tokens_without_spans, expected, "{:?}", lossy);
+
+        // spans refer to the input
+        let passed_through = tokenize(input, Utf8Policy::PassThrough);
+        let spans = |tokens: &[(Token, Span)]| -> Vec<Span> {
+            without_errors(tokens)
+                .into_iter()
+                .map(|&(_, span)| span)
+                .collect()
+        };
+        assert_eq!(spans(&tokens), spans(&passed_through), "{:?}", lossy);
+    }
+}
+
+#[test]
+fn error() {
+    for &input in INPUTS {
+        let tokens = tokenize_everywhere(input, Utf8Policy::Error);
+        let passed_through = tokenize(input, Utf8Policy::PassThrough);
+        assert_eq!(without_errors(&tokens), without_errors(&passed_through));
+
+        let invalid_sequences = String::from_utf8_lossy(input).matches('\u{fffd}').count();
+        let errors = tokens
+            .iter()
+            .filter(|(token, _)| *token == Token::Error(Error::InvalidUtf8InInputStream))
+            .count();
+        assert_eq!(errors, invalid_sequences, "{:?}", input);
+    }
+}
+
+#[test]
+fn pass_through() {
+    for &input in INPUTS {
+        tokenize_everywhere(input, Utf8Policy::PassThrough);
+    }
+
+    let tokens = tokenize(&b"a\xffb"[..], Utf8Policy::PassThrough);
+    assert_eq!(tokens[0].0, Token::String(b"a\xffb".to_vec().into()));
+}
+
+#[test]
+fn replacement_spans() {
+    let input = b"<a b=\xe2\x82>\xff<c \xf0\x9f\x98>";
+    let tokens = tokenize_everywhere(input, Utf8Policy::ReplaceInvalid);
+    let spans: Vec<_> = tokens
+        .iter()
+        .map(|(token, span)| (token.clone(), &input[span.start..span.end]))
+        .collect();
+    let Token::StartTag(a) = &spans[0].0 else {
+        panic!();
+    };
+    assert_eq!(a.attributes[b"b".as_slice()], "\u{fffd}".as_bytes());
+    assert_eq!(spans[0].1, b"<a b=\xe2\x82>");
+    assert_eq!(
+        spans[1],
+        (
+            Token::String("\u{fffd}".as_bytes().to_vec().into()),
+            &b"\xff"[..]
+        )
+    );
+    let Token::StartTag(c) = &spans[2].0 else {
+        panic!();
+    };
+    assert_eq!(c.attributes[b"\xef\xbf\xbd".as_slice()], b"".as_slice());
+    assert_eq!(spans[2].1, b"<c \xf0\x9f\x98>");
+}
+
+#[test]
+fn reset_keeps_policy() {
+    let mut tokenizer = Tokenizer::new(&b"\xff"[..]);
+    tokenizer.utf8_policy(Utf8Policy::ReplaceInvalid);
+    assert_eq!(
+        tokenizer.next().unwrap().unwrap(),
+        Token::String("\u{fffd}".as_bytes().to_vec().into())
+    );
+    let mut tokenizer = tokenizer.reset_with_reader(&b"a\xc3"[..]);
+    assert_eq!(
+        tokenizer.next().unwrap().unwrap(),
+        Token::String("a\u{fffd}".as_bytes().to_vec().into())
+    );
+}
@@ -0,0 +1,113 @@
+use std::convert::Infallible;
+
+use html5gum::emitters::callback::{CallbackEmitter, CallbackEvent};
+use html5gum::serializer::Serializer;
+use html5gum::{is_void_element, DefaultEmitter, Token, Tokenizer};
+use pretty_assertions::assert_eq;
+
+/// The name, `self_closing` and `is_void()` of every start tag.
+fn start_tags(input: &str, mark_void_elements: bool) -> Vec<(String, bool, bool)> {
+    let mut emitter = DefaultEmitter::default();
+    emitter.mark_void_elements(mark_void_elements);
+    Tokenizer::new_with_emitter(input, emitter)
+        .flatten()
+        .filter_map(|token| match token {
+            Token::StartTag(tag) => Some((
+                String::from_utf8(tag.name.to_vec()).unwrap(),
+                tag.self_closing,
+                tag.is_void(),
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+fn tag(name: &str, self_closing: bool, is_void: bool) -> (String, bool, bool) {
+    (name.to_owned(), self_closing, is_void)
+}
+
+#[test]
+fn default_emitter() {
+    let input = "<br><br/><div/><IMG src=x><param><p>";
+    assert_eq!(
+        start_tags(input, false),
+        [
+            tag("br", false, true),
+            tag("br", true, true),
+            tag("div", true, false),
+            tag("img", false, true),
+            tag("param", false, true),
+            tag("p", false, false),
+        ]
+    );
+    assert_eq!(
+        start_tags(input, true),
+        [
+            tag("br", true, true),
+            tag("br", true, true),
+            tag("div", true, false),
+            tag("img", true, true),
+            tag("param", true, true),
+            tag("p", false, false),
+        ]
+    );
+}
+
+#[test]
+fn callback_emitter() {
+    let mut events = Vec::new();
+    let mut emitter = CallbackEmitter::new(|event: CallbackEvent<'_>| -> Option<Infallible> {
+        if let CallbackEvent::CloseStartTag {
+            self_closing,
+            is_void,
+        } = event
+        {
+            events.push((self_closing, is_void));
+        }
+        None
+    });
+    emitter.preserve_case(true);
+    let Ok(()) = Tokenizer::new_with_emitter("<Br><br/><div/><hr a=b>", emitter).finish();
+    assert_eq!(
+        events,
+        [(false, true), (true, true), (true, false), (false, true)]
+    );
+}
+
+#[test]
+fn serializer_round_trip() {
+    let mut emitter = DefaultEmitter::default();
+    emitter.mark_void_elements(true);
+    let tokens: Vec<_> = Tokenizer::new_with_emitter("<p>a<br>b<img src=x.png></p>", emitter)
+        .flatten()
+        .collect();
+
+    let mut serializer = Serializer::new(Vec::new());
+    serializer.self_closing(true);
+    for token in &tokens {
+        serializer.write_token(token).unwrap();
+    }
+    let html = serializer.into_inner();
+    assert_eq!(html, b"<p>a<br/>b<img src=\"x.png\"/></p>");
+
+    // the slash does not change the tokens
+    let mut emitter = DefaultEmitter::default();
+    emitter.mark_void_elements(true);
+    let again: Vec<_> = Tokenizer::new_with_emitter(&html, emitter)
+        .flatten()
+        .collect();
+    assert_eq!(again, tokens);
+}
+
+#[test]
+fn names() {
+    for name in [
+        "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param",
+        "source", "track", "wbr",
+    ] {
+        assert!(is_void_element(name.as_bytes()), "{}", name);
+    }
+    for name in ["p", "div", "BR", "template", "svg", "brx"] {
+        assert!(!is_void_element(name.as_bytes()), "{}", name);
+    }
+}
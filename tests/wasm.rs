@@ -0,0 +1,67 @@
+//! Run with `wasm-pack test --node --features wasm`, or with `--headless --firefox` for a browser.
+#![cfg(target_arch = "wasm32")]
+
+use html5gum::wasm::JsTokenizer;
+use js_sys::{Array, Function, Reflect, Uint8Array};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+fn get(event: &JsValue, key: &str) -> JsValue {
+    Reflect::get(event, &key.into()).unwrap()
+}
+
+#[wasm_bindgen_test]
+fn tokenize_document() {
+    let events = Array::new();
+    let callback = Closure::<dyn FnMut(JsValue)>::new({
+        let events = events.clone();
+        move |event: JsValue| {
+            events.push(&event);
+        }
+    });
+
+    let mut tokenizer = JsTokenizer::new(callback.as_ref().unchecked_ref::<Function>().clone());
+    tokenizer
+        .write(&"<!DOCTYPE html><p class=a>hel".into())
+        .unwrap();
+    assert_eq!(events.length(), 2);
+    tokenizer
+        .write(&Uint8Array::from(&b"lo</p><!-- c --><b"[..]).into())
+        .unwrap();
+    tokenizer.end().unwrap();
+
+    let summary: Vec<(String, String, f64, f64)> = events
+        .iter()
+        .map(|event| {
+            let name = ["name", "value", "error"]
+                .iter()
+                .find_map(|key| get(&event, key).as_string())
+                .unwrap();
+            (
+                get(&event, "type").as_string().unwrap(),
+                name,
+                get(&event, "start").as_f64().unwrap(),
+                get(&event, "end").as_f64().unwrap(),
+            )
+        })
+        .collect();
+
+    let expected = [
+        ("doctype", "html", 0.0, 15.0),
+        ("startTag", "p", 15.0, 26.0),
+        ("string", "hello", 26.0, 31.0),
+        ("endTag", "p", 31.0, 35.0),
+        ("comment", " c ", 35.0, 45.0),
+        ("error", "eof-in-tag", 47.0, 47.0),
+    ];
+    assert_eq!(
+        summary,
+        expected.map(|(ty, name, start, end)| (ty.to_owned(), name.to_owned(), start, end))
+    );
+
+    let attributes = Array::from(&get(&events.get(1), "attributes"));
+    assert_eq!(attributes.length(), 1);
+    let attribute = Array::from(&attributes.get(0));
+    assert_eq!(attribute.get(0).as_string().unwrap(), "class");
+    assert_eq!(attribute.get(1).as_string().unwrap(), "a");
+}